@@ -0,0 +1,81 @@
+use candy_frontend::{
+    ast::AstDbStorage,
+    ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
+    cst::CstDbStorage,
+    cst_to_ast::CstToAstStorage,
+    hir::HirDbStorage,
+    hir_to_mir::HirToMirStorage,
+    lir_optimize::OptimizeLirStorage,
+    mir_optimize::OptimizeMirStorage,
+    mir_to_lir::MirToLirStorage,
+    module::{
+        FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
+        ModuleDbStorage, ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner,
+        OverlayModuleProvider, PackagesPath, VirtualModuleProvider,
+    },
+    persistent_cache::PersistentCacheOwner,
+    position::PositionConversionStorage,
+    rcst_to_cst::RcstToCstStorage,
+    string_to_rcst::StringToRcstStorage,
+};
+
+#[salsa::database(
+    AstDbStorage,
+    AstToHirStorage,
+    CstDbStorage,
+    CstToAstStorage,
+    HirDbStorage,
+    HirToMirStorage,
+    MirToLirStorage,
+    ModuleDbStorage,
+    OptimizeLirStorage,
+    OptimizeMirStorage,
+    PositionConversionStorage,
+    RcstToCstStorage,
+    StringToRcstStorage
+)]
+pub struct Database {
+    storage: salsa::Storage<Self>,
+    /// Modules opened in an editor shadow virtual modules, which shadow the
+    /// underlying module provider.
+    module_provider: OverlayModuleProvider<
+        InMemoryModuleProvider,
+        OverlayModuleProvider<VirtualModuleProvider, FileSystemModuleProvider>,
+    >,
+}
+impl salsa::Database for Database {}
+
+impl Database {
+    pub fn new(packages_path: PackagesPath) -> Self {
+        Self {
+            storage: salsa::Storage::default(),
+            module_provider: OverlayModuleProvider::new(
+                InMemoryModuleProvider::default(),
+                OverlayModuleProvider::new(
+                    VirtualModuleProvider::default(),
+                    FileSystemModuleProvider { packages_path },
+                ),
+            ),
+        }
+    }
+}
+
+impl ModuleProviderOwner for Database {
+    fn get_module_provider(&self) -> &dyn ModuleProvider {
+        &self.module_provider
+    }
+}
+impl PersistentCacheOwner for Database {}
+impl CancellationTokenOwner for Database {}
+impl MutableModuleProviderOwner for Database {
+    fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
+        &mut self.module_provider.overlay
+    }
+    fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider {
+        &mut self.module_provider.fallback.overlay
+    }
+    fn invalidate_module(&mut self, module: &Module) {
+        GetModuleContentQuery.in_db_mut(self).invalidate(module);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{Compiler, Error};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    error::CompilerError,
+    hir::CollectErrors,
+    hir_to_mir::ExecutionTarget,
+    mir_optimize::OptimizeMir,
+    module::Module,
+    position::{Offset, PositionConversionDb},
+    OptimizationConfig, TracingConfig,
+};
+use itertools::Itertools;
+use std::{
+    fmt::{self, Display, Formatter},
+    ops,
+    path::{Path, PathBuf},
+};
+
+/// An error in the code.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// The file containing the error. This is [`None`] for code that doesn't
+    /// come from a file.
+    pub file: Option<PathBuf>,
+    pub range: Range,
+    pub message: String,
+    /// Other locations that help understand the error, e.g., where an
+    /// identifier is already defined.
+    pub related: Vec<RelatedLocation>,
+}
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct RelatedLocation {
+    pub file: Option<PathBuf>,
+    pub range: Range,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Position {
+    /// Zero-based line index
+    pub line: usize,
+    /// Zero-based character index (counting grapheme clusters)
+    pub character: usize,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            format_file(self.file.as_deref()),
+            self.range,
+            self.message,
+        )?;
+        for related in &self.related {
+            write!(
+                f,
+                "\n  note: {}:{}: {}",
+                format_file(related.file.as_deref()),
+                related.range,
+                related.message,
+            )?;
+        }
+        Ok(())
+    }
+}
+fn format_file(file: Option<&Path>) -> String {
+    file.map_or_else(|| "<unknown>".to_string(), |it| it.display().to_string())
+}
+impl Display for Range {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // Editors count lines and characters starting at one.
+        write!(
+            f,
+            "{}:{} – {}:{}",
+            self.start.line + 1,
+            self.start.character + 1,
+            self.end.line + 1,
+            self.end.character + 1,
+        )
+    }
+}
+
+impl Compiler {
+    /// Compiles `file` and returns the errors in it and in the modules it
+    /// uses.
+    pub fn check(&self, file: &Path) -> Result<Vec<Diagnostic>, Error> {
+        let module = self.module_for_file(file)?;
+        let (hir, _) = self
+            .db
+            .hir(module.clone())
+            .map_err(|error| Error::from_module_error(file, error))?;
+        let mut errors = vec![];
+        hir.collect_errors(&mut errors);
+        // This also reports errors that only occur when using other modules,
+        // e.g., cycles.
+        if let Ok((_, mir_errors)) = self.db.optimized_mir(
+            ExecutionTarget::Module(module),
+            TracingConfig::off(),
+            OptimizationConfig::default(),
+        ) {
+            errors.extend(mir_errors.iter().cloned());
+        }
+
+        Ok(errors
+            .into_iter()
+            .unique()
+            .map(|error| self.to_diagnostic(error))
+            .sorted_by(|a, b| (&a.file, a.range).cmp(&(&b.file, b.range)))
+            .collect())
+    }
+
+    fn to_diagnostic(&self, error: CompilerError) -> Diagnostic {
+        Diagnostic {
+            file: error.module.try_to_path(&self.packages_path),
+            range: self.to_range(&error.module, error.span.clone()),
+            message: error.payload.to_string(),
+            related: error
+                .labels
+                .into_iter()
+                .map(|label| RelatedLocation {
+                    file: label.module.try_to_path(&self.packages_path),
+                    range: self.to_range(&label.module, label.span),
+                    message: label.message,
+                })
+                .collect(),
+        }
+    }
+    fn to_range(&self, module: &Module, span: ops::Range<Offset>) -> Range {
+        let range = self.db.range_to_positions(module.clone(), span);
+        Range {
+            start: Position {
+                line: range.start.line,
+                character: range.start.character,
+            },
+            end: Position {
+                line: range.end.line,
+                character: range.end.character,
+            },
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! A stable API for checking, running, and formatting Candy code.
+//!
+//! The other crates of the compiler change their APIs all the time. This crate
+//! only exposes its own types so that external tools like build systems or
+//! editor integrations can use the compiler without depending on its internals.
+//!
+//! ```no_run
+//! use candy::{Compiler, RunOptions};
+//! use std::path::Path;
+//!
+//! let mut compiler = Compiler::new(Path::new("packages"))?;
+//! let file = Path::new("packages/Examples/helloWorld.candy");
+//! for diagnostic in compiler.check(file)? {
+//!     println!("{diagnostic}");
+//! }
+//! let result = compiler.run(file, &RunOptions::default().with_max_instructions(1_000_000))?;
+//! println!("{}", result.stdout.join("\n"));
+//! # Ok::<(), candy::Error>(())
+//! ```
+
+#![warn(clippy::nursery, clippy::pedantic, unused_crate_dependencies)]
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions
+)]
+
+pub use self::{
+    diagnostic::{Diagnostic, Position, Range, RelatedLocation},
+    run::{Optimization, Outcome, RunOptions, RunResult},
+};
+use candy_formatter::Formatter;
+use candy_frontend::{
+    module::{Module, ModuleFromPathError, ModuleKind, MutableModuleProviderOwner, PackagesPath},
+    rcst_to_cst::RcstToCst,
+    string_to_rcst::ModuleError,
+};
+use database::Database;
+use std::{
+    fmt::{self, Display, Formatter as FmtFormatter},
+    path::{Path, PathBuf},
+};
+
+mod database;
+mod diagnostic;
+mod run;
+
+/// Compiles Candy files of the packages in one directory.
+///
+/// Results are cached, so checking or running files again after changing one
+/// of them only recompiles what's affected.
+pub struct Compiler {
+    db: Database,
+    packages_path: PackagesPath,
+}
+impl Compiler {
+    /// `packages_path` is the directory containing Core and the other
+    /// packages.
+    pub fn new(packages_path: &Path) -> Result<Self, Error> {
+        let packages_path =
+            PackagesPath::try_from(packages_path).map_err(Error::InvalidPackagesPath)?;
+        Ok(Self {
+            db: Database::new(packages_path.clone()),
+            packages_path,
+        })
+    }
+
+    /// Uses `content` instead of the content on disk for `file`, e.g., for
+    /// files with unsaved changes in an editor.
+    pub fn set_file_content(&mut self, file: &Path, content: String) -> Result<(), Error> {
+        let module = self.module_for_file(file)?;
+        self.db.did_change_module(&module, content.into_bytes());
+        Ok(())
+    }
+    /// Uses the content on disk for `file` again.
+    pub fn reset_file_content(&mut self, file: &Path) -> Result<(), Error> {
+        let module = self.module_for_file(file)?;
+        self.db.did_close_module(&module);
+        Ok(())
+    }
+
+    /// Returns the formatted content of `file`.
+    pub fn format(&self, file: &Path) -> Result<String, Error> {
+        let module = self.module_for_file(file)?;
+        let cst = self
+            .db
+            .cst(module)
+            .map_err(|error| Error::from_module_error(file, error))?;
+        Ok(cst.format_to_string())
+    }
+
+    fn module_for_file(&self, file: &Path) -> Result<Module, Error> {
+        if !file.exists() {
+            return Err(Error::FileNotFound(file.to_path_buf()));
+        }
+        Module::from_path(&self.packages_path, file, ModuleKind::Code).map_err(
+            |error| match error {
+                ModuleFromPathError::NotFound(_) => Error::FileNotFound(file.to_path_buf()),
+                ModuleFromPathError::NotInPackage(_) => Error::NotInPackage(file.to_path_buf()),
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The packages path doesn't exist or is not a directory.
+    InvalidPackagesPath(String),
+    FileNotFound(PathBuf),
+    /// The file is not inside a Candy package, i.e., none of its ancestors
+    /// contains a `_package.candy` file.
+    NotInPackage(PathBuf),
+    /// The file exists, but can't be compiled, e.g., because it's not valid
+    /// UTF-8.
+    InvalidFile {
+        file: PathBuf,
+        reason: String,
+    },
+}
+impl Error {
+    fn from_module_error(file: &Path, error: ModuleError) -> Self {
+        let reason = match error {
+            ModuleError::DoesNotExist => return Self::FileNotFound(file.to_path_buf()),
+            ModuleError::InvalidUtf8 => "The file is not valid UTF-8.",
+            ModuleError::IsNotCandy => "The file is not a Candy file.",
+            ModuleError::IsToolingModule => "The file is a tooling module.",
+        };
+        Self::InvalidFile {
+            file: file.to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut FmtFormatter) -> fmt::Result {
+        match self {
+            Self::InvalidPackagesPath(reason) => write!(f, "{reason}"),
+            Self::FileNotFound(file) => write!(f, "`{}` doesn't exist.", file.display()),
+            Self::NotInPackage(file) => {
+                write!(f, "`{}` is not in a Candy package.", file.display())
+            }
+            Self::InvalidFile { file, reason } => write!(f, "`{}`: {reason}", file.display()),
+        }
+    }
+}
+impl std::error::Error for Error {}
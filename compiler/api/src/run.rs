@@ -0,0 +1,190 @@
+use crate::{Compiler, Error};
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget, tracing::CallTracingMode, OptimizationConfig, OptimizationLevel,
+    TracingConfig, TracingMode,
+};
+use candy_vm::{
+    environment::{ChannelEnvironment, StateAfterRunWithoutHandles},
+    heap::Heap,
+    launcher::Launcher,
+    lir_to_byte_code::compile_byte_code,
+    tracer::stack_trace::StackTracer,
+    VmFinished,
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::mpsc,
+};
+
+/// How to run a program.
+///
+/// Programs only get arguments, stdin, stdout, and stderr in their
+/// environment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct RunOptions {
+    pub arguments: Vec<String>,
+    /// The lines the program can read from stdin. Afterwards, reading from
+    /// stdin returns `Nothing`.
+    pub stdin: Vec<String>,
+    /// The maximum number of bytes the program may allocate on its heap.
+    pub max_memory: Option<usize>,
+    /// The maximum number of instructions the program may execute.
+    pub max_instructions: Option<usize>,
+    pub optimization: Optimization,
+}
+impl RunOptions {
+    #[must_use]
+    pub fn with_arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+    #[must_use]
+    pub fn with_stdin(mut self, stdin: Vec<String>) -> Self {
+        self.stdin = stdin;
+        self
+    }
+    #[must_use]
+    pub const fn with_max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+    #[must_use]
+    pub const fn with_max_instructions(mut self, max_instructions: usize) -> Self {
+        self.max_instructions = Some(max_instructions);
+        self
+    }
+    #[must_use]
+    pub const fn with_optimization(mut self, optimization: Optimization) -> Self {
+        self.optimization = optimization;
+        self
+    }
+}
+
+/// How much the compiler optimizes a program before running it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Optimization {
+    /// Compiles fastest, e.g., for running code only once.
+    Off,
+    /// Only applies cheap optimizations.
+    Cheap,
+    /// The program runs fastest.
+    #[default]
+    Full,
+}
+impl Optimization {
+    const fn to_config(self) -> OptimizationConfig {
+        OptimizationConfig::for_level(match self {
+            Self::Off => OptimizationLevel::O0,
+            Self::Cheap => OptimizationLevel::O1,
+            Self::Full => OptimizationLevel::O2,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct RunResult {
+    /// The lines the program wrote to stdout.
+    pub stdout: Vec<String>,
+    /// The lines the program wrote to stderr.
+    pub stderr: Vec<String>,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The main function returned `value` (formatted as Candy code).
+    Returned {
+        value: String,
+    },
+    Panicked {
+        reason: String,
+        /// The code that is responsible for the panic.
+        responsible: String,
+        stack_trace: String,
+    },
+    ExceededMemoryLimit,
+    ExceededInstructionLimit,
+    /// The VM itself crashed while running the program.
+    Crashed,
+}
+
+impl Compiler {
+    /// Calls the `main` function of `file`.
+    pub fn run(&self, file: &Path, options: &RunOptions) -> Result<RunResult, Error> {
+        let module = self.module_for_file(file)?;
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls: CallTracingMode::OnlyForPanicTraces,
+            evaluated_expressions: TracingMode::Off,
+            debug_assertions: false,
+            strip: false,
+        };
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::MainFunction(module),
+            tracing,
+            options.optimization.to_config(),
+        )
+        .0;
+
+        let mut heap = Heap::default();
+        heap.set_memory_limit(options.max_memory);
+        let (stdin_sender, stdin_receiver) = mpsc::channel();
+        for line in &options.stdin {
+            stdin_sender.send(line.clone()).unwrap();
+        }
+        drop(stdin_sender);
+        let (stdout_sender, stdout_receiver) = mpsc::channel();
+        let (stderr_sender, stderr_receiver) = mpsc::channel();
+        let (environment_object, mut environment) = ChannelEnvironment::new(
+            &mut heap,
+            &options.arguments,
+            stdin_receiver,
+            stdout_sender,
+            stderr_sender,
+        );
+        let vm = Launcher::new(&byte_code, StackTracer::default())
+            .main_function(&mut heap, environment_object);
+        // A bug in the VM shouldn't take down the host application.
+        let state = panic::catch_unwind(AssertUnwindSafe(|| match options.max_instructions {
+            Some(max_instructions) => {
+                vm.run_n_with_environment(&mut heap, &mut environment, max_instructions)
+            }
+            None => StateAfterRunWithoutHandles::Finished(
+                vm.run_forever_with_environment(&mut heap, &mut environment),
+            ),
+        }));
+        let outcome = match state {
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished {
+                result: Ok(return_value),
+                ..
+            })) => Outcome::Returned {
+                value: return_value.to_string(),
+            },
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished {
+                result: Err(panic), ..
+            })) if panic.is_out_of_memory() => Outcome::ExceededMemoryLimit,
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished {
+                result: Err(panic),
+                tracer,
+                ..
+            })) => Outcome::Panicked {
+                reason: panic.reason,
+                responsible: panic.responsible.to_string(),
+                stack_trace: tracer.format(&self.db, &self.packages_path),
+            },
+            Ok(StateAfterRunWithoutHandles::Running(_)) => Outcome::ExceededInstructionLimit,
+            Err(_) => Outcome::Crashed,
+        };
+
+        Ok(RunResult {
+            stdout: stdout_receiver.try_iter().collect(),
+            stderr: stderr_receiver.try_iter().collect(),
+            outcome,
+        })
+    }
+}
@@ -1,8 +1,27 @@
+#include <locale.h>
 #include <stdio.h>
 #include <stdint.h>
+#include <stdlib.h>
 #include <string.h>
+#include <wctype.h>
 #include "candy_runtime.h"
 
+const candy_value_t *candy_builtin_debug_assert(candy_value_t *condition, candy_value_t *message)
+{
+    if (!candy_tag_to_bool(condition))
+    {
+        candy_panic(message);
+    }
+    return &__internal_nothing;
+}
+
+const candy_value_t *candy_builtin_debug_log(candy_value_t *message)
+{
+    print_candy_value(message);
+    printf("\n");
+    return &__internal_nothing;
+}
+
 const candy_value_t *candy_builtin_equals(candy_value_t *left, candy_value_t *right)
 {
     if (left
@@ -148,6 +167,117 @@ candy_value_t *candy_builtin_tag_without_value(candy_value_t *tag)
     return make_candy_tag(tag->value.tag.text, NULL);
 }
 
+const candy_value_t *candy_builtin_text_compare_to(candy_value_t *left, candy_value_t *right)
+{
+    // Comparing the UTF-8 bytes orders texts by their Unicode code points, just
+    // like the VM does.
+    int result = strcmp(left->value.text, right->value.text);
+    if (result < 0)
+    {
+        return &__internal_less;
+    }
+    else if (result == 0)
+    {
+        return &__internal_equal;
+    }
+    else
+    {
+        return &__internal_greater;
+    }
+}
+
+static locale_t candy_utf8_locale(void)
+{
+    static locale_t locale = (locale_t)0;
+    if (locale == (locale_t)0)
+    {
+        locale = newlocale(LC_CTYPE_MASK, "C.UTF-8", (locale_t)0);
+    }
+    if (locale == (locale_t)0)
+    {
+        locale = newlocale(LC_CTYPE_MASK, "C", (locale_t)0);
+    }
+    return locale;
+}
+
+// Unlike the VM, this only supports mappings from one code point to another,
+// so, e.g., "ß" stays the same when converting it to uppercase.
+static candy_value_t *candy_text_map_case(const char *text, wint_t (*map)(wint_t, locale_t))
+{
+    locale_t locale = candy_utf8_locale();
+    // Every code point takes at least one and at most four bytes.
+    char *result = malloc(strlen(text) * 4 + 1);
+    const unsigned char *input = (const unsigned char *)text;
+    unsigned char *output = (unsigned char *)result;
+    while (*input != 0)
+    {
+        uint32_t code_point;
+        int length;
+        if (input[0] < 0x80)
+        {
+            code_point = input[0];
+            length = 1;
+        }
+        else if (input[0] < 0xE0)
+        {
+            code_point = input[0] & 0x1F;
+            length = 2;
+        }
+        else if (input[0] < 0xF0)
+        {
+            code_point = input[0] & 0x0F;
+            length = 3;
+        }
+        else
+        {
+            code_point = input[0] & 0x07;
+            length = 4;
+        }
+        for (int index = 1; index < length; index++)
+        {
+            code_point = (code_point << 6) | (input[index] & 0x3F);
+        }
+        input += length;
+
+        code_point = map(code_point, locale);
+        if (code_point < 0x80)
+        {
+            *output++ = code_point;
+        }
+        else if (code_point < 0x800)
+        {
+            *output++ = 0xC0 | (code_point >> 6);
+            *output++ = 0x80 | (code_point & 0x3F);
+        }
+        else if (code_point < 0x10000)
+        {
+            *output++ = 0xE0 | (code_point >> 12);
+            *output++ = 0x80 | ((code_point >> 6) & 0x3F);
+            *output++ = 0x80 | (code_point & 0x3F);
+        }
+        else
+        {
+            *output++ = 0xF0 | (code_point >> 18);
+            *output++ = 0x80 | ((code_point >> 12) & 0x3F);
+            *output++ = 0x80 | ((code_point >> 6) & 0x3F);
+            *output++ = 0x80 | (code_point & 0x3F);
+        }
+    }
+    *output = 0;
+
+    candy_value_t *candy_value = make_candy_text(result);
+    free(result);
+    return candy_value;
+}
+candy_value_t *candy_builtin_text_to_lowercase(candy_value_t *text)
+{
+    return candy_text_map_case(text->value.text, towlower_l);
+}
+candy_value_t *candy_builtin_text_to_uppercase(candy_value_t *text)
+{
+    return candy_text_map_case(text->value.text, towupper_l);
+}
+
 const candy_value_t *candy_builtin_type_of(candy_value_t *value)
 {
     switch (value->type)
@@ -1,5 +1,7 @@
 #include "candy_runtime.h"
 
+const candy_value_t *candy_builtin_debug_assert(candy_value_t *condition, candy_value_t *message);
+const candy_value_t *candy_builtin_debug_log(candy_value_t *message);
 const candy_value_t *candy_builtin_equals(candy_value_t *left, candy_value_t *right);
 const candy_value_t *candy_builtin_if_else(candy_value_t *condition, candy_value_t *then, candy_value_t *otherwise);
 candy_value_t *candy_builtin_int_add(candy_value_t *left, candy_value_t *right);
@@ -17,4 +19,7 @@ const candy_value_t *candy_builtin_tag_has_value(candy_value_t *tag);
 candy_value_t *candy_builtin_tag_get_value(candy_value_t *tag);
 candy_value_t *candy_builtin_tag_without_value(candy_value_t *tag);
 const candy_value_t *candy_builtin_struct_has_key(candy_value_t *structure, candy_value_t *key);
+const candy_value_t *candy_builtin_text_compare_to(candy_value_t *left, candy_value_t *right);
+candy_value_t *candy_builtin_text_to_lowercase(candy_value_t *text);
+candy_value_t *candy_builtin_text_to_uppercase(candy_value_t *text);
 const candy_value_t *candy_builtin_type_of(candy_value_t *value);
@@ -174,6 +174,30 @@ candy_value_t *make_candy_struct(candy_value_t **keys, candy_value_t **values)
     return candy_value;
 }
 
+int64_t candy_value_to_int(const candy_value_t *value)
+{
+    if (value->type != CANDY_TYPE_INT)
+    {
+        printf("Expected an int, got ");
+        print_candy_value(value);
+        printf("\n");
+        exit(-1);
+    }
+    return value->value.integer;
+}
+
+char *candy_value_to_text(const candy_value_t *value)
+{
+    if (value->type != CANDY_TYPE_TEXT)
+    {
+        printf("Expected a text, got ");
+        print_candy_value(value);
+        printf("\n");
+        exit(-1);
+    }
+    return value->value.text;
+}
+
 candy_value_t *run_candy_main(candy_value_t *function, candy_value_t *arg)
 {
     return function->value.function.function(arg);
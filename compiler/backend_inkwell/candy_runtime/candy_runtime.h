@@ -72,9 +72,15 @@ candy_value_t *make_candy_text(char *text);
 candy_value_t *make_candy_tag(char *tag, candy_value_t *value);
 candy_value_t *make_candy_list(candy_value_t **values);
 candy_value_t *make_candy_function(candy_function function, void *environment, int env_size);
+int64_t candy_value_to_int(const candy_value_t *value);
+char *candy_value_to_text(const candy_value_t *value);
 candy_value_t *run_candy_main(candy_value_t *function, candy_value_t *arg);
 candy_function get_candy_function_pointer(candy_value_t *function);
 void *get_candy_function_environment(candy_value_t *function);
 void candy_panic(const candy_value_t *reason);
 void free_candy_value(candy_value_t *value);
+
+// Generated when compiling a library with exported functions. Evaluates the
+// module and has to be called before calling any exported function.
+void candy_init(void);
 #endif
@@ -12,25 +12,28 @@
 
 use candy_frontend::{
     builtin_functions::BuiltinFunction,
+    extern_signature::{ExternSignature, ExternType},
     hir_to_mir::ExecutionTarget,
-    mir::{Body, Expression, Id, Mir},
+    mangling::{mangle, SymbolKind},
+    mir::{Body, Expression, Id, Mir, VisitorResult},
     mir_optimize::OptimizeMir,
     rich_ir::{RichIr, ToRichIr},
     string_to_rcst::ModuleError,
-    utils::HashMapExtension,
-    TracingConfig,
+    utils::{AdjustCasingOfFirstLetter, HashMapExtension},
+    OptimizationConfig, OptimizationLevel, TracingConfig,
 };
 pub use inkwell;
 use inkwell::{
     builder::Builder,
     context::Context,
-    module::Module,
+    module::{Linkage, Module},
     support::LLVMString,
     targets::{InitializationConfig, Target, TargetMachine},
     types::{
-        BasicMetadataTypeEnum, BasicType, FunctionType, IntType, PointerType, StructType, VoidType,
+        BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, IntType, PointerType,
+        StructType, VoidType,
     },
-    values::{BasicValue, BasicValueEnum, FunctionValue, GlobalValue},
+    values::{BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, GlobalValue},
     AddressSpace,
 };
 use itertools::Itertools;
@@ -42,12 +45,20 @@ use std::{path::Path, sync::Arc};
 #[salsa::query_group(LlvmIrStorage)]
 pub trait LlvmIrDb: OptimizeMir {
     #[salsa::transparent]
-    fn llvm_ir(&self, target: ExecutionTarget) -> Result<RichIr, ModuleError>;
+    fn llvm_ir(
+        &self,
+        target: ExecutionTarget,
+        optimization: OptimizationConfig,
+    ) -> Result<RichIr, ModuleError>;
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn llvm_ir(db: &dyn LlvmIrDb, target: ExecutionTarget) -> Result<RichIr, ModuleError> {
-    let (mir, _) = db.optimized_mir(target, TracingConfig::off())?;
+fn llvm_ir(
+    db: &dyn LlvmIrDb,
+    target: ExecutionTarget,
+    optimization: OptimizationConfig,
+) -> Result<RichIr, ModuleError> {
+    let (mir, _) = db.optimized_mir(target, TracingConfig::off(), optimization)?;
 
     let context = Context::create();
     let codegen = CodeGen::new(&context, "module", mir);
@@ -57,6 +68,10 @@ fn llvm_ir(db: &dyn LlvmIrDb, target: ExecutionTarget) -> Result<RichIr, ModuleE
     Ok(llvm_ir.to_str().unwrap().to_rich_ir(true))
 }
 
+/// When compiling a library, this function evaluates the module and has to be
+/// called before any exported function.
+const INIT_FUNCTION_NAME: &str = "candy_init";
+
 #[derive(Clone)]
 struct FunctionInfo<'ctx> {
     function_value: FunctionValue<'ctx>,
@@ -72,9 +87,14 @@ pub struct CodeGen<'ctx> {
     candy_value_pointer_type: PointerType<'ctx>,
     builtins: FxHashMap<BuiltinFunction, FunctionValue<'ctx>>,
     globals: FxHashMap<Id, GlobalValue<'ctx>>,
+    /// Read-only, null-terminated string data, shared by all functions that
+    /// use the same string.
+    string_constants: FxHashMap<String, GlobalValue<'ctx>>,
     locals: FxHashMap<Id, BasicValueEnum<'ctx>>,
     functions: FxHashMap<Id, FunctionInfo<'ctx>>,
     unrepresented_ids: FxHashSet<Id>,
+    /// Functions that get a C ABI wrapper with the given name.
+    exported_functions: Vec<(String, Id)>,
 }
 
 pub struct LlvmCandyModule<'ctx> {
@@ -82,28 +102,65 @@ pub struct LlvmCandyModule<'ctx> {
 }
 
 impl<'ctx> LlvmCandyModule<'ctx> {
+    pub fn build_runtime() -> Result<(), std::io::Error> {
+        std::process::Command::new("make")
+            .args(["-C", "compiler/backend_inkwell/candy_runtime/", "clean"])
+            .spawn()?
+            .wait()?;
+
+        std::process::Command::new("make")
+            .args([
+                "-C",
+                "compiler/backend_inkwell/candy_runtime/",
+                "candy_runtime.a",
+            ])
+            .spawn()?
+            .wait()?;
+        Ok(())
+    }
+
+    /// With `strip`, the executable doesn't contain a symbol table, which
+    /// would otherwise include the mangled names of all constants and
+    /// functions.
     pub fn compile_obj_and_link(
         &self,
         path: &str,
         build_runtime: bool,
         debug: bool,
+        strip: bool,
         linker: &str,
+        optimization_level: OptimizationLevel,
     ) -> Result<(), std::io::Error> {
         if build_runtime {
-            std::process::Command::new("make")
-                .args(["-C", "compiler/backend_inkwell/candy_runtime/", "clean"])
-                .spawn()?
-                .wait()?;
-
-            std::process::Command::new("make")
-                .args([
-                    "-C",
-                    "compiler/backend_inkwell/candy_runtime/",
-                    "candy_runtime.a",
-                ])
-                .spawn()?
-                .wait()?;
+            Self::build_runtime()?;
         }
+        let o_path = self.compile_obj(path, optimization_level);
+
+        std::process::Command::new(linker)
+            .args([
+                "-dynamic-linker",
+                // TODO: This is not portable.
+                "/lib/ld-linux-x86-64.so.2",
+                "/usr/lib/crt1.o",
+                "/usr/lib/crti.o",
+                "-L/usr/lib",
+                "-lc",
+                &o_path,
+                "compiler/backend_inkwell/candy_runtime/candy_runtime.a",
+                "/usr/lib/crtn.o",
+                if debug { "-g" } else { "" },
+                if strip { "--strip-all" } else { "" },
+                "-o",
+                o_path.as_str().strip_suffix(".candy.o").unwrap(),
+            ])
+            .spawn()?
+            .wait()?;
+        Ok(())
+    }
+
+    /// Writes the module to an object file and returns its path.
+    #[must_use]
+    pub fn compile_obj(&self, path: &str, optimization_level: OptimizationLevel) -> String {
         let triple = TargetMachine::get_default_triple();
         Target::initialize_native(&InitializationConfig::default()).unwrap();
         let target = Target::from_triple(&triple).unwrap();
@@ -113,7 +170,11 @@ impl<'ctx> LlvmCandyModule<'ctx> {
                 &triple,
                 "generic",
                 "",
-                inkwell::OptimizationLevel::Default,
+                match optimization_level {
+                    OptimizationLevel::O0 => inkwell::OptimizationLevel::None,
+                    OptimizationLevel::O1 => inkwell::OptimizationLevel::Less,
+                    OptimizationLevel::O2 => inkwell::OptimizationLevel::Default,
+                },
                 inkwell::targets::RelocMode::Default,
                 inkwell::targets::CodeModel::Default,
             )
@@ -132,26 +193,7 @@ impl<'ctx> LlvmCandyModule<'ctx> {
                 Path::new(&o_path),
             )
             .unwrap();
-
-        std::process::Command::new(linker)
-            .args([
-                "-dynamic-linker",
-                // TODO: This is not portable.
-                "/lib/ld-linux-x86-64.so.2",
-                "/usr/lib/crt1.o",
-                "/usr/lib/crti.o",
-                "-L/usr/lib",
-                "-lc",
-                &o_path,
-                "compiler/backend_inkwell/candy_runtime/candy_runtime.a",
-                "/usr/lib/crtn.o",
-                if debug { "-g" } else { "" },
-                "-o",
-                o_path.as_str().strip_suffix(".candy.o").unwrap(),
-            ])
-            .spawn()?
-            .wait()?;
-        Ok(())
+        o_path
     }
 }
 
@@ -172,12 +214,74 @@ impl<'ctx> CodeGen<'ctx> {
             candy_value_pointer_type,
             builtins: FxHashMap::default(),
             globals: FxHashMap::default(),
+            string_constants: FxHashMap::default(),
             locals: FxHashMap::default(),
             functions: FxHashMap::default(),
             unrepresented_ids: FxHashSet::default(),
+            exported_functions: vec![],
         }
     }
 
+    /// Compiles the module as a library that exports the given functions with
+    /// a C ABI instead of running its main function.
+    ///
+    /// Each exported function takes and returns `candy_value_t *`s. Arguments
+    /// can be created and results inspected using the conversion helpers of
+    /// `candy_runtime.h`. Before calling any exported function, `candy_init`
+    /// has to be called to evaluate the module.
+    ///
+    /// This requires MIR compiled for [`ExecutionTarget::Module`].
+    pub fn export_functions(&mut self, names: &[String]) -> Result<(), String> {
+        for name in names {
+            let id = self
+                .find_exported_function(name)
+                .ok_or_else(|| format!("The module doesn't export a function called `{name}`."))?;
+            self.exported_functions.push((name.clone(), id));
+        }
+        Ok(())
+    }
+    /// The symbols of all extern functions used by the module.
+    ///
+    /// Extern functions are linked against C code that can do anything, so
+    /// callers should only compile modules using them if they trust the code.
+    #[must_use]
+    pub fn extern_functions(&self) -> Vec<String> {
+        let mut symbols = vec![];
+        self.mir.body.visit(&mut |_, expression, _| {
+            if let Expression::Extern { symbol, .. } = expression {
+                symbols.push(symbol.clone());
+            }
+            VisitorResult::Continue
+        });
+        symbols.into_iter().sorted().dedup().collect()
+    }
+    /// Looks up a function in the struct of exports, which is the last
+    /// expression of the module's MIR.
+    fn find_exported_function(&self, name: &str) -> Option<Id> {
+        let body = &self.mir.body;
+        let find = |id: Id| {
+            body.expressions
+                .iter()
+                .find(|(it, _)| *it == id)
+                .map(|(_, expression)| expression)
+        };
+
+        let Expression::Struct(fields) = find(body.return_value())? else {
+            return None;
+        };
+        let symbol = name.uppercase_first_letter();
+        let (_, mut value) = *fields.iter().find(|(key, _)| {
+            matches!(
+                find(*key),
+                Some(Expression::Tag { symbol: key_symbol, value: None }) if *key_symbol == symbol,
+            )
+        })?;
+        while let Some(Expression::Reference(target)) = find(value) {
+            value = *target;
+        }
+        matches!(find(value), Some(Expression::Function { .. })).then_some(value)
+    }
+
     pub fn compile(
         mut self,
         print_llvm_ir: bool,
@@ -206,6 +310,16 @@ impl<'ctx> CodeGen<'ctx> {
             &[i8_type.ptr_type(AddressSpace::default()).into()],
             self.candy_value_pointer_type,
         );
+        self.add_function(
+            "candy_value_to_int",
+            &[self.candy_value_pointer_type.into()],
+            i64_type,
+        );
+        self.add_function(
+            "candy_value_to_text",
+            &[self.candy_value_pointer_type.into()],
+            i8_type.ptr_type(AddressSpace::default()),
+        );
         self.add_function(
             "make_candy_list",
             &[self.candy_value_pointer_type.into()],
@@ -257,7 +371,12 @@ impl<'ctx> CodeGen<'ctx> {
             self.candy_value_pointer_type,
         );
 
-        let main_fn = self.add_function("main", &[], i32_type);
+        let is_library = !self.exported_functions.is_empty();
+        let main_fn = if is_library {
+            self.add_function(INIT_FUNCTION_NAME, &[], void_type)
+        } else {
+            self.add_function("main", &[], i32_type)
+        };
         let block = self.context.append_basic_block(main_fn, "entry");
 
         let run_candy_main = self.add_function(
@@ -279,7 +398,12 @@ impl<'ctx> CodeGen<'ctx> {
         let main_function = self.compile_mir(&self.mir.body.clone(), &main_info);
         // This is `None` iff there is no exported main function.
         self.builder.position_at_end(block);
-        if let Some(main_function) = main_function {
+        if is_library {
+            self.builder.build_return(None);
+            for (name, id) in self.exported_functions.clone() {
+                self.add_export_wrapper(&name, id);
+            }
+        } else if let Some(main_function) = main_function {
             let environment =
                 self.module
                     .add_global(self.candy_value_pointer_type, None, "candy_environment");
@@ -347,7 +471,7 @@ impl<'ctx> CodeGen<'ctx> {
                     let call = self.builder.build_call(make_candy_int, &[v.into()], "");
 
                     let global = self.create_global(
-                        &format!("num_{value}"),
+                        &mangle(SymbolKind::Int, *id, &value.to_string()),
                         *id,
                         call.try_as_basic_value().unwrap_left(),
                     );
@@ -362,7 +486,7 @@ impl<'ctx> CodeGen<'ctx> {
                         .build_call(make_candy_text, &[string.into()], "");
 
                     let global = self.create_global(
-                        &format!("text_{text}"),
+                        &mangle(SymbolKind::Text, *id, text),
                         *id,
                         call.try_as_basic_value().unwrap_left(),
                     );
@@ -387,7 +511,7 @@ impl<'ctx> CodeGen<'ctx> {
                     );
 
                     let global = self.create_global(
-                        &format!("tag_{symbol}"),
+                        &mangle(SymbolKind::Tag, *id, symbol),
                         *id,
                         call.try_as_basic_value().unwrap_left(),
                     );
@@ -420,7 +544,52 @@ impl<'ctx> CodeGen<'ctx> {
                     );
 
                     let global = self.create_global(
-                        &format!("fun_candy_builtin_{}", builtin.as_ref()),
+                        &mangle(SymbolKind::Builtin, *id, builtin.as_ref()),
+                        *id,
+                        call.try_as_basic_value().unwrap_left(),
+                    );
+
+                    Some(global.as_basic_value_enum())
+                }
+                Expression::Extern { symbol, signature } => {
+                    let function = if signature.is_untyped() {
+                        // Declares the C function so that the linker resolves it.
+                        self.module.get_function(symbol).unwrap_or_else(|| {
+                            self.add_function(
+                                symbol,
+                                vec![self.candy_value_pointer_type.into(); signature.arity()]
+                                    .as_slice(),
+                                self.candy_value_pointer_type,
+                            )
+                        })
+                    } else {
+                        self.add_extern_wrapper(*id, symbol, signature)
+                    };
+                    self.functions.insert(
+                        *id,
+                        FunctionInfo {
+                            function_value: function,
+                            captured_ids: vec![],
+                            env_type: None,
+                        },
+                    );
+
+                    let i64_type = self.context.i64_type();
+                    let function_ptr = function.as_global_value().as_pointer_value();
+                    let make_candy_function =
+                        self.module.get_function("make_candy_function").unwrap();
+                    let call = self.builder.build_call(
+                        make_candy_function,
+                        &[
+                            function_ptr.into(),
+                            self.candy_value_pointer_type.const_null().into(),
+                            i64_type.const_zero().into(),
+                        ],
+                        "",
+                    );
+
+                    let global = self.create_global(
+                        &mangle(SymbolKind::Extern, *id, symbol),
                         *id,
                         call.try_as_basic_value().unwrap_left(),
                     );
@@ -467,8 +636,11 @@ impl<'ctx> CodeGen<'ctx> {
                         self.builder
                             .build_call(make_candy_list, &[list_array.into()], "");
 
-                    let global =
-                        self.create_global("", *id, candy_list.try_as_basic_value().unwrap_left());
+                    let global = self.create_global(
+                        &mangle(SymbolKind::List, *id, ""),
+                        *id,
+                        candy_list.try_as_basic_value().unwrap_left(),
+                    );
 
                     Some(global.as_basic_value_enum())
                 }
@@ -567,8 +739,11 @@ impl<'ctx> CodeGen<'ctx> {
                         .builder
                         .build_call(make_candy_text, &[string.into()], "");
 
-                    let global =
-                        self.create_global(&text, *id, call.try_as_basic_value().unwrap_left());
+                    let global = self.create_global(
+                        &mangle(SymbolKind::HirId, *id, &text),
+                        *id,
+                        call.try_as_basic_value().unwrap_left(),
+                    );
 
                     Some(global.as_basic_value_enum())
                 }
@@ -579,11 +754,7 @@ impl<'ctx> CodeGen<'ctx> {
                     responsible_parameter,
                 } => {
                     self.unrepresented_ids.insert(*responsible_parameter);
-                    let name = original_hirs
-                        .iter()
-                        .sorted()
-                        .map(|it| it.to_string().replace([':', '.'], "_"))
-                        .join(", ");
+                    let name = original_hirs.iter().sorted().join(", ");
 
                     let captured_ids: Vec<_> = expr
                         .captured_ids()
@@ -592,6 +763,7 @@ impl<'ctx> CodeGen<'ctx> {
                             !(self.globals.contains_key(cap_id)
                                 || self.unrepresented_ids.contains(cap_id))
                         })
+                        .sorted()
                         .collect();
 
                     let env_types: Vec<_> = captured_ids
@@ -627,7 +799,11 @@ impl<'ctx> CodeGen<'ctx> {
                         params.push(self.candy_value_pointer_type.into());
                     }
 
-                    let function = self.add_function(&name, &params, self.candy_value_pointer_type);
+                    let function = self.add_function(
+                        &mangle(SymbolKind::Function, *id, &name),
+                        &params,
+                        self.candy_value_pointer_type,
+                    );
 
                     let function_info = FunctionInfo {
                         function_value: function,
@@ -657,7 +833,7 @@ impl<'ctx> CodeGen<'ctx> {
                     );
 
                     let global = self.create_global(
-                        &format!("fun_{name}"),
+                        &mangle(SymbolKind::FunctionObject, *id, &name),
                         *id,
                         call.try_as_basic_value().unwrap_left(),
                     );
@@ -782,13 +958,179 @@ impl<'ctx> CodeGen<'ctx> {
         let fn_name = function_ctx.function_value.get_name().to_string_lossy();
         // This "main" refers to the entrypoint of the compiled program, not to the Candy main function
         // which may be named differently.
-        if fn_name != "main" {
+        if fn_name != "main" && fn_name != INIT_FUNCTION_NAME {
             self.builder
                 .build_return(return_value.as_ref().map(|v| v as &dyn BasicValue<'ctx>));
         }
         return_value
     }
 
+    /// Adds a function with the given name that has a stable C ABI and calls
+    /// the Candy function with the given ID.
+    fn add_export_wrapper(&self, name: &str, id: Id) {
+        let FunctionInfo {
+            function_value,
+            env_type,
+            ..
+        } = self.functions[&id].clone();
+        let argument_count = function_value.count_params() - u32::from(env_type.is_some());
+        let parameter_types: Vec<BasicMetadataTypeEnum> =
+            vec![self.candy_value_pointer_type.into(); argument_count as usize];
+        let wrapper = self.add_function(name, &parameter_types, self.candy_value_pointer_type);
+        let block = self.context.append_basic_block(wrapper, "entry");
+        self.builder.position_at_end(block);
+
+        let mut arguments: Vec<BasicMetadataValueEnum> =
+            wrapper.get_params().into_iter().map(Into::into).collect();
+        if env_type.is_some() {
+            let get_candy_fn_env = self
+                .module
+                .get_function("get_candy_function_environment")
+                .unwrap();
+            let function_object = self.builder.build_load(
+                self.candy_value_pointer_type,
+                self.globals[&id].as_pointer_value(),
+                "",
+            );
+            let environment =
+                self.builder
+                    .build_call(get_candy_fn_env, &[function_object.into()], "");
+            arguments.push(environment.try_as_basic_value().unwrap_left().into());
+        }
+
+        let result = self.builder.build_call(function_value, &arguments, "");
+        self.builder
+            .build_return(Some(&result.try_as_basic_value().unwrap_left()));
+    }
+
+    /// Declares a typed extern function and adds a wrapper that takes and
+    /// returns Candy values, converting them according to the signature.
+    fn add_extern_wrapper(
+        &mut self,
+        id: Id,
+        symbol: &str,
+        signature: &ExternSignature,
+    ) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let i8_pointer_type = self.context.i8_type().ptr_type(AddressSpace::default());
+        let c_type = |type_: ExternType| -> BasicTypeEnum<'ctx> {
+            match type_ {
+                ExternType::Value => self.candy_value_pointer_type.into(),
+                ExternType::Int => i64_type.into(),
+                ExternType::Text | ExternType::Pointer => i8_pointer_type.into(),
+                ExternType::Nothing => unreachable!("Parameters can't have the type `Nothing`."),
+            }
+        };
+
+        let extern_function = self.module.get_function(symbol).unwrap_or_else(|| {
+            let parameter_types: Vec<BasicMetadataTypeEnum> = signature
+                .parameters
+                .iter()
+                .map(|it| c_type(*it).into())
+                .collect();
+            let function_type = match signature.return_type {
+                ExternType::Nothing => self.context.void_type().fn_type(&parameter_types, false),
+                return_type => c_type(return_type).fn_type(&parameter_types, false),
+            };
+            self.module.add_function(symbol, function_type, None)
+        });
+
+        let wrapper = self.add_function(
+            &mangle(SymbolKind::Function, id, symbol),
+            vec![self.candy_value_pointer_type.into(); signature.arity()].as_slice(),
+            self.candy_value_pointer_type,
+        );
+        let current_block = self.builder.get_insert_block();
+        let block = self.context.append_basic_block(wrapper, "entry");
+        self.builder.position_at_end(block);
+
+        let candy_value_to_int = self.module.get_function("candy_value_to_int").unwrap();
+        let candy_value_to_text = self.module.get_function("candy_value_to_text").unwrap();
+        let arguments: Vec<BasicMetadataValueEnum> = signature
+            .parameters
+            .iter()
+            .zip(wrapper.get_params())
+            .map(|(type_, argument)| {
+                let to_int = || {
+                    self.builder
+                        .build_call(candy_value_to_int, &[argument.into()], "")
+                        .try_as_basic_value()
+                        .unwrap_left()
+                        .into_int_value()
+                };
+                match type_ {
+                    ExternType::Value => argument.into(),
+                    ExternType::Int => to_int().into(),
+                    ExternType::Text => self
+                        .builder
+                        .build_call(candy_value_to_text, &[argument.into()], "")
+                        .try_as_basic_value()
+                        .unwrap_left()
+                        .into(),
+                    ExternType::Pointer => self
+                        .builder
+                        .build_int_to_ptr(to_int(), i8_pointer_type, "")
+                        .into(),
+                    ExternType::Nothing => unreachable!(),
+                }
+            })
+            .collect();
+        let result = self
+            .builder
+            .build_call(extern_function, &arguments, "")
+            .try_as_basic_value()
+            .left();
+
+        let make_candy_int = self.module.get_function("make_candy_int").unwrap();
+        let return_value = match signature.return_type {
+            ExternType::Value => result.unwrap(),
+            ExternType::Int => self
+                .builder
+                .build_call(make_candy_int, &[result.unwrap().into()], "")
+                .try_as_basic_value()
+                .unwrap_left(),
+            ExternType::Text => {
+                let make_candy_text = self.module.get_function("make_candy_text").unwrap();
+                self.builder
+                    .build_call(make_candy_text, &[result.unwrap().into()], "")
+                    .try_as_basic_value()
+                    .unwrap_left()
+            }
+            ExternType::Pointer => {
+                let address = self.builder.build_ptr_to_int(
+                    result.unwrap().into_pointer_value(),
+                    i64_type,
+                    "",
+                );
+                self.builder
+                    .build_call(make_candy_int, &[address.into()], "")
+                    .try_as_basic_value()
+                    .unwrap_left()
+            }
+            ExternType::Nothing => {
+                let make_candy_tag = self.module.get_function("make_candy_tag").unwrap();
+                let nothing = self.make_str_literal("Nothing");
+                self.builder
+                    .build_call(
+                        make_candy_tag,
+                        &[
+                            nothing.into(),
+                            self.candy_value_pointer_type.const_null().into(),
+                        ],
+                        "",
+                    )
+                    .try_as_basic_value()
+                    .unwrap_left()
+            }
+        };
+        self.builder.build_return(Some(&return_value));
+
+        if let Some(current_block) = current_block {
+            self.builder.position_at_end(current_block);
+        }
+        wrapper
+    }
+
     fn get_builtin(&mut self, builtin: BuiltinFunction) -> FunctionValue<'ctx> {
         if let Some(function) = self.builtins.get(&builtin) {
             return *function;
@@ -829,23 +1171,32 @@ impl<'ctx> CodeGen<'ctx> {
         global
     }
 
-    fn make_str_literal(&self, text: &str) -> BasicValueEnum<'ctx> {
-        let i8_type = self.context.i8_type();
-        let i64_type = self.context.i64_type();
-
-        let content: Vec<_> = text
-            .chars()
-            .chain(std::iter::once('\0'))
-            .map(|c| i8_type.const_int(c as u64, false))
-            .collect();
-        let v = i8_type.const_array(&content);
-
-        let len = i64_type.const_int(text.len() as u64 + 1, false);
-        let arr_alloc = self.builder.build_array_alloca(i8_type, len, "");
-        self.builder.build_store(arr_alloc, v);
+    fn make_str_literal(&mut self, text: &str) -> BasicValueEnum<'ctx> {
+        let global = if let Some(global) = self.string_constants.get(text) {
+            *global
+        } else {
+            let content = self.context.const_string(text.as_bytes(), true);
+            // The name only has to be unique, the text itself may contain
+            // characters that aren't allowed in symbol names.
+            let global = self.module.add_global(
+                content.get_type(),
+                None,
+                &format!("str.{}", self.string_constants.len()),
+            );
+            global.set_initializer(&content);
+            global.set_constant(true);
+            global.set_linkage(Linkage::Private);
+            global.set_unnamed_addr(true);
+            self.string_constants.insert(text.to_string(), global);
+            global
+        };
 
-        self.builder
-            .build_bitcast(arr_alloc, i8_type.ptr_type(AddressSpace::default()), "")
+        let i8_type = self.context.i8_type();
+        self.builder.build_bitcast(
+            global.as_pointer_value(),
+            i8_type.ptr_type(AddressSpace::default()),
+            "",
+        )
     }
 
     fn get_value_with_id(
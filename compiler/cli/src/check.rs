@@ -1,41 +1,121 @@
 use crate::{
     database::Database,
-    utils::{module_for_path, packages_path},
+    utils::{module_for_path, packages_path, OptimizationOptions},
     Exit, ProgramResult,
 };
-use candy_frontend::{ast_to_hir::AstToHir, hir::CollectErrors};
-use clap::{arg, Parser, ValueHint};
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget,
+    lints::{lint_module, LintLevel, LintLevels},
+    mir_optimize::OptimizeMir,
+    module::{Package, PackagesPath},
+    symbol_index::SymbolIndex,
+    TracingConfig,
+};
+use clap::{arg, Parser, ValueEnum, ValueHint};
+use itertools::Itertools;
 use std::path::PathBuf;
-use tracing::warn;
+use tracing::{error, warn};
+
+pub mod human;
+mod machine_readable;
 
 /// Check a Candy program for obvious errors.
 ///
 /// This command finds very obvious errors in your program. For more extensive
 /// error reporting, fuzzing the Candy program is recommended instead.
+///
+/// It also runs lints, which report code that works but is probably a mistake,
+/// e.g., unused definitions, shadowed names, or unreachable code.
+/// Packages can configure how severe each lint is in their `_package.candy`,
+/// e.g., `lints := [UnusedUse: Allow]`. Lints at the `Deny` level make the
+/// check fail.
+///
+/// With `--output json` or `--output sarif`, problems are printed as
+/// structured data that CI systems and other editors can consume.
+///
+/// It also updates the package's symbol index, which editors use for finding
+/// symbols and references across modules.
 #[derive(Parser, Debug)]
 pub struct Options {
     /// The file or package to check. If none is provided, the package of your
     /// current working directory will be checked.
     #[arg(value_hint = ValueHint::FilePath)]
     path: Option<PathBuf>,
+
+    /// Report a lint as an error. Use `warnings` to deny all lints that would
+    /// otherwise be reported as warnings.
+    #[arg(long, value_name = "LINT")]
+    deny: Vec<String>,
+
+    /// Don't report a lint at all.
+    #[arg(long, value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// How to report problems. Machine-readable formats are written to stdout.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
+    /// Errors like import cycles are found while optimizing the program, so
+    /// the same optimization flags as for running it apply.
+    #[command(flatten)]
+    optimization: OptimizationOptions,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OutputFormat {
+    /// Annotated source code for humans
+    Human,
+    /// A JSON object with a list of diagnostics
+    Json,
+    /// SARIF 2.1.0, e.g., for GitHub code scanning
+    Sarif,
 }
 
 pub fn check(options: Options) -> ProgramResult {
     let packages_path = packages_path();
-    let db = Database::new_with_file_system_module_provider(packages_path);
+    let db = Database::new_with_file_system_module_provider(packages_path.clone());
     let module = module_for_path(options.path)?;
+    update_symbol_index(&db, &packages_path, module.package());
+    let lint_levels = lint_levels(&db, module.package(), &options.deny, &options.allow)?;
 
-    // TODO: Once my other PR is merged, update this to get the MIR instead.
-    // This will return a tuple containing the MIR and errors, even from
-    // imported modules.
-
-    let (hir, _) = db.hir(module).unwrap();
-    let mut errors = vec![];
-    hir.collect_errors(&mut errors);
-    let has_errors = !errors.is_empty();
+    // Optimizing the MIR folds imported modules into this one, so this also
+    // returns their errors.
+    let (_, errors) = db
+        .optimized_mir(
+            ExecutionTarget::Module(module.clone()),
+            TracingConfig::off(),
+            options.optimization.to_config(),
+        )
+        .unwrap();
+    let errors = errors
+        .iter()
+        .cloned()
+        .sorted_by_key(|error| (error.module.to_string(), error.span.start))
+        .collect_vec();
+    let warnings = lint_module(&db, &module, &lint_levels);
+    let has_errors = !errors.is_empty()
+        || warnings
+            .iter()
+            .any(|warning| warning.level == LintLevel::Deny);
 
-    for error in errors {
-        warn!("{}", error.to_string_with_location(&db));
+    match options.output {
+        OutputFormat::Human => {
+            let mut renderer = human::DiagnosticRenderer::new(&db);
+            for error in &errors {
+                renderer.render_error(error);
+            }
+            for warning in &warnings {
+                renderer.render_lint_warning(warning);
+            }
+        }
+        OutputFormat::Json => {
+            let reports = machine_readable::reports(&db, &packages_path, &errors, &warnings);
+            println!("{:#}", machine_readable::to_json(&reports));
+        }
+        OutputFormat::Sarif => {
+            let reports = machine_readable::reports(&db, &packages_path, &errors, &warnings);
+            println!("{:#}", machine_readable::to_sarif(&reports));
+        }
     }
 
     if has_errors {
@@ -44,3 +124,39 @@ pub fn check(options: Options) -> ProgramResult {
         Ok(())
     }
 }
+
+/// The package's lint levels, overridden by the ones given on the command line.
+fn lint_levels(
+    db: &Database,
+    package: &Package,
+    deny: &[String],
+    allow: &[String],
+) -> Result<LintLevels, Exit> {
+    let mut levels = LintLevels::for_package(db, package).map_err(|message| {
+        error!("Invalid package configuration: {message}");
+        Exit::InvalidArguments
+    })?;
+    let overrides = deny
+        .iter()
+        .map(|code| (code, LintLevel::Deny))
+        .chain(allow.iter().map(|code| (code, LintLevel::Allow)));
+    for (code, level) in overrides {
+        levels.set_from_code(code, level).map_err(|message| {
+            error!("{message}");
+            Exit::InvalidArguments
+        })?;
+    }
+    Ok(levels)
+}
+
+fn update_symbol_index(db: &Database, packages_path: &PackagesPath, package: &Package) {
+    let Some(package_path) = package.to_path(packages_path) else {
+        return;
+    };
+    let mut index = SymbolIndex::load(&package_path);
+    if index.update(db, packages_path, package)
+        && let Err(error) = index.save(&package_path)
+    {
+        warn!("Couldn't save the symbol index: {error}");
+    }
+}
@@ -0,0 +1,122 @@
+use crate::database::Database;
+use candy_frontend::{
+    error::CompilerError,
+    lints::{LintLevel, LintWarning},
+    module::{Module, ModuleDb},
+    position::Offset,
+};
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label, LabelStyle},
+    files::SimpleFiles,
+    term::{
+        self,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::ops::Range;
+use tracing::warn;
+
+/// Prints errors and lint warnings together with the affected source code.
+pub struct DiagnosticRenderer<'a> {
+    db: &'a Database,
+    files: SimpleFiles<String, String>,
+    file_ids: FxHashMap<Module, usize>,
+    writer: StandardStream,
+    config: term::Config,
+}
+impl<'a> DiagnosticRenderer<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            files: SimpleFiles::new(),
+            file_ids: FxHashMap::default(),
+            writer: StandardStream::stderr(ColorChoice::Auto),
+            config: term::Config::default(),
+        }
+    }
+
+    pub fn render_error(&mut self, error: &CompilerError) {
+        let mut labels = vec![];
+        labels.extend(self.label(LabelStyle::Primary, &error.module, error.span.clone()));
+        for label in &error.labels {
+            labels.extend(
+                self.label(LabelStyle::Secondary, &label.module, label.span.clone())
+                    .map(|it| it.with_message(&label.message)),
+            );
+        }
+        let mut notes = Self::location_note(&error.module, &labels);
+        notes.extend(error.notes.iter().cloned());
+        notes.extend(
+            error
+                .suggestions
+                .iter()
+                .map(|suggestion| format!("help: {}", suggestion.message)),
+        );
+
+        let diagnostic = Diagnostic::error()
+            .with_code(error.payload.code())
+            .with_message(error.payload.to_string())
+            .with_labels(labels)
+            .with_notes(notes);
+        self.emit(&diagnostic);
+    }
+    pub fn render_lint_warning(&mut self, warning: &LintWarning) {
+        let diagnostic = match warning.level {
+            LintLevel::Allow | LintLevel::Warn => Diagnostic::warning(),
+            LintLevel::Deny => Diagnostic::error(),
+        };
+        let labels = self
+            .label(LabelStyle::Primary, &warning.module, warning.span.clone())
+            .into_iter()
+            .collect_vec();
+        let notes = Self::location_note(&warning.module, &labels);
+
+        let diagnostic = diagnostic
+            .with_code(warning.lint.code())
+            .with_message(&warning.message)
+            .with_labels(labels)
+            .with_notes(notes);
+        self.emit(&diagnostic);
+    }
+
+    /// Modules without source code, e.g., ones that don't exist, can't be
+    /// labeled, so we at least mention their name.
+    fn location_note(module: &Module, labels: &[Label<usize>]) -> Vec<String> {
+        if labels.is_empty() {
+            vec![format!("in {module}")]
+        } else {
+            vec![]
+        }
+    }
+    fn label(
+        &mut self,
+        style: LabelStyle,
+        module: &Module,
+        span: Range<Offset>,
+    ) -> Option<Label<usize>> {
+        let file_id = self.file_id(module)?;
+        Some(Label::new(style, file_id, *span.start..*span.end))
+    }
+    fn file_id(&mut self, module: &Module) -> Option<usize> {
+        if let Some(file_id) = self.file_ids.get(module) {
+            return Some(*file_id);
+        }
+        let source = self.db.get_module_content_as_string(module.clone())?;
+        let file_id = self.files.add(module.to_string(), source.to_string());
+        self.file_ids.insert(module.clone(), file_id);
+        Some(file_id)
+    }
+
+    fn emit(&self, diagnostic: &Diagnostic<usize>) {
+        if let Err(error) = term::emit(
+            &mut self.writer.lock(),
+            &self.config,
+            &self.files,
+            diagnostic,
+        ) {
+            warn!("Couldn't print a diagnostic: {error}");
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Diagnostics for CI systems and other tools.
+//!
+//! The JSON format uses zero-based lines and characters (counting grapheme
+//! clusters) like the language server, as well as byte offsets. SARIF uses
+//! one-based lines and columns as required by its specification.
+
+use crate::database::Database;
+use candy_frontend::{
+    error::CompilerError,
+    lints::{LintLevel, LintWarning},
+    module::{Module, PackagesPath},
+    position::{Offset, Position, PositionConversionDb},
+};
+use itertools::Itertools;
+use serde_json::{json, Value};
+use std::{
+    env, iter,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// A compiler error or lint warning, independent of the output format.
+pub struct Report {
+    severity: Severity,
+    code: String,
+    message: String,
+    notes: Vec<String>,
+    location: Location,
+    related: Vec<(Location, String)>,
+}
+#[derive(Clone, Copy)]
+enum Severity {
+    Error,
+    Warning,
+}
+struct Location {
+    module: Module,
+    /// `None` for modules that don't exist on disk.
+    path: Option<PathBuf>,
+    span: Range<Offset>,
+    range: Range<Position>,
+}
+
+#[must_use]
+pub fn reports(
+    db: &Database,
+    packages_path: &PackagesPath,
+    errors: &[CompilerError],
+    warnings: &[LintWarning],
+) -> Vec<Report> {
+    let errors = errors.iter().map(|error| Report {
+        severity: Severity::Error,
+        code: error.payload.code(),
+        message: error.payload.to_string(),
+        notes: error
+            .notes
+            .iter()
+            .cloned()
+            .chain(
+                error
+                    .suggestions
+                    .iter()
+                    .map(|suggestion| format!("help: {}", suggestion.message)),
+            )
+            .collect(),
+        location: Location::new(db, packages_path, &error.module, error.span.clone()),
+        related: error
+            .labels
+            .iter()
+            .map(|label| {
+                (
+                    Location::new(db, packages_path, &label.module, label.span.clone()),
+                    label.message.clone(),
+                )
+            })
+            .collect(),
+    });
+    let warnings = warnings.iter().map(|warning| Report {
+        severity: match warning.level {
+            LintLevel::Allow | LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        },
+        code: warning.lint.code().to_string(),
+        message: warning.message.clone(),
+        notes: vec![],
+        location: Location::new(db, packages_path, &warning.module, warning.span.clone()),
+        related: vec![],
+    });
+    errors.chain(warnings).collect()
+}
+impl Location {
+    fn new(
+        db: &Database,
+        packages_path: &PackagesPath,
+        module: &Module,
+        span: Range<Offset>,
+    ) -> Self {
+        Self {
+            module: module.clone(),
+            path: module.try_to_path(packages_path),
+            range: db.range_to_positions(module.clone(), span.clone()),
+            span,
+        }
+    }
+}
+impl Severity {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+#[must_use]
+pub fn to_json(reports: &[Report]) -> Value {
+    let diagnostics = reports
+        .iter()
+        .map(|report| {
+            let related = report
+                .related
+                .iter()
+                .map(|(location, message)| {
+                    let mut related = location_to_json(location);
+                    related["message"] = json!(message);
+                    related
+                })
+                .collect_vec();
+            let mut diagnostic = location_to_json(&report.location);
+            diagnostic["severity"] = json!(report.severity.as_str());
+            diagnostic["code"] = json!(report.code);
+            diagnostic["message"] = json!(report.message);
+            diagnostic["notes"] = json!(report.notes);
+            diagnostic["related"] = json!(related);
+            diagnostic
+        })
+        .collect_vec();
+    json!({ "diagnostics": diagnostics })
+}
+fn location_to_json(location: &Location) -> Value {
+    let position_to_json = |offset: Offset, position: Position| {
+        json!({
+            "offset": *offset,
+            "line": position.line,
+            "character": position.character,
+        })
+    };
+    json!({
+        "module": location.module.to_string(),
+        "path": location.path,
+        "start": position_to_json(location.span.start, location.range.start),
+        "end": position_to_json(location.span.end, location.range.end),
+    })
+}
+
+/// See <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>.
+#[must_use]
+pub fn to_sarif(reports: &[Report]) -> Value {
+    let current_directory = env::current_dir().ok();
+    let rules = reports
+        .iter()
+        .map(|report| &report.code)
+        .unique()
+        .sorted()
+        .map(|code| json!({ "id": code }))
+        .collect_vec();
+    let results = reports
+        .iter()
+        .map(|report| {
+            let related_locations = report
+                .related
+                .iter()
+                .enumerate()
+                .map(|(index, (location, message))| {
+                    let mut location = location_to_sarif(location, current_directory.as_deref());
+                    location["id"] = json!(index);
+                    location["message"] = json!({ "text": message });
+                    location
+                })
+                .collect_vec();
+            let text = iter::once(report.message.clone())
+                .chain(report.notes.iter().cloned())
+                .join("\n");
+            json!({
+                "ruleId": report.code,
+                "level": report.severity.as_str(),
+                "message": { "text": text },
+                "locations": [location_to_sarif(&report.location, current_directory.as_deref())],
+                "relatedLocations": related_locations,
+            })
+        })
+        .collect_vec();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "candy",
+                    "informationUri": "https://github.com/candy-lang/candy",
+                    "rules": rules,
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+fn location_to_sarif(location: &Location, current_directory: Option<&Path>) -> Value {
+    // Paths relative to the current directory work best for tools that show
+    // results in a repository.
+    let uri = location.path.as_ref().map_or_else(
+        || location.module.to_string(),
+        |path| {
+            current_directory
+                .and_then(|directory| path.strip_prefix(directory).ok())
+                .map_or_else(
+                    || format!("file://{}", path.to_string_lossy().replace('\\', "/")),
+                    |relative| relative.to_string_lossy().replace('\\', "/"),
+                )
+        },
+    );
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": uri },
+            "region": {
+                "startLine": location.range.start.line + 1,
+                "startColumn": location.range.start.character + 1,
+                "endLine": location.range.end.line + 1,
+                "endColumn": location.range.end.character + 1,
+            },
+        },
+    })
+}
@@ -3,6 +3,7 @@ use candy_backend_inkwell::LlvmIrStorage;
 use candy_frontend::{
     ast::AstDbStorage,
     ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
     cst::CstDbStorage,
     cst_to_ast::CstToAstStorage,
     hir::HirDbStorage,
@@ -13,18 +14,21 @@ use candy_frontend::{
     module::{
         FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
         ModuleDbStorage, ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner,
-        OverlayModuleProvider, PackagesPath,
+        OverlayModuleProvider, PackagesPath, VirtualModuleProvider,
     },
+    persistent_cache::{PersistentCache, PersistentCacheOwner},
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
     string_to_rcst::StringToRcstStorage,
 };
+use candy_vm::origins::ByteCodeOriginsStorage;
 
 #[cfg_attr(
     feature = "inkwell",
     salsa::database(
         AstDbStorage,
         AstToHirStorage,
+        ByteCodeOriginsStorage,
         CstDbStorage,
         CstToAstStorage,
         HirDbStorage,
@@ -44,6 +48,7 @@ use candy_frontend::{
     salsa::database(
         AstDbStorage,
         AstToHirStorage,
+        ByteCodeOriginsStorage,
         CstDbStorage,
         CstToAstStorage,
         HirDbStorage,
@@ -59,21 +64,33 @@ use candy_frontend::{
 )]
 pub struct Database {
     storage: salsa::Storage<Self>,
-    module_provider: OverlayModuleProvider<InMemoryModuleProvider, Box<dyn ModuleProvider + Send>>,
+    /// Modules opened in an editor shadow virtual modules, which shadow the
+    /// underlying module provider.
+    module_provider: OverlayModuleProvider<
+        InMemoryModuleProvider,
+        OverlayModuleProvider<VirtualModuleProvider, Box<dyn ModuleProvider + Send>>,
+    >,
+    persistent_cache: Option<PersistentCache>,
 }
 impl salsa::Database for Database {}
 
 impl Database {
+    /// Also uses the persistent cache in the packages directory.
     pub fn new_with_file_system_module_provider(packages_path: PackagesPath) -> Self {
-        Self::new(Box::new(FileSystemModuleProvider { packages_path }))
+        let persistent_cache = PersistentCache::open(PersistentCache::directory(&packages_path));
+        Self {
+            persistent_cache,
+            ..Self::new(Box::new(FileSystemModuleProvider { packages_path }))
+        }
     }
     pub fn new(module_provider: Box<dyn ModuleProvider + Send>) -> Self {
         Self {
             storage: salsa::Storage::default(),
             module_provider: OverlayModuleProvider::new(
                 InMemoryModuleProvider::default(),
-                module_provider,
+                OverlayModuleProvider::new(VirtualModuleProvider::default(), module_provider),
             ),
+            persistent_cache: None,
         }
     }
 }
@@ -83,10 +100,19 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl CancellationTokenOwner for Database {}
+impl PersistentCacheOwner for Database {
+    fn get_persistent_cache(&self) -> Option<&PersistentCache> {
+        self.persistent_cache.as_ref()
+    }
+}
 impl MutableModuleProviderOwner for Database {
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
         &mut self.module_provider.overlay
     }
+    fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider {
+        &mut self.module_provider.fallback.overlay
+    }
     fn invalidate_module(&mut self, module: &Module) {
         GetModuleContentQuery.in_db_mut(self).invalidate(module);
     }
@@ -1,6 +1,6 @@
 use crate::{
     database::Database,
-    utils::{module_for_path, packages_path},
+    utils::{module_for_path, packages_path, OptimizationOptions},
     Exit, ProgramResult,
 };
 #[cfg(feature = "inkwell")]
@@ -10,6 +10,7 @@ use candy_frontend::{
     cst_to_ast::CstToAst,
     hir_to_mir::{ExecutionTarget, HirToMir},
     lir_optimize::OptimizeLir,
+    mangling::{demangle, demangle_all},
     mir_optimize::OptimizeMir,
     mir_to_lir::MirToLir,
     module::Module,
@@ -19,7 +20,7 @@ use candy_frontend::{
     string_to_rcst::StringToRcst,
     tracing::CallTracingMode,
     utils::DoHash,
-    TracingConfig, TracingMode,
+    OptimizationConfig, TracingConfig, TracingMode,
 };
 use candy_vm::{byte_code::RichIrForByteCode, heap::HeapData, lir_to_byte_code::compile_byte_code};
 use clap::{Parser, ValueEnum, ValueHint};
@@ -36,6 +37,9 @@ use std::{
 };
 use walkdir::WalkDir;
 
+mod coverage;
+mod flamegraph;
+
 /// Debug the Candy compiler itself.
 ///
 /// This command compiles the given file and outputs its intermediate
@@ -73,6 +77,15 @@ pub enum Options {
     #[cfg(feature = "inkwell")]
     LlvmIr(PathAndExecutionTarget),
 
+    /// Annotated source listing of which lines a program executed
+    Coverage(coverage::Options),
+
+    /// Profile of where a program spends its time, e.g., for flame graphs
+    Flamegraph(flamegraph::Options),
+
+    /// Readable form of symbol names generated by the native backends
+    Demangle(Demangle),
+
     #[command(subcommand)]
     Gold(Gold),
 }
@@ -83,6 +96,13 @@ pub struct OnlyPath {
     path: PathBuf,
 }
 
+/// Demangle the given symbols or, if none are given, all symbols in stdin
+/// (e.g., a stack trace of a program compiled with LLVM).
+#[derive(Parser, Debug)]
+pub struct Demangle {
+    symbols: Vec<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct PathAndExecutionTargetAndTracing {
     #[arg(value_hint = ValueHint::FilePath)]
@@ -124,6 +144,13 @@ pub struct PathAndExecutionTargetAndTracing {
         require_equals(true)
     )]
     trace_evaluated_expressions: TracingMode,
+
+    /// Keep calls of `✨.debugAssert` and `✨.debugLog`.
+    #[arg(long)]
+    debug_assertions: bool,
+
+    #[command(flatten)]
+    optimization: OptimizationOptions,
 }
 impl PathAndExecutionTargetAndTracing {
     #[must_use]
@@ -132,8 +159,14 @@ impl PathAndExecutionTargetAndTracing {
             register_fuzzables: self.register_fuzzables,
             calls: self.trace_calls,
             evaluated_expressions: self.trace_evaluated_expressions,
+            debug_assertions: self.debug_assertions,
+            strip: false,
         }
     }
+    #[must_use]
+    const fn to_optimization_config(&self) -> OptimizationConfig {
+        self.optimization.to_config()
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -143,6 +176,9 @@ pub struct PathAndExecutionTarget {
 
     #[arg(long, value_enum, default_value_t = ExecutionTargetKind::Module)]
     execution_target: ExecutionTargetKind,
+
+    #[command(flatten)]
+    optimization: OptimizationOptions,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
@@ -197,7 +233,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let mir = db.optimized_mir(execution_target, tracing);
+            let mir = db.optimized_mir(execution_target, tracing, options.to_optimization_config());
             mir.ok()
                 .map(|(mir, _)| RichIr::for_optimized_mir(&module, &mir, tracing))
         }
@@ -205,7 +241,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let lir = db.lir(execution_target, tracing);
+            let lir = db.lir(execution_target, tracing, options.to_optimization_config());
             lir.ok()
                 .map(|(lir, _)| RichIr::for_lir(&module, &lir, tracing))
         }
@@ -213,7 +249,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let lir = db.optimized_lir(execution_target, tracing);
+            let lir = db.optimized_lir(execution_target, tracing, options.to_optimization_config());
             lir.ok()
                 .map(|(lir, _)| RichIr::for_optimized_lir(&module, &lir, tracing))
         }
@@ -221,15 +257,24 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let (vm_byte_code, _) = compile_byte_code(&db, execution_target, tracing);
+            let (vm_byte_code, _) = compile_byte_code(
+                &db,
+                execution_target,
+                tracing,
+                options.to_optimization_config(),
+            );
             Some(RichIr::for_byte_code(&module, &vm_byte_code, tracing))
         }
         #[cfg(feature = "inkwell")]
         Options::LlvmIr(options) => {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module);
-            db.llvm_ir(execution_target).ok()
+            let optimization = options.optimization.to_config();
+            db.llvm_ir(execution_target, optimization).ok()
         }
+        Options::Coverage(options) => return coverage::coverage(&db, options),
+        Options::Flamegraph(options) => return flamegraph::flamegraph(&db, options),
+        Options::Demangle(options) => return options.run(),
         Options::Gold(options) => return options.run(&db),
     };
 
@@ -278,6 +323,27 @@ pub fn debug(options: Options) -> ProgramResult {
     Ok(())
 }
 
+impl Demangle {
+    #[allow(clippy::unnecessary_wraps)] // Like the other commands.
+    fn run(self) -> ProgramResult {
+        if self.symbols.is_empty() {
+            for line in io::stdin().lines() {
+                let line = line.unwrap();
+                println!("{}", demangle_all(&line));
+            }
+            return Ok(());
+        }
+
+        for symbol in &self.symbols {
+            match demangle(symbol) {
+                Some(demangled) => println!("{demangled}"),
+                None => println!("{symbol}"),
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Dump IRs next to the original files to compare outputs of different compiler
 /// versions.
 #[derive(Parser, Debug)]
@@ -353,7 +419,10 @@ impl GoldOptions {
         register_fuzzables: TracingMode::Off,
         calls: CallTracingMode::OnlyForPanicTraces,
         evaluated_expressions: TracingMode::Off,
+        debug_assertions: false,
+        strip: false,
     };
+    const OPTIMIZATION_CONFIG: OptimizationConfig = OptimizationConfig::release();
 
     fn visit_irs(
         &self,
@@ -415,27 +484,43 @@ impl GoldOptions {
             visit("MIR", mir.text);
 
             let (optimized_mir, _) = db
-                .optimized_mir(execution_target.clone(), Self::TRACING_CONFIG)
+                .optimized_mir(
+                    execution_target.clone(),
+                    Self::TRACING_CONFIG,
+                    Self::OPTIMIZATION_CONFIG,
+                )
                 .unwrap();
             let optimized_mir =
                 RichIr::for_optimized_mir(&module, &optimized_mir, Self::TRACING_CONFIG);
             visit("Optimized MIR", optimized_mir.text);
 
             let (lir, _) = db
-                .lir(execution_target.clone(), Self::TRACING_CONFIG)
+                .lir(
+                    execution_target.clone(),
+                    Self::TRACING_CONFIG,
+                    Self::OPTIMIZATION_CONFIG,
+                )
                 .unwrap();
             let lir = RichIr::for_lir(&module, &lir, Self::TRACING_CONFIG);
             visit("LIR", lir.text);
 
             let (optimized_lir, _) = db
-                .optimized_lir(execution_target.clone(), Self::TRACING_CONFIG)
+                .optimized_lir(
+                    execution_target.clone(),
+                    Self::TRACING_CONFIG,
+                    Self::OPTIMIZATION_CONFIG,
+                )
                 .unwrap();
             let optimized_lir =
                 RichIr::for_optimized_lir(&module, &optimized_lir, Self::TRACING_CONFIG);
             visit("Optimized LIR", optimized_lir.text);
 
-            let (vm_byte_code, _) =
-                compile_byte_code(db, execution_target.clone(), Self::TRACING_CONFIG);
+            let (vm_byte_code, _) = compile_byte_code(
+                db,
+                execution_target.clone(),
+                Self::TRACING_CONFIG,
+                Self::OPTIMIZATION_CONFIG,
+            );
             let vm_byte_code_rich_ir =
                 RichIr::for_byte_code(&module, &vm_byte_code, Self::TRACING_CONFIG);
             visit(
@@ -445,7 +530,9 @@ impl GoldOptions {
 
             #[cfg(feature = "inkwell")]
             {
-                let llvm_ir = db.llvm_ir(execution_target).unwrap();
+                let llvm_ir = db
+                    .llvm_ir(execution_target, Self::OPTIMIZATION_CONFIG)
+                    .unwrap();
                 visit("LLVM IR", llvm_ir.text);
             }
         }
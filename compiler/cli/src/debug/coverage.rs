@@ -0,0 +1,159 @@
+use super::ExecutionTargetKind;
+use crate::{
+    database::Database,
+    utils::{module_for_path, packages_path},
+    Exit, ProgramResult,
+};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    hir::HirDb,
+    module::{Module, ModuleDb},
+    position::PositionConversionDb,
+    tracing::CallTracingMode,
+    OptimizationConfig, TracingConfig, TracingMode,
+};
+use candy_vm::{
+    heap::Heap, launcher::Launcher, lir_to_byte_code::compile_byte_code,
+    tracer::coverage::CoverageTracer, VmFinished,
+};
+use clap::{Parser, ValueHint};
+use colored::Colorize;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::error;
+
+/// Run a module and show which of its lines were executed.
+///
+/// Lines containing executed code are shown in green, lines containing code
+/// that never ran in red. The gutter shows how often each line was executed.
+#[derive(Parser, Debug)]
+pub struct Options {
+    #[arg(value_hint = ValueHint::FilePath)]
+    path: PathBuf,
+
+    /// Whether to call the exported `main` function or only run the module's
+    /// top-level code (e.g., its tests).
+    #[arg(long, value_enum, default_value_t = ExecutionTargetKind::MainFunction)]
+    execution_target: ExecutionTargetKind,
+
+    /// Instead of printing the annotated source, write the coverage to this
+    /// file in the LCOV format, which many editors can display.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    lcov: Option<PathBuf>,
+
+    #[arg(last(true))]
+    arguments: Vec<String>,
+}
+
+pub fn coverage(db: &Database, options: Options) -> ProgramResult {
+    let module = module_for_path(options.path)?;
+    let execution_target = options.execution_target.resolve(module.clone());
+    let tracing = TracingConfig {
+        register_fuzzables: TracingMode::Off,
+        calls: CallTracingMode::OnlyCurrent,
+        evaluated_expressions: TracingMode::OnlyCurrent,
+        debug_assertions: false,
+        strip: false,
+    };
+    // Fewer optimizations keep more expressions around whose coverage we can
+    // report.
+    let optimization = OptimizationConfig::debug();
+    let byte_code = compile_byte_code(db, execution_target.clone(), tracing, optimization).0;
+
+    let mut heap = Heap::default();
+    let tracer = CoverageTracer::new(module.clone());
+    let VmFinished { result, tracer, .. } = Launcher::new(&byte_code, tracer)
+        .run_with_default_environment(&mut heap, &execution_target, &options.arguments);
+    // We still show the coverage of programs that panicked since it helps to
+    // find out what happened.
+    let result = result.map(|_| ()).map_err(|panic| {
+        error!("The program panicked: {}", panic.reason);
+        error!("{} is responsible.", panic.responsible);
+        Exit::CodePanicked
+    });
+
+    let line_counts = line_counts(db, &module, &tracer);
+    if let Some(lcov_path) = options.lcov {
+        let Some(source_path) = module.try_to_path(&packages_path()) else {
+            error!("The module doesn't exist on disk.");
+            return Err(Exit::FileNotFound);
+        };
+        let lcov = format_lcov(&source_path, &line_counts);
+        if let Err(error) = fs::write(lcov_path, lcov) {
+            error!("Couldn't write the LCOV file: {error}");
+            return Err(Exit::FileNotFound);
+        }
+    } else {
+        let source = db.get_module_content_as_string(module).unwrap();
+        print_annotated_source(&source, &line_counts);
+    }
+    result
+}
+
+/// For each line, returns how often it was executed, or `None` if it doesn't
+/// contain any code.
+///
+/// Lines contain multiple expressions that can be executed a different number
+/// of times, e.g., when a function is called in a loop. We use the maximum.
+fn line_counts(db: &Database, module: &Module, tracer: &CoverageTracer) -> Vec<Option<usize>> {
+    let line_start_offsets = db.line_start_offsets(module.clone());
+    let mut line_counts = vec![None; line_start_offsets.len()];
+
+    let counts = tracer.counts();
+    for id in db.all_hir_ids(module.clone()) {
+        let Some(span) = db.hir_id_to_span(&id) else {
+            continue;
+        };
+        let line = db.offset_to_position(module.clone(), span.start).line;
+        let count = counts.get(&id).copied().unwrap_or_default();
+        let line_count = line_counts[line].get_or_insert(0);
+        *line_count = (*line_count).max(count);
+    }
+    line_counts
+}
+
+fn print_annotated_source(source: &str, line_counts: &[Option<usize>]) {
+    let gutter_width = line_counts
+        .iter()
+        .flatten()
+        .max()
+        .map_or(1, |it| it.to_string().len());
+    for (line, count) in source.lines().zip(line_counts) {
+        match count {
+            Some(0) => println!("{:>gutter_width$} │ {}", 0, line.red()),
+            Some(count) => println!("{count:>gutter_width$} │ {}", line.green()),
+            None => println!("{:gutter_width$} │ {line}", ""),
+        }
+    }
+
+    let num_lines_found = line_counts.iter().flatten().count();
+    let num_lines_hit = line_counts.iter().flatten().filter(|it| **it > 0).count();
+    println!();
+    #[allow(clippy::cast_precision_loss)]
+    let percentage = if num_lines_found == 0 {
+        100.0
+    } else {
+        num_lines_hit as f64 / num_lines_found as f64 * 100.0
+    };
+    println!("Executed {num_lines_hit} of {num_lines_found} lines ({percentage:.1} %).");
+}
+
+fn format_lcov(source_path: &Path, line_counts: &[Option<usize>]) -> String {
+    let mut lcov = String::new();
+    writeln!(lcov, "TN:").unwrap();
+    writeln!(lcov, "SF:{}", source_path.display()).unwrap();
+    for (line, count) in line_counts.iter().enumerate() {
+        if let Some(count) = count {
+            writeln!(lcov, "DA:{},{count}", line + 1).unwrap();
+        }
+    }
+    let num_lines_found = line_counts.iter().flatten().count();
+    let num_lines_hit = line_counts.iter().flatten().filter(|it| **it > 0).count();
+    writeln!(lcov, "LF:{num_lines_found}").unwrap();
+    writeln!(lcov, "LH:{num_lines_hit}").unwrap();
+    writeln!(lcov, "end_of_record").unwrap();
+    lcov
+}
@@ -0,0 +1,83 @@
+use super::ExecutionTargetKind;
+use crate::{database::Database, utils::module_for_path, Exit, ProgramResult};
+use candy_frontend::{tracing::CallTracingMode, OptimizationConfig, TracingConfig, TracingMode};
+use candy_vm::{
+    heap::Heap, launcher::Launcher, lir_to_byte_code::compile_byte_code,
+    tracer::flame_graph::FlameGraphTracer, VmFinished,
+};
+use clap::{Parser, ValueEnum, ValueHint};
+use std::{fs, path::PathBuf};
+use tracing::error;
+
+/// Run a module and measure how much time is spent in which functions.
+///
+/// Tracing all calls makes the program considerably slower, but the relative
+/// times are still useful to find out where to optimize.
+#[derive(Parser, Debug)]
+pub struct Options {
+    #[arg(value_hint = ValueHint::FilePath)]
+    path: PathBuf,
+
+    /// Whether to call the exported `main` function or only run the module's
+    /// top-level code (e.g., its tests).
+    #[arg(long, value_enum, default_value_t = ExecutionTargetKind::MainFunction)]
+    execution_target: ExecutionTargetKind,
+
+    #[arg(long, value_enum, default_value_t = Format::Collapsed)]
+    format: Format,
+
+    /// Write the profile to this file instead of stdout.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    #[arg(last(true))]
+    arguments: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    /// Collapsed stacks, e.g., for `inferno-flamegraph` or `flamegraph.pl`
+    Collapsed,
+    /// JSON for <https://www.speedscope.app>
+    Speedscope,
+}
+
+pub fn flamegraph(db: &Database, options: Options) -> ProgramResult {
+    let module = module_for_path(options.path)?;
+    let execution_target = options.execution_target.resolve(module);
+    let tracing = TracingConfig {
+        register_fuzzables: TracingMode::Off,
+        calls: CallTracingMode::All,
+        evaluated_expressions: TracingMode::Off,
+        debug_assertions: false,
+        strip: false,
+    };
+    let optimization = OptimizationConfig::release();
+    let byte_code = compile_byte_code(db, execution_target.clone(), tracing, optimization).0;
+
+    let mut heap = Heap::default();
+    let tracer = FlameGraphTracer::default();
+    let VmFinished { result, tracer, .. } = Launcher::new(&byte_code, tracer)
+        .run_with_default_environment(&mut heap, &execution_target, &options.arguments);
+    // Profiles of programs that panicked are still useful, e.g., if the
+    // program panicked after running for a long time.
+    let result = result.map(|_| ()).map_err(|panic| {
+        error!("The program panicked: {}", panic.reason);
+        error!("{} is responsible.", panic.responsible);
+        Exit::CodePanicked
+    });
+
+    let profile = match options.format {
+        Format::Collapsed => tracer.format_collapsed_stacks(&byte_code),
+        Format::Speedscope => tracer.to_speedscope_json(&byte_code).to_string(),
+    };
+    if let Some(output) = options.output {
+        if let Err(error) = fs::write(output, profile) {
+            error!("Couldn't write the profile: {error}");
+            return Err(Exit::FileNotFound);
+        }
+    } else {
+        print!("{profile}");
+    }
+    result
+}
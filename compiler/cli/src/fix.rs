@@ -0,0 +1,59 @@
+use crate::{
+    database::Database,
+    utils::{module_for_path, packages_path},
+    Exit, ProgramResult,
+};
+use candy_frontend::{module::ModuleDb, unused_uses::find_unused_uses};
+use clap::{arg, Parser, ValueHint};
+use std::{fs, path::PathBuf};
+use tracing::{error, info};
+
+/// Automatically fix problems in a Candy program.
+///
+/// Currently, this removes `use`s whose results are never used.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// The file or package to fix. If none is provided, the package of your
+    /// current working directory will be fixed.
+    #[arg(value_hint = ValueHint::FilePath)]
+    path: Option<PathBuf>,
+}
+
+pub fn fix(options: Options) -> ProgramResult {
+    let packages_path = packages_path();
+    let db = Database::new_with_file_system_module_provider(packages_path.clone());
+    let module = module_for_path(options.path)?;
+    let Some(path) = module.try_to_path(&packages_path) else {
+        error!("Couldn't find the file of module {module}.");
+        return Err(Exit::FileNotFound);
+    };
+    let Some(text) = db.get_module_content_as_string(module.clone()) else {
+        error!("Couldn't read {}.", path.display());
+        return Err(Exit::FileNotFound);
+    };
+
+    let unused_uses = find_unused_uses(&db, module);
+    if unused_uses.is_empty() {
+        info!("Nothing to fix.");
+        return Ok(());
+    }
+
+    let mut removals = vec![];
+    for unused_use in unused_uses {
+        for (name, _) in unused_use.unused_names {
+            info!("Removing unused `{name}`.");
+        }
+        removals.extend(unused_use.removals);
+    }
+    removals.sort_by_key(|it| it.start);
+    let mut fixed = (*text).clone();
+    for removal in removals.into_iter().rev() {
+        fixed.replace_range(*removal.start..*removal.end, "");
+    }
+
+    if let Err(error) = fs::write(&path, fixed) {
+        error!("Couldn't write {}: {error}", path.display());
+        return Err(Exit::FileNotFound);
+    }
+    Ok(())
+}
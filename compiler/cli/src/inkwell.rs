@@ -3,25 +3,30 @@ use crate::{
     utils::{module_for_path, packages_path},
     Exit, ProgramResult,
 };
-use candy_backend_inkwell::CodeGen;
+use candy_backend_inkwell::{CodeGen, LlvmCandyModule};
 use candy_frontend::{
     error::{CompilerError, CompilerErrorPayload},
     hir,
     hir_to_mir::ExecutionTarget,
     mir::Mir,
     mir_optimize::OptimizeMir,
-    module, TracingConfig,
+    module, OptimizationConfig, OptimizationLevel, TracingConfig,
 };
 use clap::{Parser, ValueHint};
+use itertools::Itertools;
 use rustc_hash::FxHashSet;
 use std::{ffi::OsStr, path::PathBuf, sync::Arc};
-use tracing::error;
+use tracing::{error, info};
 
 /// Compile a Candy program to a native binary.
 ///
 /// This command compiles the given file, or, if no file is provided, the package of
 /// your current working directory. The module should export a `main` function.
 /// This function is then called with an environment.
+///
+/// When passing `--export`, the module is instead compiled to an object file
+/// that exports the given functions with a C ABI. See `candy_runtime.h` for
+/// how to call them.
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug)]
 pub struct Options {
@@ -45,6 +50,32 @@ pub struct Options {
     #[arg(long, default_value = "ld.lld")]
     linker: String,
 
+    /// Export the given function with a C ABI instead of building an
+    /// executable. Can be passed multiple times.
+    #[arg(long = "export")]
+    exports: Vec<String>,
+
+    /// Allow calling extern functions. These are linked against arbitrary C
+    /// code, so only pass this flag for code you trust.
+    #[arg(long = "unsafe-allow-extern", default_value_t = false)]
+    unsafe_allow_extern: bool,
+
+    /// Keep calls of `debugAssert` and `debugLog`. Without this flag, the
+    /// optimizer removes them entirely.
+    #[arg(long, default_value_t = false)]
+    debug_assertions: bool,
+
+    /// Remove information about the source code that isn't needed to run the
+    /// program, such as HIR IDs, function names, and the symbol table. Panics
+    /// then don't say which code is responsible.
+    #[arg(long, default_value_t = false, conflicts_with = "debug")]
+    strip: bool,
+
+    /// How much to optimize the program, both in Candy's optimizer and in
+    /// LLVM: `-O0` compiles fastest, `-O2` makes the program run fastest.
+    #[arg(short = 'O', value_enum, default_value_t = OptimizationLevel::O2)]
+    optimization_level: OptimizationLevel,
+
     /// The file or package to compile. If none is provided, compile the package
     /// of your current working directory.
     #[arg(value_hint = ValueHint::FilePath)]
@@ -68,11 +99,22 @@ pub fn compile(options: &Options) -> ProgramResult {
         .to_string_lossy()
         .to_string();
 
+    let is_library = !options.exports.is_empty();
+    let target = if is_library {
+        ExecutionTarget::Module(module.clone())
+    } else {
+        ExecutionTarget::MainFunction(module.clone())
+    };
     #[allow(clippy::map_unwrap_or)]
     let (mir, errors) = db
         .optimized_mir(
-            ExecutionTarget::MainFunction(module.clone()),
-            TracingConfig::off(),
+            target,
+            TracingConfig {
+                debug_assertions: options.debug_assertions,
+                strip: options.strip,
+                ..TracingConfig::off()
+            },
+            OptimizationConfig::for_level(options.optimization_level),
         )
         .unwrap_or_else(|error| {
             let payload = CompilerErrorPayload::Module(error);
@@ -87,19 +129,57 @@ pub fn compile(options: &Options) -> ProgramResult {
         });
 
     if !errors.is_empty() {
-        for error in errors.as_ref() {
+        for error in errors
+            .iter()
+            .sorted_by_key(|it| (it.module.clone(), it.span.start))
+        {
             println!("{error:?}");
         }
         std::process::exit(1);
     }
 
     let context = candy_backend_inkwell::inkwell::context::Context::create();
-    let codegen = CodeGen::new(&context, &path, mir);
+    let mut codegen = CodeGen::new(&context, &path, mir);
+    let extern_functions = codegen.extern_functions();
+    if !extern_functions.is_empty() && !options.unsafe_allow_extern {
+        error!(
+            "The code uses the extern functions {}. Pass `--unsafe-allow-extern` if you trust it.",
+            extern_functions
+                .iter()
+                .map(|it| format!("`{it}`"))
+                .join(", "),
+        );
+        return Err(Exit::ExternFunctionsNotAllowed);
+    }
+    codegen
+        .export_functions(&options.exports)
+        .map_err(Exit::LlvmError)?;
     let llvm_candy_module = codegen
         .compile(options.print_llvm_ir, options.print_main_output)
         .map_err(|e| Exit::LlvmError(e.to_string()))?;
+
+    if is_library {
+        if options.build_runtime {
+            LlvmCandyModule::build_runtime().map_err(|err| {
+                error!("Failed to build the runtime: {err}");
+                Exit::ExternalError
+            })?;
+        }
+        let o_path = llvm_candy_module.compile_obj(&path, options.optimization_level);
+        info!(
+            "Compiled to {o_path}. Link it together with `compiler/backend_inkwell/candy_runtime/candy_runtime.a`."
+        );
+        return ProgramResult::Ok(());
+    }
     llvm_candy_module
-        .compile_obj_and_link(&path, options.build_runtime, options.debug, &options.linker)
+        .compile_obj_and_link(
+            &path,
+            options.build_runtime,
+            options.debug,
+            options.strip,
+            &options.linker,
+            options.optimization_level,
+        )
         .map_err(|err| {
             error!("Failed to compile and link executable: {err}");
             Exit::ExternalError
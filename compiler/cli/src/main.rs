@@ -1,4 +1,4 @@
-#![feature(lazy_cell)]
+#![feature(lazy_cell, let_chains)]
 #![warn(clippy::nursery, clippy::pedantic, unused_crate_dependencies)]
 #![allow(
     clippy::cognitive_complexity,
@@ -23,11 +23,15 @@ use tracing_subscriber::{
 mod check;
 mod database;
 mod debug;
+mod fix;
 mod fuzz;
 #[cfg(feature = "inkwell")]
 mod inkwell;
 mod lsp;
+mod packages;
+mod repl;
 mod run;
+mod serve;
 mod utils;
 
 #[derive(Parser, Debug)]
@@ -37,14 +41,24 @@ enum CandyOptions {
 
     Check(check::Options),
 
+    Fix(fix::Options),
+
     Fuzz(fuzz::Options),
 
+    Add(packages::AddOptions),
+
+    Install(packages::InstallOptions),
+
+    Repl(repl::Options),
+
     #[command(subcommand)]
     Debug(debug::Options),
 
     /// Start a Language Server.
     Lsp,
 
+    Serve(serve::Options),
+
     #[cfg(feature = "inkwell")]
     Inkwell(inkwell::Options),
 }
@@ -60,9 +74,14 @@ async fn main() -> ProgramResult {
     match options {
         CandyOptions::Run(options) => run::run(options),
         CandyOptions::Check(options) => check::check(options),
+        CandyOptions::Fix(options) => fix::fix(options),
         CandyOptions::Fuzz(options) => fuzz::fuzz(options),
+        CandyOptions::Add(options) => packages::add(&options),
+        CandyOptions::Install(options) => packages::install(&options),
+        CandyOptions::Repl(options) => repl::repl(&options),
         CandyOptions::Debug(options) => debug::debug(options),
         CandyOptions::Lsp => lsp::lsp().await,
+        CandyOptions::Serve(options) => serve::serve(&options),
         #[cfg(feature = "inkwell")]
         CandyOptions::Inkwell(options) => inkwell::compile(&options),
     }
@@ -72,16 +91,24 @@ pub type ProgramResult = Result<(), Exit>;
 #[derive(Debug)]
 pub enum Exit {
     CodePanicked,
+    CouldNotFetchPackage,
+    CouldNotStartServer,
     DirectoryNotFound,
+    EntryNotFound,
     #[cfg(feature = "inkwell")]
     ExternalError,
+    #[cfg(feature = "inkwell")]
+    ExternFunctionsNotAllowed,
     FileNotFound,
     FuzzingFoundFailingCases,
+    InvalidArguments,
     NotInCandyPackage,
+    OutOfMemory,
     CodeContainsErrors,
     #[cfg(feature = "inkwell")]
     LlvmError(String),
     GoldOutdated,
+    ReturnValueNotJson,
 }
 
 fn init_logger(use_stdout: bool) {
@@ -0,0 +1,170 @@
+use crate::{utils::packages_path, Exit, ProgramResult};
+use candy_frontend::module::PackagesPath;
+use clap::{arg, Args, Parser};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+use tracing::{error, info};
+
+use self::{
+    lockfile::{LockedPackage, Lockfile},
+    source::{Registry, Source},
+};
+
+mod lockfile;
+mod source;
+
+/// Add a package from a registry to the current package.
+///
+/// The package is downloaded into the packages path, where `use "Name"` finds
+/// it. Its source, revision, and checksum are recorded in the `candy.lock`
+/// next to your `_package.candy` so that `candy install` can later download
+/// exactly the same code.
+#[derive(Parser, Debug)]
+pub struct AddOptions {
+    /// The name of the package, e.g., `Foo`.
+    package: String,
+
+    #[command(flatten)]
+    registry: RegistryOptions,
+}
+
+/// Download all packages recorded in the `candy.lock` of the current package.
+///
+/// Packages that are already present with the recorded checksum are skipped.
+/// Downloaded packages whose checksum doesn't match the recorded one are
+/// rejected.
+#[derive(Parser, Debug)]
+pub struct InstallOptions {}
+
+#[derive(Args, Debug)]
+struct RegistryOptions {
+    /// Where to download packages from. Use `git+<url>` with an `https://` or
+    /// `ssh://` URL for a registry where each package is a Git repository at
+    /// `<url>/<package>`, or an HTTP(S) URL for a registry that serves
+    /// `<url>/<package>.tar.gz`.
+    ///
+    /// Defaults to the `CANDY_REGISTRY` environment variable or, if that isn't
+    /// set, to the Candy organization on GitHub.
+    #[arg(long)]
+    registry: Option<String>,
+}
+impl RegistryOptions {
+    fn resolve(&self) -> Result<Registry, Exit> {
+        let url = self
+            .registry
+            .clone()
+            .or_else(|| env::var("CANDY_REGISTRY").ok())
+            .unwrap_or_else(|| Registry::DEFAULT.to_string());
+        Registry::parse(&url).map_err(|message| {
+            error!("{message}");
+            Exit::InvalidArguments
+        })
+    }
+}
+
+pub fn add(options: &AddOptions) -> ProgramResult {
+    let name = &options.package;
+    if !is_valid_package_name(name) {
+        error!("`{name}` is not a valid package name.");
+        return Err(Exit::InvalidArguments);
+    }
+    let registry = options.registry.resolve()?;
+    let packages_path = packages_path();
+    let package_directory = current_package_directory(&packages_path)?;
+    let mut lockfile = load_lockfile(&package_directory)?;
+
+    let source = registry.source_of(name);
+    info!("Downloading {name} from {source}…");
+    let fetched = source
+        .fetch(&packages_path, name, None, None)
+        .map_err(|message| {
+            error!("Couldn't add {name}: {message}");
+            Exit::CouldNotFetchPackage
+        })?;
+    lockfile.packages.insert(
+        name.clone(),
+        LockedPackage {
+            source: source.to_string(),
+            revision: fetched.revision,
+            checksum: fetched.checksum,
+        },
+    );
+    save_lockfile(&lockfile, &package_directory)?;
+    info!("Added {name}.");
+    Ok(())
+}
+
+pub fn install(_options: &InstallOptions) -> ProgramResult {
+    let packages_path = packages_path();
+    let package_directory = current_package_directory(&packages_path)?;
+    let lockfile = load_lockfile(&package_directory)?;
+    if lockfile.packages.is_empty() {
+        info!("There are no packages to install.");
+        return Ok(());
+    }
+
+    let mut has_failed = false;
+    for (name, locked) in &lockfile.packages {
+        let destination = packages_path.join(name);
+        if destination.is_dir()
+            && source::checksum(&destination).is_ok_and(|it| it == locked.checksum)
+        {
+            info!("{name} is up to date.");
+            continue;
+        }
+
+        info!("Downloading {name} from {}…", locked.source);
+        let result = Source::parse(&locked.source).and_then(|source| {
+            source.fetch(
+                &packages_path,
+                name,
+                locked.revision.as_deref(),
+                Some(&locked.checksum),
+            )
+        });
+        if let Err(message) = result {
+            error!("Couldn't install {name}: {message}");
+            has_failed = true;
+        }
+    }
+
+    if has_failed {
+        Err(Exit::CouldNotFetchPackage)
+    } else {
+        info!("Installed all packages.");
+        Ok(())
+    }
+}
+
+/// Package names are used as directory names and in `use "…"`.
+fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+fn current_package_directory(packages_path: &PackagesPath) -> Result<PathBuf, Exit> {
+    packages_path
+        .find_surrounding_package(&env::current_dir().unwrap())
+        .and_then(|package| package.to_path(packages_path))
+        .ok_or_else(|| {
+            error!("You are not in a Candy package. Navigate into the package that should use the dependencies.");
+            Exit::NotInCandyPackage
+        })
+}
+fn load_lockfile(package_directory: &Path) -> Result<Lockfile, Exit> {
+    Lockfile::load(package_directory).map_err(|message| {
+        error!("Couldn't read the lockfile: {message}");
+        Exit::InvalidArguments
+    })
+}
+fn save_lockfile(lockfile: &Lockfile, package_directory: &Path) -> ProgramResult {
+    lockfile.save(package_directory).map_err(|error| {
+        error!("Couldn't save the lockfile: {error}");
+        Exit::FileNotFound
+    })
+}
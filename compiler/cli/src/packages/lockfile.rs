@@ -0,0 +1,89 @@
+use super::is_valid_package_name;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The exact versions of a package's dependencies, stored in `candy.lock` next
+/// to its `_package.candy`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LockedPackage {
+    /// Either `git+<url>` or an HTTP(S) URL of a `.tar.gz` archive.
+    pub source: String,
+    /// The commit of Git sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// See [`super::source::checksum`].
+    pub checksum: String,
+}
+
+impl Lockfile {
+    fn file(package_directory: &Path) -> PathBuf {
+        package_directory.join("candy.lock")
+    }
+
+    /// Returns an empty lockfile if the package doesn't have one yet.
+    pub fn load(package_directory: &Path) -> Result<Self, String> {
+        match fs::read_to_string(Self::file(package_directory)) {
+            Ok(content) => Self::parse(&content),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+    /// Package names become directory names in the packages path, so a
+    /// lockfile containing a name like `../foo` is rejected as a whole.
+    fn parse(content: &str) -> Result<Self, String> {
+        let lockfile: Self = serde_json::from_str(content).map_err(|error| error.to_string())?;
+        if let Some(name) = lockfile
+            .packages
+            .keys()
+            .find(|name| !is_valid_package_name(name))
+        {
+            return Err(format!("`{name}` is not a valid package name."));
+        }
+        Ok(lockfile)
+    }
+    pub fn save(&self, package_directory: &Path) -> io::Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(Self::file(package_directory), content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lockfile;
+
+    #[test]
+    fn rejects_package_names_outside_of_the_packages_path() {
+        let lockfile = r#"{
+            "packages": {
+                "../../home": {
+                    "source": "https://example.com/home.tar.gz",
+                    "checksum": "sha256:00"
+                }
+            }
+        }"#;
+        assert!(Lockfile::parse(lockfile).is_err());
+    }
+
+    #[test]
+    fn accepts_valid_package_names() {
+        let lockfile = r#"{
+            "packages": {
+                "Foo": {
+                    "source": "git+https://github.com/candy-lang/Foo",
+                    "revision": "0123456789abcdef0123456789abcdef01234567",
+                    "checksum": "sha256:00"
+                }
+            }
+        }"#;
+        assert!(Lockfile::parse(lockfile).is_ok());
+    }
+}
@@ -0,0 +1,265 @@
+use flate2::read::GzDecoder;
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use walkdir::{DirEntry, WalkDir};
+
+/// Where packages are downloaded from.
+pub enum Registry {
+    /// Each package is a Git repository at `<url>/<package>`.
+    Git(String),
+    /// Each package is a `.tar.gz` archive at `<url>/<package>.tar.gz`.
+    Http(String),
+}
+impl Registry {
+    pub const DEFAULT: &'static str = "git+https://github.com/candy-lang";
+
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let url = url.trim_end_matches('/');
+        if let Some(url) = url.strip_prefix("git+") {
+            validate_git_url(url)?;
+            Ok(Self::Git(url.to_string()))
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            Ok(Self::Http(url.to_string()))
+        } else {
+            Err(format!(
+                "`{url}` is not a valid registry. Use `git+<url>` or an HTTP(S) URL.",
+            ))
+        }
+    }
+
+    pub fn source_of(&self, package: &str) -> Source {
+        match self {
+            Self::Git(url) => Source::Git(format!("{url}/{package}")),
+            Self::Http(url) => Source::Http(format!("{url}/{package}.tar.gz")),
+        }
+    }
+}
+
+/// Where a single package is downloaded from.
+pub enum Source {
+    Git(String),
+    Http(String),
+}
+pub struct Fetched {
+    /// The commit of Git sources.
+    pub revision: Option<String>,
+    pub checksum: String,
+}
+impl Source {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if let Some(url) = source.strip_prefix("git+") {
+            validate_git_url(url)?;
+            Ok(Self::Git(url.to_string()))
+        } else if source.starts_with("http://") || source.starts_with("https://") {
+            Ok(Self::Http(source.to_string()))
+        } else {
+            Err(format!("`{source}` is not a valid package source."))
+        }
+    }
+
+    /// Downloads the package into `<packages_path>/<name>`, replacing the
+    /// previous version.
+    ///
+    /// The package is only moved into place if it matches the expected
+    /// checksum (if any), so a failed download never breaks an installed
+    /// package.
+    pub fn fetch(
+        &self,
+        packages_path: &Path,
+        name: &str,
+        revision: Option<&str>,
+        expected_checksum: Option<&str>,
+    ) -> Result<Fetched, String> {
+        let download_directory = packages_path.join(format!(".{name}.download"));
+        remove_directory_if_exists(&download_directory)?;
+        fs::create_dir_all(&download_directory).map_err(|error| error.to_string())?;
+
+        let result = self
+            .download(&download_directory, revision)
+            .and_then(|revision| {
+                let root = package_root(&download_directory)?;
+                let checksum = checksum(&root)?;
+                if let Some(expected_checksum) = expected_checksum
+                    && checksum != expected_checksum
+                {
+                    return Err(format!(
+                        "The checksum doesn't match the one in the lockfile. Expected {expected_checksum}, but the download has {checksum}.",
+                    ));
+                }
+
+                let destination = packages_path.join(name);
+                remove_directory_if_exists(&destination)?;
+                fs::rename(&root, &destination).map_err(|error| error.to_string())?;
+                Ok(Fetched { revision, checksum })
+            });
+        remove_directory_if_exists(&download_directory)?;
+        result
+    }
+    /// Returns the revision of Git sources.
+    fn download(&self, directory: &Path, revision: Option<&str>) -> Result<Option<String>, String> {
+        match self {
+            Self::Git(url) => {
+                let directory = directory.to_string_lossy();
+                // The URL and revision may come from a lockfile we didn't
+                // write, so they must never be interpreted as options.
+                validate_git_url(url)?;
+                if let Some(revision) = revision {
+                    validate_revision(revision)?;
+                    git(&["clone", "--quiet", "--", url, &directory])?;
+                    git(&["-C", &directory, "checkout", "--quiet", revision, "--"])?;
+                } else {
+                    git(&["clone", "--quiet", "--depth", "1", "--", url, &directory])?;
+                }
+                let revision = git(&["-C", &directory, "rev-parse", "HEAD"])?;
+                Ok(Some(revision.trim().to_string()))
+            }
+            Self::Http(url) => {
+                let response = ureq::get(url).call().map_err(|error| error.to_string())?;
+                tar::Archive::new(GzDecoder::new(response.into_reader()))
+                    .unpack(directory)
+                    .map_err(|error| format!("Couldn't unpack the archive: {error}"))?;
+                Ok(None)
+            }
+        }
+    }
+}
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Git(url) => write!(f, "git+{url}"),
+            Self::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Other protocols like `ext::` or local paths let Git run arbitrary commands
+/// or read arbitrary files.
+fn validate_git_url(url: &str) -> Result<(), String> {
+    if url.starts_with("https://") || url.starts_with("ssh://") {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{url}` is not a valid Git URL. Only `https://` and `ssh://` URLs are supported.",
+        ))
+    }
+}
+/// Revisions in the lockfile are full commit hashes.
+fn validate_revision(revision: &str) -> Result<(), String> {
+    if !revision.is_empty() && revision.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(format!("`{revision}` is not a valid Git commit."))
+    }
+}
+
+fn git(arguments: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(arguments)
+        .output()
+        .map_err(|error| format!("Couldn't run Git: {error}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`git {}` failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Archives often wrap the package in a single directory.
+fn package_root(directory: &Path) -> Result<PathBuf, String> {
+    let mut root = directory.to_path_buf();
+    loop {
+        if root.join("_package.candy").is_file() {
+            return Ok(root);
+        }
+        let children = fs::read_dir(&root)
+            .map_err(|error| error.to_string())?
+            .map(|child| child.map(|it| it.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?;
+        match children.as_slice() {
+            [child] if child.is_dir() => root = child.clone(),
+            _ => return Err("The download doesn't contain a `_package.candy`.".to_string()),
+        }
+    }
+}
+
+/// A SHA-256 hash of the paths and contents of all files in the package,
+/// ignoring Git's metadata.
+pub fn checksum(directory: &Path) -> Result<String, String> {
+    let files = WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_ok(|entry| entry.file_type().is_file())
+        .map_ok(DirEntry::into_path)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| error.to_string())?;
+
+    let mut hasher = Sha256::new();
+    for file in files.iter().sorted() {
+        let relative_path = file.strip_prefix(directory).unwrap();
+        let content = fs::read(file).map_err(|error| error.to_string())?;
+        hasher.update(
+            relative_path
+                .to_string_lossy()
+                .replace('\\', "/")
+                .as_bytes(),
+        );
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .join("");
+    Ok(format!("sha256:{hash}"))
+}
+
+fn remove_directory_if_exists(directory: &Path) -> Result<(), String> {
+    if directory.exists() {
+        fs::remove_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_revision, Registry, Source};
+
+    #[test]
+    fn rejects_git_sources_that_are_options() {
+        assert!(Source::parse("git+--upload-pack=touch pwned").is_err());
+        assert!(Source::parse("git+-u").is_err());
+        assert!(Registry::parse("git+--upload-pack=touch pwned").is_err());
+    }
+    #[test]
+    fn rejects_git_sources_with_unsupported_protocols() {
+        assert!(Source::parse("git+ext::sh -c touch% pwned").is_err());
+        assert!(Source::parse("git+file:///etc").is_err());
+        assert!(Source::parse("git+/etc").is_err());
+    }
+    #[test]
+    fn accepts_https_and_ssh_git_sources() {
+        assert!(Source::parse("git+https://github.com/candy-lang/Foo").is_ok());
+        assert!(Source::parse("git+ssh://git@github.com/candy-lang/Foo").is_ok());
+        assert!(Registry::parse(Registry::DEFAULT).is_ok());
+    }
+
+    #[test]
+    fn rejects_revisions_that_are_options() {
+        assert!(validate_revision("--upload-pack=touch pwned").is_err());
+        assert!(validate_revision("-q").is_err());
+        assert!(validate_revision("").is_err());
+        assert!(validate_revision("0123456789abcdef0123456789abcdef01234567").is_ok());
+    }
+}
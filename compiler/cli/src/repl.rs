@@ -0,0 +1,438 @@
+//! The REPL evaluates each line in its own virtual module.
+//!
+//! For example, entering `foo = 21` and then `foo | ✨.intAdd foo` results in
+//! these modules:
+//!
+//! ```candy
+//! # replSnippet0
+//! foo = 21
+//! replExports := [Foo: foo]
+//!
+//! # replSnippet1
+//! [ReplExports: replSnippet0] = use ".replSnippet0"
+//! [Foo: foo] = replSnippet0
+//! replResult := foo | ✨.intAdd foo
+//! replExports := []
+//! ```
+//!
+//! Because earlier snippets don't change anymore, salsa reuses their optimized
+//! MIR and only the new snippet has to be compiled. The VM can't load modules
+//! at runtime, so module folding inlines the earlier snippets and running a
+//! snippet evaluates their top-level code again. That's fine because top-level
+//! code can't have side effects.
+
+use crate::{
+    check::human::DiagnosticRenderer, database::Database, run::report_panic, utils::packages_path,
+    ProgramResult,
+};
+use candy_frontend::{
+    ast::AstKind,
+    ast_to_hir::AstToHir,
+    cst_to_ast::CstToAst,
+    exports::top_level_definitions,
+    format::{MaxLength, Precedence},
+    hir,
+    hir_to_mir::ExecutionTarget,
+    module::{
+        Module, ModuleKind, MutableModuleProviderOwner, Package, PackagesPath, VirtualModuleLayer,
+    },
+    tracing::CallTracingMode,
+    utils::AdjustCasingOfFirstLetter,
+    OptimizationConfig, OptimizationLevel, TracingConfig, TracingMode,
+};
+use candy_vm::{
+    byte_code::ByteCode,
+    heap::{Data, Heap, HirId, InlineObject, Struct, Tag, Text, ToDebugText},
+    launcher::Launcher,
+    lir_to_byte_code::compile_byte_code,
+    tracer::{stack_trace::StackTracer, Tracer},
+    VmFinished,
+};
+use clap::Parser;
+use itertools::Itertools;
+use std::{
+    collections::BTreeMap,
+    env::current_dir,
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+/// Evaluate Candy code interactively.
+///
+/// Each line is either an expression, whose value gets printed, or definitions
+/// like `foo = 21` that later lines can use. The REPL runs in the package of
+/// your current working directory, so relative `use`s refer to its modules.
+///
+/// Lines starting with a colon are commands:
+///
+/// - `:type <expression>` prints the type of the expression's value.
+///
+/// - `:trace <expression>` also prints the calls made while evaluating the
+///   expression.
+///
+/// - `:reset` forgets all definitions.
+///
+/// - `:quit` ends the session, just like pressing Ctrl+D.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// How much to optimize the code: `-O0` compiles fastest, `-O2` makes the
+    /// code run fastest.
+    #[arg(short = 'O', value_enum, default_value_t = OptimizationLevel::O0)]
+    optimization_level: OptimizationLevel,
+
+    /// Run calls of `debugAssert` and `debugLog`.
+    #[arg(long)]
+    debug_assertions: bool,
+}
+
+#[allow(clippy::unnecessary_wraps)] // Like the other commands.
+pub fn repl(options: &Options) -> ProgramResult {
+    let packages_path = packages_path();
+    let current_dir = current_dir().unwrap();
+    let package = packages_path
+        .find_surrounding_package(&current_dir)
+        .unwrap_or(Package::User(current_dir));
+    let mut repl = Repl {
+        db: Database::new_with_file_system_module_provider(packages_path.clone()),
+        packages_path,
+        package,
+        optimization: OptimizationConfig::for_level(options.optimization_level),
+        debug_assertions: options.debug_assertions,
+        heap: Heap::default(),
+        byte_codes: vec![],
+        snippet_count: 0,
+        definitions: BTreeMap::new(),
+    };
+
+    println!("Welcome to the 🍭 Candy REPL! Enter `:quit` or press Ctrl+D to exit.");
+    // Evaluated code may read from stdin as well, so we don't keep it locked.
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        line.clear();
+        if !matches!(io::stdin().read_line(&mut line), Ok(length) if length > 0) {
+            break;
+        }
+        let line = line.trim();
+
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => {}
+            (":quit", _) => break,
+            (":reset", _) => repl.reset(),
+            (":type", expression) => repl.evaluate(expression, Mode::Type),
+            (":trace", expression) => repl.evaluate(expression, Mode::Trace),
+            (command, _) if command.starts_with(':') => {
+                println!("Unknown command `{command}`. Available commands are `:type`, `:trace`, `:reset`, and `:quit`.");
+            }
+            _ => repl.evaluate(line, Mode::Value),
+        }
+    }
+    repl.reset();
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Mode {
+    Value,
+    Type,
+    Trace,
+}
+
+struct Repl {
+    db: Database,
+    packages_path: PackagesPath,
+    package: Package,
+    optimization: OptimizationConfig,
+    debug_assertions: bool,
+    heap: Heap,
+    /// The byte code of all evaluated snippets. Objects on the heap can still
+    /// reference their constants.
+    byte_codes: Vec<ByteCode>,
+    snippet_count: usize,
+    /// For each name defined so far, the snippet that defined it last.
+    definitions: BTreeMap<String, usize>,
+}
+impl Repl {
+    fn module(&self, name: &str) -> Module {
+        Module::new(
+            self.package.clone(),
+            vec![name.to_string()],
+            ModuleKind::Code,
+        )
+    }
+    fn snippet_module(&self, index: usize) -> Module {
+        self.module(&format!("replSnippet{index}"))
+    }
+
+    fn evaluate(&mut self, line: &str, mode: Mode) {
+        let Some((is_expression, defined_names)) = self.analyze(line) else {
+            return;
+        };
+        if mode != Mode::Value && !is_expression {
+            println!("This command expects an expression.");
+            return;
+        }
+
+        let index = self.snippet_count;
+        let module = self.snippet_module(index);
+        let source = self.snippet_source(line, mode, is_expression, &defined_names);
+        self.db.set_virtual_module(
+            VirtualModuleLayer::Repl,
+            &module,
+            source.clone().into_bytes(),
+        );
+
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls: if mode == Mode::Trace {
+                CallTracingMode::All
+            } else {
+                CallTracingMode::OnlyForPanicTraces
+            },
+            evaluated_expressions: TracingMode::Off,
+            debug_assertions: self.debug_assertions,
+            strip: false,
+        };
+        let (byte_code, errors) = compile_byte_code(
+            &self.db,
+            ExecutionTarget::Module(module.clone()),
+            tracing,
+            self.optimization,
+        );
+        if !errors.is_empty() {
+            {
+                let mut renderer = DiagnosticRenderer::new(&self.db);
+                for error in errors.iter() {
+                    renderer.render_error(error);
+                }
+            }
+            self.db
+                .remove_virtual_module(VirtualModuleLayer::Repl, &module);
+            return;
+        }
+
+        let tracer = (
+            StackTracer::default(),
+            (mode == Mode::Trace).then(|| CallRecorder::new(module.clone())),
+        );
+        let VmFinished {
+            result,
+            tracer: (stack_tracer, call_recorder),
+            ..
+        } = Launcher::new(&byte_code, tracer)
+            .module(&mut self.heap)
+            .run_forever_without_handles(&mut self.heap);
+        self.byte_codes.push(byte_code);
+
+        if let Some(call_recorder) = call_recorder {
+            self.print_calls(&call_recorder, &source);
+        }
+        let exports = match result {
+            Ok(exports) => Struct::try_from(exports).unwrap(),
+            Err(panic) => {
+                report_panic(&self.db, &self.packages_path, &panic, &stack_tracer);
+                self.db
+                    .remove_virtual_module(VirtualModuleLayer::Repl, &module);
+                return;
+            }
+        };
+
+        if is_expression {
+            let key = Text::create(&mut self.heap, true, "ReplResult");
+            let result = exports.get(Tag::create(key)).unwrap();
+            println!(
+                "{}",
+                result.to_debug_text(Precedence::Low, MaxLength::Unlimited),
+            );
+        }
+        InlineObject::from(exports).drop(&mut self.heap);
+
+        self.snippet_count += 1;
+        for name in defined_names {
+            self.definitions.insert(name, index);
+        }
+    }
+
+    /// Returns whether the line is a single expression and the names it
+    /// defines.
+    fn analyze(&mut self, line: &str) -> Option<(bool, Vec<String>)> {
+        let module = self.module("replInput");
+        self.db
+            .set_virtual_module(VirtualModuleLayer::Repl, &module, line.as_bytes().to_vec());
+        let result = self.db.ast(module.clone()).ok().map(|(asts, _)| {
+            let is_expression = matches!(
+                asts.as_slice(),
+                [ast] if !matches!(ast.kind, AstKind::Assignment(_)),
+            );
+            let defined_names = top_level_definitions(&asts)
+                .into_iter()
+                .map(|definition| definition.name)
+                .filter(|name| !name.starts_with("repl"))
+                .unique()
+                .collect();
+            (is_expression, defined_names)
+        });
+        self.db
+            .remove_virtual_module(VirtualModuleLayer::Repl, &module);
+        result
+    }
+
+    fn snippet_source(
+        &self,
+        line: &str,
+        mode: Mode,
+        is_expression: bool,
+        defined_names: &[String],
+    ) -> String {
+        let mut source = String::new();
+        let names_by_snippet = self
+            .definitions
+            .iter()
+            .map(|(name, snippet)| (*snippet, name))
+            .into_group_map();
+        for (snippet, names) in names_by_snippet.into_iter().sorted() {
+            writeln!(
+                source,
+                "[ReplExports: replSnippet{snippet}] = use \".replSnippet{snippet}\"",
+            )
+            .unwrap();
+            writeln!(
+                source,
+                "[{}] = replSnippet{snippet}",
+                names
+                    .iter()
+                    .map(|name| format!("{}: {name}", name.uppercase_first_letter()))
+                    .join(", "),
+            )
+            .unwrap();
+        }
+
+        let line = match (mode, is_expression) {
+            (Mode::Type, _) => format!("replResult := ✨.typeOf ({line})"),
+            (_, true) => format!("replResult := {line}"),
+            (_, false) => line.to_string(),
+        };
+        writeln!(source, "{line}").unwrap();
+
+        writeln!(
+            source,
+            "replExports := [{}]",
+            defined_names
+                .iter()
+                .map(|name| format!("{}: {name}", name.uppercase_first_letter()))
+                .join(", "),
+        )
+        .unwrap();
+        source
+    }
+
+    fn print_calls(&self, call_recorder: &CallRecorder, source: &str) {
+        for call in &call_recorder.calls {
+            let Some(span) = self.db.hir_id_to_span(&call.call_site) else {
+                continue;
+            };
+            let mut line = format!(
+                "{}{}",
+                "  ".repeat(call.depth),
+                &source[*span.start..*span.end]
+            );
+            if !call.arguments.is_empty() {
+                write!(line, " with {}", call.arguments.iter().join(", ")).unwrap();
+            }
+            if let Some(return_value) = &call.return_value {
+                write!(line, " → {return_value}").unwrap();
+            }
+            println!("{line}");
+        }
+    }
+
+    fn reset(&mut self) {
+        self.db.clear_virtual_modules(VirtualModuleLayer::Repl);
+        // The heap can reference constants of the byte code, so we drop it
+        // first.
+        self.heap = Heap::default();
+        self.byte_codes.clear();
+        self.snippet_count = 0;
+        self.definitions.clear();
+    }
+}
+
+/// Records the calls whose call site is in the given module.
+struct CallRecorder {
+    module: Module,
+    /// For each call on the VM's call stack, the index of the recorded call if
+    /// it's recorded.
+    stack: Vec<Option<usize>>,
+    calls: Vec<RecordedCall>,
+}
+struct RecordedCall {
+    depth: usize,
+    call_site: hir::Id,
+    arguments: Vec<String>,
+    return_value: Option<String>,
+}
+impl CallRecorder {
+    const fn new(module: Module) -> Self {
+        Self {
+            module,
+            stack: vec![],
+            calls: vec![],
+        }
+    }
+
+    fn record(&mut self, call_site: HirId, arguments: &[InlineObject]) -> Option<usize> {
+        let call_site = call_site.get();
+        if call_site.module != self.module {
+            return None;
+        }
+
+        self.calls.push(RecordedCall {
+            depth: self.stack.iter().flatten().count(),
+            call_site: call_site.clone(),
+            arguments: arguments
+                .iter()
+                // HIR IDs are only passed to `needs`.
+                .filter(|argument| !matches!(Data::from(**argument), Data::HirId(_)))
+                .map(|argument| argument.to_debug_text(Precedence::High, MaxLength::Limited(40)))
+                .collect(),
+            return_value: None,
+        });
+        Some(self.calls.len() - 1)
+    }
+}
+impl Tracer for CallRecorder {
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        _callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        let index = self.record(call_site, &arguments);
+        self.stack.push(index);
+    }
+    fn call_ended(&mut self, _heap: &mut Heap, return_value: Option<InlineObject>) {
+        if let Some(Some(index)) = self.stack.pop()
+            && let Some(return_value) = return_value
+        {
+            self.calls[index].return_value =
+                Some(return_value.to_debug_text(Precedence::Low, MaxLength::Limited(40)));
+        }
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        _callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        // The tail call replaces the current call and, therefore, also
+        // receives its return value.
+        let current = self.stack.pop().flatten();
+        let index = self.record(call_site, &arguments).or(current);
+        self.stack.push(index);
+    }
+}
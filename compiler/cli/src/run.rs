@@ -1,27 +1,47 @@
 use crate::{
     database::Database,
-    utils::{module_for_path, packages_path},
+    utils::{module_for_path, packages_path, OptimizationOptions},
     Exit, ProgramResult,
 };
 use candy_frontend::{
-    hir_to_mir::ExecutionTarget, tracing::CallTracingMode, TracingConfig, TracingMode,
+    hir_to_mir::ExecutionTarget,
+    module::{Module, PackagesPath},
+    tracing::CallTracingMode,
+    TracingConfig, TracingMode,
 };
 use candy_vm::{
-    environment::DefaultEnvironment, heap::Heap, lir_to_byte_code::compile_byte_code,
-    tracer::stack_trace::StackTracer, Vm, VmFinished,
+    environment::{DefaultEnvironment, EmptyEnvironment, FileSystemAccess},
+    heap::Heap,
+    json::{from_json, to_json},
+    launcher::{LaunchError, LaunchOptions, Launcher},
+    lir_to_byte_code::compile_byte_code,
+    tracer::{chrome::ChromeTracer, stack_trace::StackTracer},
+    Panic, VmFinished,
 };
-use clap::{Parser, ValueHint};
+use clap::{Parser, ValueEnum, ValueHint};
 use std::{
+    fs,
+    num::NonZeroUsize,
     path::PathBuf,
     time::{Duration, Instant},
 };
-use tracing::{debug, error};
+use tokio::runtime::Runtime;
+use tracing::{debug, error, info};
+
+mod hot_reload;
 
 /// Run a Candy program.
 ///
 /// This command runs the given file, or, if no file is provided, the package of
 /// your current working directory. The module should export a `main` function.
 /// This function is then called with an environment.
+///
+/// Alternatively, you can call any exported function by passing its name via
+/// `--entry` and its arguments as a JSON array via `--args`. The function's
+/// result is then printed as JSON. For example:
+///
+/// `candy run file.candy --entry foo --args '[1, "two", {"Key": true}]'`
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug)]
 pub struct Options {
     /// The file or package to run. If none is provided, the package of your
@@ -29,25 +49,157 @@ pub struct Options {
     #[arg(value_hint = ValueHint::FilePath)]
     path: Option<PathBuf>,
 
+    #[command(flatten)]
+    optimization: OptimizationOptions,
+
+    /// Run calls of `debugAssert` and `debugLog`. Without this flag, the
+    /// optimizer removes them entirely.
+    #[arg(long)]
+    debug_assertions: bool,
+
+    /// Remove information about the source code that isn't needed to run the
+    /// program, such as HIR IDs, function names, and stack traces. Panics then
+    /// don't say which code is responsible.
+    #[arg(long, conflicts_with = "trace_out")]
+    strip: bool,
+
+    /// The name of an exported function to call instead of `main`.
+    #[arg(long)]
+    entry: Option<String>,
+
+    /// The arguments for the `--entry` function as a JSON array.
+    #[arg(long, requires = "entry")]
+    args: Option<String>,
+
+    /// The maximum number of bytes the program may allocate on its heap. If
+    /// the program allocates more, it panics. Like stack overflows, this panic
+    /// can't be caught using `try`.
+    #[arg(long, visible_alias = "max-memory")]
+    max_heap: Option<usize>,
+
+    /// The maximum number of nested calls. If the program's call stack gets
+    /// deeper, e.g., because of an infinite recursion, it panics. Tail calls
+    /// don't count.
+    ///
+    /// Unlimited by default because Candy code uses recursion instead of
+    /// loops, so any default would reject some valid programs. The call stack
+    /// doesn't live on the native stack, so deep recursion only costs memory.
+    #[arg(long)]
+    max_stack_depth: Option<usize>,
+
+    /// Deny the program access to the file system except for the paths given
+    /// via `--allow-read` and `--allow-write`. Running processes is denied
+    /// entirely.
+    #[arg(long, conflicts_with = "entry")]
+    sandbox: bool,
+
+    /// With `--sandbox`, allow reading this file or directory and everything
+    /// below it. Can be passed multiple times.
+    #[arg(long, value_hint = ValueHint::AnyPath, requires = "sandbox")]
+    allow_read: Vec<PathBuf>,
+
+    /// With `--sandbox`, allow reading and writing this file or directory and
+    /// everything below it. Can be passed multiple times.
+    #[arg(long, value_hint = ValueHint::AnyPath, requires = "sandbox")]
+    allow_write: Vec<PathBuf>,
+
+    /// The format of the trace written to `--trace-out`.
+    #[arg(long, value_enum, default_value_t = TraceFormat::Chrome, requires = "trace_out")]
+    trace_format: TraceFormat,
+
+    /// Record when each call starts and ends and write the result to this
+    /// file. This makes the program considerably slower.
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "entry")]
+    trace_out: Option<PathBuf>,
+
+    /// Sample the call stack while running and print the functions in which
+    /// the program spent the most time.
+    #[arg(long, conflicts_with = "entry")]
+    profile: bool,
+
+    /// How many instructions to run between two samples of `--profile`.
+    #[arg(long, default_value_t = NonZeroUsize::new(1000).unwrap(), requires = "profile")]
+    profile_interval: NonZeroUsize,
+
+    /// Follow a sample of the allocated objects and write a CSV histogram of
+    /// how many instructions they lived per object kind to this file.
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "entry")]
+    object_lifetimes_out: Option<PathBuf>,
+
+    /// Follow every n-th allocated object for `--object-lifetimes-out`.
+    #[arg(
+        long,
+        default_value_t = NonZeroUsize::new(100).unwrap(),
+        requires = "object_lifetimes_out"
+    )]
+    object_lifetimes_interval: NonZeroUsize,
+
+    /// Update the program whenever a Candy file of its package changes. If
+    /// only constants changed, the program continues running with the new
+    /// values. Otherwise, it restarts and loses its state. If the changed code
+    /// doesn't compile, the old program keeps running.
+    #[arg(
+        long,
+        conflicts_with_all = ["entry", "trace_out", "profile", "object_lifetimes_out"]
+    )]
+    hot_reload: bool,
+
+    /// Regularly check the heap and the VM's stacks for consistency and abort
+    /// if they're corrupted. This makes the program much slower and is only
+    /// meant for finding bugs in the VM.
+    #[arg(long)]
+    paranoid: bool,
+
+    /// How many instructions to run between two checks of `--paranoid`.
+    #[arg(long, default_value_t = NonZeroUsize::new(100).unwrap(), requires = "paranoid")]
+    paranoid_interval: NonZeroUsize,
+
     #[arg(last(true))]
     arguments: Vec<String>,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TraceFormat {
+    /// Chrome's trace event format, e.g., for <https://ui.perfetto.dev>
+    Chrome,
+}
+
 pub fn run(options: Options) -> ProgramResult {
     let packages_path = packages_path();
     let db = Database::new_with_file_system_module_provider(packages_path.clone());
-    let module = module_for_path(options.path)?;
+    let module = module_for_path(options.path.clone())?;
 
     let tracing = TracingConfig {
         register_fuzzables: TracingMode::Off,
-        calls: CallTracingMode::OnlyForPanicTraces,
+        calls: if options.trace_out.is_some() {
+            CallTracingMode::All
+        } else if options.strip {
+            CallTracingMode::Off
+        } else {
+            CallTracingMode::OnlyForPanicTraces
+        },
         evaluated_expressions: TracingMode::Off,
+        debug_assertions: options.debug_assertions,
+        strip: options.strip,
     };
 
+    if let Some(entry) = &options.entry {
+        return run_entry(&db, &packages_path, module, tracing, entry, &options);
+    }
+    if options.hot_reload {
+        return hot_reload::run_with_hot_reload(db, &packages_path, &module, tracing, &options);
+    }
+
     debug!("Running {module}.");
 
     let compilation_start = Instant::now();
-    let byte_code = compile_byte_code(&db, ExecutionTarget::MainFunction(module), tracing).0;
+    let byte_code = compile_byte_code(
+        &db,
+        ExecutionTarget::MainFunction(module),
+        tracing,
+        options.optimization.to_config(),
+    )
+    .0;
 
     let compilation_end = Instant::now();
     debug!(
@@ -57,41 +209,175 @@ pub fn run(options: Options) -> ProgramResult {
 
     debug!("Running program.");
     let mut heap = Heap::default();
-    let (environment_object, mut environment) =
-        DefaultEnvironment::new(&mut heap, &options.arguments);
-    let vm = Vm::for_main_function(
-        &byte_code,
-        &mut heap,
-        environment_object,
+    heap.set_memory_limit(options.max_heap);
+    if options.object_lifetimes_out.is_some() {
+        heap.enable_lifetime_sampling(options.object_lifetimes_interval);
+    }
+    let (environment_object, environment) = DefaultEnvironment::new(&mut heap, &options.arguments);
+    // Network operations run on their own runtime so that the program can't
+    // interfere with the one driving the CLI.
+    let network_reactor = Runtime::new().expect("Couldn't start the network reactor.");
+    let mut environment = environment
+        .with_file_system_access(file_system_access(&options))
+        .with_network_reactor(network_reactor.handle().clone());
+    let tracer = (
         StackTracer::default(),
+        options
+            .trace_out
+            .as_ref()
+            .map(|_| match options.trace_format {
+                TraceFormat::Chrome => ChromeTracer::default(),
+            }),
     );
-    let VmFinished { result, tracer, .. } =
-        vm.run_forever_with_environment(&mut heap, &mut environment);
+    let vm = Launcher::new(&byte_code, tracer)
+        .with_options(launch_options(&options))
+        .main_function(&mut heap, environment_object);
+    let VmFinished {
+        result,
+        tracer: (stack_tracer, chrome_tracer),
+        profile,
+        ..
+    } = vm.run_forever_with_environment(&mut heap, &mut environment);
+    // Dropping the environment closes its connections. We're inside the CLI's
+    // runtime, where blocking on the reactor's shutdown isn't allowed.
+    drop(environment);
+    network_reactor.shutdown_background();
     let result = match result {
         Ok(return_value) => {
             debug!("The main function returned: {return_value:?}");
             Ok(())
         }
-        Err(panic) => {
-            error!("The program panicked: {}", panic.reason);
-            error!("{} is responsible.", panic.responsible);
-            error!(
-                "This is the stack trace:\n{}",
-                tracer.format(&db, &packages_path),
-            );
-            Err(Exit::CodePanicked)
-        }
+        Err(panic) => Err(report_panic(&db, &packages_path, &panic, &stack_tracer)),
     };
     let execution_end = Instant::now();
     debug!(
         "Execution took {}.",
         format_duration(execution_end - compilation_end),
     );
+    debug!("Heap: {}", heap.statistics());
+
+    if let Some(profile) = profile {
+        info!(
+            "Hot functions:\n{}",
+            profile.format_hot_functions(&byte_code, 20),
+        );
+    }
+    if let (Some(trace_out), Some(chrome_tracer)) = (options.trace_out, chrome_tracer) {
+        let trace = chrome_tracer.to_json(&byte_code).to_string();
+        if let Err(error) = fs::write(trace_out, trace) {
+            error!("Couldn't write the trace: {error}");
+            return Err(Exit::FileNotFound);
+        }
+    }
+    if let (Some(object_lifetimes_out), Some(histogram)) =
+        (options.object_lifetimes_out, heap.take_lifetime_histogram())
+    {
+        if let Err(error) = fs::write(object_lifetimes_out, histogram.to_csv()) {
+            error!("Couldn't write the object lifetimes: {error}");
+            return Err(Exit::FileNotFound);
+        }
+    }
 
     drop(byte_code); // Make sure the byte code is kept around until here.
     result
 }
 
+/// Calls the exported function `entry` with arguments decoded from JSON and
+/// prints its JSON-encoded result.
+fn run_entry(
+    db: &Database,
+    packages_path: &PackagesPath,
+    module: Module,
+    tracing: TracingConfig,
+    entry: &str,
+    options: &Options,
+) -> ProgramResult {
+    let arguments: Vec<serde_json::Value> = match &options.args {
+        Some(arguments) => serde_json::from_str(arguments).map_err(|error| {
+            error!("The arguments must be a JSON array: {error}");
+            Exit::InvalidArguments
+        })?,
+        None => vec![],
+    };
+
+    debug!("Running {module}.");
+    let byte_code = compile_byte_code(
+        db,
+        ExecutionTarget::Module(module),
+        tracing,
+        options.optimization.to_config(),
+    )
+    .0;
+    let mut heap = Heap::default();
+    heap.set_memory_limit(options.max_heap);
+    let arguments = arguments
+        .iter()
+        .map(|argument| from_json(&mut heap, argument))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| {
+            error!("Invalid argument: {error}");
+            Exit::InvalidArguments
+        })?;
+
+    debug!("Calling `{entry}`.");
+    let VmFinished { result, tracer, .. } = Launcher::new(&byte_code, StackTracer::default())
+        .with_options(launch_options(options))
+        .run_exported_function(&mut heap, entry, &arguments, &mut EmptyEnvironment)
+        .map_err(|error| {
+            error!("{error}");
+            match error {
+                LaunchError::FunctionNotExported { .. } => Exit::EntryNotFound,
+                LaunchError::WrongNumberOfArguments { .. } => Exit::InvalidArguments,
+            }
+        })?;
+    let return_value = result.map_err(|panic| report_panic(db, packages_path, &panic, &tracer))?;
+
+    let return_value = to_json(return_value).map_err(|error| {
+        error!("The return value can't be converted to JSON: {error}");
+        Exit::ReturnValueNotJson
+    })?;
+    println!("{return_value}");
+    Ok(())
+}
+
+fn launch_options(options: &Options) -> LaunchOptions {
+    LaunchOptions {
+        max_stack_depth: options.max_stack_depth,
+        profile_interval: options.profile.then_some(options.profile_interval),
+        paranoid_interval: options.paranoid.then_some(options.paranoid_interval),
+    }
+}
+
+fn file_system_access(options: &Options) -> FileSystemAccess {
+    if options.sandbox {
+        FileSystemAccess::Sandboxed {
+            readable: options.allow_read.clone(),
+            writable: options.allow_write.clone(),
+        }
+    } else {
+        FileSystemAccess::Unrestricted
+    }
+}
+
+pub fn report_panic(
+    db: &Database,
+    packages_path: &PackagesPath,
+    panic: &Panic,
+    tracer: &StackTracer,
+) -> Exit {
+    error!("The program panicked: {}", panic.reason);
+    error!("{} is responsible.", panic.responsible);
+    error!(
+        "This is the stack trace:\n{}",
+        tracer.format(db, packages_path),
+    );
+    if panic.is_out_of_memory() {
+        Exit::OutOfMemory
+    } else {
+        Exit::CodePanicked
+    }
+}
+
 fn format_duration(duration: Duration) -> String {
     if duration < Duration::from_millis(1) {
         format!("{} µs", duration.as_micros())
@@ -0,0 +1,238 @@
+//! Updating a program whenever its source code changes, enabled by
+//! `candy run --hot-reload`.
+//!
+//! `use` is resolved while compiling, so the whole program is compiled into a
+//! single byte code and a running VM references its instructions from call
+//! stacks and functions on the heap. If a change only affects constants (e.g.,
+//! a number or text), the running VM continues with the new byte code (see
+//! [`Vm::swap_byte_code`](candy_vm::Vm::swap_byte_code)) and keeps its state.
+//! Otherwise, we start the new program from scratch once it compiles without
+//! errors, which loses the old program's state. Until then, the old program
+//! keeps running.
+
+use super::{file_system_access, launch_options, report_panic, Options};
+use crate::{database::Database, Exit, ProgramResult};
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget,
+    module::{Module, ModuleKind, MutableModuleProviderOwner, PackagesPath},
+    OptimizationConfig, TracingConfig,
+};
+use candy_vm::{
+    byte_code::ByteCode,
+    environment::{DefaultEnvironment, StateAfterRunWithoutHandles},
+    heap::Heap,
+    launcher::Launcher,
+    lir_to_byte_code::compile_byte_code,
+    tracer::stack_trace::StackTracer,
+    VmFinished,
+};
+use rustc_hash::FxHashMap;
+use std::{
+    mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::runtime::Runtime;
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+
+/// How often we look for changed files.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// How many instructions the VM runs before we check whether it's time to look
+/// for changed files.
+const INSTRUCTIONS_PER_BATCH: usize = 10_000;
+
+pub fn run_with_hot_reload(
+    mut db: Database,
+    packages_path: &PackagesPath,
+    module: &Module,
+    tracing: TracingConfig,
+    options: &Options,
+) -> ProgramResult {
+    let Some(package_path) = module.package().to_path(packages_path) else {
+        error!("Only packages on the file system can be hot-reloaded.");
+        return Err(Exit::InvalidArguments);
+    };
+    let mut watcher = PackageWatcher::new(package_path);
+    let network_reactor = Runtime::new().expect("Couldn't start the network reactor.");
+
+    let optimization = options.optimization.to_config();
+    let mut byte_code =
+        Rc::new(compile(&db, module, tracing, optimization).unwrap_or_else(|byte_code| *byte_code));
+    loop {
+        info!("Running {module}.");
+        let mut runner = Runner {
+            db: &mut db,
+            packages_path,
+            module,
+            tracing,
+            optimization,
+            options,
+            watcher: &mut watcher,
+        };
+        byte_code = runner
+            .run_until_restart(byte_code, &network_reactor)
+            .unwrap_or_else(|| {
+                info!("Waiting for changes…");
+                Rc::new(runner.wait_for_reload())
+            });
+        info!("Restarting.");
+    }
+}
+
+struct Runner<'a> {
+    db: &'a mut Database,
+    packages_path: &'a PackagesPath,
+    module: &'a Module,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+    options: &'a Options,
+    watcher: &'a mut PackageWatcher,
+}
+impl<'a> Runner<'a> {
+    /// Runs the program until it finishes or a change makes it compile to new
+    /// byte code that the running VM can't continue with, which is then
+    /// returned.
+    fn run_until_restart(
+        &mut self,
+        byte_code: Rc<ByteCode>,
+        network_reactor: &Runtime,
+    ) -> Option<Rc<ByteCode>> {
+        // Values on the heap may still reference the constants of byte code
+        // that was swapped out, so we keep it until the heap is gone.
+        #[allow(clippy::collection_is_never_read)]
+        let mut old_byte_codes = vec![];
+        let mut heap = Heap::default();
+        let (environment_object, environment) =
+            DefaultEnvironment::new(&mut heap, &self.options.arguments);
+        let mut environment = environment
+            .with_file_system_access(file_system_access(self.options))
+            .with_network_reactor(network_reactor.handle().clone());
+        let mut vm = Launcher::new(byte_code, StackTracer::default())
+            .with_options(launch_options(self.options))
+            .main_function(&mut heap, environment_object);
+
+        let mut last_check = Instant::now();
+        loop {
+            match vm.run_n_with_environment(&mut heap, &mut environment, INSTRUCTIONS_PER_BATCH) {
+                StateAfterRunWithoutHandles::Running(new_vm) => vm = new_vm,
+                StateAfterRunWithoutHandles::Finished(VmFinished { result, tracer, .. }) => {
+                    match result {
+                        Ok(return_value) => info!("The main function returned: {return_value:?}"),
+                        Err(panic) => {
+                            report_panic(self.db, self.packages_path, &panic, &tracer);
+                        }
+                    }
+                    return None;
+                }
+            }
+
+            if last_check.elapsed() >= CHECK_INTERVAL {
+                last_check = Instant::now();
+                if let Some(byte_code) = self.recompile_if_changed() {
+                    match vm.swap_byte_code(Rc::new(byte_code)) {
+                        Ok(old_byte_code) => {
+                            info!("Only constants changed, so the program continues running.");
+                            old_byte_codes.push(old_byte_code);
+                        }
+                        Err(byte_code) => return Some(byte_code),
+                    }
+                }
+            }
+        }
+    }
+    fn wait_for_reload(&mut self) -> ByteCode {
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            if let Some(byte_code) = self.recompile_if_changed() {
+                return byte_code;
+            }
+        }
+    }
+
+    /// Returns the new byte code if files changed and the program compiles
+    /// without errors.
+    fn recompile_if_changed(&mut self) -> Option<ByteCode> {
+        let changed_files = self.watcher.changed_files();
+        if changed_files.is_empty() {
+            return None;
+        }
+        for file in changed_files {
+            // Deleted files can't be resolved to modules anymore. Modules that
+            // still use them fail to compile once they're invalidated.
+            if let Ok(module) = Module::from_path(self.packages_path, &file, ModuleKind::Code) {
+                self.db.invalidate_module(&module);
+            }
+        }
+        compile(self.db, self.module, self.tracing, self.optimization).ok()
+    }
+}
+
+/// Returns the byte code as an error if the program contains errors.
+fn compile(
+    db: &Database,
+    module: &Module,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> Result<ByteCode, Box<ByteCode>> {
+    let (byte_code, errors) = compile_byte_code(
+        db,
+        ExecutionTarget::MainFunction(module.clone()),
+        tracing,
+        optimization,
+    );
+    if errors.is_empty() {
+        return Ok(byte_code);
+    }
+    for error in errors.iter() {
+        warn!("{}", error.to_string_with_location(db));
+    }
+    Err(Box::new(byte_code))
+}
+
+/// Detects changes to the Candy files of a package by polling their
+/// modification times.
+struct PackageWatcher {
+    package_path: PathBuf,
+    modification_times: FxHashMap<PathBuf, SystemTime>,
+}
+impl PackageWatcher {
+    fn new(package_path: PathBuf) -> Self {
+        let modification_times = Self::modification_times(&package_path);
+        Self {
+            package_path,
+            modification_times,
+        }
+    }
+
+    /// Returns the files that were created, modified, or deleted since the
+    /// last call.
+    fn changed_files(&mut self) -> Vec<PathBuf> {
+        let old = mem::replace(
+            &mut self.modification_times,
+            Self::modification_times(&self.package_path),
+        );
+        let new = &self.modification_times;
+        let mut changed = new
+            .iter()
+            .filter(|(path, time)| old.get(*path) != Some(time))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        changed.extend(old.into_keys().filter(|path| !new.contains_key(path)));
+        changed
+    }
+
+    fn modification_times(package_path: &Path) -> FxHashMap<PathBuf, SystemTime> {
+        WalkDir::new(package_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|it| it == "candy"))
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((entry.into_path(), modified))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,553 @@
+use self::traces::{CallRecorder, TraceStream, Traces};
+use crate::{database::Database, utils::packages_path, Exit, ProgramResult};
+use candy_formatter::Formatter;
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    cst_to_ast::CstToAst,
+    hir::CollectErrors,
+    hir_to_mir::{ExecutionTarget, HirToMir},
+    lir_optimize::OptimizeLir,
+    mir_optimize::OptimizeMir,
+    mir_to_lir::MirToLir,
+    module::{Module, ModuleKind, MutableModuleProviderOwner, Package, PackagesPath},
+    position::PositionConversionDb,
+    rcst_to_cst::RcstToCst,
+    rich_ir::RichIr,
+    string_to_rcst::StringToRcst,
+    tracing::CallTracingMode,
+    OptimizationConfig, TracingConfig, TracingMode,
+};
+use candy_vm::{
+    byte_code::RichIrForByteCode,
+    environment::{ChannelEnvironment, StateAfterRunWithoutHandles},
+    heap::{Heap, Struct},
+    launcher::Launcher,
+    lir_to_byte_code::compile_byte_code,
+    tracer::stack_trace::StackTracer,
+    VmFinished,
+};
+use clap::Parser;
+use serde_json::{json, Value};
+use std::{
+    io::{self, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{debug, error, info, warn};
+
+mod traces;
+
+/// Start an HTTP server for compiling, running, and formatting Candy code.
+///
+/// This is intended for online playgrounds and for editor integrations that
+/// can't spawn the CLI for every request. All endpoints accept `POST` requests
+/// with a JSON object. Most of them take the `source` of a single module, which
+/// can use the packages next to Core:
+///
+/// - `/compile`: Returns the errors in the module, each with a primary range
+///   and secondary `labels`.
+/// - `/run`: Calls the module's `main` function. The optional `arguments` and
+///   `stdin` are lists of texts, the latter being the lines the program can
+///   read before it receives `Nothing`. Returns what the program wrote to stdout and stderr and whether it
+///   returned or panicked.
+/// - `/format`: Returns the formatted source.
+/// - `/ir`: Returns the intermediate representation named `ir` (e.g., `hir` or
+///   `optimized-mir`), optionally for the `executionTarget` `main-function`.
+/// - `/trace`: Runs the module like `/run`, but records its calls. While the
+///   program runs, the response streams one JSON object per line for each
+///   started and ended call and each line of output. The last one has the type
+///   `finished` and contains the outcome and the ID of the `trace`. Calls are
+///   only recorded in the module itself unless `calls` is `all`.
+///
+/// Recorded traces are kept after the program finished:
+///
+/// - `/traces`: Lists the most recent traces.
+/// - `/traces/calls`: Returns the calls made by the `call` with the given ID in
+///   the `trace`, or the outermost calls if there's no `call`. Each call
+///   contains the number of calls it made, so clients can fetch them lazily.
+///
+/// Programs only get arguments, stdin, stdout, and stderr in their environment
+/// and are stopped once they exceed the memory or instruction limit. There's no
+/// further isolation, so only expose the server publicly behind a sandbox.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: String,
+
+    /// The maximum number of bytes a program may allocate on its heap.
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    max_memory: usize,
+
+    /// The maximum number of instructions a program may execute.
+    #[arg(long, default_value_t = 100_000_000)]
+    max_instructions: usize,
+
+    /// The maximum size of a request body in bytes.
+    #[arg(long, default_value_t = 1024 * 1024)]
+    max_body_size: usize,
+
+    /// The number of recorded traces to keep. Older traces are discarded.
+    #[arg(long, default_value_t = 16)]
+    max_traces: usize,
+
+    /// The origin (e.g., `https://play.candy-lang.org`) from which browsers may
+    /// send requests. By default, cross-origin requests are not allowed.
+    #[arg(long)]
+    allowed_origin: Option<String>,
+}
+
+pub fn serve(options: &Options) -> ProgramResult {
+    let server = Server::http(&options.address).map_err(|error| {
+        error!("Couldn't listen on {}: {error}", options.address);
+        Exit::CouldNotStartServer
+    })?;
+    info!("Listening on http://{}.", options.address);
+
+    let packages_path = packages_path();
+    let mut state = ServerState {
+        db: Database::new_with_file_system_module_provider(packages_path.clone()),
+        packages_path,
+        module: Module::new(
+            Package::Anonymous {
+                url: "playground".to_string(),
+            },
+            vec![],
+            ModuleKind::Code,
+        ),
+        max_memory: options.max_memory,
+        max_instructions: options.max_instructions,
+        max_body_size: options.max_body_size,
+        allowed_origin: options.allowed_origin.clone(),
+        traces: Traces::new(options.max_traces),
+    };
+    for request in server.incoming_requests() {
+        state.handle(request);
+    }
+    Ok(())
+}
+
+/// Requests are handled one after another. All of them share the database, so
+/// Core and other used packages only get compiled once.
+struct ServerState {
+    db: Database,
+    packages_path: PackagesPath,
+    module: Module,
+    max_memory: usize,
+    max_instructions: usize,
+    max_body_size: usize,
+    allowed_origin: Option<String>,
+    traces: Traces,
+}
+impl ServerState {
+    fn handle(&mut self, mut request: Request) {
+        debug!("{} {}", request.method(), request.url());
+        if request.method() == &Method::Options && self.allowed_origin.is_some() {
+            // CORS preflight of browsers
+            let response = Response::empty(204)
+                .with_header(header("Access-Control-Allow-Methods", "POST"))
+                .with_header(header("Access-Control-Allow-Headers", "Content-Type"));
+            self.respond(request, response);
+            return;
+        }
+
+        if request.method() == &Method::Post && request.url() == "/trace" {
+            // The response is streamed while the program runs.
+            match self
+                .read_body(&mut request)
+                .and_then(|body| self.start_trace(&body))
+            {
+                Ok((id, stream)) => self.stream_trace(request, id, stream),
+                Err(error) => self.respond_with_json(request, Err(error)),
+            }
+            return;
+        }
+
+        let result = if request.method() == &Method::Post {
+            self.read_body(&mut request)
+                .and_then(|body| self.handle_endpoint(request.url(), &body))
+        } else {
+            Err((405, "Only POST requests are supported.".to_string()))
+        };
+        self.respond_with_json(request, result);
+    }
+    fn respond_with_json(&self, request: Request, result: Result<Value, (u16, String)>) {
+        let (status, body) = match result {
+            Ok(body) => (200, body),
+            Err((status, message)) => (status, json!({ "error": message })),
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"));
+        self.respond(request, response);
+    }
+    fn read_body(&self, request: &mut Request) -> Result<Value, (u16, String)> {
+        let too_large = || {
+            (
+                413,
+                format!(
+                    "The body must not be larger than {} bytes.",
+                    self.max_body_size,
+                ),
+            )
+        };
+        if request
+            .body_length()
+            .is_some_and(|length| length > self.max_body_size)
+        {
+            return Err(too_large());
+        }
+
+        // The declared length is optional, so we also stop reading after the
+        // limit.
+        let mut body = String::new();
+        request
+            .as_reader()
+            .take(self.max_body_size as u64 + 1)
+            .read_to_string(&mut body)
+            .map_err(|error| (400, format!("Couldn't read the body: {error}")))?;
+        if body.len() > self.max_body_size {
+            return Err(too_large());
+        }
+        serde_json::from_str(&body)
+            .map_err(|error| (400, format!("The body is not valid JSON: {error}")))
+    }
+    fn respond<R: Read>(&self, request: Request, mut response: Response<R>) {
+        if let Some(allowed_origin) = &self.allowed_origin {
+            response.add_header(header("Access-Control-Allow-Origin", allowed_origin));
+            response.add_header(header("Vary", "Origin"));
+        }
+        if let Err(error) = request.respond(response) {
+            warn!("Couldn't send a response: {error}");
+        }
+    }
+
+    fn handle_endpoint(&mut self, url: &str, body: &Value) -> Result<Value, (u16, String)> {
+        match url {
+            "/traces" => return Ok(self.traces.list()),
+            "/traces/calls" => return self.traces.calls(body),
+            _ => {}
+        }
+
+        self.set_source(body)?;
+        match url {
+            "/compile" => Ok(self.compile()),
+            "/run" => {
+                let arguments = texts(body, "arguments")?;
+                let stdin = texts(body, "stdin")?;
+                Ok(self.run(&arguments, stdin))
+            }
+            "/format" => self.format(),
+            "/ir" => {
+                let ir = body["ir"]
+                    .as_str()
+                    .ok_or_else(|| (400, "The `ir` must be a text.".to_string()))?;
+                let execution_target = match body["executionTarget"].as_str() {
+                    None | Some("module") => ExecutionTarget::Module(self.module.clone()),
+                    Some("main-function") => ExecutionTarget::MainFunction(self.module.clone()),
+                    Some(other) => {
+                        return Err((400, format!("Unknown execution target `{other}`.")));
+                    }
+                };
+                self.ir(ir, execution_target)
+                    .map(|ir| json!({ "ir": ir }))
+                    .map_err(|message| (400, message))
+            }
+            _ => Err((404, format!("There's no endpoint at `{url}`."))),
+        }
+    }
+
+    fn set_source(&mut self, body: &Value) -> Result<(), (u16, String)> {
+        let source = body["source"]
+            .as_str()
+            .ok_or_else(|| (400, "The `source` must be a text.".to_string()))?;
+        self.db
+            .did_change_module(&self.module, source.as_bytes().to_vec());
+        Ok(())
+    }
+
+    fn compile(&self) -> Value {
+        let mut errors = vec![];
+        if let Ok((hir, _)) = self.db.hir(self.module.clone()) {
+            hir.collect_errors(&mut errors);
+        }
+        let errors = errors
+            .into_iter()
+            .map(|error| {
+                let range_to_json = |module: &Module, span| {
+                    let range = self.db.range_to_positions(module.clone(), span);
+                    json!({
+                        "start": { "line": range.start.line, "character": range.start.character },
+                        "end": { "line": range.end.line, "character": range.end.character },
+                    })
+                };
+                let labels = error
+                    .labels
+                    .iter()
+                    .map(|label| {
+                        json!({
+                            "module": label.module.to_string(),
+                            "range": range_to_json(&label.module, label.span.clone()),
+                            "message": label.message,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let range = range_to_json(&error.module, error.span.clone());
+                json!({
+                    "module": error.module.to_string(),
+                    "start": range["start"],
+                    "end": range["end"],
+                    "message": error.payload.to_string(),
+                    "labels": labels,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "errors": errors })
+    }
+
+    fn run(&self, arguments: &[String], stdin: Vec<String>) -> Value {
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls: CallTracingMode::OnlyForPanicTraces,
+            evaluated_expressions: TracingMode::Off,
+            debug_assertions: false,
+            strip: false,
+        };
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::MainFunction(self.module.clone()),
+            tracing,
+            OptimizationConfig::default(),
+        )
+        .0;
+
+        let mut heap = Heap::default();
+        heap.set_memory_limit(Some(self.max_memory));
+        let (environment_object, mut environment, stdout_receiver, stderr_receiver) =
+            create_environment(&mut heap, arguments, stdin);
+        let vm = Launcher::new(&byte_code, StackTracer::default())
+            .main_function(&mut heap, environment_object);
+        // A bug in the VM shouldn't take down the whole server.
+        let state = panic::catch_unwind(AssertUnwindSafe(|| {
+            vm.run_n_with_environment(&mut heap, &mut environment, self.max_instructions)
+        }));
+        let outcome = match state {
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished {
+                result: Ok(return_value),
+                ..
+            })) => json!({ "type": "returned", "value": return_value.to_string() }),
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished {
+                result: Err(panic),
+                tracer,
+                ..
+            })) => json!({
+                "type": "panicked",
+                "reason": panic.reason,
+                "responsible": panic.responsible.to_string(),
+                "stackTrace": tracer.format(&self.db, &self.packages_path),
+            }),
+            Ok(StateAfterRunWithoutHandles::Running(_)) => json!({
+                "type": "exceededInstructionLimit",
+                "maxInstructions": self.max_instructions,
+            }),
+            Err(_) => json!({ "type": "crashed" }),
+        };
+
+        json!({
+            "stdout": stdout_receiver.try_iter().collect::<Vec<_>>(),
+            "stderr": stderr_receiver.try_iter().collect::<Vec<_>>(),
+            "outcome": outcome,
+        })
+    }
+
+    fn start_trace(&mut self, body: &Value) -> Result<(usize, TraceStream), (u16, String)> {
+        self.set_source(body)?;
+        let arguments = texts(body, "arguments")?;
+        let stdin = texts(body, "stdin")?;
+        let calls = match body["calls"].as_str() {
+            None | Some("module") => CallTracingMode::OnlyCurrent,
+            Some("all") => CallTracingMode::All,
+            Some(other) => return Err((400, format!("Unknown call tracing mode `{other}`."))),
+        };
+
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls,
+            evaluated_expressions: TracingMode::Off,
+            debug_assertions: false,
+            strip: false,
+        };
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::MainFunction(self.module.clone()),
+            tracing,
+            OptimizationConfig::default(),
+        )
+        .0;
+
+        let mut heap = Heap::default();
+        heap.set_memory_limit(Some(self.max_memory));
+        let (environment_object, environment, stdout_receiver, stderr_receiver) =
+            create_environment(&mut heap, &arguments, stdin);
+        let vm = Launcher::new(Rc::new(byte_code), CallRecorder::default())
+            .main_function(&mut heap, environment_object);
+        let stream = TraceStream::new(
+            vm,
+            heap,
+            environment,
+            stdout_receiver,
+            stderr_receiver,
+            self.max_instructions,
+        );
+        Ok((self.traces.next_id(), stream))
+    }
+    /// Writes the response ourselves so that each step's events reach the
+    /// client immediately. If the client disconnects, the program is stopped
+    /// and its trace is discarded.
+    fn stream_trace(&mut self, request: Request, id: usize, mut stream: TraceStream) {
+        let cors_headers =
+            self.allowed_origin
+                .as_ref()
+                .map_or_else(String::new, |allowed_origin| {
+                    format!("Access-Control-Allow-Origin: {allowed_origin}\r\nVary: Origin\r\n")
+                });
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n{cors_headers}\r\n",
+        );
+
+        let mut writer = request.into_writer();
+        if let Err(error) = write_trace_events(&mut writer, &head, id, &mut stream) {
+            warn!("Couldn't stream trace {id}: {error}");
+            return;
+        }
+        self.traces.add(id, stream.into_trace());
+    }
+
+    fn format(&self) -> Result<Value, (u16, String)> {
+        let cst = self
+            .db
+            .cst(self.module.clone())
+            .map_err(|error| (500, format!("Couldn't parse the module: {error:?}")))?;
+        Ok(json!({ "formatted": cst.format_to_string() }))
+    }
+
+    fn ir(&self, ir: &str, execution_target: ExecutionTarget) -> Result<String, String> {
+        let module = &self.module;
+        let tracing = TracingConfig::off();
+        let optimization = OptimizationConfig::default();
+        let rich_ir = match ir {
+            "rcst" => RichIr::for_rcst(module, &self.db.rcst(module.clone())),
+            "cst" => RichIr::for_cst(module, &self.db.cst(module.clone())),
+            "ast" => self
+                .db
+                .ast(module.clone())
+                .ok()
+                .map(|(ast, _)| RichIr::for_ast(module, &ast)),
+            "hir" => self
+                .db
+                .hir(module.clone())
+                .ok()
+                .map(|(hir, _)| RichIr::for_hir(module, &hir)),
+            "mir" => self
+                .db
+                .mir(execution_target, tracing)
+                .ok()
+                .map(|(mir, _)| RichIr::for_mir(module, &mir, tracing)),
+            "optimized-mir" => self
+                .db
+                .optimized_mir(execution_target, tracing, optimization)
+                .ok()
+                .map(|(mir, _)| RichIr::for_optimized_mir(module, &mir, tracing)),
+            "lir" => self
+                .db
+                .lir(execution_target, tracing, optimization)
+                .ok()
+                .map(|(lir, _)| RichIr::for_lir(module, &lir, tracing)),
+            "optimized-lir" => self
+                .db
+                .optimized_lir(execution_target, tracing, optimization)
+                .ok()
+                .map(|(lir, _)| RichIr::for_optimized_lir(module, &lir, tracing)),
+            "vm-byte-code" => {
+                let (byte_code, _) =
+                    compile_byte_code(&self.db, execution_target, tracing, optimization);
+                Some(RichIr::for_byte_code(module, &byte_code, tracing))
+            }
+            _ => return Err(format!("Unknown IR `{ir}`.")),
+        };
+        rich_ir
+            .map(|it| it.text)
+            .ok_or_else(|| "The IR couldn't be generated.".to_string())
+    }
+}
+
+fn write_trace_events(
+    writer: &mut impl Write,
+    head: &str,
+    id: usize,
+    stream: &mut TraceStream,
+) -> io::Result<()> {
+    writer.write_all(head.as_bytes())?;
+    while !stream.is_finished() {
+        for mut event in stream.step() {
+            if event["type"] == "finished" {
+                event["trace"] = id.into();
+            }
+            writeln!(writer, "{event}")?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Returns the environment for the main function together with receivers for
+/// what the program writes to stdout and stderr.
+fn create_environment(
+    heap: &mut Heap,
+    arguments: &[String],
+    stdin: Vec<String>,
+) -> (
+    Struct,
+    ChannelEnvironment,
+    Receiver<String>,
+    Receiver<String>,
+) {
+    let (stdin_sender, stdin_receiver) = mpsc::channel();
+    for line in stdin {
+        stdin_sender.send(line).unwrap();
+    }
+    drop(stdin_sender);
+    let (stdout_sender, stdout_receiver) = mpsc::channel();
+    let (stderr_sender, stderr_receiver) = mpsc::channel();
+    let (environment_object, environment) = ChannelEnvironment::new(
+        heap,
+        arguments,
+        stdin_receiver,
+        stdout_sender,
+        stderr_sender,
+    );
+    (
+        environment_object,
+        environment,
+        stdout_receiver,
+        stderr_receiver,
+    )
+}
+
+fn texts(body: &Value, key: &str) -> Result<Vec<String>, (u16, String)> {
+    match &body[key] {
+        Value::Null => Ok(vec![]),
+        Value::Array(items) => items
+            .iter()
+            .map(|it| it.as_str().map(ToString::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| (400, format!("The `{key}` must be a list of texts."))),
+        _ => Err((400, format!("The `{key}` must be a list of texts."))),
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
@@ -0,0 +1,361 @@
+//! Recording the calls of a program so that frontends can visualize its
+//! execution, used by the `/trace`, `/traces`, and `/traces/calls` endpoints.
+//!
+//! While a program runs, each started and ended call is sent to the client as
+//! soon as it happens. Afterwards, the calls stay available as a tree that
+//! clients can explore lazily, one level of callees at a time, because the
+//! whole tree of a longer-running program is too large to send at once.
+
+use candy_frontend::format::{MaxLength, Precedence};
+use candy_vm::{
+    byte_code::ByteCode,
+    environment::{ChannelEnvironment, StateAfterRunWithoutHandles},
+    heap::{Heap, HirId, InlineObject, ToDebugText},
+    tracer::Tracer,
+    Vm, VmFinished,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::mpsc::Receiver,
+};
+
+/// Between steps, the recorded events are sent to the client.
+const INSTRUCTIONS_PER_STEP: usize = 10_000;
+/// Values can be arbitrarily large, so we only record a preview of them.
+const MAX_VALUE_LENGTH: usize = 100;
+
+/// The most recent traces, each identified by an increasing number.
+pub struct Traces {
+    max_count: usize,
+    next_id: usize,
+    entries: VecDeque<(usize, Trace)>,
+}
+impl Traces {
+    pub const fn new(max_traces: usize) -> Self {
+        Self {
+            max_count: max_traces,
+            next_id: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn next_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+    /// Removes the oldest traces if there are too many.
+    pub fn add(&mut self, id: usize, trace: Trace) {
+        self.entries.push_back((id, trace));
+        while self.entries.len() > self.max_count {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn list(&self) -> Value {
+        let traces = self
+            .entries
+            .iter()
+            .map(|(id, trace)| {
+                json!({
+                    "id": id,
+                    "callCount": trace.calls.len(),
+                    "outcome": trace.outcome,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "traces": traces })
+    }
+    /// Returns the calls made by the `call` in the `trace`, or the outermost
+    /// calls if no `call` is given.
+    pub fn calls(&self, body: &Value) -> Result<Value, (u16, String)> {
+        let id = body["trace"]
+            .as_u64()
+            .ok_or_else(|| (400, "The `trace` must be a number.".to_string()))?;
+        let (_, trace) = self
+            .entries
+            .iter()
+            .find(|(it, _)| *it as u64 == id)
+            .ok_or_else(|| (404, format!("There's no trace {id}.")))?;
+
+        let children = match &body["call"] {
+            Value::Null => &trace.roots,
+            call => {
+                let call = call
+                    .as_u64()
+                    .ok_or_else(|| (400, "The `call` must be a number.".to_string()))?;
+                &usize::try_from(call)
+                    .ok()
+                    .and_then(|call| trace.calls.get(call))
+                    .ok_or_else(|| (404, format!("There's no call {call} in trace {id}.")))?
+                    .children
+            }
+        };
+        let calls = children
+            .iter()
+            .map(|&call| trace.calls[call].to_json(call))
+            .collect::<Vec<_>>();
+        Ok(json!({ "calls": calls }))
+    }
+}
+
+/// The calls of a program and how it ended.
+#[derive(Debug, Default)]
+pub struct Trace {
+    calls: Vec<TracedCall>,
+    roots: Vec<usize>,
+    call_stack: Vec<usize>,
+    outcome: Value,
+}
+#[derive(Debug)]
+struct TracedCall {
+    call_site: String,
+    callee: String,
+    arguments: Vec<String>,
+    /// Tail calls replace their caller, so they are siblings of it.
+    is_tail_call: bool,
+    /// Only set if the call returned.
+    return_value: Option<String>,
+    children: Vec<usize>,
+}
+
+impl Trace {
+    /// Adds the event to the tree and returns it as it is sent to the client.
+    fn record(&mut self, event: CallEvent) -> Value {
+        match event {
+            CallEvent::Started {
+                call_site,
+                callee,
+                arguments,
+                is_tail_call,
+            } => {
+                let id = self.calls.len();
+                let parent = self.call_stack.last().copied();
+                match parent {
+                    Some(parent) => self.calls[parent].children.push(id),
+                    None => self.roots.push(id),
+                }
+                self.call_stack.push(id);
+                let event = json!({
+                    "type": "callStarted",
+                    "id": id,
+                    "parent": parent,
+                    "callSite": call_site,
+                    "callee": callee,
+                    "arguments": arguments,
+                    "isTailCall": is_tail_call,
+                });
+                self.calls.push(TracedCall {
+                    call_site,
+                    callee,
+                    arguments,
+                    is_tail_call,
+                    return_value: None,
+                    children: vec![],
+                });
+                event
+            }
+            CallEvent::Ended { return_value } => {
+                let id = self.call_stack.pop().unwrap();
+                let event = json!({
+                    "type": "callEnded",
+                    "id": id,
+                    "returnValue": return_value,
+                });
+                self.calls[id].return_value = return_value;
+                event
+            }
+        }
+    }
+}
+impl TracedCall {
+    fn to_json(&self, id: usize) -> Value {
+        json!({
+            "id": id,
+            "callSite": self.call_site,
+            "callee": self.callee,
+            "arguments": self.arguments,
+            "isTailCall": self.is_tail_call,
+            "returnValue": self.return_value,
+            "childCount": self.children.len(),
+        })
+    }
+}
+
+/// A running program whose calls are being recorded.
+pub struct TraceStream {
+    /// Only `None` once the program finished.
+    vm: Option<Vm<Rc<ByteCode>, CallRecorder>>,
+    heap: Heap,
+    environment: ChannelEnvironment,
+    stdout: Receiver<String>,
+    stderr: Receiver<String>,
+    remaining_instructions: usize,
+    max_instructions: usize,
+    trace: Trace,
+}
+impl TraceStream {
+    pub fn new(
+        vm: Vm<Rc<ByteCode>, CallRecorder>,
+        heap: Heap,
+        environment: ChannelEnvironment,
+        stdout: Receiver<String>,
+        stderr: Receiver<String>,
+        max_instructions: usize,
+    ) -> Self {
+        Self {
+            vm: Some(vm),
+            heap,
+            environment,
+            stdout,
+            stderr,
+            remaining_instructions: max_instructions,
+            max_instructions,
+            trace: Trace::default(),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_finished(&self) -> bool {
+        self.vm.is_none()
+    }
+    #[must_use]
+    pub fn into_trace(self) -> Trace {
+        self.trace
+    }
+
+    /// Runs the program for a bit and returns what happened in the meantime.
+    ///
+    /// The last event has the type `finished` and contains the program's
+    /// outcome.
+    pub fn step(&mut self) -> Vec<Value> {
+        let vm = self.vm.take().unwrap();
+        let instructions = self.remaining_instructions.min(INSTRUCTIONS_PER_STEP);
+        self.remaining_instructions -= instructions;
+        // A bug in the VM shouldn't take down the whole server.
+        let state = panic::catch_unwind(AssertUnwindSafe(|| {
+            vm.run_n_with_environment(&mut self.heap, &mut self.environment, instructions)
+        }));
+
+        let (call_events, outcome) = match state {
+            Ok(StateAfterRunWithoutHandles::Running(mut vm)) => {
+                let call_events = mem::take(&mut vm.tracer_mut().events);
+                if self.remaining_instructions > 0 {
+                    self.vm = Some(vm);
+                    (call_events, None)
+                } else {
+                    let outcome = json!({
+                        "type": "exceededInstructionLimit",
+                        "maxInstructions": self.max_instructions,
+                    });
+                    (call_events, Some(outcome))
+                }
+            }
+            Ok(StateAfterRunWithoutHandles::Finished(VmFinished { tracer, result, .. })) => {
+                let outcome = match result {
+                    Ok(return_value) => {
+                        json!({ "type": "returned", "value": return_value.to_string() })
+                    }
+                    Err(panic) => json!({
+                        "type": "panicked",
+                        "reason": panic.reason,
+                        "responsible": panic.responsible.to_string(),
+                    }),
+                };
+                (tracer.events, Some(outcome))
+            }
+            Err(_) => (vec![], Some(json!({ "type": "crashed" }))),
+        };
+
+        let mut events = call_events
+            .into_iter()
+            .map(|event| self.trace.record(event))
+            .collect::<Vec<_>>();
+        events.extend(
+            self.stdout
+                .try_iter()
+                .map(|line| json!({ "type": "stdout", "line": line })),
+        );
+        events.extend(
+            self.stderr
+                .try_iter()
+                .map(|line| json!({ "type": "stderr", "line": line })),
+        );
+        if let Some(outcome) = outcome {
+            events.push(json!({ "type": "finished", "outcome": outcome }));
+            self.trace.outcome = outcome;
+        }
+        events
+    }
+}
+
+/// Records calls until they're sent to the client by the [`TraceStream`].
+#[derive(Debug, Default)]
+pub struct CallRecorder {
+    events: Vec<CallEvent>,
+}
+#[derive(Debug)]
+enum CallEvent {
+    Started {
+        call_site: String,
+        callee: String,
+        arguments: Vec<String>,
+        is_tail_call: bool,
+    },
+    Ended {
+        return_value: Option<String>,
+    },
+}
+
+impl CallRecorder {
+    fn started(
+        &mut self,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: &[InlineObject],
+        is_tail_call: bool,
+    ) {
+        self.events.push(CallEvent::Started {
+            call_site: call_site.get().to_string(),
+            callee: preview(callee),
+            arguments: arguments.iter().map(|it| preview(*it)).collect(),
+            is_tail_call,
+        });
+    }
+}
+impl Tracer for CallRecorder {
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.started(call_site, callee, &arguments, false);
+    }
+    fn call_ended(&mut self, _heap: &mut Heap, return_value: Option<InlineObject>) {
+        self.events.push(CallEvent::Ended {
+            return_value: return_value.map(preview),
+        });
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.events.push(CallEvent::Ended { return_value: None });
+        self.started(call_site, callee, &arguments, true);
+    }
+}
+
+fn preview(value: InlineObject) -> String {
+    value.to_debug_text(Precedence::High, MaxLength::Limited(MAX_VALUE_LENGTH))
+}
@@ -1,5 +1,9 @@
 use crate::Exit;
-use candy_frontend::module::{Module, ModuleFromPathError, ModuleKind, PackagesPath};
+use candy_frontend::{
+    module::{Module, ModuleFromPathError, ModuleKind, PackagesPath},
+    OptimizationConfig, OptimizationLevel,
+};
+use clap::Parser;
 use std::{
     env::{current_dir, current_exe},
     path::PathBuf,
@@ -40,3 +44,22 @@ pub fn module_for_path(path: impl Into<Option<PathBuf>>) -> Result<Module, Exit>
         Ok(Module::new(package, vec![], ModuleKind::Code))
     }
 }
+
+#[derive(Parser, Debug)]
+pub struct OptimizationOptions {
+    /// How much to optimize the program: `-O0` compiles fastest, `-O2` makes
+    /// the program run fastest.
+    #[arg(short = 'O', value_enum, default_value_t = OptimizationLevel::O2)]
+    level: OptimizationLevel,
+
+    /// Only apply this many optimizations to each module. Useful for
+    /// bisecting which optimization breaks a program.
+    #[arg(long = "optimization-fuel", env = "CANDY_MIR_OPTIMIZATION_FUEL")]
+    fuel: Option<usize>,
+}
+impl OptimizationOptions {
+    #[must_use]
+    pub const fn to_config(&self) -> OptimizationConfig {
+        OptimizationConfig::for_level(self.level).with_fuel(self.fuel)
+    }
+}
@@ -0,0 +1,58 @@
+use candy_frontend::{
+    ast::AstDbStorage,
+    ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
+    cst::CstDbStorage,
+    cst_to_ast::CstToAstStorage,
+    hir::HirDbStorage,
+    hir_to_mir::HirToMirStorage,
+    lir_optimize::OptimizeLirStorage,
+    mir_optimize::OptimizeMirStorage,
+    mir_to_lir::MirToLirStorage,
+    module::{
+        FileSystemModuleProvider, ModuleDbStorage, ModuleProvider, ModuleProviderOwner,
+        PackagesPath,
+    },
+    persistent_cache::PersistentCacheOwner,
+    position::PositionConversionStorage,
+    rcst_to_cst::RcstToCstStorage,
+    string_to_rcst::StringToRcstStorage,
+};
+
+#[salsa::database(
+    AstDbStorage,
+    AstToHirStorage,
+    CstDbStorage,
+    CstToAstStorage,
+    HirDbStorage,
+    HirToMirStorage,
+    MirToLirStorage,
+    ModuleDbStorage,
+    OptimizeLirStorage,
+    OptimizeMirStorage,
+    PositionConversionStorage,
+    RcstToCstStorage,
+    StringToRcstStorage
+)]
+pub struct Database {
+    storage: salsa::Storage<Self>,
+    module_provider: FileSystemModuleProvider,
+}
+impl salsa::Database for Database {}
+
+impl Database {
+    pub fn new(packages_path: PackagesPath) -> Self {
+        Self {
+            storage: salsa::Storage::default(),
+            module_provider: FileSystemModuleProvider { packages_path },
+        }
+    }
+}
+
+impl ModuleProviderOwner for Database {
+    fn get_module_provider(&self) -> &dyn ModuleProvider {
+        &self.module_provider
+    }
+}
+impl PersistentCacheOwner for Database {}
+impl CancellationTokenOwner for Database {}
@@ -0,0 +1,83 @@
+use crate::value::Value;
+use candy_frontend::utils::AdjustCasingOfFirstLetter;
+use candy_vm::{
+    byte_code::ByteCode,
+    environment::Environment,
+    heap::{Handle, Heap, List, Struct, Text},
+    tracer::Tracer,
+    Vm, VmHandleCall,
+};
+use rustc_hash::FxHashMap;
+use std::borrow::Borrow;
+
+pub type HostFunctionCallback = Box<dyn FnMut(Vec<Value>) -> Value>;
+
+pub struct HostFunction {
+    pub name: String,
+    pub argument_count: usize,
+    pub callback: HostFunctionCallback,
+}
+
+/// Offers the host functions to a program as handles.
+///
+/// Each function is available under its name with an uppercase first letter,
+/// e.g., `environment.greet` for a function registered as `greet`.
+pub struct HostEnvironment<'a> {
+    handles: FxHashMap<Handle, &'a mut HostFunction>,
+}
+impl<'a> HostEnvironment<'a> {
+    pub fn new(
+        heap: &mut Heap,
+        arguments: &[String],
+        host_functions: &'a mut [HostFunction],
+    ) -> (Struct, Self) {
+        let arguments = arguments
+            .iter()
+            .map(|it| Text::create(heap, true, it).into())
+            .collect::<Vec<_>>();
+        let arguments = List::create(heap, true, &arguments);
+
+        let mut fields = vec![(heap.default_symbols().arguments, arguments.into())];
+        let mut handles = FxHashMap::default();
+        for host_function in host_functions {
+            let handle = Handle::new(heap, host_function.argument_count);
+            let name = host_function.name.uppercase_first_letter();
+            let symbol = heap
+                .default_symbols()
+                .get(&name)
+                .unwrap_or_else(|| Text::create(heap, true, &name));
+            fields.push((symbol, **handle));
+            handles.insert(handle, host_function);
+        }
+        let environment_object = Struct::create_with_symbol_keys(heap, true, fields);
+        (environment_object, Self { handles })
+    }
+}
+impl Environment for HostEnvironment<'_> {
+    fn handle<B: Borrow<ByteCode>, T: Tracer>(
+        &mut self,
+        heap: &mut Heap,
+        call: VmHandleCall<B, T>,
+    ) -> Vm<B, T> {
+        let host_function = self.handles.get_mut(&call.handle).unwrap_or_else(|| {
+            panic!(
+                "A handle was called that doesn't exist: {handle:?}",
+                handle = call.handle,
+            )
+        });
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|argument| Value::from_heap(*argument))
+            .collect::<Result<Vec<_>, _>>();
+        let result = match arguments {
+            Ok(arguments) => (host_function.callback)(arguments),
+            Err(error) => Value::error(format!(
+                "Host function `{}` can't receive this argument: {error}",
+                host_function.name,
+            )),
+        };
+        let result = result.to_heap(heap);
+        call.complete(heap, result)
+    }
+}
@@ -0,0 +1,349 @@
+//! Hosting the Candy VM in other Rust programs.
+//!
+//! This crate compiles and runs Candy code without exposing the compiler's
+//! internals. Values are exchanged as [`Value`]s, which convert from and to
+//! common Rust types. Host functions registered on the builder are available
+//! to the program's `main` function in its environment.
+//!
+//! ```no_run
+//! use candy_embed::{CandyRuntime, Tracing, Value};
+//! use std::path::Path;
+//!
+//! let mut runtime = CandyRuntime::builder()
+//!     .package_root("packages")
+//!     .tracing(Tracing::StackTraces)
+//!     .host_function("print", 1, |arguments| {
+//!         println!("{}", arguments[0]);
+//!         Value::nothing()
+//!     })
+//!     .build()?;
+//! let file = Path::new("packages/Examples/helloWorld.candy");
+//! runtime.run_main(file, &[])?;
+//! let sum: i64 = runtime
+//!     .call(file, "add", &[1.into(), 2.into()])?
+//!     .try_into()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#![warn(clippy::nursery, clippy::pedantic, unused_crate_dependencies)]
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions
+)]
+
+pub use self::value::{ConversionError, Value};
+use self::{
+    database::Database,
+    environment::{HostEnvironment, HostFunction, HostFunctionCallback},
+};
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget,
+    module::{Module, ModuleFromPathError, ModuleKind, PackagesPath},
+    tracing::CallTracingMode,
+    OptimizationConfig, OptimizationLevel, TracingConfig, TracingMode,
+};
+use candy_vm::{
+    environment::EmptyEnvironment,
+    heap::{Heap, InlineObject},
+    launcher::{LaunchError, Launcher},
+    lir_to_byte_code::compile_byte_code,
+    tracer::stack_trace::StackTracer,
+    Panic, VmFinished,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+mod database;
+mod environment;
+mod value;
+
+/// Whether to record what's necessary for explaining panics.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Tracing {
+    /// Panics only contain their reason and the responsible code.
+    #[default]
+    Off,
+    /// Panics also contain a stack trace. This makes programs slower.
+    StackTraces,
+}
+
+/// How much the compiler optimizes code before running it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Optimization {
+    /// Compiles fastest.
+    Off,
+    /// Only applies cheap optimizations.
+    Cheap,
+    /// The code runs fastest.
+    #[default]
+    Full,
+}
+
+pub struct CandyRuntimeBuilder {
+    package_root: Option<PathBuf>,
+    tracing: Tracing,
+    optimization: Optimization,
+    debug_assertions: bool,
+    host_functions: Vec<HostFunction>,
+}
+impl CandyRuntimeBuilder {
+    /// The directory containing Core and the other packages. This is
+    /// required.
+    #[must_use]
+    pub fn package_root(mut self, package_root: impl Into<PathBuf>) -> Self {
+        self.package_root = Some(package_root.into());
+        self
+    }
+    #[must_use]
+    pub const fn tracing(mut self, tracing: Tracing) -> Self {
+        self.tracing = tracing;
+        self
+    }
+    #[must_use]
+    pub const fn optimization(mut self, optimization: Optimization) -> Self {
+        self.optimization = optimization;
+        self
+    }
+    /// Run calls of `debugAssert` and `debugLog`. By default, the compiler
+    /// removes them.
+    #[must_use]
+    pub const fn debug_assertions(mut self, debug_assertions: bool) -> Self {
+        self.debug_assertions = debug_assertions;
+        self
+    }
+    /// Makes `callback` available to `main` functions as
+    /// `environment.<Name>`, e.g., `environment.print` for `print`.
+    ///
+    /// Arguments that can't be converted to a [`Value`] (e.g., functions)
+    /// make the call return an `Error` instead.
+    #[must_use]
+    pub fn host_function(
+        mut self,
+        name: impl Into<String>,
+        argument_count: usize,
+        callback: impl FnMut(Vec<Value>) -> Value + 'static,
+    ) -> Self {
+        let callback: HostFunctionCallback = Box::new(callback);
+        self.host_functions.push(HostFunction {
+            name: name.into(),
+            argument_count,
+            callback,
+        });
+        self
+    }
+
+    pub fn build(self) -> Result<CandyRuntime, Error> {
+        let package_root = self.package_root.ok_or(Error::MissingPackageRoot)?;
+        let packages_path =
+            PackagesPath::try_from(package_root.as_path()).map_err(Error::InvalidPackageRoot)?;
+        Ok(CandyRuntime {
+            db: Database::new(packages_path.clone()),
+            packages_path,
+            tracing: self.tracing,
+            optimization: self.optimization,
+            debug_assertions: self.debug_assertions,
+            host_functions: self.host_functions,
+        })
+    }
+}
+
+/// Compiles and runs Candy files.
+///
+/// Compilation results are cached, so running code of the same packages
+/// repeatedly only compiles it once.
+pub struct CandyRuntime {
+    db: Database,
+    packages_path: PackagesPath,
+    tracing: Tracing,
+    optimization: Optimization,
+    debug_assertions: bool,
+    host_functions: Vec<HostFunction>,
+}
+impl CandyRuntime {
+    #[must_use]
+    pub const fn builder() -> CandyRuntimeBuilder {
+        CandyRuntimeBuilder {
+            package_root: None,
+            tracing: Tracing::Off,
+            optimization: Optimization::Full,
+            debug_assertions: false,
+            host_functions: vec![],
+        }
+    }
+
+    /// Calls the `main` function of `file` with an environment containing the
+    /// `arguments` and the host functions.
+    pub fn run_main(&mut self, file: &Path, arguments: &[String]) -> Result<Value, Error> {
+        let module = self.module_for_file(file)?;
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::MainFunction(module),
+            self.tracing_config(),
+            self.optimization_config(),
+        )
+        .0;
+
+        let mut heap = Heap::default();
+        let (environment_object, mut environment) =
+            HostEnvironment::new(&mut heap, arguments, &mut self.host_functions);
+        let finished = Launcher::new(&byte_code, StackTracer::default())
+            .main_function(&mut heap, environment_object)
+            .run_forever_with_environment(&mut heap, &mut environment);
+        self.finish(finished)
+    }
+
+    /// Calls the exported function `name` of `file`.
+    ///
+    /// Unlike `main` functions, this function doesn't receive an environment,
+    /// so it can't use host functions.
+    pub fn call(&self, file: &Path, name: &str, arguments: &[Value]) -> Result<Value, Error> {
+        let module = self.module_for_file(file)?;
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::Module(module),
+            self.tracing_config(),
+            self.optimization_config(),
+        )
+        .0;
+
+        let mut heap = Heap::default();
+        let arguments = arguments
+            .iter()
+            .map(|argument| argument.to_heap(&mut heap))
+            .collect::<Vec<_>>();
+        let finished = Launcher::new(&byte_code, StackTracer::default())
+            .run_exported_function(&mut heap, name, &arguments, &mut EmptyEnvironment)
+            .map_err(|error| match error {
+                LaunchError::FunctionNotExported { name } => Error::FunctionNotExported(name),
+                LaunchError::WrongNumberOfArguments {
+                    name,
+                    expected,
+                    actual,
+                } => Error::WrongNumberOfArguments {
+                    name,
+                    expected,
+                    actual,
+                },
+            })?;
+        self.finish(finished)
+    }
+
+    const fn tracing_config(&self) -> TracingConfig {
+        TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls: match self.tracing {
+                Tracing::Off => CallTracingMode::Off,
+                Tracing::StackTraces => CallTracingMode::OnlyForPanicTraces,
+            },
+            evaluated_expressions: TracingMode::Off,
+            debug_assertions: self.debug_assertions,
+            strip: false,
+        }
+    }
+    const fn optimization_config(&self) -> OptimizationConfig {
+        OptimizationConfig::for_level(match self.optimization {
+            Optimization::Off => OptimizationLevel::O0,
+            Optimization::Cheap => OptimizationLevel::O1,
+            Optimization::Full => OptimizationLevel::O2,
+        })
+    }
+    fn finish(&self, finished: VmFinished<StackTracer>) -> Result<Value, Error> {
+        let return_value: InlineObject = finished
+            .result
+            .map_err(|panic| self.panicked(panic, &finished.tracer))?;
+        Value::from_heap(return_value).map_err(Error::Conversion)
+    }
+    fn panicked(&self, panic: Panic, tracer: &StackTracer) -> Error {
+        Error::Panicked {
+            reason: panic.reason,
+            responsible: panic.responsible.to_string(),
+            stack_trace: match self.tracing {
+                Tracing::Off => None,
+                Tracing::StackTraces => Some(tracer.format(&self.db, &self.packages_path)),
+            },
+        }
+    }
+
+    fn module_for_file(&self, file: &Path) -> Result<Module, Error> {
+        if !file.exists() {
+            return Err(Error::FileNotFound(file.to_path_buf()));
+        }
+        Module::from_path(&self.packages_path, file, ModuleKind::Code).map_err(
+            |error| match error {
+                ModuleFromPathError::NotFound(_) => Error::FileNotFound(file.to_path_buf()),
+                ModuleFromPathError::NotInPackage(_) => Error::NotInPackage(file.to_path_buf()),
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// [`CandyRuntimeBuilder::package_root`] wasn't called.
+    MissingPackageRoot,
+    /// The package root doesn't exist or is not a directory.
+    InvalidPackageRoot(String),
+    FileNotFound(PathBuf),
+    /// The file is not inside a Candy package, i.e., none of its ancestors
+    /// contains a `_package.candy` file.
+    NotInPackage(PathBuf),
+    FunctionNotExported(String),
+    WrongNumberOfArguments {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The program panicked. The stack trace is only available with
+    /// [`Tracing::StackTraces`].
+    Panicked {
+        reason: String,
+        /// The code that is responsible for the panic.
+        responsible: String,
+        stack_trace: Option<String>,
+    },
+    /// The program returned a value that can't be converted to a [`Value`].
+    Conversion(ConversionError),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::MissingPackageRoot => write!(f, "No package root was set."),
+            Self::InvalidPackageRoot(reason) => write!(f, "{reason}"),
+            Self::FileNotFound(file) => write!(f, "`{}` doesn't exist.", file.display()),
+            Self::NotInPackage(file) => {
+                write!(f, "`{}` is not in a Candy package.", file.display())
+            }
+            Self::FunctionNotExported(name) => {
+                write!(f, "The module doesn't export a function called `{name}`.")
+            }
+            Self::WrongNumberOfArguments {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "`{name}` expects {expected} arguments, but {actual} were given.",
+            ),
+            Self::Panicked {
+                reason,
+                responsible,
+                stack_trace,
+            } => {
+                write!(
+                    f,
+                    "The program panicked: {reason}\n{responsible} is responsible."
+                )?;
+                if let Some(stack_trace) = stack_trace {
+                    write!(f, "\nThis is the stack trace:\n{stack_trace}")?;
+                }
+                Ok(())
+            }
+            Self::Conversion(error) => write!(f, "{error}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
@@ -0,0 +1,319 @@
+//! Candy values on the Rust side.
+//!
+//! | Rust                 | Candy                                           |
+//! |----------------------|-------------------------------------------------|
+//! | `()`                 | `Nothing`                                       |
+//! | `bool`               | `True` / `False`                                |
+//! | `i64`                | int                                             |
+//! | `String` / `&str`    | text                                            |
+//! | `Vec<T>`             | list                                            |
+//! | `HashMap<String, T>` | struct                                          |
+//!
+//! Map keys starting with an uppercase letter become tags, all other keys
+//! become texts. Functions, builtins, handles, and HIR IDs stay inside the VM
+//! and can't be converted.
+
+use candy_vm::heap::{Data, Heap, InlineObject, Int, List, Struct, Tag, Text};
+use rustc_hash::FxHashMap;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    hash::BuildHasher,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Text(String),
+    Tag {
+        symbol: String,
+        value: Option<Box<Value>>,
+    },
+    List(Vec<Value>),
+    Struct(Vec<(Value, Value)>),
+}
+impl Value {
+    #[must_use]
+    pub fn nothing() -> Self {
+        Self::tag("Nothing")
+    }
+    #[must_use]
+    pub fn tag(symbol: impl Into<String>) -> Self {
+        Self::Tag {
+            symbol: symbol.into(),
+            value: None,
+        }
+    }
+    #[must_use]
+    pub fn tag_with_value(symbol: impl Into<String>, value: impl Into<Self>) -> Self {
+        Self::Tag {
+            symbol: symbol.into(),
+            value: Some(Box::new(value.into())),
+        }
+    }
+    #[must_use]
+    pub fn ok(value: impl Into<Self>) -> Self {
+        Self::tag_with_value("Ok", value)
+    }
+    #[must_use]
+    pub fn error(value: impl Into<Self>) -> Self {
+        Self::tag_with_value("Error", value)
+    }
+
+    pub(crate) fn from_heap(object: InlineObject) -> Result<Self, ConversionError> {
+        let value = match Data::from(object) {
+            Data::Int(int) => {
+                let int = int.get();
+                Self::Int(i64::try_from(&*int).map_err(|_| {
+                    ConversionError(format!("The int {int} doesn't fit into an `i64`."))
+                })?)
+            }
+            Data::Text(text) => Self::Text(text.get().to_string()),
+            Data::Tag(tag) => Self::Tag {
+                symbol: tag.symbol().get().to_string(),
+                value: tag
+                    .value()
+                    .map(|value| Self::from_heap(value).map(Box::new))
+                    .transpose()?,
+            },
+            Data::List(list) => Self::List(
+                list.items()
+                    .iter()
+                    .map(|item| Self::from_heap(*item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Data::Struct(struct_) => Self::Struct(
+                struct_
+                    .iter()
+                    .map(|(_, key, value)| Ok((Self::from_heap(key)?, Self::from_heap(value)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Data::HirId(_)
+            | Data::Function(_)
+            | Data::Builtin(_)
+            | Data::Handle(_)
+            | Data::Bytes(_)
+            | Data::Cell(_)
+            | Data::Fraction(_)
+            | Data::Map(_)
+            | Data::Set(_)
+            | Data::TextBuilder(_) => {
+                return Err(ConversionError(format!(
+                    "{object:?} can't be converted to a Rust value."
+                )));
+            }
+        };
+        Ok(value)
+    }
+    pub(crate) fn to_heap(&self, heap: &mut Heap) -> InlineObject {
+        match self {
+            Self::Int(int) => Int::create(heap, true, *int).into(),
+            Self::Text(text) => Text::create(heap, true, text).into(),
+            Self::Tag { symbol, value } => {
+                let symbol = heap
+                    .default_symbols()
+                    .get(symbol)
+                    .unwrap_or_else(|| Text::create(heap, true, symbol));
+                let value = value.as_ref().map(|value| value.to_heap(heap));
+                Tag::create_with_value_option(heap, true, symbol, value).into()
+            }
+            Self::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_heap(heap))
+                    .collect::<Vec<_>>();
+                List::create(heap, true, &items).into()
+            }
+            Self::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, value)| (key.to_heap(heap), value.to_heap(heap)))
+                    .collect::<FxHashMap<_, _>>();
+                Struct::create(heap, true, &fields).into()
+            }
+        }
+    }
+}
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Int(int) => write!(f, "{int}"),
+            Self::Text(text) => write!(f, "{text:?}"),
+            Self::Tag { symbol, value } => match value {
+                Some(value) => write!(f, "{symbol} ({value})"),
+                None => write!(f, "{symbol}"),
+            },
+            Self::List(items) => {
+                write!(f, "(")?;
+                for item in items {
+                    write!(f, "{item}, ")?;
+                }
+                if items.is_empty() {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Self::Struct(fields) => {
+                write!(f, "[")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Self::nothing()
+    }
+}
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::tag(if value { "True" } else { "False" })
+    }
+}
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+impl<T: Into<Self>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Self::List(value.into_iter().map(Into::into).collect())
+    }
+}
+impl<T: Into<Self>, S: BuildHasher> From<HashMap<String, T, S>> for Value {
+    fn from(value: HashMap<String, T, S>) -> Self {
+        Self::Struct(
+            value
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = if key.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        Self::tag(key)
+                    } else {
+                        Self::Text(key)
+                    };
+                    (key, value.into())
+                })
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Tag {
+                symbol,
+                value: None,
+            } if symbol == "Nothing" => Ok(()),
+            _ => Err(ConversionError::expected("`Nothing`", &value)),
+        }
+    }
+}
+impl TryFrom<Value> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Tag {
+                symbol,
+                value: None,
+            } if symbol == "True" => Ok(true),
+            Value::Tag {
+                symbol,
+                value: None,
+            } if symbol == "False" => Ok(false),
+            _ => Err(ConversionError::expected("a bool", &value)),
+        }
+    }
+}
+impl TryFrom<Value> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(int) => Ok(int),
+            _ => Err(ConversionError::expected("an int", &value)),
+        }
+    }
+}
+impl TryFrom<Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Text(text) => Ok(text),
+            _ => Err(ConversionError::expected("a text", &value)),
+        }
+    }
+}
+impl<T: TryFrom<Value, Error = ConversionError>> TryFrom<Value> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::try_from).collect(),
+            _ => Err(ConversionError::expected("a list", &value)),
+        }
+    }
+}
+impl<T, S> TryFrom<Value> for HashMap<String, T, S>
+where
+    T: TryFrom<Value, Error = ConversionError>,
+    S: BuildHasher + Default,
+{
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let Value::Struct(fields) = value else {
+            return Err(ConversionError::expected("a struct", &value));
+        };
+        fields
+            .into_iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Value::Text(text) => text,
+                    Value::Tag {
+                        symbol,
+                        value: None,
+                    } => symbol,
+                    _ => return Err(ConversionError::expected("a text or tag key", &key)),
+                };
+                Ok((key, T::try_from(value)?))
+            })
+            .collect()
+    }
+}
+
+/// A value couldn't be converted between Rust and Candy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConversionError(pub String);
+impl ConversionError {
+    fn expected(expected: &str, actual: &Value) -> Self {
+        Self(format!("Expected {expected}, but got `{actual}`."))
+    }
+}
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl std::error::Error for ConversionError {}
@@ -4,7 +4,7 @@ use candy_formatter::Formatter;
 use candy_frontend::{
     ast::{
         Assignment, AssignmentBody, Ast, AstDbStorage, AstKind, Call, Function, List, Match,
-        MatchCase, OrPattern, Struct, StructAccess, Text,
+        MatchCase, NamedArgument, OrPattern, Struct, StructAccess, Text,
     },
     cst::CstDbStorage,
     cst_to_ast::{CstToAst, CstToAstStorage},
@@ -12,6 +12,7 @@ use candy_frontend::{
         GetModuleContentQuery, InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind,
         ModuleProvider, ModuleProviderOwner, Package,
     },
+    persistent_cache::PersistentCacheOwner,
     position::Offset,
     rcst_to_cst::{RcstToCst, RcstToCstStorage},
     string_to_rcst::StringToRcstStorage,
@@ -47,6 +48,7 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl PersistentCacheOwner for Database {}
 
 fuzz_target!(|data: &[u8]| {
     let mut db = Database::default();
@@ -107,6 +109,7 @@ impl NormalizeSpans for Ast {
                 receiver.normalize_spans();
                 arguments.normalize_spans();
             }
+            AstKind::NamedArgument(NamedArgument { name: _, value }) => value.normalize_spans(),
             AstKind::Assignment(Assignment { is_public: _, body }) => match body {
                 AssignmentBody::Function { name: _, function } => function.normalize_spans(),
                 AssignmentBody::Body { pattern, body } => {
@@ -133,6 +136,7 @@ impl NormalizeSpans for Ast {
 }
 impl NormalizeSpans for Function {
     fn normalize_spans(&mut self) {
+        self.parameters.normalize_spans();
         self.body.normalize_spans();
     }
 }
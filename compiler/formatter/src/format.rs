@@ -2,7 +2,7 @@ use crate::{
     existing_parentheses::ExistingParentheses,
     existing_whitespace::{
         ExistingWhitespace, TrailingWhitespace, TrailingWithIndentationConfig,
-        WhitespacePositionInBody,
+        WhitespacePositionInBody, NEWLINE,
     },
     format_collection::{
         apply_trailing_comma_condition, format_collection, TrailingCommaCondition,
@@ -186,9 +186,11 @@ pub fn format_cst<'a>(
         | CstKind::OpeningCurlyBrace
         | CstKind::ClosingCurlyBrace => SinglelineWidth::from(1).into(),
         CstKind::Arrow => SinglelineWidth::from(2).into(),
-        CstKind::SingleQuote | CstKind::DoubleQuote | CstKind::Percent | CstKind::Octothorpe => {
-            SinglelineWidth::from(1).into()
-        }
+        CstKind::SingleQuote
+        | CstKind::DoubleQuote
+        | CstKind::Percent
+        | CstKind::Octothorpe
+        | CstKind::RawTextPrefix => SinglelineWidth::from(1).into(),
         CstKind::Whitespace(_) | CstKind::Newline(_) => {
             panic!("Whitespace and newlines should be handled separately.")
         }
@@ -237,11 +239,16 @@ pub fn format_cst<'a>(
                 + string.width()
         }
         CstKind::OpeningText {
+            raw_prefix,
             opening_single_quotes,
             opening_double_quote,
         } => {
             // TODO: Format text
             let mut width = Width::default();
+            if let Some(raw_prefix) = raw_prefix {
+                width +=
+                    format_cst(edits, previous_width, raw_prefix, info).min_width(info.indentation);
+            }
             for opening_single_quote in opening_single_quotes {
                 width += format_cst(edits, previous_width + width, opening_single_quote, info)
                     .min_width(info.indentation);
@@ -294,14 +301,19 @@ pub fn format_cst<'a>(
             let previous_width_for_lines =
                 Width::multiline(None, info.indentation.with_indent().width());
             let mut first_parts_width = Width::default();
-            for part in first_parts {
-                first_parts_width += format_cst(
-                    edits,
-                    previous_width_for_lines + first_parts_width,
-                    part,
-                    &info,
-                )
-                .min_width(info.indentation);
+            for (index, part) in first_parts.iter().enumerate() {
+                let next_part = first_parts.get(index + 1).unwrap_or(last_part);
+                first_parts_width +=
+                    format_text_newline(edits, part, next_part, info.indentation.with_indent())
+                        .unwrap_or_else(|| {
+                            format_cst(
+                                edits,
+                                previous_width_for_lines + first_parts_width,
+                                part,
+                                &info,
+                            )
+                            .min_width(info.indentation)
+                        });
             }
 
             let last_part = format_cst(edits, previous_width + first_parts_width, last_part, &info);
@@ -527,6 +539,25 @@ pub fn format_cst<'a>(
 
             return FormattedCst::new(width, whitespace);
         }
+        CstKind::NamedArgument { name, colon, value } => {
+            let name = format_cst(edits, previous_width, name, info);
+            let mut colon = format_cst(edits, previous_width + name.child_width(), colon, info);
+            let name_width = name.into_empty_and_move_comments_to(edits, &mut colon.whitespace);
+            let colon_width = colon.into_trailing_with_space(edits);
+
+            // The value is parsed like an argument of a single-line call, so it needs the same
+            // parentheses as a receiver.
+            let (value_width, whitespace) = format_receiver(
+                edits,
+                previous_width + name_width + colon_width,
+                value,
+                info,
+                ReceiverParent::Call,
+            )
+            .split();
+
+            return FormattedCst::new(name_width + colon_width + value_width, whitespace);
+        }
         CstKind::List {
             opening_parenthesis,
             items,
@@ -1028,6 +1059,37 @@ pub fn format_cst<'a>(
     FormattedCst::new(width, ExistingWhitespace::empty(cst.data.span.end))
 }
 
+/// Formats a newline in a text together with the indentation of the following line.
+///
+/// Lines are indented by one level more than the text itself. Any additional indentation belongs
+/// to the following text part and stays untouched. Empty lines don't get indented.
+fn format_text_newline(
+    edits: &mut TextEdits,
+    part: &Cst,
+    next_part: &Cst,
+    indentation: Indentation,
+) -> Option<Width> {
+    const fn is_text_newline(cst: &Cst) -> bool {
+        match &cst.kind {
+            CstKind::TextNewline(_) => true,
+            CstKind::TrailingWhitespace { child, .. } => child.kind.is_text_newline(),
+            _ => false,
+        }
+    }
+
+    if !is_text_newline(part) {
+        return None;
+    }
+
+    let indentation = if is_text_newline(next_part) {
+        Indentation::default()
+    } else {
+        indentation
+    };
+    edits.change(part.data.span.clone(), format!("{NEWLINE}{indentation}"));
+    Some(Width::NEWLINE + indentation.width())
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum ReceiverParent {
     BinaryBar,
@@ -1226,6 +1288,7 @@ pub impl<D> CstExtension for Cst<D> {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::RawTextPrefix
             | CstKind::Whitespace(_)
             | CstKind::Newline(_)
             | CstKind::Comment { .. } => None,
@@ -1241,6 +1304,8 @@ pub impl<D> CstExtension for Cst<D> {
             CstKind::BinaryBar { .. } => Some(PrecedenceCategory::Low),
             CstKind::Parenthesized { .. } => Some(PrecedenceCategory::High),
             CstKind::Call { .. } => Some(PrecedenceCategory::Low),
+            // Named arguments only occur as arguments, where they never need parentheses.
+            CstKind::NamedArgument { .. } => Some(PrecedenceCategory::High),
             CstKind::List { .. } => Some(PrecedenceCategory::High),
             CstKind::ListItem { .. } => None,
             CstKind::Struct { .. } => Some(PrecedenceCategory::High),
@@ -1367,6 +1432,11 @@ mod test {
         // Hexadecimal
         test("0x123", "0x123\n");
         test("0XDEADc0de", "0xDEADC0DE\n");
+
+        // Underscores
+        test("1_000_000", "1_000_000\n");
+        test("0B1010_0101", "0b1010_0101\n");
+        test("0xdead_c0de", "0xDEAD_C0DE\n");
     }
     #[test]
     fn test_text() {
@@ -1397,6 +1467,22 @@ mod test {
         test("\"foo\n  bar\"", "\"\n  foo\n  bar\n\"\n");
         test("\"foo\n  bar\n\"", "\"\n  foo\n  bar\n\"\n");
         test("\"foo\n  {0}\n  bar\n\"", "\"\n  foo\n  {0}\n  bar\n\"\n");
+
+        // Additional indentation
+        test("\"\n  foo\n    bar\n\"", "\"\n  foo\n    bar\n\"\n");
+        test("\"\n    foo\n  bar\n\"", "\"\n    foo\n  bar\n\"\n");
+        test("\"\n  foo\n\n    bar\n\"", "\"\n  foo\n\n    bar\n\"\n");
+        test(
+            "foo =\n  \"\n    bar\n      {0}\n  \"",
+            "foo = \"\n  bar\n    {0}\n\"\n",
+        );
+
+        // Raw
+        test("r\"foo{0}bar\"", "r\"foo{0}bar\"\n");
+        test(
+            "r'\"\n  {\n    \"foo\": 0\n  }\"'",
+            "r'\"\n  {\n    \"foo\": 0\n  }\n\"'\n",
+        );
     }
     #[test]
     fn test_binary_bar() {
@@ -1568,6 +1654,15 @@ mod test {
         test("(foo bar) baz", "(foo bar) baz\n");
         test("(foo | bar) baz", "(foo | bar) baz\n");
 
+        // Named arguments
+
+        test("foo bar count:3", "foo bar count: 3\n");
+        test("foo bar count:   3 ", "foo bar count: 3\n");
+        test("foo count: (bar)", "foo count: bar\n");
+        test("foo count: (bar baz)", "foo count: (bar baz)\n");
+        test("foo\n  count: bar baz", "foo count: (bar baz)\n");
+        test("foo count: { bar }", "foo count: { bar }\n");
+
         // Trailing sandwich-like
 
         test("foo{bar}", "foo { bar }\n");
@@ -1998,6 +2093,7 @@ mod test {
         test("foo bar=baz ", "foo bar = baz\n");
         test("foo\n  bar=baz ", "foo bar = baz\n");
         test("foo\n  bar\n  =\n  baz ", "foo bar = baz\n");
+        test("foo bar count:3=baz ", "foo bar count: 3 = baz\n");
         // foo
         //   firstlooooooooooooooooooooooooooooooooongArgument
         //   secondlooooooooooooooooooooooooooooooooongArgument
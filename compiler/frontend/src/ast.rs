@@ -1,4 +1,4 @@
-use super::{cst, cst_to_ast::CstToAst, error::CompilerError};
+use super::{cst_to_ast::CstToAst, error::CompilerError};
 use crate::{
     module::Module,
     rich_ir::{RichIrBuilder, ToRichIr, TokenType},
@@ -7,11 +7,12 @@ use derive_more::{Deref, From};
 use enumset::EnumSet;
 use num_bigint::{BigInt, BigUint};
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     num::NonZeroUsize,
 };
-use strum_macros::EnumIs;
+use strum_macros::{AsRefStr, EnumIs};
 
 #[salsa::query_group(AstDbStorage)]
 pub trait AstDb: CstToAst {
@@ -23,7 +24,7 @@ fn find_ast(db: &dyn AstDb, id: Id) -> Option<Ast> {
     ast.find(&id).cloned()
 }
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Id {
     pub module: Module,
     pub local: usize,
@@ -44,14 +45,14 @@ impl Display for Id {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Deref)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deref, Deserialize, Serialize)]
 pub struct Ast {
     pub id: Id,
     #[deref]
     pub kind: AstKind,
 }
 
-#[derive(Clone, Debug, EnumIs, Eq, From, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, EnumIs, Eq, From, Hash, PartialEq, Serialize)]
 pub enum AstKind {
     Int(Int),
     Text(Text),
@@ -63,6 +64,7 @@ pub enum AstKind {
     StructAccess(StructAccess),
     Function(Function),
     Call(Call),
+    NamedArgument(NamedArgument),
     Assignment(Assignment),
     Match(Match),
     MatchCase(MatchCase),
@@ -70,80 +72,88 @@ pub enum AstKind {
     Error { errors: Vec<CompilerError> },
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Int(pub BigUint);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Text(pub Vec<Ast>);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct TextPart(pub AstString);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Identifier(pub AstString);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Symbol(pub AstString);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct List(pub Vec<Ast>);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Struct {
     pub fields: Vec<(Option<Ast>, Ast)>,
 }
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct StructAccess {
     pub struct_: Box<Ast>,
     pub key: AstString,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Function {
     pub parameters: Vec<Ast>,
     pub body: Vec<Ast>,
     pub fuzzable: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Call {
     pub receiver: Box<Ast>,
     pub arguments: Vec<Ast>,
     pub is_from_pipe: bool,
 }
+/// A named argument in a call (`foo count: 3`) or an optional parameter with a
+/// default value in a function definition (`foo count: 3 = …`).
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+pub struct NamedArgument {
+    pub name: AstString,
+    pub value: Box<Ast>,
+}
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Assignment {
     pub is_public: bool,
     pub body: AssignmentBody,
 }
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub enum AssignmentBody {
     Function { name: AstString, function: Function },
     Body { pattern: Box<Ast>, body: Vec<Ast> },
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct Match {
     pub expression: Box<Ast>,
     pub cases: Vec<Ast>,
 }
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct MatchCase {
     pub pattern: Box<Ast>,
     pub body: Vec<Ast>,
 }
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct OrPattern(pub Vec<Ast>);
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Deref)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deref, Deserialize, Serialize)]
 pub struct AstString {
     pub id: Id,
     #[deref]
     pub value: String,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(AsRefStr, Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+#[strum(serialize_all = "kebab-case")]
 pub enum AstError {
     ExpectedNameOrPatternInAssignment,
     ExpectedParameter,
@@ -154,7 +164,6 @@ pub enum AstError {
     OrPatternIsMissingIdentifiers {
         identifier: String,
         number_of_missing_captures: NonZeroUsize,
-        all_captures: Vec<cst::Id>,
     },
     ParenthesizedInPattern,
     ParenthesizedMissesClosingParenthesis,
@@ -191,6 +200,7 @@ impl FindAst for Ast {
             AstKind::StructAccess(access) => access.find(id),
             AstKind::Function(function) => function.find(id),
             AstKind::Call(call) => call.find(id),
+            AstKind::NamedArgument(named_argument) => named_argument.find(id),
             AstKind::Assignment(assignment) => assignment.find(id),
             AstKind::Match(match_) => match_.find(id),
             AstKind::MatchCase(match_case) => match_case.find(id),
@@ -228,6 +238,11 @@ impl FindAst for Call {
         self.receiver.find(id).or_else(|| self.arguments.find(id))
     }
 }
+impl FindAst for NamedArgument {
+    fn find(&self, id: &Id) -> Option<&Ast> {
+        self.value.find(id)
+    }
+}
 impl FindAst for Assignment {
     fn find(&self, id: &Id) -> Option<&Ast> {
         self.body.find(id)
@@ -295,6 +310,7 @@ impl AstKind {
             Self::StructAccess(_)
             | Self::Function(_)
             | Self::Call(_)
+            | Self::NamedArgument(_)
             | Self::Assignment(_)
             | Self::Match(_)
             | Self::MatchCase(_) => {}
@@ -337,6 +353,9 @@ impl CollectErrors for Ast {
             }
             AstKind::Function(function) => function.body.collect_errors(errors),
             AstKind::Call(call) => call.arguments.collect_errors(errors),
+            AstKind::NamedArgument(NamedArgument { name: _, value }) => {
+                value.collect_errors(errors);
+            }
             AstKind::Assignment(assignment) => match assignment.body {
                 AssignmentBody::Function { name: _, function } => {
                     function.body.collect_errors(errors);
@@ -390,6 +409,7 @@ impl ToRichIr for Ast {
             AstKind::StructAccess(struct_access) => struct_access.build_rich_ir(builder),
             AstKind::Function(function) => function.build_rich_ir(builder),
             AstKind::Call(call) => call.build_rich_ir(builder),
+            AstKind::NamedArgument(named_argument) => named_argument.build_rich_ir(builder),
             AstKind::Assignment(assignment) => assignment.build_rich_ir(builder),
             AstKind::Match(match_) => match_.build_rich_ir(builder),
             AstKind::MatchCase(match_case) => match_case.build_rich_ir(builder),
@@ -510,6 +530,14 @@ impl ToRichIr for Call {
         builder.push_foldable(|builder| builder.push_children_multiline(&self.arguments));
     }
 }
+impl ToRichIr for NamedArgument {
+    fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
+        builder.push("named argument ", None, EnumSet::empty());
+        self.name.build_rich_ir(builder);
+        builder.push(": ", None, EnumSet::empty());
+        self.value.build_rich_ir(builder);
+    }
+}
 impl ToRichIr for Assignment {
     fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
         builder.push("assignment: ", None, EnumSet::empty());
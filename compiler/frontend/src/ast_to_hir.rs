@@ -7,12 +7,17 @@ use crate::{
     cst::{self, CstDb},
     cst_to_ast::CstToAst,
     error::{CompilerError, CompilerErrorPayload},
+    exports::top_level_definitions,
+    extern_signature::{ExternSignature, ExternType},
     hir::{
         self, Body, Expression, Function, FunctionKind, HirError, IdKey, Pattern,
         PatternIdentifierId,
     },
     id::IdGenerator,
-    module::{Module, Package},
+    match_analysis::analyze_match,
+    mir_optimize::module_folding::statically_imported_modules,
+    module::{Module, Package, UsePath},
+    persistent_cache::{self, CacheKey},
     position::Offset,
     string_to_rcst::ModuleError,
     utils::AdjustCasingOfFirstLetter,
@@ -87,11 +92,32 @@ fn cst_to_last_hir_id(db: &dyn AstToHir, module: Module, id: cst::Id) -> Option<
     db.cst_to_hir_ids(module, id).pop()
 }
 
+#[allow(clippy::needless_pass_by_value)]
 fn hir(db: &dyn AstToHir, module: Module) -> HirResult {
-    db.ast(module.clone()).map(|(ast, _)| {
-        let (body, id_mapping) = compile_top_level(db, module, &ast);
-        (Arc::new(body), Arc::new(id_mapping))
-    })
+    // Names imported from other modules are checked using their ASTs, so the
+    // HIR also depends on the modules it `use`s.
+    let key = || {
+        let content = db.get_module_content(module.clone());
+        let mut key = CacheKey::new("hir")
+            .with_module_content(&module, content.as_deref().map(Vec::as_slice));
+        if let Ok((ast, _)) = db.ast(module.clone()) {
+            for imported_module in statically_imported_modules(&module, &ast)
+                .into_iter()
+                .sorted()
+            {
+                let content = db.get_module_content(imported_module.clone());
+                key = key
+                    .with_module_content(&imported_module, content.as_deref().map(Vec::as_slice));
+            }
+        }
+        key
+    };
+    let (body, id_mapping) =
+        persistent_cache::get_or_compute(db.get_persistent_cache(), &module, key, || {
+            db.ast(module.clone())
+                .map(|(ast, _)| compile_top_level(db, module.clone(), &ast))
+        })?;
+    Ok((Arc::new(body), Arc::new(id_mapping)))
 }
 
 fn compile_top_level(
@@ -108,6 +134,7 @@ fn compile_top_level(
         body: Body::default(),
         id_prefix: hir::Id::new(module, vec![]).into(),
         identifiers: im::HashMap::new(),
+        function_signatures: FxHashMap::default(),
         is_top_level: true,
         use_id: None,
         builtins_id: None,
@@ -153,11 +180,29 @@ struct Context<'a> {
     body: Body,
     id_prefix: IdPrefix,
     identifiers: im::HashMap<String, hir::Id>,
+    function_signatures: FxHashMap<hir::Id, FunctionSignature>,
     is_top_level: bool,
     use_id: Option<hir::Id>,
     builtins_id: Option<hir::Id>,
 }
 
+/// The parameters of a function defined in this module, keyed by the ID of the
+/// identifier the function is assigned to.
+///
+/// Named arguments and default values can only be resolved for calls to these
+/// functions because other modules are only `use`d at runtime.
+#[derive(Clone, Debug)]
+struct FunctionSignature {
+    name: String,
+    /// Parameter names and the IDs of their default values.
+    parameters: Vec<(String, Option<hir::Id>)>,
+}
+impl FunctionSignature {
+    fn has_default_values(&self) -> bool {
+        self.parameters.iter().any(|(_, default)| default.is_some())
+    }
+}
+
 impl Context<'_> {
     fn with_non_top_level<F, T>(&mut self, func: F) -> T
     where
@@ -280,6 +325,16 @@ impl Context<'_> {
             }
             AstKind::Function(function) => self.compile_function(ast.id.clone(), function, None),
             AstKind::Call(call) => self.lower_call(Some(ast.id.clone()), call),
+            AstKind::NamedArgument(ast::NamedArgument { name, value }) => {
+                // Named arguments in calls we can resolve are handled in
+                // `lower_call_with_signature`.
+                self.compile_single(value);
+                self.push_error(
+                    name.id.clone(),
+                    self.db.ast_id_to_span(&name.id).unwrap(),
+                    HirError::UnsupportedNamedArgument,
+                )
+            }
             AstKind::Assignment(Assignment { is_public, body }) => {
                 // An assignment to a single identifier (i.e., no destructuring)
                 // gets converted to at least two HIR expressions:
@@ -292,15 +347,26 @@ impl Context<'_> {
                 // for IDE features.
                 let (names, body) = match body {
                     ast::AssignmentBody::Function { name, function } => {
+                        let parameters = self.lower_default_values(function);
                         let body = self.compile_function(ast.id.clone(), function, &***name);
                         let name_id = self.push(
                             name.id.clone(),
                             Expression::Reference(body.clone()),
                             name.value.clone(),
                         );
+                        if let Some(parameters) = parameters {
+                            self.function_signatures.insert(
+                                name_id.clone(),
+                                FunctionSignature {
+                                    name: name.value.clone(),
+                                    parameters,
+                                },
+                            );
+                        }
                         (vec![(name.value.clone(), name.id.clone(), name_id)], body)
                     }
                     ast::AssignmentBody::Body { pattern, body } => {
+                        self.check_imported_names(pattern, body);
                         let body = self.with_non_top_level(|scope| scope.compile(body));
 
                         let names = if let AstKind::Identifier(Identifier(name)) = &pattern.kind {
@@ -353,16 +419,27 @@ impl Context<'_> {
                 if *is_public {
                     if self.is_top_level {
                         for (name, ast_id, id) in names {
-                            if let Entry::Vacant(entry) =
-                                self.public_identifiers.entry(name.clone())
-                            {
-                                entry.insert(id);
-                            } else {
-                                self.push_error(
-                                    ast_id.clone(),
-                                    self.db.ast_id_to_display_span(&ast_id).unwrap(),
-                                    HirError::PublicAssignmentWithSameName { name },
-                                );
+                            match self.public_identifiers.entry(name.clone()) {
+                                Entry::Vacant(entry) => {
+                                    entry.insert(id);
+                                }
+                                Entry::Occupied(entry) => {
+                                    let previous_span = self.id_mapping[entry.get()]
+                                        .as_ref()
+                                        .and_then(|it| self.db.ast_id_to_display_span(it));
+                                    let mut error = self.create_error(
+                                        self.db.ast_id_to_display_span(&ast_id).unwrap(),
+                                        HirError::PublicAssignmentWithSameName { name },
+                                    );
+                                    if let Some(previous_span) = previous_span {
+                                        error = error.with_label(
+                                            self.module.clone(),
+                                            previous_span,
+                                            "The identifier is already defined here.",
+                                        );
+                                    }
+                                    self.push_compiler_error(ast_id, error);
+                                }
                             }
                         }
                     } else {
@@ -382,13 +459,46 @@ impl Context<'_> {
                 // inside the cases.
                 let match_id = self.create_next_id(ast.id.clone(), None);
                 let (_, cases) = self.with_scope(match_id.clone(), |scope| {
-                    cases
+                    let patterns = cases
                         .iter()
                         .map(|case| match &case.kind {
-                            AstKind::MatchCase(MatchCase { box pattern, body }) => {
-                                let (pattern, pattern_identifiers) = scope.lower_pattern(pattern);
+                            AstKind::MatchCase(MatchCase { box pattern, .. }) => {
+                                let (lowered, identifiers) = scope.lower_pattern(pattern);
+                                (Some(pattern), lowered, identifiers)
+                            }
+                            AstKind::Error { errors } => {
+                                let pattern = Pattern::Error {
+                                    errors: errors.clone(),
+                                };
+                                (None, pattern, FxHashMap::default())
+                            }
+                            _ => unreachable!("Expected match case in match cases, got {case:?}."),
+                        })
+                        .collect_vec();
+                    let analysis = analyze_match(patterns.iter().map(|(_, pattern, _)| pattern));
 
+                    let mut cases = cases
+                        .iter()
+                        .zip_eq(patterns)
+                        .enumerate()
+                        .map(
+                            |(index, (case, (pattern_ast, pattern, pattern_identifiers)))| {
                                 let (body, ()) = scope.with_scope(None, |scope| {
+                                    let is_unreachable = analysis
+                                        .as_ref()
+                                        .is_some_and(|it| it.unreachable_cases.contains(&index));
+                                    if is_unreachable {
+                                        let pattern_ast = pattern_ast.unwrap();
+                                        scope.push_error(
+                                            pattern_ast.id.clone(),
+                                            scope
+                                                .db
+                                                .ast_id_to_display_span(&pattern_ast.id)
+                                                .unwrap(),
+                                            HirError::UnreachableMatchCase,
+                                        );
+                                    }
+
                                     for (name, (ast_id, identifier_id)) in
                                         pattern_identifiers.clone()
                                     {
@@ -398,25 +508,37 @@ impl Context<'_> {
                                             name.clone(),
                                         );
                                     }
-                                    scope.compile(body.as_ref());
-                                });
-
-                                (pattern, body)
-                            }
-                            AstKind::Error { errors } => {
-                                let pattern = Pattern::Error {
-                                    errors: errors.clone(),
-                                };
-
-                                let (body, ()) = scope.with_scope(None, |scope| {
-                                    scope.compile(&[]);
+                                    match &case.kind {
+                                        AstKind::MatchCase(MatchCase { body, .. }) => {
+                                            scope.compile(body.as_ref());
+                                        }
+                                        AstKind::Error { .. } => {
+                                            scope.compile(&[]);
+                                        }
+                                        _ => unreachable!(),
+                                    }
                                 });
-
                                 (pattern, body)
-                            }
-                            _ => unreachable!("Expected match case in match cases, got {case:?}."),
-                        })
-                        .collect_vec()
+                            },
+                        )
+                        .collect_vec();
+
+                    // Values that no case matches reach this additional case,
+                    // which panics with the error. That's also what happens
+                    // without it, just with a less helpful message.
+                    if let Some(missing) = analysis.and_then(|it| it.missing_value) {
+                        let (body, _) = scope.with_scope(None, |scope| {
+                            scope.push_error(
+                                ast.id.clone(),
+                                scope.db.ast_id_to_display_span(&ast.id).unwrap(),
+                                HirError::NonExhaustiveMatch {
+                                    missing: missing.clone(),
+                                },
+                            )
+                        });
+                        cases.push((Pattern::NewIdentifier(PatternIdentifierId(0)), body));
+                    }
+                    cases
                 });
 
                 self.push_with_existing_id(match_id, Expression::Match { expression, cases }, None)
@@ -540,6 +662,31 @@ impl Context<'_> {
             .unwrap_or_else(|| self.push(id, Expression::Text(String::new()), None))
     }
 
+    /// Lowers the default values of optional parameters in the current scope so
+    /// that they are evaluated once when the function is defined.
+    ///
+    /// Returns `None` if any parameter is invalid.
+    fn lower_default_values(
+        &mut self,
+        function: &ast::Function,
+    ) -> Option<Vec<(String, Option<hir::Id>)>> {
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|parameter| match &parameter.kind {
+                AstKind::Identifier(Identifier(name)) => Some((name.value.clone(), None)),
+                AstKind::NamedArgument(ast::NamedArgument { name, value }) => {
+                    let default = self.with_non_top_level(|scope| scope.compile_single(value));
+                    Some((name.value.clone(), Some(default)))
+                }
+                _ => None,
+            })
+            .collect_vec();
+        // Collecting separately ensures all default values get lowered, even
+        // after an invalid parameter.
+        parameters.into_iter().collect()
+    }
+
     fn compile_function(
         &mut self,
         id: ast::Id,
@@ -551,7 +698,11 @@ impl Context<'_> {
             // TODO: Error on parameters with same name
             let mut parameters = Vec::with_capacity(function.parameters.len());
             for parameter in &function.parameters {
-                if let AstKind::Identifier(Identifier(parameter)) = &parameter.kind {
+                if let AstKind::Identifier(Identifier(parameter))
+                | AstKind::NamedArgument(ast::NamedArgument {
+                    name: parameter, ..
+                }) = &parameter.kind
+                {
                     let name = parameter.value.to_string();
                     parameters.push(scope.id_prefix.id.child(name.clone()));
 
@@ -684,9 +835,9 @@ impl Context<'_> {
                         ),
                     },
                     _ => {
-                        return self.push_error(
+                        return self.push_call_error(
                             id,
-                            self.db.ast_id_to_span(name_id).unwrap(),
+                            name_id,
                             HirError::NeedsWithWrongNumberOfArguments {
                                 num_args: call.arguments.len(),
                             },
@@ -695,8 +846,64 @@ impl Context<'_> {
                 };
                 return self.push(id, expression, None);
             }
+            AstKind::Identifier(Identifier(AstString {
+                id: name_id,
+                value: name,
+            })) if name == "extern" => {
+                let (symbol, signature) = match &call.arguments[..] {
+                    [symbol, arity] => (symbol, Self::untyped_extern_signature(arity)),
+                    [symbol, parameters, return_type] => (
+                        symbol,
+                        Self::typed_extern_signature(parameters, return_type),
+                    ),
+                    _ => {
+                        return self.push_call_error(
+                            id,
+                            name_id,
+                            HirError::ExternWithInvalidArguments,
+                        );
+                    }
+                };
+                let symbol = match &symbol.kind {
+                    AstKind::Text(Text(parts)) => match &parts[..] {
+                        [Ast {
+                            kind: AstKind::TextPart(TextPart(symbol)),
+                            ..
+                        }] => Some(symbol.value.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let (Some(symbol), Some(signature)) = (symbol, signature) else {
+                    return self.push_call_error(id, name_id, HirError::ExternWithInvalidArguments);
+                };
+                return self.push(id, Expression::Extern { symbol, signature }, None);
+            }
             _ => self.compile_single(call.receiver.as_ref()),
         };
+
+        if let AstKind::Identifier(Identifier(name)) = &call.receiver.kind
+            && let Some(signature) = self
+                .identifiers
+                .get(&name.value)
+                .and_then(|id| self.function_signatures.get(id))
+            && (signature.has_default_values()
+                || uncompiled_arguments
+                    .iter()
+                    .any(|it| it.kind.is_named_argument()))
+        {
+            let signature = signature.clone();
+            let compiled_arguments = call.arguments.iter().zip(arguments).collect_vec();
+            return self.lower_call_with_signature(
+                id,
+                &name.id,
+                function,
+                compiled_arguments,
+                uncompiled_arguments,
+                &signature,
+            );
+        }
+
         arguments.extend(self.lower_call_arguments(uncompiled_arguments));
         self.push(
             id,
@@ -707,6 +914,138 @@ impl Context<'_> {
             None,
         )
     }
+    fn untyped_extern_signature(arity: &Ast) -> Option<ExternSignature> {
+        match &arity.kind {
+            AstKind::Int(Int(arity)) => usize::try_from(arity).ok().map(ExternSignature::untyped),
+            _ => None,
+        }
+    }
+    fn typed_extern_signature(parameters: &Ast, return_type: &Ast) -> Option<ExternSignature> {
+        fn extern_type(ast: &Ast) -> Option<ExternType> {
+            match &ast.kind {
+                AstKind::Symbol(Symbol(symbol)) => ExternType::from_symbol(&symbol.value),
+                _ => None,
+            }
+        }
+
+        let AstKind::List(List(parameters)) = &parameters.kind else {
+            return None;
+        };
+        let parameters = parameters
+            .iter()
+            .map(|parameter| extern_type(parameter).filter(|it| *it != ExternType::Nothing))
+            .collect::<Option<Vec<_>>>()?;
+        Some(ExternSignature {
+            parameters,
+            return_type: extern_type(return_type)?,
+        })
+    }
+    /// Resolves named arguments and fills in default values for a call to a
+    /// function defined in this module.
+    fn lower_call_with_signature<'a>(
+        &mut self,
+        id: Option<ast::Id>,
+        name_id: &ast::Id,
+        function: hir::Id,
+        compiled_arguments: Vec<(&'a Ast, hir::Id)>,
+        uncompiled_arguments: &'a [Ast],
+        signature: &FunctionSignature,
+    ) -> hir::Id {
+        let mut arguments = compiled_arguments
+            .into_iter()
+            .map(|(ast, id)| (ast, None, id))
+            .collect_vec();
+        for argument in uncompiled_arguments {
+            let (name, value) = match &argument.kind {
+                AstKind::NamedArgument(ast::NamedArgument { name, value }) => {
+                    (Some(name), self.compile_single(value))
+                }
+                _ => (None, self.compile_single(argument)),
+            };
+            arguments.push((argument, name, value));
+        }
+
+        let mut resolved_arguments = vec![None; signature.parameters.len()];
+        let mut errors = vec![];
+        let mut num_positional_arguments = 0;
+        for (argument, name, value) in &arguments {
+            let index = if let Some(name) = name {
+                let Some(index) = signature
+                    .parameters
+                    .iter()
+                    .position(|(parameter, _)| parameter == &name.value)
+                else {
+                    errors.push(self.create_error(
+                        self.db.ast_id_to_span(&name.id).unwrap(),
+                        HirError::UnknownNamedArgument {
+                            function: signature.name.clone(),
+                            name: name.value.clone(),
+                        },
+                    ));
+                    continue;
+                };
+                index
+            } else {
+                num_positional_arguments += 1;
+                num_positional_arguments - 1
+            };
+
+            match resolved_arguments.get_mut(index) {
+                Some(resolved @ None) => *resolved = Some(value.clone()),
+                Some(Some(_)) => errors.push(self.create_error(
+                    self.db.ast_id_to_display_span(&argument.id).unwrap(),
+                    HirError::ArgumentGivenMultipleTimes {
+                        parameter: signature.parameters[index].0.clone(),
+                    },
+                )),
+                // Reported below.
+                None => {}
+            }
+        }
+
+        if num_positional_arguments > signature.parameters.len() {
+            errors.push(self.create_call_error(
+                id.as_ref(),
+                name_id,
+                HirError::CallWithTooManyArguments {
+                    function: signature.name.clone(),
+                    num_parameters: signature.parameters.len(),
+                    num_args: arguments.len(),
+                },
+            ));
+        }
+        let resolved_arguments = resolved_arguments
+            .into_iter()
+            .zip(&signature.parameters)
+            .map(|(argument, (parameter, default))| {
+                let argument = argument.or_else(|| default.clone());
+                if argument.is_none() {
+                    errors.push(self.create_call_error(
+                        id.as_ref(),
+                        name_id,
+                        HirError::CallMissesArgument {
+                            function: signature.name.clone(),
+                            parameter: parameter.clone(),
+                        },
+                    ));
+                }
+                argument
+            })
+            .collect_vec();
+
+        if !errors.is_empty() {
+            return self.push(id, Expression::Error { errors }, None);
+        }
+        let arguments = resolved_arguments.into_iter().map(Option::unwrap).collect();
+        self.push(
+            id,
+            Expression::Call {
+                function,
+                arguments,
+            },
+            None,
+        )
+    }
     fn lower_call_arguments(&mut self, arguments: &[Ast]) -> Vec<hir::Id> {
         arguments
             .iter()
@@ -753,19 +1092,149 @@ impl Context<'_> {
         ast_id: impl Into<Option<ast::Id>>,
         span: Range<Offset>,
         error: HirError,
+    ) -> hir::Id {
+        let error = self.create_error(span, error);
+        self.push_compiler_error(ast_id, error)
+    }
+    /// Reports an error at the called function's name and additionally labels
+    /// the whole call, which may span multiple lines.
+    fn push_call_error(
+        &mut self,
+        call_id: Option<ast::Id>,
+        name_id: &ast::Id,
+        error: HirError,
+    ) -> hir::Id {
+        let error = self.create_call_error(call_id.as_ref(), name_id, error);
+        self.push_compiler_error(call_id, error)
+    }
+    fn create_call_error(
+        &self,
+        call_id: Option<&ast::Id>,
+        name_id: &ast::Id,
+        error: HirError,
+    ) -> CompilerError {
+        let mut error = self.create_error(self.db.ast_id_to_span(name_id).unwrap(), error);
+        if let Some(call_span) = call_id.and_then(|id| self.db.ast_id_to_logical_span(id)) {
+            error = error.with_label(self.module.clone(), call_span, "This is the whole call.");
+        }
+        error
+    }
+    fn push_compiler_error(
+        &mut self,
+        ast_id: impl Into<Option<ast::Id>>,
+        error: CompilerError,
     ) -> hir::Id {
         self.push(
             ast_id,
             Expression::Error {
-                errors: vec![CompilerError {
-                    module: self.module.clone(),
-                    span,
-                    payload: error.into(),
-                }],
+                errors: vec![error],
             },
             None,
         )
     }
+    fn create_error(&self, span: Range<Offset>, error: HirError) -> CompilerError {
+        CompilerError {
+            module: self.module.clone(),
+            span,
+            payload: error.into(),
+            labels: vec![],
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    /// Reports names in `[foo, Bar: baz] = use "…"` that the used module
+    /// doesn't export.
+    fn check_imported_names(&mut self, pattern: &Ast, body: &[Ast]) {
+        let AstKind::Struct(Struct { fields }) = &pattern.kind else {
+            return;
+        };
+        let [Ast {
+            kind:
+                AstKind::Call(Call {
+                    receiver,
+                    arguments,
+                    ..
+                }),
+            ..
+        }] = body
+        else {
+            return;
+        };
+        if !matches!(&receiver.kind, AstKind::Identifier(Identifier(name)) if name.value == "use") {
+            return;
+        }
+        let [Ast {
+            kind: AstKind::Text(Text(parts)),
+            ..
+        }] = arguments.as_slice()
+        else {
+            return;
+        };
+        let [Ast {
+            kind: AstKind::TextPart(TextPart(path)),
+            ..
+        }] = parts.as_slice()
+        else {
+            return;
+        };
+        let Ok(imported_module) =
+            UsePath::parse(&path.value).and_then(|path| path.resolve_relative_to(&self.module))
+        else {
+            return;
+        };
+        // Modules that don't exist are reported when the `use` is evaluated.
+        let Ok((asts, _)) = self.db.ast(imported_module.clone()) else {
+            return;
+        };
+        let definitions = top_level_definitions(&asts);
+
+        for (key, value) in fields {
+            let (key, id) = match (key, &value.kind) {
+                (
+                    Some(Ast {
+                        kind: AstKind::Symbol(Symbol(key)),
+                        ..
+                    }),
+                    _,
+                ) => (key.value.clone(), key.id.clone()),
+                (None, AstKind::Identifier(Identifier(name))) => {
+                    (name.value.uppercase_first_letter(), name.id.clone())
+                }
+                _ => continue,
+            };
+            let matching = definitions
+                .iter()
+                .filter(|definition| definition.export_key() == key)
+                .collect_vec();
+            if matching.iter().any(|definition| definition.is_public) {
+                continue;
+            }
+
+            let Some(span) = self.db.ast_id_to_display_span(&id) else {
+                continue;
+            };
+            let mut error = self.create_error(
+                span,
+                HirError::NotExported {
+                    module: imported_module.clone(),
+                    name: key.lowercase_first_letter(),
+                },
+            );
+            if let Some(definition) = matching.first()
+                && let Some(definition_span) = self.db.ast_id_to_display_span(&definition.id)
+            {
+                error = error
+                    .with_label(
+                        imported_module.clone(),
+                        definition_span,
+                        "It's defined here, but private.",
+                    )
+                    .with_note("Use `:=` instead of `=` to export a definition.");
+            }
+            self.push_compiler_error(id, error);
+        }
+    }
 
     fn create_next_id(
         &mut self,
@@ -980,11 +1449,12 @@ impl<'a> PatternContext<'a> {
             }
             AstKind::StructAccess(_)
             | AstKind::Function(_)
+            | AstKind::NamedArgument(_)
             | AstKind::Assignment(_)
             | AstKind::Match(_)
             | AstKind::MatchCase(_) => {
                 panic!(
-                    "AST pattern can't contain struct access, function, call, assignment, match, or match case, but found {ast:?}."
+                    "AST pattern can't contain struct access, function, named argument, assignment, match, or match case, but found {ast:?}."
                 )
             }
             AstKind::OrPattern(OrPattern(patterns)) => {
@@ -1004,9 +1474,92 @@ impl<'a> PatternContext<'a> {
         Pattern::Error {
             errors: vec![CompilerError {
                 module: self.module.clone(),
-                span: self.db.ast_id_to_span(&ast.id).unwrap(),
+                span: self.db.ast_id_to_logical_span(&ast.id).unwrap(),
                 payload: CompilerErrorPayload::Hir(error),
+                labels: vec![],
+                notes: vec![],
+                suggestions: vec![],
             }],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AstToHir;
+    use crate::{
+        error::{CompilerErrorPayload, ErrorLabel},
+        hir::{CollectErrors, HirError},
+        module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase},
+        position::Offset,
+    };
+    use std::{ops::Range, path::PathBuf};
+
+    fn span_of(source: &str, text: &str) -> Range<Offset> {
+        let start = source.find(text).unwrap();
+        Offset(start)..Offset(start + text.len())
+    }
+
+    #[test]
+    fn reports_names_not_exported_by_the_used_module() {
+        let mut db = TestDatabase::default();
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let main = Module::new(package.clone(), vec!["main".to_string()], ModuleKind::Code);
+        let library = Module::new(
+            package,
+            vec!["main".to_string(), "library".to_string()],
+            ModuleKind::Code,
+        );
+        let main_source = "[public, private, missing] = use \".library\"\n";
+        let library_source = "public := 1\nprivate = 2\n";
+        db.did_open_module(&main, main_source.as_bytes().to_vec());
+        db.did_open_module(&library, library_source.as_bytes().to_vec());
+
+        let (hir, _) = db.hir(main.clone()).unwrap();
+        let mut errors = vec![];
+        hir.collect_errors(&mut errors);
+        let mut errors = errors
+            .into_iter()
+            .filter(|it| {
+                matches!(
+                    it.payload,
+                    CompilerErrorPayload::Hir(HirError::NotExported { .. }),
+                )
+            })
+            .collect::<Vec<_>>();
+        errors.sort_by_key(|it| it.span.start);
+
+        let [private_error, missing_error] = errors.as_slice() else {
+            panic!("Expected two errors, got {errors:?}.");
+        };
+
+        assert_eq!(private_error.module, main);
+        assert_eq!(private_error.span, span_of(main_source, "private"));
+        assert_eq!(
+            private_error.payload,
+            CompilerErrorPayload::Hir(HirError::NotExported {
+                module: library.clone(),
+                name: "private".to_string(),
+            }),
+        );
+        assert_eq!(
+            private_error.labels,
+            vec![ErrorLabel {
+                module: library.clone(),
+                span: span_of(library_source, "private"),
+                message: "It's defined here, but private.".to_string(),
+            }],
+        );
+        assert_eq!(private_error.notes.len(), 1);
+
+        assert_eq!(missing_error.span, span_of(main_source, "missing"));
+        assert_eq!(
+            missing_error.payload,
+            CompilerErrorPayload::Hir(HirError::NotExported {
+                module: library,
+                name: "missing".to_string(),
+            }),
+        );
+        assert!(missing_error.labels.is_empty());
+    }
+}
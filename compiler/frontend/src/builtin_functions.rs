@@ -3,6 +3,7 @@ use crate::{
     rich_ir::{RichIrBuilder, ToRichIr, TokenModifier, TokenType},
 };
 use enumset::EnumSet;
+use serde::{Deserialize, Serialize};
 use strum_macros::{AsRefStr, VariantArray};
 
 /// These are all built-ins.
@@ -22,10 +23,32 @@ use strum_macros::{AsRefStr, VariantArray};
 ///
 /// See the source code of the `Builtins` package for documentation on what
 /// these functions do.
-#[derive(AsRefStr, Clone, Copy, Debug, Eq, PartialEq, Hash, VariantArray)]
+#[derive(
+    AsRefStr, Clone, Copy, Debug, Eq, PartialEq, Hash, VariantArray, Deserialize, Serialize,
+)]
 #[strum(serialize_all = "snake_case")]
 pub enum BuiltinFunction {
+    BytesConcatenate,
+    BytesFromList,
+    BytesFromText,
+    BytesGetRange,
+    BytesLength,
+    BytesToList,
+    BytesToText,
+    CellCreate,
+    CellGet,
+    CellSet,
+    DebugAssert,
+    DebugLog,
     Equals,
+    FractionAdd,
+    FractionCompareTo,
+    FractionCreate,
+    FractionDenominator,
+    FractionDivide,
+    FractionMultiply,
+    FractionNumerator,
+    FractionSubtract,
     FunctionRun,
     GetArgumentCount,
     IfElse,
@@ -43,13 +66,30 @@ pub enum BuiltinFunction {
     IntShiftLeft,
     IntShiftRight,
     IntSubtract,
+    ListConcatenate,
     ListFilled,
     ListGet,
+    ListGetRange,
     ListInsert,
     ListLength,
     ListRemoveAt,
     ListReplace,
+    MapEntries,
+    MapFromList,
+    MapGet,
+    MapHasKey,
+    MapInsert,
+    MapRemove,
+    MapSize,
+    MapUnion,
     Print,
+    SetContains,
+    SetFromList,
+    SetInsert,
+    SetItems,
+    SetRemove,
+    SetSize,
+    SetUnion,
     StructGet,
     StructGetKeys,
     StructHasKey,
@@ -57,7 +97,12 @@ pub enum BuiltinFunction {
     TagHasValue,
     TagWithoutValue,
     TagWithValue,
+    TextBuilderAppend,
+    TextBuilderAppendInt,
+    TextBuilderBuild,
+    TextBuilderCreate,
     TextCharacters,
+    TextCompareTo,
     TextConcatenate,
     TextContains,
     TextEndsWith,
@@ -66,9 +111,12 @@ pub enum BuiltinFunction {
     TextIsEmpty,
     TextLength,
     TextStartsWith,
+    TextToLowercase,
+    TextToUppercase,
     TextTrimEnd,
     TextTrimStart,
     ToDebugText,
+    Try,
     TypeOf,
 }
 
@@ -76,7 +124,27 @@ impl BuiltinFunction {
     #[must_use]
     pub const fn is_pure(&self) -> bool {
         match self {
+            Self::BytesConcatenate => true,
+            Self::BytesFromList => true,
+            Self::BytesFromText => true,
+            Self::BytesGetRange => true,
+            Self::BytesLength => true,
+            Self::BytesToList => true,
+            Self::BytesToText => true,
+            Self::CellCreate => false,
+            Self::CellGet => false,
+            Self::CellSet => false,
+            Self::DebugAssert => false,
+            Self::DebugLog => false,
             Self::Equals => true,
+            Self::FractionAdd => true,
+            Self::FractionCompareTo => true,
+            Self::FractionCreate => true,
+            Self::FractionDenominator => true,
+            Self::FractionDivide => true,
+            Self::FractionMultiply => true,
+            Self::FractionNumerator => true,
+            Self::FractionSubtract => true,
             Self::FunctionRun => false,
             Self::GetArgumentCount => true,
             Self::IfElse => false,
@@ -94,13 +162,30 @@ impl BuiltinFunction {
             Self::IntShiftLeft => true,
             Self::IntShiftRight => true,
             Self::IntSubtract => true,
+            Self::ListConcatenate => true,
             Self::ListFilled => true,
             Self::ListGet => true,
+            Self::ListGetRange => true,
             Self::ListInsert => true,
             Self::ListLength => true,
             Self::ListRemoveAt => true,
             Self::ListReplace => true,
+            Self::MapEntries => true,
+            Self::MapFromList => true,
+            Self::MapGet => true,
+            Self::MapHasKey => true,
+            Self::MapInsert => true,
+            Self::MapRemove => true,
+            Self::MapSize => true,
+            Self::MapUnion => true,
             Self::Print => false,
+            Self::SetContains => true,
+            Self::SetFromList => true,
+            Self::SetInsert => true,
+            Self::SetItems => true,
+            Self::SetRemove => true,
+            Self::SetSize => true,
+            Self::SetUnion => true,
             Self::StructGet => true,
             Self::StructGetKeys => true,
             Self::StructHasKey => true,
@@ -108,7 +193,12 @@ impl BuiltinFunction {
             Self::TagHasValue => true,
             Self::TagWithoutValue => true,
             Self::TagWithValue => true,
+            Self::TextBuilderAppend => false,
+            Self::TextBuilderAppendInt => false,
+            Self::TextBuilderBuild => false,
+            Self::TextBuilderCreate => false,
             Self::TextCharacters => true,
+            Self::TextCompareTo => true,
             Self::TextConcatenate => true,
             Self::TextContains => true,
             Self::TextEndsWith => true,
@@ -117,9 +207,12 @@ impl BuiltinFunction {
             Self::TextIsEmpty => true,
             Self::TextLength => true,
             Self::TextStartsWith => true,
+            Self::TextToLowercase => true,
+            Self::TextToUppercase => true,
             Self::TextTrimEnd => true,
             Self::TextTrimStart => true,
             Self::ToDebugText => true,
+            Self::Try => false,
             Self::TypeOf => true,
         }
     }
@@ -127,7 +220,27 @@ impl BuiltinFunction {
     #[must_use]
     pub const fn num_parameters(&self) -> usize {
         match self {
+            Self::BytesConcatenate => 2,
+            Self::BytesFromList => 1,
+            Self::BytesFromText => 1,
+            Self::BytesGetRange => 3,
+            Self::BytesLength => 1,
+            Self::BytesToList => 1,
+            Self::BytesToText => 1,
+            Self::CellCreate => 1,
+            Self::CellGet => 1,
+            Self::CellSet => 2,
+            Self::DebugAssert => 2,
+            Self::DebugLog => 1,
             Self::Equals => 2,
+            Self::FractionAdd => 2,
+            Self::FractionCompareTo => 2,
+            Self::FractionCreate => 2,
+            Self::FractionDenominator => 1,
+            Self::FractionDivide => 2,
+            Self::FractionMultiply => 2,
+            Self::FractionNumerator => 1,
+            Self::FractionSubtract => 2,
             Self::FunctionRun => 1,
             Self::GetArgumentCount => 1,
             Self::IfElse => 3,
@@ -145,13 +258,30 @@ impl BuiltinFunction {
             Self::IntShiftLeft => 2,
             Self::IntShiftRight => 2,
             Self::IntSubtract => 2,
+            Self::ListConcatenate => 2,
             Self::ListFilled => 2,
             Self::ListGet => 2,
+            Self::ListGetRange => 3,
             Self::ListInsert => 3,
             Self::ListLength => 1,
             Self::ListRemoveAt => 2,
             Self::ListReplace => 3,
+            Self::MapEntries => 1,
+            Self::MapFromList => 1,
+            Self::MapGet => 2,
+            Self::MapHasKey => 2,
+            Self::MapInsert => 3,
+            Self::MapRemove => 2,
+            Self::MapSize => 1,
+            Self::MapUnion => 2,
             Self::Print => 1,
+            Self::SetContains => 2,
+            Self::SetFromList => 1,
+            Self::SetInsert => 2,
+            Self::SetItems => 1,
+            Self::SetRemove => 2,
+            Self::SetSize => 1,
+            Self::SetUnion => 2,
             Self::StructGet => 2,
             Self::StructGetKeys => 1,
             Self::StructHasKey => 2,
@@ -159,7 +289,12 @@ impl BuiltinFunction {
             Self::TagHasValue => 1,
             Self::TagWithoutValue => 1,
             Self::TagWithValue => 2,
+            Self::TextBuilderAppend => 2,
+            Self::TextBuilderAppendInt => 2,
+            Self::TextBuilderBuild => 1,
+            Self::TextBuilderCreate => 1,
             Self::TextCharacters => 1,
+            Self::TextCompareTo => 2,
             Self::TextConcatenate => 2,
             Self::TextContains => 2,
             Self::TextEndsWith => 2,
@@ -168,12 +303,25 @@ impl BuiltinFunction {
             Self::TextIsEmpty => 1,
             Self::TextLength => 1,
             Self::TextStartsWith => 2,
+            Self::TextToLowercase => 1,
+            Self::TextToUppercase => 1,
             Self::TextTrimEnd => 1,
             Self::TextTrimStart => 1,
             Self::ToDebugText => 1,
+            Self::Try => 1,
             Self::TypeOf => 1,
         }
     }
+
+    /// Debug builtins only run if debug assertions are enabled (see
+    /// [`TracingConfig::debug_assertions`]). Otherwise, they're lowered to a
+    /// function that does nothing so that the optimizer removes their calls.
+    ///
+    /// [`TracingConfig::debug_assertions`]: crate::TracingConfig::debug_assertions
+    #[must_use]
+    pub const fn is_debug_only(&self) -> bool {
+        matches!(self, Self::DebugAssert | Self::DebugLog)
+    }
 }
 
 impl_display_via_richir!(BuiltinFunction);
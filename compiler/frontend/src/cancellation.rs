@@ -0,0 +1,77 @@
+//! Cooperative cancellation of long-running queries.
+//!
+//! Tools like the language server keep analyzing code while the user is still
+//! typing. Optimizing a big module can take a while, and the result is useless
+//! once the module changed again. Therefore, databases can provide a
+//! [`CancellationToken`]. Expensive queries regularly call
+//! [`CancellationTokenOwner::unwind_if_canceled`], which unwinds with a
+//! [`Canceled`] payload after the token was canceled. Salsa discards the
+//! partial results of unwound queries, so they are simply recomputed the next
+//! time they're needed.
+
+use std::{
+    panic::{self, UnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A flag shared between whoever starts work and whoever wants to abort it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+impl CancellationToken {
+    /// Makes queries using this token unwind at their next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Allows new work to run to completion.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+    #[must_use]
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub trait CancellationTokenOwner {
+    /// Databases whose queries can't be canceled return `None`.
+    #[must_use]
+    fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        None
+    }
+
+    #[must_use]
+    fn is_canceled(&self) -> bool {
+        self.get_cancellation_token()
+            .is_some_and(CancellationToken::is_canceled)
+    }
+    /// Aborts the current query by unwinding with a [`Canceled`] payload if
+    /// the token was canceled.
+    fn unwind_if_canceled(&self) {
+        if self.is_canceled() {
+            Canceled::throw();
+        }
+    }
+}
+
+/// The panic payload of canceled queries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Canceled;
+impl Canceled {
+    pub fn throw() -> ! {
+        // We don't use `panic!(…)` since that would run the panic hook, which
+        // prints a message and possibly a backtrace.
+        panic::resume_unwind(Box::new(Self))
+    }
+
+    /// Runs `f` and returns `Err(Canceled)` if it got canceled. Other panics
+    /// are propagated.
+    pub fn catch<T>(f: impl FnOnce() -> T + UnwindSafe) -> Result<T, Self> {
+        panic::catch_unwind(f).map_err(|payload| match payload.downcast::<Self>() {
+            Ok(canceled) => *canceled,
+            Err(payload) => panic::resume_unwind(payload),
+        })
+    }
+}
@@ -31,11 +31,24 @@ fn comment_rcst(db: &dyn CommentStringToRcst, id: hir::Id) -> Arc<Vec<Rcst>> {
         match db.find_cst(id.module, cst_id).kind {
             cst::CstKind::Assignment {
                 box assignment_sign,
+                body,
                 ..
-            } => match assignment_sign.kind {
-                cst::CstKind::TrailingWhitespace { whitespace, .. } => whitespace,
-                _ => vec![],
-            },
+            } => {
+                let mut comments_and_newlines = match assignment_sign.kind {
+                    cst::CstKind::TrailingWhitespace { whitespace, .. } => whitespace,
+                    _ => vec![],
+                };
+                // Comments in the following lines are part of the body.
+                comments_and_newlines.extend(body.into_iter().take_while(|it| {
+                    matches!(
+                        it.kind,
+                        cst::CstKind::Whitespace(_)
+                            | cst::CstKind::Newline(_)
+                            | cst::CstKind::Comment { .. }
+                    )
+                }));
+                comments_and_newlines
+            }
             _ => panic!(
                 "Tried to get the comment RCST for something other than a module or assignment."
             ),
@@ -47,10 +60,13 @@ fn comment_rcst(db: &dyn CommentStringToRcst, id: hir::Id) -> Arc<Vec<Rcst>> {
             cst::CstKind::Comment { comment, .. } => {
                 Some(comment.strip_prefix(' ').unwrap_or(comment))
             }
-            cst::CstKind::Newline(_) => None,
+            cst::CstKind::Whitespace(_) | cst::CstKind::Newline(_) => None,
             _ => unreachable!(),
         })
         .collect_vec();
+    if comment_lines.is_empty() {
+        return Arc::new(vec![]);
+    }
 
     let (remaining, rcsts) = parse::blocks(comment_lines, 0).unwrap();
     assert!(remaining.is_empty());
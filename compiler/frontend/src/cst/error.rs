@@ -1,4 +1,10 @@
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+use enumset::{EnumSet, EnumSetType};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use strum_macros::AsRefStr;
+
+#[derive(AsRefStr, Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[strum(serialize_all = "kebab-case")]
 pub enum CstError {
     BinaryBarMissesRight,
     CurlyBraceNotClosed,
@@ -9,6 +15,7 @@ pub enum CstError {
     MatchCaseMissesArrow,
     MatchCaseMissesBody,
     MatchMissesCases,
+    NamedArgumentMissesValue,
     OpeningParenthesisMissesExpression,
     OrPatternMissesRight,
     ParenthesisNotClosed,
@@ -27,3 +34,68 @@ pub enum CstError {
     WeirdWhitespace,
     WeirdWhitespaceInIndentation,
 }
+impl CstError {
+    /// What the parser expected instead of the erroneous input.
+    #[must_use]
+    pub fn expected(self) -> EnumSet<Expectation> {
+        match self {
+            Self::BinaryBarMissesRight
+            | Self::ListItemMissesValue
+            | Self::MatchCaseMissesBody
+            | Self::NamedArgumentMissesValue
+            | Self::OpeningParenthesisMissesExpression
+            | Self::StructFieldMissesKey
+            | Self::StructFieldMissesValue
+            | Self::TextInterpolationMissesExpression
+            | Self::UnexpectedCharacters
+            | Self::UnparsedRest => Expectation::Expression.into(),
+            Self::CurlyBraceNotClosed | Self::TextInterpolationNotClosed => {
+                Expectation::ClosingCurlyBrace.into()
+            }
+            Self::IntContainsNonDigits => Expectation::Digit.into(),
+            Self::ListNotClosed => Expectation::Comma | Expectation::ClosingParenthesis,
+            Self::MatchCaseMissesArrow => Expectation::Arrow.into(),
+            Self::MatchMissesCases | Self::OrPatternMissesRight => Expectation::Pattern.into(),
+            Self::ParenthesisNotClosed => Expectation::ClosingParenthesis.into(),
+            Self::StructFieldMissesColon => Expectation::Colon.into(),
+            Self::StructNotClosed => Expectation::Comma | Expectation::ClosingBracket,
+            Self::TextNotClosed => Expectation::DoubleQuote.into(),
+            Self::IdentifierContainsNonAlphanumericAscii
+            | Self::SymbolContainsNonAlphanumericAscii
+            | Self::TextNotSufficientlyIndented
+            | Self::TooMuchWhitespace
+            | Self::WeirdWhitespace
+            | Self::WeirdWhitespaceInIndentation => EnumSet::empty(),
+        }
+    }
+}
+
+#[derive(Debug, EnumSetType)]
+pub enum Expectation {
+    Arrow,
+    ClosingBracket,
+    ClosingCurlyBrace,
+    ClosingParenthesis,
+    Colon,
+    Comma,
+    Digit,
+    DoubleQuote,
+    Expression,
+    Pattern,
+}
+impl Display for Expectation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Arrow => write!(f, "`->`"),
+            Self::ClosingBracket => write!(f, "`]`"),
+            Self::ClosingCurlyBrace => write!(f, "`}}`"),
+            Self::ClosingParenthesis => write!(f, "`)`"),
+            Self::Colon => write!(f, "`:`"),
+            Self::Comma => write!(f, "`,`"),
+            Self::Digit => write!(f, "a digit"),
+            Self::DoubleQuote => write!(f, "`\"`"),
+            Self::Expression => write!(f, "an expression"),
+            Self::Pattern => write!(f, "a pattern"),
+        }
+    }
+}
@@ -3,9 +3,10 @@ use crate::{
     rich_ir::{RichIrBuilder, ToRichIr, TokenType},
 };
 use enumset::EnumSet;
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display, Formatter};
 
-#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Id(pub usize);
 
 impl_countable_id!(Id);
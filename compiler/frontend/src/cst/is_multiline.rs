@@ -31,6 +31,7 @@ impl<D> IsMultiline for CstKind<D> {
             Self::DoubleQuote => false,
             Self::Percent => false,
             Self::Octothorpe => false,
+            Self::RawTextPrefix => false,
             Self::Whitespace(_) => false,
             Self::Newline(_) => true,
             Self::Comment { .. } => false,
@@ -66,6 +67,9 @@ impl<D> IsMultiline for CstKind<D> {
                 receiver,
                 arguments,
             } => receiver.is_multiline() || arguments.is_multiline(),
+            Self::NamedArgument { name, colon, value } => {
+                name.is_multiline() || colon.is_multiline() || value.is_multiline()
+            }
             Self::List {
                 opening_parenthesis,
                 items,
@@ -2,10 +2,11 @@ use super::{Cst, CstData, CstError};
 use crate::rich_ir::{RichIrBuilder, ToRichIr, TokenType};
 use enumset::EnumSet;
 use num_bigint::{BigInt, BigUint};
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
 use strum_macros::EnumIs;
 
-#[derive(Clone, Debug, EnumIs, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, EnumIs, Eq, Hash, PartialEq, Serialize)]
 pub enum CstKind<D = CstData> {
     EqualsSign,         // =
     Comma,              // ,
@@ -24,6 +25,7 @@ pub enum CstKind<D = CstData> {
     DoubleQuote,        // "
     Percent,            // %
     Octothorpe,         // #
+    RawTextPrefix,      // r
     Whitespace(String), // contains only non-multiline whitespace
     Newline(String), // the associated `String` because some systems (such as Windows) have weird newlines
     Comment {
@@ -42,6 +44,7 @@ pub enum CstKind<D = CstData> {
         string: String,
     },
     OpeningText {
+        raw_prefix: Option<Box<Cst<D>>>,
         opening_single_quotes: Vec<Cst<D>>,
         opening_double_quote: Box<Cst<D>>,
     },
@@ -75,6 +78,11 @@ pub enum CstKind<D = CstData> {
         receiver: Box<Cst<D>>,
         arguments: Vec<Cst<D>>,
     },
+    NamedArgument {
+        name: Box<Cst<D>>,
+        colon: Box<Cst<D>>,
+        value: Box<Cst<D>>,
+    },
     List {
         opening_parenthesis: Box<Cst<D>>,
         items: Vec<Cst<D>>,
@@ -125,7 +133,7 @@ pub enum CstKind<D = CstData> {
         error: CstError,
     },
 }
-#[derive(Clone, Debug, EnumIs, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, EnumIs, Eq, Hash, PartialEq, Serialize)]
 pub enum IntRadix {
     Binary,
     Hexadecimal,
@@ -162,6 +170,7 @@ impl<D> CstKind<D> {
             | Self::DoubleQuote
             | Self::Percent
             | Self::Octothorpe
+            | Self::RawTextPrefix
             | Self::Whitespace(_)
             | Self::Newline(_) => vec![],
             Self::Comment { octothorpe, .. } => vec![octothorpe],
@@ -172,10 +181,12 @@ impl<D> CstKind<D> {
             }
             Self::Identifier(_) | Self::Symbol(_) | Self::Int { .. } => vec![],
             Self::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
             } => {
                 let mut children = vec![];
+                children.extend(raw_prefix.as_deref());
                 children.extend(opening_single_quotes);
                 children.push(opening_double_quote);
                 children
@@ -234,6 +245,9 @@ impl<D> CstKind<D> {
                 children.extend(arguments);
                 children
             }
+            Self::NamedArgument { name, colon, value } => {
+                vec![name.as_ref(), colon.as_ref(), value.as_ref()]
+            }
             Self::List {
                 opening_parenthesis,
                 items,
@@ -348,6 +362,7 @@ impl<D> Display for CstKind<D> {
             Self::DoubleQuote => '"'.fmt(f),
             Self::Percent => '%'.fmt(f),
             Self::Octothorpe => '#'.fmt(f),
+            Self::RawTextPrefix => 'r'.fmt(f),
             Self::Whitespace(whitespace) => whitespace.fmt(f),
             Self::Newline(newline) => newline.fmt(f),
             Self::Comment {
@@ -377,9 +392,13 @@ impl<D> Display for CstKind<D> {
                 string.fmt(f)
             }
             Self::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
             } => {
+                if let Some(raw_prefix) = raw_prefix {
+                    raw_prefix.fmt(f)?;
+                }
                 for opening_single_quote in opening_single_quotes {
                     opening_single_quote.fmt(f)?;
                 }
@@ -444,6 +463,11 @@ impl<D> Display for CstKind<D> {
                 }
                 Ok(())
             }
+            Self::NamedArgument { name, colon, value } => {
+                name.fmt(f)?;
+                colon.fmt(f)?;
+                value.fmt(f)
+            }
             Self::List {
                 opening_parenthesis,
                 items,
@@ -611,6 +635,9 @@ where
             Self::Octothorpe => {
                 builder.push_simple("Octothorpe");
             }
+            Self::RawTextPrefix => {
+                builder.push_simple("RawTextPrefix");
+            }
             Self::Whitespace(whitespace) => {
                 builder.push_simple(format!("Whitespace \"{whitespace}\""));
             }
@@ -675,10 +702,15 @@ where
                     .push_reference(BigInt::from(value.clone()), start..builder.current_offset());
             }
             Self::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
             } => {
                 builder.push_cst_kind("OpeningText", |builder| {
+                    if let Some(raw_prefix) = raw_prefix {
+                        builder.push_cst_kind_property("raw_prefix", raw_prefix);
+                    }
+
                     builder.push_cst_kind_property_name("opening_single_quotes");
                     builder.push_indented_foldable(|builder| {
                         for opening_single_quote in opening_single_quotes {
@@ -800,6 +832,13 @@ where
                     });
                 });
             }
+            Self::NamedArgument { name, colon, value } => {
+                builder.push_cst_kind("NamedArgument", |builder| {
+                    builder.push_cst_kind_property("name", name);
+                    builder.push_cst_kind_property("colon", colon);
+                    builder.push_cst_kind_property("value", value);
+                });
+            }
             Self::List {
                 opening_parenthesis,
                 items,
@@ -1,6 +1,12 @@
-use self::tree_with_ids::TreeWithIds;
+use self::skeleton::module_skeleton;
+pub(crate) use self::tree_with_ids::TreeWithIds;
 pub use self::{
-    error::CstError, id::Id, is_multiline::IsMultiline, kind::CstKind, kind::IntRadix,
+    error::{CstError, Expectation},
+    id::Id,
+    is_multiline::IsMultiline,
+    kind::CstKind,
+    kind::IntRadix,
+    skeleton::{Definition, DefinitionKind},
     unwrap_whitespace_and_comment::UnwrapWhitespaceAndComment,
 };
 use crate::{
@@ -10,26 +16,29 @@ use crate::{
     rich_ir::{RichIrBuilder, ToRichIr},
 };
 use derive_more::Deref;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     ops::Range,
+    sync::Arc,
 };
 
 mod error;
 mod id;
 mod is_multiline;
 mod kind;
+mod skeleton;
 mod tree_with_ids;
 mod unwrap_whitespace_and_comment;
 
-#[derive(Clone, Debug, Deref, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deref, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Cst<D = CstData> {
     pub data: D,
     #[deref]
     pub kind: CstKind<D>,
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct CstData {
     pub id: Id,
     pub span: Range<Offset>,
@@ -49,6 +58,34 @@ impl Cst {
             _ => self.data.span.clone(),
         }
     }
+
+    /// Returns the span of the whole expression, even if it spans multiple
+    /// lines.
+    ///
+    /// Unlike `data.span`, this excludes whitespace and comments after the
+    /// expression. For example, the span of a call whose arguments are on the
+    /// following lines ends after the last argument and not at the start of the
+    /// next expression.
+    #[must_use]
+    pub fn logical_span(&self) -> Range<Offset> {
+        let start = self.data.span.start;
+        let end = self.logical_end().unwrap_or(self.data.span.end);
+        start..end
+    }
+    fn logical_end(&self) -> Option<Offset> {
+        match &self.kind {
+            CstKind::Whitespace(_) | CstKind::Newline(_) | CstKind::Comment { .. } => None,
+            CstKind::TrailingWhitespace { child, .. } => child.logical_end(),
+            kind => {
+                let children = kind.children();
+                if children.is_empty() {
+                    Some(self.data.span.end)
+                } else {
+                    children.iter().rev().find_map(|child| child.logical_end())
+                }
+            }
+        }
+    }
 }
 impl Display for Cst {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
@@ -72,6 +109,7 @@ impl ToRichIr for Cst {
 pub trait CstDb: RcstToCst {
     fn find_cst(&self, module: Module, id: Id) -> Cst;
     fn find_cst_by_offset(&self, module: Module, offset: Offset) -> Cst;
+    fn module_skeleton(&self, module: Module) -> Arc<Vec<Definition>>;
 }
 
 fn find_cst(db: &dyn CstDb, module: Module, id: Id) -> Cst {
@@ -0,0 +1,228 @@
+use super::{Cst, CstDb, CstKind};
+use crate::{module::Module, position::Offset};
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, sync::Arc};
+
+/// A top-level assignment of a module.
+///
+/// Unlike the HIR, the skeleton only looks at the top level of the CST without
+/// lowering the bodies of definitions. This keeps outlines of huge modules
+/// fast.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub is_public: bool,
+    /// The whole assignment.
+    pub span: Range<Offset>,
+    /// Only the assigned identifier.
+    pub name_span: Range<Offset>,
+    /// The first line of the doc comment.
+    pub doc_summary: Option<String>,
+}
+/// What a definition looks like syntactically. References are not followed,
+/// so `foo = bar` is a [`DefinitionKind::Variable`] even if `bar` is a
+/// function.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum DefinitionKind {
+    Function,
+    Constant,
+    Symbol,
+    List,
+    Struct,
+    Variable,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn module_skeleton(db: &dyn CstDb, module: Module) -> Arc<Vec<Definition>> {
+    let Ok(csts) = db.cst(module) else {
+        return Arc::default();
+    };
+    let mut definitions = vec![];
+    for cst in csts.iter() {
+        let cst = unwrap_trailing_whitespace(cst);
+        let CstKind::Assignment {
+            left,
+            assignment_sign,
+            body,
+        } = &cst.kind
+        else {
+            continue;
+        };
+        let is_public = unwrap_trailing_whitespace(assignment_sign)
+            .kind
+            .is_colon_equals_sign();
+        let doc_summary = doc_summary(assignment_sign, body);
+
+        let left = unwrap_trailing_whitespace(left);
+        if let CstKind::Call { receiver, .. } = &left.kind
+            && let receiver = unwrap_trailing_whitespace(receiver)
+            && let CstKind::Identifier(name) = &receiver.kind
+        {
+            definitions.push(Definition {
+                name: name.clone(),
+                kind: DefinitionKind::Function,
+                is_public,
+                span: cst.data.span.clone(),
+                name_span: receiver.data.span.clone(),
+                doc_summary,
+            });
+        } else if let CstKind::Identifier(name) = &left.kind {
+            definitions.push(Definition {
+                name: name.clone(),
+                kind: body_kind(body),
+                is_public,
+                span: cst.data.span.clone(),
+                name_span: left.data.span.clone(),
+                doc_summary,
+            });
+        } else {
+            let mut identifiers = vec![];
+            collect_pattern_identifiers(left, &mut identifiers);
+            definitions.extend(identifiers.into_iter().map(|(name, span)| Definition {
+                name,
+                kind: DefinitionKind::Variable,
+                is_public,
+                span: span.clone(),
+                name_span: span,
+                doc_summary: doc_summary.clone(),
+            }));
+        }
+    }
+    Arc::new(definitions)
+}
+
+const fn unwrap_trailing_whitespace(mut cst: &Cst) -> &Cst {
+    while let CstKind::TrailingWhitespace { child, .. } = &cst.kind {
+        cst = child;
+    }
+    cst
+}
+
+/// Doc comments are the comments at the start of a definition's body, usually
+/// in the line after the assignment sign.
+fn doc_summary(assignment_sign: &Cst, body: &[Cst]) -> Option<String> {
+    let whitespace = match &assignment_sign.kind {
+        CstKind::TrailingWhitespace { whitespace, .. } => whitespace.as_slice(),
+        _ => &[],
+    };
+    whitespace
+        .iter()
+        .chain(
+            body.iter()
+                .take_while(|it| it.kind.is_whitespace_or_comment()),
+        )
+        .find_map(|it| match &it.kind {
+            CstKind::Comment { comment, .. } => Some(comment.trim()),
+            _ => None,
+        })
+        .filter(|it| !it.is_empty())
+        .map(ToString::to_string)
+}
+
+fn body_kind(body: &[Cst]) -> DefinitionKind {
+    let mut expressions = body.iter().map(unwrap_trailing_whitespace).filter(|it| {
+        !matches!(
+            it.kind,
+            CstKind::Whitespace(_) | CstKind::Newline(_) | CstKind::Comment { .. },
+        )
+    });
+    let (Some(expression), None) = (expressions.next(), expressions.next()) else {
+        return DefinitionKind::Variable;
+    };
+    match expression.kind {
+        CstKind::Function { .. } => DefinitionKind::Function,
+        CstKind::Int { .. } | CstKind::Text { .. } => DefinitionKind::Constant,
+        CstKind::Symbol(_) => DefinitionKind::Symbol,
+        CstKind::List { .. } => DefinitionKind::List,
+        CstKind::Struct { .. } => DefinitionKind::Struct,
+        _ => DefinitionKind::Variable,
+    }
+}
+
+fn collect_pattern_identifiers(pattern: &Cst, identifiers: &mut Vec<(String, Range<Offset>)>) {
+    match &pattern.kind {
+        CstKind::Identifier(name) => identifiers.push((name.clone(), pattern.data.span.clone())),
+        CstKind::TrailingWhitespace { child, .. } => {
+            collect_pattern_identifiers(child, identifiers);
+        }
+        CstKind::Parenthesized { inner, .. } => collect_pattern_identifiers(inner, identifiers),
+        // Both sides of an or-pattern bind the same identifiers.
+        CstKind::BinaryBar { left, .. } => collect_pattern_identifiers(left, identifiers),
+        CstKind::Call { arguments, .. } => {
+            for argument in arguments {
+                collect_pattern_identifiers(argument, identifiers);
+            }
+        }
+        CstKind::List { items, .. } => {
+            for item in items {
+                collect_pattern_identifiers(item, identifiers);
+            }
+        }
+        CstKind::ListItem { value, .. } | CstKind::StructField { value, .. } => {
+            collect_pattern_identifiers(value, identifiers);
+        }
+        CstKind::Struct { fields, .. } => {
+            for field in fields {
+                collect_pattern_identifiers(field, identifiers);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cst::{CstDb, DefinitionKind},
+        module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase},
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_top_level_definitions() {
+        let mut db = TestDatabase::default();
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["foo".to_string()],
+            ModuleKind::Code,
+        );
+        let source = "foo a b = a\nbar := 42\n[baz, Qux: qux] = use \"Core\"\nmain := { env ->\n  inner = 1\n}\n";
+        db.did_open_module(&module, source.as_bytes().to_vec());
+
+        let definitions = db
+            .module_skeleton(module)
+            .iter()
+            .map(|it| (it.name.clone(), it.kind, it.is_public))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            definitions,
+            [
+                ("foo".to_string(), DefinitionKind::Function, false),
+                ("bar".to_string(), DefinitionKind::Constant, true),
+                ("baz".to_string(), DefinitionKind::Variable, false),
+                ("qux".to_string(), DefinitionKind::Variable, false),
+                ("main".to_string(), DefinitionKind::Function, true),
+            ],
+        );
+    }
+
+    #[test]
+    fn finds_doc_summaries() {
+        let mut db = TestDatabase::default();
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["foo".to_string()],
+            ModuleKind::Code,
+        );
+        let source = "foo a :=\n  # Returns `a`.\n  # More details.\n  a\nbar = 1\n";
+        db.did_open_module(&module, source.as_bytes().to_vec());
+
+        let summaries = db
+            .module_skeleton(module)
+            .iter()
+            .map(|it| it.doc_summary.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(summaries, [Some("Returns `a`.".to_string()), None]);
+    }
+}
@@ -35,6 +35,7 @@ impl TreeWithIds for Cst {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::RawTextPrefix
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => None,
             CstKind::Comment {
@@ -52,10 +53,13 @@ impl TreeWithIds for Cst {
                 string: _,
             } => None,
             CstKind::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
-            } => opening_single_quotes
-                .find(id)
+            } => raw_prefix
+                .as_ref()
+                .and_then(|raw_prefix| raw_prefix.find(id))
+                .or_else(|| opening_single_quotes.find(id))
                 .or_else(|| opening_double_quote.find(id)),
             CstKind::ClosingText {
                 closing_double_quote,
@@ -96,6 +100,10 @@ impl TreeWithIds for Cst {
                 receiver,
                 arguments,
             } => receiver.find(id).or_else(|| arguments.find(id)),
+            CstKind::NamedArgument { name, colon, value } => name
+                .find(id)
+                .or_else(|| colon.find(id))
+                .or_else(|| value.find(id)),
             CstKind::List {
                 opening_parenthesis,
                 items,
@@ -194,6 +202,7 @@ impl TreeWithIds for Cst {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::RawTextPrefix
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => (None, false),
             CstKind::Comment {
@@ -232,6 +241,7 @@ impl TreeWithIds for Cst {
                 }
             }
             CstKind::OpeningText {
+                raw_prefix: _,
                 opening_single_quotes: _,
                 opening_double_quote: _,
             }
@@ -266,6 +276,12 @@ impl TreeWithIds for Cst {
                     .or_else(|| arguments.find_by_offset(offset)),
                 false,
             ),
+            CstKind::NamedArgument { name, colon, value } => (
+                name.find_by_offset(offset)
+                    .or_else(|| colon.find_by_offset(offset))
+                    .or_else(|| value.find_by_offset(offset)),
+                false,
+            ),
             CstKind::List {
                 opening_parenthesis,
                 items,
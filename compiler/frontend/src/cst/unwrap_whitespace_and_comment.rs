@@ -25,6 +25,7 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::RawTextPrefix
             | CstKind::Whitespace(_)
             | CstKind::Newline(_)
             | CstKind::Comment { .. }) => kind.clone(),
@@ -35,9 +36,13 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
                 kind.clone()
             }
             CstKind::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
             } => CstKind::OpeningText {
+                raw_prefix: raw_prefix
+                    .as_ref()
+                    .map(UnwrapWhitespaceAndComment::unwrap_whitespace_and_comment),
                 opening_single_quotes: opening_single_quotes.unwrap_whitespace_and_comment(),
                 opening_double_quote: opening_double_quote.unwrap_whitespace_and_comment(),
             },
@@ -88,6 +93,11 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
                 receiver: receiver.unwrap_whitespace_and_comment(),
                 arguments: arguments.unwrap_whitespace_and_comment(),
             },
+            CstKind::NamedArgument { name, colon, value } => CstKind::NamedArgument {
+                name: name.unwrap_whitespace_and_comment(),
+                colon: colon.unwrap_whitespace_and_comment(),
+                value: value.unwrap_whitespace_and_comment(),
+            },
             CstKind::List {
                 opening_parenthesis,
                 items,
@@ -4,12 +4,13 @@ use rustc_hash::{FxHashMap, FxHashSet};
 use crate::{
     ast::{
         self, Assignment, AssignmentBody, Ast, AstError, AstKind, AstString, Call, CollectErrors,
-        Function, Identifier, Int, List, Match, MatchCase, OrPattern, Struct, StructAccess, Symbol,
-        Text, TextPart,
+        Function, Identifier, Int, List, Match, MatchCase, NamedArgument, OrPattern, Struct,
+        StructAccess, Symbol, Text, TextPart,
     },
-    cst::{self, Cst, CstDb, CstKind, UnwrapWhitespaceAndComment},
-    error::{CompilerError, CompilerErrorPayload},
+    cst::{self, Cst, CstDb, CstKind, TreeWithIds, UnwrapWhitespaceAndComment},
+    error::{CompilerError, CompilerErrorPayload, SuggestedEdit},
     module::Module,
+    persistent_cache::{self, CacheKey},
     position::Offset,
     rcst_to_cst::RcstToCst,
     string_to_rcst::ModuleError,
@@ -25,6 +26,8 @@ pub trait CstToAst: CstDb + RcstToCst {
     fn ast_id_to_span(&self, id: &ast::Id) -> Option<Range<Offset>>;
     #[salsa::transparent]
     fn ast_id_to_display_span(&self, id: &ast::Id) -> Option<Range<Offset>>;
+    #[salsa::transparent]
+    fn ast_id_to_logical_span(&self, id: &ast::Id) -> Option<Range<Offset>>;
 
     #[salsa::transparent]
     fn cst_to_ast_ids(&self, module: Module, id: cst::Id) -> Vec<ast::Id>;
@@ -46,6 +49,10 @@ fn ast_id_to_display_span(db: &dyn CstToAst, id: &ast::Id) -> Option<Range<Offse
     let cst_id = db.ast_to_cst_id(id)?;
     Some(db.find_cst(id.module.clone(), cst_id).display_span())
 }
+fn ast_id_to_logical_span(db: &dyn CstToAst, id: &ast::Id) -> Option<Range<Offset>> {
+    let cst_id = db.ast_to_cst_id(id)?;
+    Some(db.find_cst(id.module.clone(), cst_id).logical_span())
+}
 
 fn cst_to_ast_ids(db: &dyn CstToAst, module: Module, id: cst::Id) -> Vec<ast::Id> {
     if let Ok((_, ast_to_cst_id_mapping)) = db.ast(module) {
@@ -60,14 +67,23 @@ fn cst_to_ast_ids(db: &dyn CstToAst, module: Module, id: cst::Id) -> Vec<ast::Id
     }
 }
 
+#[allow(clippy::needless_pass_by_value)]
 fn ast(db: &dyn CstToAst, module: Module) -> AstResult {
-    let mut context = LoweringContext::new(module.clone());
-
-    db.cst(module).map(|cst| {
-        let cst = cst.unwrap_whitespace_and_comment();
-        let asts = context.lower_csts(&cst);
-        (Arc::new(asts), Arc::new(context.id_mapping))
-    })
+    let content = db.get_module_content(module.clone());
+    let (asts, id_mapping) = persistent_cache::get_or_compute(
+        db.get_persistent_cache(),
+        &module,
+        || CacheKey::new("ast").with_module_content(&module, content.as_deref().map(Vec::as_slice)),
+        || {
+            let mut context = LoweringContext::new(module.clone());
+            db.cst(module.clone()).map(|cst| {
+                let cst = cst.unwrap_whitespace_and_comment();
+                let asts = context.lower_csts(&cst);
+                (asts, context.id_mapping)
+            })
+        },
+    )?;
+    Ok((Arc::new(asts), Arc::new(id_mapping)))
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -113,7 +129,8 @@ impl LoweringContext {
             | CstKind::SingleQuote
             | CstKind::DoubleQuote
             | CstKind::Percent
-            | CstKind::Octothorpe => self.create_error_ast(
+            | CstKind::Octothorpe
+            | CstKind::RawTextPrefix => self.create_error_ast(
                 cst,
                 vec![self.create_error(cst, AstError::UnexpectedPunctuation)],
             ),
@@ -144,6 +161,10 @@ impl LoweringContext {
 
                 let opening_single_quote_count = match &opening.kind {
                     CstKind::OpeningText {
+                        raw_prefix: None | Some(box Cst {
+                            kind: CstKind::RawTextPrefix,
+                            ..
+                        }),
                         opening_single_quotes,
                         opening_double_quote: box Cst {
                             kind: CstKind::DoubleQuote,
@@ -152,7 +173,7 @@ impl LoweringContext {
                     } if opening_single_quotes
                         .iter()
                         .all(|single_quote| single_quote.kind.is_single_quote()) => opening_single_quotes.len(),
-                    _ => panic!("Text needs to start with any number of single quotes followed by a double quote, but started with {opening}.")
+                    _ => panic!("Text needs to start with an optional raw prefix and any number of single quotes followed by a double quote, but started with {opening}.")
                 };
 
                 let lowered_parts = parts.iter().filter_map(|part| {
@@ -218,7 +239,11 @@ impl LoweringContext {
                         .all(|single_quote| single_quote.kind.is_single_quote())
                         && opening_single_quote_count == closing_single_quotes.len()
                 ) {
-                    errors.push(self.create_error(closing, AstError::TextMissesClosingQuote));
+                    errors.push(self.create_missing_closing_error(
+                        closing,
+                        AstError::TextMissesClosingQuote,
+                        &format!("\"{}", "'".repeat(opening_single_quote_count)),
+                    ));
                 }
 
                 self.create_errors_or_ast(cst, errors, Text(lowered_parts))
@@ -271,6 +296,7 @@ impl LoweringContext {
                     LoweringType::Pattern | LoweringType::PatternLiteralPart => {
                         let mut patterns = vec![];
 
+                        let or_pattern = cst;
                         let mut cst = cst;
                         while let Cst {
                             kind: CstKind::BinaryBar { left, bar, right },
@@ -305,21 +331,29 @@ impl LoweringContext {
                                 continue;
                             }
 
-                            let empty_vec = vec![];
-                            let all_captures = captured_identifiers
-                                .iter()
-                                .flat_map(|it| it.get(identifier).unwrap_or(&empty_vec))
-                                .filter_map(|it| self.id_mapping.get(it).copied())
-                                .collect_vec();
-                            errors.push(self.create_error(
+                            let mut error = self.create_error(
                                 left,
                                 AstError::OrPatternIsMissingIdentifiers {
                                     identifier: identifier.clone(),
-                                    number_of_missing_captures:
-                                        number_of_missing_captures.try_into().unwrap(),
-                                    all_captures,
+                                    number_of_missing_captures: number_of_missing_captures
+                                        .try_into()
+                                        .unwrap(),
                                 },
-                            ));
+                            );
+                            for capture in captured_identifiers
+                                .iter()
+                                .filter_map(|it| it.get(identifier))
+                                .flatten()
+                                .filter_map(|it| self.id_mapping.get(it))
+                                .filter_map(|it| or_pattern.find(*it))
+                            {
+                                error = error.with_label(
+                                    self.module.clone(),
+                                    capture.display_span(),
+                                    "The identifier is bound here.",
+                                );
+                            }
+                            errors.push(error);
                         }
 
                         self.create_errors_or_ast(cst, errors, OrPattern(patterns))
@@ -350,9 +384,10 @@ impl LoweringContext {
 
                 let mut errors = vec![];
                 if !closing_parenthesis.kind.is_closing_parenthesis() {
-                    errors.push(self.create_error(
+                    errors.push(self.create_missing_closing_error(
                         closing_parenthesis,
                         AstError::ParenthesizedMissesClosingParenthesis,
+                        ")",
                     ));
                 }
 
@@ -400,6 +435,13 @@ impl LoweringContext {
                     },
                 )
             }
+            CstKind::NamedArgument { name, colon, value } => {
+                if lowering_type != LoweringType::Expression {
+                    return self.create_ast_for_invalid_expression_in_pattern(cst);
+                };
+
+                self.lower_named_argument(cst, name, colon, value)
+            }
             CstKind::List {
                 opening_parenthesis,
                 items,
@@ -444,12 +486,11 @@ impl LoweringContext {
                 }
 
                 if !closing_parenthesis.kind.is_closing_parenthesis() {
-                    errors.push(
-                        self.create_error(
-                            closing_parenthesis,
-                            AstError::ListMissesClosingParenthesis,
-                        ),
-                    );
+                    errors.push(self.create_missing_closing_error(
+                        closing_parenthesis,
+                        AstError::ListMissesClosingParenthesis,
+                        ")",
+                    ));
                 }
 
                 self.create_errors_or_ast(cst, errors, List(ast_items))
@@ -535,9 +576,11 @@ impl LoweringContext {
                 }
 
                 if !closing_bracket.kind.is_closing_bracket() {
-                    errors.push(
-                        self.create_error(closing_bracket, AstError::StructMissesClosingBrace),
-                    );
+                    errors.push(self.create_missing_closing_error(
+                        closing_bracket,
+                        AstError::StructMissesClosingBrace,
+                        "]",
+                    ));
                 }
 
                 self.create_errors_or_ast(
@@ -634,9 +677,10 @@ impl LoweringContext {
 
                 let mut errors = vec![];
                 if !closing_curly_brace.kind.is_closing_curly_brace() {
-                    errors.push(self.create_error(
+                    errors.push(self.create_missing_closing_error(
                         closing_curly_brace,
                         AstError::FunctionMissesClosingCurlyBrace,
+                        "}",
                     ));
                 }
 
@@ -748,6 +792,32 @@ impl LoweringContext {
         }
     }
 
+    fn lower_named_argument(&mut self, cst: &Cst, name: &Cst, colon: &Cst, value: &Cst) -> Ast {
+        assert!(
+            colon.kind.is_colon(),
+            "Named argument should always have a colon, but instead had {colon}.",
+        );
+
+        let name = match &name.kind {
+            CstKind::Identifier(identifier) => self.create_string(name.data.id, identifier.clone()),
+            CstKind::Error { error, .. } => {
+                return self.create_error_ast(name, vec![self.create_error(name, *error)]);
+            }
+            _ => panic!(
+                "Expected an identifier as the name of a named argument, but found `{name}`.",
+            ),
+        };
+        let value = self.lower_cst(value, LoweringType::Expression);
+
+        self.create_ast(
+            cst.data.id,
+            NamedArgument {
+                name,
+                value: Box::new(value),
+            },
+        )
+    }
+
     fn lower_parameters(&mut self, csts: &[Cst], is_fuzzable: bool) -> Vec<Ast> {
         csts.iter()
             .enumerate()
@@ -762,6 +832,9 @@ impl LoweringContext {
         if let CstKind::Identifier(identifier) = &cst.kind {
             let identifier = self.create_string(cst.data.id, identifier.clone());
             self.create_ast(cst.data.id, Identifier(identifier))
+        } else if let CstKind::NamedArgument { name, colon, value } = &cst.kind {
+            // An optional parameter with a default value, e.g., `count: 3`.
+            self.lower_named_argument(cst, name, colon, value)
         } else {
             let identifier = self.create_string(cst.data.id, format!("<invalid#{index}>"));
             self.create_ast(cst.data.id, Identifier(identifier));
@@ -830,8 +903,27 @@ impl LoweringContext {
     fn create_error(&self, cst: &Cst, error: impl Into<CompilerErrorPayload>) -> CompilerError {
         CompilerError {
             module: self.module.clone(),
-            span: cst.data.span.clone(),
+            span: cst.logical_span(),
             payload: error.into(),
+            labels: vec![],
+            notes: vec![],
+            suggestions: vec![],
         }
     }
+    /// Also suggests inserting the missing token where it's expected.
+    fn create_missing_closing_error(
+        &self,
+        closing: &Cst,
+        error: AstError,
+        closing_text: &str,
+    ) -> CompilerError {
+        let offset = closing.data.span.start;
+        self.create_error(closing, error).with_suggestion(
+            format!("Insert `{closing_text}`"),
+            vec![SuggestedEdit {
+                span: offset..offset,
+                replacement: closing_text.to_string(),
+            }],
+        )
+    }
 }
@@ -1,6 +1,6 @@
 use enumset::EnumSet;
 
-use super::{ast::AstError, cst, cst::CstError, hir::HirError};
+use super::{ast::AstError, cst::CstError, hir::HirError};
 use crate::{
     mir::MirError,
     module::Module,
@@ -10,16 +10,47 @@ use crate::{
 };
 use derive_more::From;
 use itertools::Itertools;
-use std::{fmt::Display, hash::Hash, ops::Range};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Write},
+    hash::Hash,
+    ops::Range,
+};
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
 pub struct CompilerError {
     pub module: Module,
+    /// The primary span, which points at the cause of the error.
     pub span: Range<Offset>,
     pub payload: CompilerErrorPayload,
+    /// Secondary locations that give context, e.g., the whole call when only
+    /// the called function is the primary span or where a conflicting
+    /// definition is. They can also be in other modules.
+    pub labels: Vec<ErrorLabel>,
+    /// Additional explanations that don't belong to a specific location.
+    pub notes: Vec<String>,
+    /// Edits that probably fix the error. Tools can offer them as quick fixes.
+    pub suggestions: Vec<Suggestion>,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+pub struct ErrorLabel {
+    pub module: Module,
+    pub span: Range<Offset>,
+    pub message: String,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+pub struct Suggestion {
+    pub message: String,
+    /// Edits in the module of the error.
+    pub edits: Vec<SuggestedEdit>,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+pub struct SuggestedEdit {
+    pub span: Range<Offset>,
+    pub replacement: String,
 }
 
-#[derive(Clone, Debug, Eq, From, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, From, Hash, PartialEq, Serialize)]
 pub enum CompilerErrorPayload {
     Module(ModuleError),
     Cst(CstError),
@@ -27,17 +58,84 @@ pub enum CompilerErrorPayload {
     Hir(HirError),
     Mir(MirError),
 }
+impl CompilerErrorPayload {
+    /// A stable identifier of the kind of error, e.g., `hir/unknown-reference`.
+    #[must_use]
+    pub fn code(&self) -> String {
+        let (stage, kind) = match self {
+            Self::Module(error) => ("module", error.as_ref()),
+            Self::Cst(error) => ("cst", error.as_ref()),
+            Self::Ast(error) => ("ast", error.as_ref()),
+            Self::Hir(error) => ("hir", error.as_ref()),
+            Self::Mir(error) => ("mir", error.as_ref()),
+        };
+        format!("{stage}/{kind}")
+    }
+}
 impl CompilerError {
     pub fn for_whole_module(module: Module, payload: impl Into<CompilerErrorPayload>) -> Self {
         Self {
             module,
             span: Offset(0)..Offset(0),
             payload: payload.into(),
+            labels: vec![],
+            notes: vec![],
+            suggestions: vec![],
         }
     }
+    #[must_use]
+    pub fn with_label(
+        mut self,
+        module: Module,
+        span: Range<Offset>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.labels.push(ErrorLabel {
+            module,
+            span,
+            message: message.into(),
+        });
+        self
+    }
+    #[must_use]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    #[must_use]
+    pub fn with_suggestion(
+        mut self,
+        message: impl Into<String>,
+        edits: Vec<SuggestedEdit>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            message: message.into(),
+            edits,
+        });
+        self
+    }
+
     pub fn to_string_with_location(&self, db: &impl PositionConversionDb) -> String {
         let range = db.range_to_positions(self.module.clone(), self.span.clone());
-        format!("{}:{}: {}", self.module, range.format(), self.payload)
+        let mut result = format!("{}:{}: {}", self.module, range.format(), self.payload);
+        for label in &self.labels {
+            let range = db.range_to_positions(label.module.clone(), label.span.clone());
+            write!(
+                result,
+                "\n  note: {}:{}: {}",
+                label.module,
+                range.format(),
+                label.message,
+            )
+            .unwrap();
+        }
+        for note in &self.notes {
+            write!(result, "\n  note: {note}").unwrap();
+        }
+        for suggestion in &self.suggestions {
+            write!(result, "\n  help: {}", suggestion.message).unwrap();
+        }
+        result
     }
 }
 impl Display for CompilerErrorPayload {
@@ -49,47 +147,59 @@ impl Display for CompilerErrorPayload {
                 ModuleError::IsNotCandy => "The module is not Candy.".to_string(),
                 ModuleError::IsToolingModule => "The module is a tooling module.".to_string(),
             },
-            Self::Cst(error) => match error {
-                CstError::BinaryBarMissesRight => "There should be a right side after this bar.",
-                CstError::CurlyBraceNotClosed => "The curly brace is not closed.",
-                CstError::IdentifierContainsNonAlphanumericAscii => {
-                    "This identifier contains non-alphanumeric ASCII characters."
-                }
-                CstError::IntContainsNonDigits => {
-                    "This integer contains characters that are not digits."
-                }
-                CstError::ListItemMissesValue => "This list item is missing a value.",
-                CstError::ListNotClosed => "The list is not closed.",
-                CstError::MatchMissesCases => "This match misses cases to match against.",
-                CstError::MatchCaseMissesArrow => "This match case misses an arrow.",
-                CstError::MatchCaseMissesBody => "This match case misses a body to run.",
-                CstError::OpeningParenthesisMissesExpression => {
-                    "Here's an opening parenthesis without an expression after it."
-                }
-                CstError::OrPatternMissesRight => "This or-pattern misses a right-hand side.",
-                CstError::ParenthesisNotClosed => "This parenthesis isn't closed.",
-                CstError::StructFieldMissesColon => "This struct field misses a colon.",
-                CstError::StructFieldMissesKey => "This struct field misses a key.",
-                CstError::StructFieldMissesValue => "This struct field misses a value.",
-                CstError::StructNotClosed => "This struct is not closed.",
-                CstError::SymbolContainsNonAlphanumericAscii => {
-                    "This symbol contains non-alphanumeric ASCII characters."
-                }
-                CstError::TextNotClosed => "This text isn't closed.",
-                CstError::TextNotSufficientlyIndented => "This text isn't sufficiently indented.",
-                CstError::TextInterpolationNotClosed => "This text interpolation isn't closed.",
-                CstError::TextInterpolationMissesExpression => {
-                    "Here's a start of a text interpolation without an expression after it."
-                }
-                CstError::TooMuchWhitespace => "There is too much whitespace here.",
-                CstError::UnexpectedCharacters => "This is an unexpected character.",
-                CstError::UnparsedRest => "The parser couldn't parse this rest.",
-                CstError::WeirdWhitespace => "This is weird whitespace.",
-                CstError::WeirdWhitespaceInIndentation => {
-                    "This is weird whitespace. Make sure to use indent using two spaces."
+            Self::Cst(error) => {
+                let message = match error {
+                    CstError::BinaryBarMissesRight => {
+                        "There should be a right side after this bar."
+                    }
+                    CstError::CurlyBraceNotClosed => "The curly brace is not closed.",
+                    CstError::IdentifierContainsNonAlphanumericAscii => {
+                        "This identifier contains non-alphanumeric ASCII characters."
+                    }
+                    CstError::IntContainsNonDigits => {
+                        "This integer contains characters that are not digits or underscores that don't separate digits."
+                    }
+                    CstError::ListItemMissesValue => "This list item is missing a value.",
+                    CstError::ListNotClosed => "The list is not closed.",
+                    CstError::MatchMissesCases => "This match misses cases to match against.",
+                    CstError::MatchCaseMissesArrow => "This match case misses an arrow.",
+                    CstError::MatchCaseMissesBody => "This match case misses a body to run.",
+                    CstError::NamedArgumentMissesValue => "This named argument misses a value.",
+                    CstError::OpeningParenthesisMissesExpression => {
+                        "Here's an opening parenthesis without an expression after it."
+                    }
+                    CstError::OrPatternMissesRight => "This or-pattern misses a right-hand side.",
+                    CstError::ParenthesisNotClosed => "This parenthesis isn't closed.",
+                    CstError::StructFieldMissesColon => "This struct field misses a colon.",
+                    CstError::StructFieldMissesKey => "This struct field misses a key.",
+                    CstError::StructFieldMissesValue => "This struct field misses a value.",
+                    CstError::StructNotClosed => "This struct is not closed.",
+                    CstError::SymbolContainsNonAlphanumericAscii => {
+                        "This symbol contains non-alphanumeric ASCII characters."
+                    }
+                    CstError::TextNotClosed => "This text isn't closed.",
+                    CstError::TextNotSufficientlyIndented => {
+                        "This text isn't sufficiently indented."
+                    }
+                    CstError::TextInterpolationNotClosed => "This text interpolation isn't closed.",
+                    CstError::TextInterpolationMissesExpression => {
+                        "Here's a start of a text interpolation without an expression after it."
+                    }
+                    CstError::TooMuchWhitespace => "There is too much whitespace here.",
+                    CstError::UnexpectedCharacters => "This is an unexpected character.",
+                    CstError::UnparsedRest => "The parser couldn't parse this.",
+                    CstError::WeirdWhitespace => "This is weird whitespace.",
+                    CstError::WeirdWhitespaceInIndentation => {
+                        "This is weird whitespace. Make sure to use indent using two spaces."
+                    }
+                };
+                let expected = error.expected();
+                if expected.is_empty() {
+                    message.to_string()
+                } else {
+                    format!("{message} Expected {}.", expected.iter().join(" or "))
                 }
             }
-            .to_string(),
             Self::Ast(error) => match error {
                 AstError::ExpectedNameOrPatternInAssignment => {
                     "An assignment should have a name or pattern on the left side.".to_string()
@@ -151,9 +261,31 @@ impl Display for CompilerErrorPayload {
                 AstError::UnexpectedPunctuation => "This punctuation was unexpected.".to_string(),
             },
             Self::Hir(error) => match error {
+                HirError::ArgumentGivenMultipleTimes { parameter } => {
+                    format!("The argument for `{parameter}` is given multiple times.")
+                }
+                HirError::CallMissesArgument { function, parameter } => {
+                    format!("This call to `{function}` misses an argument for `{parameter}`.")
+                }
+                HirError::CallWithTooManyArguments { function, num_parameters, num_args } => {
+                    format!("`{function}` accepts {num_parameters} arguments, but was called with {num_args} arguments.")
+                }
+                HirError::ExternWithInvalidArguments => {
+                    "`extern` must be called with a text containing the symbol name and either an int containing the number of parameters, e.g., `extern \"candy_add\" 2`, or a list of parameter types and a return type, e.g., `extern \"abs\" (Int,) Int`.".to_string()
+                }
                 HirError::NeedsWithWrongNumberOfArguments { num_args } => {
                     format!("`needs` accepts one or two arguments, but was called with {num_args} arguments. Its parameters are the `condition` and an optional `message`.")
                 }
+                HirError::NonExhaustiveMatch { missing } => {
+                    if missing == "_" {
+                        "This match doesn't handle all values. Consider adding a `_ ->` case.".to_string()
+                    } else {
+                        format!("This match doesn't handle values like `{missing}`.")
+                    }
+                }
+                HirError::NotExported { module, name } => {
+                    format!("{module} doesn't export `{name}`.")
+                }
                 HirError::PatternContainsCall => "Calls in patterns are not allowed.".to_string(),
                 HirError::PublicAssignmentInNotTopLevel => {
                     "Public assignments (:=) can only be used in top-level code.".to_string()
@@ -161,7 +293,16 @@ impl Display for CompilerErrorPayload {
                 HirError::PublicAssignmentWithSameName { name } => {
                     format!("There already exists a public assignment (:=) named `{name}`.")
                 }
+                HirError::UnknownNamedArgument { function, name } => {
+                    format!("`{function}` doesn't have a parameter named `{name}`.")
+                }
                 HirError::UnknownReference { name } => format!("`{name}` is not in scope."),
+                HirError::UnreachableMatchCase => {
+                    "This case can't be reached because the cases above already match all values it matches.".to_string()
+                }
+                HirError::UnsupportedNamedArgument => {
+                    "Named arguments are only supported when calling a function defined in this module by its name.".to_string()
+                }
             },
             Self::Mir(error) => match error {
                 MirError::UseWithInvalidPath { module, path } => {
@@ -188,28 +329,6 @@ impl Display for CompilerErrorPayload {
     }
 }
 
-impl CompilerError {
-    #[must_use]
-    pub fn to_related_information(&self) -> Vec<(Module, cst::Id, String)> {
-        match &self.payload {
-            CompilerErrorPayload::Ast(AstError::OrPatternIsMissingIdentifiers {
-                all_captures,
-                ..
-            }) => all_captures
-                .iter()
-                .map(|capture| {
-                    (
-                        self.module.clone(),
-                        *capture,
-                        "The identifier is bound here.".to_string(),
-                    )
-                })
-                .collect(),
-            _ => vec![],
-        }
-    }
-}
-
 impl ToRichIr for CompilerError {
     fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
         let range = builder.push(
@@ -0,0 +1,57 @@
+//! Modules export their public assignments (`foo := …`). All other top-level
+//! definitions (`foo = …`) are private to the module.
+//!
+//! The HIR of a module contains a struct of its exports, but looking at the
+//! HIR of another module while lowering this one could lead to cycles. The
+//! AST of a module doesn't depend on other modules, so we use that instead.
+
+use crate::{
+    ast::{self, Assignment, AssignmentBody, Ast, AstKind},
+    utils::AdjustCasingOfFirstLetter,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TopLevelDefinition {
+    pub name: String,
+    /// The ID of the defining identifier.
+    pub id: ast::Id,
+    pub is_public: bool,
+}
+impl TopLevelDefinition {
+    /// The key of this definition in the struct returned by `use`, e.g.,
+    /// `Foo` for `foo`.
+    #[must_use]
+    pub fn export_key(&self) -> String {
+        self.name.uppercase_first_letter()
+    }
+}
+
+#[must_use]
+pub fn top_level_definitions(asts: &[Ast]) -> Vec<TopLevelDefinition> {
+    let mut definitions = vec![];
+    for ast in asts {
+        let AstKind::Assignment(Assignment { is_public, body }) = &ast.kind else {
+            continue;
+        };
+        match body {
+            AssignmentBody::Function { name, .. } => definitions.push(TopLevelDefinition {
+                name: name.value.clone(),
+                id: name.id.clone(),
+                is_public: *is_public,
+            }),
+            AssignmentBody::Body { pattern, .. } => {
+                for (name, ids) in pattern.captured_identifiers() {
+                    let Some(id) = ids.into_iter().min() else {
+                        continue;
+                    };
+                    definitions.push(TopLevelDefinition {
+                        name,
+                        id,
+                        is_public: *is_public,
+                    });
+                }
+            }
+        }
+    }
+    definitions
+}
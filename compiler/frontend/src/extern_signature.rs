@@ -0,0 +1,110 @@
+//! Signatures of functions implemented outside of Candy.
+//!
+//! An untyped `extern "symbol" 2` passes all arguments as Candy values and
+//! expects a Candy value in return, so the C function has to use the runtime's
+//! API. A typed `extern "symbol" (Int, Text) Int` instead declares the C types,
+//! and the native backends convert between them and Candy values:
+//!
+//! | Type      | C type            | Candy value                          |
+//! |-----------|-------------------|--------------------------------------|
+//! | `Value`   | `candy_value_t *` | any value                            |
+//! | `Int`     | `int64_t`         | int                                  |
+//! | `Text`    | `const char *`    | text, as NUL-terminated UTF-8        |
+//! | `Pointer` | `void *`          | int containing the address           |
+//! | `Nothing` | `void`            | `Nothing` (only as the return type)  |
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ExternType {
+    Value,
+    Int,
+    Text,
+    Pointer,
+    Nothing,
+}
+impl ExternType {
+    /// The name used in Candy code, e.g., `Int`.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Value => "Value",
+            Self::Int => "Int",
+            Self::Text => "Text",
+            Self::Pointer => "Pointer",
+            Self::Nothing => "Nothing",
+        }
+    }
+    #[must_use]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        [
+            Self::Value,
+            Self::Int,
+            Self::Text,
+            Self::Pointer,
+            Self::Nothing,
+        ]
+        .into_iter()
+        .find(|it| it.symbol() == symbol)
+    }
+}
+/// The lowercase name used in the LIR text format, e.g., `int`.
+impl Display for ExternType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol().to_lowercase())
+    }
+}
+impl FromStr for ExternType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let first = chars.next().ok_or(())?;
+        let symbol = format!("{}{}", first.to_ascii_uppercase(), chars.as_str());
+        Self::from_symbol(&symbol)
+            .filter(|it| it.to_string() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ExternSignature {
+    pub parameters: Vec<ExternType>,
+    pub return_type: ExternType,
+}
+impl ExternSignature {
+    /// Passes all arguments and the return value as Candy values.
+    #[must_use]
+    pub fn untyped(arity: usize) -> Self {
+        Self {
+            parameters: vec![ExternType::Value; arity],
+            return_type: ExternType::Value,
+        }
+    }
+
+    #[must_use]
+    pub fn arity(&self) -> usize {
+        self.parameters.len()
+    }
+    #[must_use]
+    pub fn is_untyped(&self) -> bool {
+        self.return_type == ExternType::Value
+            && self.parameters.iter().all(|it| *it == ExternType::Value)
+    }
+}
+impl Display for ExternSignature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", parameter.symbol())?;
+        }
+        write!(f, ") -> {}", self.return_type.symbol())
+    }
+}
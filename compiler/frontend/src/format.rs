@@ -6,10 +6,27 @@ use std::{borrow::Cow, ops::Sub};
 
 pub enum FormatValue<'a, T: Copy> {
     Int(Cow<'a, BigInt>),
-    Tag { symbol: &'a str, value: Option<T> },
+    Fraction {
+        numerator: &'a BigInt,
+        denominator: &'a BigInt,
+    },
+    Tag {
+        symbol: &'a str,
+        value: Option<T>,
+    },
     Text(&'a str),
     List(&'a [T]),
     Struct(Cow<'a, Vec<(T, T)>>),
+    /// Entries sorted by their keys.
+    Map(Vec<(T, T)>),
+    /// Items in ascending order.
+    Set(Vec<T>),
+    Bytes(&'a [u8]),
+    /// Cells can contain themselves, so their value isn't shown.
+    Cell,
+    /// Text builders are shown without their contents since those may
+    /// change.
+    TextBuilder,
     Function,
     SendPort,
     ReceivePort,
@@ -57,10 +74,18 @@ pub fn format_value<'a, T: 'a + Copy>(
     precedence: Precedence,
     max_length: MaxLength,
     visitor: &impl Fn(T) -> Option<FormatValue<'a, T>>,
+) -> Option<String> {
+    format_value_data(visitor(value)?, precedence, max_length, visitor)
+}
+fn format_value_data<'a, 'b, T: 'a + Copy>(
+    value: FormatValue<'b, T>,
+    precedence: Precedence,
+    max_length: MaxLength,
+    visitor: &impl Fn(T) -> Option<FormatValue<'a, T>>,
 ) -> Option<String> {
     // For each case, the different alternatives of printing are listed.
     // Depending on the available space, the best is chosen.
-    Some(match visitor(value)? {
+    Some(match value {
         FormatValue::Int(int) => {
             // - int
             // - `…`
@@ -72,6 +97,20 @@ pub fn format_value<'a, T: 'a + Copy>(
                 "…".to_string()
             }
         }
+        FormatValue::Fraction {
+            numerator,
+            denominator,
+        } => {
+            // - `numerator/denominator`
+            // - `…`
+
+            let string = format!("{numerator}/{denominator}");
+            if max_length.fits(string.len()) {
+                string
+            } else {
+                "…".to_string()
+            }
+        }
         FormatValue::Tag { symbol, value } => {
             // - full: `Tag Value` or `(Tag Value)` or `Tag`
             // - only symbol: `Tag …` or `(Tag …)` or `Tag`
@@ -130,6 +169,56 @@ pub fn format_value<'a, T: 'a + Copy>(
                 "…".to_string()
             }
         }
+        FormatValue::Bytes(bytes) => {
+            // - all bytes in hex: `<68 69 21>`
+            // - some bytes: `<68 69 + 1 more>`
+            // - no bytes shown: `<3 bytes>`
+            // - `…`
+
+            let all_bytes = format!("<{:02x}>", bytes.iter().format(" "));
+            if max_length.fits(all_bytes.len()) {
+                return Some(all_bytes);
+            }
+
+            // Each shown byte takes at least three characters.
+            let MaxLength::Limited(max_length_value) = max_length else {
+                unreachable!();
+            };
+            for shown in (1..bytes.len().min(max_length_value / 3 + 1)).rev() {
+                let some_bytes = format!(
+                    "<{:02x} + {} more>",
+                    bytes[..shown].iter().format(" "),
+                    bytes.len() - shown,
+                );
+                if max_length.fits(some_bytes.len()) {
+                    return Some(some_bytes);
+                }
+            }
+
+            let summary = format!("<{} bytes>", bytes.len());
+            if max_length.fits(summary.len()) {
+                summary
+            } else {
+                "…".to_string()
+            }
+        }
+        FormatValue::Cell => {
+            // - `cell`
+            // - `…`
+
+            if max_length.fits(4) { "cell" } else { "…" }.to_string()
+        }
+        FormatValue::TextBuilder => {
+            // - `textBuilder`
+            // - `…`
+
+            if max_length.fits(11) {
+                "textBuilder"
+            } else {
+                "…"
+            }
+            .to_string()
+        }
         FormatValue::Function => {
             // - `{ … }`
             // - `…`
@@ -307,6 +396,30 @@ pub fn format_value<'a, T: 'a + Copy>(
                     .join(", "),
             )
         }
+        FormatValue::Map(entries) => {
+            // - `map ` followed by the entries formatted like a struct
+            // - `…`
+
+            format_prefixed(
+                "map",
+                FormatValue::Struct(Cow::Owned(entries)),
+                precedence,
+                max_length,
+                visitor,
+            )?
+        }
+        FormatValue::Set(items) => {
+            // - `set ` followed by the items formatted like a list
+            // - `…`
+
+            format_prefixed(
+                "set",
+                FormatValue::List(&items),
+                precedence,
+                max_length,
+                visitor,
+            )?
+        }
         FormatValue::SendPort => match precedence {
             Precedence::High => "(send port)",
             Precedence::Low => "send port",
@@ -319,3 +432,32 @@ pub fn format_value<'a, T: 'a + Copy>(
         .to_string(),
     })
 }
+
+/// Formats the `value` after the `prefix`, e.g., `map [Foo: 1]`.
+fn format_prefixed<'a, 'b, T: 'a + Copy>(
+    prefix: &str,
+    value: FormatValue<'b, T>,
+    precedence: Precedence,
+    max_length: MaxLength,
+    visitor: &impl Fn(T) -> Option<FormatValue<'a, T>>,
+) -> Option<String> {
+    let needs_parentheses = precedence == Precedence::High;
+    // prefix, space, and optional parentheses
+    let length_needed_for_prefix = prefix.len() + 1 + if needs_parentheses { 2 } else { 0 };
+    // The formatted value takes at least two characters, e.g., `[]`.
+    if !max_length.fits(length_needed_for_prefix + 2) {
+        return Some("…".to_string());
+    }
+
+    let value = format_value_data(
+        value,
+        Precedence::Low,
+        max_length - length_needed_for_prefix,
+        visitor,
+    )?;
+    Some(if needs_parentheses {
+        format!("({prefix} {value})")
+    } else {
+        format!("{prefix} {value}")
+    })
+}
@@ -2,6 +2,7 @@ use crate::{
     ast_to_hir::AstToHir,
     builtin_functions::BuiltinFunction,
     error::CompilerError,
+    extern_signature::ExternSignature,
     impl_countable_id, impl_display_via_richir,
     module::{Module, ModuleKind, Package},
     rich_ir::{ReferenceKey, RichIrBuilder, ToRichIr, TokenType},
@@ -12,12 +13,14 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use linked_hash_map::LinkedHashMap;
 use num_bigint::BigUint;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Debug, Display, Formatter},
     hash::{Hash, Hasher},
     sync::Arc,
 };
+use strum_macros::AsRefStr;
 use tracing::info;
 
 #[salsa::query_group(HirDbStorage)]
@@ -79,7 +82,7 @@ impl Expression {
                 ids.extend_from_slice(items);
             }
             Self::Struct(entries) => {
-                for (key_id, value_id) in entries {
+                for (key_id, value_id) in entries.iter().sorted() {
                     ids.push(key_id.clone());
                     ids.push(value_id.clone());
                 }
@@ -113,7 +116,7 @@ impl Expression {
             Self::UseModule { relative_path, .. } => {
                 ids.push(relative_path.clone());
             }
-            Self::Builtin(_) => {}
+            Self::Builtin(_) | Self::Extern { .. } => {}
             Self::Needs { condition, reason } => {
                 ids.push(condition.clone());
                 ids.push(reason.clone());
@@ -129,14 +132,39 @@ impl Body {
             expression.collect_all_ids(ids);
         }
     }
+
+    /// Collects the IDs that are referenced by expressions in this body or
+    /// nested bodies.
+    pub fn collect_referenced_ids(&self, referenced: &mut FxHashSet<Id>) {
+        for expression in self.expressions.values() {
+            match expression {
+                Expression::Function(Function { body, .. }) => {
+                    body.collect_referenced_ids(referenced);
+                }
+                Expression::Match { expression, cases } => {
+                    referenced.insert(expression.clone());
+                    for (_, body) in cases {
+                        body.collect_referenced_ids(referenced);
+                    }
+                }
+                expression => {
+                    let mut ids = vec![];
+                    expression.collect_all_ids(&mut ids);
+                    referenced.extend(ids);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Id {
     pub module: Module,
     pub keys: IdPath,
 }
-#[derive(Clone, Eq, From, Hash, Ord, PartialEq, PartialOrd, Debug, Default)]
+#[derive(
+    Clone, Eq, From, Hash, Ord, PartialEq, PartialOrd, Debug, Default, Deserialize, Serialize,
+)]
 pub struct IdPath(String);
 #[derive(Clone, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
 pub enum IdKey {
@@ -201,6 +229,7 @@ lazy_static! {
     static ref PLATFORM_MODULE: Module = tooling_module("platform".to_string());
     static ref FUZZER_MODULE: Module = tooling_module("fuzzer".to_string());
     static ref DUMMY_MODULE: Module = tooling_module("dummy".to_string());
+    static ref STRIPPED_MODULE: Module = tooling_module("stripped".to_string());
     static ref NEEDS_MODULE: Module = Module::new(
         Package::Anonymous {
             url: "$generated".to_string(),
@@ -249,6 +278,12 @@ impl Id {
     pub fn dummy() -> Self {
         Self::tooling(DUMMY_MODULE.clone())
     }
+    /// Replaces all IDs of Candy code when compiling with
+    /// [`TracingConfig::strip`](crate::TracingConfig::strip).
+    #[must_use]
+    pub fn stripped() -> Self {
+        Self::tooling(STRIPPED_MODULE.clone())
+    }
 
     #[must_use]
     pub fn needs() -> Self {
@@ -368,7 +403,7 @@ impl From<&str> for IdKey {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub enum Expression {
     Int(BigUint),
     Text(String),
@@ -390,6 +425,11 @@ pub enum Expression {
     },
     Function(Function),
     Builtin(BuiltinFunction),
+    /// A function implemented outside of Candy, called via the C ABI.
+    Extern {
+        symbol: String,
+        signature: ExternSignature,
+    },
     Call {
         function: Id,
         arguments: Vec<Id>,
@@ -419,7 +459,7 @@ impl Hash for Expression {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct PatternIdentifierId(pub usize);
 impl_countable_id!(PatternIdentifierId);
 impl Debug for PatternIdentifierId {
@@ -439,7 +479,7 @@ impl ToRichIr for PatternIdentifierId {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub enum Pattern {
     NewIdentifier(PatternIdentifierId),
     Int(BigUint),
@@ -541,13 +581,13 @@ impl Pattern {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Function {
     pub parameters: Vec<Id>,
     pub body: Body,
     pub kind: FunctionKind,
 }
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum FunctionKind {
     /// A normal function (e.g., `foo a = …`).
     Normal,
@@ -579,7 +619,7 @@ impl FunctionKind {
     }
 }
 
-#[derive(Clone, Debug, Eq, Default, PartialEq)]
+#[derive(Clone, Debug, Eq, Default, PartialEq, Deserialize, Serialize)]
 pub struct Body {
     pub expressions: LinkedHashMap<Id, Expression>,
     pub identifiers: FxHashMap<Id, String>,
@@ -591,13 +631,46 @@ impl Hash for Body {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(AsRefStr, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[strum(serialize_all = "kebab-case")]
 pub enum HirError {
-    NeedsWithWrongNumberOfArguments { num_args: usize },
+    ArgumentGivenMultipleTimes {
+        parameter: String,
+    },
+    CallMissesArgument {
+        function: String,
+        parameter: String,
+    },
+    CallWithTooManyArguments {
+        function: String,
+        num_parameters: usize,
+        num_args: usize,
+    },
+    ExternWithInvalidArguments,
+    NeedsWithWrongNumberOfArguments {
+        num_args: usize,
+    },
+    NonExhaustiveMatch {
+        missing: String,
+    },
+    NotExported {
+        module: Module,
+        name: String,
+    },
     PatternContainsCall,
     PublicAssignmentInNotTopLevel,
-    PublicAssignmentWithSameName { name: String },
-    UnknownReference { name: String },
+    PublicAssignmentWithSameName {
+        name: String,
+    },
+    UnknownNamedArgument {
+        function: String,
+        name: String,
+    },
+    UnknownReference {
+        name: String,
+    },
+    UnreachableMatchCase,
+    UnsupportedNamedArgument,
 }
 
 impl Body {
@@ -635,7 +708,7 @@ impl ToRichIr for Expression {
             Self::Struct(fields) => {
                 builder.push("[", None, EnumSet::empty());
                 builder.push_children_custom(
-                    fields.iter().collect_vec(),
+                    fields.iter().sorted().collect_vec(),
                     |builder, (key, value)| {
                         key.build_rich_ir(builder);
                         builder.push(": ", None, EnumSet::empty());
@@ -690,6 +763,13 @@ impl ToRichIr for Expression {
             Self::Builtin(builtin) => {
                 builtin.build_rich_ir(builder);
             }
+            Self::Extern { symbol, signature } => {
+                builder.push(
+                    format!("extern {symbol} {signature}"),
+                    None,
+                    EnumSet::empty(),
+                );
+            }
             Self::Call {
                 function,
                 arguments,
@@ -836,6 +916,7 @@ impl Expression {
             Self::Match { cases, .. } => cases.iter().find_map(|(_, body)| body.find(id)),
             Self::Function(Function { body, .. }) => body.find(id),
             Self::Builtin(_) => None,
+            Self::Extern { .. } => None,
             Self::Call { .. } => None,
             Self::UseModule { .. } => None,
             Self::Needs { .. } => None,
@@ -849,10 +930,11 @@ impl Body {
         if let Some(expression) = self.expressions.get(id) {
             Some(expression)
         } else {
+            // We can't simply take the greatest ID less than the searched one:
+            // `foo#1` sorts before `foo:bar`, but only `foo` contains it.
             self.expressions
                 .iter()
-                .filter(|(it, _)| it <= &id)
-                .max_by_key(|(id, _)| id.keys.clone())?
+                .find(|(it, _)| it.is_same_module_and_any_parent_of(id))?
                 .1
                 .find(id)
         }
@@ -878,7 +960,11 @@ impl CollectErrors for Expression {
                     body.collect_errors(errors);
                 }
             }
-            Self::Builtin(_) | Self::Call { .. } | Self::UseModule { .. } | Self::Needs { .. } => {}
+            Self::Builtin(_)
+            | Self::Extern { .. }
+            | Self::Call { .. }
+            | Self::UseModule { .. }
+            | Self::Needs { .. } => {}
             Self::Function(function) => function.body.collect_errors(errors),
             Self::Destructure { pattern, .. } => pattern.collect_errors(errors),
             Self::Error {
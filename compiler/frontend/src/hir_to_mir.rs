@@ -331,13 +331,27 @@ impl<'a> LoweringContext<'a> {
             hir::Expression::Text(text) => body.push_text(text.clone()),
             hir::Expression::Reference(reference) => body.push_reference(self.mapping[reference]),
             hir::Expression::Symbol(symbol) => body.push_tag(symbol.clone(), None),
+            hir::Expression::Builtin(builtin)
+                if builtin.is_debug_only() && !self.tracing.debug_assertions =>
+            {
+                body.push_function(hir_id.clone(), |body, _| {
+                    for _ in 0..builtin.num_parameters() {
+                        body.new_parameter();
+                    }
+                    body.push_nothing();
+                })
+            }
             hir::Expression::Builtin(builtin) => body.push_builtin(*builtin),
+            hir::Expression::Extern { symbol, signature } => {
+                body.push_extern(symbol.clone(), signature.clone())
+            }
             hir::Expression::List(items) => {
                 body.push_list(items.iter().map(|item| self.mapping[item]).collect())
             }
             hir::Expression::Struct(fields) => {
                 let fields = fields
                     .iter()
+                    .sorted()
                     .map(|(key, value)| (self.mapping[key], self.mapping[value]))
                     .collect();
                 body.push_struct(fields)
@@ -19,29 +19,42 @@
     clippy::too_many_lines
 )]
 
-pub use self::tracing::{CallTracingMode, TracingConfig, TracingMode};
+pub use self::{
+    optimization::{OptimizationConfig, OptimizationLevel},
+    tracing::{CallTracingMode, TracingConfig, TracingMode},
+};
 
 pub mod ast;
 pub mod ast_to_hir;
 pub mod builtin_functions;
+pub mod cancellation;
 pub mod comment;
 pub mod cst;
 pub mod cst_to_ast;
 pub mod error;
+pub mod exports;
+pub mod extern_signature;
 pub mod format;
 pub mod hir;
 pub mod hir_to_mir;
 pub mod id;
+pub mod lints;
 pub mod lir;
 pub mod lir_optimize;
+pub mod mangling;
+pub mod match_analysis;
 pub mod mir;
 pub mod mir_optimize;
 pub mod mir_to_lir;
 pub mod module;
+pub mod optimization;
+pub mod persistent_cache;
 pub mod position;
 pub mod rcst;
 pub mod rcst_to_cst;
 pub mod rich_ir;
 pub mod string_to_rcst;
+pub mod symbol_index;
 pub mod tracing;
+pub mod unused_uses;
 pub mod utils;
@@ -0,0 +1,62 @@
+//! Finds expressions whose value is thrown away, e.g.:
+//!
+//! ```candy
+//! foo =
+//!   3
+//!   4
+//! ```
+//!
+//! Calls can have side effects and often return `Nothing`, so we only report
+//! expressions that are obviously values.
+
+use super::visit_bodies;
+use crate::{
+    ast::{Ast, AstKind, Symbol},
+    ast_to_hir::AstToHir,
+    module::Module,
+    position::Offset,
+};
+use std::ops::Range;
+
+pub fn find(db: &impl AstToHir, module: &Module) -> Vec<(Range<Offset>, String)> {
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return vec![];
+    };
+    let mut problems = vec![];
+    visit_bodies(&asts, false, &mut |body, has_value| {
+        let ignored = if has_value {
+            body.split_last().map_or(&[][..], |(_, rest)| rest)
+        } else {
+            body
+        };
+        problems.extend(
+            ignored
+                .iter()
+                .filter(|ast| is_obviously_a_value(ast))
+                .filter_map(|ast| db.ast_id_to_span(&ast.id))
+                .map(|span| (span, "This value is never used.".to_string())),
+        );
+    });
+    problems
+}
+
+fn is_obviously_a_value(ast: &Ast) -> bool {
+    match &ast.kind {
+        AstKind::Symbol(Symbol(symbol)) => symbol.value != "Nothing",
+        AstKind::Int(_)
+        | AstKind::Text(_)
+        | AstKind::Identifier(_)
+        | AstKind::List(_)
+        | AstKind::Struct(_)
+        | AstKind::StructAccess(_)
+        | AstKind::Function(_) => true,
+        AstKind::TextPart(_)
+        | AstKind::Call(_)
+        | AstKind::NamedArgument(_)
+        | AstKind::Assignment(_)
+        | AstKind::Match(_)
+        | AstKind::MatchCase(_)
+        | AstKind::OrPattern(_)
+        | AstKind::Error { .. } => false,
+    }
+}
@@ -0,0 +1,449 @@
+//! Lints find code that works but probably doesn't do what its author
+//! intended, e.g., a `use` whose result is never referenced or code after a
+//! `needs False`.
+//!
+//! Unlike compiler errors, lints don't change how the code runs. Each lint has
+//! a [`LintLevel`] that decides whether it's ignored, reported as a warning, or
+//! reported as an error. Packages can configure the levels in their
+//! `_package.candy`:
+//!
+//! ```candy
+//! lints := [UnusedUse: Allow]
+//! ```
+//!
+//! Tools can override these, e.g., `candy check --deny warnings` reports all
+//! warnings as errors.
+
+use crate::{
+    ast::{
+        Assignment, AssignmentBody, Ast, AstKind, Call, Function, Identifier, List, Match,
+        MatchCase, NamedArgument, Struct, StructAccess, Symbol, Text,
+    },
+    ast_to_hir::AstToHir,
+    module::{Module, ModuleKind, Package},
+    position::{Offset, PositionConversionDb, RangeOfPosition},
+    unused_uses::find_unused_uses,
+};
+use rustc_hash::FxHashMap;
+use std::ops::Range;
+use strum::VariantArray;
+
+mod ignored_results;
+mod shadowed_names;
+mod unreachable_code;
+mod unused_definitions;
+
+#[derive(
+    Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, strum_macros::VariantArray,
+)]
+pub enum Lint {
+    /// An expression whose value is neither used nor returned, e.g., a
+    /// literal on its own line.
+    IgnoredResult,
+    /// A definition with the same name as a definition or parameter that is
+    /// still in scope.
+    ShadowedName,
+    /// Code after an expression that always panics.
+    UnreachableCode,
+    /// A definition that is never referenced.
+    UnusedDefinition,
+    UnusedUse,
+}
+impl Lint {
+    /// The name used on the command line, e.g., `unused-use`.
+    #[must_use]
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::IgnoredResult => "ignored-result",
+            Self::ShadowedName => "shadowed-name",
+            Self::UnreachableCode => "unreachable-code",
+            Self::UnusedDefinition => "unused-definition",
+            Self::UnusedUse => "unused-use",
+        }
+    }
+    /// The name used in `_package.candy`, e.g., `UnusedUse`.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::IgnoredResult => "IgnoredResult",
+            Self::ShadowedName => "ShadowedName",
+            Self::UnreachableCode => "UnreachableCode",
+            Self::UnusedDefinition => "UnusedDefinition",
+            Self::UnusedUse => "UnusedUse",
+        }
+    }
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::VARIANTS.iter().copied().find(|it| it.code() == code)
+    }
+    #[must_use]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::VARIANTS
+            .iter()
+            .copied()
+            .find(|it| it.symbol() == symbol)
+    }
+
+    #[must_use]
+    pub const fn default_level(self) -> LintLevel {
+        match self {
+            Self::IgnoredResult
+            | Self::ShadowedName
+            | Self::UnreachableCode
+            | Self::UnusedDefinition
+            | Self::UnusedUse => LintLevel::Warn,
+        }
+    }
+
+    /// The spans and messages of all problems this lint finds in the module.
+    fn find(self, db: &impl AstToHir, module: &Module) -> Vec<(Range<Offset>, String)> {
+        match self {
+            Self::IgnoredResult => ignored_results::find(db, module),
+            Self::ShadowedName => shadowed_names::find(db, module),
+            Self::UnreachableCode => unreachable_code::find(db, module),
+            Self::UnusedDefinition => unused_definitions::find(db, module),
+            Self::UnusedUse => find_unused_uses(db, module.clone())
+                .into_iter()
+                .flat_map(|unused_use| unused_use.unused_names)
+                .map(|(name, span)| (span, format!("`{name}` is imported but never used.")))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+impl LintLevel {
+    #[must_use]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "Allow" => Some(Self::Allow),
+            "Warn" => Some(Self::Warn),
+            "Deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LintLevels {
+    levels: FxHashMap<Lint, LintLevel>,
+    /// Whether lints at the [`LintLevel::Warn`] level are reported as errors.
+    deny_warnings: bool,
+}
+impl LintLevels {
+    /// The levels configured in the package's `_package.candy`.
+    ///
+    /// Returns the default levels if the package doesn't configure any, and an
+    /// error if the configuration is invalid.
+    pub fn for_package(db: &impl AstToHir, package: &Package) -> Result<Self, String> {
+        let module = Module::new(
+            package.clone(),
+            vec!["_package".to_string()],
+            ModuleKind::Code,
+        );
+        let Ok((asts, _)) = db.ast(module) else {
+            return Ok(Self::default());
+        };
+        let Some(fields) = asts.iter().find_map(lints_assignment) else {
+            return Ok(Self::default());
+        };
+
+        let mut levels = Self::default();
+        for (key, value) in fields {
+            let (
+                Some(Ast {
+                    kind: AstKind::Symbol(Symbol(lint)),
+                    ..
+                }),
+                Ast {
+                    kind: AstKind::Symbol(Symbol(level)),
+                    ..
+                },
+            ) = (key, value)
+            else {
+                return Err(
+                    "`lints` in `_package.candy` must map lints to levels, e.g., `[UnusedUse: Allow]`."
+                        .to_string(),
+                );
+            };
+            let lint = Lint::from_symbol(&lint.value)
+                .ok_or_else(|| format!("`{}` is not a lint.", lint.value))?;
+            let level = LintLevel::from_symbol(&level.value).ok_or_else(|| {
+                format!(
+                    "`{}` is not a lint level. Use `Allow`, `Warn`, or `Deny`.",
+                    level.value,
+                )
+            })?;
+            levels.set(lint, level);
+        }
+        Ok(levels)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+    /// Applies a level given on the command line, where `code` is either a
+    /// lint's [code](Lint::code) or `warnings`, which only supports
+    /// [`LintLevel::Deny`].
+    pub fn set_from_code(&mut self, code: &str, level: LintLevel) -> Result<(), String> {
+        if code == "warnings" {
+            if level != LintLevel::Deny {
+                return Err("`warnings` can only be denied.".to_string());
+            }
+            self.deny_warnings = true;
+            return Ok(());
+        }
+        let lint = Lint::from_code(code).ok_or_else(|| format!("`{code}` is not a lint."))?;
+        self.set(lint, level);
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        let level = self
+            .levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level());
+        if self.deny_warnings && level == LintLevel::Warn {
+            LintLevel::Deny
+        } else {
+            level
+        }
+    }
+}
+
+fn lints_assignment(ast: &Ast) -> Option<&[(Option<Ast>, Ast)]> {
+    let AstKind::Assignment(Assignment {
+        body: AssignmentBody::Body { pattern, body },
+        ..
+    }) = &ast.kind
+    else {
+        return None;
+    };
+    let AstKind::Identifier(Identifier(name)) = &pattern.kind else {
+        return None;
+    };
+    if name.value != "lints" {
+        return None;
+    }
+    let [Ast {
+        kind: AstKind::Struct(Struct { fields }),
+        ..
+    }] = body.as_slice()
+    else {
+        return None;
+    };
+    Some(fields)
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LintWarning {
+    pub lint: Lint,
+    /// Either [`LintLevel::Warn`] or [`LintLevel::Deny`].
+    pub level: LintLevel,
+    pub module: Module,
+    pub span: Range<Offset>,
+    pub message: String,
+}
+impl LintWarning {
+    pub fn to_string_with_location(&self, db: &impl PositionConversionDb) -> String {
+        let range = db.range_to_positions(self.module.clone(), self.span.clone());
+        let severity = match self.level {
+            LintLevel::Allow | LintLevel::Warn => "warning",
+            LintLevel::Deny => "error",
+        };
+        format!(
+            "{}:{}: {severity}[{}]: {}",
+            self.module,
+            range.format(),
+            self.lint.code(),
+            self.message,
+        )
+    }
+}
+
+/// Runs all lints that aren't allowed on the module.
+#[must_use]
+pub fn lint_module(db: &impl AstToHir, module: &Module, levels: &LintLevels) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    for lint in Lint::VARIANTS.iter().copied() {
+        let level = levels.level(lint);
+        if level == LintLevel::Allow {
+            continue;
+        }
+        warnings.extend(
+            lint.find(db, module)
+                .into_iter()
+                .map(|(span, message)| LintWarning {
+                    lint,
+                    level,
+                    module: module.clone(),
+                    span,
+                    message,
+                }),
+        );
+    }
+    warnings.sort_by_key(|warning| (warning.span.start, warning.lint));
+    warnings
+}
+
+/// Calls `visit` for the given body and all bodies nested in it, e.g., of
+/// functions, match cases, and assignments. The flag passed to `visit` tells
+/// whether the body's last expression is its value, which isn't the case for
+/// a module's top level.
+fn visit_bodies(body: &[Ast], has_value: bool, visit: &mut impl FnMut(&[Ast], bool)) {
+    visit(body, has_value);
+    for ast in body {
+        visit_nested_bodies(ast, visit);
+    }
+}
+fn visit_nested_bodies(ast: &Ast, visit: &mut impl FnMut(&[Ast], bool)) {
+    match &ast.kind {
+        AstKind::Int(_)
+        | AstKind::TextPart(_)
+        | AstKind::Identifier(_)
+        | AstKind::Symbol(_)
+        | AstKind::OrPattern(_)
+        | AstKind::Error { .. } => {}
+        AstKind::Text(Text(parts)) => {
+            for part in parts {
+                visit_nested_bodies(part, visit);
+            }
+        }
+        AstKind::List(List(items)) => {
+            for item in items {
+                visit_nested_bodies(item, visit);
+            }
+        }
+        AstKind::Struct(Struct { fields }) => {
+            for (key, value) in fields {
+                if let Some(key) = key {
+                    visit_nested_bodies(key, visit);
+                }
+                visit_nested_bodies(value, visit);
+            }
+        }
+        AstKind::StructAccess(StructAccess { struct_, .. }) => {
+            visit_nested_bodies(struct_, visit);
+        }
+        AstKind::Function(function) => visit_function_bodies(function, visit),
+        AstKind::Call(Call {
+            receiver,
+            arguments,
+            ..
+        }) => {
+            visit_nested_bodies(receiver, visit);
+            for argument in arguments {
+                visit_nested_bodies(argument, visit);
+            }
+        }
+        AstKind::NamedArgument(NamedArgument { value, .. }) => visit_nested_bodies(value, visit),
+        AstKind::Assignment(Assignment { body, .. }) => match body {
+            AssignmentBody::Function { function, .. } => visit_function_bodies(function, visit),
+            AssignmentBody::Body { body, .. } => visit_bodies(body, true, visit),
+        },
+        AstKind::Match(Match { expression, cases }) => {
+            visit_nested_bodies(expression, visit);
+            for case in cases {
+                visit_nested_bodies(case, visit);
+            }
+        }
+        AstKind::MatchCase(MatchCase { body, .. }) => visit_bodies(body, true, visit),
+    }
+}
+fn visit_function_bodies(function: &Function, visit: &mut impl FnMut(&[Ast], bool)) {
+    // Default values of optional parameters can contain functions.
+    for parameter in &function.parameters {
+        visit_nested_bodies(parameter, visit);
+    }
+    visit_bodies(&function.body, true, visit);
+}
+
+/// The span from the start of the first to the end of the last AST.
+fn span_of_all(db: &impl AstToHir, asts: &[Ast]) -> Option<Range<Offset>> {
+    let start = db.ast_id_to_span(&asts.first()?.id)?.start;
+    let end = db.ast_id_to_span(&asts.last()?.id)?.end;
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint_module, Lint, LintLevel, LintLevels};
+    use crate::module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase};
+    use itertools::Itertools;
+    use std::path::PathBuf;
+
+    #[test]
+    fn applies_package_and_command_line_levels() {
+        let mut db = TestDatabase::default();
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let module =
+            |name: &str| Module::new(package.clone(), vec![name.to_string()], ModuleKind::Code);
+        let (config, main) = (module("_package"), module("main"));
+        db.did_open_module(&main, b"foo = use \".foo\"\n".to_vec());
+
+        let levels = LintLevels::for_package(&db, &package).unwrap();
+        let warnings = lint_module(&db, &main, &levels);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].level, LintLevel::Warn);
+
+        let mut denying = levels;
+        denying.set_from_code("warnings", LintLevel::Deny).unwrap();
+        assert_eq!(lint_module(&db, &main, &denying)[0].level, LintLevel::Deny);
+
+        db.did_open_module(&config, b"lints := [UnusedUse: Allow]\n".to_vec());
+        let mut levels = LintLevels::for_package(&db, &package).unwrap();
+        assert!(lint_module(&db, &main, &levels).is_empty());
+        levels.set_from_code("unused-use", LintLevel::Deny).unwrap();
+        assert_eq!(levels.level(Lint::UnusedUse), LintLevel::Deny);
+
+        db.did_open_module(&config, b"lints := [UnusedThing: Allow]\n".to_vec());
+        assert!(LintLevels::for_package(&db, &package).is_err());
+    }
+
+    #[test]
+    fn finds_suspicious_code() {
+        let mut db = TestDatabase::default();
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let module = Module::new(package, vec!["main".to_string()], ModuleKind::Code);
+        let source = "\
+foo = 1
+bar = foo
+bar
+baz a =
+  a = 2
+  3
+qux =
+  needs False
+  4
+";
+        db.did_open_module(&module, source.as_bytes().to_vec());
+
+        let warnings = lint_module(&db, &module, &LintLevels::default())
+            .into_iter()
+            .map(|warning| {
+                (
+                    warning.lint,
+                    source[*warning.span.start..*warning.span.end].trim(),
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            warnings,
+            vec![
+                (Lint::IgnoredResult, "bar"),
+                (Lint::UnusedDefinition, "baz"),
+                (Lint::ShadowedName, "a"),
+                (Lint::UnusedDefinition, "a"),
+                (Lint::UnusedDefinition, "qux"),
+                (Lint::UnreachableCode, "4"),
+            ],
+        );
+    }
+}
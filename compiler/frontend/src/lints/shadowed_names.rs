@@ -0,0 +1,142 @@
+//! Finds definitions and parameters that have the same name as another one
+//! that is still in scope, e.g., the inner `foo` in:
+//!
+//! ```candy
+//! foo = 1
+//! bar =
+//!   foo = 2
+//!   foo
+//! ```
+//!
+//! Names starting with an underscore are ignored.
+
+use crate::{
+    ast::{
+        self, Assignment, AssignmentBody, Ast, AstKind, Call, Function, Identifier, List, Match,
+        MatchCase, NamedArgument, Struct, StructAccess, Text,
+    },
+    ast_to_hir::AstToHir,
+    module::Module,
+    position::Offset,
+};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+use std::ops::Range;
+
+pub fn find(db: &impl AstToHir, module: &Module) -> Vec<(Range<Offset>, String)> {
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return vec![];
+    };
+    let mut context = Context {
+        db,
+        scopes: vec![],
+        problems: vec![],
+    };
+    context.visit_body(&asts);
+    context.problems
+}
+
+struct Context<'a, DB: AstToHir> {
+    db: &'a DB,
+    scopes: Vec<FxHashSet<String>>,
+    problems: Vec<(Range<Offset>, String)>,
+}
+impl<'a, DB: AstToHir> Context<'a, DB> {
+    fn visit_body(&mut self, body: &[Ast]) {
+        self.scopes.push(FxHashSet::default());
+        self.visit_all(body);
+        self.scopes.pop();
+    }
+    fn visit_all(&mut self, asts: &[Ast]) {
+        for ast in asts {
+            self.visit(ast);
+        }
+    }
+    fn visit(&mut self, ast: &Ast) {
+        match &ast.kind {
+            AstKind::Int(_)
+            | AstKind::TextPart(_)
+            | AstKind::Identifier(_)
+            | AstKind::Symbol(_)
+            | AstKind::OrPattern(_)
+            | AstKind::Error { .. } => {}
+            AstKind::Text(Text(parts)) => self.visit_all(parts),
+            AstKind::List(List(items)) => self.visit_all(items),
+            AstKind::Struct(Struct { fields }) => {
+                for (key, value) in fields {
+                    if let Some(key) = key {
+                        self.visit(key);
+                    }
+                    self.visit(value);
+                }
+            }
+            AstKind::StructAccess(StructAccess { struct_, .. }) => self.visit(struct_),
+            AstKind::Function(function) => self.visit_function(function),
+            AstKind::Call(Call {
+                receiver,
+                arguments,
+                ..
+            }) => {
+                self.visit(receiver);
+                self.visit_all(arguments);
+            }
+            AstKind::NamedArgument(NamedArgument { value, .. }) => self.visit(value),
+            AstKind::Assignment(Assignment { body, .. }) => match body {
+                AssignmentBody::Function { name, function } => {
+                    self.visit_function(function);
+                    self.define(&name.value, &name.id);
+                }
+                AssignmentBody::Body { pattern, body } => {
+                    self.visit_body(body);
+                    self.define_pattern(pattern);
+                }
+            },
+            AstKind::Match(Match { expression, cases }) => {
+                self.visit(expression);
+                self.visit_all(cases);
+            }
+            AstKind::MatchCase(MatchCase { pattern, body }) => {
+                self.scopes.push(FxHashSet::default());
+                self.define_pattern(pattern);
+                self.visit_all(body);
+                self.scopes.pop();
+            }
+        }
+    }
+    fn visit_function(&mut self, function: &Function) {
+        self.scopes.push(FxHashSet::default());
+        for parameter in &function.parameters {
+            match &parameter.kind {
+                AstKind::Identifier(Identifier(name)) => self.define(&name.value, &name.id),
+                AstKind::NamedArgument(NamedArgument { name, value }) => {
+                    self.visit(value);
+                    self.define(&name.value, &name.id);
+                }
+                _ => {}
+            }
+        }
+        self.visit_all(&function.body);
+        self.scopes.pop();
+    }
+
+    fn define_pattern(&mut self, pattern: &Ast) {
+        let identifiers = pattern
+            .captured_identifiers()
+            .into_iter()
+            .filter_map(|(name, ids)| Some((ids.into_iter().min()?, name)))
+            .sorted();
+        for (id, name) in identifiers {
+            self.define(&name, &id);
+        }
+    }
+    fn define(&mut self, name: &str, id: &ast::Id) {
+        if !name.starts_with('_')
+            && self.scopes.iter().any(|scope| scope.contains(name))
+            && let Some(span) = self.db.ast_id_to_span(id)
+        {
+            self.problems
+                .push((span, format!("`{name}` shadows an earlier definition.")));
+        }
+        self.scopes.last_mut().unwrap().insert(name.to_string());
+    }
+}
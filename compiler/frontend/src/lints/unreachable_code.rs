@@ -0,0 +1,63 @@
+//! Finds code after an expression that always panics, e.g.:
+//!
+//! ```candy
+//! foo =
+//!   needs False "Not implemented yet."
+//!   bar
+//! ```
+
+use super::{span_of_all, visit_bodies};
+use crate::{
+    ast::{Assignment, AssignmentBody, Ast, AstKind, Call, Identifier, StructAccess, Symbol},
+    ast_to_hir::AstToHir,
+    module::Module,
+    position::Offset,
+};
+use std::ops::Range;
+
+pub fn find(db: &impl AstToHir, module: &Module) -> Vec<(Range<Offset>, String)> {
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return vec![];
+    };
+    let mut problems = vec![];
+    visit_bodies(&asts, false, &mut |body, _| {
+        let Some(index) = body.iter().position(always_panics) else {
+            return;
+        };
+        if let Some(span) = span_of_all(db, &body[index + 1..]) {
+            problems.push((
+                span,
+                "This code is unreachable because the code before always panics.".to_string(),
+            ));
+        }
+    });
+    problems
+}
+
+/// Calls of `panic` (including `✨.panic`), `needs False`, and assignments
+/// whose body contains one of these.
+fn always_panics(ast: &Ast) -> bool {
+    match &ast.kind {
+        AstKind::Call(Call {
+            receiver,
+            arguments,
+            ..
+        }) => match &receiver.kind {
+            AstKind::Identifier(Identifier(name)) if name.value == "panic" => true,
+            AstKind::StructAccess(StructAccess { key, .. }) if key.value == "Panic" => true,
+            AstKind::Identifier(Identifier(name)) if name.value == "needs" => matches!(
+                arguments.first(),
+                Some(Ast {
+                    kind: AstKind::Symbol(Symbol(condition)),
+                    ..
+                }) if condition.value == "False",
+            ),
+            _ => false,
+        },
+        AstKind::Assignment(Assignment {
+            body: AssignmentBody::Body { body, .. },
+            ..
+        }) => body.iter().any(always_panics),
+        _ => false,
+    }
+}
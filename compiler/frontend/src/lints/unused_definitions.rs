@@ -0,0 +1,74 @@
+//! Finds definitions that are never referenced, e.g., `bar` in:
+//!
+//! ```candy
+//! foo =
+//!   bar = 1
+//!   2
+//! ```
+//!
+//! Public definitions are exported and, therefore, always used. Unused `use`s
+//! are reported by [`Lint::UnusedUse`](super::Lint::UnusedUse) instead.
+
+use super::visit_bodies;
+use crate::{
+    ast::{self, Assignment, AssignmentBody, Ast, AstKind},
+    ast_to_hir::AstToHir,
+    module::Module,
+    position::Offset,
+    unused_uses::is_use_assignment,
+};
+use rustc_hash::FxHashSet;
+use std::ops::Range;
+
+pub fn find(db: &impl AstToHir, module: &Module) -> Vec<(Range<Offset>, String)> {
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return vec![];
+    };
+    let Ok((hir, _)) = db.hir(module.clone()) else {
+        return vec![];
+    };
+    let mut referenced = FxHashSet::default();
+    hir.collect_referenced_ids(&mut referenced);
+
+    let mut problems = vec![];
+    visit_bodies(&asts, false, &mut |body, _| {
+        for ast in body {
+            for (name, id) in defined_names(ast) {
+                if name.starts_with('_')
+                    || db
+                        .ast_to_hir_ids(&id)
+                        .iter()
+                        .any(|id| referenced.contains(id))
+                {
+                    continue;
+                }
+                if let Some(span) = db.ast_id_to_span(&id) {
+                    problems.push((span, format!("`{name}` is defined but never used.")));
+                }
+            }
+        }
+    });
+    problems
+}
+
+/// The names and IDs of the identifiers defined by a private assignment.
+fn defined_names(ast: &Ast) -> Vec<(String, ast::Id)> {
+    let AstKind::Assignment(Assignment {
+        is_public: false,
+        body,
+    }) = &ast.kind
+    else {
+        return vec![];
+    };
+    if is_use_assignment(ast) {
+        return vec![];
+    }
+    match body {
+        AssignmentBody::Function { name, .. } => vec![(name.value.clone(), name.id.clone())],
+        AssignmentBody::Body { pattern, .. } => pattern
+            .captured_identifiers()
+            .into_iter()
+            .filter_map(|(name, ids)| Some((name, ids.into_iter().next()?)))
+            .collect(),
+    }
+}
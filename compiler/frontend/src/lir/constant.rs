@@ -1,6 +1,7 @@
 use super::BodyId;
 use crate::{
     builtin_functions::BuiltinFunction,
+    extern_signature::ExternSignature,
     hir,
     id::CountableId,
     impl_countable_id, impl_display_via_richir,
@@ -100,6 +101,10 @@ pub enum Constant {
         value: Option<ConstantId>,
     },
     Builtin(BuiltinFunction),
+    Extern {
+        symbol: String,
+        signature: ExternSignature,
+    },
     List(Vec<ConstantId>),
     Struct(FxHashMap<ConstantId, ConstantId>),
     HirId(hir::Id),
@@ -132,6 +137,13 @@ impl Constant {
             Self::Builtin(builtin) => {
                 builtin.build_rich_ir(builder);
             }
+            Self::Extern { symbol, signature } => {
+                builder.push(
+                    format!("extern {symbol} {signature}"),
+                    None,
+                    EnumSet::empty(),
+                );
+            }
             Self::List(items) => {
                 builder.push("(", None, EnumSet::empty());
                 builder.push_children_custom(
@@ -147,7 +159,7 @@ impl Constant {
             Self::Struct(fields) => {
                 builder.push("[", None, EnumSet::empty());
                 builder.push_children_custom(
-                    fields.iter().collect_vec(),
+                    fields.iter().sorted().collect_vec(),
                     |builder, (key, value)| {
                         key.build_rich_ir_with_constants(builder, constants);
                         builder.push(": ", None, EnumSet::empty());
@@ -9,16 +9,24 @@ use itertools::Itertools;
 
 #[derive(Clone, Debug, Eq, From, PartialEq)]
 pub enum Expression {
+    /// Frame-allocated tags, lists, and structs don't escape the body creating
+    /// them (see [`crate::mir_optimize::escape_analysis`]). They aren't
+    /// reference-counted and are freed when the body returns.
     CreateTag {
         symbol: String,
         value: Id,
+        is_frame_allocated: bool,
     },
 
-    #[from]
-    CreateList(Vec<Id>),
+    CreateList {
+        items: Vec<Id>,
+        is_frame_allocated: bool,
+    },
 
-    #[from]
-    CreateStruct(Vec<(Id, Id)>),
+    CreateStruct {
+        fields: Vec<(Id, Id)>,
+        is_frame_allocated: bool,
+    },
 
     CreateFunction {
         captured: Vec<Id>,
@@ -94,15 +102,15 @@ pub enum Expression {
 impl Expression {
     pub fn replace_ids(&mut self, mut replacer: impl FnMut(Id) -> Id) {
         match self {
-            Self::CreateTag { symbol: _, value } => {
+            Self::CreateTag { value, .. } => {
                 *value = replacer(*value);
             }
-            Self::CreateList(items) => {
+            Self::CreateList { items, .. } => {
                 for item in items {
                     *item = replacer(*item);
                 }
             }
-            Self::CreateStruct(fields) => {
+            Self::CreateStruct { fields, .. } => {
                 for (key, value) in fields {
                     *key = replacer(*key);
                     *value = replacer(*value);
@@ -212,13 +220,21 @@ impl Expression {
         let body = body.into();
 
         match self {
-            Self::CreateTag { symbol, value } => {
+            Self::CreateTag {
+                symbol,
+                value,
+                is_frame_allocated,
+            } => {
                 let range = builder.push(symbol, TokenType::Symbol, EnumSet::empty());
                 builder.push_reference(ReferenceKey::Symbol(symbol.clone()), range);
                 builder.push(" ", None, EnumSet::empty());
                 value.build_rich_ir_with_constants(builder, constants, body);
+                Self::push_frame_allocation(builder, *is_frame_allocated);
             }
-            Self::CreateList(items) => {
+            Self::CreateList {
+                items,
+                is_frame_allocated,
+            } => {
                 builder.push("(", None, EnumSet::empty());
                 builder.push_children_custom(
                     items,
@@ -229,8 +245,12 @@ impl Expression {
                     builder.push(",", None, EnumSet::empty());
                 }
                 builder.push(")", None, EnumSet::empty());
+                Self::push_frame_allocation(builder, *is_frame_allocated);
             }
-            Self::CreateStruct(fields) => {
+            Self::CreateStruct {
+                fields,
+                is_frame_allocated,
+            } => {
                 builder.push("[", None, EnumSet::empty());
                 builder.push_children_custom(
                     fields.iter().collect_vec(),
@@ -242,6 +262,7 @@ impl Expression {
                     ", ",
                 );
                 builder.push("]", None, EnumSet::empty());
+                Self::push_frame_allocation(builder, *is_frame_allocated);
             }
             Self::CreateFunction { captured, body_id } => {
                 builder.push("{ ", None, EnumSet::empty());
@@ -408,6 +429,11 @@ impl Expression {
             }
         }
     }
+    fn push_frame_allocation(builder: &mut RichIrBuilder, is_frame_allocated: bool) {
+        if is_frame_allocated {
+            builder.push(" # frame-allocated", TokenType::Comment, EnumSet::empty());
+        }
+    }
 }
 
 impl_display_via_richir!(Expression);
@@ -23,7 +23,7 @@
 //! other responsibilities are guaranteed to be constants, so they are not
 //! reference counted anyways.
 
-pub use self::{body::*, constant::*, expression::*, id::*};
+pub use self::{body::*, constant::*, expression::*, id::*, text::*};
 use crate::rich_ir::{RichIrBuilder, ToRichIr, TokenType};
 use enumset::EnumSet;
 
@@ -31,6 +31,7 @@ mod body;
 mod constant;
 mod expression;
 mod id;
+mod text;
 
 // TODO: `impl Hash for Lir`
 // TODO: `impl ToRichIr for Lir`
@@ -0,0 +1,974 @@
+//! A stable, round-trippable textual syntax for the LIR.
+//!
+//! Unlike the rich IR, which is meant for humans and inlines constants, this
+//! format can be parsed back into an identical [`Lir`]. This allows writing
+//! test cases for the VM and native backends directly in LIR and lets external
+//! tools generate LIR.
+//!
+//! ```text
+//! constants:
+//!   %0 = int 42
+//!   %1 = text "Hello, world!"
+//!   %2 = tag Foo %0
+//!   %3 = builtin print
+//!   %4 = hir_id tooling "user" [] code ""
+//!   %5 = function body_0
+//! bodies:
+//!   body_0 captured 0 parameters 1:
+//!     original_hir user "/path/to/package" ["main"] code "main"
+//!     $2 = call $0 [$1] $1
+//! ```
+//!
+//! Constants are written as `%<index> = <kind> <arguments…>` and bodies as a
+//! header followed by their original HIR IDs and expressions, one per line.
+//! IDs must be numbered sequentially. Lists of IDs are enclosed in brackets,
+//! struct fields are written as alternating keys and values, and texts use
+//! Rust's string escapes. Externs are written with their arity
+//! (`extern "candy_add" 2`) or, if typed, with their parameter and return types
+//! (`extern "puts" [text] int`). Frame-allocated tags, lists, and structs end
+//! with `in_frame`. Lines starting with `#` are comments.
+
+use super::{Bodies, Body, BodyId, Constant, ConstantId, Constants, Expression, Id, Lir};
+use crate::{
+    builtin_functions::BuiltinFunction,
+    extern_signature::{ExternSignature, ExternType},
+    hir::{self, IdPath},
+    id::CountableId,
+    module::{Module, ModuleKind, Package},
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+use strum::VariantArray;
+
+impl Lir {
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str("constants:\n");
+        for (id, constant) in self.constants().ids_and_constants() {
+            writeln!(text, "  {id} = {}", ConstantText(constant)).unwrap();
+        }
+        text.push_str("bodies:\n");
+        for (id, body) in self.bodies().ids_and_bodies() {
+            writeln!(
+                text,
+                "  {id} captured {} parameters {}:",
+                body.captured_count(),
+                body.parameter_count(),
+            )
+            .unwrap();
+            for hir_id in body.original_hirs().iter().sorted() {
+                writeln!(text, "    original_hir {}", HirIdText(hir_id)).unwrap();
+            }
+            for (id, expression) in body.ids_and_expressions() {
+                writeln!(text, "    {id} = {}", ExpressionText(expression)).unwrap();
+            }
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, LirParseError> {
+        Parser::default().parse(text)
+    }
+}
+impl FromStr for Lir {
+    type Err = LirParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LirParseError {
+    /// One-based line number.
+    pub line: usize,
+    pub message: String,
+}
+impl Display for LirParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+// Emitting
+
+struct ConstantText<'a>(&'a Constant);
+impl Display for ConstantText<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Constant::Int(int) => write!(f, "int {int}"),
+            Constant::Text(text) => write!(f, "text {text:?}"),
+            Constant::Tag { symbol, value } => {
+                write!(f, "tag {symbol}")?;
+                if let Some(value) = value {
+                    write!(f, " {value}")?;
+                }
+                Ok(())
+            }
+            Constant::Builtin(builtin) => write!(f, "builtin {}", builtin.as_ref()),
+            Constant::Extern { symbol, signature } => {
+                write!(f, "extern {symbol:?} ")?;
+                if signature.is_untyped() {
+                    write!(f, "{}", signature.arity())
+                } else {
+                    write!(
+                        f,
+                        "{} {}",
+                        IdList(&signature.parameters),
+                        signature.return_type,
+                    )
+                }
+            }
+            Constant::List(items) => write!(f, "list {}", IdList(items)),
+            Constant::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .sorted()
+                    .flat_map(<[_; 2]>::from)
+                    .collect_vec();
+                write!(f, "struct {}", IdList(&fields))
+            }
+            Constant::HirId(id) => write!(f, "hir_id {}", HirIdText(id)),
+            Constant::Function(body_id) => write!(f, "function {body_id}"),
+        }
+    }
+}
+
+struct FrameAllocation(bool);
+impl Display for FrameAllocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.0 {
+            write!(f, " in_frame")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct ExpressionText<'a>(&'a Expression);
+impl Display for ExpressionText<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::CreateTag {
+                symbol,
+                value,
+                is_frame_allocated,
+            } => write!(
+                f,
+                "create_tag {symbol} {value}{}",
+                FrameAllocation(*is_frame_allocated),
+            ),
+            Expression::CreateList {
+                items,
+                is_frame_allocated,
+            } => write!(
+                f,
+                "create_list {}{}",
+                IdList(items),
+                FrameAllocation(*is_frame_allocated),
+            ),
+            Expression::CreateStruct {
+                fields,
+                is_frame_allocated,
+            } => {
+                let fields = fields
+                    .iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect_vec();
+                write!(
+                    f,
+                    "create_struct {}{}",
+                    IdList(&fields),
+                    FrameAllocation(*is_frame_allocated),
+                )
+            }
+            Expression::CreateFunction { captured, body_id } => {
+                write!(f, "create_function {body_id} {}", IdList(captured))
+            }
+            Expression::Constant(id) => write!(f, "constant {id}"),
+            Expression::Reference(id) => write!(f, "reference {id}"),
+            Expression::Dup { id, amount } => write!(f, "dup {id} {amount}"),
+            Expression::Drop(id) => write!(f, "drop {id}"),
+            Expression::Call {
+                function,
+                arguments,
+                responsible,
+            } => write!(f, "call {function} {} {responsible}", IdList(arguments)),
+            Expression::IfElse {
+                condition,
+                then_body_id,
+                then_captured,
+                else_body_id,
+                else_captured,
+                responsible,
+            } => write!(
+                f,
+                "if_else {condition} {then_body_id} {} {else_body_id} {} {responsible}",
+                IdList(then_captured),
+                IdList(else_captured),
+            ),
+            Expression::Panic {
+                reason,
+                responsible,
+            } => write!(f, "panic {reason} {responsible}"),
+            Expression::TraceCallStarts {
+                hir_call,
+                function,
+                arguments,
+                responsible,
+            } => write!(
+                f,
+                "trace_call_starts {hir_call} {function} {} {responsible}",
+                IdList(arguments),
+            ),
+            Expression::TraceCallEnds { return_value } => {
+                write!(f, "trace_call_ends")?;
+                if let Some(return_value) = return_value {
+                    write!(f, " {return_value}")?;
+                }
+                Ok(())
+            }
+            Expression::TraceTailCall {
+                hir_call,
+                function,
+                arguments,
+                responsible,
+            } => write!(
+                f,
+                "trace_tail_call {hir_call} {function} {} {responsible}",
+                IdList(arguments),
+            ),
+            Expression::TraceExpressionEvaluated {
+                hir_expression,
+                value,
+            } => write!(f, "trace_expression_evaluated {hir_expression} {value}"),
+            Expression::TraceFoundFuzzableFunction {
+                hir_definition,
+                function,
+            } => write!(
+                f,
+                "trace_found_fuzzable_function {hir_definition} {function}"
+            ),
+        }
+    }
+}
+
+struct IdList<'a, T: Display>(&'a [T]);
+impl<T: Display> Display for IdList<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "[{}]", self.0.iter().join(" "))
+    }
+}
+
+/// `<package kind> <package> [<path segments>] <module kind> <keys>`
+struct HirIdText<'a>(&'a hir::Id);
+impl Display for HirIdText<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let module = &self.0.module;
+        match module.package() {
+            Package::User(path) => write!(f, "user {:?}", path.to_string_lossy())?,
+            Package::Managed(path) => write!(f, "managed {:?}", path.to_string_lossy())?,
+            Package::Anonymous { url } => write!(f, "anonymous {url:?}")?,
+            Package::Tooling(name) => write!(f, "tooling {name:?}")?,
+        }
+        write!(
+            f,
+            " [{}] ",
+            module.path().iter().map(|it| format!("{it:?}")).join(" "),
+        )?;
+        match module.kind() {
+            ModuleKind::Code => write!(f, "code")?,
+            ModuleKind::Asset => write!(f, "asset")?,
+        }
+        write!(f, " {:?}", self.0.keys.to_string())
+    }
+}
+
+// Parsing
+
+#[derive(Default)]
+struct Parser {
+    constants: Constants,
+    constant_count: usize,
+    bodies: Bodies,
+    body_count: usize,
+    current_body: Option<CurrentBody>,
+
+    /// Referenced constant and body IDs are only validated at the end since
+    /// they may refer to definitions further down.
+    referenced_constants: Vec<(usize, ConstantId)>,
+    referenced_bodies: Vec<(usize, BodyId)>,
+}
+struct CurrentBody {
+    original_hirs: FxHashSet<hir::Id>,
+    captured_count: usize,
+    parameter_count: usize,
+    expressions: Vec<Expression>,
+}
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Section {
+    None,
+    Constants,
+    Bodies,
+}
+impl Parser {
+    fn parse(mut self, text: &str) -> Result<Lir, LirParseError> {
+        let mut section = Section::None;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| LirParseError {
+                line: line_number,
+                message,
+            };
+
+            let tokens = tokenize(line).map_err(error)?;
+            let mut tokens = Tokens {
+                tokens: &tokens,
+                line: line_number,
+            };
+            match tokens.peek() {
+                None => continue,
+                Some(Token::Word(word)) if word == "constants:" => {
+                    if section != Section::None {
+                        return Err(error("Unexpected `constants:` section.".to_string()));
+                    }
+                    section = Section::Constants;
+                    tokens.next()?;
+                }
+                Some(Token::Word(word)) if word == "bodies:" => {
+                    if section == Section::Bodies {
+                        return Err(error("Duplicate `bodies:` section.".to_string()));
+                    }
+                    section = Section::Bodies;
+                    tokens.next()?;
+                }
+                Some(_) => match section {
+                    Section::None => {
+                        return Err(error(
+                            "Expected a `constants:` or `bodies:` section.".to_string(),
+                        ))
+                    }
+                    Section::Constants => self.parse_constant(&mut tokens)?,
+                    Section::Bodies => self.parse_body_line(&mut tokens)?,
+                },
+            }
+            tokens.expect_end()?;
+        }
+        self.finish_body();
+
+        for (line, id) in self.referenced_constants {
+            if id.to_usize() >= self.constant_count {
+                return Err(LirParseError {
+                    line,
+                    message: format!("Constant {id} is not defined."),
+                });
+            }
+        }
+        for (line, id) in self.referenced_bodies {
+            if id.to_usize() >= self.body_count {
+                return Err(LirParseError {
+                    line,
+                    message: format!("Body {id} is not defined."),
+                });
+            }
+        }
+        Ok(Lir::new(self.constants, self.bodies))
+    }
+
+    fn parse_constant(&mut self, tokens: &mut Tokens) -> Result<(), LirParseError> {
+        let id = tokens.constant_id()?;
+        if id.to_usize() != self.constant_count {
+            return Err(tokens.error(format!(
+                "Expected constant %{}, found {id}.",
+                self.constant_count,
+            )));
+        }
+        tokens.expect_word("=")?;
+
+        let kind = tokens.word()?;
+        let constant = match kind.as_str() {
+            "int" => {
+                let int = tokens.word()?;
+                Constant::Int(
+                    int.parse::<BigInt>()
+                        .map_err(|error| tokens.error(format!("Invalid int `{int}`: {error}")))?,
+                )
+            }
+            "text" => Constant::Text(tokens.text()?),
+            "tag" => {
+                let symbol = tokens.word()?;
+                let value = if tokens.peek().is_some() {
+                    Some(self.referenced_constant(tokens)?)
+                } else {
+                    None
+                };
+                Constant::Tag { symbol, value }
+            }
+            "builtin" => {
+                let name = tokens.word()?;
+                let builtin = BuiltinFunction::VARIANTS
+                    .iter()
+                    .find(|it| it.as_ref() == name)
+                    .ok_or_else(|| tokens.error(format!("Unknown builtin `{name}`.")))?;
+                Constant::Builtin(*builtin)
+            }
+            "extern" => {
+                let symbol = tokens.text()?;
+                let signature = if tokens.peek() == Some(&Token::OpeningBracket) {
+                    ExternSignature {
+                        parameters: tokens.list(Tokens::extern_type)?,
+                        return_type: tokens.extern_type()?,
+                    }
+                } else {
+                    ExternSignature::untyped(tokens.usize()?)
+                };
+                Constant::Extern { symbol, signature }
+            }
+            "list" => Constant::List(self.referenced_constant_list(tokens)?),
+            "struct" => {
+                let fields = self.referenced_constant_list(tokens)?;
+                if fields.len() % 2 != 0 {
+                    return Err(tokens.error(
+                        "Struct fields must consist of alternating keys and values.".to_string(),
+                    ));
+                }
+                let fields: FxHashMap<_, _> = fields.into_iter().tuples().collect();
+                Constant::Struct(fields)
+            }
+            "hir_id" => Constant::HirId(tokens.hir_id()?),
+            "function" => Constant::Function(self.referenced_body(tokens)?),
+            _ => return Err(tokens.error(format!("Unknown constant kind `{kind}`."))),
+        };
+        self.constants.push(constant);
+        self.constant_count += 1;
+        Ok(())
+    }
+
+    fn parse_body_line(&mut self, tokens: &mut Tokens) -> Result<(), LirParseError> {
+        match tokens.peek() {
+            Some(Token::Word(word)) if word.starts_with("body_") => {
+                self.finish_body();
+                let id = tokens.body_id()?;
+                if id.to_usize() != self.body_count {
+                    return Err(tokens.error(format!(
+                        "Expected body body_{}, found {id}.",
+                        self.body_count,
+                    )));
+                }
+                tokens.expect_word("captured")?;
+                let captured_count = tokens.usize()?;
+                tokens.expect_word("parameters")?;
+                let parameter_count = tokens.word()?;
+                let parameter_count = parameter_count
+                    .strip_suffix(':')
+                    .and_then(|it| it.parse().ok())
+                    .ok_or_else(|| {
+                        tokens.error(format!(
+                            "Expected a parameter count followed by `:`, found `{parameter_count}`.",
+                        ))
+                    })?;
+                self.current_body = Some(CurrentBody {
+                    original_hirs: FxHashSet::default(),
+                    captured_count,
+                    parameter_count,
+                    expressions: vec![],
+                });
+                self.body_count += 1;
+            }
+            Some(Token::Word(word)) if word == "original_hir" => {
+                tokens.next()?;
+                let id = tokens.hir_id()?;
+                let body = self.current_body(tokens)?;
+                if !body.expressions.is_empty() {
+                    return Err(tokens
+                        .error("Original HIR IDs must come before the expressions.".to_string()));
+                }
+                body.original_hirs.insert(id);
+            }
+            _ => {
+                let id = tokens.id()?;
+                let expected_id = {
+                    let body = self.current_body(tokens)?;
+                    body.captured_count + body.parameter_count + 1 + body.expressions.len()
+                };
+                if id.to_usize() != expected_id {
+                    return Err(tokens.error(format!("Expected ${expected_id}, found {id}.")));
+                }
+                tokens.expect_word("=")?;
+                let expression = self.parse_expression(tokens, id)?;
+                self.current_body(tokens)?.expressions.push(expression);
+            }
+        }
+        Ok(())
+    }
+    fn parse_expression(
+        &mut self,
+        tokens: &mut Tokens,
+        own_id: Id,
+    ) -> Result<Expression, LirParseError> {
+        let local = |tokens: &mut Tokens| {
+            let id = tokens.id()?;
+            if id >= own_id {
+                return Err(tokens.error(format!("{id} is not yet defined.")));
+            }
+            Ok(id)
+        };
+        let locals = |tokens: &mut Tokens| {
+            let ids = tokens.list(Tokens::id)?;
+            if let Some(id) = ids.iter().find(|it| **it >= own_id) {
+                return Err(tokens.error(format!("{id} is not yet defined.")));
+            }
+            Ok(ids)
+        };
+
+        let kind = tokens.word()?;
+        let expression = match kind.as_str() {
+            "create_tag" => Expression::CreateTag {
+                symbol: tokens.word()?,
+                value: local(tokens)?,
+                is_frame_allocated: tokens.optional_word("in_frame"),
+            },
+            "create_list" => Expression::CreateList {
+                items: locals(tokens)?,
+                is_frame_allocated: tokens.optional_word("in_frame"),
+            },
+            "create_struct" => {
+                let fields = locals(tokens)?;
+                if fields.len() % 2 != 0 {
+                    return Err(tokens.error(
+                        "Struct fields must consist of alternating keys and values.".to_string(),
+                    ));
+                }
+                Expression::CreateStruct {
+                    fields: fields.into_iter().tuples().collect(),
+                    is_frame_allocated: tokens.optional_word("in_frame"),
+                }
+            }
+            "create_function" => Expression::CreateFunction {
+                body_id: self.referenced_body(tokens)?,
+                captured: locals(tokens)?,
+            },
+            "constant" => Expression::Constant(self.referenced_constant(tokens)?),
+            "reference" => Expression::Reference(local(tokens)?),
+            "dup" => Expression::Dup {
+                id: local(tokens)?,
+                amount: tokens.usize()?,
+            },
+            "drop" => Expression::Drop(local(tokens)?),
+            "call" => Expression::Call {
+                function: local(tokens)?,
+                arguments: locals(tokens)?,
+                responsible: local(tokens)?,
+            },
+            "if_else" => Expression::IfElse {
+                condition: local(tokens)?,
+                then_body_id: self.referenced_body(tokens)?,
+                then_captured: locals(tokens)?,
+                else_body_id: self.referenced_body(tokens)?,
+                else_captured: locals(tokens)?,
+                responsible: local(tokens)?,
+            },
+            "panic" => Expression::Panic {
+                reason: local(tokens)?,
+                responsible: local(tokens)?,
+            },
+            "trace_call_starts" => Expression::TraceCallStarts {
+                hir_call: local(tokens)?,
+                function: local(tokens)?,
+                arguments: locals(tokens)?,
+                responsible: local(tokens)?,
+            },
+            "trace_call_ends" => Expression::TraceCallEnds {
+                return_value: if tokens.peek().is_some() {
+                    Some(local(tokens)?)
+                } else {
+                    None
+                },
+            },
+            "trace_tail_call" => Expression::TraceTailCall {
+                hir_call: local(tokens)?,
+                function: local(tokens)?,
+                arguments: locals(tokens)?,
+                responsible: local(tokens)?,
+            },
+            "trace_expression_evaluated" => Expression::TraceExpressionEvaluated {
+                hir_expression: local(tokens)?,
+                value: local(tokens)?,
+            },
+            "trace_found_fuzzable_function" => Expression::TraceFoundFuzzableFunction {
+                hir_definition: local(tokens)?,
+                function: local(tokens)?,
+            },
+            _ => return Err(tokens.error(format!("Unknown expression kind `{kind}`."))),
+        };
+        Ok(expression)
+    }
+
+    fn current_body(&mut self, tokens: &Tokens) -> Result<&mut CurrentBody, LirParseError> {
+        self.current_body
+            .as_mut()
+            .ok_or_else(|| tokens.error("Expected a body header.".to_string()))
+    }
+    fn finish_body(&mut self) {
+        if let Some(current_body) = self.current_body.take() {
+            let mut body = Body::new(
+                current_body.original_hirs,
+                current_body.captured_count,
+                current_body.parameter_count,
+            );
+            for expression in current_body.expressions {
+                body.push(expression);
+            }
+            self.bodies.push(body);
+        }
+    }
+
+    fn referenced_constant(&mut self, tokens: &mut Tokens) -> Result<ConstantId, LirParseError> {
+        let id = tokens.constant_id()?;
+        self.referenced_constants.push((tokens.line, id));
+        Ok(id)
+    }
+    fn referenced_constant_list(
+        &mut self,
+        tokens: &mut Tokens,
+    ) -> Result<Vec<ConstantId>, LirParseError> {
+        let ids = tokens.list(Tokens::constant_id)?;
+        self.referenced_constants
+            .extend(ids.iter().map(|id| (tokens.line, *id)));
+        Ok(ids)
+    }
+    fn referenced_body(&mut self, tokens: &mut Tokens) -> Result<BodyId, LirParseError> {
+        let id = tokens.body_id()?;
+        self.referenced_bodies.push((tokens.line, id));
+        Ok(id)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    OpeningBracket,
+    ClosingBracket,
+}
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '#' => break,
+            _ if char.is_whitespace() => {}
+            '[' => tokens.push(Token::OpeningBracket),
+            ']' => tokens.push(Token::ClosingBracket),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("Unterminated text.".to_string()),
+                        Some('"') => break,
+                        Some('\\') => text.push(parse_escape(&mut chars)?),
+                        Some(char) => text.push(char),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            _ => {
+                let mut word = char.to_string();
+                while let Some(&char) = chars.peek()
+                    && !char.is_whitespace()
+                    && !matches!(char, '[' | ']' | '"' | '#')
+                {
+                    word.push(char);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+/// Parses the escape sequences produced by Rust's `Debug` implementation for
+/// strings.
+fn parse_escape(chars: &mut impl Iterator<Item = char>) -> Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('0') => Ok('\0'),
+        Some(char @ ('\\' | '"' | '\'')) => Ok(char),
+        Some('u') => {
+            if chars.next() != Some('{') {
+                return Err("Expected `{` after `\\u`.".to_string());
+            }
+            let hex: String = chars.take_while(|it| *it != '}').collect();
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("Invalid unicode escape `\\u{{{hex}}}`."))
+        }
+        Some(char) => Err(format!("Unknown escape sequence `\\{char}`.")),
+        None => Err("Unterminated text.".to_string()),
+    }
+}
+
+struct Tokens<'a> {
+    tokens: &'a [Token],
+    line: usize,
+}
+impl<'a> Tokens<'a> {
+    const fn error(&self, message: String) -> LirParseError {
+        LirParseError {
+            line: self.line,
+            message,
+        }
+    }
+
+    const fn peek(&self) -> Option<&'a Token> {
+        self.tokens.first()
+    }
+    fn next(&mut self) -> Result<&'a Token, LirParseError> {
+        let (first, rest) = self
+            .tokens
+            .split_first()
+            .ok_or_else(|| self.error("Unexpected end of line.".to_string()))?;
+        self.tokens = rest;
+        Ok(first)
+    }
+    fn expect_end(&self) -> Result<(), LirParseError> {
+        self.peek().map_or(Ok(()), |token| {
+            Err(self.error(format!("Unexpected {token:?}.")))
+        })
+    }
+
+    fn word(&mut self) -> Result<String, LirParseError> {
+        match self.next()? {
+            Token::Word(word) => Ok(word.clone()),
+            token => Err(self.error(format!("Expected a word, found {token:?}."))),
+        }
+    }
+    fn expect_word(&mut self, expected: &str) -> Result<(), LirParseError> {
+        let word = self.word()?;
+        if word == expected {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected `{expected}`, found `{word}`.")))
+        }
+    }
+    /// Consumes the next token if it's the `expected` word.
+    fn optional_word(&mut self, expected: &str) -> bool {
+        let is_present = matches!(self.peek(), Some(Token::Word(word)) if word == expected);
+        if is_present {
+            self.tokens = &self.tokens[1..];
+        }
+        is_present
+    }
+    fn text(&mut self) -> Result<String, LirParseError> {
+        match self.next()? {
+            Token::Text(text) => Ok(text.clone()),
+            token => Err(self.error(format!("Expected a text, found {token:?}."))),
+        }
+    }
+    fn usize(&mut self) -> Result<usize, LirParseError> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|_| self.error(format!("Expected a number, found `{word}`.")))
+    }
+    fn extern_type(&mut self) -> Result<ExternType, LirParseError> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|()| self.error(format!("Expected an extern type, found `{word}`.")))
+    }
+    fn list<T>(
+        &mut self,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, LirParseError>,
+    ) -> Result<Vec<T>, LirParseError> {
+        if self.next()? != &Token::OpeningBracket {
+            return Err(self.error("Expected `[`.".to_string()));
+        }
+        let mut items = vec![];
+        while self.peek() != Some(&Token::ClosingBracket) {
+            items.push(parse_item(self)?);
+        }
+        self.next()?;
+        Ok(items)
+    }
+
+    fn prefixed_index(&mut self, prefix: &str) -> Result<usize, LirParseError> {
+        let word = self.word()?;
+        word.strip_prefix(prefix)
+            .and_then(|it| it.parse().ok())
+            .ok_or_else(|| self.error(format!("Expected `{prefix}<index>`, found `{word}`.")))
+    }
+    fn id(&mut self) -> Result<Id, LirParseError> {
+        self.prefixed_index("$").map(Id::from_usize)
+    }
+    fn constant_id(&mut self) -> Result<ConstantId, LirParseError> {
+        self.prefixed_index("%").map(ConstantId::from_usize)
+    }
+    fn body_id(&mut self) -> Result<BodyId, LirParseError> {
+        self.prefixed_index("body_").map(BodyId::from_usize)
+    }
+
+    fn hir_id(&mut self) -> Result<hir::Id, LirParseError> {
+        let package_kind = self.word()?;
+        let package = self.text()?;
+        let package = match package_kind.as_str() {
+            "user" => Package::User(PathBuf::from(package)),
+            "managed" => Package::Managed(PathBuf::from(package)),
+            "anonymous" => Package::Anonymous { url: package },
+            "tooling" => Package::Tooling(package),
+            _ => return Err(self.error(format!("Unknown package kind `{package_kind}`."))),
+        };
+        let path = self.list(Self::text)?;
+        let kind = match self.word()?.as_str() {
+            "code" => ModuleKind::Code,
+            "asset" => ModuleKind::Asset,
+            kind => return Err(self.error(format!("Unknown module kind `{kind}`."))),
+        };
+        let keys = IdPath::from(self.text()?);
+        Ok(hir::Id {
+            module: Module::new(package, path, kind),
+            keys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Lir {
+        let module = Module::new(
+            Package::User(PathBuf::from("/path/to/package")),
+            vec!["foo".to_string(), "bar".to_string()],
+            ModuleKind::Code,
+        );
+        let hir_id = hir::Id {
+            module,
+            keys: IdPath::from("main".to_string()),
+        };
+
+        let mut constants = Constants::default();
+        let int = constants.push(BigInt::from(-42));
+        let text = constants.push("Hello, \"world\"!\n\u{1}".to_string());
+        let tag = constants.push(Constant::Tag {
+            symbol: "Foo".to_string(),
+            value: Some(int),
+        });
+        let empty_tag = constants.push(Constant::Tag {
+            symbol: "Bar".to_string(),
+            value: None,
+        });
+        let builtin = constants.push(BuiltinFunction::Print);
+        constants.push(Constant::Extern {
+            symbol: "candy_add".to_string(),
+            signature: ExternSignature::untyped(2),
+        });
+        constants.push(Constant::Extern {
+            symbol: "puts".to_string(),
+            signature: ExternSignature {
+                parameters: vec![ExternType::Text],
+                return_type: ExternType::Int,
+            },
+        });
+        constants.push(vec![int, text]);
+        constants.push(FxHashMap::from_iter([(tag, int), (empty_tag, text)]));
+        let responsible = constants.push(hir_id.clone());
+        constants.push(Constant::HirId(hir::Id::user()));
+        let function = constants.push(BodyId::from_usize(1));
+
+        let mut bodies = Bodies::default();
+        let mut main = Body::new(FxHashSet::from_iter([hir_id]), 0, 0);
+        let builtin = main.push(Expression::Constant(builtin));
+        let text = main.push(Expression::Constant(text));
+        let responsible = main.push(Expression::Constant(responsible));
+        let list = main.push(Expression::CreateList {
+            items: vec![text, text],
+            is_frame_allocated: false,
+        });
+        main.push(Expression::Dup {
+            id: text,
+            amount: 2,
+        });
+        main.push(Expression::CreateStruct {
+            fields: vec![(text, list)],
+            is_frame_allocated: true,
+        });
+        main.push(Expression::CreateFunction {
+            captured: vec![text],
+            body_id: BodyId::from_usize(1),
+        });
+        main.push(Expression::Constant(function));
+        let result = main.push(Expression::Call {
+            function: builtin,
+            arguments: vec![text],
+            responsible,
+        });
+        main.push(Expression::TraceCallEnds {
+            return_value: Some(result),
+        });
+        main.push(Expression::TraceCallEnds { return_value: None });
+        main.push(Expression::Drop(list));
+        bodies.push(main);
+
+        let mut function = Body::new(FxHashSet::default(), 1, 1);
+        let reason = function.push(Expression::Reference(Id::from_usize(0)));
+        function.push(Expression::Panic {
+            reason,
+            responsible: Id::from_usize(2),
+        });
+        bodies.push(function);
+
+        Lir::new(constants, bodies)
+    }
+
+    #[test]
+    fn round_trips() {
+        let lir = example();
+        let text = lir.to_text();
+        assert_eq!(Lir::parse(&text), Ok(lir));
+        assert_eq!(Lir::parse(&text).unwrap().to_text(), text);
+    }
+
+    #[test]
+    fn parses_handwritten_lir() {
+        let text = r#"
+            # Prints a greeting.
+            constants:
+              %0 = text "Hi"
+              %1 = builtin print
+              %2 = hir_id tooling "user" [] code ""
+            bodies:
+              body_0 captured 0 parameters 0:
+                $1 = constant %1
+                $2 = constant %0
+                $3 = constant %2
+                $4 = call $1 [$2] $3
+        "#;
+        let lir = Lir::parse(text).unwrap();
+        assert_eq!(
+            lir.bodies().get(BodyId::from_usize(0)).expressions().len(),
+            4
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        let error = |text: &str| Lir::parse(text).unwrap_err();
+        assert_eq!(error("%0 = int 1").line, 1);
+        assert_eq!(error("constants:\n  %1 = int 1").line, 2);
+        assert_eq!(error("constants:\n  %0 = function body_0").line, 2);
+        assert_eq!(
+            error("bodies:\n  body_0 captured 0 parameters 0:\n    $1 = reference $1").line,
+            3,
+        );
+        assert_eq!(error("constants:\n  %0 = text \"foo").line, 2);
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     lir::{Bodies, Body, Expression, Id, Lir},
     mir_to_lir::{LirResult, MirToLir},
     utils::{HashMapExtension, HashSetExtension},
-    TracingConfig,
+    OptimizationConfig, TracingConfig,
 };
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -11,7 +11,12 @@ use std::{collections::hash_map::Entry, sync::Arc};
 
 #[salsa::query_group(OptimizeLirStorage)]
 pub trait OptimizeLir: MirToLir {
-    fn optimized_lir(&self, target: ExecutionTarget, tracing: TracingConfig) -> LirResult;
+    fn optimized_lir(
+        &self,
+        target: ExecutionTarget,
+        tracing: TracingConfig,
+        optimization: OptimizationConfig,
+    ) -> LirResult;
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -19,17 +24,21 @@ fn optimized_lir(
     db: &dyn OptimizeLir,
     target: ExecutionTarget,
     tracing: TracingConfig,
+    optimization: OptimizationConfig,
 ) -> LirResult {
-    let (lir, errors) = db.lir(target, tracing)?;
+    let (lir, errors) = db.lir(target, tracing, optimization)?;
+    Ok((Arc::new(optimize_lir(&lir)), errors))
+}
 
+/// Optimizes LIR that wasn't produced by the [`lir`](MirToLir::lir) query.
+#[must_use]
+pub fn optimize_lir(lir: &Lir) -> Lir {
     let mut bodies = Bodies::default();
     for (id, body) in lir.bodies().ids_and_bodies() {
         let new_id = bodies.push(body.optimize());
         assert_eq!(id, new_id);
     }
-
-    let optimized_lir = Lir::new(lir.constants().clone(), bodies);
-    Ok((Arc::new(optimized_lir), errors))
+    Lir::new(lir.constants().clone(), bodies)
 }
 
 impl Body {
@@ -0,0 +1,200 @@
+//! Symbol names for code generated by the native backends.
+//!
+//! Names derived from Candy code (e.g., the content of a text constant or the
+//! HIR IDs of a function) may contain characters that aren't allowed in object
+//! files, and different expressions may derive the same name. A mangled name
+//! is always unique since it contains the expression's MIR ID, and it only
+//! consists of ASCII letters, digits, and underscores:
+//!
+//! ```text
+//! _C <kind> <MIR ID> _ <name>
+//! ```
+//!
+//! In the name, ASCII letters and digits are kept and all other bytes are
+//! written as an underscore followed by two uppercase hex digits. For example,
+//! the text `"Hi!"` with the ID `$12` becomes `_CT12_Hi_21`.
+
+use crate::{id::CountableId, mir::Id};
+use std::fmt::{self, Display, Formatter, Write};
+
+const PREFIX: &str = "_C";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SymbolKind {
+    Int,
+    Text,
+    Tag,
+    List,
+    HirId,
+    Builtin,
+    Extern,
+    /// The machine code of a function
+    Function,
+    /// The Candy value of a function, i.e., the code and its captured values
+    FunctionObject,
+}
+impl SymbolKind {
+    const fn code(self) -> char {
+        match self {
+            Self::Int => 'I',
+            Self::Text => 'T',
+            Self::Tag => 'G',
+            Self::List => 'L',
+            Self::HirId => 'H',
+            Self::Builtin => 'B',
+            Self::Extern => 'E',
+            Self::Function => 'F',
+            Self::FunctionObject => 'O',
+        }
+    }
+    const fn from_code(code: char) -> Option<Self> {
+        let kind = match code {
+            'I' => Self::Int,
+            'T' => Self::Text,
+            'G' => Self::Tag,
+            'L' => Self::List,
+            'H' => Self::HirId,
+            'B' => Self::Builtin,
+            'E' => Self::Extern,
+            'F' => Self::Function,
+            'O' => Self::FunctionObject,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+impl Display for SymbolKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Int => "int",
+            Self::Text => "text",
+            Self::Tag => "tag",
+            Self::List => "list",
+            Self::HirId => "HIR ID",
+            Self::Builtin => "builtin",
+            Self::Extern => "extern function",
+            Self::Function => "function",
+            Self::FunctionObject => "function object",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[must_use]
+pub fn mangle(kind: SymbolKind, id: Id, name: &str) -> String {
+    let mut mangled = format!("{PREFIX}{}{}_", kind.code(), id.to_usize());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            mangled.push(byte as char);
+        } else {
+            write!(mangled, "_{byte:02X}").unwrap();
+        }
+    }
+    mangled
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DemangledSymbol {
+    pub kind: SymbolKind,
+    pub id: Id,
+    pub name: String,
+}
+impl Display for DemangledSymbol {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {} ({})", self.kind, self.name, self.id)
+    }
+}
+
+/// Returns [`None`] if `symbol` isn't a mangled name.
+#[must_use]
+pub fn demangle(symbol: &str) -> Option<DemangledSymbol> {
+    let rest = symbol.strip_prefix(PREFIX)?;
+    let mut chars = rest.chars();
+    let kind = SymbolKind::from_code(chars.next()?)?;
+    let (id, escaped_name) = chars.as_str().split_once('_')?;
+    if id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let id = Id::from_usize(id.parse().ok()?);
+
+    let escaped_name = escaped_name.as_bytes();
+    let mut name = vec![];
+    let mut index = 0;
+    while index < escaped_name.len() {
+        match escaped_name[index] {
+            b'_' => {
+                let hex = escaped_name.get(index + 1..index + 3)?;
+                name.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                index += 3;
+            }
+            byte if byte.is_ascii_alphanumeric() => {
+                name.push(byte);
+                index += 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(DemangledSymbol {
+        kind,
+        id,
+        name: String::from_utf8(name).ok()?,
+    })
+}
+
+/// Replaces all mangled names in `text` (e.g., a stack trace of a native
+/// program) with their demangled form.
+#[must_use]
+pub fn demangle_all(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PREFIX) {
+        let is_start_of_word = !rest[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|it| it.is_ascii_alphanumeric() || it == '_');
+        let length = rest[start..]
+            .find(|it: char| !(it.is_ascii_alphanumeric() || it == '_'))
+            .unwrap_or(rest.len() - start);
+        let word = &rest[start..start + length];
+        result.push_str(&rest[..start]);
+        match demangle(word).filter(|_| is_start_of_word) {
+            Some(demangled) => write!(result, "{demangled}").unwrap(),
+            None => result.push_str(word),
+        }
+        rest = &rest[start + length..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{demangle, demangle_all, mangle, SymbolKind};
+    use crate::{id::CountableId, mir::Id};
+
+    #[test]
+    fn mangled_names_are_valid_symbols() {
+        let mangled = mangle(SymbolKind::Text, Id::from_usize(12), "Hi!");
+        assert_eq!(mangled, "_CT12_Hi_21");
+    }
+
+    #[test]
+    fn demangling_reverses_mangling() {
+        for name in ["", "main", "Examples:helloWorld:0, Core:int:3", "ünicode_"] {
+            let mangled = mangle(SymbolKind::Function, Id::from_usize(3), name);
+            let demangled = demangle(&mangled).unwrap();
+            assert_eq!(demangled.kind, SymbolKind::Function);
+            assert_eq!(demangled.id, Id::from_usize(3));
+            assert_eq!(demangled.name, name);
+        }
+    }
+
+    #[test]
+    fn demangles_names_in_text() {
+        let mangled = mangle(SymbolKind::Function, Id::from_usize(4), "foo");
+        assert_eq!(
+            demangle_all(&format!("at {mangled}+0x12 in x_CT1_a")),
+            "at function foo ($4)+0x12 in x_CT1_a",
+        );
+    }
+}
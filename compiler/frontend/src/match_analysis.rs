@@ -0,0 +1,612 @@
+//! Finds match cases that can never be reached and matches that don't handle
+//! all values.
+//!
+//! Candy is dynamically typed, so we don't know which values a match can
+//! receive. The two checks therefore make different assumptions:
+//!
+//! - A case is unreachable if no value at all reaches it, i.e., every value it
+//!   matches is matched by a case above it. Here, we consider all values, so a
+//!   `_` after `True` and `False` is still reachable, e.g., for `Foo`.
+//! - A match is non-exhaustive if its patterns don't cover all values of the
+//!   type that the patterns imply. Tags only consist of the tags that appear in
+//!   the patterns, lists only have the lengths that appear in the patterns, and
+//!   structs contain all keys that appear in the patterns. Ints and texts have
+//!   infinitely many values, so they're only covered by a catch-all pattern.
+//!   This way, a match on `True` and `False` is exhaustive, but one on `Foo 1`
+//!   and `Foo 2` isn't.
+//!
+//! Both checks use the usefulness algorithm from Luc Maranget's paper
+//! [“Warnings for pattern matching”](http://moscova.inria.fr/~maranget/papers/warn/index.html).
+
+use crate::hir::Pattern;
+use itertools::Itertools;
+use num_bigint::BigUint;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::iter;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MatchAnalysis {
+    /// Indices of cases that can never be reached.
+    pub unreachable_cases: Vec<usize>,
+    /// An example of a value that no case matches, formatted as a pattern in
+    /// which `_` stands for any value not handled by the cases.
+    pub missing_value: Option<String>,
+}
+
+/// Returns [`None`] if a pattern contains errors.
+#[must_use]
+pub fn analyze_match<'a>(patterns: impl IntoIterator<Item = &'a Pattern>) -> Option<MatchAnalysis> {
+    let rows: Vec<Pat> = patterns
+        .into_iter()
+        .map(Pat::from_hir)
+        .collect::<Option<_>>()?;
+
+    let signatures = Signatures::collect(&rows);
+    let paths = [String::new()];
+
+    let open = Usefulness {
+        signatures: &signatures,
+        world: World::Open,
+    };
+    let unreachable_cases = (0..rows.len())
+        .filter(|&index| {
+            let matrix = rows[..index]
+                .iter()
+                .map(|row| vec![row.clone()])
+                .collect_vec();
+            open.find(&matrix, &[rows[index].clone()], &paths).is_none()
+        })
+        .collect();
+
+    let closed = Usefulness {
+        signatures: &signatures,
+        world: World::Closed,
+    };
+    let matrix = rows.iter().map(|row| vec![row.clone()]).collect_vec();
+    let missing_value = closed
+        .find(&matrix, &[Pat::Wildcard], &paths)
+        .map(|witnesses| witnesses.into_iter().exactly_one().unwrap().to_string());
+
+    Some(MatchAnalysis {
+        unreachable_cases,
+        missing_value,
+    })
+}
+
+/// Which values exist besides those mentioned in patterns. See the module
+/// documentation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum World {
+    /// All Candy values.
+    Open,
+    /// Only the values of the type implied by the patterns.
+    Closed,
+}
+
+#[derive(Clone, Debug)]
+enum Pat<'a> {
+    Wildcard,
+    Int(&'a BigUint),
+    Text(&'a str),
+    Tag {
+        symbol: &'a str,
+        value: Option<Box<Pat<'a>>>,
+    },
+    List(Vec<Pat<'a>>),
+    /// Keys are sorted and unique.
+    Struct(Vec<(String, Pat<'a>)>),
+    /// A struct field that is present, as opposed to a missing one. Struct
+    /// patterns don't care about keys they don't mention, which may be
+    /// missing.
+    Present(Box<Pat<'a>>),
+    Or(Vec<Pat<'a>>),
+}
+impl<'a> Pat<'a> {
+    fn from_hir(pattern: &'a Pattern) -> Option<Self> {
+        Some(match pattern {
+            Pattern::NewIdentifier(_) => Self::Wildcard,
+            Pattern::Int(int) => Self::Int(int),
+            Pattern::Text(text) => Self::Text(text),
+            Pattern::Tag { symbol, value } => Self::Tag {
+                symbol,
+                value: match value {
+                    Some(value) => Some(Box::new(Self::from_hir(value)?)),
+                    None => None,
+                },
+            },
+            Pattern::List(items) => {
+                Self::List(items.iter().map(Self::from_hir).collect::<Option<_>>()?)
+            }
+            Pattern::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, value)| Some((key_text(key)?, Self::from_hir(value)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                // For duplicate keys, the value has to match all patterns. We
+                // conservatively only keep the first one.
+                let fields = fields
+                    .into_iter()
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .dedup_by(|(a, _), (b, _)| a == b)
+                    .collect();
+                Self::Struct(fields)
+            }
+            Pattern::Or(patterns) => {
+                Self::Or(patterns.iter().map(Self::from_hir).collect::<Option<_>>()?)
+            }
+            Pattern::Error { .. } => return None,
+        })
+    }
+
+    fn constructor(&self) -> Option<Constructor<'a>> {
+        match self {
+            Self::Wildcard | Self::Or(_) => None,
+            Self::Int(int) => Some(Constructor::Int(int)),
+            Self::Text(text) => Some(Constructor::Text(text)),
+            Self::Tag { symbol, value } => Some(Constructor::Tag {
+                symbol,
+                has_value: value.is_some(),
+            }),
+            Self::List(items) => Some(Constructor::List(items.len())),
+            Self::Struct(_) => Some(Constructor::Struct),
+            Self::Present(_) => Some(Constructor::Present),
+        }
+    }
+    /// The patterns for the constructor's fields, in the order of
+    /// [`Constructor::field_paths`].
+    fn fields(&self, struct_keys: &[String]) -> Vec<Self> {
+        match self {
+            Self::Wildcard | Self::Or(_) => unreachable!(),
+            Self::Int(_) | Self::Text(_) => vec![],
+            Self::Tag { value, .. } => value.iter().map(|value| (**value).clone()).collect(),
+            Self::List(items) => items.clone(),
+            Self::Struct(fields) => struct_keys
+                .iter()
+                .map(|key| {
+                    fields
+                        .iter()
+                        .find(|(it, _)| it == key)
+                        .map_or(Self::Wildcard, |(_, value)| {
+                            Self::Present(Box::new(value.clone()))
+                        })
+                })
+                .collect(),
+            Self::Present(value) => vec![(**value).clone()],
+        }
+    }
+}
+
+/// Formats a struct key, which can't contain identifiers.
+fn key_text(key: &Pattern) -> Option<String> {
+    Some(match key {
+        Pattern::Int(int) => int.to_string(),
+        Pattern::Text(text) => format!("\"{text}\""),
+        Pattern::Tag { symbol, value } => match value {
+            Some(value) => format!("{symbol} ({})", key_text(value)?),
+            None => symbol.clone(),
+        },
+        Pattern::List(items) => match items.as_slice() {
+            [] => "(,)".to_string(),
+            [item] => format!("({},)", key_text(item)?),
+            items => format!(
+                "({})",
+                items
+                    .iter()
+                    .map(key_text)
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", "),
+            ),
+        },
+        Pattern::Struct(fields) => format!(
+            "[{}]",
+            fields
+                .iter()
+                .map(|(key, value)| Some(format!("{}: {}", key_text(key)?, key_text(value)?)))
+                .collect::<Option<Vec<_>>>()?
+                .join(", "),
+        ),
+        Pattern::NewIdentifier(_) | Pattern::Or(_) | Pattern::Error { .. } => return None,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Constructor<'a> {
+    Int(&'a BigUint),
+    Text(&'a str),
+    Tag {
+        symbol: &'a str,
+        has_value: bool,
+    },
+    List(usize),
+    /// Structs are a single constructor whose fields are all keys used in a
+    /// column.
+    Struct,
+    Present,
+}
+impl Constructor<'_> {
+    /// Identifies where in the matched value the constructor's fields are.
+    fn field_paths(self, path: &str, struct_keys: &[String]) -> Vec<String> {
+        match self {
+            Self::Int(_) | Self::Text(_) => vec![],
+            Self::Tag { symbol, has_value } => {
+                if has_value {
+                    vec![format!("{path}/{symbol}")]
+                } else {
+                    vec![]
+                }
+            }
+            Self::List(length) => (0..length)
+                .map(|index| format!("{path}/{length}.{index}"))
+                .collect(),
+            Self::Struct => struct_keys
+                .iter()
+                .map(|key| format!("{path}/[{key}]"))
+                .collect(),
+            Self::Present => vec![format!("{path}/?")],
+        }
+    }
+}
+
+/// The constructors that patterns use at each path (see
+/// [`Constructor::field_paths`]). In the closed world, these are all values
+/// at that path.
+#[derive(Debug, Default)]
+struct Signatures<'a>(FxHashMap<String, Vec<Constructor<'a>>>);
+impl<'a> Signatures<'a> {
+    fn collect(rows: &[Pat<'a>]) -> Self {
+        let mut signatures = Self::default();
+        for row in rows {
+            signatures.add(row, "");
+        }
+        signatures
+    }
+    fn add(&mut self, pattern: &Pat<'a>, path: &str) {
+        if let Pat::Or(patterns) = pattern {
+            for pattern in patterns {
+                self.add(pattern, path);
+            }
+            return;
+        }
+        let Some(constructor) = pattern.constructor() else {
+            return;
+        };
+
+        let constructors = self.0.entry(path.to_string()).or_default();
+        if !constructors.contains(&constructor) {
+            constructors.push(constructor);
+        }
+
+        let struct_keys = match pattern {
+            Pat::Struct(fields) => fields.iter().map(|(key, _)| key.clone()).collect(),
+            _ => vec![],
+        };
+        for (field, path) in pattern
+            .fields(&struct_keys)
+            .iter()
+            .zip_eq(constructor.field_paths(path, &struct_keys))
+        {
+            self.add(field, &path);
+        }
+    }
+
+    /// Constructors that build all values at the `path`, or [`None`] if there
+    /// are infinitely many values.
+    fn complete(&self, path: &str, world: World) -> Option<&[Constructor<'a>]> {
+        if world == World::Open {
+            return None;
+        }
+        let constructors = self.0.get(path)?;
+        if constructors
+            .iter()
+            .any(|it| matches!(it, Constructor::Int(_) | Constructor::Text(_)))
+        {
+            return None;
+        }
+        Some(constructors.as_slice())
+    }
+}
+
+/// The keys of all struct patterns at the start of the rows and `head`.
+fn struct_keys(rows: &[Vec<Pat>], head: &Pat) -> Vec<String> {
+    rows.iter()
+        .map(|row| &row[0])
+        .chain([head])
+        .flat_map(expand_or)
+        .filter_map(|pattern| match pattern {
+            Pat::Struct(fields) => Some(fields.iter().map(|(key, _)| key.clone())),
+            _ => None,
+        })
+        .flatten()
+        .sorted()
+        .dedup()
+        .collect()
+}
+fn expand_or<'p, 'a>(pattern: &'p Pat<'a>) -> Vec<&'p Pat<'a>> {
+    match pattern {
+        Pat::Or(patterns) => patterns.iter().flat_map(expand_or).collect(),
+        pattern => vec![pattern],
+    }
+}
+
+/// If the row starts with the `constructor`, returns its fields followed by
+/// the rest of the row.
+fn specialize<'a>(
+    row: &[Pat<'a>],
+    constructor: Constructor<'a>,
+    struct_keys: &[String],
+) -> Vec<Vec<Pat<'a>>> {
+    let (head, rest) = row.split_first().unwrap();
+    let fields = match head {
+        Pat::Wildcard => vec![Pat::Wildcard; constructor.field_paths("", struct_keys).len()],
+        Pat::Or(patterns) => {
+            return patterns
+                .iter()
+                .flat_map(|pattern| {
+                    let row = iter::once(pattern.clone()).chain(rest.iter().cloned());
+                    specialize(&row.collect_vec(), constructor, struct_keys)
+                })
+                .collect();
+        }
+        head if head.constructor() != Some(constructor) => return vec![],
+        head => head.fields(struct_keys),
+    };
+    vec![fields.into_iter().chain(rest.iter().cloned()).collect()]
+}
+/// Rows starting with a wildcard, without it.
+fn default_rows<'a>(rows: &[Vec<Pat<'a>>]) -> Vec<Vec<Pat<'a>>> {
+    rows.iter()
+        .flat_map(|row| {
+            expand_or(&row[0])
+                .into_iter()
+                .filter(|head| matches!(head, Pat::Wildcard))
+                .map(|_| row[1..].to_vec())
+                .collect_vec()
+        })
+        .collect()
+}
+
+struct Usefulness<'s, 'a> {
+    signatures: &'s Signatures<'a>,
+    world: World,
+}
+impl<'a> Usefulness<'_, 'a> {
+    /// Returns values matched by `vector` but not by any of the `rows`, or
+    /// [`None`] if there are none.
+    ///
+    /// `paths` contains the path of each column (see
+    /// [`Constructor::field_paths`]).
+    fn find(
+        &self,
+        rows: &[Vec<Pat<'a>>],
+        vector: &[Pat<'a>],
+        paths: &[String],
+    ) -> Option<Vec<Witness>> {
+        let Some((head, rest)) = vector.split_first() else {
+            return if rows.is_empty() { Some(vec![]) } else { None };
+        };
+        let (path, rest_paths) = paths.split_first().unwrap();
+
+        if let Pat::Or(patterns) = head {
+            return patterns.iter().find_map(|pattern| {
+                let vector = iter::once(pattern.clone()).chain(rest.iter().cloned());
+                self.find(rows, &vector.collect_vec(), paths)
+            });
+        }
+
+        let keys = struct_keys(rows, head);
+        let find_for = |constructor: Constructor<'a>| {
+            let rows = rows
+                .iter()
+                .flat_map(|row| specialize(row, constructor, &keys))
+                .collect_vec();
+            let vector = specialize(vector, constructor, &keys).pop().unwrap();
+            let paths = constructor
+                .field_paths(path, &keys)
+                .into_iter()
+                .chain(rest_paths.iter().cloned())
+                .collect_vec();
+            let witnesses = self.find(&rows, &vector, &paths)?;
+            Some(Witness::apply(constructor, &keys, witnesses))
+        };
+
+        if let Some(constructor) = head.constructor() {
+            return find_for(constructor);
+        }
+
+        let used_constructors = rows
+            .iter()
+            .flat_map(|row| expand_or(&row[0]))
+            .filter_map(Pat::constructor)
+            .collect::<FxHashSet<_>>();
+        let missing_constructor = match self.signatures.complete(path, self.world) {
+            Some(constructors) => {
+                match constructors
+                    .iter()
+                    .find(|it| !used_constructors.contains(it))
+                {
+                    Some(constructor) => Some(*constructor),
+                    None => return constructors.iter().copied().find_map(find_for),
+                }
+            }
+            None => None,
+        };
+
+        let mut witnesses = self.find(&default_rows(rows), rest, rest_paths)?;
+        let witness = missing_constructor.map_or(Witness::Other, |constructor| {
+            let arity = constructor.field_paths(path, &keys).len();
+            let fields = (0..arity).map(|_| Witness::Other).collect();
+            Witness::apply(constructor, &keys, fields).pop().unwrap()
+        });
+        witnesses.insert(0, witness);
+        Some(witnesses)
+    }
+}
+
+#[derive(Debug)]
+enum Witness {
+    /// Any value not built by the constructors in this position.
+    Other,
+    Tag {
+        symbol: String,
+        value: Option<Box<Witness>>,
+    },
+    List(Vec<Witness>),
+    Struct(Vec<(String, Witness)>),
+    Present(Box<Witness>),
+}
+impl Witness {
+    /// Replaces the witnesses for the `constructor`'s fields at the start of
+    /// `witnesses` with one for the constructor itself.
+    fn apply(constructor: Constructor, struct_keys: &[String], witnesses: Vec<Self>) -> Vec<Self> {
+        let mut witnesses = witnesses.into_iter();
+        let witness = match constructor {
+            // Ints and texts are never complete, so their columns only
+            // produce `Other`.
+            Constructor::Int(_) | Constructor::Text(_) => Self::Other,
+            Constructor::Tag { symbol, has_value } => Self::Tag {
+                symbol: symbol.to_string(),
+                value: has_value.then(|| Box::new(witnesses.next().unwrap())),
+            },
+            Constructor::List(length) => Self::List(witnesses.by_ref().take(length).collect()),
+            Constructor::Struct => Self::Struct(
+                struct_keys
+                    .iter()
+                    .cloned()
+                    .zip(witnesses.by_ref().take(struct_keys.len()))
+                    .collect(),
+            ),
+            Constructor::Present => Self::Present(Box::new(witnesses.next().unwrap())),
+        };
+        iter::once(witness).chain(witnesses).collect()
+    }
+}
+impl std::fmt::Display for Witness {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Other => write!(f, "_"),
+            Self::Tag { symbol, value } => match value {
+                Some(value) if matches!(**value, Self::Tag { value: Some(_), .. }) => {
+                    write!(f, "{symbol} ({value})")
+                }
+                Some(value) => write!(f, "{symbol} {value}"),
+                None => write!(f, "{symbol}"),
+            },
+            Self::List(items) => match items.as_slice() {
+                [] => write!(f, "(,)"),
+                [item] => write!(f, "({item},)"),
+                items => write!(f, "({})", items.iter().join(", ")),
+            },
+            Self::Struct(fields) => write!(
+                f,
+                "[{}]",
+                fields
+                    .iter()
+                    .map(|(key, value)| format!("{key}: {value}"))
+                    .join(", "),
+            ),
+            Self::Present(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze_match, MatchAnalysis};
+    use crate::hir::{Pattern, PatternIdentifierId};
+
+    fn tag(symbol: &str) -> Pattern {
+        Pattern::Tag {
+            symbol: symbol.to_string(),
+            value: None,
+        }
+    }
+    fn tag_with_value(symbol: &str, value: Pattern) -> Pattern {
+        Pattern::Tag {
+            symbol: symbol.to_string(),
+            value: Some(Box::new(value)),
+        }
+    }
+    const fn identifier() -> Pattern {
+        Pattern::NewIdentifier(PatternIdentifierId(0))
+    }
+    fn analyze(patterns: &[Pattern]) -> MatchAnalysis {
+        analyze_match(patterns).unwrap()
+    }
+
+    #[test]
+    fn matches_on_all_tags_are_exhaustive() {
+        assert_eq!(
+            analyze(&[tag("True"), tag("False")]),
+            MatchAnalysis {
+                unreachable_cases: vec![],
+                missing_value: None,
+            },
+        );
+    }
+
+    #[test]
+    fn catch_all_after_tags_is_reachable() {
+        assert_eq!(
+            analyze(&[tag("True"), tag("False"), identifier()]).unreachable_cases,
+            Vec::<usize>::new(),
+        );
+    }
+
+    #[test]
+    fn cases_after_catch_all_are_unreachable() {
+        assert_eq!(
+            analyze(&[identifier(), tag("True"), tag("False")]).unreachable_cases,
+            vec![1, 2],
+        );
+        assert_eq!(
+            analyze(&[
+                tag("Foo"),
+                Pattern::Or(vec![tag("Bar"), tag("Foo")]),
+                tag("Bar")
+            ])
+            .unreachable_cases,
+            vec![2],
+        );
+    }
+
+    #[test]
+    fn ints_need_a_catch_all() {
+        let one = Pattern::Int(1u8.into());
+        let two = Pattern::Int(2u8.into());
+        assert_eq!(
+            analyze(&[
+                tag_with_value("Foo", one.clone()),
+                tag_with_value("Foo", two)
+            ])
+            .missing_value
+            .as_deref(),
+            Some("Foo _"),
+        );
+        assert_eq!(analyze(&[one, identifier()]).missing_value, None,);
+    }
+
+    #[test]
+    fn finds_missing_combinations() {
+        let pair = |a: &str, b: &str| Pattern::List(vec![tag(a), tag(b)]);
+        assert_eq!(
+            analyze(&[pair("A", "A"), pair("A", "B"), pair("B", "A")])
+                .missing_value
+                .as_deref(),
+            Some("(B, B)"),
+        );
+    }
+
+    #[test]
+    fn struct_patterns_ignore_other_keys() {
+        let foo = Pattern::Struct(vec![(tag("Foo"), identifier())]);
+        let foo_bar = Pattern::Struct(vec![(tag("Foo"), identifier()), (tag("Bar"), identifier())]);
+        assert_eq!(
+            analyze(&[foo.clone(), foo_bar.clone()]).unreachable_cases,
+            vec![1],
+        );
+        assert_eq!(
+            analyze(&[foo_bar, foo]).unreachable_cases,
+            Vec::<usize>::new(),
+        );
+    }
+}
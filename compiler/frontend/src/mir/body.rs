@@ -1,6 +1,7 @@
 use super::{expression::Expression, id::Id};
 use crate::{
     builtin_functions::BuiltinFunction,
+    extern_signature::ExternSignature,
     hir,
     id::{CountableId, IdGenerator},
     impl_display_via_richir,
@@ -358,6 +359,9 @@ impl BodyBuilder {
     pub fn push_builtin(&mut self, function: BuiltinFunction) -> Id {
         self.push(Expression::Builtin(function))
     }
+    pub fn push_extern(&mut self, symbol: String, signature: ExternSignature) -> Id {
+        self.push(Expression::Extern { symbol, signature })
+    }
     pub fn push_list(&mut self, list: Vec<Id>) -> Id {
         self.push(Expression::List(list))
     }
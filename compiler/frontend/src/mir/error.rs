@@ -1,6 +1,9 @@
 use crate::module::Module;
+use serde::{Deserialize, Serialize};
+use strum_macros::AsRefStr;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(AsRefStr, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[strum(serialize_all = "kebab-case")]
 pub enum MirError {
     UseWithInvalidPath { module: Module, path: String },
     UseHasTooManyParentNavigations { module: Module, path: String },
@@ -1,6 +1,7 @@
 use super::{body::Body, id::Id};
 use crate::{
     builtin_functions::BuiltinFunction,
+    extern_signature::ExternSignature,
     hir, impl_display_via_richir,
     module::Module,
     rich_ir::{ReferenceKey, RichIrBuilder, ToRichIr, TokenType},
@@ -35,6 +36,13 @@ pub enum Expression {
     #[from]
     Builtin(BuiltinFunction),
 
+    /// A function implemented outside of Candy. Backends call it via the C
+    /// ABI.
+    Extern {
+        symbol: String,
+        signature: ExternSignature,
+    },
+
     #[from]
     List(Vec<Id>),
 
@@ -249,6 +257,10 @@ impl Hash for Expression {
                 value.hash(state);
             }
             Self::Builtin(builtin) => builtin.hash(state),
+            Self::Extern { symbol, signature } => {
+                symbol.hash(state);
+                signature.hash(state);
+            }
             Self::List(items) => items.hash(state),
             Self::Struct(fields) => fields.len().hash(state),
             Self::Reference(id) => id.hash(state),
@@ -355,6 +367,13 @@ impl ToRichIr for Expression {
             Self::Builtin(builtin) => {
                 builtin.build_rich_ir(builder);
             }
+            Self::Extern { symbol, signature } => {
+                builder.push(
+                    format!("extern {symbol} {signature}"),
+                    None,
+                    EnumSet::empty(),
+                );
+            }
             Self::List(items) => {
                 builder.push("(", None, EnumSet::empty());
                 builder.push_children(items, ", ");
@@ -0,0 +1,262 @@
+//! Capture minimization avoids that functions capture values only to repeat a
+//! computation that the surrounding scope already performed. Most notably,
+//! this makes functions capture the fields of a struct instead of the whole
+//! struct if the surrounding scope already accessed these fields.
+//!
+//! Here's a before-and-after example:
+//!
+//! ```mir
+//! $0 = builtinStructGet          |  $0 = builtinStructGet
+//! $1 = Foo                       |  $1 = Foo
+//! $2 = call $0 with $a $1        |  $2 = call $0 with $a $1
+//! $3 = { ($4 responsible) ->     |  $3 = { ($4 responsible) ->
+//!   $5 = call $0 with $a $1      |    $5 = $2
+//! }                              |  }
+//! ```
+//!
+//! Afterwards, `$3` no longer captures the struct `$a`, only the field `$2`.
+//! This shrinks the closure environments in both the VM and the native
+//! backends.
+//!
+//! We only reuse deterministic calls that happened before the function was
+//! defined. Moving a computation out of a function instead would be wrong if
+//! the computation can panic: It would panic even if the function is never
+//! called.
+//!
+//! That's also why functions still capture the whole struct if only they
+//! access a field: Builtins like `✨.structGet` count as pure because their
+//! callers check the arguments beforehand, e.g., using `needs`. These checks
+//! are usually part of the function, so the field might not exist unless the
+//! function is called. If the struct's keys are known at compile-time,
+//! [constant folding] already replaces the access with the field's value.
+//!
+//! [constant folding]: super::constant_folding
+
+use super::pure::PurenessInsights;
+use crate::mir::{Body, Expression, Id, VisitorResult};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+pub fn minimize_captures(body: &mut Body, pureness: &mut PurenessInsights) {
+    // (function, arguments) → result
+    let mut calls = FxHashMap::<(Id, Vec<Id>), Id>::default();
+    for (id, expression) in body.iter_mut() {
+        if pureness.is_definition_deterministic(expression)
+            && let Expression::Call {
+                function,
+                arguments,
+                ..
+            } = expression
+        {
+            calls.entry((*function, arguments.clone())).or_insert(id);
+            continue;
+        }
+
+        if let Expression::Function { body, .. } = expression
+            && !calls.is_empty()
+        {
+            reuse_calls_in_function(body, &calls, pureness);
+        }
+    }
+}
+
+fn reuse_calls_in_function(
+    body: &mut Body,
+    calls: &FxHashMap<(Id, Vec<Id>), Id>,
+    pureness: &mut PurenessInsights,
+) {
+    // Later expressions can reference the outer results directly, which may
+    // make further calls match outer ones.
+    let mut replacements = FxHashMap::<Id, Id>::default();
+    body.visit_mut(&mut |id, expression, _| {
+        expression.replace_id_references(&mut |id| {
+            if let Some(replacement) = replacements.get(id) {
+                *id = *replacement;
+            }
+        });
+
+        if let Expression::Call {
+            function,
+            arguments,
+            ..
+        } = expression
+            && let Some(result) = calls.get(&(*function, arguments.clone()))
+        {
+            replacements.insert(id, *result);
+            *expression = Expression::Reference(*result);
+        }
+        VisitorResult::Continue
+    });
+    if replacements.is_empty() {
+        return;
+    }
+
+    // Nothing references the replaced expressions anymore, so we can remove
+    // them unless they're returned.
+    let replaced_ids = replacements.into_keys().collect::<FxHashSet<_>>();
+    body.visit_bodies(&mut |body| {
+        let return_value = body.return_value();
+        for (id, _) in body.remove_all(|id, _| id != return_value && replaced_ids.contains(&id)) {
+            pureness.on_remove(id);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::minimize_captures;
+    use crate::{
+        builtin_functions::BuiltinFunction,
+        hir,
+        id::IdGenerator,
+        mir::{Body, BodyBuilder, Expression, FunctionBodyBuilder, Id},
+        mir_optimize::pure::PurenessInsights,
+    };
+    use insta::assert_snapshot;
+
+    /// Builds a function that takes a struct `$1` and passes it to `build`
+    /// together with the `structGet` builtin and the `Foo` tag.
+    fn function_with_struct(build: impl FnOnce(&mut FunctionBodyBuilder, Id, Id, Id, Id)) -> Body {
+        let mut builder = BodyBuilder::new(IdGenerator::default());
+        builder.push_function(hir::Id::dummy(), |builder, responsible| {
+            let struct_ = builder.new_parameter();
+            let struct_get = builder.push_builtin(BuiltinFunction::StructGet);
+            let key = builder.push_tag("Foo".to_string(), None);
+            build(builder, struct_, struct_get, key, responsible);
+        });
+        let (_, body) = builder.finish();
+        let (_, Expression::Function { body, .. }) = body.iter().next().unwrap() else {
+            unreachable!();
+        };
+        body.clone()
+    }
+    fn minimize(body: &Body) -> String {
+        let mut body = body.clone();
+        let mut pureness = PurenessInsights::default();
+        for (id, expression) in body.iter() {
+            pureness.visit_optimized(id, expression);
+        }
+        minimize_captures(&mut body, &mut pureness);
+        body.to_string()
+    }
+
+    #[test]
+    fn captures_field_instead_of_struct() {
+        let body = function_with_struct(|builder, struct_, struct_get, key, responsible| {
+            let field = builder.push_call(struct_get, vec![struct_, key], responsible);
+            builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                let field = builder.push_call(struct_get, vec![struct_, key], responsible);
+                builder.push_call(field, vec![], responsible);
+            });
+            builder.push_list(vec![field]);
+        });
+        assert_snapshot!(body.to_string(), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        # tooling:dummy::
+        $8 = { (responsible $5) ->
+          $6 = call $2 with $1 $3 ($5 is responsible)
+          $7 = call $6 with no arguments ($5 is responsible)
+        }
+        $9 = ($4,)
+        "###);
+        assert_snapshot!(minimize(&body), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        # tooling:dummy::
+        $8 = { (responsible $5) ->
+          $7 = call $4 with no arguments ($5 is responsible)
+        }
+        $9 = ($4,)
+        "###);
+    }
+
+    #[test]
+    fn captures_nested_field() {
+        let body = function_with_struct(|builder, struct_, struct_get, key, responsible| {
+            let field = builder.push_call(struct_get, vec![struct_, key], responsible);
+            builder.push_call(struct_get, vec![field, key], responsible);
+            builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                let field = builder.push_call(struct_get, vec![struct_, key], responsible);
+                let nested_field = builder.push_call(struct_get, vec![field, key], responsible);
+                builder.push_call(nested_field, vec![], responsible);
+            });
+        });
+        assert_snapshot!(body.to_string(), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        $5 = call $2 with $4 $3 ($0 is responsible)
+        # tooling:dummy::
+        $10 = { (responsible $6) ->
+          $7 = call $2 with $1 $3 ($6 is responsible)
+          $8 = call $2 with $7 $3 ($6 is responsible)
+          $9 = call $8 with no arguments ($6 is responsible)
+        }
+        "###);
+        assert_snapshot!(minimize(&body), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        $5 = call $2 with $4 $3 ($0 is responsible)
+        # tooling:dummy::
+        $10 = { (responsible $6) ->
+          $9 = call $5 with no arguments ($6 is responsible)
+        }
+        "###);
+    }
+
+    #[test]
+    fn keeps_returned_field_as_reference() {
+        let body = function_with_struct(|builder, struct_, struct_get, key, responsible| {
+            builder.push_call(struct_get, vec![struct_, key], responsible);
+            builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                builder.push_call(struct_get, vec![struct_, key], responsible);
+            });
+        });
+        assert_snapshot!(body.to_string(), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        # tooling:dummy::
+        $7 = { (responsible $5) ->
+          $6 = call $2 with $1 $3 ($5 is responsible)
+        }
+        "###);
+        assert_snapshot!(minimize(&body), @r###"
+        $2 = builtinStructGet
+        $3 = Foo
+        $4 = call $2 with $1 $3 ($0 is responsible)
+        # tooling:dummy::
+        $7 = { (responsible $5) ->
+          $6 = $4
+        }
+        "###);
+    }
+
+    #[test]
+    fn keeps_struct_if_field_is_accessed_after_function() {
+        let body = function_with_struct(|builder, struct_, struct_get, key, responsible| {
+            builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                builder.push_call(struct_get, vec![struct_, key], responsible);
+            });
+            builder.push_call(struct_get, vec![struct_, key], responsible);
+        });
+        let before = body.to_string();
+        assert_eq!(minimize(&body), before);
+    }
+
+    #[test]
+    fn keeps_non_deterministic_calls() {
+        let body = function_with_struct(|builder, struct_, _, key, responsible| {
+            let function = builder.new_parameter();
+            builder.push_call(function, vec![struct_, key], responsible);
+            builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                builder.push_call(function, vec![struct_, key], responsible);
+            });
+        });
+        let before = body.to_string();
+        assert_eq!(minimize(&body), before);
+    }
+}
@@ -160,7 +160,7 @@ pub fn eliminate_common_subtrees(body: &mut Body, pureness: &mut PurenessInsight
 }
 
 impl Body {
-    fn all_functions(&self) -> Vec<(Id, FxHashSet<hir::Id>)> {
+    pub(super) fn all_functions(&self) -> Vec<(Id, FxHashSet<hir::Id>)> {
         let mut ids_and_expressions = vec![];
         self.visit(&mut |id, expression, _| {
             if let Expression::Function { original_hirs, .. } = expression {
@@ -173,7 +173,7 @@ impl Body {
 }
 
 #[derive(Default)]
-struct NormalizationState {
+pub(super) struct NormalizationState {
     id_generator: IdGenerator<Id>,
     id_mapping: FxHashMap<Id, Id>,
 }
@@ -202,7 +202,7 @@ impl NormalizationState {
 /// Two functions where local expressions have different IDs are usually not
 /// considered equal. This trait calculates normalized hashes expressions by
 /// normalizing all locally defined IDs.
-trait NormalizedComparison {
+pub(super) trait NormalizedComparison {
     fn does_equal_normalized(&self, other: &Self) -> bool {
         self.equals_normalized(
             &mut NormalizationState::default(),
@@ -385,6 +385,16 @@ impl NormalizedComparison for Expression {
             }
             (Self::Builtin(self_builtin), Self::Builtin(other_builtin)) => self_builtin
                 .equals_normalized(self_normalization, other_builtin, other_normalization),
+            (
+                Self::Extern {
+                    symbol: self_symbol,
+                    signature: self_signature,
+                },
+                Self::Extern {
+                    symbol: other_symbol,
+                    signature: other_signature,
+                },
+            ) => self_symbol == other_symbol && self_signature == other_signature,
             (Self::List(self_items), Self::List(other_items)) => {
                 self_items.equals_normalized(self_normalization, other_items, other_normalization)
             }
@@ -598,6 +608,10 @@ impl NormalizedComparison for Expression {
                 value.hash_normalized(normalization, state);
             }
             Self::Builtin(builtin) => builtin.hash_normalized(normalization, state),
+            Self::Extern { symbol, signature } => {
+                symbol.hash(state);
+                signature.hash(state);
+            }
             Self::List(items) => items.hash_normalized(normalization, state),
             Self::Struct(fields) => fields.len().hash_normalized(normalization, state),
             Self::Reference(id) => id.hash_normalized(normalization, state),
@@ -116,10 +116,43 @@ fn run_builtin(
     );
 
     let result = match builtin {
+        // Bytes only exist at runtime.
+        BuiltinFunction::BytesConcatenate
+        | BuiltinFunction::BytesFromList
+        | BuiltinFunction::BytesFromText
+        | BuiltinFunction::BytesGetRange
+        | BuiltinFunction::BytesLength
+        | BuiltinFunction::BytesToList
+        | BuiltinFunction::BytesToText => return None,
+        // Cells are mutable, so their contents are only known at runtime.
+        BuiltinFunction::CellCreate | BuiltinFunction::CellGet | BuiltinFunction::CellSet => {
+            return None;
+        }
+        BuiltinFunction::DebugAssert => {
+            let [condition, _] = arguments else {
+                unreachable!()
+            };
+            // Assertions that are known to hold don't need to be checked.
+            match visible.get(*condition).try_into() {
+                Ok(true) => Expression::nothing(),
+                _ => return None,
+            }
+        }
+        BuiltinFunction::DebugLog => return None,
         BuiltinFunction::Equals => {
             let [a, b] = arguments else { unreachable!() };
             a.semantically_equals(*b, visible, pureness)?.into()
         }
+        // The MIR has no expression for fractions, so they only exist at
+        // runtime.
+        BuiltinFunction::FractionAdd
+        | BuiltinFunction::FractionCompareTo
+        | BuiltinFunction::FractionCreate
+        | BuiltinFunction::FractionDenominator
+        | BuiltinFunction::FractionDivide
+        | BuiltinFunction::FractionMultiply
+        | BuiltinFunction::FractionNumerator
+        | BuiltinFunction::FractionSubtract => return None,
         BuiltinFunction::FunctionRun => {
             let [function] = arguments else {
                 unreachable!()
@@ -136,6 +169,7 @@ fn run_builtin(
             };
             match visible.get(*function) {
                 Expression::Builtin(builtin) => builtin.num_parameters().into(),
+                Expression::Extern { signature, .. } => signature.arity().into(),
                 Expression::Function { parameters, .. } => parameters.len().into(),
                 _ => return None,
             }
@@ -374,6 +408,18 @@ fn run_builtin(
                 _ => return None,
             }
         }
+        BuiltinFunction::ListConcatenate => {
+            let [list_a, list_b] = arguments else {
+                unreachable!()
+            };
+            let Expression::List(list_a) = visible.get(*list_a) else {
+                return None;
+            };
+            let Expression::List(list_b) = visible.get(*list_b) else {
+                return None;
+            };
+            list_a.iter().chain(list_b).copied().collect_vec().into()
+        }
         BuiltinFunction::ListFilled => {
             let [length, item] = arguments else {
                 unreachable!()
@@ -397,6 +443,24 @@ fn run_builtin(
             // TODO: Support lists longer than `usize::MAX`.
             list.get(index.to_usize().unwrap())?.into()
         }
+        BuiltinFunction::ListGetRange => {
+            let [list, start_inclusive, end_exclusive] = arguments else {
+                unreachable!()
+            };
+            let Expression::List(list) = visible.get(*list) else {
+                return None;
+            };
+            let Expression::Int(start_inclusive) = visible.get(*start_inclusive) else {
+                return None;
+            };
+            let Expression::Int(end_exclusive) = visible.get(*end_exclusive) else {
+                return None;
+            };
+            // TODO: Support lists longer than `usize::MAX`.
+            list.get(start_inclusive.to_usize().unwrap()..end_exclusive.to_usize().unwrap())?
+                .to_vec()
+                .into()
+        }
         BuiltinFunction::ListInsert => return None,
         BuiltinFunction::ListLength => {
             let [list] = arguments else { unreachable!() };
@@ -407,7 +471,24 @@ fn run_builtin(
         }
         BuiltinFunction::ListRemoveAt => return None,
         BuiltinFunction::ListReplace => return None,
+        // The MIR has no expressions for maps and sets, so they only exist at
+        // runtime.
+        BuiltinFunction::MapEntries
+        | BuiltinFunction::MapFromList
+        | BuiltinFunction::MapGet
+        | BuiltinFunction::MapHasKey
+        | BuiltinFunction::MapInsert
+        | BuiltinFunction::MapRemove
+        | BuiltinFunction::MapSize
+        | BuiltinFunction::MapUnion => return None,
         BuiltinFunction::Print => return None,
+        BuiltinFunction::SetContains
+        | BuiltinFunction::SetFromList
+        | BuiltinFunction::SetInsert
+        | BuiltinFunction::SetItems
+        | BuiltinFunction::SetRemove
+        | BuiltinFunction::SetSize
+        | BuiltinFunction::SetUnion => return None,
         BuiltinFunction::StructGet => {
             let [struct_, key] = arguments else {
                 unreachable!()
@@ -519,6 +600,12 @@ fn run_builtin(
                 value: Some(*value),
             }
         }
+        // Text builders are mutable, so their contents are only known at
+        // runtime.
+        BuiltinFunction::TextBuilderAppend
+        | BuiltinFunction::TextBuilderAppendInt
+        | BuiltinFunction::TextBuilderBuild
+        | BuiltinFunction::TextBuilderCreate => return None,
         BuiltinFunction::TextCharacters => {
             let [text] = arguments else { unreachable!() };
             let Expression::Text(text) = visible.get(*text) else {
@@ -533,6 +620,18 @@ fn run_builtin(
             expression.replace_with_multiple(NAME, body, pureness);
             return None;
         }
+        BuiltinFunction::TextCompareTo => {
+            let [a, b] = arguments else { unreachable!() };
+            if a == b {
+                return Some(Ordering::Equal.into());
+            }
+
+            let (Expression::Text(a), Expression::Text(b)) = (visible.get(*a), visible.get(*b))
+            else {
+                return None;
+            };
+            a.cmp(b).into()
+        }
         BuiltinFunction::TextConcatenate => {
             let [a, b] = arguments else { unreachable!() };
             match (visible.get(*a), visible.get(*b)) {
@@ -676,6 +775,20 @@ fn run_builtin(
             };
             text.starts_with(suffix).into()
         }
+        BuiltinFunction::TextToLowercase => {
+            let [text] = arguments else { unreachable!() };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            Expression::Text(text.to_lowercase())
+        }
+        BuiltinFunction::TextToUppercase => {
+            let [text] = arguments else { unreachable!() };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            Expression::Text(text.to_uppercase())
+        }
         BuiltinFunction::TextTrimEnd => {
             let [text] = arguments else { unreachable!() };
             let Expression::Text(text) = visible.get(*text) else {
@@ -703,7 +816,7 @@ fn run_builtin(
                             symbol,
                             value: *value,
                         },
-                        Expression::Builtin(_) => FormatValue::Function,
+                        Expression::Builtin(_) | Expression::Extern { .. } => FormatValue::Function,
                         Expression::List(items) => FormatValue::List(items),
                         Expression::Struct(entries) => FormatValue::Struct(Cow::Borrowed(entries)),
                         Expression::Function { .. } => FormatValue::Function,
@@ -712,12 +825,13 @@ fn run_builtin(
                 })?;
             formatted.into()
         }
+        BuiltinFunction::Try => return None,
         BuiltinFunction::TypeOf => Expression::tag(
             match visible.get(arguments[0]) {
                 Expression::Int(_) => "Int",
                 Expression::Text(_) => "Text",
                 Expression::Tag { .. } => "Tag",
-                Expression::Builtin(_) => "Function",
+                Expression::Builtin(_) | Expression::Extern { .. } => "Function",
                 Expression::List(_) => "List",
                 Expression::Struct(_) => "Struct",
                 Expression::Reference(_) => return None,
@@ -730,7 +844,27 @@ fn run_builtin(
                         return None;
                     };
                     match builtin {
+                        BuiltinFunction::BytesConcatenate => "Bytes",
+                        BuiltinFunction::BytesFromList => "Bytes",
+                        BuiltinFunction::BytesFromText => "Bytes",
+                        BuiltinFunction::BytesGetRange => "Bytes",
+                        BuiltinFunction::BytesLength => "Int",
+                        BuiltinFunction::BytesToList => "List",
+                        BuiltinFunction::BytesToText => "Tag",
+                        BuiltinFunction::CellCreate => "Cell",
+                        BuiltinFunction::CellGet => return None,
+                        BuiltinFunction::CellSet => "Tag",
+                        BuiltinFunction::DebugAssert => "Tag",
+                        BuiltinFunction::DebugLog => "Tag",
                         BuiltinFunction::Equals => "Tag",
+                        BuiltinFunction::FractionAdd => return None,
+                        BuiltinFunction::FractionCompareTo => "Tag",
+                        BuiltinFunction::FractionCreate => return None,
+                        BuiltinFunction::FractionDenominator => "Int",
+                        BuiltinFunction::FractionDivide => return None,
+                        BuiltinFunction::FractionMultiply => return None,
+                        BuiltinFunction::FractionNumerator => "Int",
+                        BuiltinFunction::FractionSubtract => return None,
                         BuiltinFunction::GetArgumentCount => "Int",
                         BuiltinFunction::FunctionRun => return None,
                         BuiltinFunction::IfElse => return None,
@@ -748,13 +882,30 @@ fn run_builtin(
                         BuiltinFunction::IntShiftLeft => "Int",
                         BuiltinFunction::IntShiftRight => "Int",
                         BuiltinFunction::IntSubtract => "Int",
+                        BuiltinFunction::ListConcatenate => "List",
                         BuiltinFunction::ListFilled => "List",
                         BuiltinFunction::ListGet => return None,
+                        BuiltinFunction::ListGetRange => "List",
                         BuiltinFunction::ListInsert => "List",
                         BuiltinFunction::ListLength => "Int",
                         BuiltinFunction::ListRemoveAt => "List",
                         BuiltinFunction::ListReplace => "List",
+                        BuiltinFunction::MapEntries => "List",
+                        BuiltinFunction::MapFromList => "Map",
+                        BuiltinFunction::MapGet => return None,
+                        BuiltinFunction::MapHasKey => "Tag",
+                        BuiltinFunction::MapInsert => "Map",
+                        BuiltinFunction::MapRemove => "Map",
+                        BuiltinFunction::MapSize => "Int",
+                        BuiltinFunction::MapUnion => "Map",
                         BuiltinFunction::Print => "Tag",
+                        BuiltinFunction::SetContains => "Tag",
+                        BuiltinFunction::SetFromList => "Set",
+                        BuiltinFunction::SetInsert => "Set",
+                        BuiltinFunction::SetItems => "List",
+                        BuiltinFunction::SetRemove => "Set",
+                        BuiltinFunction::SetSize => "Int",
+                        BuiltinFunction::SetUnion => "Set",
                         BuiltinFunction::StructGet => return None,
                         BuiltinFunction::StructGetKeys => "List",
                         BuiltinFunction::StructHasKey => "Tag",
@@ -762,7 +913,12 @@ fn run_builtin(
                         BuiltinFunction::TagHasValue => "Tag",
                         BuiltinFunction::TagWithoutValue => "Tag",
                         BuiltinFunction::TagWithValue => "Tag",
+                        BuiltinFunction::TextBuilderAppend => "Tag",
+                        BuiltinFunction::TextBuilderAppendInt => "Tag",
+                        BuiltinFunction::TextBuilderBuild => "Text",
+                        BuiltinFunction::TextBuilderCreate => "TextBuilder",
                         BuiltinFunction::TextCharacters => "List",
+                        BuiltinFunction::TextCompareTo => "Tag",
                         BuiltinFunction::TextConcatenate => "Text",
                         BuiltinFunction::TextContains => "Tag",
                         BuiltinFunction::TextEndsWith => "Tag",
@@ -771,9 +927,12 @@ fn run_builtin(
                         BuiltinFunction::TextIsEmpty => "Tag",
                         BuiltinFunction::TextLength => "Int",
                         BuiltinFunction::TextStartsWith => "Tag",
+                        BuiltinFunction::TextToLowercase => "Text",
+                        BuiltinFunction::TextToUppercase => "Text",
                         BuiltinFunction::TextTrimEnd => "Text",
                         BuiltinFunction::TextTrimStart => "Text",
                         BuiltinFunction::ToDebugText => "Text",
+                        BuiltinFunction::Try => "Tag",
                         BuiltinFunction::TypeOf => "Tag",
                     }
                 }
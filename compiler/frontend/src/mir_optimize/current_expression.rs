@@ -1,10 +1,11 @@
-use super::{pure::PurenessInsights, OptimizeMir};
+use super::{fuel::OptimizationFuel, pure::PurenessInsights, OptimizeMir};
 use crate::{
     error::CompilerError,
     id::IdGenerator,
     mir::{Body, Expression, Id, VisibleExpressions},
     mir_optimize::log::OptimizationLogger,
-    TracingConfig,
+    module::Module,
+    OptimizationConfig, TracingConfig,
 };
 use rustc_hash::FxHashSet;
 use std::ops::Deref;
@@ -12,10 +13,16 @@ use std::ops::Deref;
 pub struct Context<'a> {
     pub db: &'a dyn OptimizeMir,
     pub tracing: &'a TracingConfig,
+    pub optimization: &'a OptimizationConfig,
     pub errors: &'a mut FxHashSet<CompilerError>,
     pub visible: &'a mut VisibleExpressions,
     pub id_generator: &'a mut IdGenerator<Id>,
     pub pureness: &'a mut PurenessInsights,
+    /// The module being optimized, preceded by the modules that (transitively)
+    /// import it if it's part of an import cycle.
+    pub import_stack: &'a [Module],
+    /// [`None`] if the amount of optimizations is unlimited.
+    pub fuel: Option<OptimizationFuel>,
 }
 
 pub struct CurrentExpression<'a> {
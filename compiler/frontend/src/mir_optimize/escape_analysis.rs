@@ -0,0 +1,112 @@
+//! Escape analysis finds values that never outlive the function creating them.
+//! Backends can allocate these values in the function's frame and free them
+//! all at once when the function returns instead of reference-counting them.
+//!
+//! We only consider lists, structs, and tags with a value since those are the
+//! values created by a function that aren't constants. Such a value escapes if
+//! it's
+//!
+//! - returned,
+//! - stored in another value,
+//! - captured by a function,
+//! - passed to a function other than a builtin that only inspects its
+//!   arguments (see [`only_inspects_arguments`]), or
+//! - passed to any function in tail position, which only runs after the frame
+//!   is gone.
+//!
+//! For example, `$3` doesn't escape here:
+//!
+//! ```mir
+//! $3 = ($1, $2)
+//! $4 = call builtins.listLength with $3 ($0 is responsible)
+//! $5 = call builtins.intAdd with $4 $1 ($0 is responsible)
+//! ```
+//!
+//! Like [`loops`](super::loops), this analyzes the final MIR after all other
+//! optimizations.
+
+use crate::{
+    builtin_functions::BuiltinFunction,
+    mir::{Body, Expression, Id, Mir, VisitorResult},
+};
+use rustc_hash::FxHashSet;
+
+#[must_use]
+pub fn find_non_escaping_values(mir: &Mir) -> FxHashSet<Id> {
+    let mut inspecting_builtins = FxHashSet::default();
+    mir.body.visit(&mut |id, expression, _| {
+        if let Expression::Builtin(builtin) = expression
+            && only_inspects_arguments(*builtin)
+        {
+            inspecting_builtins.insert(id);
+        }
+        VisitorResult::Continue
+    });
+
+    let mut non_escaping = FxHashSet::default();
+    collect_non_escaping_values(&mir.body, &inspecting_builtins, &mut non_escaping);
+    non_escaping
+}
+
+/// Builtins that neither return nor store their arguments. Values they return
+/// are either new or children of the arguments that they dup.
+const fn only_inspects_arguments(builtin: BuiltinFunction) -> bool {
+    matches!(
+        builtin,
+        BuiltinFunction::Equals
+            | BuiltinFunction::ListGet
+            | BuiltinFunction::ListLength
+            | BuiltinFunction::StructGet
+            | BuiltinFunction::StructHasKey
+            | BuiltinFunction::TagGetValue
+            | BuiltinFunction::TagHasValue
+            | BuiltinFunction::TypeOf,
+    )
+}
+
+fn collect_non_escaping_values(
+    body: &Body,
+    inspecting_builtins: &FxHashSet<Id>,
+    non_escaping: &mut FxHashSet<Id>,
+) {
+    let return_value = body.return_value();
+    let mut candidates: FxHashSet<_> = body
+        .iter()
+        .filter(|(id, expression)| {
+            *id != return_value
+                && matches!(
+                    expression,
+                    Expression::List(_)
+                        | Expression::Struct(_)
+                        | Expression::Tag { value: Some(_), .. },
+                )
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for (id, expression) in body.iter() {
+        match expression {
+            Expression::Function { body, .. } => {
+                collect_non_escaping_values(body, inspecting_builtins, non_escaping);
+                for captured in expression.captured_ids() {
+                    candidates.remove(&captured);
+                }
+            }
+            Expression::Call {
+                function,
+                responsible,
+                ..
+            } if inspecting_builtins.contains(function) && id != return_value => {
+                candidates.remove(function);
+                candidates.remove(responsible);
+            }
+            _ => {
+                for referenced in expression.referenced_ids() {
+                    candidates.remove(&referenced);
+                }
+            }
+        }
+    }
+
+    non_escaping.extend(candidates);
+}
@@ -0,0 +1,131 @@
+//! Optimization fuel helps compiler developers find the specific optimization
+//! application that breaks a program.
+//!
+//! When fuel is enabled, every applied optimization consumes one unit of fuel.
+//! Once the fuel runs out, no further optimizations are applied. By bisecting
+//! over the amount of fuel, you can find the first application that causes a
+//! miscompilation. The last application that still had fuel available is
+//! recorded and logged after optimizing each module.
+//!
+//! Fuel is part of the [`OptimizationConfig`], so results optimized with
+//! different amounts of fuel are cached separately. Each module gets the whole
+//! amount of fuel. The CLI sets it via `--optimization-fuel` or the
+//! `CANDY_MIR_OPTIMIZATION_FUEL` environment variable.
+//!
+//! Some transformations are necessary for the code to work at all (e.g.,
+//! resolving `use` by [module folding] or [reference following]). These don't
+//! consume fuel and are always applied.
+//!
+//! [`OptimizationConfig`]: crate::OptimizationConfig
+//! [module folding]: super::module_folding
+//! [reference following]: super::reference_following
+
+use crate::mir::Id;
+use std::fmt::{self, Display, Formatter};
+use tracing::info;
+
+#[derive(Clone, Debug)]
+pub struct OptimizationFuel {
+    initial: usize,
+    remaining: usize,
+    last_application: Option<(String, Id)>,
+}
+impl OptimizationFuel {
+    #[must_use]
+    pub const fn new(fuel: usize) -> Self {
+        Self {
+            initial: fuel,
+            remaining: fuel,
+            last_application: None,
+        }
+    }
+
+    /// Whether optimizations may still be applied.
+    #[must_use]
+    pub const fn has_fuel(&self) -> bool {
+        self.remaining > 0
+    }
+    /// Records that the optimization with the given name was applied to the
+    /// expression with the given ID.
+    pub fn consume(&mut self, optimization_name: &str, id: Id) {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.last_application = Some((optimization_name.to_string(), id));
+        if self.remaining == 0 {
+            info!("Optimization fuel exhausted. {self}");
+        }
+    }
+}
+impl Display for OptimizationFuel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Used {} of {} units of optimization fuel",
+            self.initial - self.remaining,
+            self.initial,
+        )?;
+        if let Some((optimization_name, id)) = &self.last_application {
+            write!(f, ", last applied {optimization_name} to {id}")?;
+        }
+        write!(f, ".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hir_to_mir::ExecutionTarget,
+        mir::{Body, Expression},
+        mir_optimize::OptimizeMir,
+        module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase},
+        OptimizationConfig, TracingConfig,
+    };
+    use std::path::PathBuf;
+
+    fn optimized_body(fuel: Option<usize>) -> Body {
+        let mut db = TestDatabase::default();
+        db.did_open_module(&Module::from_package_name("Builtins".to_string()), vec![]);
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["main".to_string()],
+            ModuleKind::Code,
+        );
+        db.did_open_module(&module, b"identity a = a\nfoo := identity 3\n".to_vec());
+        let (mir, _, _) = db
+            .optimized_mir_without_tail_calls(
+                ExecutionTarget::Module(module),
+                TracingConfig::off(),
+                OptimizationConfig::default().with_fuel(fuel),
+            )
+            .unwrap();
+        mir.body.clone()
+    }
+    fn contains(body: &Body, predicate: &impl Fn(&Expression) -> bool) -> bool {
+        body.iter().any(|(_, expression)| {
+            predicate(expression)
+                || matches!(expression, Expression::Function { body, .. } if contains(body, predicate))
+        })
+    }
+    fn contains_call(body: &Body) -> bool {
+        contains(body, &|expression| {
+            matches!(expression, Expression::Call { .. })
+        })
+    }
+
+    #[test]
+    fn applies_all_optimizations_without_fuel() {
+        let body = optimized_body(None);
+        assert!(!contains_call(&body));
+    }
+
+    #[test]
+    fn applies_no_optimizations_with_zero_fuel() {
+        let body = optimized_body(Some(0));
+        assert!(contains_call(&body));
+    }
+
+    #[test]
+    fn applies_optimizations_while_fuel_is_left() {
+        let body = optimized_body(Some(1_000));
+        assert!(!contains_call(&body));
+    }
+}
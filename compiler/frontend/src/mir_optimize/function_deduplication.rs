@@ -0,0 +1,180 @@
+//! Function deduplication merges structurally identical functions across the
+//! whole MIR.
+//!
+//! [Common subtree elimination] only merges functions defined in the same
+//! body. After [module folding], the same function (e.g., from a core utility
+//! imported by many modules) can still exist many times in different bodies.
+//! If one of them is visible where another one is defined, the latter can
+//! reuse the former.
+//!
+//! Here's a before-and-after example:
+//!
+//! ```mir
+//! $0 = { $1 ($2 responsible) ->    |  $0 = { $1 ($2 responsible) ->
+//!   $3 = call ... with $1 ...      |    $3 = call ... with $1 ...
+//! }                                |  }
+//! $4 = { $5 ($6 responsible) ->    |  $4 = { $5 ($6 responsible) ->
+//!   $7 = { $8 ($9 responsible) ->  |    $10 = call $0 with $5 ...
+//!     $11 = call ... with $8 ...   |  }
+//!   }                              |
+//!   $10 = call $7 with $5 ...      |
+//! }                                |
+//! ```
+//!
+//! This shrinks the byte code and improves instruction cache behavior.
+//!
+//! Functions are compared after normalizing their locally defined IDs, so two
+//! functions are only equal if they also capture the same values.
+//!
+//! [Common subtree elimination]: super::common_subtree_elimination
+//! [module folding]: super::module_folding
+
+use super::{common_subtree_elimination::NormalizedComparison, pure::PurenessInsights};
+use crate::{
+    hir,
+    mir::{Body, Expression, Id, Mir, VisitorResult},
+};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::mem;
+
+impl Mir {
+    pub fn deduplicate_functions(&mut self, pureness: &mut PurenessInsights) {
+        // Merging functions can make functions referencing them equal, so we
+        // repeat until nothing changes.
+        loop {
+            let mut duplicates = FxHashMap::default();
+            find_duplicates(&self.body, &mut FxHashMap::default(), &mut duplicates);
+            if duplicates.is_empty() {
+                break;
+            }
+            self.body.merge_duplicates(&duplicates, pureness);
+        }
+    }
+}
+
+/// Maps functions to the earlier, visible, identical function that they can be
+/// replaced with.
+fn find_duplicates<'a>(
+    body: &'a Body,
+    visible: &mut FxHashMap<u64, Vec<(Id, &'a Expression)>>,
+    duplicates: &mut FxHashMap<Id, Id>,
+) {
+    let mut registered_hashes = vec![];
+    for (id, expression) in body.iter() {
+        let Expression::Function {
+            body: function_body,
+            ..
+        } = expression
+        else {
+            continue;
+        };
+        let hash = expression.do_hash_normalized();
+        let candidates = visible.entry(hash).or_default();
+        if let Some((canonical_id, _)) = candidates
+            .iter()
+            .find(|(_, candidate)| candidate.does_equal_normalized(expression))
+        {
+            duplicates.insert(id, *canonical_id);
+            continue;
+        }
+        candidates.push((id, expression));
+        registered_hashes.push(hash);
+
+        find_duplicates(function_body, visible, duplicates);
+    }
+
+    // Functions defined in this body aren't visible outside of it.
+    for hash in registered_hashes {
+        visible.get_mut(&hash).unwrap().pop();
+    }
+}
+
+impl Body {
+    fn merge_duplicates(
+        &mut self,
+        duplicates: &FxHashMap<Id, Id>,
+        pureness: &mut PurenessInsights,
+    ) {
+        let canonical_ids: FxHashSet<_> = duplicates.values().copied().collect();
+        let mut inner_function_ids = FxHashMap::default();
+        self.visit(&mut |id, expression, _| {
+            if canonical_ids.contains(&id)
+                && let Expression::Function { body, .. } = expression
+            {
+                inner_function_ids.insert(
+                    id,
+                    body.all_functions()
+                        .into_iter()
+                        .map(|(id, _)| id)
+                        .collect_vec(),
+                );
+            }
+            VisitorResult::Continue
+        });
+
+        let mut additional_function_hirs: FxHashMap<Id, FxHashSet<hir::Id>> =
+            FxHashMap::default();
+        self.visit_bodies(&mut |body| {
+            for (id, expression) in body.iter_mut() {
+                let Some(canonical_id) = duplicates.get(&id) else {
+                    continue;
+                };
+                let old_expression =
+                    mem::replace(expression, Expression::Reference(*canonical_id));
+                pureness.on_remove(id);
+                pureness.visit_optimized(id, expression);
+                for defined_id in old_expression.defined_ids() {
+                    pureness.on_remove(defined_id);
+                }
+                let Expression::Function {
+                    original_hirs,
+                    body: function_body,
+                    ..
+                } = old_expression
+                else {
+                    unreachable!();
+                };
+
+                additional_function_hirs
+                    .entry(*canonical_id)
+                    .or_default()
+                    .extend(original_hirs);
+                for ((_, child_hirs), canonical_child_id) in function_body
+                    .all_functions()
+                    .into_iter()
+                    .zip_eq(&inner_function_ids[canonical_id])
+                {
+                    additional_function_hirs
+                        .entry(*canonical_child_id)
+                        .or_default()
+                        .extend(child_hirs);
+                }
+            }
+        });
+
+        self.replace_id_references(&mut |id| {
+            if let Some(canonical_id) = duplicates.get(id) {
+                *id = *canonical_id;
+            }
+        });
+        self.visit_bodies(&mut |body| {
+            let return_value = body.return_value();
+            for (id, _) in
+                body.remove_all(|id, _| id != return_value && duplicates.contains_key(&id))
+            {
+                pureness.on_remove(id);
+            }
+        });
+
+        // Add function HIR IDs to the functions they got merged into.
+        self.visit_mut(&mut |id, expression, _| {
+            if let Expression::Function { original_hirs, .. } = expression
+                && let Some(additional_hirs) = additional_function_hirs.remove(&id)
+            {
+                original_hirs.extend(additional_hirs);
+            }
+            VisitorResult::Continue
+        });
+    }
+}
@@ -0,0 +1,801 @@
+//! A simple interpreter for the MIR.
+//!
+//! Running Candy code efficiently is the VM's job. This interpreter instead
+//! exists to observe the behavior of the MIR before and after optimizations
+//! (see [`super::validate`]) without depending on the later compiler stages.
+//! Hence, it's optimized for simplicity:
+//!
+//! - Values are plain Rust values that are cloned liberally.
+//! - Trace expressions are ignored.
+//! - `use` is resolved lazily by interpreting the used module's MIR.
+//!
+//! Some programs can't be interpreted faithfully, e.g., because they run for
+//! too long or call a builtin function with invalid arguments (which the VM
+//! doesn't support either – the `Builtins` package ensures that they're only
+//! called with valid arguments). In these cases, the interpreter aborts with
+//! [`Exit::Unsupported`].
+
+use crate::{
+    builtin_functions::BuiltinFunction,
+    format::{format_value, FormatValue, MaxLength, Precedence},
+    hir,
+    mir::{Body, Expression, Id, Mir},
+    module::{Module, UsePath},
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
+use rustc_hash::FxHashMap;
+use std::{
+    borrow::Cow,
+    fmt::{self, Debug, Formatter},
+    ptr,
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The maximum number of expressions evaluated per run.
+const MAX_STEPS: usize = 1_000_000;
+/// The maximum depth of nested function calls.
+///
+/// The interpreter runs functions recursively, so this limit prevents stack
+/// overflows.
+const MAX_CALL_DEPTH: usize = 200;
+/// The maximum length of lists created by builtins and the maximum amount
+/// ints can be shifted by.
+const MAX_SIZE: usize = 10_000;
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(BigInt),
+    Text(String),
+    Tag {
+        symbol: String,
+        value: Option<Box<Value>>,
+    },
+    List(Vec<Value>),
+    /// The entries are sorted by their keys' debug representation so that the
+    /// order doesn't depend on how the struct was created.
+    Struct(Vec<(Value, Value)>),
+    HirId(hir::Id),
+    Builtin(BuiltinFunction),
+    Function(Rc<Closure>),
+}
+#[derive(Clone)]
+pub struct Closure {
+    parameters: Vec<Id>,
+    responsible_parameter: Id,
+    body: Rc<Body>,
+    captured: im::HashMap<Id, Value>,
+}
+impl Debug for Closure {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{{ {} parameters … }}", self.parameters.len())
+    }
+}
+
+impl Value {
+    #[must_use]
+    pub fn tag(symbol: impl Into<String>, value: impl Into<Option<Self>>) -> Self {
+        Self::Tag {
+            symbol: symbol.into(),
+            value: value.into().map(Box::new),
+        }
+    }
+    #[must_use]
+    pub fn nothing() -> Self {
+        Self::tag("Nothing", None)
+    }
+    #[must_use]
+    pub fn bool(value: bool) -> Self {
+        Self::tag(if value { "True" } else { "False" }, None)
+    }
+    fn try_into_bool(&self) -> Option<bool> {
+        match self {
+            Self::Tag {
+                symbol,
+                value: None,
+            } if symbol == "True" => Some(true),
+            Self::Tag {
+                symbol,
+                value: None,
+            } if symbol == "False" => Some(false),
+            _ => None,
+        }
+    }
+    /// Texts are used as they are, other values are formatted.
+    fn to_debug_message(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            _ => self.to_debug_text(),
+        }
+    }
+    /// Creates a struct, removing duplicate keys (the last value wins).
+    ///
+    /// Returns `None` if keys can't be compared.
+    pub(super) fn create_struct(entries: Vec<(Self, Self)>) -> Option<Vec<(Self, Self)>> {
+        let mut deduplicated: Vec<(Self, Self)> = vec![];
+        for (key, value) in entries {
+            let mut existing = None;
+            for (index, (other_key, _)) in deduplicated.iter().enumerate() {
+                if key.equals(other_key)? {
+                    existing = Some(index);
+                    break;
+                }
+            }
+            match existing {
+                Some(index) => deduplicated[index].1 = value,
+                None => deduplicated.push((key, value)),
+            }
+        }
+        deduplicated.sort_by_cached_key(|(key, _)| format!("{key:?}"));
+        Some(deduplicated)
+    }
+
+    /// Compares two values like the `equals` builtin does.
+    ///
+    /// Returns `None` if functions are involved since the interpreter can't
+    /// reproduce their identity semantics.
+    #[must_use]
+    pub fn equals(&self, other: &Self) -> Option<bool> {
+        Some(match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Text(a), Self::Text(b)) => a == b,
+            (
+                Self::Tag {
+                    symbol: symbol_a,
+                    value: value_a,
+                },
+                Self::Tag {
+                    symbol: symbol_b,
+                    value: value_b,
+                },
+            ) => {
+                if symbol_a != symbol_b {
+                    return Some(false);
+                }
+                match (value_a, value_b) {
+                    (None, None) => true,
+                    (Some(a), Some(b)) => a.equals(b)?,
+                    _ => false,
+                }
+            }
+            (Self::List(a), Self::List(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                for (a, b) in a.iter().zip_eq(b) {
+                    if !a.equals(b)? {
+                        return Some(false);
+                    }
+                }
+                true
+            }
+            (Self::Struct(a), Self::Struct(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                for ((key_a, value_a), (key_b, value_b)) in a.iter().zip_eq(b) {
+                    if !key_a.equals(key_b)? || !value_a.equals(value_b)? {
+                        return Some(false);
+                    }
+                }
+                true
+            }
+            (Self::HirId(a), Self::HirId(b)) => a == b,
+            (Self::Builtin(a), Self::Builtin(b)) => a == b,
+            (Self::Function(_), _) | (_, Self::Function(_)) => return None,
+            _ => false,
+        })
+    }
+    /// Whether both values are indistinguishable from the outside.
+    ///
+    /// In contrast to [`Self::equals`], functions are treated as opaque: Any
+    /// two functions accepting the same number of arguments are equivalent.
+    #[must_use]
+    pub fn is_equivalent_to(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Tag {
+                    symbol: symbol_a,
+                    value: value_a,
+                },
+                Self::Tag {
+                    symbol: symbol_b,
+                    value: value_b,
+                },
+            ) => {
+                symbol_a == symbol_b
+                    && match (value_a, value_b) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => a.is_equivalent_to(b),
+                        _ => false,
+                    }
+            }
+            (Self::List(a), Self::List(b)) => {
+                a.len() == b.len() && a.iter().zip_eq(b).all(|(a, b)| a.is_equivalent_to(b))
+            }
+            (Self::Struct(a), Self::Struct(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip_eq(b)
+                        .all(|((key_a, value_a), (key_b, value_b))| {
+                            key_a.is_equivalent_to(key_b) && value_a.is_equivalent_to(value_b)
+                        })
+            }
+            (Self::Builtin(_) | Self::Function(_), Self::Builtin(_) | Self::Function(_)) => {
+                self.argument_count() == other.argument_count()
+            }
+            _ => self.equals(other) == Some(true),
+        }
+    }
+    #[must_use]
+    pub fn argument_count(&self) -> Option<usize> {
+        match self {
+            Self::Builtin(builtin) => Some(builtin.num_parameters()),
+            Self::Function(closure) => Some(closure.parameters.len()),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn to_debug_text(&self) -> String {
+        // `format_value` refers to nested values by copyable handles, so we
+        // flatten the value into a list of nodes referencing each other by
+        // index.
+        enum Node<'a> {
+            Value(&'a Value),
+            Tag(&'a str, Option<usize>),
+            List(Vec<usize>),
+            Struct(Vec<(usize, usize)>),
+        }
+        fn flatten<'a>(value: &'a Value, nodes: &mut Vec<Node<'a>>) -> usize {
+            let node = match value {
+                Value::Tag { symbol, value } => {
+                    Node::Tag(symbol, value.as_ref().map(|value| flatten(value, nodes)))
+                }
+                Value::List(items) => {
+                    Node::List(items.iter().map(|item| flatten(item, nodes)).collect())
+                }
+                Value::Struct(entries) => Node::Struct(
+                    entries
+                        .iter()
+                        .map(|(key, value)| (flatten(key, nodes), flatten(value, nodes)))
+                        .collect(),
+                ),
+                _ => Node::Value(value),
+            };
+            nodes.push(node);
+            nodes.len() - 1
+        }
+
+        let mut nodes = vec![];
+        let root = flatten(self, &mut nodes);
+        format_value(root, Precedence::Low, MaxLength::Unlimited, &|index| {
+            Some(match &nodes[index] {
+                Node::Value(Self::Int(int)) => FormatValue::Int(Cow::Borrowed(int)),
+                Node::Value(Self::Text(text)) => FormatValue::Text(text),
+                Node::Value(_) => FormatValue::Function,
+                Node::Tag(symbol, value) => FormatValue::Tag {
+                    symbol,
+                    value: *value,
+                },
+                Node::List(items) => FormatValue::List(items),
+                Node::Struct(entries) => FormatValue::Struct(Cow::Borrowed(entries)),
+            })
+        })
+        .unwrap()
+    }
+}
+
+/// Why the interpreter stopped before reaching the end of the code.
+#[derive(Clone, Debug)]
+pub enum Exit {
+    Panic { reason: Value, responsible: Value },
+    Unsupported(String),
+}
+pub type InterpreterResult<T = Value> = Result<T, Exit>;
+
+pub struct Interpreter<'a> {
+    /// Returns the unoptimized MIR of a module.
+    load_module: &'a dyn Fn(Module) -> Option<Arc<Mir>>,
+    loaded_modules: Vec<Arc<Mir>>,
+    module_values: FxHashMap<Module, InterpreterResult>,
+    /// Function bodies by the address of the body in the MIR they were
+    /// defined in.
+    ///
+    /// All MIRs that bodies are referenced from (the interpreted MIR, all
+    /// loaded modules, and the bodies stored here) outlive the interpreter, so
+    /// addresses are never reused.
+    function_bodies: FxHashMap<usize, Rc<Body>>,
+    steps: usize,
+    call_depth: usize,
+    /// Texts printed using the `print` builtin.
+    pub printed: Vec<String>,
+}
+impl<'a> Interpreter<'a> {
+    #[must_use]
+    pub fn new(load_module: &'a dyn Fn(Module) -> Option<Arc<Mir>>) -> Self {
+        Self {
+            load_module,
+            loaded_modules: vec![],
+            module_values: FxHashMap::default(),
+            function_bodies: FxHashMap::default(),
+            steps: 0,
+            call_depth: 0,
+            printed: vec![],
+        }
+    }
+
+    /// Evaluates the given MIR from scratch.
+    pub fn run(&mut self, mir: &Mir) -> InterpreterResult {
+        self.steps = 0;
+        self.printed.clear();
+        self.run_body(&mir.body, im::HashMap::new())
+    }
+    /// Calls a function that was returned by an earlier [`Self::run`].
+    pub fn run_function(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+        responsible: Value,
+    ) -> InterpreterResult {
+        self.steps = 0;
+        self.printed.clear();
+        self.call(function, arguments, responsible)
+    }
+    fn run_body(
+        &mut self,
+        body: &Body,
+        mut environment: im::HashMap<Id, Value>,
+    ) -> InterpreterResult {
+        for (id, expression) in body.iter() {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return Err(Exit::Unsupported("The step limit was reached.".to_string()));
+            }
+
+            let value = self.evaluate(expression, &environment)?;
+            environment.insert(id, value);
+        }
+        Ok(environment[&body.return_value()].clone())
+    }
+    fn evaluate(
+        &mut self,
+        expression: &Expression,
+        environment: &im::HashMap<Id, Value>,
+    ) -> InterpreterResult {
+        let get = |id: &Id| environment[id].clone();
+        Ok(match expression {
+            Expression::Int(int) => Value::Int(int.clone()),
+            Expression::Text(text) => Value::Text(text.clone()),
+            Expression::Tag { symbol, value } => {
+                Value::tag(symbol.clone(), value.as_ref().map(get))
+            }
+            Expression::Builtin(builtin) => Value::Builtin(*builtin),
+            Expression::Extern { .. } => {
+                return Err(Exit::Unsupported(
+                    "Extern functions can't be evaluated at compile time.".to_string(),
+                ));
+            }
+            Expression::List(items) => Value::List(items.iter().map(get).collect()),
+            Expression::Struct(entries) => Value::Struct(
+                Value::create_struct(
+                    entries
+                        .iter()
+                        .map(|(key, value)| (get(key), get(value)))
+                        .collect(),
+                )
+                .ok_or_else(|| Exit::Unsupported("Struct keys can't be compared.".to_string()))?,
+            ),
+            Expression::Reference(id) => get(id),
+            Expression::HirId(id) => Value::HirId(id.clone()),
+            Expression::Function {
+                original_hirs: _,
+                parameters,
+                responsible_parameter,
+                body,
+            } => {
+                let body = self
+                    .function_bodies
+                    .entry(ptr::from_ref(body) as usize)
+                    .or_insert_with(|| Rc::new(body.clone()))
+                    .clone();
+                Value::Function(Rc::new(Closure {
+                    parameters: parameters.clone(),
+                    responsible_parameter: *responsible_parameter,
+                    body,
+                    captured: environment.clone(),
+                }))
+            }
+            Expression::Parameter => {
+                return Err(Exit::Unsupported(
+                    "Parameters can't be evaluated.".to_string(),
+                ))
+            }
+            Expression::Call {
+                function,
+                arguments,
+                responsible,
+            } => {
+                return self.call(
+                    &get(function),
+                    arguments.iter().map(get).collect(),
+                    get(responsible),
+                )
+            }
+            Expression::UseModule {
+                current_module,
+                relative_path,
+                responsible: _,
+            } => {
+                let Value::Text(path) = get(relative_path) else {
+                    return Err(Exit::Unsupported("The use path is not a text.".to_string()));
+                };
+                let module = UsePath::parse(&path)
+                    .and_then(|path| path.resolve_relative_to(current_module))
+                    .map_err(Exit::Unsupported)?;
+                return self.use_module(module);
+            }
+            Expression::Panic {
+                reason,
+                responsible,
+            } => {
+                return Err(Exit::Panic {
+                    reason: get(reason),
+                    responsible: get(responsible),
+                })
+            }
+            Expression::TraceCallStarts { .. }
+            | Expression::TraceCallEnds { .. }
+            | Expression::TraceTailCall { .. }
+            | Expression::TraceExpressionEvaluated { .. }
+            | Expression::TraceFoundFuzzableFunction { .. } => Value::nothing(),
+        })
+    }
+    fn use_module(&mut self, module: Module) -> InterpreterResult {
+        if let Some(value) = self.module_values.get(&module) {
+            return value.clone();
+        }
+        let Some(mir) = (self.load_module)(module.clone()) else {
+            return Err(Exit::Unsupported(format!(
+                "Module {module} can't be loaded."
+            )));
+        };
+
+        // Modules importing themselves (transitively) are reported by the
+        // compiler, so we don't need to handle them here.
+        self.module_values.insert(
+            module.clone(),
+            Err(Exit::Unsupported(format!("Module {module} uses itself."))),
+        );
+        self.loaded_modules.push(mir.clone());
+        let value = self.run_body(&mir.body, im::HashMap::new());
+        self.module_values.insert(module, value.clone());
+        value
+    }
+
+    fn call(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+        responsible: Value,
+    ) -> InterpreterResult {
+        match function {
+            Value::Builtin(builtin) => {
+                if arguments.len() != builtin.num_parameters() {
+                    return Err(Exit::Unsupported(format!(
+                        "The builtin {builtin} was called with the wrong number of arguments.",
+                    )));
+                }
+                self.run_builtin(*builtin, &arguments, responsible)
+            }
+            Value::Function(closure) => {
+                if arguments.len() != closure.parameters.len() {
+                    return Err(Exit::Unsupported(
+                        "A function was called with the wrong number of arguments.".to_string(),
+                    ));
+                }
+                if self.call_depth >= MAX_CALL_DEPTH {
+                    return Err(Exit::Unsupported(
+                        "The call depth limit was reached.".to_string(),
+                    ));
+                }
+
+                let mut environment = closure.captured.clone();
+                environment.extend(closure.parameters.iter().copied().zip(arguments));
+                environment.insert(closure.responsible_parameter, responsible);
+
+                self.call_depth += 1;
+                let result = self.run_body(&closure.body, environment);
+                self.call_depth -= 1;
+                result
+            }
+            _ => Err(Exit::Unsupported(
+                "Only functions and builtins can be called.".to_string(),
+            )),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn run_builtin(
+        &mut self,
+        builtin: BuiltinFunction,
+        arguments: &[Value],
+        responsible: Value,
+    ) -> InterpreterResult {
+        let invalid_arguments = || {
+            Exit::Unsupported(format!(
+                "The builtin {builtin} was called with invalid arguments.",
+            ))
+        };
+        let to_size = |int: &BigInt| {
+            int.to_usize()
+                .filter(|it| *it <= MAX_SIZE)
+                .ok_or_else(invalid_arguments)
+        };
+
+        Ok(match (builtin, arguments) {
+            (BuiltinFunction::DebugAssert, [condition, message]) => {
+                if !condition.try_into_bool().ok_or_else(invalid_arguments)? {
+                    return Err(Exit::Panic {
+                        reason: Value::Text(message.to_debug_message()),
+                        responsible,
+                    });
+                }
+                Value::nothing()
+            }
+            (BuiltinFunction::DebugLog, [message]) => {
+                self.printed.push(message.to_debug_message());
+                Value::nothing()
+            }
+            (BuiltinFunction::Equals, [a, b]) => {
+                Value::bool(a.equals(b).ok_or_else(invalid_arguments)?)
+            }
+            (BuiltinFunction::FunctionRun, [function]) => {
+                return self.call(function, vec![], responsible);
+            }
+            (BuiltinFunction::GetArgumentCount, [function]) => Value::Int(
+                function
+                    .argument_count()
+                    .ok_or_else(invalid_arguments)?
+                    .into(),
+            ),
+            (BuiltinFunction::IfElse, [condition, then, else_]) => {
+                let condition = condition.try_into_bool().ok_or_else(invalid_arguments)?;
+                return self.call(if condition { then } else { else_ }, vec![], responsible);
+            }
+            (BuiltinFunction::IntAdd, [Value::Int(a), Value::Int(b)]) => Value::Int(a + b),
+            (BuiltinFunction::IntBitLength, [Value::Int(a)]) if !a.is_negative() => {
+                Value::Int(a.bits().into())
+            }
+            (BuiltinFunction::IntBitwiseAnd, [Value::Int(a), Value::Int(b)]) => Value::Int(a & b),
+            (BuiltinFunction::IntBitwiseOr, [Value::Int(a), Value::Int(b)]) => Value::Int(a | b),
+            (BuiltinFunction::IntBitwiseXor, [Value::Int(a), Value::Int(b)]) => Value::Int(a ^ b),
+            (BuiltinFunction::IntCompareTo, [Value::Int(a), Value::Int(b)]) => {
+                Value::tag(format!("{:?}", a.cmp(b)), None)
+            }
+            (BuiltinFunction::IntDivideTruncating, [Value::Int(a), Value::Int(b)])
+                if !b.is_zero() =>
+            {
+                Value::Int(a / b)
+            }
+            (BuiltinFunction::IntModulo, [Value::Int(a), Value::Int(b)]) if !b.is_zero() => {
+                Value::Int(a.mod_floor(b))
+            }
+            (BuiltinFunction::IntMultiply, [Value::Int(a), Value::Int(b)]) => Value::Int(a * b),
+            (BuiltinFunction::IntParse, [Value::Text(text)]) => BigInt::from_str(text).map_or_else(
+                |_| {
+                    Value::tag(
+                        "Error",
+                        Value::tag("NotAnInteger", Value::Text(text.clone())),
+                    )
+                },
+                |int| Value::tag("Ok", Value::Int(int)),
+            ),
+            (BuiltinFunction::IntRemainder, [Value::Int(a), Value::Int(b)]) if !b.is_zero() => {
+                Value::Int(a % b)
+            }
+            (BuiltinFunction::IntShiftLeft, [Value::Int(value), Value::Int(amount)]) => {
+                Value::Int(value << to_size(amount)?)
+            }
+            (BuiltinFunction::IntShiftRight, [Value::Int(value), Value::Int(amount)]) => {
+                Value::Int(value >> to_size(amount)?)
+            }
+            (BuiltinFunction::IntSubtract, [Value::Int(a), Value::Int(b)]) => Value::Int(a - b),
+            (BuiltinFunction::ListConcatenate, [Value::List(list_a), Value::List(list_b)]) => {
+                if list_a.len() + list_b.len() > MAX_SIZE {
+                    return Err(invalid_arguments());
+                }
+                Value::List(list_a.iter().chain(list_b).cloned().collect())
+            }
+            (BuiltinFunction::ListFilled, [Value::Int(length), item]) => {
+                Value::List(vec![item.clone(); to_size(length)?])
+            }
+            (BuiltinFunction::ListGet, [Value::List(list), Value::Int(index)]) => index
+                .to_usize()
+                .and_then(|index| list.get(index))
+                .ok_or_else(invalid_arguments)?
+                .clone(),
+            (
+                BuiltinFunction::ListGetRange,
+                [Value::List(list), Value::Int(start_inclusive), Value::Int(end_exclusive)],
+            ) => {
+                let (Some(start_inclusive), Some(end_exclusive)) =
+                    (start_inclusive.to_usize(), end_exclusive.to_usize())
+                else {
+                    return Err(invalid_arguments());
+                };
+                Value::List(
+                    list.get(start_inclusive..end_exclusive)
+                        .ok_or_else(invalid_arguments)?
+                        .to_vec(),
+                )
+            }
+            (BuiltinFunction::ListInsert, [Value::List(list), Value::Int(index), item]) => {
+                let index = index
+                    .to_usize()
+                    .filter(|it| *it <= list.len() && list.len() < MAX_SIZE)
+                    .ok_or_else(invalid_arguments)?;
+                let mut list = list.clone();
+                list.insert(index, item.clone());
+                Value::List(list)
+            }
+            (BuiltinFunction::ListLength, [Value::List(list)]) => Value::Int(list.len().into()),
+            (BuiltinFunction::ListRemoveAt, [Value::List(list), Value::Int(index)]) => {
+                let index = index
+                    .to_usize()
+                    .filter(|it| *it < list.len())
+                    .ok_or_else(invalid_arguments)?;
+                let mut list = list.clone();
+                list.remove(index);
+                Value::List(list)
+            }
+            (BuiltinFunction::ListReplace, [Value::List(list), Value::Int(index), item]) => {
+                let index = index
+                    .to_usize()
+                    .filter(|it| *it < list.len())
+                    .ok_or_else(invalid_arguments)?;
+                let mut list = list.clone();
+                list[index] = item.clone();
+                Value::List(list)
+            }
+            (BuiltinFunction::Print, [Value::Text(message)]) => {
+                self.printed.push(message.clone());
+                Value::nothing()
+            }
+            (BuiltinFunction::StructGet, [Value::Struct(entries), key]) => {
+                let mut found = None;
+                for (entry_key, value) in entries {
+                    if key.equals(entry_key).ok_or_else(invalid_arguments)? {
+                        found = Some(value.clone());
+                        break;
+                    }
+                }
+                found.ok_or_else(invalid_arguments)?
+            }
+            (BuiltinFunction::StructGetKeys, [Value::Struct(entries)]) => {
+                Value::List(entries.iter().map(|(key, _)| key.clone()).collect())
+            }
+            (BuiltinFunction::StructHasKey, [Value::Struct(entries), key]) => {
+                let mut has_key = false;
+                for (entry_key, _) in entries {
+                    if key.equals(entry_key).ok_or_else(invalid_arguments)? {
+                        has_key = true;
+                        break;
+                    }
+                }
+                Value::bool(has_key)
+            }
+            (
+                BuiltinFunction::TagGetValue,
+                [Value::Tag {
+                    value: Some(value), ..
+                }],
+            ) => (**value).clone(),
+            (BuiltinFunction::TagHasValue, [Value::Tag { value, .. }]) => {
+                Value::bool(value.is_some())
+            }
+            (BuiltinFunction::TagWithoutValue, [Value::Tag { symbol, .. }]) => {
+                Value::tag(symbol.clone(), None)
+            }
+            (BuiltinFunction::TagWithValue, [Value::Tag { symbol, .. }, value]) => {
+                Value::tag(symbol.clone(), value.clone())
+            }
+            (BuiltinFunction::TextCharacters, [Value::Text(text)]) => Value::List(
+                text.graphemes(true)
+                    .map(|it| Value::Text(it.to_string()))
+                    .collect(),
+            ),
+            (BuiltinFunction::TextCompareTo, [Value::Text(a), Value::Text(b)]) => {
+                Value::tag(format!("{:?}", a.cmp(b)), None)
+            }
+            (BuiltinFunction::TextConcatenate, [Value::Text(a), Value::Text(b)]) => {
+                Value::Text(format!("{a}{b}"))
+            }
+            (BuiltinFunction::TextContains, [Value::Text(text), Value::Text(pattern)]) => {
+                Value::bool(text.contains(pattern.as_str()))
+            }
+            (BuiltinFunction::TextEndsWith, [Value::Text(text), Value::Text(suffix)]) => {
+                Value::bool(text.ends_with(suffix.as_str()))
+            }
+            (BuiltinFunction::TextFromUtf8, [bytes @ Value::List(items)]) => {
+                let bytes_vec: Vec<u8> = items
+                    .iter()
+                    .map(|it| match it {
+                        Value::Int(int) => int.to_u8(),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid_arguments)?;
+                String::from_utf8(bytes_vec).map_or_else(
+                    |_| Value::tag("Error", Value::tag("NotUtf8", bytes.clone())),
+                    |text| Value::tag("Ok", Value::Text(text)),
+                )
+            }
+            (
+                BuiltinFunction::TextGetRange,
+                [Value::Text(text), Value::Int(start_inclusive), Value::Int(end_exclusive)],
+            ) => {
+                let (Some(start_inclusive), Some(end_exclusive)) =
+                    (start_inclusive.to_usize(), end_exclusive.to_usize())
+                else {
+                    return Err(invalid_arguments());
+                };
+                if start_inclusive > end_exclusive {
+                    return Err(invalid_arguments());
+                }
+                Value::Text(
+                    text.graphemes(true)
+                        .skip(start_inclusive)
+                        .take(end_exclusive - start_inclusive)
+                        .collect(),
+                )
+            }
+            (BuiltinFunction::TextIsEmpty, [Value::Text(text)]) => Value::bool(text.is_empty()),
+            (BuiltinFunction::TextLength, [Value::Text(text)]) => {
+                Value::Int(text.graphemes(true).count().into())
+            }
+            (BuiltinFunction::TextStartsWith, [Value::Text(text), Value::Text(prefix)]) => {
+                Value::bool(text.starts_with(prefix.as_str()))
+            }
+            (BuiltinFunction::TextToLowercase, [Value::Text(text)]) => {
+                Value::Text(text.to_lowercase())
+            }
+            (BuiltinFunction::TextToUppercase, [Value::Text(text)]) => {
+                Value::Text(text.to_uppercase())
+            }
+            (BuiltinFunction::TextTrimEnd, [Value::Text(text)]) => {
+                Value::Text(text.trim_end().to_string())
+            }
+            (BuiltinFunction::TextTrimStart, [Value::Text(text)]) => {
+                Value::Text(text.trim_start().to_string())
+            }
+            (BuiltinFunction::ToDebugText, [value]) => Value::Text(value.to_debug_text()),
+            (BuiltinFunction::Try, [function]) => match self.call(function, vec![], responsible) {
+                Ok(value) => Value::tag("Ok", value),
+                Err(Exit::Panic { reason, .. }) => Value::tag("Error", reason),
+                Err(exit @ Exit::Unsupported(_)) => return Err(exit),
+            },
+            (BuiltinFunction::TypeOf, [value]) => Value::tag(
+                match value {
+                    Value::Int(_) => "Int",
+                    Value::Text(_) => "Text",
+                    Value::Tag { .. } => "Tag",
+                    Value::List(_) => "List",
+                    Value::Struct(_) => "Struct",
+                    Value::HirId(_) => return Err(invalid_arguments()),
+                    // Like constant folding, we treat builtins as functions.
+                    // Otherwise, calling them would panic in the unoptimized
+                    // MIR since `hir_to_mir` only allows calling functions.
+                    Value::Builtin(_) | Value::Function(_) => "Function",
+                },
+                None,
+            ),
+            _ => return Err(invalid_arguments()),
+        })
+    }
+}
@@ -2,6 +2,7 @@ use super::{complexity::Complexity, current_expression::CurrentExpression};
 use crate::{
     hir_to_mir::ExecutionTarget,
     mir::{Body, Id},
+    optimization::OptimizationConfig,
     rich_ir::RichIrBuilder,
     tracing::TracingConfig,
 };
@@ -26,12 +27,14 @@ impl OptimizationLogger {
     pub fn log_optimized_mir_without_tail_calls_start(
         target: &ExecutionTarget,
         tracing: TracingConfig,
+        optimization: OptimizationConfig,
     ) {
         Self::run(|logger| {
             logger.write_line("1. `optimized_mir_without_tail_calls(…)`");
             logger.indent();
             logger.write_newline();
             logger.write_line(&format!("Execution target: {target}"));
+            logger.write_line(&format!("Optimization level: {:?}", optimization.level));
             logger.write_newline();
             logger.write_line("```python");
             let mut builder = RichIrBuilder::default();
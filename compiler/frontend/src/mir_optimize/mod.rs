@@ -44,49 +44,91 @@
 
 use self::{
     current_expression::{Context, CurrentExpression},
+    fuel::OptimizationFuel,
     log::OptimizationLogger,
     pure::PurenessInsights,
 };
-use super::{hir, hir_to_mir::HirToMir, mir::Mir, tracing::TracingConfig};
+use super::{
+    cancellation::CancellationTokenOwner,
+    hir,
+    hir_to_mir::HirToMir,
+    mir::Mir,
+    optimization::{InliningMode, OptimizationConfig},
+    tracing::TracingConfig,
+};
 use crate::{
     error::CompilerError,
     hir_to_mir::ExecutionTarget,
-    mir::{Body, Expression, MirError, VisibleExpressions},
+    mir::{Body, Expression, Id, MirError, VisibleExpressions},
+    module::Module,
     string_to_rcst::ModuleError,
     utils::DoHash,
 };
 use rustc_hash::FxHashSet;
 use std::{mem, sync::Arc};
-use tracing::debug;
+use tracing::{debug, error};
 
 mod after_panic;
 mod call_tracing;
+mod capture_minimization;
 mod cleanup;
 mod common_subtree_elimination;
 mod complexity;
 mod constant_folding;
 mod constant_lifting;
 mod current_expression;
+pub mod escape_analysis;
+pub mod fuel;
+mod function_deduplication;
 mod inlining;
+pub mod interpreter;
 mod log;
-mod module_folding;
+pub(crate) mod module_folding;
 mod pure;
 mod reference_following;
+mod specialization;
+mod strip;
 mod tail_calls;
 mod tree_shaking;
 mod utils;
-mod validate;
+pub mod validate;
 
 #[salsa::query_group(OptimizeMirStorage)]
-pub trait OptimizeMir: HirToMir {
-    fn optimized_mir(&self, target: ExecutionTarget, tracing: TracingConfig) -> OptimizedMirResult;
+pub trait OptimizeMir: HirToMir + CancellationTokenOwner {
+    fn optimized_mir(
+        &self,
+        target: ExecutionTarget,
+        tracing: TracingConfig,
+        optimization: OptimizationConfig,
+    ) -> OptimizedMirResult;
 
     #[salsa::cycle(recover_from_cycle)]
     fn optimized_mir_without_tail_calls(
         &self,
         target: ExecutionTarget,
         tracing: TracingConfig,
+        optimization: OptimizationConfig,
     ) -> OptimizedMirWithoutTailCallsResult;
+
+    /// Optimizes a module that's part of an import cycle while it's being
+    /// imported by the `importers` (the outermost one first). Only importers
+    /// that are part of the cycle are passed so that the result is shared
+    /// between import chains entering the cycle through the same module.
+    ///
+    /// `use`s of the module itself or any of the importers are not folded,
+    /// but replaced with a deferred cycle error that panics when it's
+    /// evaluated. See [`module_folding`] for details.
+    #[salsa::cycle(recover_from_cycle_in_import_cycle)]
+    fn optimized_mir_in_import_cycle(
+        &self,
+        module: Module,
+        importers: Vec<Module>,
+        tracing: TracingConfig,
+        optimization: OptimizationConfig,
+    ) -> OptimizedMirWithoutTailCallsResult;
+
+    /// The modules that are imported using `use` with a text literal.
+    fn statically_imported_modules(&self, module: Module) -> Arc<FxHashSet<Module>>;
 }
 
 pub type OptimizedMirResult = Result<(Arc<Mir>, Arc<FxHashSet<CompilerError>>), ModuleError>;
@@ -105,11 +147,15 @@ fn optimized_mir(
     db: &dyn OptimizeMir,
     target: ExecutionTarget,
     tracing: TracingConfig,
+    optimization: OptimizationConfig,
 ) -> OptimizedMirResult {
-    let (mir, _, errors) = db.optimized_mir_without_tail_calls(target, tracing)?;
+    let (mir, _, errors) = db.optimized_mir_without_tail_calls(target, tracing, optimization)?;
     let mut mir = (*mir).clone();
 
     tail_calls::simplify_tail_call_tracing(&mut mir);
+    if tracing.strip {
+        strip::strip(&mut mir);
+    }
 
     Ok((Arc::new(mir), errors))
 }
@@ -119,48 +165,125 @@ fn optimized_mir_without_tail_calls(
     db: &dyn OptimizeMir,
     target: ExecutionTarget,
     tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> OptimizedMirWithoutTailCallsResult {
+    let module = target.module().clone();
+    let (mir, pureness, mut errors) =
+        optimize_module(db, &target, &[module], tracing, optimization)?;
+
+    // Modules imported by this one may contain `use`s that close a cycle. The
+    // resulting panics only matter if they happen while loading this module.
+    module_folding::remove_cycle_errors_not_reached_while_loading(&mir, &mut errors);
+
+    Ok((Arc::new(mir), Arc::new(pureness), Arc::new(errors)))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn optimized_mir_in_import_cycle(
+    db: &dyn OptimizeMir,
+    module: Module,
+    importers: Vec<Module>,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
 ) -> OptimizedMirWithoutTailCallsResult {
+    let mut import_stack = importers;
+    import_stack.push(module.clone());
+    let (mir, pureness, errors) = optimize_module(
+        db,
+        &ExecutionTarget::Module(module),
+        &import_stack,
+        tracing,
+        optimization,
+    )?;
+    Ok((Arc::new(mir), Arc::new(pureness), Arc::new(errors)))
+}
+
+fn optimize_module(
+    db: &dyn OptimizeMir,
+    target: &ExecutionTarget,
+    import_stack: &[Module],
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> Result<(Mir, PurenessInsights, FxHashSet<CompilerError>), ModuleError> {
     let module = target.module();
     debug!("{module}: Compiling.");
-    OptimizationLogger::log_optimized_mir_without_tail_calls_start(&target, tracing);
-    let (mir, errors) = db.mir(target.clone(), tracing)?;
-    let mut mir = (*mir).clone();
+    OptimizationLogger::log_optimized_mir_without_tail_calls_start(target, tracing, optimization);
+    let (original_mir, errors) = db.mir(target.clone(), tracing)?;
+    let mut mir = (*original_mir).clone();
     let mut pureness = PurenessInsights::default();
     let mut errors = (*errors).clone();
 
     let complexity_before = mir.complexity();
-    mir.optimize(db, &tracing, &mut pureness, &mut errors);
+    let fuel = mir.optimize(
+        db,
+        &tracing,
+        &optimization,
+        &mut pureness,
+        &mut errors,
+        import_stack,
+    );
     let complexity_after = mir.complexity();
 
     debug!("{module}: Done. Optimized from {complexity_before} to {complexity_after}");
+    if let Some(fuel) = fuel {
+        debug!("{module}: {fuel}");
+    }
     OptimizationLogger::log_optimized_mir_without_tail_calls_end(
         complexity_before,
         complexity_after,
     );
-    Ok((Arc::new(mir), Arc::new(pureness), Arc::new(errors)))
+
+    // Uses that close an import cycle panic instead of evaluating the module,
+    // so the optimized MIR intentionally behaves differently in that case.
+    if cfg!(debug_assertions) && validate::should_check_optimizations() && import_stack.len() == 1 {
+        if let Err(violation) = validate::check_equivalence(db, &original_mir, &mir, tracing) {
+            error!("{module}: {violation}");
+            panic!("The MIR optimizations changed the behavior of {module}.");
+        }
+    }
+    Ok((mir, pureness, errors))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn statically_imported_modules(db: &dyn OptimizeMir, module: Module) -> Arc<FxHashSet<Module>> {
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return Arc::default();
+    };
+    Arc::new(module_folding::statically_imported_modules(&module, &asts))
 }
 
 impl Mir {
+    /// Returns the remaining fuel if [`OptimizationConfig::fuel`] is set.
     pub fn optimize(
         &mut self,
         db: &dyn OptimizeMir,
         tracing: &TracingConfig,
+        optimization: &OptimizationConfig,
         pureness: &mut PurenessInsights,
         errors: &mut FxHashSet<CompilerError>,
-    ) {
+        import_stack: &[Module],
+    ) -> Option<OptimizationFuel> {
         let mut context = Context {
             db,
             tracing,
+            optimization,
             errors,
             visible: &mut VisibleExpressions::none_visible(),
             id_generator: &mut self.id_generator,
             pureness,
+            import_stack,
+            fuel: optimization.fuel.map(OptimizationFuel::new),
         };
         context.optimize_body(&mut self.body);
+        let fuel = context.fuel;
+        if optimization.function_deduplication {
+            self.deduplicate_functions(pureness);
+        }
         if cfg!(debug_assertions) {
             self.validate();
         }
         self.cleanup(pureness);
+        fuel
     }
 }
 
@@ -171,6 +294,10 @@ impl Context<'_> {
         OptimizationLogger::log_optimize_body_start(body);
         let mut index = 0;
         while index < body.expressions.len() {
+            // Optimizing big modules takes a while, so we regularly check
+            // whether anyone is still interested in the result.
+            self.db.unwind_if_canceled();
+
             // Thoroughly optimize the expression.
             let mut expression = CurrentExpression::new(body, index);
             self.optimize_expression(&mut expression);
@@ -191,8 +318,27 @@ impl Context<'_> {
             *expression = self.visible.remove(*id);
         }
 
-        after_panic::remove_expressions_after_panic(body, self.pureness);
-        common_subtree_elimination::eliminate_common_subtrees(body, self.pureness);
+        Self::apply_body_optimization(
+            &mut self.fuel,
+            body,
+            "Removing Expressions After Panic",
+            |body| {
+                after_panic::remove_expressions_after_panic(body, self.pureness);
+            },
+        );
+        if self.optimization.common_subtree_elimination {
+            Self::apply_body_optimization(
+                &mut self.fuel,
+                body,
+                "Common Subtree Elimination",
+                |body| {
+                    common_subtree_elimination::eliminate_common_subtrees(body, self.pureness);
+                },
+            );
+        }
+        Self::apply_body_optimization(&mut self.fuel, body, "Capture Minimization", |body| {
+            capture_minimization::minimize_captures(body, self.pureness);
+        });
         {
             // Reference following
             let mut index = 0;
@@ -215,12 +361,61 @@ impl Context<'_> {
                 *expression = self.visible.remove(*id);
             }
         }
-        call_tracing::remove_unnecessary_call_tracing(body, self.pureness, self.tracing.calls);
-        tree_shaking::tree_shake(body, self.pureness);
-        reference_following::remove_redundant_return_references(body, self.pureness);
+        Self::apply_body_optimization(&mut self.fuel, body, "Call Tracing Removal", |body| {
+            call_tracing::remove_unnecessary_call_tracing(body, self.pureness, self.tracing.calls);
+        });
+        if self.optimization.specialization {
+            // Unused references would prevent specializing functions.
+            Self::apply_body_optimization(&mut self.fuel, body, "Tree Shaking", |body| {
+                tree_shaking::tree_shake(body, self.pureness);
+            });
+            let mut specialized_functions = FxHashSet::default();
+            Self::apply_body_optimization(&mut self.fuel, body, "Specialization", |body| {
+                specialized_functions =
+                    specialization::specialize_functions(body, self.pureness, self.id_generator);
+            });
+            if !specialized_functions.is_empty() {
+                self.optimize_expressions_again(body, &specialized_functions);
+            }
+        }
+        Self::apply_body_optimization(&mut self.fuel, body, "Tree Shaking", |body| {
+            tree_shaking::tree_shake(body, self.pureness);
+        });
+        Self::apply_body_optimization(
+            &mut self.fuel,
+            body,
+            "Redundant Return Reference Removal",
+            |body| {
+                reference_following::remove_redundant_return_references(body, self.pureness);
+            },
+        );
         OptimizationLogger::log_optimize_body_end();
     }
 
+    /// Optimizes the expressions with the given IDs again, e.g., because they
+    /// are copies of functions in which more values are known.
+    fn optimize_expressions_again(&mut self, body: &mut Body, ids: &FxHashSet<Id>) {
+        let mut index = 0;
+        while index < body.expressions.len() {
+            let mut expression = CurrentExpression::new(body, index);
+            if ids.contains(&expression.id()) {
+                self.optimize_expression(&mut expression);
+                if cfg!(debug_assertions) {
+                    expression.validate(self.visible);
+                }
+                self.pureness.visit_optimized(expression.id(), &expression);
+            }
+
+            let new_id = expression.id();
+            index = expression.index() + 1;
+            let expression = mem::replace(expression.get_mut_carefully(), Expression::Parameter);
+            self.visible.insert(new_id, expression);
+        }
+        for (id, expression) in &mut body.expressions {
+            *expression = self.visible.remove(*id);
+        }
+    }
+
     fn optimize_expression(&mut self, expression: &mut CurrentExpression) {
         OptimizationLogger::log_optimize_expression_start(expression);
         'outer: loop {
@@ -250,14 +445,38 @@ impl Context<'_> {
             loop {
                 let hashcode_before = expression.do_hash();
 
+                // Lowering the MIR relies on references being followed, so this
+                // doesn't consume fuel.
                 reference_following::follow_references(self, expression);
-                constant_folding::fold_constants(self, expression);
+                self.apply_optimization(
+                    expression,
+                    "Constant Folding",
+                    constant_folding::fold_constants,
+                );
 
                 let is_call = matches!(**expression, Expression::Call { .. });
-                inlining::inline_tiny_functions(self, expression);
-                inlining::inline_needs_function(self, expression);
+                if self.optimization.inlining >= InliningMode::Tiny {
+                    self.apply_optimization(
+                        expression,
+                        "Inlining Tiny Functions",
+                        inlining::inline_tiny_functions,
+                    );
+                    self.apply_optimization(
+                        expression,
+                        "Inlining Needs Function",
+                        inlining::inline_needs_function,
+                    );
+                }
+                // Calls of functions containing `use` must always be inlined,
+                // so this doesn't consume fuel.
                 inlining::inline_functions_containing_use(self, expression);
-                inlining::inline_calls_with_constant_arguments(self, expression);
+                if self.optimization.inlining >= InliningMode::All {
+                    self.apply_optimization(
+                        expression,
+                        "Inlining Calls With Constant Arguments",
+                        inlining::inline_calls_with_constant_arguments,
+                    );
+                }
                 if is_call && matches!(**expression, Expression::Function { .. }) {
                     // We inlined a function call and the resulting code starts with
                     // a function definition. We need to visit that first before
@@ -265,7 +484,13 @@ impl Context<'_> {
                     continue 'outer;
                 }
 
-                constant_lifting::lift_constants(self, expression);
+                if self.optimization.constant_lifting {
+                    self.apply_optimization(
+                        expression,
+                        "Constant Lifting",
+                        constant_lifting::lift_constants,
+                    );
+                }
 
                 if expression.do_hash() == hashcode_before {
                     break 'outer;
@@ -274,17 +499,88 @@ impl Context<'_> {
         }
         OptimizationLogger::log_optimize_expression_end();
     }
+
+    /// Applies an optimization to the current expression if there's
+    /// [optimization fuel](fuel) left.
+    fn apply_optimization(
+        &mut self,
+        expression: &mut CurrentExpression,
+        optimization_name: &str,
+        optimization: impl FnOnce(&mut Self, &mut CurrentExpression),
+    ) {
+        let Some(fuel) = &self.fuel else {
+            optimization(self, expression);
+            return;
+        };
+        if !fuel.has_fuel() {
+            return;
+        }
+
+        let hashcode_before = expression.do_hash();
+        optimization(self, expression);
+        if expression.do_hash() != hashcode_before {
+            let id = expression.id();
+            self.fuel.as_mut().unwrap().consume(optimization_name, id);
+        }
+    }
+    /// Applies an optimization to a whole body if there's
+    /// [optimization fuel](fuel) left.
+    fn apply_body_optimization(
+        fuel: &mut Option<OptimizationFuel>,
+        body: &mut Body,
+        optimization_name: &str,
+        optimization: impl FnOnce(&mut Body),
+    ) {
+        let Some(fuel) = fuel else {
+            optimization(body);
+            return;
+        };
+        if !fuel.has_fuel() {
+            return;
+        }
+
+        let hashcode_before = body.do_hash();
+        optimization(body);
+        if body.do_hash() != hashcode_before {
+            fuel.consume(optimization_name, body.return_value());
+        }
+    }
 }
 
+/// Import cycles are usually detected before they lead to a query cycle (see
+/// [`module_folding`]). This is only a fallback for `use`s whose path is not a
+/// text literal, but still known at compile-time.
 #[allow(clippy::trivially_copy_pass_by_ref, clippy::unnecessary_wraps)]
 fn recover_from_cycle(
     _db: &dyn OptimizeMir,
     cycle: &[String],
     target: &ExecutionTarget,
     _tracing: &TracingConfig,
+    _optimization: &OptimizationConfig,
+) -> OptimizedMirWithoutTailCallsResult {
+    Ok(panicking_mir_for_cycle(target.module(), cycle))
+}
+#[allow(clippy::trivially_copy_pass_by_ref, clippy::unnecessary_wraps)]
+fn recover_from_cycle_in_import_cycle(
+    _db: &dyn OptimizeMir,
+    cycle: &[String],
+    module: &Module,
+    _importers: &[Module],
+    _tracing: &TracingConfig,
+    _optimization: &OptimizationConfig,
 ) -> OptimizedMirWithoutTailCallsResult {
+    Ok(panicking_mir_for_cycle(module, cycle))
+}
+fn panicking_mir_for_cycle(
+    module: &Module,
+    cycle: &[String],
+) -> (
+    Arc<Mir>,
+    Arc<PurenessInsights>,
+    Arc<FxHashSet<CompilerError>>,
+) {
     let error = CompilerError::for_whole_module(
-        target.module().clone(),
+        module.clone(),
         MirError::ModuleHasCycle {
             cycle: cycle.to_vec(),
         },
@@ -292,13 +588,13 @@ fn recover_from_cycle(
 
     let mir = Mir::build(|body| {
         let reason = body.push_text(error.payload.to_string());
-        let responsible = body.push_hir_id(hir::Id::new(target.module().clone(), vec![]));
+        let responsible = body.push_hir_id(hir::Id::new(module.clone(), vec![]));
         body.push_panic(reason, responsible);
     });
 
-    Ok((
+    (
         Arc::new(mir),
         Arc::default(),
         Arc::new(FxHashSet::from_iter([error])),
-    ))
+    )
 }
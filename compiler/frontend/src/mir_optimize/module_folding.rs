@@ -24,18 +24,65 @@
 //! and compiling other modules. Module folding is a necessity for building
 //! binaries that don't include the Candy compiler itself.
 //!
+//! # Import Cycles
+//!
+//! Modules can import each other in a cycle. Before folding a module, we check
+//! whether it (transitively) imports the module that's currently being
+//! optimized or one of the modules importing it. Only looking at `use`s with a
+//! text literal is enough for that because these are the only ones that can be
+//! resolved before optimizing. If so, the imported module is optimized
+//! separately for the importers that are part of the cycle (see
+//! [`optimized_mir_in_import_cycle`](super::OptimizeMir::optimized_mir_in_import_cycle)).
+//! Importers outside of the cycle don't influence the result, so import chains
+//! that enter the cycle through the same module share it.
+//!
+//! The `use` that closes the cycle can't be folded. Instead, it's replaced with
+//! a deferred cycle error, i.e., a panic that only happens when the `use` is
+//! evaluated:
+//!
+//! ```candy
+//! # a.candy
+//! [identity] = use "..b"
+//! four := identity 4
+//!
+//! # b.candy
+//! identity := { a -> a }
+//! four := {
+//!   [four] = use "..a"
+//!   four
+//! }
+//! ```
+//!
+//! Here, `a` can be loaded and uses `identity` from `b`. We only report a
+//! compile-time error for the cycle if the deferred cycle error is reached
+//! while loading the module.
+//!
+//! Note that calling `four` of `b` always panics, even after `a` finished
+//! loading. This is not lazy initialization: Modules aren't split into thunks
+//! that are evaluated on first access. Instead, each module is folded into its
+//! importer, so there's no loaded instance of `a` that the `use` could refer to
+//! at runtime. Until that's supported, `use`s that close a cycle only work if
+//! they are never evaluated.
+//!
 //! [constant folding]: super::constant_folding
 //! [inlining]: super::inlining
 
-use super::current_expression::{Context, CurrentExpression};
+use super::{
+    current_expression::{Context, CurrentExpression},
+    OptimizeMir,
+};
 use crate::{
+    ast::{
+        Assignment, AssignmentBody, Ast, AstKind, Call, Function, Identifier, List, Match,
+        MatchCase, NamedArgument, OrPattern, Struct, StructAccess, Text, TextPart,
+    },
     error::{CompilerError, CompilerErrorPayload},
     hir_to_mir::ExecutionTarget,
     id::IdGenerator,
-    mir::{Body, BodyBuilder, Expression, Id, MirError},
+    mir::{Body, BodyBuilder, Expression, Id, Mir, MirError},
     module::{Module, UsePath},
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::mem;
 
 const NAME: &str = "Module Folding";
@@ -105,12 +152,69 @@ pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
         }
     };
 
-    match context.db.optimized_mir_without_tail_calls(
-        ExecutionTarget::Module(module_to_import.clone()),
-        context.tracing.for_child_module(),
-    ) {
+    let use_span = match context.visible.get(responsible) {
+        Expression::HirId(id) => context.db.hir_id_to_display_span(id),
+        _ => None,
+    };
+
+    if let Some(index) = context
+        .import_stack
+        .iter()
+        .position(|module| module == &module_to_import)
+    {
+        let cycle = context.import_stack[index..]
+            .iter()
+            .chain([&module_to_import])
+            .map(ToString::to_string)
+            .collect();
+        let mut error = CompilerError::for_whole_module(
+            current_module.clone(),
+            MirError::ModuleHasCycle { cycle },
+        );
+        if let Some(span) = use_span {
+            error.span = span;
+        }
+        let error = error.with_note(
+            "This `use` panics when it's evaluated. Move it into a function that's only called after loading the modules or remove the cycle.",
+        );
+        expression.replace_with_multiple(
+            NAME,
+            panicking_expression(context.id_generator, error.payload.to_string(), responsible),
+            context.pureness,
+        );
+        context.errors.insert(error);
+        return;
+    }
+
+    let importers = importers_in_cycle(context.db, &module_to_import, context.import_stack);
+    let result = if importers.is_empty() {
+        context.db.optimized_mir_without_tail_calls(
+            ExecutionTarget::Module(module_to_import.clone()),
+            context.tracing.for_child_module(),
+            *context.optimization,
+        )
+    } else {
+        context.db.optimized_mir_in_import_cycle(
+            module_to_import.clone(),
+            importers.to_vec(),
+            context.tracing.for_child_module(),
+            *context.optimization,
+        )
+    };
+    match result {
         Ok((mir, other_pureness, more_errors)) => {
-            context.errors.extend(more_errors.iter().cloned());
+            context.errors.extend(more_errors.iter().map(|error| {
+                match (&error.payload, &use_span) {
+                    (CompilerErrorPayload::Mir(MirError::ModuleHasCycle { .. }), Some(span)) => {
+                        error.clone().with_label(
+                            current_module.clone(),
+                            span.clone(),
+                            "The cycle includes this use.",
+                        )
+                    }
+                    _ => error.clone(),
+                }
+            }));
 
             let mapping: FxHashMap<Id, Id> = mir
                 .body
@@ -157,6 +261,141 @@ pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
     };
 }
 
+/// The part of the `import_stack` that forms a cycle with `module`, i.e., the
+/// modules starting at the outermost one that `module` (transitively) imports.
+///
+/// Each module in the stack imports the next one, so `module` also imports all
+/// modules after the outermost one.
+fn importers_in_cycle<'a>(
+    db: &dyn OptimizeMir,
+    module: &Module,
+    import_stack: &'a [Module],
+) -> &'a [Module] {
+    let mut imported = FxHashSet::default();
+    let mut to_visit = vec![module.clone()];
+    while let Some(module) = to_visit.pop() {
+        for imported_module in db.statically_imported_modules(module).iter() {
+            if imported.insert(imported_module.clone()) {
+                to_visit.push(imported_module.clone());
+            }
+        }
+    }
+
+    let start = import_stack
+        .iter()
+        .position(|importer| imported.contains(importer))
+        .unwrap_or(import_stack.len());
+    &import_stack[start..]
+}
+
+/// The modules imported by `use`s with a text literal anywhere in the module.
+#[must_use]
+pub fn statically_imported_modules(module: &Module, asts: &[Ast]) -> FxHashSet<Module> {
+    let mut imported = FxHashSet::default();
+    for ast in asts {
+        collect_statically_imported_modules(module, ast, &mut imported);
+    }
+    imported
+}
+fn collect_statically_imported_modules(
+    module: &Module,
+    ast: &Ast,
+    imported: &mut FxHashSet<Module>,
+) {
+    let visit_all = |asts: &[Ast], imported: &mut FxHashSet<Module>| {
+        for ast in asts {
+            collect_statically_imported_modules(module, ast, imported);
+        }
+    };
+    match &ast.kind {
+        AstKind::Int(_)
+        | AstKind::Text(_)
+        | AstKind::TextPart(_)
+        | AstKind::Identifier(_)
+        | AstKind::Symbol(_)
+        | AstKind::Error { .. } => {}
+        AstKind::List(List(items)) | AstKind::OrPattern(OrPattern(items)) => {
+            visit_all(items, imported);
+        }
+        AstKind::Struct(Struct { fields }) => {
+            for (key, value) in fields {
+                if let Some(key) = key {
+                    collect_statically_imported_modules(module, key, imported);
+                }
+                collect_statically_imported_modules(module, value, imported);
+            }
+        }
+        AstKind::StructAccess(StructAccess { struct_, .. }) => {
+            collect_statically_imported_modules(module, struct_, imported);
+        }
+        AstKind::Function(Function { body, .. }) => visit_all(body, imported),
+        AstKind::Call(Call {
+            receiver,
+            arguments,
+            ..
+        }) => {
+            if let AstKind::Identifier(Identifier(name)) = &receiver.kind
+                && name.value == "use"
+                && let [Ast {
+                    kind: AstKind::Text(Text(parts)),
+                    ..
+                }] = arguments.as_slice()
+                && let [Ast {
+                    kind: AstKind::TextPart(TextPart(path)),
+                    ..
+                }] = parts.as_slice()
+                && let Ok(imported_module) = resolve_module(module, path)
+            {
+                imported.insert(imported_module);
+            }
+            collect_statically_imported_modules(module, receiver, imported);
+            visit_all(arguments, imported);
+        }
+        AstKind::NamedArgument(NamedArgument { value, .. }) => {
+            collect_statically_imported_modules(module, value, imported);
+        }
+        AstKind::Assignment(Assignment { body, .. }) => match body {
+            AssignmentBody::Function { function, .. } => visit_all(&function.body, imported),
+            AssignmentBody::Body { body, .. } => visit_all(body, imported),
+        },
+        AstKind::Match(Match { expression, cases }) => {
+            collect_statically_imported_modules(module, expression, imported);
+            visit_all(cases, imported);
+        }
+        AstKind::MatchCase(MatchCase { body, .. }) => visit_all(body, imported),
+    }
+}
+
+/// Removes errors about import cycles whose panic is not reached while loading
+/// the module, i.e., that are not in the module's top-level body.
+pub fn remove_cycle_errors_not_reached_while_loading(
+    mir: &Mir,
+    errors: &mut FxHashSet<CompilerError>,
+) {
+    let texts: FxHashMap<Id, &str> = mir
+        .body
+        .iter()
+        .filter_map(|(id, expression)| match expression {
+            Expression::Text(text) => Some((id, text.as_str())),
+            _ => None,
+        })
+        .collect();
+    let reached_panic_reasons: FxHashSet<&str> = mir
+        .body
+        .iter()
+        .filter_map(|(_, expression)| match expression {
+            Expression::Panic { reason, .. } => texts.get(reason).copied(),
+            _ => None,
+        })
+        .collect();
+    errors.retain(|error| {
+        !matches!(
+            error.payload,
+            CompilerErrorPayload::Mir(MirError::ModuleHasCycle { .. }),
+        ) || reached_panic_reasons.contains(error.payload.to_string().as_str())
+    });
+}
+
 fn resolve_module(current_module: &Module, path: &str) -> Result<Module, MirError> {
     let Ok(path) = UsePath::parse(path) else {
         return Err(MirError::UseWithInvalidPath {
@@ -189,3 +428,124 @@ fn panicking_expression(
     );
     body.expressions
 }
+
+#[cfg(test)]
+mod tests {
+    use super::importers_in_cycle;
+    use crate::{
+        error::CompilerErrorPayload,
+        hir_to_mir::ExecutionTarget,
+        mir::{Expression, MirError},
+        mir_optimize::OptimizeMir,
+        module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase},
+        OptimizationConfig, TracingConfig,
+    };
+    use std::path::PathBuf;
+
+    fn database(modules: &[(&str, &str)]) -> TestDatabase {
+        let mut db = TestDatabase::default();
+        db.did_open_module(&Module::from_package_name("Builtins".to_string()), vec![]);
+        for (name, source) in modules {
+            db.did_open_module(&module(name), source.as_bytes().to_vec());
+        }
+        db
+    }
+    fn module(name: &str) -> Module {
+        Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec![name.to_string()],
+            ModuleKind::Code,
+        )
+    }
+
+    fn cycle_panics_and_errors(db: &TestDatabase, name: &str) -> (usize, usize) {
+        let (mir, _, errors) = db
+            .optimized_mir_without_tail_calls(
+                ExecutionTarget::Module(module(name)),
+                TracingConfig::off(),
+                OptimizationConfig::default(),
+            )
+            .unwrap();
+        let panics = mir
+            .body
+            .iter()
+            .flat_map(|(_, expression)| {
+                let mut texts = vec![];
+                collect_texts(expression, &mut texts);
+                texts
+            })
+            .filter(|text| text.starts_with("There's a cycle in the used modules"))
+            .count();
+        let errors = errors
+            .iter()
+            .filter(|error| {
+                matches!(
+                    error.payload,
+                    CompilerErrorPayload::Mir(MirError::ModuleHasCycle { .. }),
+                )
+            })
+            .count();
+        (panics, errors)
+    }
+    fn collect_texts(expression: &Expression, texts: &mut Vec<String>) {
+        match expression {
+            Expression::Text(text) => texts.push(text.clone()),
+            Expression::Function { body, .. } => {
+                for (_, expression) in body.iter() {
+                    collect_texts(expression, texts);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn use_closing_a_cycle_in_a_function_always_panics_when_called() {
+        // Similar to the example from the module documentation, but `a` also
+        // exports `four` of `b` so that it's not removed.
+        let db = database(&[
+            (
+                "a",
+                "[identity, four] = use \"..b\"\nfourOfB := four\nidentityOfFour := identity 4\n",
+            ),
+            (
+                "b",
+                "identity := { a -> a }\nfour := {\n  [identityOfFour] = use \"..a\"\n  identityOfFour\n}\n",
+            ),
+        ]);
+
+        // `a` loads fine, but `four` of `b` still contains the deferred cycle
+        // error because modules are not initialized lazily.
+        let (panics, errors) = cycle_panics_and_errors(&db, "a");
+        assert!(panics > 0);
+        assert_eq!(errors, 0);
+    }
+
+    #[test]
+    fn reports_cycles_reached_while_loading() {
+        let db = database(&[
+            ("a", "[b] = use \"..b\"\na := 1\n"),
+            ("b", "[a] = use \"..a\"\nb := 2\n"),
+        ]);
+
+        let (panics, errors) = cycle_panics_and_errors(&db, "a");
+        assert!(panics > 0);
+        assert_eq!(errors, 1);
+    }
+
+    #[test]
+    fn only_importers_in_the_cycle_are_relevant() {
+        // `main` imports the cycle `a` → `b` → `a`, but isn't part of it.
+        let db = database(&[
+            ("main", "[a] = use \"..a\"\n"),
+            ("a", "[b] = use \"..b\"\na := 1\n"),
+            ("b", "b := { [a] = use \"..a\"\n  a\n}\n"),
+        ]);
+
+        assert_eq!(
+            importers_in_cycle(&db, &module("b"), &[module("main"), module("a")]),
+            &[module("a")],
+        );
+        assert!(importers_in_cycle(&db, &module("a"), &[module("main")]).is_empty());
+    }
+}
@@ -35,6 +35,22 @@
 //!
 //! - `call builtins.intAdd $0 $1` (this has needs and can panic)
 //!
+//! # Cells and Text Builders
+//!
+//! Cells and text builders are the only mutable values, so their builtins are
+//! neither deterministic nor pure:
+//!
+//! - `call ✨.cellCreate $0` returns a new cell each time. Merging two calls
+//!   would make code share a cell that expects its own.
+//! - `call ✨.cellGet $0` depends on the previous `cellSet` calls.
+//! - `call ✨.cellSet $0 $1` changes the cell. Removing it because its result
+//!   (`Nothing`) is unused would change what later `cellGet` calls return.
+//!
+//! Consequently, functions using cells are neither deterministic nor pure
+//! either, and cells never become part of compile-time known values. The same
+//! applies to `textBuilderCreate`, `textBuilderAppend`,
+//! `textBuilderAppendInt`, and `textBuilderBuild`.
+//!
 //! # Const
 //!
 //! Const expressions are compile-time known. All captured expressions must also
@@ -65,6 +81,7 @@ impl PurenessInsights {
             | Expression::Text(_)
             | Expression::Tag { .. }
             | Expression::Builtin(_)
+            | Expression::Extern { .. }
             | Expression::List(_)
             | Expression::Struct(_)
             | Expression::Reference(_)
@@ -85,7 +102,22 @@ impl PurenessInsights {
     pub fn is_function_deterministic(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Builtin(builtin) => match builtin {
-                BuiltinFunction::Equals
+                BuiltinFunction::BytesConcatenate
+                | BuiltinFunction::BytesFromList
+                | BuiltinFunction::BytesFromText
+                | BuiltinFunction::BytesGetRange
+                | BuiltinFunction::BytesLength
+                | BuiltinFunction::BytesToList
+                | BuiltinFunction::BytesToText
+                | BuiltinFunction::Equals
+                | BuiltinFunction::FractionAdd
+                | BuiltinFunction::FractionCompareTo
+                | BuiltinFunction::FractionCreate
+                | BuiltinFunction::FractionDenominator
+                | BuiltinFunction::FractionDivide
+                | BuiltinFunction::FractionMultiply
+                | BuiltinFunction::FractionNumerator
+                | BuiltinFunction::FractionSubtract
                 | BuiltinFunction::GetArgumentCount
                 | BuiltinFunction::IntAdd
                 | BuiltinFunction::IntBitLength
@@ -101,12 +133,29 @@ impl PurenessInsights {
                 | BuiltinFunction::IntShiftLeft
                 | BuiltinFunction::IntShiftRight
                 | BuiltinFunction::IntSubtract
+                | BuiltinFunction::ListConcatenate
                 | BuiltinFunction::ListFilled
                 | BuiltinFunction::ListGet
+                | BuiltinFunction::ListGetRange
                 | BuiltinFunction::ListInsert
                 | BuiltinFunction::ListLength
                 | BuiltinFunction::ListRemoveAt
                 | BuiltinFunction::ListReplace
+                | BuiltinFunction::MapEntries
+                | BuiltinFunction::MapFromList
+                | BuiltinFunction::MapGet
+                | BuiltinFunction::MapHasKey
+                | BuiltinFunction::MapInsert
+                | BuiltinFunction::MapRemove
+                | BuiltinFunction::MapSize
+                | BuiltinFunction::MapUnion
+                | BuiltinFunction::SetContains
+                | BuiltinFunction::SetFromList
+                | BuiltinFunction::SetInsert
+                | BuiltinFunction::SetItems
+                | BuiltinFunction::SetRemove
+                | BuiltinFunction::SetSize
+                | BuiltinFunction::SetUnion
                 | BuiltinFunction::StructGet
                 | BuiltinFunction::StructGetKeys
                 | BuiltinFunction::StructHasKey
@@ -115,6 +164,7 @@ impl PurenessInsights {
                 | BuiltinFunction::TagWithoutValue
                 | BuiltinFunction::TagWithValue
                 | BuiltinFunction::TextCharacters
+                | BuiltinFunction::TextCompareTo
                 | BuiltinFunction::TextConcatenate
                 | BuiltinFunction::TextContains
                 | BuiltinFunction::TextEndsWith
@@ -123,13 +173,25 @@ impl PurenessInsights {
                 | BuiltinFunction::TextIsEmpty
                 | BuiltinFunction::TextLength
                 | BuiltinFunction::TextStartsWith
+                | BuiltinFunction::TextToLowercase
+                | BuiltinFunction::TextToUppercase
                 | BuiltinFunction::TextTrimEnd
                 | BuiltinFunction::TextTrimStart
                 | BuiltinFunction::ToDebugText
                 | BuiltinFunction::TypeOf => true,
-                BuiltinFunction::FunctionRun | BuiltinFunction::IfElse | BuiltinFunction::Print => {
-                    false
-                }
+                BuiltinFunction::CellCreate
+                | BuiltinFunction::CellGet
+                | BuiltinFunction::CellSet
+                | BuiltinFunction::DebugAssert
+                | BuiltinFunction::DebugLog
+                | BuiltinFunction::FunctionRun
+                | BuiltinFunction::IfElse
+                | BuiltinFunction::Print
+                | BuiltinFunction::TextBuilderAppend
+                | BuiltinFunction::TextBuilderAppendInt
+                | BuiltinFunction::TextBuilderBuild
+                | BuiltinFunction::TextBuilderCreate
+                | BuiltinFunction::Try => false,
             },
             Expression::Function { body, .. } => body
                 .iter()
@@ -142,7 +204,9 @@ impl PurenessInsights {
             | Expression::HirId(_)
             | Expression::UseModule { .. }
             | Expression::Panic { .. } => true, // always panics
-            Expression::Parameter
+            // Extern functions may do anything.
+            Expression::Extern { .. }
+            | Expression::Parameter
             | Expression::Call { .. }
             | Expression::TraceCallStarts { .. }
             | Expression::TraceCallEnds { .. }
@@ -164,6 +228,7 @@ impl PurenessInsights {
             | Expression::Text(_)
             | Expression::Tag { .. }
             | Expression::Builtin(_)
+            | Expression::Extern { .. }
             | Expression::List(_)
             | Expression::Struct(_)
             | Expression::Reference(_)
@@ -184,7 +249,22 @@ impl PurenessInsights {
     pub fn is_function_pure(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Builtin(builtin) => match builtin {
-                BuiltinFunction::Equals
+                BuiltinFunction::BytesConcatenate
+                | BuiltinFunction::BytesFromList
+                | BuiltinFunction::BytesFromText
+                | BuiltinFunction::BytesGetRange
+                | BuiltinFunction::BytesLength
+                | BuiltinFunction::BytesToList
+                | BuiltinFunction::BytesToText
+                | BuiltinFunction::Equals
+                | BuiltinFunction::FractionAdd
+                | BuiltinFunction::FractionCompareTo
+                | BuiltinFunction::FractionCreate
+                | BuiltinFunction::FractionDenominator
+                | BuiltinFunction::FractionDivide
+                | BuiltinFunction::FractionMultiply
+                | BuiltinFunction::FractionNumerator
+                | BuiltinFunction::FractionSubtract
                 | BuiltinFunction::GetArgumentCount
                 | BuiltinFunction::IntAdd
                 | BuiltinFunction::IntBitLength
@@ -200,12 +280,29 @@ impl PurenessInsights {
                 | BuiltinFunction::IntShiftLeft
                 | BuiltinFunction::IntShiftRight
                 | BuiltinFunction::IntSubtract
+                | BuiltinFunction::ListConcatenate
                 | BuiltinFunction::ListFilled
                 | BuiltinFunction::ListGet
+                | BuiltinFunction::ListGetRange
                 | BuiltinFunction::ListInsert
                 | BuiltinFunction::ListLength
                 | BuiltinFunction::ListRemoveAt
                 | BuiltinFunction::ListReplace
+                | BuiltinFunction::MapEntries
+                | BuiltinFunction::MapFromList
+                | BuiltinFunction::MapGet
+                | BuiltinFunction::MapHasKey
+                | BuiltinFunction::MapInsert
+                | BuiltinFunction::MapRemove
+                | BuiltinFunction::MapSize
+                | BuiltinFunction::MapUnion
+                | BuiltinFunction::SetContains
+                | BuiltinFunction::SetFromList
+                | BuiltinFunction::SetInsert
+                | BuiltinFunction::SetItems
+                | BuiltinFunction::SetRemove
+                | BuiltinFunction::SetSize
+                | BuiltinFunction::SetUnion
                 | BuiltinFunction::StructGet
                 | BuiltinFunction::StructGetKeys
                 | BuiltinFunction::StructHasKey
@@ -214,6 +311,7 @@ impl PurenessInsights {
                 | BuiltinFunction::TagWithoutValue
                 | BuiltinFunction::TagWithValue
                 | BuiltinFunction::TextCharacters
+                | BuiltinFunction::TextCompareTo
                 | BuiltinFunction::TextConcatenate
                 | BuiltinFunction::TextContains
                 | BuiltinFunction::TextEndsWith
@@ -222,13 +320,25 @@ impl PurenessInsights {
                 | BuiltinFunction::TextIsEmpty
                 | BuiltinFunction::TextLength
                 | BuiltinFunction::TextStartsWith
+                | BuiltinFunction::TextToLowercase
+                | BuiltinFunction::TextToUppercase
                 | BuiltinFunction::TextTrimEnd
                 | BuiltinFunction::TextTrimStart
                 | BuiltinFunction::ToDebugText
                 | BuiltinFunction::TypeOf => true,
-                BuiltinFunction::FunctionRun | BuiltinFunction::IfElse | BuiltinFunction::Print => {
-                    false
-                }
+                BuiltinFunction::CellCreate
+                | BuiltinFunction::CellGet
+                | BuiltinFunction::CellSet
+                | BuiltinFunction::DebugAssert
+                | BuiltinFunction::DebugLog
+                | BuiltinFunction::FunctionRun
+                | BuiltinFunction::IfElse
+                | BuiltinFunction::Print
+                | BuiltinFunction::TextBuilderAppend
+                | BuiltinFunction::TextBuilderAppendInt
+                | BuiltinFunction::TextBuilderBuild
+                | BuiltinFunction::TextBuilderCreate
+                | BuiltinFunction::Try => false,
             },
             Expression::Function { body, .. } => body
                 .iter()
@@ -305,6 +415,22 @@ impl PurenessInsights {
         pure_functions.remove(id);
         const_definitions.remove(id);
     }
+    // Called when expressions are copied: Each copy gets the insights of its
+    // original.
+    pub(super) fn on_duplicate(&mut self, mapping: &FxHashMap<Id, Id>) {
+        fn duplicate(values: &mut IdSet, mapping: &FxHashMap<Id, Id>) {
+            for (original_id, new_id) in mapping {
+                if values.contains(*original_id) {
+                    values.insert(*new_id);
+                }
+            }
+        }
+        duplicate(&mut self.deterministic_definitions, mapping);
+        duplicate(&mut self.deterministic_functions, mapping);
+        duplicate(&mut self.pure_definitions, mapping);
+        duplicate(&mut self.pure_functions, mapping);
+        duplicate(&mut self.const_definitions, mapping);
+    }
     pub(super) fn include(&mut self, other: &Self, mapping: &FxHashMap<Id, Id>) {
         fn insert(source: &IdSet, mapping: &FxHashMap<Id, Id>, target: &mut IdSet) {
             for id in source {
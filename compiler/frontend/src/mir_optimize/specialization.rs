@@ -0,0 +1,403 @@
+//! Specialization removes parameters from functions that are only ever called
+//! directly, either because the parameter is unused or because all call sites
+//! pass the same constant for it. This complements [inlining] for functions
+//! that are too big to be inlined.
+//!
+//! Here's a before-and-after example:
+//!
+//! ```mir
+//! $0 = 2                               |  $0 = 2
+//! $1 = { $2 $3 ($4 responsible) ->     |  $1 = { ($4 responsible) ->
+//!   $5 = call ... with $2 ($4 ...)     |    $5 = call ... with $0 ($4 ...)
+//!   ...                                |    ...
+//! }                                    |  }
+//! $6 = call $1 with $0 $a ($b ...)     |  $6 = call $1 with ($b ...)
+//! $7 = call $1 with $0 $c ($d ...)     |  $7 = call $1 with ($d ...)
+//! ```
+//!
+//! Here, `$2` is always `$0` and `$3` is unused, so both are removed.
+//!
+//! If call sites pass different constants for a parameter, we create up to
+//! [`MAX_SPECIALIZATIONS`] copies of the function – one for each combination
+//! of constant arguments – and redirect the call sites to them. Within each
+//! copy, [constant folding] can then make use of the now-known values, which
+//! is why the copies are optimized again afterwards. The original function is
+//! removed by [tree shaking] if it's no longer called.
+//!
+//! A function is only specialized if all of its references are calls with the
+//! correct number of arguments. Otherwise, it might be called from somewhere
+//! we don't know about. Hence, [tree shaking] runs before this optimization to
+//! remove unused references, such as leftover checks whether it's a function.
+//!
+//! [constant folding]: super::constant_folding
+//! [inlining]: super::inlining
+//! [tree shaking]: super::tree_shaking
+
+use super::pure::PurenessInsights;
+use crate::{
+    id::IdGenerator,
+    mir::{Body, Expression, Id},
+};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+const MAX_SPECIALIZATIONS: usize = 4;
+
+/// Returns the IDs of the specialized copies.
+pub fn specialize_functions(
+    body: &mut Body,
+    pureness: &mut PurenessInsights,
+    id_generator: &mut IdGenerator<Id>,
+) -> FxHashSet<Id> {
+    // Constants defined in this body so far. Functions defined afterwards can
+    // capture them.
+    let mut constants = FxHashSet::default();
+    let mut specialized_functions = FxHashSet::default();
+    let mut index = 0;
+    while index < body.expressions.len() {
+        let (id, expression) = &body.expressions[index];
+        if pureness.is_definition_const(expression) {
+            constants.insert(*id);
+        }
+        if matches!(expression, Expression::Function { .. }) {
+            let num_specializations =
+                specialize_function(body, index, &constants, pureness, id_generator);
+            specialized_functions.extend(
+                body.expressions[index + 1..=index + num_specializations]
+                    .iter()
+                    .map(|(id, _)| *id),
+            );
+            index += num_specializations;
+        }
+        index += 1;
+    }
+    specialized_functions
+}
+
+/// Returns the number of specialized copies inserted after the function.
+fn specialize_function(
+    body: &mut Body,
+    index: usize,
+    constants: &FxHashSet<Id>,
+    pureness: &mut PurenessInsights,
+    id_generator: &mut IdGenerator<Id>,
+) -> usize {
+    let (function_id, function) = &body.expressions[index];
+    let function_id = *function_id;
+    if function_id == body.return_value() {
+        return 0;
+    }
+    let Expression::Function { parameters, .. } = function else {
+        unreachable!();
+    };
+    let Some(mut calls) =
+        find_call_arguments(&body.expressions[index..], function_id, parameters.len())
+    else {
+        return 0;
+    };
+    if calls.is_empty() {
+        // Tree shaking takes care of this function.
+        return 0;
+    }
+
+    remove_parameters(body, index, &mut calls, constants, pureness);
+    create_specializations(body, index, &calls, constants, pureness, id_generator)
+}
+
+/// Collects the arguments of all calls of the function. Returns `None` if the
+/// function is referenced in any other way.
+fn find_call_arguments(
+    expressions: &[(Id, Expression)],
+    function: Id,
+    num_parameters: usize,
+) -> Option<Vec<Vec<Id>>> {
+    fn collect(
+        expressions: &[(Id, Expression)],
+        function: Id,
+        num_parameters: usize,
+        calls: &mut Vec<Vec<Id>>,
+    ) -> bool {
+        for (_, expression) in expressions {
+            match expression {
+                Expression::Function { body, .. } => {
+                    if !collect(&body.expressions, function, num_parameters, calls) {
+                        return false;
+                    }
+                }
+                Expression::Call {
+                    function: callee,
+                    arguments,
+                    responsible,
+                } if *callee == function
+                    && arguments.len() == num_parameters
+                    && !arguments.contains(&function)
+                    && *responsible != function =>
+                {
+                    calls.push(arguments.clone());
+                }
+                _ => {
+                    if expression.referenced_ids().contains(&function) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    let mut calls = vec![];
+    collect(expressions, function, num_parameters, &mut calls).then_some(calls)
+}
+
+/// Removes parameters that are unused or always receive the same constant.
+fn remove_parameters(
+    body: &mut Body,
+    index: usize,
+    calls: &mut [Vec<Id>],
+    constants: &FxHashSet<Id>,
+    pureness: &mut PurenessInsights,
+) {
+    let (function_id, function) = &mut body.expressions[index];
+    let function_id = *function_id;
+    let referenced = function.referenced_ids();
+    let Expression::Function {
+        parameters,
+        body: function_body,
+        ..
+    } = function
+    else {
+        unreachable!();
+    };
+
+    // For each parameter: whether to keep it
+    let mut keep = vec![true; parameters.len()];
+    let mut replacements = FxHashMap::default();
+    for (parameter_index, parameter) in parameters.iter().enumerate() {
+        if !referenced.contains(parameter) {
+            keep[parameter_index] = false;
+            continue;
+        }
+
+        let argument = calls[0][parameter_index];
+        if constants.contains(&argument)
+            && calls.iter().all(|call| call[parameter_index] == argument)
+        {
+            keep[parameter_index] = false;
+            replacements.insert(*parameter, argument);
+        }
+    }
+    if keep.iter().all(|keep| *keep) {
+        return;
+    }
+
+    function_body.replace_id_references(&mut |id| {
+        if let Some(replacement) = replacements.get(id) {
+            *id = *replacement;
+        }
+    });
+    for (parameter, _) in parameters.iter().zip(&keep).filter(|(_, keep)| !**keep) {
+        pureness.on_remove(*parameter);
+    }
+    retain_kept(parameters, &keep);
+
+    for call in calls.iter_mut() {
+        retain_kept(call, &keep);
+    }
+    visit_calls_mut(
+        &mut body.expressions[index..],
+        function_id,
+        &mut |_, arguments| retain_kept(arguments, &keep),
+    );
+}
+
+/// Creates copies of the function for call sites passing different constants
+/// and redirects these call sites to them. Returns the number of copies.
+fn create_specializations(
+    body: &mut Body,
+    index: usize,
+    calls: &[Vec<Id>],
+    constants: &FxHashSet<Id>,
+    pureness: &mut PurenessInsights,
+    id_generator: &mut IdGenerator<Id>,
+) -> usize {
+    let (function_id, function) = &body.expressions[index];
+    let function_id = *function_id;
+    if function.referenced_ids().contains(&function_id) {
+        // Copies of recursive functions would still call the original.
+        return 0;
+    }
+
+    let constant_arguments = |arguments: &[Id]| {
+        arguments
+            .iter()
+            .map(|argument| constants.contains(argument).then_some(*argument))
+            .collect_vec()
+    };
+    let specializations = calls
+        .iter()
+        .map(|arguments| constant_arguments(arguments))
+        .filter(|constant_arguments| constant_arguments.iter().any(Option::is_some))
+        .unique()
+        .collect_vec();
+    if specializations.is_empty() || specializations.len() > MAX_SPECIALIZATIONS {
+        return 0;
+    }
+
+    let Expression::Function { parameters, .. } = function else {
+        unreachable!();
+    };
+    let mut specialized_functions = FxHashMap::default();
+    let mut new_expressions = vec![];
+    for constant_arguments in specializations {
+        let fresh_ids: FxHashMap<Id, Id> = function
+            .defined_ids()
+            .into_iter()
+            .map(|id| (id, id_generator.generate()))
+            .collect();
+        pureness.on_duplicate(&fresh_ids);
+
+        let mut mapping = fresh_ids;
+        for (parameter, argument) in parameters.iter().zip(&constant_arguments) {
+            if let Some(argument) = argument {
+                mapping.insert(*parameter, *argument);
+            }
+        }
+        let mut specialized = function.clone();
+        specialized.replace_ids(&mut |id| {
+            if let Some(replacement) = mapping.get(id) {
+                *id = *replacement;
+            }
+        });
+        let Expression::Function { parameters, .. } = &mut specialized else {
+            unreachable!();
+        };
+        let keep = constant_arguments.iter().map(Option::is_none).collect_vec();
+        retain_kept(parameters, &keep);
+
+        let specialized_id = id_generator.generate();
+        pureness.visit_optimized(specialized_id, &specialized);
+        new_expressions.push((specialized_id, specialized));
+        specialized_functions.insert(constant_arguments, (specialized_id, keep));
+    }
+
+    let num_specializations = new_expressions.len();
+    body.expressions.splice(index + 1..=index, new_expressions);
+    visit_calls_mut(
+        &mut body.expressions[index + 1 + num_specializations..],
+        function_id,
+        &mut |callee, arguments| {
+            if let Some((specialized_id, keep)) =
+                specialized_functions.get(&constant_arguments(arguments))
+            {
+                *callee = *specialized_id;
+                retain_kept(arguments, keep);
+            }
+        },
+    );
+    num_specializations
+}
+
+fn visit_calls_mut(
+    expressions: &mut [(Id, Expression)],
+    function: Id,
+    visitor: &mut impl FnMut(&mut Id, &mut Vec<Id>),
+) {
+    for (_, expression) in expressions {
+        match expression {
+            Expression::Function { body, .. } => {
+                visit_calls_mut(&mut body.expressions, function, visitor);
+            }
+            Expression::Call {
+                function: callee,
+                arguments,
+                ..
+            } if *callee == function => visitor(callee, arguments),
+            _ => {}
+        }
+    }
+}
+
+fn retain_kept(ids: &mut Vec<Id>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    ids.retain(|_| *keep.next().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hir_to_mir::ExecutionTarget,
+        mir::{Body, Expression},
+        mir_optimize::OptimizeMir,
+        module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase},
+        optimization::InliningMode,
+        OptimizationConfig, TracingConfig,
+    };
+    use std::path::PathBuf;
+
+    const MATCH_ONE: &str = "f a =\n  a %\n    1 -> One\n    _ -> Other\n";
+
+    fn optimized_body(source: &str) -> Body {
+        let mut db = TestDatabase::default();
+        db.did_open_module(&Module::from_package_name("Builtins".to_string()), vec![]);
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["main".to_string()],
+            ModuleKind::Code,
+        );
+        db.did_open_module(&module, source.as_bytes().to_vec());
+        // Without inlining, `f` stays around to be specialized.
+        let config = OptimizationConfig {
+            inlining: InliningMode::OnlyRequired,
+            ..OptimizationConfig::default()
+        };
+        let (mir, _, _) = db
+            .optimized_mir_without_tail_calls(
+                ExecutionTarget::Module(module),
+                TracingConfig::off(),
+                config,
+            )
+            .unwrap();
+        mir.body.clone()
+    }
+    /// Returns the number of parameters and expressions of each copy of `f`.
+    fn copies_of_f(body: &Body) -> Vec<(usize, usize)> {
+        body.iter()
+            .filter_map(|(_, expression)| match expression {
+                Expression::Function {
+                    original_hirs,
+                    parameters,
+                    body,
+                    ..
+                } if original_hirs
+                    .iter()
+                    .any(|id| id.to_string().ends_with(":main:f")) =>
+                {
+                    Some((parameters.len(), body.expressions.len()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn folds_constants_in_specialized_copies() {
+        let body = optimized_body(&format!("{MATCH_ONE}one := f 1\nother := f 2\n"));
+        // `2` is defined after `f`, so only `f 1` is specialized.
+        let copies = copies_of_f(&body);
+        assert_eq!(copies.len(), 2, "{body}");
+        let (original_parameters, original_expressions) = copies[0];
+        let (specialized_parameters, specialized_expressions) = copies[1];
+        assert_eq!(original_parameters, 1);
+        assert_eq!(specialized_parameters, 0);
+        // Comparing `1` with `1` and branching on the result got folded.
+        assert!(specialized_expressions < original_expressions, "{body}",);
+    }
+
+    #[test]
+    fn keeps_functions_that_are_referenced_otherwise() {
+        let body = optimized_body(&format!(
+            "{MATCH_ONE}one := f 1\nother := f 2\nfunction := f\n",
+        ));
+        assert_eq!(copies_of_f(&body), vec![(1, 5)], "{body}");
+    }
+}
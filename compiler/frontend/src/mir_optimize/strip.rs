@@ -0,0 +1,81 @@
+//! Stripping removes information about the source code that the compiled
+//! program doesn't need to run. This makes byte code and native binaries
+//! smaller and doesn't expose the structure of the source code to people
+//! inspecting them.
+//!
+//! - HIR IDs of Candy code are replaced by [`hir::Id::stripped`]. IDs blaming
+//!   the tooling (e.g., the platform or `needs`) are kept since they are
+//!   independent of the source code.
+//! - Functions forget the HIR IDs they were created from, which are otherwise
+//!   used as their names.
+//! - Tracing expressions are removed together with the HIR IDs only they used.
+//!
+//! Here's a before-and-after example:
+//!
+//! ```mir
+//! $0 = HirId(main:foo)       |  $0 = HirId(stripped)
+//! $1 = HirId(main:bar:1)     |
+//! $2 = traceCallStarts ...   |
+//! $3 = call $4 with $5 ($0)  |  $3 = call $4 with $5 ($0)
+//! $6 = traceCallEnds $3      |
+//! ```
+//!
+//! Since this runs as a final pass over the MIR after all other optimizations,
+//! we don't have to update any [`PurenessInsights`](super::pure::PurenessInsights).
+
+use crate::{
+    hir,
+    mir::{Body, Expression, Mir, VisitorResult},
+    module::Package,
+};
+use rustc_hash::FxHashSet;
+
+pub fn strip(mir: &mut Mir) {
+    mir.body.visit_bodies(&mut |body| {
+        for (_, expression) in body.iter_mut() {
+            match expression {
+                Expression::HirId(id) if !is_tooling(id) => *id = hir::Id::stripped(),
+                Expression::Function { original_hirs, .. } => original_hirs.clear(),
+                _ => {}
+            }
+        }
+        remove_tracing(body);
+    });
+
+    let mut referenced = FxHashSet::default();
+    mir.body.visit(&mut |_, expression, _| {
+        // Functions reference the IDs referenced in their bodies, which we
+        // visit anyway.
+        if !matches!(expression, Expression::Function { .. }) {
+            referenced.extend(expression.referenced_ids());
+        }
+        VisitorResult::Continue
+    });
+    mir.body.visit_bodies(&mut |body| {
+        let return_value = body.return_value();
+        body.remove_all(|id, expression| {
+            id != return_value
+                && matches!(expression, Expression::HirId(_))
+                && !referenced.contains(&id)
+        });
+    });
+}
+
+fn is_tooling(id: &hir::Id) -> bool {
+    matches!(id.module.package(), Package::Tooling(_)) || *id == hir::Id::needs()
+}
+
+fn remove_tracing(body: &mut Body) {
+    let return_value = body.return_value();
+    body.remove_all(|id, expression| {
+        id != return_value
+            && matches!(
+                expression,
+                Expression::TraceCallStarts { .. }
+                    | Expression::TraceCallEnds { .. }
+                    | Expression::TraceTailCall { .. }
+                    | Expression::TraceExpressionEvaluated { .. }
+                    | Expression::TraceFoundFuzzableFunction { .. },
+            )
+    });
+}
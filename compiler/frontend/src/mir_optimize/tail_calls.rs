@@ -16,25 +16,40 @@ fn visit_body(body: &mut Body) {
     }
 }
 fn simplify_body(body: &mut Body) {
-    let [.., (call_id, Expression::Call { .. }), (_, Expression::TraceCallEnds { .. }), (_, Expression::Reference(reference_target))] =
-        &mut body.expressions[..]
-    else {
+    let [.., (_, Expression::Reference(reference_target))] = &body.expressions[..] else {
         return;
     };
+    let reference_target = *reference_target;
 
-    if call_id != reference_target {
+    // When a traced call got inlined and its body ended with a tail call, we
+    // end with multiple `TraceCallEnds` in a row. All of these calls are tail
+    // calls now.
+    let num_trace_call_ends = body.expressions[..body.expressions.len() - 1]
+        .iter()
+        .rev()
+        .take_while(|(_, expression)| matches!(expression, Expression::TraceCallEnds { .. }))
+        .count();
+    if num_trace_call_ends == 0 {
+        return;
+    }
+    let call_index = body.expressions.len() - 2 - num_trace_call_ends;
+    let Some((call_id, Expression::Call { .. })) = body.expressions.get(call_index) else {
+        return;
+    };
+
+    if *call_id != reference_target {
         // There's a call at the end of the function, but we return something
         // else. This does not form a tail call.
         return;
     }
 
     // Remove the trace call ends and reference
-    body.expressions.truncate(body.expressions.len() - 2);
+    body.expressions.truncate(call_index + 1);
 
     // Find the matching trace call starts
     let mut nesting = 0;
-    let mut trace_call_starts = None;
-    for (index, (_, expression)) in body.expressions.iter_mut().enumerate().rev().skip(1) {
+    let mut num_remaining = num_trace_call_ends;
+    for (_, expression) in body.expressions.iter_mut().rev().skip(1) {
         match expression {
             Expression::TraceCallStarts {
                 hir_call,
@@ -42,28 +57,25 @@ fn simplify_body(body: &mut Body) {
                 arguments,
                 responsible,
             } => {
-                if nesting == 0 {
-                    trace_call_starts = Some((
-                        index,
-                        *hir_call,
-                        *function,
-                        mem::take(arguments),
-                        *responsible,
-                    ));
+                if nesting > 0 {
+                    nesting -= 1;
+                    continue;
+                }
+
+                *expression = Expression::TraceTailCall {
+                    hir_call: *hir_call,
+                    function: *function,
+                    arguments: mem::take(arguments),
+                    responsible: *responsible,
+                };
+                num_remaining -= 1;
+                if num_remaining == 0 {
                     break;
                 }
-                nesting -= 1;
             }
             Expression::TraceCallEnds { .. } => nesting += 1,
             _ => {}
         }
     }
-    let (index, hir_call, function, arguments, responsible) = trace_call_starts.unwrap();
-
-    body.expressions[index].1 = Expression::TraceTailCall {
-        hir_call,
-        function,
-        arguments,
-        responsible,
-    };
+    assert_eq!(num_remaining, 0);
 }
@@ -56,7 +56,11 @@ impl Expression {
     }
     fn collect_referenced_ids(&self, referenced: &mut FxHashSet<Id>) {
         match self {
-            Self::Int(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
+            Self::Int(_)
+            | Self::Text(_)
+            | Self::Builtin(_)
+            | Self::Extern { .. }
+            | Self::HirId(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     referenced.insert(*value);
@@ -219,6 +223,16 @@ impl Id {
                 Some(true)
             }
             (Expression::Builtin(a), Expression::Builtin(b)) => Some(a == b),
+            (
+                Expression::Extern {
+                    symbol: symbol_a,
+                    signature: signature_a,
+                },
+                Expression::Extern {
+                    symbol: symbol_b,
+                    signature: signature_b,
+                },
+            ) => Some(symbol_a == symbol_b && signature_a == signature_b),
             (Expression::List(a), Expression::List(b)) => {
                 if a.len() != b.len() {
                     return Some(false);
@@ -262,7 +276,11 @@ impl Expression {
     /// this expression.
     pub fn replace_id_references(&mut self, replacer: &mut impl FnMut(&mut Id)) {
         match self {
-            Self::Int(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
+            Self::Int(_)
+            | Self::Text(_)
+            | Self::Builtin(_)
+            | Self::Extern { .. }
+            | Self::HirId(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     replacer(value);
@@ -1,5 +1,29 @@
-use crate::mir::{Body, Expression, Id, Mir, VisibleExpressions};
+//! Validation of the MIR.
+//!
+//! Besides checking that the MIR is well-formed, this module contains a
+//! differential checker for optimizations: The MIR before and after
+//! optimizing is [interpreted](super::interpreter) and both versions have to
+//! behave the same. This check is expensive, so it only runs if the
+//! `CANDY_MIR_CHECK_OPTIMIZATIONS` environment variable is set (in debug
+//! builds) or when fuzzing the optimizer.
+
+use super::{
+    interpreter::{Exit, Interpreter, InterpreterResult, Value},
+    OptimizeMir,
+};
+use crate::{
+    hir,
+    hir_to_mir::ExecutionTarget,
+    mir::{Body, Expression, Id, Mir, VisibleExpressions},
+    module::Module,
+    OptimizationConfig, TracingConfig,
+};
+use itertools::Itertools;
 use rustc_hash::FxHashSet;
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+};
 use tracing::error;
 
 impl Mir {
@@ -60,3 +84,232 @@ impl Expression {
         }
     }
 }
+
+/// How often each exported function is called with random arguments.
+const RUNS_PER_FUNCTION: usize = 16;
+const SEED: u64 = 0x00C0_FFEE;
+
+#[must_use]
+pub fn should_check_optimizations() -> bool {
+    env::var("CANDY_MIR_CHECK_OPTIMIZATIONS").is_ok()
+}
+
+/// Compares the behavior of the unoptimized and optimized MIR of the given
+/// target.
+pub fn check_optimizations(
+    db: &dyn OptimizeMir,
+    target: ExecutionTarget,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> Result<(), EquivalenceViolation> {
+    let Ok((original, _)) = db.mir(target.clone(), tracing) else {
+        return Ok(());
+    };
+    let Ok((optimized, _, _)) = db.optimized_mir_without_tail_calls(target, tracing, optimization)
+    else {
+        return Ok(());
+    };
+    check_equivalence(db, &original, &optimized, tracing)
+}
+
+/// Checks that both MIRs behave the same.
+///
+/// First, both MIRs are evaluated. If they return a struct (e.g., the exports
+/// of a module), all contained functions are called with random arguments.
+/// Runs that the interpreter doesn't support are skipped.
+pub fn check_equivalence(
+    db: &dyn OptimizeMir,
+    original: &Mir,
+    optimized: &Mir,
+    tracing: TracingConfig,
+) -> Result<(), EquivalenceViolation> {
+    // Modules that haven't been folded are resolved in their unoptimized form
+    // for both MIRs.
+    let load_module = |module: Module| {
+        db.mir(ExecutionTarget::Module(module), tracing.for_child_module())
+            .ok()
+            .map(|(mir, _)| mir)
+    };
+    let mut original_interpreter = Interpreter::new(&load_module);
+    let mut optimized_interpreter = Interpreter::new(&load_module);
+
+    let original_result = original_interpreter.run(original);
+    let optimized_result = optimized_interpreter.run(optimized);
+    let original = Behavior::new(original_result, &original_interpreter);
+    let optimized = Behavior::new(optimized_result, &optimized_interpreter);
+    if !original.is_equivalent_to(&optimized) {
+        return Err(EquivalenceViolation {
+            action: "Evaluating the MIR".to_string(),
+            original,
+            optimized,
+        });
+    }
+
+    let (Ok(Value::Struct(original_entries)), Ok(Value::Struct(optimized_entries))) =
+        (original.result, optimized.result)
+    else {
+        return Ok(());
+    };
+    let mut random = Random::new(SEED);
+    for ((key, original_function), (_, optimized_function)) in
+        original_entries.iter().zip_eq(&optimized_entries)
+    {
+        let Value::Function(_) = original_function else {
+            continue;
+        };
+        let argument_count = original_function.argument_count().unwrap();
+        for _ in 0..RUNS_PER_FUNCTION {
+            let arguments = (0..argument_count).map(|_| random.value(2)).collect_vec();
+            let responsible = Value::HirId(hir::Id::fuzzer());
+
+            let original_result = original_interpreter.run_function(
+                original_function,
+                arguments.clone(),
+                responsible.clone(),
+            );
+            let optimized_result = optimized_interpreter.run_function(
+                optimized_function,
+                arguments.clone(),
+                responsible,
+            );
+            let original = Behavior::new(original_result, &original_interpreter);
+            let optimized = Behavior::new(optimized_result, &optimized_interpreter);
+            if !original.is_equivalent_to(&optimized) {
+                return Err(EquivalenceViolation {
+                    action: format!(
+                        "Calling {} with {}",
+                        key.to_debug_text(),
+                        arguments.iter().map(Value::to_debug_text).join(", "),
+                    ),
+                    original,
+                    optimized,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct EquivalenceViolation {
+    pub action: String,
+    pub original: Behavior,
+    pub optimized: Behavior,
+}
+impl Display for EquivalenceViolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{} behaves differently after optimizing.", self.action)?;
+        writeln!(f, "Before: {}", self.original)?;
+        write!(f, "After: {}", self.optimized)
+    }
+}
+
+#[derive(Debug)]
+pub struct Behavior {
+    pub result: InterpreterResult,
+    pub printed: Vec<String>,
+}
+impl Behavior {
+    fn new(result: InterpreterResult, interpreter: &Interpreter) -> Self {
+        Self {
+            result,
+            printed: interpreter.printed.clone(),
+        }
+    }
+
+    /// Unsupported runs are considered equivalent to everything.
+    fn is_equivalent_to(&self, other: &Self) -> bool {
+        let results_are_equivalent = match (&self.result, &other.result) {
+            (Err(Exit::Unsupported(_)), _) | (_, Err(Exit::Unsupported(_))) => return true,
+            (Ok(a), Ok(b)) => a.is_equivalent_to(b),
+            (
+                Err(Exit::Panic {
+                    reason: reason_a,
+                    responsible: responsible_a,
+                }),
+                Err(Exit::Panic {
+                    reason: reason_b,
+                    responsible: responsible_b,
+                }),
+            ) => {
+                reason_a.is_equivalent_to(reason_b) && responsible_a.is_equivalent_to(responsible_b)
+            }
+            _ => false,
+        };
+        results_are_equivalent && self.printed == other.printed
+    }
+}
+impl Display for Behavior {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.result {
+            Ok(value) => write!(f, "returned {}", value.to_debug_text())?,
+            Err(Exit::Panic {
+                reason,
+                responsible,
+            }) => write!(
+                f,
+                "panicked with {} ({} is responsible)",
+                reason.to_debug_text(),
+                responsible.to_debug_text(),
+            )?,
+            Err(Exit::Unsupported(reason)) => write!(f, "unsupported: {reason}")?,
+        }
+        if !self.printed.is_empty() {
+            write!(f, ", printed {:?}", self.printed)?;
+        }
+        Ok(())
+    }
+}
+
+/// A xorshift pseudo random number generator, so that runs are reproducible.
+struct Random(u64);
+impl Random {
+    const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    fn choose<'a, T>(&mut self, options: &'a [T]) -> &'a T {
+        &options[(self.next() % options.len() as u64) as usize]
+    }
+
+    fn value(&mut self, depth: usize) -> Value {
+        const TEXTS: &[&str] = &["", "a", "Candy", " padded ", "🍭", "123", "-4"];
+        const SYMBOLS: &[&str] = &["True", "False", "Nothing", "Ok", "Error", "Less", "Foo"];
+
+        let kinds = if depth == 0 { 3 } else { 6 };
+        match self.next() % kinds {
+            0 => Value::Int(match self.next() % 4 {
+                0 => 0.into(),
+                1 => (*self.choose(&[1, -1, 2, 10, 255, 256])).into(),
+                #[allow(clippy::cast_possible_wrap)]
+                2 => (self.next() as i64).into(),
+                _ => (self.next() % 100).into(),
+            }),
+            1 => Value::Text((*self.choose(TEXTS)).to_string()),
+            2 => Value::tag(*self.choose(SYMBOLS), None),
+            3 => Value::tag(*self.choose(SYMBOLS), self.value(depth - 1)),
+            4 => Value::List(
+                (0..self.next() % 4)
+                    .map(|_| self.value(depth - 1))
+                    .collect(),
+            ),
+            _ => {
+                let entries = (0..self.next() % 4)
+                    .map(|_| {
+                        (
+                            Value::tag(*self.choose(SYMBOLS), None),
+                            self.value(depth - 1),
+                        )
+                    })
+                    .collect();
+                Value::Struct(Value::create_struct(entries).unwrap())
+            }
+        }
+    }
+}
@@ -5,11 +5,12 @@ use crate::{
     hir_to_mir::ExecutionTarget,
     id::CountableId,
     lir::{self, Lir},
-    mir,
-    mir_optimize::OptimizeMir,
+    mir::{self, Mir},
+    mir_optimize::{escape_analysis, OptimizeMir},
+    module::Module,
     string_to_rcst::ModuleError,
     utils::{HashMapExtension, HashSetExtension},
-    TracingConfig,
+    OptimizationConfig, TracingConfig,
 };
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -17,16 +18,36 @@ use std::sync::Arc;
 
 #[salsa::query_group(MirToLirStorage)]
 pub trait MirToLir: OptimizeMir {
-    fn lir(&self, target: ExecutionTarget, tracing: TracingConfig) -> LirResult;
+    fn lir(
+        &self,
+        target: ExecutionTarget,
+        tracing: TracingConfig,
+        optimization: OptimizationConfig,
+    ) -> LirResult;
 }
 
 pub type LirResult = Result<(Arc<Lir>, Arc<FxHashSet<CompilerError>>), ModuleError>;
 
-fn lir(db: &dyn MirToLir, target: ExecutionTarget, tracing: TracingConfig) -> LirResult {
+fn lir(
+    db: &dyn MirToLir,
+    target: ExecutionTarget,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> LirResult {
     let module = target.module().clone();
-    let (mir, errors) = db.optimized_mir(target, tracing)?;
+    let (mir, errors) = db.optimized_mir(target, tracing, optimization)?;
+    let lir = lower_mir(module, &mir);
+    Ok((Arc::new(lir), errors))
+}
 
-    let mut context = LoweringContext::default();
+/// Lowers the MIR of a module that wasn't produced by the [`MirToLir::lir`]
+/// query, e.g., only a part of a module's MIR.
+#[must_use]
+pub fn lower_mir(module: Module, mir: &Mir) -> Lir {
+    let mut context = LoweringContext {
+        non_escaping_values: escape_analysis::find_non_escaping_values(mir),
+        ..LoweringContext::default()
+    };
     context.compile_function(
         FxHashSet::from_iter([hir::Id::new(module, vec![])]),
         &[],
@@ -34,9 +55,7 @@ fn lir(db: &dyn MirToLir, target: ExecutionTarget, tracing: TracingConfig) -> Li
         mir::Id::from_usize(0),
         &mir.body,
     );
-    let lir = Lir::new(context.constants, context.bodies);
-
-    Ok((Arc::new(lir), errors))
+    Lir::new(context.constants, context.bodies)
 }
 
 #[derive(Debug, Default)]
@@ -44,8 +63,8 @@ struct LoweringContext {
     constants: lir::Constants,
     constant_mapping: FxHashMap<mir::Id, lir::ConstantId>,
     bodies: lir::Bodies,
-    potential_if_else_bodies: FxHashMap<mir::Id, IfElseBody>,
     delegating_if_else_body_id: Option<lir::BodyId>,
+    non_escaping_values: FxHashSet<mir::Id>,
 }
 #[derive(Clone, Debug)]
 struct IfElseBody {
@@ -118,6 +137,11 @@ struct CurrentBody {
     body: lir::Body,
     current_constant: Option<mir::Id>,
     ids_to_drop: FxHashSet<lir::Id>,
+    /// Functions defined in this body that can be used as ifElse bodies.
+    ///
+    /// Functions defined in an outer body can't, even if they're visible, as
+    /// their captured IDs refer to the outer body.
+    potential_if_else_bodies: FxHashMap<mir::Id, IfElseBody>,
 }
 impl CurrentBody {
     fn compile_function(
@@ -175,6 +199,7 @@ impl CurrentBody {
             body,
             current_constant: None,
             ids_to_drop,
+            potential_if_else_bodies: FxHashMap::default(),
         }
     }
 
@@ -206,6 +231,7 @@ impl CurrentBody {
                             lir::Expression::CreateTag {
                                 symbol: symbol.clone(),
                                 value,
+                                is_frame_allocated: context.non_escaping_values.contains(&id),
                             },
                         );
                     }
@@ -221,6 +247,14 @@ impl CurrentBody {
                 }
             }
             mir::Expression::Builtin(builtin) => self.push_constant(context, id, *builtin),
+            mir::Expression::Extern { symbol, signature } => self.push_constant(
+                context,
+                id,
+                lir::Constant::Extern {
+                    symbol: symbol.clone(),
+                    signature: signature.clone(),
+                },
+            ),
             mir::Expression::List(items) => {
                 if let Some(items) = items
                     .iter()
@@ -230,7 +264,13 @@ impl CurrentBody {
                     self.push_constant(context, id, items);
                 } else {
                     let items = self.ids_for(context, items);
-                    self.push(id, items);
+                    self.push(
+                        id,
+                        lir::Expression::CreateList {
+                            items,
+                            is_frame_allocated: context.non_escaping_values.contains(&id),
+                        },
+                    );
                 }
             }
             mir::Expression::Struct(fields) => {
@@ -249,7 +289,13 @@ impl CurrentBody {
                             (self.id_for(context, *key), self.id_for(context, *value))
                         })
                         .collect_vec();
-                    self.push(id, fields);
+                    self.push(
+                        id,
+                        lir::Expression::CreateStruct {
+                            fields,
+                            is_frame_allocated: context.non_escaping_values.contains(&id),
+                        },
+                    );
                 }
             }
             mir::Expression::Reference(referenced_id) => {
@@ -295,7 +341,7 @@ impl CurrentBody {
                 let captured = self.ids_for(context, &captured);
 
                 if parameters.is_empty() {
-                    context.potential_if_else_bodies.force_insert(
+                    self.potential_if_else_bodies.force_insert(
                         id,
                         IfElseBody {
                             body_id,
@@ -343,7 +389,7 @@ impl CurrentBody {
                     };
 
                     let condition = self.id_for(context, *condition);
-                    let then_body = context
+                    let then_body = self
                         .potential_if_else_bodies
                         .get(then_body)
                         .cloned()
@@ -351,7 +397,7 @@ impl CurrentBody {
                             body_id: context.delegating_if_else_body_id(),
                             captured: vec![self.id_for(context, *then_body)],
                         });
-                    let else_body = context
+                    let else_body = self
                         .potential_if_else_bodies
                         .get(else_body)
                         .cloned()
@@ -395,7 +441,13 @@ impl CurrentBody {
                 // here, since the instructions will never be executed anyway.
                 // We just push an empty struct, as if the imported module
                 // hadn't exported anything.
-                self.push(id, lir::Expression::CreateStruct(vec![]));
+                self.push(
+                    id,
+                    lir::Expression::CreateStruct {
+                        fields: vec![],
+                        is_frame_allocated: false,
+                    },
+                );
             }
             mir::Expression::Panic {
                 reason,
@@ -517,10 +569,26 @@ impl CurrentBody {
 
     fn push(&mut self, mir_id: mir::Id, expression: impl Into<lir::Expression>) -> lir::Id {
         let expression = expression.into();
-        let is_constant = matches!(expression, lir::Expression::Constant(_));
+        // Constants and frame-allocated values aren't reference-counted.
+        let is_reference_counted = !matches!(
+            expression,
+            lir::Expression::Constant(_)
+                | lir::Expression::CreateTag {
+                    is_frame_allocated: true,
+                    ..
+                }
+                | lir::Expression::CreateList {
+                    is_frame_allocated: true,
+                    ..
+                }
+                | lir::Expression::CreateStruct {
+                    is_frame_allocated: true,
+                    ..
+                },
+        );
         let id = self.body.push(expression);
         self.id_mapping.force_insert(mir_id, id);
-        if !is_constant {
+        if is_reference_counted {
             self.ids_to_drop.force_insert(id);
         }
         id
@@ -575,3 +643,89 @@ impl CurrentBody {
         self.body
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::lower_mir;
+    use crate::{
+        builtin_functions::BuiltinFunction,
+        hir,
+        mir::Mir,
+        module::{Module, ModuleKind, Package},
+    };
+    use insta::assert_snapshot;
+    use std::path::PathBuf;
+
+    #[test]
+    fn if_else_bodies_defined_in_outer_bodies_are_captured() {
+        // After function deduplication, an ifElse call can use functions
+        // defined in an outer body as its branches.
+        let mir = Mir::build(|builder| {
+            builder.push_function(hir::Id::dummy(), |builder, _| {
+                let value = builder.new_parameter();
+                let then_body = builder.push_function(hir::Id::dummy(), |builder, _| {
+                    builder.push_reference(value);
+                });
+                let else_body = builder.push_function(hir::Id::dummy(), |builder, _| {
+                    builder.push_reference(value);
+                });
+                builder.push_function(hir::Id::dummy(), |builder, responsible| {
+                    let condition = builder.new_parameter();
+                    let if_else = builder.push_builtin(BuiltinFunction::IfElse);
+                    builder.push_call(if_else, vec![condition, then_body, else_body], responsible);
+                });
+            });
+        });
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec![],
+            ModuleKind::Code,
+        );
+
+        let lir = lower_mir(module, &mir);
+        // The ifElse in `body_4` uses delegating bodies that call the captured
+        // functions.
+        assert_snapshot!(lir.to_text(), @r###"
+        constants:
+          %0 = builtin if_else
+          %1 = function body_5
+        bodies:
+          body_0 captured 1 parameters 0:
+            original_hir tooling "dummy" [] code ""
+            $2 = dup $0 1
+            $3 = reference $0
+          body_1 captured 1 parameters 0:
+            original_hir tooling "dummy" [] code ""
+            $2 = dup $0 1
+            $3 = reference $0
+          body_2 captured 1 parameters 0:
+            $2 = call $0 [] $1
+          body_3 captured 1 parameters 0:
+            $2 = call $0 [] $1
+          body_4 captured 2 parameters 1:
+            original_hir tooling "dummy" [] code ""
+            $4 = dup $2 1
+            $5 = dup $0 1
+            $6 = dup $1 1
+            $7 = if_else $2 body_2 [$0] body_3 [$1] $3
+            $8 = drop $2
+            $9 = reference $7
+          body_5 captured 0 parameters 1:
+            original_hir tooling "dummy" [] code ""
+            $2 = dup $0 1
+            $3 = create_function body_0 [$0]
+            $4 = dup $0 1
+            $5 = create_function body_1 [$0]
+            $6 = dup $3 1
+            $7 = dup $5 1
+            $8 = create_function body_4 [$3 $5]
+            $9 = drop $5
+            $10 = drop $3
+            $11 = drop $0
+            $12 = reference $8
+          body_6 captured 0 parameters 0:
+            original_hir user "/non/existent" [] code ""
+            $1 = constant %1
+        "###);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{InMemoryModuleProvider, Module, ModuleKind, Package};
+use include_dir::{include_dir, Dir};
+use std::path::{Component, Path, PathBuf};
+
+static BUNDLED_BUILTINS: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../packages/Builtins");
+static BUNDLED_CORE: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../packages/Core");
+
+/// A package whose modules only exist in memory.
+///
+/// Tests and embedders can use this to compile Candy code without touching
+/// the file system:
+///
+/// ```
+/// # use candy_frontend::module::InMemoryPackage;
+/// let package = InMemoryPackage::default()
+///     .with_bundled_core()
+///     .with_module("", "[double] = use \".math\"\nmain _ := double 21")
+///     .with_module("math", "double a := a | ✨.intAdd a");
+/// let main = package.module("");
+/// let module_provider = package.into_module_provider();
+/// ```
+///
+/// The resulting provider can then back the database's
+/// [`ModuleProviderOwner`](super::ModuleProviderOwner).
+pub struct InMemoryPackage {
+    package: Package,
+    module_provider: InMemoryModuleProvider,
+}
+impl Default for InMemoryPackage {
+    fn default() -> Self {
+        Self::new(Package::User(PathBuf::from("/")))
+    }
+}
+impl InMemoryPackage {
+    #[must_use]
+    pub fn new(package: Package) -> Self {
+        Self {
+            package,
+            module_provider: InMemoryModuleProvider::default(),
+        }
+    }
+
+    /// Adds a code module. The path is relative to the package and uses `/`
+    /// as a separator, e.g., `foo/bar` for `foo/bar.candy` or
+    /// `foo/bar/_.candy`. The empty path refers to the package's `_.candy`.
+    #[must_use]
+    pub fn with_module(mut self, path: &str, source_code: impl AsRef<str>) -> Self {
+        let module = self.module(path);
+        self.module_provider.add_str(&module, source_code);
+        self
+    }
+    /// Adds an asset module, e.g., `data.json`.
+    #[must_use]
+    pub fn with_asset(mut self, path: &str, content: Vec<u8>) -> Self {
+        let module = Module::new(self.package.clone(), split_path(path), ModuleKind::Asset);
+        self.module_provider.add(&module, content);
+        self
+    }
+    /// Adds the `Builtins` and `Core` packages as they were when the compiler
+    /// was built, so that the modules can `use "Core"` without a packages path.
+    #[must_use]
+    pub fn with_bundled_core(mut self) -> Self {
+        for (name, dir) in [("Builtins", &BUNDLED_BUILTINS), ("Core", &BUNDLED_CORE)] {
+            add_dir(
+                &mut self.module_provider,
+                &Package::Managed(PathBuf::from(name)),
+                dir,
+            );
+        }
+        self
+    }
+
+    #[must_use]
+    pub const fn package(&self) -> &Package {
+        &self.package
+    }
+    /// The code module at the given path, using the same format as
+    /// [`with_module`](Self::with_module).
+    #[must_use]
+    pub fn module(&self, path: &str) -> Module {
+        Module::new(self.package.clone(), split_path(path), ModuleKind::Code)
+    }
+
+    #[must_use]
+    pub fn into_module_provider(self) -> InMemoryModuleProvider {
+        self.module_provider
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|it| !it.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn add_dir(module_provider: &mut InMemoryModuleProvider, package: &Package, dir: &Dir) {
+    for file in dir.files() {
+        let (path, kind) = module_path(file.path());
+        let module = Module::new(package.clone(), path, kind);
+        module_provider.add(&module, file.contents().to_vec());
+    }
+    for child in dir.dirs() {
+        add_dir(module_provider, package, child);
+    }
+}
+/// Mirrors how [`Module::from_package_and_path`] maps files to modules.
+fn module_path(relative_path: &Path) -> (Vec<String>, ModuleKind) {
+    let mut path = relative_path
+        .components()
+        .map(|component| match component {
+            Component::Normal(it) => it.to_str().expect("Invalid UTF-8 in path.").to_owned(),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+
+    let last = path.pop().unwrap();
+    let Some(last) = last.strip_suffix(".candy") else {
+        path.push(last);
+        return (path, ModuleKind::Asset);
+    };
+    if last != "_" {
+        path.push(last.to_string());
+    }
+    (path, ModuleKind::Code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemoryPackage;
+    use crate::module::{Module, ModuleKind, ModuleProvider, Package};
+    use std::path::PathBuf;
+
+    #[test]
+    fn provides_added_and_bundled_modules() {
+        let package = InMemoryPackage::default()
+            .with_bundled_core()
+            .with_module("", "main _ := 1")
+            .with_module("foo/bar", "bar = 2");
+        let root = package.module("");
+        let bar = package.module("/foo/bar");
+        assert_eq!(bar.path(), &["foo", "bar"]);
+
+        let module_provider = package.into_module_provider();
+        assert_eq!(
+            module_provider.get_content(&root).unwrap().as_slice(),
+            b"main _ := 1",
+        );
+        assert!(module_provider.get_content(&bar).is_some());
+        assert!(module_provider
+            .get_content(&Module::from_package_name("Core".to_string()))
+            .is_some());
+        let core_int = Module::new(
+            Package::Managed(PathBuf::from("Core")),
+            vec!["int".to_string()],
+            ModuleKind::Code,
+        );
+        assert!(module_provider.get_content(&core_int).is_some());
+    }
+}
@@ -1,4 +1,7 @@
+#[cfg(test)]
+pub(crate) use self::module_provider_owner::test::Database as TestDatabase;
 pub use self::{
+    in_memory_package::InMemoryPackage,
     module::{Module, ModuleFromPathError, ModuleKind},
     module_provider::{
         FileSystemModuleProvider, InMemoryModuleProvider, ModuleProvider, OverlayModuleProvider,
@@ -6,16 +9,19 @@ pub use self::{
     module_provider_owner::{ModuleProviderOwner, MutableModuleProviderOwner},
     package::{Package, PackagesPath},
     use_path::UsePath,
+    virtual_module_provider::{VirtualModuleLayer, VirtualModuleProvider},
 };
 use salsa::query_group;
 use std::sync::Arc;
 
+mod in_memory_package;
 #[allow(clippy::module_inception)]
 mod module;
 mod module_provider;
 mod module_provider_owner;
 mod package;
 mod use_path;
+mod virtual_module_provider;
 
 #[query_group(ModuleDbStorage)]
 pub trait ModuleDb: ModuleProviderOwner {
@@ -1,10 +1,12 @@
 use super::package::{Package, PackagesPath};
 use crate::{
     impl_display_via_richir,
+    persistent_cache::{entry_module, is_entry_module},
     rich_ir::{RichIrBuilder, ToRichIr, TokenType},
 };
 use enumset::EnumSet;
 use itertools::Itertools;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     fmt::{self, Display, Formatter},
     fs,
@@ -16,13 +18,13 @@ use tracing::{error, warn};
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Module(Arc<ModuleInner>);
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 struct ModuleInner {
     package: Package,
     path: Vec<String>,
     kind: ModuleKind,
 }
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum ModuleKind {
     Code,
     Asset,
@@ -32,6 +34,30 @@ impl From<ModuleInner> for Module {
         Self(Arc::new(val))
     }
 }
+/// Every ID contains its module, so entries of the
+/// [`PersistentCache`](crate::persistent_cache::PersistentCache) store the
+/// module they belong to only once instead of repeating it in every ID.
+impl Serialize for Module {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let inner = if is_entry_module(self) {
+            None
+        } else {
+            Some(&*self.0)
+        };
+        inner.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for Module {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<ModuleInner>::deserialize(deserializer)?.map_or_else(
+            || {
+                entry_module()
+                    .ok_or_else(|| de::Error::custom("The module of the cache entry is missing."))
+            },
+            |inner| Ok(inner.into()),
+        )
+    }
+}
 impl Module {
     #[must_use]
     pub fn new(package: Package, path: Vec<String>, kind: ModuleKind) -> Self {
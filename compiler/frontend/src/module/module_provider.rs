@@ -74,6 +74,7 @@ impl ModuleProvider for FileSystemModuleProvider {
     }
 }
 
+#[derive(Default)]
 pub struct OverlayModuleProvider<O: ModuleProvider, F: ModuleProvider> {
     pub overlay: O,
     pub fallback: F,
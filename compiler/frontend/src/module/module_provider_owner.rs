@@ -1,4 +1,6 @@
-use super::{InMemoryModuleProvider, Module, ModuleProvider};
+use super::{
+    InMemoryModuleProvider, Module, ModuleProvider, VirtualModuleLayer, VirtualModuleProvider,
+};
 
 pub trait ModuleProviderOwner {
     #[must_use]
@@ -8,6 +10,8 @@ pub trait ModuleProviderOwner {
 pub trait MutableModuleProviderOwner: ModuleProviderOwner {
     #[must_use]
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider;
+    #[must_use]
+    fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider;
     fn invalidate_module(&mut self, module: &Module);
 
     fn did_open_module(&mut self, module: &Module, content: Vec<u8>) {
@@ -29,22 +33,47 @@ pub trait MutableModuleProviderOwner: ModuleProviderOwner {
             .cloned()
             .collect()
     }
+
+    /// Adds or replaces a module that only exists in memory. Modules opened in
+    /// an editor still take precedence.
+    fn set_virtual_module(&mut self, layer: VirtualModuleLayer, module: &Module, content: Vec<u8>) {
+        self.get_virtual_module_provider()
+            .layer_mut(layer)
+            .add(module, content);
+        self.invalidate_module(module);
+    }
+    fn remove_virtual_module(&mut self, layer: VirtualModuleLayer, module: &Module) {
+        self.get_virtual_module_provider()
+            .layer_mut(layer)
+            .remove(module);
+        self.invalidate_module(module);
+    }
+    fn clear_virtual_modules(&mut self, layer: VirtualModuleLayer) {
+        for module in self.get_virtual_module_provider().remove_layer(layer) {
+            self.invalidate_module(&module);
+        }
+    }
 }
 
 #[cfg(test)]
-mod test {
+pub mod test {
     use std::path::PathBuf;
 
     use super::*;
     use crate::{
         ast::AstDbStorage,
         ast_to_hir::AstToHirStorage,
+        cancellation::CancellationTokenOwner,
         cst::{CstDbStorage, CstKind},
         cst_to_ast::CstToAstStorage,
         hir::HirDbStorage,
         hir_to_mir::HirToMirStorage,
         mir_optimize::OptimizeMirStorage,
-        module::{GetModuleContentQuery, ModuleDb, ModuleDbStorage, ModuleKind, Package},
+        module::{
+            GetModuleContentQuery, ModuleDb, ModuleDbStorage, ModuleKind, OverlayModuleProvider,
+            Package,
+        },
+        persistent_cache::PersistentCacheOwner,
         position::PositionConversionStorage,
         rcst_to_cst::RcstToCstStorage,
         string_to_rcst::{StringToRcst, StringToRcstStorage},
@@ -66,7 +95,7 @@ mod test {
     #[derive(Default)]
     pub struct Database {
         storage: salsa::Storage<Self>,
-        module_provider: InMemoryModuleProvider,
+        module_provider: OverlayModuleProvider<InMemoryModuleProvider, VirtualModuleProvider>,
     }
     impl salsa::Database for Database {}
     impl ModuleProviderOwner for Database {
@@ -74,9 +103,14 @@ mod test {
             &self.module_provider
         }
     }
+    impl CancellationTokenOwner for Database {}
+    impl PersistentCacheOwner for Database {}
     impl MutableModuleProviderOwner for Database {
         fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
-            &mut self.module_provider
+            &mut self.module_provider.overlay
+        }
+        fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider {
+            &mut self.module_provider.fallback
         }
         fn invalidate_module(&mut self, module: &Module) {
             GetModuleContentQuery.in_db_mut(self).invalidate(module);
@@ -129,4 +163,35 @@ mod test {
 
         db.did_close_module(&module);
     }
+
+    #[test]
+    fn virtual_modules_work() {
+        let mut db = Database::default();
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["foo".to_string()],
+            ModuleKind::Code,
+        );
+        let content = |db: &Database| {
+            db.get_module_content_as_string(module.clone())
+                .map(|content| content.as_ref().clone())
+        };
+
+        db.set_virtual_module(VirtualModuleLayer::Generated, &module, b"1".to_vec());
+        assert_eq!(content(&db).as_deref(), Some("1"));
+
+        db.set_virtual_module(VirtualModuleLayer::Repl, &module, b"2".to_vec());
+        assert_eq!(content(&db).as_deref(), Some("2"));
+
+        db.did_open_module(&module, b"3".to_vec());
+        assert_eq!(content(&db).as_deref(), Some("3"));
+        db.did_close_module(&module);
+        assert_eq!(content(&db).as_deref(), Some("2"));
+
+        db.clear_virtual_modules(VirtualModuleLayer::Repl);
+        assert_eq!(content(&db).as_deref(), Some("1"));
+
+        db.remove_virtual_module(VirtualModuleLayer::Generated, &module);
+        assert_eq!(content(&db), None);
+    }
 }
@@ -0,0 +1,67 @@
+use super::{InMemoryModuleProvider, Module, ModuleProvider};
+use std::sync::Arc;
+
+/// A group of virtual modules that is usually replaced or removed as a whole.
+///
+/// If multiple layers contain the same module, the one declared first wins.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum VirtualModuleLayer {
+    /// Snippets entered in a REPL.
+    Repl,
+    /// Code synthesized by tests or tools like the fuzzer.
+    Synthesized,
+    /// Modules generated from other modules, e.g., bindings.
+    Generated,
+}
+
+/// Modules that only exist in memory and that are not opened in an editor.
+///
+/// Databases put this between the modules opened in an editor and their
+/// underlying module provider. Use the methods of
+/// [`MutableModuleProviderOwner`](super::MutableModuleProviderOwner) to modify
+/// it so that salsa's caches get invalidated.
+#[derive(Default)]
+pub struct VirtualModuleProvider {
+    /// Sorted by layer.
+    layers: Vec<(VirtualModuleLayer, InMemoryModuleProvider)>,
+}
+impl VirtualModuleProvider {
+    pub fn layer_mut(&mut self, layer: VirtualModuleLayer) -> &mut InMemoryModuleProvider {
+        let index = match self
+            .layers
+            .binary_search_by_key(&layer, |(layer, _)| *layer)
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.layers
+                    .insert(index, (layer, InMemoryModuleProvider::default()));
+                index
+            }
+        };
+        &mut self.layers[index].1
+    }
+    /// Removes all modules of the layer and returns them.
+    pub fn remove_layer(&mut self, layer: VirtualModuleLayer) -> Vec<Module> {
+        let Ok(index) = self
+            .layers
+            .binary_search_by_key(&layer, |(layer, _)| *layer)
+        else {
+            return vec![];
+        };
+        let (_, modules) = self.layers.remove(index);
+        modules.get_all_modules().cloned().collect()
+    }
+
+    pub fn get_all_modules(&self) -> impl Iterator<Item = &Module> {
+        self.layers
+            .iter()
+            .flat_map(|(_, modules)| modules.get_all_modules())
+    }
+}
+impl ModuleProvider for VirtualModuleProvider {
+    fn get_content(&self, module: &Module) -> Option<Arc<Vec<u8>>> {
+        self.layers
+            .iter()
+            .find_map(|(_, modules)| modules.get_content(module))
+    }
+}
@@ -0,0 +1,109 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Trades compilation speed for the speed and size of the compiled program.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum OptimizationLevel {
+    /// Only apply transformations that compiling requires, such as inlining
+    /// `use`s. Compiles fastest.
+    #[value(name = "0")]
+    O0,
+
+    /// Apply cheap optimizations.
+    #[value(name = "1")]
+    O1,
+
+    /// Apply all optimizations. The resulting program runs fastest.
+    #[default]
+    #[value(name = "2")]
+    O2,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)]
+pub struct OptimizationConfig {
+    pub level: OptimizationLevel,
+    pub inlining: InliningMode,
+    pub common_subtree_elimination: bool,
+    pub constant_lifting: bool,
+    /// Removing unused and constant parameters from functions and creating
+    /// copies of functions for different constant arguments.
+    pub specialization: bool,
+    /// Merging identical functions across the whole MIR.
+    pub function_deduplication: bool,
+    /// How many optimizations may be applied to each module (see
+    /// [`fuel`](crate::mir_optimize::fuel)). Unlimited if [`None`].
+    pub fuel: Option<usize>,
+}
+impl OptimizationConfig {
+    #[must_use]
+    pub const fn for_level(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::O0 => Self {
+                level,
+                inlining: InliningMode::OnlyRequired,
+                common_subtree_elimination: false,
+                constant_lifting: false,
+                specialization: false,
+                function_deduplication: false,
+                fuel: None,
+            },
+            OptimizationLevel::O1 => Self {
+                level,
+                inlining: InliningMode::Tiny,
+                common_subtree_elimination: true,
+                constant_lifting: true,
+                specialization: false,
+                function_deduplication: false,
+                fuel: None,
+            },
+            OptimizationLevel::O2 => Self {
+                level,
+                inlining: InliningMode::All,
+                common_subtree_elimination: true,
+                constant_lifting: true,
+                specialization: true,
+                function_deduplication: true,
+                fuel: None,
+            },
+        }
+    }
+
+    #[must_use]
+    pub const fn with_fuel(mut self, fuel: Option<usize>) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Compiles fast, e.g., for debug builds.
+    #[must_use]
+    pub const fn debug() -> Self {
+        Self::for_level(OptimizationLevel::O0)
+    }
+    /// Runs fast, e.g., for release builds.
+    #[must_use]
+    pub const fn release() -> Self {
+        Self::for_level(OptimizationLevel::O2)
+    }
+}
+impl Default for OptimizationConfig {
+    fn default() -> Self {
+        Self::for_level(OptimizationLevel::default())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InliningMode {
+    /// Only inline functions containing `use`, which is necessary to resolve
+    /// imports.
+    OnlyRequired,
+
+    /// Also inline tiny functions and `needs`.
+    Tiny,
+
+    /// Also inline calls where all arguments are constants.
+    All,
+}
@@ -0,0 +1,291 @@
+//! A persistent cache of the frontend's query results.
+//!
+//! Each invocation of the CLI starts with an empty salsa database and would
+//! compile every module again, including all of Core. Instead, the RCST, CST,
+//! AST, and HIR of modules are stored in the `.candy-cache` directory and only
+//! computed again if something they depend on changed.
+//!
+//! Each entry is a separate file named after the SHA-256 hash of everything
+//! the result depends on (see [`CacheKey`]). Entries are never modified, so
+//! invocations compiling different packages share the cache without
+//! overwriting each other's entries.
+//!
+//! The cache is only valid for the compiler binary that wrote it, since a
+//! different compiler might produce different results for the same input.
+//! Opening the cache with a different compiler removes all entries. Until
+//! then, entries of outdated sources stay around – the directory can be
+//! deleted at any time.
+
+use crate::module::Module;
+use bincode::{DefaultOptions, Options};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+};
+use tracing::warn;
+
+pub trait PersistentCacheOwner {
+    /// Databases that always compute query results return `None`.
+    #[must_use]
+    fn get_persistent_cache(&self) -> Option<&PersistentCache> {
+        None
+    }
+}
+
+/// Returns the cached result for `key` or computes it using `compute`.
+///
+/// Only results that don't depend on anything else than what's in the key may
+/// be cached.
+#[must_use]
+pub fn get_or_compute<T: Serialize + DeserializeOwned>(
+    cache: Option<&PersistentCache>,
+    module: &Module,
+    key: impl FnOnce() -> CacheKey,
+    compute: impl FnOnce() -> T,
+) -> T {
+    match cache {
+        Some(cache) => cache.get_or_compute(module, key(), compute),
+        None => compute(),
+    }
+}
+
+#[derive(Debug)]
+pub struct PersistentCache {
+    directory: PathBuf,
+    compiler_fingerprint: String,
+}
+impl PersistentCache {
+    #[must_use]
+    pub fn directory(packages_path: &Path) -> PathBuf {
+        packages_path.join(".candy-cache")
+    }
+
+    /// Returns `None` if we can't tell whether the entries were written by this
+    /// compiler or the directory can't be used.
+    #[must_use]
+    pub fn open(directory: PathBuf) -> Option<Self> {
+        let compiler_fingerprint = compiler_fingerprint()?;
+        let fingerprint_file = directory.join("compiler");
+        let is_from_this_compiler = fs::read_to_string(&fingerprint_file)
+            .is_ok_and(|fingerprint| fingerprint == compiler_fingerprint);
+        if !is_from_this_compiler
+            && let Err(error) = reset(&directory, &fingerprint_file, &compiler_fingerprint)
+        {
+            warn!(
+                "Couldn't reset the persistent cache in `{}`: {error}",
+                directory.display(),
+            );
+            return None;
+        }
+
+        Some(Self {
+            directory,
+            compiler_fingerprint,
+        })
+    }
+
+    /// Returns the cached result for `key` or computes and stores it.
+    ///
+    /// IDs in the result refer to `module`, which is therefore only stored
+    /// once per entry.
+    #[must_use]
+    pub fn get_or_compute<T: Serialize + DeserializeOwned>(
+        &self,
+        module: &Module,
+        key: CacheKey,
+        compute: impl FnOnce() -> T,
+    ) -> T {
+        let file = key.file(&self.directory, &self.compiler_fingerprint);
+        if let Some(value) = with_entry_module(module, || read_entry(&file)) {
+            return value;
+        }
+
+        let value = compute();
+        if let Err(error) = with_entry_module(module, || write_entry(&file, &value)) {
+            warn!("Couldn't write `{}`: {error}", file.display());
+        }
+        value
+    }
+}
+
+/// Everything a cached result depends on besides the compiler.
+#[must_use]
+pub struct CacheKey {
+    query: &'static str,
+    hasher: Sha256,
+}
+impl CacheKey {
+    pub fn new(query: &'static str) -> Self {
+        Self {
+            query,
+            hasher: Sha256::new_with_prefix(query),
+        }
+    }
+
+    pub fn with_bytes(mut self, bytes: &[u8]) -> Self {
+        // The length prefix ensures that different parts can't be combined to
+        // the same key.
+        self.hasher.update((bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+        self
+    }
+    pub fn with_module(self, module: &Module) -> Self {
+        self.with_bytes(format!("{module:?}").as_bytes())
+    }
+    /// Adds the module and its content, or a marker if it doesn't exist.
+    pub fn with_module_content(self, module: &Module, content: Option<&[u8]>) -> Self {
+        let key = self.with_module(module);
+        match content {
+            Some(content) => key.with_bytes(b"exists").with_bytes(content),
+            None => key.with_bytes(b"missing"),
+        }
+    }
+
+    fn file(self, directory: &Path, compiler_fingerprint: &str) -> PathBuf {
+        let hash = self.hasher.chain_update(compiler_fingerprint).finalize();
+        directory.join(self.query).join(format!("{hash:x}.bin"))
+    }
+}
+
+fn read_entry<T: DeserializeOwned>(file: &Path) -> Option<T> {
+    let content = match fs::read(file) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            warn!("Couldn't read `{}`: {error}", file.display());
+            return None;
+        }
+    };
+
+    let mut deserializer = bincode::Deserializer::from_slice(&content, options());
+    // Results can be nested deeper than the stack allows, so serde_stacker
+    // grows the stack on demand.
+    T::deserialize(serde_stacker::Deserializer::new(&mut deserializer))
+        .map_err(|error| warn!("Couldn't deserialize `{}`: {error}", file.display()))
+        .ok()
+}
+fn write_entry<T: Serialize>(file: &Path, value: &T) -> io::Result<()> {
+    fs::create_dir_all(file.parent().unwrap())?;
+
+    // Other invocations might read the entry at the same time, so we write it
+    // to a temporary file first and then move it into place.
+    let temporary_file = file.with_extension(format!("{}.tmp", process::id()));
+    let mut writer = BufWriter::new(fs::File::create(&temporary_file)?);
+    let mut serializer = bincode::Serializer::new(&mut writer, options());
+    value
+        .serialize(serde_stacker::Serializer::new(&mut serializer))
+        .map_err(io::Error::other)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(temporary_file, file)
+}
+fn options() -> impl Options {
+    DefaultOptions::new()
+}
+
+fn reset(directory: &Path, fingerprint_file: &Path, compiler_fingerprint: &str) -> io::Result<()> {
+    match fs::remove_dir_all(directory) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    fs::create_dir_all(directory)?;
+    fs::write(fingerprint_file, compiler_fingerprint)
+}
+
+/// Changes whenever the compiler is rebuilt.
+fn compiler_fingerprint() -> Option<String> {
+    #[derive(Serialize)]
+    struct Fingerprint {
+        path: PathBuf,
+        length: u64,
+        modified: std::time::SystemTime,
+    }
+
+    let path = env::current_exe().ok()?;
+    let metadata = path.metadata().ok()?;
+    let fingerprint = Fingerprint {
+        length: metadata.len(),
+        modified: metadata.modified().ok()?,
+        path,
+    };
+    let fingerprint = bincode::serialize(&fingerprint).ok()?;
+    Some(format!("{:x}", Sha256::digest(fingerprint)))
+}
+
+thread_local! {
+    /// The module whose query result is currently being (de)serialized.
+    static ENTRY_MODULE: RefCell<Option<Module>> = const { RefCell::new(None) };
+}
+fn with_entry_module<R>(module: &Module, f: impl FnOnce() -> R) -> R {
+    let previous = ENTRY_MODULE.replace(Some(module.clone()));
+    let result = f();
+    ENTRY_MODULE.set(previous);
+    result
+}
+#[must_use]
+pub(crate) fn is_entry_module(module: &Module) -> bool {
+    ENTRY_MODULE.with_borrow(|entry_module| entry_module.as_ref() == Some(module))
+}
+#[must_use]
+pub(crate) fn entry_module() -> Option<Module> {
+    ENTRY_MODULE.with_borrow(Clone::clone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{self, Ast, AstKind, AstString, Identifier},
+        module::{ModuleKind, Package},
+    };
+
+    #[test]
+    fn stores_the_entry_module_only_once() {
+        let module = Module::new(
+            Package::User("/non/existent".into()),
+            vec!["foo".to_string()],
+            ModuleKind::Code,
+        );
+        let other_module = Module::new(
+            Package::User("/non/existent".into()),
+            vec!["bar".to_string()],
+            ModuleKind::Code,
+        );
+        let ast = |module: &Module| Ast {
+            id: ast::Id::new(module.clone(), 0),
+            kind: AstKind::Identifier(Identifier(AstString {
+                id: ast::Id::new(module.clone(), 1),
+                value: "baz".to_string(),
+            })),
+        };
+        let asts = vec![ast(&module), ast(&other_module)];
+
+        let directory = env::temp_dir().join(format!("candy-cache-test-{}", process::id()));
+        let file = directory.join("entry.bin");
+        with_entry_module(&module, || write_entry(&file, &asts)).unwrap();
+        let deserialized: Vec<Ast> = with_entry_module(&module, || read_entry(&file)).unwrap();
+        // Without the entry module, the stored IDs can't be resolved.
+        assert!(read_entry::<Vec<Ast>>(&file).is_none());
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(deserialized, asts);
+    }
+
+    #[test]
+    fn keys_depend_on_how_the_input_is_split() {
+        let file = |key: CacheKey| key.file(Path::new("/"), "compiler");
+        assert_ne!(
+            file(CacheKey::new("query").with_bytes(b"ab").with_bytes(b"c")),
+            file(CacheKey::new("query").with_bytes(b"a").with_bytes(b"bc")),
+        );
+        assert_eq!(
+            file(CacheKey::new("query").with_bytes(b"abc")),
+            file(CacheKey::new("query").with_bytes(b"abc")),
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::module::{Module, ModuleDb};
 use derive_more::{Deref, DerefMut, From};
 use extension_trait::extension_trait;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     ops::Range,
@@ -11,7 +12,20 @@ use unicode_segmentation::UnicodeSegmentation;
 /// The offset of a character in a string as the number of bytes preceding it in
 /// UTF-8 encoding.
 #[derive(
-    Clone, Copy, Debug, Default, Deref, DerefMut, Eq, From, Hash, Ord, PartialEq, PartialOrd,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deref,
+    DerefMut,
+    Deserialize,
+    Eq,
+    From,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
 )]
 #[from(forward)]
 pub struct Offset(pub usize);
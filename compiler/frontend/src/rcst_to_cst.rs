@@ -7,6 +7,7 @@ use crate::{
     cst::{CstData, Id},
     id::IdGenerator,
     module::Module,
+    persistent_cache::{self, CacheKey},
     position::Offset,
 };
 use extension_trait::extension_trait;
@@ -19,9 +20,16 @@ pub trait RcstToCst: StringToRcst {
 
 pub type CstResult = Result<Arc<Vec<Cst>>, ModuleError>;
 
+#[allow(clippy::needless_pass_by_value)]
 fn cst(db: &dyn RcstToCst, module: Module) -> Result<Arc<Vec<Cst>>, ModuleError> {
-    let rcsts = db.rcst(module)?;
-    Ok(Arc::new(rcsts.to_csts()))
+    let content = db.get_module_content(module.clone());
+    let csts = persistent_cache::get_or_compute(
+        db.get_persistent_cache(),
+        &module,
+        || CacheKey::new("cst").with_module_content(&module, content.as_deref().map(Vec::as_slice)),
+        || db.rcst(module.clone()).map(|rcsts| rcsts.to_csts()),
+    )?;
+    Ok(Arc::new(csts))
 }
 
 #[derive(Default)]
@@ -114,6 +122,10 @@ impl Rcst {
                 *state.offset += 1;
                 CstKind::Octothorpe
             }
+            CstKind::RawTextPrefix => {
+                *state.offset += 1;
+                CstKind::RawTextPrefix
+            }
             CstKind::Whitespace(whitespace) => {
                 *state.offset += whitespace.len();
                 CstKind::Whitespace(whitespace.clone())
@@ -162,9 +174,13 @@ impl Rcst {
                 }
             }
             CstKind::OpeningText {
+                raw_prefix,
                 opening_single_quotes,
                 opening_double_quote,
             } => CstKind::OpeningText {
+                raw_prefix: raw_prefix
+                    .as_ref()
+                    .map(|raw_prefix| Box::new(raw_prefix.to_cst(state))),
                 opening_single_quotes: opening_single_quotes.to_csts_helper(state),
                 opening_double_quote: Box::new(opening_double_quote.to_cst(state)),
             },
@@ -208,6 +224,11 @@ impl Rcst {
                 receiver: Box::new(receiver.to_cst(state)),
                 arguments: arguments.to_csts_helper(state),
             },
+            CstKind::NamedArgument { name, colon, value } => CstKind::NamedArgument {
+                name: Box::new(name.to_cst(state)),
+                colon: Box::new(colon.to_cst(state)),
+                value: Box::new(value.to_cst(state)),
+            },
             CstKind::List {
                 opening_parenthesis,
                 items,
@@ -4,8 +4,8 @@ use super::{
     int::int,
     list::list,
     literal::{
-        arrow, bar, closing_bracket, closing_curly_brace, closing_parenthesis, colon_equals_sign,
-        dot, equals_sign, percent,
+        arrow, bar, closing_bracket, closing_curly_brace, closing_parenthesis, colon,
+        colon_equals_sign, dot, equals_sign, percent,
     },
     struct_::struct_,
     text::text,
@@ -163,16 +163,14 @@ fn expression_suffix_call<'a>(
         let last = expressions.pop().unwrap();
         expressions.push(last.wrap_in_whitespace(whitespace));
 
-        let parsed_expression = expression(
-            i,
-            indentation,
-            ExpressionParsingOptions {
-                allow_assignment: false,
-                allow_call: has_multiline_whitespace,
-                allow_bar: has_multiline_whitespace,
-                allow_function: true,
-            },
-        );
+        let options = ExpressionParsingOptions {
+            allow_assignment: false,
+            allow_call: has_multiline_whitespace,
+            allow_bar: has_multiline_whitespace,
+            allow_function: true,
+        };
+        let parsed_expression =
+            named_argument(i, indentation, options).or_else(|| expression(i, indentation, options));
         let (i, expr) = if let Some(it) = parsed_expression {
             it
         } else {
@@ -212,6 +210,40 @@ fn expression_suffix_call<'a>(
     ))
 }
 
+/// A named argument in a call, e.g., `count: 3` in `foo bar count: 3`.
+#[instrument(level = "trace")]
+fn named_argument(
+    input: &str,
+    indentation: usize,
+    options: ExpressionParsingOptions,
+) -> Option<(&str, Rcst)> {
+    let (input, name) = identifier(input)?;
+    if colon_equals_sign(input).is_some() {
+        return None;
+    }
+    let (input, colon) = colon(input)?;
+    let (input, whitespace) = whitespaces_and_newlines(input, indentation + 1, true);
+    let colon = colon.wrap_in_whitespace(whitespace);
+
+    let (input, value) = expression(input, indentation, options).unwrap_or_else(|| {
+        let error = CstKind::Error {
+            unparsable_input: String::new(),
+            error: CstError::NamedArgumentMissesValue,
+        };
+        (input, error.into())
+    });
+
+    Some((
+        input,
+        CstKind::NamedArgument {
+            name: Box::new(name),
+            colon: Box::new(colon),
+            value: Box::new(value),
+        }
+        .into(),
+    ))
+}
+
 #[instrument(level = "trace")]
 fn expression_suffix_bar<'a>(
     input: &'a str,
@@ -1480,5 +1512,102 @@ mod test {
             Identifier "bar"
         "###
         );
+        assert_rich_ir_snapshot!(
+            expression(
+                "foo bar count: 3",
+                0,
+                ExpressionParsingOptions {
+                    allow_assignment: true,
+                    allow_call: true,
+                    allow_bar: true,
+                    allow_function: true
+                }
+            ),
+            @r###"
+        Remaining input: ""
+        Parsed: Call:
+          receiver: TrailingWhitespace:
+            child: Identifier "foo"
+            whitespace:
+              Whitespace " "
+          arguments:
+            TrailingWhitespace:
+              child: Identifier "bar"
+              whitespace:
+                Whitespace " "
+            NamedArgument:
+              name: Identifier "count"
+              colon: TrailingWhitespace:
+                child: Colon
+                whitespace:
+                  Whitespace " "
+              value: Int:
+                radix_prefix: None
+                value: 3
+                string: "3"
+        "###
+        );
+        assert_rich_ir_snapshot!(
+            expression(
+                "foo count:",
+                0,
+                ExpressionParsingOptions {
+                    allow_assignment: true,
+                    allow_call: true,
+                    allow_bar: true,
+                    allow_function: true
+                }
+            ),
+            @r###"
+        Remaining input: ""
+        Parsed: Call:
+          receiver: TrailingWhitespace:
+            child: Identifier "foo"
+            whitespace:
+              Whitespace " "
+          arguments:
+            NamedArgument:
+              name: Identifier "count"
+              colon: Colon
+              value: Error:
+                unparsable_input: ""
+                error: NamedArgumentMissesValue
+        "###
+        );
+        assert_rich_ir_snapshot!(
+            expression(
+                "foo count := 3",
+                0,
+                ExpressionParsingOptions {
+                    allow_assignment: true,
+                    allow_call: true,
+                    allow_bar: true,
+                    allow_function: true
+                }
+            ),
+            @r###"
+        Remaining input: ""
+        Parsed: Assignment:
+          left: TrailingWhitespace:
+            child: Call:
+              receiver: TrailingWhitespace:
+                child: Identifier "foo"
+                whitespace:
+                  Whitespace " "
+              arguments:
+                Identifier "count"
+            whitespace:
+              Whitespace " "
+          assignment_sign: TrailingWhitespace:
+            child: ColonEqualsSign
+            whitespace:
+              Whitespace " "
+          body:
+            Int:
+              radix_prefix: None
+              value: 3
+              string: "3"
+        "###
+        );
     }
 }
@@ -3,10 +3,15 @@ use crate::{
     cst::{CstError, CstKind, IntRadix},
     rcst::Rcst,
 };
+use itertools::Itertools;
 use num_bigint::BigUint;
 use num_traits::Num;
 use tracing::instrument;
 
+/// Parses an int like `123`, `1_000_000`, `0b1010`, or `0xFF`.
+///
+/// Underscores can separate digits to make long numbers more readable. The
+/// CST keeps the original digits including underscores for the formatter.
 #[instrument(level = "trace")]
 pub fn int(input: &str) -> Option<(&str, Rcst)> {
     let (input, string) = word(input)?;
@@ -15,11 +20,10 @@ pub fn int(input: &str) -> Option<(&str, Rcst)> {
     }
 
     let rcst = if (string.starts_with("0b") || string.starts_with("0B"))
-        && string.len() >= 3
-        && string.chars().skip(2).all(|c| c == '0' || c == '1')
+        && let Some(digits) = digits_without_separators(&string[2..], |c| c == '0' || c == '1')
     {
         // Binary
-        let value = BigUint::from_str_radix(&string[2..], 2).expect("Couldn't parse binary int.");
+        let value = BigUint::from_str_radix(&digits, 2).expect("Couldn't parse binary int.");
         CstKind::Int {
             radix_prefix: Some((IntRadix::Binary, string[..2].to_string())),
             value,
@@ -27,21 +31,19 @@ pub fn int(input: &str) -> Option<(&str, Rcst)> {
         }
         .into()
     } else if (string.starts_with("0x") || string.starts_with("0X"))
-        && string.len() >= 3
-        && string.chars().skip(2).all(|c| c.is_ascii_hexdigit())
+        && let Some(digits) = digits_without_separators(&string[2..], |c| c.is_ascii_hexdigit())
     {
         // Hexadecimal
-        let value =
-            BigUint::from_str_radix(&string[2..], 16).expect("Couldn't parse hexadecimal int.");
+        let value = BigUint::from_str_radix(&digits, 16).expect("Couldn't parse hexadecimal int.");
         CstKind::Int {
             radix_prefix: Some((IntRadix::Hexadecimal, string[..2].to_string())),
             value,
             string: string[2..].to_string(),
         }
         .into()
-    } else if string.chars().all(|c| c.is_ascii_digit()) {
+    } else if let Some(digits) = digits_without_separators(&string, |c| c.is_ascii_digit()) {
         // Decimal
-        let value = str::parse(&string).expect("Couldn't parse decimal int.");
+        let value = str::parse(&digits).expect("Couldn't parse decimal int.");
         CstKind::Int {
             radix_prefix: None,
             value,
@@ -58,6 +60,18 @@ pub fn int(input: &str) -> Option<(&str, Rcst)> {
     Some((input, rcst))
 }
 
+/// Returns the digits without the underscores separating them.
+///
+/// Returns `None` if there are no digits, if the string contains other
+/// characters, or if an underscore is not surrounded by digits.
+fn digits_without_separators(string: &str, is_digit: impl Fn(char) -> bool) -> Option<String> {
+    let groups = string.split('_').collect_vec();
+    groups
+        .iter()
+        .all(|group| !group.is_empty() && group.chars().all(&is_digit))
+        .then(|| groups.concat())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -151,5 +165,50 @@ mod test {
           unparsable_input: "3D"
           error: IntContainsNonDigits
         "###);
+
+        // Underscores
+        assert_rich_ir_snapshot!(int("1_000_000"), @r###"
+        Remaining input: ""
+        Parsed: Int:
+          radix_prefix: None
+          value: 1000000
+          string: "1_000_000"
+        "###);
+        assert_rich_ir_snapshot!(int("0b1010_0101"), @r###"
+        Remaining input: ""
+        Parsed: Int:
+          radix_prefix:
+            radix: Binary
+            prefix: "0b"
+          value: 165
+          string: "1010_0101"
+        "###);
+        assert_rich_ir_snapshot!(int("0xFF_ff"), @r###"
+        Remaining input: ""
+        Parsed: Int:
+          radix_prefix:
+            radix: Hexadecimal
+            prefix: "0x"
+          value: 65535
+          string: "FF_ff"
+        "###);
+        assert_rich_ir_snapshot!(int("1__000"), @r###"
+        Remaining input: ""
+        Parsed: Error:
+          unparsable_input: "1__000"
+          error: IntContainsNonDigits
+        "###);
+        assert_rich_ir_snapshot!(int("1_"), @r###"
+        Remaining input: ""
+        Parsed: Error:
+          unparsable_input: "1_"
+          error: IntContainsNonDigits
+        "###);
+        assert_rich_ir_snapshot!(int("0x_12"), @r###"
+        Remaining input: ""
+        Parsed: Error:
+          unparsable_input: "0x_12"
+          error: IntContainsNonDigits
+        "###);
     }
 }
@@ -32,6 +32,7 @@ define_literal!(single_quote, "'", CstKind::SingleQuote);
 define_literal!(double_quote, "\"", CstKind::DoubleQuote);
 define_literal!(percent, "%", CstKind::Percent);
 define_literal!(octothorpe, "#", CstKind::Octothorpe);
+define_literal!(raw_text_prefix, "r", CstKind::RawTextPrefix);
 
 #[instrument(level = "trace")]
 pub fn newline(input: &str) -> Option<(&str, Rcst)> {
@@ -21,19 +21,23 @@ mod word;
 use crate::{
     cst::{CstError, CstKind},
     module::{Module, ModuleDb, ModuleKind, Package},
+    persistent_cache::{self, CacheKey, PersistentCacheOwner},
     rcst::Rcst,
     rich_ir::{RichIrBuilder, ToRichIr, TokenType},
 };
 use enumset::EnumSet;
+use serde::{Deserialize, Serialize};
 use std::{str, sync::Arc};
+use strum_macros::AsRefStr;
 
 #[salsa::query_group(StringToRcstStorage)]
-pub trait StringToRcst: ModuleDb {
+pub trait StringToRcst: ModuleDb + PersistentCacheOwner {
     fn rcst(&self, module: Module) -> RcstResult;
 }
 
 pub type RcstResult = Result<Arc<Vec<Rcst>>, ModuleError>;
 
+#[allow(clippy::needless_pass_by_value)]
 fn rcst(db: &dyn StringToRcst, module: Module) -> RcstResult {
     if module.kind() != ModuleKind::Code {
         return Err(ModuleError::IsNotCandy);
@@ -43,43 +47,73 @@ fn rcst(db: &dyn StringToRcst, module: Module) -> RcstResult {
         return Err(ModuleError::IsToolingModule);
     }
     let source = db
-        .get_module_content(module)
+        .get_module_content(module.clone())
         .ok_or(ModuleError::DoesNotExist)?;
     let Ok(source) = str::from_utf8(source.as_slice()) else {
         return Err(ModuleError::InvalidUtf8);
     };
-    Ok(Arc::new(parse_rcst(source)))
+    // The RCST doesn't contain the module, so modules with the same source
+    // share the entry.
+    let rcsts = persistent_cache::get_or_compute(
+        db.get_persistent_cache(),
+        &module,
+        || CacheKey::new("rcst").with_bytes(source.as_bytes()),
+        || parse_rcst(source),
+    );
+    Ok(Arc::new(rcsts))
 }
 #[must_use]
 pub fn parse_rcst(source: &str) -> Vec<Rcst> {
     let (mut rest, mut rcsts) = body::body(source, 0);
-    if !rest.is_empty() {
-        let trailing_newline = if rest.ends_with("\r\n") {
-            let (_, newline) = literal::newline(&rest[rest.len() - 2..]).unwrap();
-            rest = &rest[..rest.len() - 2];
-            Some(newline)
-        } else if rest.ends_with('\n') {
-            let (_, newline) = literal::newline(&rest[rest.len() - 1..]).unwrap();
-            rest = &rest[..rest.len() - 1];
-            Some(newline)
-        } else {
-            None
-        };
-        rcsts.push(
-            CstKind::Error {
-                unparsable_input: rest.to_string(),
-                error: CstError::UnparsedRest,
-            }
-            .into(),
-        );
-        if let Some(trailing_newline) = trailing_newline {
-            rcsts.push(trailing_newline);
+    while !rest.is_empty() {
+        // We resynchronize at the next top-level statement so that an error
+        // only affects the statement it occurs in instead of the remaining
+        // file.
+        let (unparsable_input, newline, new_rest) = split_at_next_statement(rest);
+        if !unparsable_input.is_empty() {
+            rcsts.push(
+                CstKind::Error {
+                    unparsable_input: unparsable_input.to_string(),
+                    error: CstError::UnparsedRest,
+                }
+                .into(),
+            );
         }
+        rcsts.extend(newline);
+
+        let (new_rest, mut new_rcsts) = body::body(new_rest, 0);
+        rest = new_rest;
+        rcsts.append(&mut new_rcsts);
     }
     rcsts
 }
+/// Splits the input at the next line that isn't indented, i.e., that starts a
+/// new top-level statement.
+///
+/// Returns the input before that line (without trailing blank lines), the
+/// newline after it, and the rest.
+fn split_at_next_statement(input: &str) -> (&str, Option<Rcst>, &str) {
+    let statement_start = input
+        .match_indices('\n')
+        .map(|(index, _)| index + 1)
+        .find(|index| input[*index..].starts_with(|c: char| !c.is_whitespace()))
+        .unwrap_or(input.len());
+    let content_end = input[..statement_start].trim_end().len();
+    let Some(newline_index) = input[content_end..].find('\n') else {
+        return (input, None, "");
+    };
+    let newline_index = content_end + newline_index;
+    let newline_start = if input[..newline_index].ends_with('\r') {
+        newline_index - 1
+    } else {
+        newline_index
+    };
+    let (rest, newline) = literal::newline(&input[newline_start..]).unwrap();
+    (&input[..newline_start], Some(newline), rest)
+}
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+#[derive(AsRefStr, PartialEq, Eq, Debug, Clone, Copy, Hash, Deserialize, Serialize)]
+#[strum(serialize_all = "kebab-case")]
 pub enum ModuleError {
     DoesNotExist,
     InvalidUtf8,
@@ -97,3 +131,39 @@ impl ToRichIr for ModuleError {
         builder.push(text, TokenType::Comment, EnumSet::empty());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn errors(source: &str) -> Vec<(String, CstError)> {
+        parse_rcst(source)
+            .into_iter()
+            .filter_map(|rcst| match rcst.kind {
+                CstKind::Error {
+                    unparsable_input,
+                    error,
+                } => Some((unparsable_input, error)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resynchronizes_at_top_level_statements() {
+        assert_eq!(
+            errors("= foo\nbar\n.baz\nqux"),
+            vec![
+                ("= foo".to_string(), CstError::UnparsedRest),
+                (".baz".to_string(), CstError::UnparsedRest),
+            ],
+        );
+    }
+    #[test]
+    fn keeps_indented_lines_in_the_erroneous_statement() {
+        assert_eq!(
+            errors("= foo\n  bar\n\nbaz\n"),
+            vec![("= foo\n  bar".to_string(), CstError::UnparsedRest)],
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use super::{
     expression::{expression, ExpressionParsingOptions},
-    literal::{closing_curly_brace, double_quote, newline, opening_curly_brace, single_quote},
+    literal::{
+        closing_curly_brace, double_quote, newline, opening_curly_brace, raw_text_prefix,
+        single_quote,
+    },
     utils::parse_multiple,
     whitespace::whitespaces_and_newlines,
 };
@@ -11,9 +14,23 @@ use crate::{
 use itertools::Itertools;
 use tracing::instrument;
 
+/// Parses a text like `"Hello, {name}!"`.
+///
+/// Texts prefixed with `r` are raw: They don't support interpolations, so curly
+/// braces don't need any special treatment.
+///
+/// Lines of multi-line texts are indented by one level more than the text
+/// itself. That indentation isn't part of the text, but any indentation going
+/// beyond it is.
 // TODO: It might be a good idea to ignore text interpolations in patterns
 #[instrument(level = "trace")]
 pub fn text(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
+    let (input, raw_prefix) = match raw_text_prefix(input) {
+        Some((input_after_prefix, raw_prefix)) if input_after_prefix.starts_with(['\'', '"']) => {
+            (input_after_prefix, Some(Box::new(raw_prefix)))
+        }
+        _ => (input, None),
+    };
     let (input, opening_single_quotes) = parse_multiple(input, single_quote, None)?;
     let (mut input, opening_double_quote) = double_quote(input)?;
 
@@ -47,14 +64,27 @@ pub fn text(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
 
     let closing = loop {
         // TODO Use higher indentation in multiline text
-        if let Some((input_after_interpolation, interpolation)) =
-            text_interpolation(input, indentation, opening_single_quotes.len() + 1)
+        if raw_prefix.is_none()
+            && let Some((input_after_interpolation, interpolation)) =
+                text_interpolation(input, indentation, opening_single_quotes.len() + 1)
         {
             input = input_after_interpolation;
+            if let Some(indentation) =
+                split_off_additional_indentation(&mut opening_whitespace, &mut parts)
+            {
+                parts.push(CstKind::TextPart(indentation).into());
+            }
             parts.push(interpolation);
-        } else if let Some((input_after_part, part)) = text_part(input, opening_single_quotes.len())
+        } else if let Some((input_after_part, mut part)) =
+            text_part(input, opening_single_quotes.len(), raw_prefix.is_some())
         {
             input = input_after_part;
+            if let Some(indentation) =
+                split_off_additional_indentation(&mut opening_whitespace, &mut parts)
+                && let CstKind::TextPart(text) = &mut part.kind
+            {
+                text.insert_str(0, &indentation);
+            }
             parts.push(part);
         } else {
             let (input_after_whitespace, whitespace) =
@@ -146,6 +176,7 @@ pub fn text(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
         CstKind::Text {
             opening: Box::new(
                 CstKind::OpeningText {
+                    raw_prefix,
                     opening_single_quotes,
                     opening_double_quote: Box::new(opening_double_quote),
                 }
@@ -215,19 +246,20 @@ fn text_interpolation(
 }
 
 #[instrument(level = "trace")]
-fn text_part(mut input: &str, single_quotes_count: usize) -> Option<(&str, Rcst)> {
+fn text_part(mut input: &str, single_quotes_count: usize, is_raw: bool) -> Option<(&str, Rcst)> {
     let mut text_part = vec![];
     loop {
         let next_char = input.chars().next();
         // TODO Optimize this somehow
         if next_char.is_none()
             || newline(input).is_some()
-            || parse_multiple(
-                input,
-                opening_curly_brace,
-                Some((single_quotes_count + 1, true)),
-            )
-            .is_some()
+            || (!is_raw
+                && parse_multiple(
+                    input,
+                    opening_curly_brace,
+                    Some((single_quotes_count + 1, true)),
+                )
+                .is_some())
             || double_quote(input)
                 .and_then(|(input_after_double_quote, _)| {
                     parse_multiple(
@@ -252,6 +284,58 @@ fn text_part(mut input: &str, single_quotes_count: usize) -> Option<(&str, Rcst)
     }
 }
 
+/// Removes the indentation beyond the text's own indentation from the
+/// whitespace in front of the line that is about to be parsed and returns it.
+///
+/// After a newline, [`whitespaces_and_newlines`] parses the text's indentation
+/// and any additional whitespace into separate items. This whitespace is either
+/// part of the opening quotes (for the first line) or of the preceding text
+/// newline.
+#[instrument(level = "trace")]
+fn split_off_additional_indentation(
+    opening_whitespace: &mut Vec<Rcst>,
+    parts: &mut [Rcst],
+) -> Option<String> {
+    let whitespace = match parts.last_mut() {
+        None => opening_whitespace,
+        Some(Rcst {
+            kind:
+                CstKind::TrailingWhitespace {
+                    child:
+                        box Rcst {
+                            kind: CstKind::TextNewline(_),
+                            ..
+                        },
+                    whitespace,
+                },
+            ..
+        }) => whitespace,
+        Some(_) => return None,
+    };
+    let line_start = whitespace
+        .iter()
+        .rposition(|it| it.kind.is_newline())
+        .map_or(0, |index| index + 1);
+    let [Rcst {
+        kind: CstKind::Whitespace(_),
+        ..
+    }, Rcst {
+        kind: CstKind::Whitespace(_),
+        ..
+    }] = &whitespace[line_start..]
+    else {
+        return None;
+    };
+    let Some(Rcst {
+        kind: CstKind::Whitespace(additional_indentation),
+        ..
+    }) = whitespace.pop()
+    else {
+        unreachable!()
+    };
+    Some(additional_indentation)
+}
+
 #[instrument(level = "trace")]
 fn convert_whitespace_into_text_newlines(whitespace: Vec<Rcst>) -> Vec<Rcst> {
     let mut last_newline: Option<Rcst> = None;
@@ -305,10 +389,9 @@ mod test {
             whitespace:
               Newline "\n"
               Whitespace "  "
-              Whitespace "  "
           parts:
             TrailingWhitespace:
-              child: TextPart "text"
+              child: TextPart "  text"
               whitespace:
                 Newline "\n"
           closing: ClosingText:
@@ -332,9 +415,8 @@ mod test {
               child: TextNewline "\n"
               whitespace:
                 Whitespace "  "
-                Whitespace "  "
             TrailingWhitespace:
-              child: TextPart "bar"
+              child: TextPart "  bar"
               whitespace:
                 Newline "\n"
           closing: ClosingText:
@@ -747,5 +829,94 @@ mod test {
             closing_double_quote: DoubleQuote
             closing_single_quotes:
         "###);
+        assert_rich_ir_snapshot!(text("r\"foo {bar}\"", 0), @r###"
+        Remaining input: ""
+        Parsed: Text:
+          opening: OpeningText:
+            raw_prefix: RawTextPrefix
+            opening_single_quotes:
+            opening_double_quote: DoubleQuote
+          parts:
+            TextPart "foo {bar}"
+          closing: ClosingText:
+            closing_double_quote: DoubleQuote
+            closing_single_quotes:
+        "###);
+        //   r'"
+        //     {
+        //       "foo": {bar}
+        //     }
+        //   "'
+        assert_rich_ir_snapshot!(
+            text("r'\"\n    {\n      \"foo\": {bar}\n    }\n  \"'", 1),
+            @r###"
+        Remaining input: ""
+        Parsed: Text:
+          opening: TrailingWhitespace:
+            child: OpeningText:
+              raw_prefix: RawTextPrefix
+              opening_single_quotes:
+                SingleQuote
+              opening_double_quote: DoubleQuote
+            whitespace:
+              Newline "\n"
+              Whitespace "    "
+          parts:
+            TextPart "{"
+            TrailingWhitespace:
+              child: TextNewline "\n"
+              whitespace:
+                Whitespace "    "
+            TextPart "  "foo": {bar}"
+            TrailingWhitespace:
+              child: TextNewline "\n"
+              whitespace:
+                Whitespace "    "
+            TrailingWhitespace:
+              child: TextPart "}"
+              whitespace:
+                Newline "\n"
+                Whitespace "  "
+          closing: ClosingText:
+            closing_double_quote: DoubleQuote
+            closing_single_quotes:
+              SingleQuote
+        "###
+        );
+        // "
+        //   foo
+        //     {bar}
+        // "
+        assert_rich_ir_snapshot!(text("\"\n  foo\n    {bar}\n\"", 0), @r###"
+        Remaining input: ""
+        Parsed: Text:
+          opening: TrailingWhitespace:
+            child: OpeningText:
+              opening_single_quotes:
+              opening_double_quote: DoubleQuote
+            whitespace:
+              Newline "\n"
+              Whitespace "  "
+          parts:
+            TextPart "foo"
+            TrailingWhitespace:
+              child: TextNewline "\n"
+              whitespace:
+                Whitespace "  "
+            TextPart "  "
+            TrailingWhitespace:
+              child: TextInterpolation:
+                opening_curly_braces:
+                  OpeningCurlyBrace
+                expression: Identifier "bar"
+                closing_curly_braces:
+                  ClosingCurlyBrace
+              whitespace:
+                Newline "\n"
+          closing: ClosingText:
+            closing_double_quote: DoubleQuote
+            closing_single_quotes:
+        "###);
+        assert_rich_ir_snapshot!(text("raw", 0), @"Nothing was parsed");
     }
 }
@@ -0,0 +1,309 @@
+//! A persistent index of the symbols in a package.
+//!
+//! Lowering hundreds of modules just to list their definitions or to find the
+//! modules that might reference one of them is slow. Instead, the index stores
+//! what the CST says about each module: its top-level definitions (including
+//! whether they're exported and their doc summaries), the identifiers it
+//! mentions, and the modules of the same package that it uses.
+//!
+//! Each entry remembers a hash of the source it was built from, so updating
+//! the index only parses modules that changed since. The index is saved in the
+//! package's `.candy` directory and shared by the language server and the CLI.
+
+use crate::{
+    cst::{Cst, CstDb, CstKind, DefinitionKind, UnwrapWhitespaceAndComment},
+    module::{Module, ModuleKind, Package, PackagesPath, UsePath},
+    position::Offset,
+};
+use rustc_hash::{FxHashSet, FxHasher};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    hash::Hasher,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SymbolIndex {
+    /// Keyed by the module's path in its package, e.g., `foo/bar`.
+    modules: BTreeMap<String, ModuleIndex>,
+}
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModuleIndex {
+    source_hash: u64,
+    pub definitions: Vec<IndexedDefinition>,
+    /// All identifiers in the module, including the ones referencing local
+    /// variables and parameters.
+    pub references: Vec<IndexedReference>,
+    /// The keys of modules in the same package that this module uses.
+    pub uses: Vec<String>,
+}
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IndexedDefinition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub is_exported: bool,
+    pub span: Range<Offset>,
+    pub name_span: Range<Offset>,
+    pub doc_summary: Option<String>,
+}
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IndexedReference {
+    pub name: String,
+    pub span: Range<Offset>,
+}
+
+impl SymbolIndex {
+    #[must_use]
+    pub fn file(package_path: &Path) -> PathBuf {
+        package_path.join(".candy").join("symbolIndex.json")
+    }
+    /// Returns an empty index if none was saved for the package or it can't be
+    /// read, e.g., because it was written by an incompatible version.
+    #[must_use]
+    pub fn load(package_path: &Path) -> Self {
+        fs::read(Self::file(package_path))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+    pub fn save(&self, package_path: &Path) -> io::Result<()> {
+        let file = Self::file(package_path);
+        fs::create_dir_all(file.parent().unwrap())?;
+        fs::write(file, serde_json::to_vec(self)?)
+    }
+
+    /// Reindexes all modules of the package whose source changed and removes
+    /// the ones that no longer exist.
+    ///
+    /// Returns whether the index changed.
+    pub fn update(
+        &mut self,
+        db: &dyn CstDb,
+        packages_path: &PackagesPath,
+        package: &Package,
+    ) -> bool {
+        let modules = modules_in_package(packages_path, package);
+        let keys = modules.iter().map(key).collect::<FxHashSet<_>>();
+        let module_count = self.modules.len();
+        self.modules.retain(|key, _| keys.contains(key));
+        let mut has_changed = self.modules.len() != module_count;
+
+        for module in &modules {
+            has_changed |= self.update_module(db, module);
+        }
+        has_changed
+    }
+    /// Reindexes the module if its source changed.
+    ///
+    /// Returns whether the index changed.
+    pub fn update_module(&mut self, db: &dyn CstDb, module: &Module) -> bool {
+        let key = key(module);
+        let Some(source) = db.get_module_content(module.clone()) else {
+            return self.modules.remove(&key).is_some();
+        };
+        let mut hasher = FxHasher::default();
+        hasher.write(&source);
+        let source_hash = hasher.finish();
+
+        if self
+            .modules
+            .get(&key)
+            .is_some_and(|it| it.source_hash == source_hash)
+        {
+            return false;
+        }
+        self.modules
+            .insert(key, ModuleIndex::build(db, module, source_hash));
+        true
+    }
+
+    #[must_use]
+    pub fn module(&self, module: &Module) -> Option<&ModuleIndex> {
+        self.modules.get(&key(module))
+    }
+    /// Definitions whose name contains the query, ignoring case.
+    pub fn search<'a>(
+        &'a self,
+        package: &'a Package,
+        query: &str,
+    ) -> impl Iterator<Item = (Module, &'a IndexedDefinition)> + 'a {
+        let query = query.to_lowercase();
+        self.modules.iter().flat_map(move |(key, index)| {
+            let query = query.clone();
+            index
+                .definitions
+                .iter()
+                .filter(move |definition| definition.name.to_lowercase().contains(&query))
+                .map(move |definition| (module_for_key(package, key), definition))
+        })
+    }
+    /// Modules that use the given module and mention `name`, i.e., the only
+    /// modules that can reference its export called `name`.
+    #[must_use]
+    pub fn modules_possibly_referencing(&self, module: &Module, name: &str) -> Vec<Module> {
+        let used_key = key(module);
+        self.modules
+            .iter()
+            .filter(|(_, index)| {
+                index.uses.contains(&used_key) && index.references.iter().any(|it| it.name == name)
+            })
+            .map(|(key, _)| module_for_key(module.package(), key))
+            .collect()
+    }
+}
+
+impl ModuleIndex {
+    fn build(db: &dyn CstDb, module: &Module, source_hash: u64) -> Self {
+        let definitions = db
+            .module_skeleton(module.clone())
+            .iter()
+            .map(|definition| IndexedDefinition {
+                name: definition.name.clone(),
+                kind: definition.kind,
+                is_exported: definition.is_public,
+                span: definition.span.clone(),
+                name_span: definition.name_span.clone(),
+                doc_summary: definition.doc_summary.clone(),
+            })
+            .collect();
+
+        let mut references = vec![];
+        let mut uses = vec![];
+        if let Ok(csts) = db.cst(module.clone()) {
+            for cst in csts.iter() {
+                collect_references_and_uses(cst, module, &mut references, &mut uses);
+            }
+        }
+        uses.sort();
+        uses.dedup();
+
+        Self {
+            source_hash,
+            definitions,
+            references,
+            uses,
+        }
+    }
+}
+
+fn collect_references_and_uses(
+    cst: &Cst,
+    module: &Module,
+    references: &mut Vec<IndexedReference>,
+    uses: &mut Vec<String>,
+) {
+    match &cst.kind {
+        CstKind::Identifier(name) => references.push(IndexedReference {
+            name: name.clone(),
+            span: cst.data.span.clone(),
+        }),
+        CstKind::Call {
+            receiver,
+            arguments,
+        } => {
+            if let CstKind::Identifier(name) = &receiver.unwrap_whitespace_and_comment().kind
+                && name == "use"
+                && let [argument] = &arguments[..]
+                && let Some(used_module) = resolve_use(argument, module)
+            {
+                uses.push(key(&used_module));
+            }
+        }
+        _ => {}
+    }
+    for child in cst.kind.children() {
+        collect_references_and_uses(child, module, references, uses);
+    }
+}
+/// Only uses of code modules in the same package are relevant for the index.
+fn resolve_use(argument: &Cst, module: &Module) -> Option<Module> {
+    let argument = argument.unwrap_whitespace_and_comment();
+    let CstKind::Text { parts, .. } = &argument.kind else {
+        return None;
+    };
+    let [part] = &parts[..] else {
+        return None;
+    };
+    let CstKind::TextPart(path) = &part.kind else {
+        return None;
+    };
+    let used_module = UsePath::parse(path)
+        .ok()?
+        .resolve_relative_to(module)
+        .ok()?;
+    (used_module.package() == module.package() && used_module.kind() == ModuleKind::Code)
+        .then_some(used_module)
+}
+
+fn key(module: &Module) -> String {
+    module.path().join("/")
+}
+fn module_for_key(package: &Package, key: &str) -> Module {
+    let path = if key.is_empty() {
+        vec![]
+    } else {
+        key.split('/').map(ToString::to_string).collect()
+    };
+    Module::new(package.clone(), path, ModuleKind::Code)
+}
+
+#[must_use]
+pub fn modules_in_package(packages_path: &PackagesPath, package: &Package) -> Vec<Module> {
+    let Some(path) = package.to_path(packages_path) else {
+        return vec![];
+    };
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|it| {
+            it.file_type().is_file() && it.path().extension().is_some_and(|it| it == "candy")
+        })
+        .filter_map(|file| {
+            Module::from_package_and_path(
+                packages_path,
+                package.clone(),
+                file.path(),
+                ModuleKind::Code,
+            )
+            .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SymbolIndex;
+    use crate::module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase};
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_modules_that_may_reference_an_export() {
+        let mut db = TestDatabase::default();
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let module =
+            |name: &str| Module::new(package.clone(), vec![name.to_string()], ModuleKind::Code);
+        let (lib, user, other) = (module("lib"), module("user"), module("other"));
+        db.did_open_module(&lib, b"# Greets.\nhello := 1\n".to_vec());
+        db.did_open_module(&user, b"[hello] = use \"..lib\"\nx = hello\n".to_vec());
+        db.did_open_module(&other, b"hello = 2\n".to_vec());
+
+        let mut index = SymbolIndex::default();
+        for module in [&lib, &user, &other] {
+            assert!(index.update_module(&db, module));
+        }
+        assert!(!index.update_module(&db, &lib));
+
+        assert_eq!(index.modules_possibly_referencing(&lib, "hello"), [user]);
+        let exported = index
+            .search(&package, "HELL")
+            .filter(|(_, definition)| definition.is_exported)
+            .map(|(module, _)| module)
+            .collect::<Vec<_>>();
+        assert_eq!(exported, [lib]);
+    }
+}
@@ -7,6 +7,15 @@ pub struct TracingConfig {
     pub register_fuzzables: TracingMode,
     pub calls: CallTracingMode,
     pub evaluated_expressions: TracingMode,
+    /// Whether `✨.debugAssert` and `✨.debugLog` run. If disabled, the
+    /// optimizer removes them entirely.
+    #[serde(default)]
+    pub debug_assertions: bool,
+    /// Whether to remove information about the source code that isn't needed
+    /// to run the program: HIR IDs, function names, and tracing. Panics then
+    /// blame [`hir::Id::stripped`](crate::hir::Id::stripped).
+    #[serde(default)]
+    pub strip: bool,
 }
 impl TracingConfig {
     #[must_use]
@@ -15,6 +24,8 @@ impl TracingConfig {
             register_fuzzables: TracingMode::Off,
             calls: CallTracingMode::Off,
             evaluated_expressions: TracingMode::Off,
+            debug_assertions: false,
+            strip: false,
         }
     }
 
@@ -24,6 +35,8 @@ impl TracingConfig {
             register_fuzzables: self.register_fuzzables.for_child_module(),
             calls: self.calls.for_child_module(),
             evaluated_expressions: self.evaluated_expressions.for_child_module(),
+            debug_assertions: self.debug_assertions,
+            strip: self.strip,
         }
     }
 }
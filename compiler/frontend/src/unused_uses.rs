@@ -0,0 +1,232 @@
+//! Finds `use`s whose results are never referenced.
+//!
+//! Only top-level assignments of a single `use "…"` call are considered, e.g.:
+//!
+//! ```candy
+//! foo = use ".foo"
+//! [bar, Baz: baz] = use "Bar"
+//! ```
+//!
+//! Public assignments (`:=`) are exported and, therefore, always used.
+
+use crate::{
+    ast::{self, Assignment, AssignmentBody, Ast, AstKind, Call, Identifier, Struct},
+    ast_to_hir::AstToHir,
+    hir,
+    module::Module,
+    position::Offset,
+};
+use rustc_hash::FxHashSet;
+use std::ops::Range;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnusedUse {
+    /// The names that are never referenced, with the spans of their
+    /// identifiers.
+    pub unused_names: Vec<(String, Range<Offset>)>,
+    /// Deleting these ranges removes the unused names while keeping the rest
+    /// of the formatting intact. If no name is used, this removes the whole
+    /// assignment including its line break.
+    pub removals: Vec<Range<Offset>>,
+}
+
+#[must_use]
+pub fn find_unused_uses(db: &impl AstToHir, module: Module) -> Vec<UnusedUse> {
+    let Some(text) = db.get_module_content_as_string(module.clone()) else {
+        return vec![];
+    };
+    let Ok((asts, _)) = db.ast(module.clone()) else {
+        return vec![];
+    };
+    let Ok((hir, _)) = db.hir(module) else {
+        return vec![];
+    };
+    let mut referenced = FxHashSet::default();
+    hir.collect_referenced_ids(&mut referenced);
+
+    let context = Context {
+        db,
+        text: &text,
+        referenced: &referenced,
+    };
+    asts.iter()
+        .filter_map(|ast| context.check_assignment(ast))
+        .collect()
+}
+
+/// Whether the AST is an assignment of a single `use "…"` call.
+#[must_use]
+pub fn is_use_assignment(ast: &Ast) -> bool {
+    let AstKind::Assignment(Assignment {
+        body: AssignmentBody::Body { body, .. },
+        ..
+    }) = &ast.kind
+    else {
+        return false;
+    };
+    let [Ast {
+        kind: AstKind::Call(Call { receiver, .. }),
+        ..
+    }] = body.as_slice()
+    else {
+        return false;
+    };
+    matches!(&receiver.kind, AstKind::Identifier(Identifier(name)) if name.value == "use")
+}
+
+struct Context<'a, DB: AstToHir> {
+    db: &'a DB,
+    text: &'a str,
+    referenced: &'a FxHashSet<hir::Id>,
+}
+impl<'a, DB: AstToHir> Context<'a, DB> {
+    fn check_assignment(&self, ast: &Ast) -> Option<UnusedUse> {
+        if !is_use_assignment(ast) {
+            return None;
+        }
+        let AstKind::Assignment(Assignment {
+            is_public: false,
+            body: AssignmentBody::Body { pattern, .. },
+        }) = &ast.kind
+        else {
+            return None;
+        };
+
+        match &pattern.kind {
+            AstKind::Identifier(Identifier(name)) => {
+                if self.is_referenced(&name.id) {
+                    return None;
+                }
+                Some(UnusedUse {
+                    unused_names: vec![(name.value.clone(), self.span(&name.id)?)],
+                    removals: vec![self.whole_lines(&ast.id)?],
+                })
+            }
+            AstKind::Struct(Struct { fields }) => self.check_struct_fields(ast, fields),
+            _ => None,
+        }
+    }
+    fn check_struct_fields(&self, ast: &Ast, fields: &[(Option<Ast>, Ast)]) -> Option<UnusedUse> {
+        // Spans of all fields and whether they are unused.
+        let mut spans = vec![];
+        let mut unused_names = vec![];
+        for (key, value) in fields {
+            let value_span = self.span(&value.id)?;
+            let start = key.as_ref().map_or(Some(value_span.start), |key| {
+                Some(self.span(&key.id)?.start)
+            })?;
+            let is_unused = if let AstKind::Identifier(Identifier(name)) = &value.kind
+                && !self.is_referenced(&value.id)
+            {
+                unused_names.push((name.value.clone(), value_span.clone()));
+                true
+            } else {
+                false
+            };
+            spans.push((start..value_span.end, is_unused));
+        }
+        if unused_names.is_empty() {
+            return None;
+        }
+        if unused_names.len() == fields.len() {
+            return Some(UnusedUse {
+                unused_names,
+                removals: vec![self.whole_lines(&ast.id)?],
+            });
+        }
+
+        // Unused fields before the last used one are removed up to the start
+        // of the following field. Trailing unused fields are removed starting
+        // at the end of the last used field so that a trailing comma and the
+        // line break before the closing bracket stay.
+        let last_used_index = spans.iter().rposition(|(_, is_unused)| !is_unused)?;
+        let mut removals = vec![];
+        for (index, (span, is_unused)) in spans[..last_used_index].iter().enumerate() {
+            if *is_unused {
+                removals.push(span.start..spans[index + 1].0.start);
+            }
+        }
+        if last_used_index + 1 < spans.len() {
+            removals.push(spans[last_used_index].0.end..spans.last().unwrap().0.end);
+        }
+        Some(UnusedUse {
+            unused_names,
+            removals,
+        })
+    }
+
+    fn is_referenced(&self, identifier_id: &ast::Id) -> bool {
+        let ids = self.db.ast_to_hir_ids(identifier_id);
+        // Without a HIR ID, we can't tell and assume it's used.
+        ids.is_empty() || ids.iter().any(|id| self.referenced.contains(id))
+    }
+    fn span(&self, id: &ast::Id) -> Option<Range<Offset>> {
+        self.db.ast_id_to_span(id)
+    }
+    /// The span of the AST extended to whole lines, including the final line
+    /// break.
+    fn whole_lines(&self, id: &ast::Id) -> Option<Range<Offset>> {
+        let span = self.span(id)?;
+        let start = self.text[..*span.start].rfind('\n').map_or(0, |it| it + 1);
+        let end = self.text[*span.end..]
+            .find('\n')
+            .map_or(self.text.len(), |it| *span.end + it + 1);
+        Some(Offset(start)..Offset(end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_unused_uses;
+    use crate::module::{Module, ModuleKind, MutableModuleProviderOwner, Package, TestDatabase};
+    use std::path::PathBuf;
+
+    fn remove_unused_uses(source: &str) -> String {
+        let mut db = TestDatabase::default();
+        let module = Module::new(
+            Package::User(PathBuf::from("/non/existent")),
+            vec!["foo".to_string()],
+            ModuleKind::Code,
+        );
+        db.did_open_module(&module, source.as_bytes().to_vec());
+
+        let mut removals = find_unused_uses(&db, module)
+            .into_iter()
+            .flat_map(|it| it.removals)
+            .collect::<Vec<_>>();
+        removals.sort_by_key(|it| it.start);
+        let mut result = source.to_string();
+        for removal in removals.into_iter().rev() {
+            result.replace_range(*removal.start..*removal.end, "");
+        }
+        result
+    }
+
+    #[test]
+    fn removes_unused_uses() {
+        assert_eq!(
+            remove_unused_uses("foo = use \".foo\"\nbar = use \".bar\"\nmain := { env -> bar }\n"),
+            "bar = use \".bar\"\nmain := { env -> bar }\n",
+        );
+        assert_eq!(
+            remove_unused_uses("foo := use \".foo\"\n"),
+            "foo := use \".foo\"\n",
+        );
+    }
+    #[test]
+    fn removes_unused_names_of_destructured_uses() {
+        let uses = |names: &str| format!("{names} = use \"Core\"\nmain := {{ env -> a b }}\n");
+        assert_eq!(remove_unused_uses(&uses("[a, b, c]")), uses("[a, b]"));
+        assert_eq!(remove_unused_uses(&uses("[c, a, b]")), uses("[a, b]"));
+        assert_eq!(remove_unused_uses(&uses("[a, c, d, b]")), uses("[a, b]"));
+        assert_eq!(remove_unused_uses(&uses("[a, b, C: c, d]")), uses("[a, b]"));
+        assert_eq!(
+            remove_unused_uses(&uses("[\n  a,\n  b,\n  c,\n]")),
+            uses("[\n  a,\n  b,\n]"),
+        );
+        assert_eq!(
+            remove_unused_uses("[c, d] = use \"Core\"\nmain := { env -> env }\n"),
+            "main := { env -> env }\n",
+        );
+    }
+}
@@ -24,6 +24,7 @@ pub struct Fuzzer {
     pub function: Function,
     pub function_id: Id,
     pool: InputPool,
+    num_inputs_tried: usize,
     status: Option<Status>, // only `None` during transitions
 }
 
@@ -90,6 +91,7 @@ impl Fuzzer {
             function,
             function_id,
             pool,
+            num_inputs_tried: 0,
             status: Some(Status::StillFuzzing {
                 total_coverage: Coverage::none(num_instructions),
                 input,
@@ -142,6 +144,12 @@ impl Fuzzer {
     pub const fn input_pool(&self) -> &InputPool {
         &self.pool
     }
+    /// The number of inputs the function finished running with so far,
+    /// including those that timed out.
+    #[must_use]
+    pub const fn num_inputs_tried(&self) -> usize {
+        self.num_inputs_tried
+    }
 
     pub fn run(&mut self, max_instructions: usize) {
         let mut status = self.status.take().unwrap();
@@ -177,13 +185,22 @@ impl Fuzzer {
                 runner,
             };
         };
+        self.num_inputs_tried += 1;
 
         let call_string = format!("`{} {}`", self.function_id.function_name(), input);
         debug!("{}", result.to_string(&call_string));
         match result {
-            RunResult::Timeout => self.create_new_fuzzing_case(total_coverage),
+            RunResult::Timeout | RunResult::OutOfMemory => {
+                self.create_new_fuzzing_case(total_coverage)
+            }
             RunResult::Done { .. } | RunResult::NeedsUnfulfilled { .. } => {
-                let function_range = self.byte_code.range_of_function(&self.function_id);
+                // If the byte code has no body for the function, we can only
+                // measure the coverage of the whole byte code.
+                let function_range = self
+                    .byte_code
+                    .origins
+                    .range_of_function(&self.function_id)
+                    .unwrap_or_else(|| 0.into()..self.byte_code.instructions.len().into());
                 let function_coverage = total_coverage.in_range(&function_range);
 
                 // We favor small inputs with good code coverage.
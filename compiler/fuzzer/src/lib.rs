@@ -26,26 +26,34 @@ use candy_frontend::{
     module::Module,
     position::PositionConversionDb,
     tracing::CallTracingMode,
-    {hir::Id, TracingConfig, TracingMode},
+    {hir::Id, OptimizationConfig, TracingConfig, TracingMode},
 };
 use candy_vm::{
-    heap::Heap, lir_to_byte_code::compile_byte_code, tracer::stack_trace::StackTracer, Panic, Vm,
-    VmFinished,
+    heap::Heap, lir_to_byte_code::compile_byte_code, origins::ByteCodeOriginsDb,
+    tracer::stack_trace::StackTracer, Panic, Vm, VmFinished,
 };
+use itertools::Itertools;
 use std::rc::Rc;
 use tracing::{debug, error, info};
 
+/// The tracing configuration of byte code that can be fuzzed.
+pub const TRACING: TracingConfig = TracingConfig {
+    register_fuzzables: TracingMode::OnlyCurrent,
+    calls: CallTracingMode::Off,
+    evaluated_expressions: TracingMode::Off,
+    debug_assertions: true,
+    strip: false,
+};
+
 pub fn fuzz<DB>(db: &DB, module: Module) -> Vec<FailingFuzzCase>
 where
-    DB: AstToHir + CstDb + OptimizeLir + PositionConversionDb,
+    DB: AstToHir + ByteCodeOriginsDb + CstDb + OptimizeLir + PositionConversionDb,
 {
-    let tracing = TracingConfig {
-        register_fuzzables: TracingMode::OnlyCurrent,
-        calls: CallTracingMode::Off,
-        evaluated_expressions: TracingMode::Off,
-    };
-    let (byte_code, _) = compile_byte_code(db, ExecutionTarget::Module(module), tracing);
+    let target = ExecutionTarget::Module(module);
+    let (byte_code, _) =
+        compile_byte_code(db, target.clone(), TRACING, OptimizationConfig::default());
     let byte_code = Rc::new(byte_code);
+    let origins = db.byte_code_origins(target, TRACING, OptimizationConfig::default());
 
     let mut heap = Heap::default();
     let VmFinished {
@@ -61,17 +69,17 @@ where
 
     let mut failing_cases = vec![];
 
-    for (id, function) in fuzzables {
+    for (id, function) in fuzzables.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
         info!("Fuzzing {id}.");
         let mut fuzzer = Fuzzer::new(byte_code.clone(), function, id.clone());
         fuzzer.run(100_000);
 
         match fuzzer.into_result() {
             FuzzerResult::StillFuzzing { total_coverage, .. } => {
-                let coverage = total_coverage
-                    .in_range(&byte_code.range_of_function(&id))
-                    .relative_coverage();
-                debug!("Achieved a coverage of {:.1} %.", coverage * 100.0);
+                if let Some(range) = origins.range_of_function(&id) {
+                    let coverage = total_coverage.in_range(&range).relative_coverage();
+                    debug!("Achieved a coverage of {:.1} %.", coverage * 100.0);
+                }
             }
             FuzzerResult::FoundPanic {
                 input,
@@ -13,6 +13,7 @@ use rustc_hash::FxHashMap;
 use std::borrow::Borrow;
 
 const MAX_INSTRUCTIONS: usize = 1_000_000;
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
 
 pub struct Runner<B: Borrow<ByteCode>> {
     pub byte_code: B,
@@ -31,6 +32,10 @@ pub enum RunResult {
     /// Executing the function with the input took more than `MAX_INSTRUCTIONS`.
     Timeout,
 
+    /// Executing the function with the input allocated more than
+    /// `MEMORY_LIMIT` bytes.
+    OutOfMemory,
+
     /// The execution finished successfully with a value.
     Done {
         heap: Heap,
@@ -54,6 +59,7 @@ impl RunResult {
     pub fn to_string(&self, call: &str) -> String {
         match self {
             Self::Timeout => format!("{call} timed out."),
+            Self::OutOfMemory => format!("{call} ran out of memory."),
             Self::Done { return_value, .. } => format!("{call} returned {return_value}."),
             Self::NeedsUnfulfilled { reason } => {
                 format!("{call} panicked and it's our fault: {reason}")
@@ -68,7 +74,7 @@ impl RunResult {
 impl<B: Borrow<ByteCode> + Clone> Runner<B> {
     #[must_use]
     pub fn new(byte_code: B, function: Function, input: &Input) -> Self {
-        let mut heap = Heap::default();
+        let mut heap = Heap::with_memory_limit(MEMORY_LIMIT);
         let num_instructions = byte_code.borrow().instructions.len();
 
         let mut mapping = FxHashMap::default();
@@ -121,8 +127,11 @@ impl<B: Borrow<ByteCode> + Clone> Runner<B> {
                 StateAfterRunWithoutHandles::Finished(VmFinished {
                     tracer,
                     result: Err(panic),
+                    ..
                 }) => {
-                    let result = if panic.responsible == Id::fuzzer() {
+                    let result = if panic.is_out_of_memory() {
+                        RunResult::OutOfMemory
+                    } else if panic.responsible == Id::fuzzer() {
                         RunResult::NeedsUnfulfilled {
                             reason: panic.reason,
                         }
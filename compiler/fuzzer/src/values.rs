@@ -163,11 +163,7 @@ impl InlineObjectGeneration for InlineObject {
                     list.replace(heap, index_to_mutate, new_item).into()
                 } else if len > 0 && rng.gen_bool(0.5) {
                     // Remove item
-                    let new_list = list.remove(heap, rng.gen_range(0..len));
-                    for item in new_list.items() {
-                        item.dup(heap);
-                    }
-                    new_list.into()
+                    list.remove(heap, rng.gen_range(0..len)).into()
                 } else {
                     // Add item
                     for item in list.items() {
@@ -225,7 +221,15 @@ impl InlineObjectGeneration for InlineObject {
                 // No `dup()` necessary since these are inline.
                 (*BuiltinFunction::VARIANTS.choose(rng).unwrap()).into()
             }
-            Data::HirId(_) | Data::Function(_) | Data::Handle(_) => {
+            Data::HirId(_)
+            | Data::Function(_)
+            | Data::Handle(_)
+            | Data::Bytes(_)
+            | Data::Cell(_)
+            | Data::Fraction(_)
+            | Data::Map(_)
+            | Data::Set(_)
+            | Data::TextBuilder(_) => {
                 panic!("Couldn't have been created for fuzzing.")
             }
         }
@@ -237,7 +241,12 @@ impl InlineObjectGeneration for InlineObject {
                 Int::Inline(int) => int.get().abs().bit_length() as usize,
                 Int::Heap(int) => int.get().bits().try_into().unwrap_or(usize::MAX),
             },
+            Data::Fraction(fraction) => {
+                let bits = fraction.get().numer().bits() + fraction.get().denom().bits();
+                bits.try_into().unwrap_or(usize::MAX)
+            }
             Data::Text(text) => text.byte_len() + 1,
+            Data::Bytes(bytes) => bytes.len() + 1,
             Data::Tag(tag) => {
                 1 + tag
                     .value()
@@ -258,7 +267,26 @@ impl InlineObjectGeneration for InlineObject {
                     .sum::<usize>()
                     + 1
             }
-            Data::HirId(_) | Data::Function(_) | Data::Builtin(_) | Data::Handle(_) => 1,
+            Data::Map(map) => {
+                map.entries()
+                    .into_iter()
+                    .map(|(key, value)| key.complexity() + value.complexity())
+                    .sum::<usize>()
+                    + 1
+            }
+            Data::Set(set) => {
+                set.items()
+                    .iter()
+                    .map(|item| item.complexity())
+                    .sum::<usize>()
+                    + 1
+            }
+            Data::HirId(_)
+            | Data::Function(_)
+            | Data::Builtin(_)
+            | Data::Handle(_)
+            | Data::Cell(_)
+            | Data::TextBuilder(_) => 1,
         }
     }
 }
@@ -1,8 +1,11 @@
+use crate::recompilation_statistics::{RecompilationStatistics, RecompilationStatisticsReport};
 #[cfg(feature = "inkwell")]
 use candy_backend_inkwell::LlvmIrStorage;
 use candy_frontend::{
     ast::AstDbStorage,
     ast_to_hir::AstToHirStorage,
+    cancellation::{CancellationToken, CancellationTokenOwner},
+    comment::string_to_rcst::CommentStringToRcstStorage,
     cst::CstDbStorage,
     cst_to_ast::CstToAstStorage,
     hir::HirDbStorage,
@@ -13,18 +16,23 @@ use candy_frontend::{
     module::{
         FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
         ModuleDbStorage, ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner,
-        OverlayModuleProvider, PackagesPath,
+        OverlayModuleProvider, PackagesPath, VirtualModuleProvider,
     },
+    persistent_cache::PersistentCacheOwner,
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
     string_to_rcst::StringToRcstStorage,
 };
+use candy_vm::origins::ByteCodeOriginsStorage;
+use std::sync::Mutex;
 
 #[cfg_attr(
     feature = "inkwell",
     salsa::database(
         AstDbStorage,
         AstToHirStorage,
+        ByteCodeOriginsStorage,
+        CommentStringToRcstStorage,
         CstDbStorage,
         CstToAstStorage,
         HirDbStorage,
@@ -44,6 +52,8 @@ use candy_frontend::{
     salsa::database(
         AstDbStorage,
         AstToHirStorage,
+        ByteCodeOriginsStorage,
+        CommentStringToRcstStorage,
         CstDbStorage,
         CstToAstStorage,
         HirDbStorage,
@@ -60,9 +70,20 @@ use candy_frontend::{
 pub struct Database {
     storage: salsa::Storage<Self>,
     pub packages_path: PackagesPath,
-    module_provider: OverlayModuleProvider<InMemoryModuleProvider, Box<dyn ModuleProvider + Send>>,
+    /// Modules opened in an editor shadow virtual modules, which shadow the
+    /// underlying module provider.
+    module_provider: OverlayModuleProvider<
+        InMemoryModuleProvider,
+        OverlayModuleProvider<VirtualModuleProvider, Box<dyn ModuleProvider + Send>>,
+    >,
+    recompilation_statistics: Mutex<RecompilationStatistics>,
+    cancellation_token: Option<CancellationToken>,
+}
+impl salsa::Database for Database {
+    fn salsa_event(&self, event: salsa::Event) {
+        self.recompilation_statistics.lock().unwrap().record(&event);
+    }
 }
-impl salsa::Database for Database {}
 
 impl Database {
     #[must_use]
@@ -83,10 +104,32 @@ impl Database {
             packages_path,
             module_provider: OverlayModuleProvider::new(
                 InMemoryModuleProvider::default(),
-                module_provider,
+                OverlayModuleProvider::new(VirtualModuleProvider::default(), module_provider),
             ),
+            recompilation_statistics: Mutex::default(),
+            cancellation_token: None,
         }
     }
+    /// Lets long-running queries of this database abort early once `token`
+    /// gets canceled.
+    #[must_use]
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Attributes the queries that salsa executes or reuses from now on to an
+    /// edit of `module`.
+    pub fn start_recording_edit(&self, module: Module) {
+        self.recompilation_statistics
+            .lock()
+            .unwrap()
+            .start_edit(module);
+    }
+    #[must_use]
+    pub fn recompilation_statistics(&self) -> RecompilationStatisticsReport {
+        self.recompilation_statistics.lock().unwrap().report(self)
+    }
 }
 
 impl ModuleProviderOwner for Database {
@@ -94,10 +137,19 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl PersistentCacheOwner for Database {}
+impl CancellationTokenOwner for Database {
+    fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+}
 impl MutableModuleProviderOwner for Database {
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
         &mut self.module_provider.overlay
     }
+    fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider {
+        &mut self.module_provider.fallback.overlay
+    }
     fn invalidate_module(&mut self, module: &Module) {
         GetModuleContentQuery.in_db_mut(self).invalidate(module);
     }
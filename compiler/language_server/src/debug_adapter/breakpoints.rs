@@ -0,0 +1,77 @@
+use super::session::StartAt1Config;
+use crate::{database::Database, utils::LspPositionConversion};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    hir::{HirDb, Id},
+    module::Module,
+};
+use rustc_hash::FxHashMap;
+
+/// The breakpoints that are currently active in a VM.
+///
+/// A breakpoint set on a line covers all HIR expressions starting on that
+/// line. Because evaluating a line usually traces several of these expressions,
+/// we only stop at the first one and ignore the others until execution moves
+/// on to a different line in the same function.
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    /// HIR ID → DAP breakpoint ID
+    ids: FxHashMap<Id, usize>,
+    /// The breakpoint we stopped at last and the call stack depth at that time.
+    last_stop: Option<(usize, usize)>,
+}
+impl Breakpoints {
+    pub fn set(&mut self, ids: FxHashMap<Id, usize>) {
+        self.ids = ids;
+        self.last_stop = None;
+    }
+
+    #[must_use]
+    pub fn get(&self, id: &Id) -> Option<usize> {
+        self.ids.get(id).copied()
+    }
+    pub fn forget_last_stop(&mut self) {
+        self.last_stop = None;
+    }
+
+    /// Returns the ID of the breakpoint we should stop at, if any.
+    pub fn on_expression(&mut self, id: &Id, call_stack_depth: usize) -> Option<usize> {
+        let breakpoint = self.ids.get(id).copied();
+
+        if let Some((last_breakpoint, last_depth)) = self.last_stop
+            && (call_stack_depth < last_depth
+                || (call_stack_depth == last_depth && breakpoint != Some(last_breakpoint)))
+        {
+            self.last_stop = None;
+        }
+
+        let breakpoint = breakpoint?;
+        if self.last_stop == Some((breakpoint, call_stack_depth)) {
+            return None;
+        }
+        self.last_stop = Some((breakpoint, call_stack_depth));
+        Some(breakpoint)
+    }
+}
+
+/// Returns the IDs of all HIR expressions starting on the given line.
+///
+/// The line is given in the client's format, i.e., it respects
+/// [`StartAt1Config`].
+pub fn hir_ids_on_line(
+    db: &Database,
+    start_at_1_config: StartAt1Config,
+    module: &Module,
+    line: usize,
+) -> Vec<Id> {
+    db.all_hir_ids(module.clone())
+        .into_iter()
+        .filter(|id| {
+            let Some(span) = db.hir_id_to_span(id) else {
+                return false;
+            };
+            let range = db.range_to_lsp_range(module.clone(), span);
+            start_at_1_config.range_to_dap(range).start.line as usize == line
+        })
+        .collect()
+}
@@ -0,0 +1,97 @@
+use super::breakpoints::Breakpoints;
+use candy_frontend::hir::Id;
+
+/// Decides when a running program should be paused, either because it hit a
+/// breakpoint or because a step finished.
+///
+/// The controller lives in the [`DebugTracer`](super::tracer::DebugTracer) and
+/// is informed about every traced expression. The session then checks after
+/// each instruction whether the program should be paused.
+#[derive(Debug, Default)]
+pub struct ExecutionController {
+    pub breakpoints: Breakpoints,
+    stepping: Option<Stepping>,
+    pause_reason: Option<PauseReason>,
+}
+impl ExecutionController {
+    pub fn step(&mut self, kind: StepKind, call_stack_depth: usize) {
+        self.stepping = Some(Stepping {
+            kind,
+            initial_call_stack_depth: call_stack_depth,
+        });
+    }
+    /// Forgets about ongoing steps and pending pauses, e.g., when the user
+    /// continues or pauses the program manually.
+    pub fn reset(&mut self) {
+        self.stepping = None;
+        self.pause_reason = None;
+    }
+
+    pub fn on_event(&mut self, id: &Id, event: TraceEvent, call_stack_depth: usize) {
+        if self.pause_reason.is_some() {
+            return;
+        }
+
+        if let Some(breakpoint) = self.breakpoints.on_expression(id, call_stack_depth) {
+            self.pause(PauseReason::Breakpoint(breakpoint));
+        } else if let Some(stepping) = self.stepping
+            && stepping.is_finished(event, call_stack_depth)
+        {
+            self.pause(PauseReason::Step);
+        }
+    }
+    fn pause(&mut self, reason: PauseReason) {
+        self.stepping = None;
+        self.pause_reason = Some(reason);
+    }
+
+    pub fn take_pause_reason(&mut self) -> Option<PauseReason> {
+        self.pause_reason.take()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StepKind {
+    Next,
+    In,
+    Out,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Stepping {
+    kind: StepKind,
+    /// The number of calls on the tracer's call stack when the step started.
+    initial_call_stack_depth: usize,
+}
+impl Stepping {
+    const fn is_finished(self, event: TraceEvent, call_stack_depth: usize) -> bool {
+        // We only stop after evaluating expressions. Otherwise, each call would
+        // take two steps: one when it starts and one when its value is
+        // evaluated.
+        if !matches!(event, TraceEvent::ExpressionEvaluated) {
+            return false;
+        }
+
+        match self.kind {
+            // Run until we're back in the same function or its caller.
+            StepKind::Next => call_stack_depth <= self.initial_call_stack_depth,
+            // Stop in the first called function or, if there are no calls, at
+            // the next expression.
+            StepKind::In => true,
+            // Run until the current function returns to its caller.
+            StepKind::Out => call_stack_depth < self.initial_call_stack_depth,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceEvent {
+    ExpressionEvaluated,
+    CallStarted,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseReason {
+    Breakpoint(usize),
+    Step,
+}
@@ -0,0 +1,174 @@
+use super::{
+    breakpoints::Breakpoints,
+    execution_controller::{ExecutionController, PauseReason, TraceEvent},
+    tracer::TracerState,
+};
+use candy_frontend::hir::Id;
+use candy_vm::{heap::InlineObject, tracer::stack_trace::Call};
+
+/// A recording of everything the [`DebugTracer`](super::tracer::DebugTracer)
+/// saw, which allows us to travel back in time without re-running the program.
+///
+/// Objects referenced by recorded events are kept alive for the rest of the
+/// session. Because objects in Candy are immutable, each past state can be
+/// inspected just like the current one. The only exception is the heap, which
+/// we only have in its current form.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    /// Only set if we traveled back in time.
+    past: Option<Past>,
+}
+#[derive(Debug)]
+struct Entry {
+    event: Event,
+    /// The number of calls on the tracer's call stack before the event.
+    call_stack_depth: usize,
+}
+#[derive(Clone, Debug)]
+pub enum Event {
+    ValueEvaluated { expression: Id, value: InlineObject },
+    CallStarted(Call),
+    CallEnded,
+    TailCall(Call),
+}
+#[derive(Debug)]
+struct Past {
+    /// The number of entries that lead to the shown state.
+    position: usize,
+    state: TracerState,
+}
+
+impl Event {
+    pub fn trace_event(&self) -> Option<(&Id, TraceEvent)> {
+        match self {
+            Self::ValueEvaluated { expression, .. } => {
+                Some((expression, TraceEvent::ExpressionEvaluated))
+            }
+            Self::CallStarted(call) | Self::TailCall(call) => {
+                Some((call.call_site.get(), TraceEvent::CallStarted))
+            }
+            Self::CallEnded => None,
+        }
+    }
+}
+
+impl History {
+    pub fn record(&mut self, event: Event, call_stack_depth: usize) {
+        assert!(
+            self.past.is_none(),
+            "The VM can't run while we're in the past.",
+        );
+        self.entries.push(Entry {
+            event,
+            call_stack_depth,
+        });
+    }
+
+    #[must_use]
+    pub fn past_state(&self) -> Option<&TracerState> {
+        self.past.as_ref().map(|it| &it.state)
+    }
+    fn position(&self) -> usize {
+        self.past
+            .as_ref()
+            .map_or(self.entries.len(), |it| it.position)
+    }
+
+    /// Travels back to the previous expression in the current function or one
+    /// of its callers, skipping over calls.
+    ///
+    /// If there's no such expression, we end up at the start of the program.
+    pub fn step_back(&mut self, controller: &mut ExecutionController, call_stack_depth: usize) {
+        let target = self.entries[..self.position().saturating_sub(1)]
+            .iter()
+            .rposition(|entry| {
+                matches!(entry.event, Event::ValueEvaluated { .. })
+                    && entry.call_stack_depth <= call_stack_depth
+            });
+        self.travel_to(target.map_or(0, |it| it + 1), &mut controller.breakpoints);
+    }
+
+    /// Travels back to the previous breakpoint hit and returns its ID.
+    ///
+    /// If no breakpoint was hit before, we end up at the start of the program.
+    pub fn reverse_continue(&mut self, controller: &mut ExecutionController) -> Option<usize> {
+        let breakpoints = &controller.breakpoints;
+        let entries = &self.entries[..self.position().saturating_sub(1)];
+        let Some((mut index, breakpoint)) =
+            entries.iter().enumerate().rev().find_map(|(index, entry)| {
+                let (id, _) = entry.event.trace_event()?;
+                breakpoints.get(id).map(|breakpoint| (index, breakpoint))
+            })
+        else {
+            self.travel_to(0, &mut controller.breakpoints);
+            return None;
+        };
+
+        // Running forward, we stop at the first expression of a breakpoint's
+        // line, so we do the same here.
+        let depth = entries[index].call_stack_depth;
+        for (previous_index, entry) in entries[..index].iter().enumerate().rev() {
+            if entry.call_stack_depth > depth {
+                continue;
+            }
+            let is_same_breakpoint = entry.call_stack_depth == depth
+                && entry
+                    .event
+                    .trace_event()
+                    .and_then(|(id, _)| breakpoints.get(id))
+                    == Some(breakpoint);
+            if !is_same_breakpoint {
+                break;
+            }
+            index = previous_index;
+        }
+
+        self.travel_to(index + 1, &mut controller.breakpoints);
+        Some(breakpoint)
+    }
+
+    /// Replays the recorded events after the current position until the
+    /// controller wants to pause.
+    ///
+    /// If we reach the end of the history before that, we're back in the
+    /// present and the VM has to continue running.
+    pub fn replay(&mut self, controller: &mut ExecutionController) -> Option<PauseReason> {
+        let Past {
+            position,
+            mut state,
+        } = self.past.take()?;
+        for (index, entry) in self.entries.iter().enumerate().skip(position) {
+            if let Some((id, event)) = entry.event.trace_event() {
+                controller.on_event(id, event, entry.call_stack_depth);
+            }
+            state.apply(&entry.event);
+
+            if let Some(reason) = controller.take_pause_reason() {
+                self.past = Some(Past {
+                    position: index + 1,
+                    state,
+                });
+                return Some(reason);
+            }
+        }
+        None
+    }
+
+    fn travel_to(&mut self, position: usize, breakpoints: &mut Breakpoints) {
+        let mut state = TracerState::default();
+        for entry in &self.entries[..position] {
+            state.apply(&entry.event);
+        }
+        self.past = Some(Past { position, state });
+
+        // Continuing from here should skip the rest of the breakpoint's line
+        // we're on, just like after hitting it while running forward.
+        breakpoints.forget_last_stop();
+        if let Some(entry) = position.checked_sub(1).map(|it| &self.entries[it])
+            && let Some((id, _)) = entry.event.trace_event()
+        {
+            breakpoints.on_expression(id, entry.call_stack_depth);
+        }
+    }
+}
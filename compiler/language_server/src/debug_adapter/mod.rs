@@ -12,6 +12,9 @@ use tokio::sync::{mpsc, RwLock};
 use tower_lsp::{jsonrpc, Client};
 use tracing::error;
 
+mod breakpoints;
+mod execution_controller;
+mod history;
 mod paused;
 mod session;
 mod tracer;
@@ -0,0 +1,102 @@
+use super::{stack_trace::StackFrameKey, PausedState};
+use crate::database::Database;
+use candy_frontend::{hir::HirDb, utils::AdjustCasingOfFirstLetter};
+use candy_vm::heap::{Data, InlineObject};
+use dap::{requests::EvaluateArguments, responses::EvaluateResponse};
+
+impl PausedState {
+    /// Evaluates expressions of the form `name` or `name.field.0`.
+    ///
+    /// The name is looked up in the locals and arguments of the given stack
+    /// frame. Struct fields are accessed by their (tag) key and list items by
+    /// their index.
+    pub fn evaluate(
+        &mut self,
+        db: &Database,
+        args: &EvaluateArguments,
+        supports_variable_type: bool,
+    ) -> Result<EvaluateResponse, &'static str> {
+        let stack_frame_key = match args.frame_id {
+            Some(frame_id) => self
+                .stack_frame_ids
+                .id_to_key(frame_id.try_into().map_err(|_| "invalid-frame-id")?)
+                .clone(),
+            None => StackFrameKey::topmost(self.vm_ref()),
+        };
+
+        let mut segments = args.expression.trim().split('.');
+        let name = segments.next().unwrap();
+        let mut value = self
+            .look_up(db, &stack_frame_key, name)
+            .ok_or("unknown-variable")?;
+        for segment in segments {
+            value = Self::access(value, segment).ok_or("unknown-field")?;
+        }
+
+        let variable = self.create_variable(args.expression.clone(), value, supports_variable_type);
+        Ok(EvaluateResponse {
+            result: variable.value,
+            type_field: variable.type_field,
+            presentation_hint: variable.presentation_hint,
+            variables_reference: variable.variables_reference,
+            named_variables: variable.named_variables,
+            indexed_variables: variable.indexed_variables,
+            memory_reference: variable.memory_reference,
+        })
+    }
+
+    fn look_up(
+        &self,
+        db: &Database,
+        stack_frame_key: &StackFrameKey,
+        name: &str,
+    ) -> Option<InlineObject> {
+        let vm = self.vm_ref();
+
+        // Later locals shadow earlier ones.
+        let local = stack_frame_key
+            .get_locals(vm)
+            .iter()
+            .rev()
+            .find(|(id, _)| {
+                db.containing_body_of(id.clone())
+                    .identifiers
+                    .get(id)
+                    .is_some_and(|it| it == name)
+            })
+            .map(|(_, value)| *value);
+        if local.is_some() {
+            return local;
+        }
+
+        let call = &stack_frame_key.get(vm)?.call;
+        let Data::Function(function) = Data::from(call.callee) else {
+            return None;
+        };
+        self.parameter_names(db, function)
+            .into_iter()
+            .zip(call.arguments.iter().copied())
+            .find(|(parameter, _)| parameter == name)
+            .map(|(_, argument)| argument)
+    }
+
+    fn access(value: InlineObject, segment: &str) -> Option<InlineObject> {
+        match Data::from(value) {
+            Data::Struct(struct_) => {
+                let key = segment.uppercase_first_letter();
+                struct_
+                    .iter()
+                    .find(|(_, key_object, _)| {
+                        matches!(
+                            Data::from(*key_object),
+                            Data::Tag(tag) if !tag.has_value() && tag.symbol().get() == key,
+                        )
+                    })
+                    .map(|(_, _, value)| value)
+            }
+            Data::List(list) => list.items().get(segment.parse::<usize>().ok()?).copied(),
+            Data::Tag(tag) if segment == "value" => tag.value(),
+            _ => None,
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use self::{stack_trace::StackFrameKey, utils::IdMapping, variable::VariablesKey};
-use super::DebugVm;
+use super::{tracer::DebugTracer, DebugVm};
 use candy_vm::heap::Heap;
 
+mod evaluate;
 mod memory;
 mod scope;
 mod stack_trace;
@@ -22,6 +23,13 @@ impl PausedState {
         }
     }
 
+    /// Forgets the IDs of stack frames and variables handed out to the client,
+    /// e.g., because we traveled in time and they now refer to something else.
+    pub fn reset_ids(&mut self) {
+        self.stack_frame_ids = IdMapping::default();
+        self.variables_ids = IdMapping::default();
+    }
+
     #[must_use]
     pub fn heap_ref(&self) -> &Heap {
         &self.vm.as_ref().unwrap().heap
@@ -30,6 +38,9 @@ impl PausedState {
     pub fn vm_ref(&self) -> &DebugVm {
         &self.vm.as_ref().unwrap().vm
     }
+    pub fn tracer_mut(&mut self) -> &mut DebugTracer {
+        self.vm.as_mut().unwrap().vm.tracer_mut()
+    }
 }
 pub struct PausedVm {
     pub heap: Heap,
@@ -7,7 +7,9 @@ use crate::{
     },
     utils::{module_to_url, LspPositionConversion},
 };
-use candy_frontend::{ast_to_hir::AstToHir, hir::Id, utils::AdjustCasingOfFirstLetter};
+use candy_frontend::{
+    ast_to_hir::AstToHir, hir::Id, module::Module, utils::AdjustCasingOfFirstLetter,
+};
 use candy_vm::{
     byte_code::ByteCode,
     heap::{Data, InlineObject},
@@ -19,6 +21,7 @@ use dap::{
     types::{PresentationHint, Source, StackFramePresentationhint},
 };
 use itertools::Itertools;
+use lsp_types::Range;
 use std::{borrow::Borrow, hash::Hash};
 
 impl PausedState {
@@ -28,26 +31,34 @@ impl PausedState {
         start_at_1_config: StartAt1Config,
         args: &StackTraceArguments,
     ) -> StackTraceResponse {
-        let tracer = self.vm.as_ref().unwrap().vm.tracer();
+        let vm = &self.vm.as_ref().unwrap().vm;
+        let tracer = vm.tracer().state();
+        let frames = tracer.call_stack.iter().flatten().collect_vec();
+
+        // Each frame is currently at the call site of the frame above it. The
+        // topmost frame is at the expression that was evaluated last.
+        let position_in_frame = |index: usize| {
+            frames
+                .get(index)
+                .map(|frame| frame.call.call_site.get())
+                .or(tracer.current_expression.as_ref())
+        };
 
         let start_frame = args.start_frame.unwrap_or_default();
         let levels = args
             .levels
             .and_then(|it| if it == 0 { None } else { Some(it) })
             .unwrap_or(usize::MAX);
-        let call_stack = &tracer.call_stack[..tracer.call_stack.len() - start_frame];
-        let total_frames = tracer.call_stack.len() + 1;
+        let total_frames = frames.len() + 1;
 
-        let mut stack_frames = Vec::with_capacity((1 + call_stack.len()).min(levels));
+        let mut stack_frames = Vec::with_capacity(total_frames.min(levels));
         stack_frames.extend(
-            call_stack
-                .iter()
-                .flatten()
-                .collect_vec()
+            frames
                 .iter()
                 .enumerate()
                 .rev()
                 .skip(start_frame)
+                .take(levels)
                 .map(|(index, frame)| {
                     let id = self
                         .stack_frame_ids
@@ -58,23 +69,27 @@ impl PausedState {
                         start_at_1_config,
                         id,
                         frame,
-                        self.vm.as_ref().unwrap().vm.byte_code(),
+                        position_in_frame(index + 1),
+                        vm.byte_code(),
                     )
                 }),
         );
 
-        if stack_frames.len() < levels {
+        if stack_frames.len() < levels && start_frame < total_frames {
+            let position = position_in_frame(0);
+            let source = position.map(|it| Self::source(db, &it.module, vm.byte_code()));
+            let range = position.and_then(|it| Self::range(db, start_at_1_config, it));
             stack_frames.push(dap::types::StackFrame {
                 id: self
                     .stack_frame_ids
                     .key_to_id(StackFrameKey { index: 0 })
                     .get(),
                 name: "Spawn".to_string(),
-                source: None,
-                line: 1,
-                column: 1,
-                end_line: None,
-                end_column: None,
+                source,
+                line: range.map_or(1, |it| it.start.line as usize),
+                column: range.map_or(1, |it| it.start.character as usize),
+                end_line: range.map(|it| it.end.line as usize),
+                end_column: range.map(|it| it.end.character as usize),
                 can_restart: Some(false),
                 instruction_pointer_reference: None,
                 module_id: None,
@@ -93,34 +108,23 @@ impl PausedState {
         start_at_1_config: StartAt1Config,
         id: usize,
         frame: &StackFrame,
+        position: Option<&Id>,
         byte_code: &ByteCode,
     ) -> dap::types::StackFrame {
         let (name, source, range) = match Data::from(frame.call.callee) {
             Data::Function(function) => {
-                let functions = byte_code.functions_behind(function.body());
+                let functions = byte_code.origins.functions_behind(function.body());
                 assert_eq!(functions.len(), 1);
                 let function = functions.iter().next().unwrap();
 
-                let source = Source {
-                    name: Some(ToString::to_string(&function.module)),
-                    path: Some(ToString::to_string(
-                        &module_to_url(&function.module, &db.packages_path).unwrap(),
-                    )),
-                    source_reference: None,
-                    presentation_hint: if byte_code.module.package() == function.module.package() {
-                        PresentationHint::Emphasize
-                    } else {
-                        PresentationHint::Normal
-                    },
-                    origin: None,
-                    sources: None,
-                    adapter_data: None,
-                    checksums: None,
-                };
-                let range = db.hir_id_to_span(function).unwrap();
-                let range = db.range_to_lsp_range(function.module.clone(), range);
-                let range = start_at_1_config.range_to_dap(range);
-                (function.function_name(), Some(source), Some(range))
+                // If we don't know where inside the function we are, we fall
+                // back to the function's definition.
+                let position = position
+                    .filter(|it| it.module == function.module)
+                    .unwrap_or(function);
+                let source = Self::source(db, &position.module, byte_code);
+                let range = Self::range(db, start_at_1_config, position);
+                (function.function_name(), Some(source), range)
             }
             Data::Builtin(builtin) => {
                 let name = format!(
@@ -145,6 +149,29 @@ impl PausedState {
             presentation_hint: Some(StackFramePresentationhint::Normal),
         }
     }
+    fn source(db: &Database, module: &Module, byte_code: &ByteCode) -> Source {
+        Source {
+            name: Some(ToString::to_string(module)),
+            path: Some(ToString::to_string(
+                &module_to_url(module, &db.packages_path).unwrap(),
+            )),
+            source_reference: None,
+            presentation_hint: if byte_code.module.package() == module.package() {
+                PresentationHint::Emphasize
+            } else {
+                PresentationHint::Normal
+            },
+            origin: None,
+            sources: None,
+            adapter_data: None,
+            checksums: None,
+        }
+    }
+    fn range(db: &Database, start_at_1_config: StartAt1Config, id: &Id) -> Option<Range> {
+        let range = db.hir_id_to_span(id)?;
+        let range = db.range_to_lsp_range(id.module.clone(), range);
+        Some(start_at_1_config.range_to_dap(range))
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -153,6 +180,12 @@ pub struct StackFrameKey {
     index: usize,
 }
 impl StackFrameKey {
+    pub fn topmost<B: Borrow<ByteCode>>(vm: &Vm<B, DebugTracer>) -> Self {
+        Self {
+            index: vm.tracer().state().call_stack.iter().map(Vec::len).sum(),
+        }
+    }
+
     pub fn get<'a, B: Borrow<ByteCode>>(
         &self,
         vm: &'a Vm<B, DebugTracer>,
@@ -163,6 +196,7 @@ impl StackFrameKey {
 
         Some(
             vm.tracer()
+                .state()
                 .call_stack
                 .iter()
                 .flatten()
@@ -175,7 +209,7 @@ impl StackFrameKey {
         vm: &'a Vm<B, DebugTracer>,
     ) -> &'a Vec<(Id, InlineObject)> {
         if self.index == 0 {
-            &vm.tracer().root_locals
+            &vm.tracer().state().root_locals
         } else {
             &self.get(vm).unwrap().locals
         }
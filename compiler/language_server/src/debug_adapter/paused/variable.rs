@@ -1,7 +1,12 @@
 use super::{memory::MemoryReference, stack_trace::StackFrameKey, PausedState};
 use crate::database::Database;
-use candy_frontend::hir::{self, Expression, HirDb};
-use candy_vm::heap::{Data, DataDiscriminants, InlineObject, ObjectInHeap, Tag};
+use candy_frontend::{
+    format::{MaxLength, Precedence},
+    hir::{self, Expression, HirDb},
+};
+use candy_vm::heap::{
+    Data, DataDiscriminants, Function, InlineObject, ObjectInHeap, Tag, ToDebugText,
+};
 use dap::{
     requests::VariablesArguments,
     responses::VariablesResponse,
@@ -46,32 +51,15 @@ impl PausedState {
                 match Data::from(call.callee) {
                     Data::Function(function) => {
                         if should_include_named {
-                            let functions = self
-                                .vm
-                                .as_ref()
-                                .unwrap()
-                                .vm
-                                .byte_code()
-                                .functions_behind(function.body());
-                            assert_eq!(functions.len(), 1);
-                            let function: &hir::Id = functions.iter().next().unwrap();
-
-                            let Expression::Function(hir::Function { parameters, .. }) =
-                                db.find_expression(function.clone()).unwrap()
-                            else {
-                                panic!("Function's HIR is not a function: {function}");
-                            };
-
                             variables.extend(
-                                parameters
-                                    .iter()
-                                    .map(|it| it.keys.last_as_str().unwrap())
+                                self.parameter_names(db, function)
+                                    .into_iter()
                                     .zip_eq(call.arguments.clone())
                                     .skip(start)
                                     .take(count)
                                     .map(|(parameter, argument)| {
                                         self.create_variable(
-                                            parameter.to_string(),
+                                            parameter,
                                             argument,
                                             supports_variable_type,
                                         )
@@ -241,6 +229,26 @@ impl PausedState {
         VariablesResponse { variables }
     }
 
+    pub(super) fn parameter_names(&self, db: &Database, function: Function) -> Vec<String> {
+        let functions = self
+            .vm_ref()
+            .byte_code()
+            .origins
+            .functions_behind(function.body());
+        assert_eq!(functions.len(), 1);
+        let function: &hir::Id = functions.iter().next().unwrap();
+
+        let Expression::Function(hir::Function { parameters, .. }) =
+            db.find_expression(function.clone()).unwrap()
+        else {
+            panic!("Function's HIR is not a function: {function}");
+        };
+        parameters
+            .iter()
+            .map(|it| it.keys.last_as_str().unwrap().to_string())
+            .collect()
+    }
+
     fn create_length_variable(length: usize, supports_variable_type: bool) -> Variable {
         Variable {
             name: "<length>".to_string(),
@@ -254,7 +262,7 @@ impl PausedState {
             memory_reference: None,
         }
     }
-    fn create_variable(
+    pub(super) fn create_variable(
         &mut self,
         name: String,
         object: InlineObject,
@@ -280,7 +288,7 @@ impl PausedState {
 
         Variable {
             name,
-            value: object.to_string(),
+            value: object.to_debug_text(Precedence::Low, MaxLength::Limited(100)),
             type_field: Self::type_field_for(data.into(), supports_variable_type),
             presentation_hint: Some(Self::presentation_hint_for(data.into())),
             evaluate_name: None,
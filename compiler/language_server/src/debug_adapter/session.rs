@@ -1,35 +1,39 @@
 use super::{
+    breakpoints::hir_ids_on_line,
+    execution_controller::{PauseReason, StepKind},
     paused::{PausedState, PausedVm},
     tracer::DebugTracer,
     DebugVm, ServerToClient, ServerToClientMessage, SessionId,
 };
 use crate::database::Database;
 use candy_frontend::{
+    hir,
     hir_to_mir::ExecutionTarget,
     module::{Module, ModuleKind, PackagesPath},
     tracing::CallTracingMode,
-    TracingConfig, TracingMode,
+    OptimizationConfig, TracingConfig, TracingMode,
 };
 use candy_vm::{
-    byte_code::Instruction,
     environment::StateAfterRunWithoutHandles,
     heap::{Heap, Struct},
+    launcher::Launcher,
     lir_to_byte_code::compile_byte_code,
-    Vm,
+    VmFinished,
 };
 use dap::{
-    events::StoppedEventBody,
+    events::{ExitedEventBody, StoppedEventBody, ThreadEventBody},
     prelude::EventBody,
-    requests::{Command, InitializeArguments, Request},
+    requests::{Command, InitializeArguments, Request, SetBreakpointsArguments},
     responses::{
-        Response, ResponseBody, ResponseMessage, SetExceptionBreakpointsResponse, ThreadsResponse,
+        ContinueResponse, Response, ResponseBody, ResponseMessage, SetBreakpointsResponse,
+        SetExceptionBreakpointsResponse, ThreadsResponse,
     },
-    types::{Capabilities, StoppedEventReason, Thread},
+    types::{Breakpoint, Capabilities, StoppedEventReason, Thread, ThreadEventReason},
 };
 use lsp_types::{Position, Range};
 use rustc_hash::FxHashMap;
 use std::{mem, num::NonZeroUsize, path::PathBuf, rc::Rc};
-use tokio::sync::mpsc;
+use tokio::sync::mpsc::{self, error::TryRecvError};
 use tower_lsp::Client;
 use tracing::error;
 
@@ -47,8 +51,28 @@ pub async fn run_debug_session(
         client,
         db,
         state: State::Initial,
+        breakpoints: FxHashMap::default(),
+        next_breakpoint_id: 0,
     };
-    while let Some(request) = client_to_server.recv().await {
+    loop {
+        // While the program is running, we alternate between running a batch
+        // of instructions and handling the requests that arrived in between.
+        let request = if session.state.is_running() {
+            match client_to_server.try_recv() {
+                Ok(request) => request,
+                Err(TryRecvError::Empty) => {
+                    session.run_batch().await;
+                    continue;
+                }
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            let Some(request) = client_to_server.recv().await else {
+                break;
+            };
+            request
+        };
+
         let seq = request.seq;
         match session.handle(request).await {
             Ok(()) => {}
@@ -61,11 +85,21 @@ pub async fn run_debug_session(
     }
 }
 
+/// The number of instructions to run before checking for new requests.
+const BATCH_SIZE: usize = 10_000;
+
+/// We only run a single VM, so there's only one thread.
+const THREAD_ID: usize = 0;
+
 struct DebugSession {
     session_id: SessionId,
     client: Client,
     db: Database,
     state: State,
+    /// HIR ID → DAP breakpoint ID, grouped by module so that a
+    /// `setBreakpoints` request can replace all breakpoints of one source
+    breakpoints: FxHashMap<Module, FxHashMap<hir::Id, usize>>,
+    next_breakpoint_id: usize,
 }
 
 // `Launched` is much larger than `Initial` and `Initialized`, but it's also the
@@ -81,19 +115,31 @@ enum State {
 }
 
 enum ExecutionState {
-    #[allow(dead_code)] // WIP
-    Running(DebugVm),
+    Running(PausedVm),
     Paused(PausedState),
 }
 
 impl DebugSession {
     pub async fn handle(&mut self, request: Request) -> Result<(), &'static str> {
         match request.command {
-            Command::Attach(_) => todo!(),
+            // There are no VMs outside of this session that we could attach
+            // to. To run a program without pausing on entry, clients can
+            // launch it with `stopOnEntry: false`.
+            Command::Attach(_) => Err("attach-not-supported"),
             Command::BreakpointLocations(_) => todo!(),
             Command::Completions(_) => todo!(),
             Command::ConfigurationDone => todo!(),
-            Command::Continue(_) => todo!(),
+            Command::Continue(_) => {
+                let state = self.state.require_paused_mut()?;
+                state.tracer_mut().controller.reset();
+                self.resume(
+                    request.seq,
+                    ResponseBody::Continue(ContinueResponse {
+                        all_threads_continued: Some(true),
+                    }),
+                )
+                .await
+            }
             Command::DataBreakpointInfo(_) => todo!(),
             Command::Disassamble(_) => todo!(),
             Command::Disconnect(_) => {
@@ -113,7 +159,21 @@ impl DebugSession {
                     .await;
                 Ok(())
             }
-            Command::Evaluate(_) => todo!(),
+            Command::Evaluate(args) => {
+                let supports_variable_type = self
+                    .state
+                    .require_initialized()?
+                    .supports_variable_type
+                    .unwrap_or_default();
+                let response = self.state.require_paused_mut()?.evaluate(
+                    &self.db,
+                    &args,
+                    supports_variable_type,
+                )?;
+                self.send_response_ok(request.seq, ResponseBody::Evaluate(response))
+                    .await;
+                Ok(())
+            }
             Command::ExceptionInfo(_) => todo!(),
             Command::Goto(_) => todo!(),
             Command::GotoTargets(_) => todo!(),
@@ -127,9 +187,9 @@ impl DebugSession {
                     supports_function_breakpoints: None,
                     supports_conditional_breakpoints: None,
                     supports_hit_conditional_breakpoints: None,
-                    supports_evaluate_for_hovers: None,
+                    supports_evaluate_for_hovers: Some(true),
                     exception_breakpoint_filters: None,
-                    supports_step_back: None,
+                    supports_step_back: Some(true),
                     supports_set_variable: None,
                     supports_restart_frame: None,
                     supports_goto_targets_request: None,
@@ -150,7 +210,7 @@ impl DebugSession {
                     supports_log_points: None,
                     supports_terminate_threads_request: None,
                     supports_set_expression: None,
-                    supports_terminate_request: None,
+                    supports_terminate_request: Some(true),
                     supports_data_breakpoints: None,
                     supports_read_memory_request: Some(true),
                     supports_write_memory_request: None,
@@ -170,73 +230,38 @@ impl DebugSession {
                 Ok(())
             }
             Command::Launch(args) => {
+                self.start(
+                    request.seq,
+                    args.program,
+                    args.stop_on_entry.unwrap_or(true),
+                )
+                .await
+            }
+            Command::LoadedSources => todo!(),
+            Command::Modules(_) => todo!(),
+            Command::Next(_) => self.step(request.seq, StepKind::Next).await,
+            Command::Pause(_) => {
                 let state = mem::replace(&mut self.state, State::Initial);
-                let initialize_arguments = match state {
-                    State::Initial => {
-                        self.state = state;
-                        return Err("not-initialized");
-                    }
-                    State::Initialized(initialize_arguments) => initialize_arguments,
-                    State::Launched { .. } => {
-                        self.state = state;
-                        return Err("already-launched");
-                    }
+                let State::Launched {
+                    initialize_arguments,
+                    execution_state: ExecutionState::Running(PausedVm { heap, mut vm }),
+                } = state
+                else {
+                    self.state = state;
+                    return Err("not-running");
                 };
-
-                let module = self.parse_module(args.program)?;
-
-                let tracing = TracingConfig {
-                    register_fuzzables: TracingMode::Off,
-                    calls: CallTracingMode::All,
-                    evaluated_expressions: TracingMode::All,
+                vm.tracer_mut().controller.reset();
+                self.state = State::Launched {
+                    initialize_arguments,
+                    execution_state: ExecutionState::Paused(PausedState::new(heap, vm)),
                 };
-                let byte_code = compile_byte_code(
-                    &self.db,
-                    ExecutionTarget::MainFunction(module.clone()),
-                    tracing,
-                )
-                .0;
 
-                self.send_response_ok(request.seq, ResponseBody::Launch)
+                self.send_response_ok(request.seq, ResponseBody::Pause)
                     .await;
-
-                let mut heap = Heap::default();
-                let environment = Struct::create(&mut heap, true, &FxHashMap::default());
-                let tracer = DebugTracer::default();
-                let vm = Vm::for_main_function(Rc::new(byte_code), &mut heap, environment, tracer);
-
-                // TODO: remove when we support pause and continue
-                let vm = match vm.run_n_without_handles(&mut heap, 10000) {
-                    StateAfterRunWithoutHandles::Running(vm) => Some(vm),
-                    StateAfterRunWithoutHandles::Finished(_) => None,
-                };
-
-                if let Some(vm) = vm {
-                    self.state = State::Launched {
-                        initialize_arguments,
-                        execution_state: ExecutionState::Paused(PausedState::new(heap, vm)),
-                    };
-
-                    self.send(EventBody::Stopped(StoppedEventBody {
-                        reason: StoppedEventReason::Entry,
-                        description: Some("Paused on program start".to_string()),
-                        thread_id: Some(0),
-                        preserve_focus_hint: Some(false),
-                        text: None,
-                        all_threads_stopped: Some(true),
-                        hit_breakpoint_ids: Some(vec![]),
-                    }))
+                self.send_stopped(StoppedEventReason::Pause, None, vec![])
                     .await;
-                } else {
-                    self.send(EventBody::Terminated(None)).await;
-                }
-
                 Ok(())
             }
-            Command::LoadedSources => todo!(),
-            Command::Modules(_) => todo!(),
-            Command::Next(_) => self.step(request.seq, StepKind::Next).await,
-            Command::Pause(_) => todo!(),
             Command::ReadMemory(args) => {
                 let state = self.state.require_paused_mut()?;
                 let response = state.read_memory(&args)?;
@@ -246,14 +271,35 @@ impl DebugSession {
             }
             Command::Restart(_) => todo!(),
             Command::RestartFrame(_) => todo!(),
-            Command::ReverseContinue(_) => todo!(),
+            Command::ReverseContinue(_) => {
+                let state = self.state.require_paused_mut()?;
+                let tracer = state.tracer_mut();
+                tracer.controller.reset();
+                let breakpoint = tracer.history.reverse_continue(&mut tracer.controller);
+                state.reset_ids();
+
+                self.send_response_ok(request.seq, ResponseBody::ReverseContinue)
+                    .await;
+                match breakpoint {
+                    Some(breakpoint) => {
+                        self.send_paused(PauseReason::Breakpoint(breakpoint)).await;
+                    }
+                    None => self.send_stopped_at_program_start().await,
+                }
+                Ok(())
+            }
             Command::Scopes(args) => {
                 let scopes = self.state.require_paused_mut()?.scopes(&args);
                 self.send_response_ok(request.seq, ResponseBody::Scopes(scopes))
                     .await;
                 Ok(())
             }
-            Command::SetBreakpoints(_) => todo!(),
+            Command::SetBreakpoints(args) => {
+                let response = self.set_breakpoints(args)?;
+                self.send_response_ok(request.seq, ResponseBody::SetBreakpoints(response))
+                    .await;
+                Ok(())
+            }
             Command::SetDataBreakpoints(_) => todo!(),
             Command::SetExceptionBreakpoints(_) => {
                 self.send_response_ok(
@@ -278,17 +324,54 @@ impl DebugSession {
                     .await;
                 Ok(())
             }
-            Command::StepBack(_) => todo!(),
+            Command::StepBack(_) => {
+                // TODO: honor `args.granularity`
+                let state = self.state.require_paused_mut()?;
+                let tracer = state.tracer_mut();
+                tracer.controller.reset();
+                let call_stack_depth = tracer.state().call_stack.len();
+                tracer
+                    .history
+                    .step_back(&mut tracer.controller, call_stack_depth);
+                state.reset_ids();
+
+                self.send_response_ok(request.seq, ResponseBody::StepBack)
+                    .await;
+                self.send_paused(PauseReason::Step).await;
+                Ok(())
+            }
             Command::StepIn(_) => self.step(request.seq, StepKind::In).await,
             Command::StepInTargets(_) => todo!(),
             Command::StepOut(_) => self.step(request.seq, StepKind::Out).await,
-            Command::Terminate(_) => todo!(),
+            Command::Terminate(_) => {
+                let initialize_arguments = self.state.require_initialized()?.clone();
+                if !matches!(self.state, State::Launched { .. }) {
+                    return Err("not-launched");
+                }
+                self.state = State::Initialized(initialize_arguments);
+
+                self.send_response_ok(request.seq, ResponseBody::Terminate)
+                    .await;
+                self.send_terminated().await;
+                Ok(())
+            }
             Command::TerminateThreads(_) => todo!(),
             Command::Threads => {
-                let threads = vec![Thread {
-                    id: 0,
-                    name: "Candy program".to_string(),
-                }];
+                let threads = match &self.state {
+                    State::Initial | State::Initialized(_) => vec![],
+                    State::Launched {
+                        execution_state, ..
+                    } => {
+                        let status = match execution_state {
+                            ExecutionState::Running(_) => "running",
+                            ExecutionState::Paused(_) => "paused",
+                        };
+                        vec![Thread {
+                            id: THREAD_ID,
+                            name: format!("Candy program ({status})"),
+                        }]
+                    }
+                };
                 self.send_response_ok(
                     request.seq,
                     ResponseBody::Threads(ThreadsResponse { threads }),
@@ -315,75 +398,235 @@ impl DebugSession {
             Command::Cancel(_) => todo!(),
         }
     }
+    /// Starts a step. The program then runs like after a continue request
+    /// until the [`ExecutionController`](super::execution_controller::ExecutionController)
+    /// decides that the step is finished.
     async fn step(
         &mut self,
         request_seq: NonZeroUsize,
         kind: StepKind,
     ) -> Result<(), &'static str> {
-        self.state.require_paused()?;
+        // TODO: honor `args.granularity`
+        let state = self.state.require_paused_mut()?;
+        let tracer = state.tracer_mut();
+        tracer.controller.reset();
+        let call_stack_depth = tracer.state().call_stack.len();
+        tracer.controller.step(kind, call_stack_depth);
+
         let response_body = match kind {
             StepKind::Next => ResponseBody::Next,
             StepKind::In => ResponseBody::StepIn,
             StepKind::Out => ResponseBody::StepOut,
         };
-        self.send_response_ok(request_seq, response_body).await;
+        self.resume(request_seq, response_body).await
+    }
+    /// Resumes the paused program after the controller was told when to pause
+    /// again.
+    ///
+    /// If we traveled back in time, we first replay the recorded history, which
+    /// might already make us pause again. Only afterwards, the VM continues
+    /// running.
+    async fn resume(
+        &mut self,
+        request_seq: NonZeroUsize,
+        response_body: ResponseBody,
+    ) -> Result<(), &'static str> {
+        let state = self.state.require_paused_mut()?;
+        let tracer = state.tracer_mut();
+        let pause_reason = tracer.history.replay(&mut tracer.controller);
+
+        if let Some(pause_reason) = pause_reason {
+            state.reset_ids();
+            self.send_response_ok(request_seq, response_body).await;
+            self.send_paused(pause_reason).await;
+        } else {
+            let vm = state.vm.take().unwrap();
+            self.state.set_execution_state(ExecutionState::Running(vm));
+            self.send_response_ok(request_seq, response_body).await;
+        }
+        Ok(())
+    }
+
+    /// Starts the program for a launch request.
+    ///
+    /// By default, we stop on entry so that the user can step through the
+    /// program from the beginning. Otherwise, the program just runs until the
+    /// user pauses it.
+    async fn start(
+        &mut self,
+        request_seq: NonZeroUsize,
+        program: Option<String>,
+        stop_on_entry: bool,
+    ) -> Result<(), &'static str> {
+        match self.state {
+            State::Initial => return Err("not-initialized"),
+            State::Initialized(_) => {}
+            State::Launched { .. } => return Err("already-launched"),
+        }
+
+        let module = self.parse_module(program)?;
+
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::Off,
+            calls: CallTracingMode::All,
+            evaluated_expressions: TracingMode::All,
+            debug_assertions: true,
+            strip: false,
+        };
+        // Fewer optimizations keep the executed code closer to the source.
+        let byte_code = compile_byte_code(
+            &self.db,
+            ExecutionTarget::MainFunction(module),
+            tracing,
+            OptimizationConfig::debug(),
+        )
+        .0;
 
-        let state = self.state.require_paused_mut().unwrap();
+        self.send_response_ok(request_seq, ResponseBody::Launch)
+            .await;
 
-        // TODO: honor `args.granularity`
-        let PausedVm { mut heap, mut vm } = state.vm.take().unwrap();
-        let initial_stack_size = vm.call_stack().len();
-        let vm_after_stepping = loop {
-            let Some(instruction_pointer) = vm.next_instruction() else {
-                break None; // The VM finished executing anyways.
-            };
-            let is_trace_instruction = matches!(
-                vm.byte_code().instructions[*instruction_pointer],
-                Instruction::TraceCallEnds { .. } | Instruction::TraceExpressionEvaluated,
-            );
+        let mut heap = Heap::default();
+        let environment = Struct::create(&mut heap, true, &FxHashMap::default());
+        let mut tracer = DebugTracer::default();
+        tracer.controller.breakpoints.set(self.breakpoint_ids());
+        let vm = Launcher::new(Rc::new(byte_code), tracer).main_function(&mut heap, environment);
+
+        let State::Initialized(initialize_arguments) =
+            mem::replace(&mut self.state, State::Initial)
+        else {
+            unreachable!();
+        };
+        let execution_state = if stop_on_entry {
+            ExecutionState::Paused(PausedState::new(heap, vm))
+        } else {
+            ExecutionState::Running(PausedVm::new(heap, vm))
+        };
+        self.state = State::Launched {
+            initialize_arguments,
+            execution_state,
+        };
 
+        self.send(EventBody::Thread(ThreadEventBody {
+            reason: ThreadEventReason::Started,
+            thread_id: THREAD_ID,
+        }))
+        .await;
+        if stop_on_entry {
+            self.send_stopped_at_program_start().await;
+        }
+        Ok(())
+    }
+
+    /// Runs the VM for a batch of instructions if the program is running.
+    async fn run_batch(&mut self) {
+        let state = mem::replace(&mut self.state, State::Initial);
+        let State::Launched {
+            initialize_arguments,
+            execution_state: ExecutionState::Running(PausedVm { mut heap, mut vm }),
+        } = state
+        else {
+            self.state = state;
+            return;
+        };
+
+        // We run instructions one by one so that we can stop right after the
+        // instruction that traced an expression we should pause at.
+        let mut instructions_run = 0;
+        let pause_reason = loop {
             match vm.run_without_handles(&mut heap) {
-                StateAfterRunWithoutHandles::Running(new_vm) => {
-                    vm = new_vm;
-                }
-                StateAfterRunWithoutHandles::Finished(_) => break None,
-            };
+                StateAfterRunWithoutHandles::Running(new_vm) => vm = new_vm,
+                StateAfterRunWithoutHandles::Finished(VmFinished { result, .. }) => {
+                    self.state = State::Initialized(initialize_arguments);
 
-            if is_trace_instruction {
-                continue; // Doesn't count.
+                    let exit_code = match result {
+                        Ok(_) => 0,
+                        Err(panic) => {
+                            error!("The program panicked: {}", panic.reason);
+                            1
+                        }
+                    };
+                    self.send(EventBody::Exited(ExitedEventBody { exit_code }))
+                        .await;
+                    self.send_terminated().await;
+                    return;
+                }
             }
+            instructions_run += 1;
 
-            let did_step = match kind {
-                StepKind::Next => vm.call_stack().len() <= initial_stack_size,
-                StepKind::In => true,
-                StepKind::Out => vm.call_stack().len() < initial_stack_size,
-            };
-            if did_step {
-                break Some(vm);
+            let pause_reason = vm.tracer_mut().controller.take_pause_reason();
+            if pause_reason.is_some() || instructions_run >= BATCH_SIZE {
+                break pause_reason;
             }
         };
 
-        if let Some(vm) = vm_after_stepping {
-            state.vm = Some(PausedVm::new(heap, vm));
-
-            self.send(EventBody::Stopped(StoppedEventBody {
-                reason: StoppedEventReason::Step,
-                description: None,
-                thread_id: Some(0),
-                preserve_focus_hint: Some(false),
-                text: None,
-                all_threads_stopped: Some(true),
-                hit_breakpoint_ids: Some(vec![]),
-            }))
-            .await;
+        if let Some(pause_reason) = pause_reason {
+            self.state = State::Launched {
+                initialize_arguments,
+                execution_state: ExecutionState::Paused(PausedState::new(heap, vm)),
+            };
+            self.send_paused(pause_reason).await;
         } else {
-            // TODO: Don't stop the debugging session just because the Candy VM
-            // finished. In case of panics, it's very useful to be able to
-            // inspect what went wrong.
-            self.send(EventBody::Terminated(None)).await;
+            self.state = State::Launched {
+                initialize_arguments,
+                execution_state: ExecutionState::Running(PausedVm::new(heap, vm)),
+            };
+            // Give the runtime a chance to deliver new requests.
+            tokio::task::yield_now().await;
         }
+    }
 
-        Ok(())
+    fn set_breakpoints(
+        &mut self,
+        args: SetBreakpointsArguments,
+    ) -> Result<SetBreakpointsResponse, &'static str> {
+        let start_at_1_config = self.state.require_initialized()?.into();
+        let module = self.parse_module(args.source.path.clone())?;
+
+        let mut ids = FxHashMap::default();
+        let breakpoints = args
+            .breakpoints
+            .unwrap_or_default()
+            .into_iter()
+            .map(|breakpoint| {
+                let id = self.next_breakpoint_id;
+                self.next_breakpoint_id += 1;
+
+                let hir_ids =
+                    hir_ids_on_line(&self.db, start_at_1_config, &module, breakpoint.line);
+                let verified = !hir_ids.is_empty();
+                ids.extend(hir_ids.into_iter().map(|hir_id| (hir_id, id)));
+                Breakpoint {
+                    id: Some(id),
+                    verified,
+                    message: if verified {
+                        None
+                    } else {
+                        Some("There's no expression on this line.".to_string())
+                    },
+                    source: Some(args.source.clone()),
+                    line: Some(breakpoint.line),
+                    column: None,
+                    end_line: None,
+                    end_column: None,
+                    instruction_reference: None,
+                    offset: None,
+                }
+            })
+            .collect();
+        self.breakpoints.insert(module, ids);
+
+        let breakpoint_ids = self.breakpoint_ids();
+        if let Some(vm) = self.state.vm_mut() {
+            vm.tracer_mut().controller.breakpoints.set(breakpoint_ids);
+        }
+
+        Ok(SetBreakpointsResponse { breakpoints })
+    }
+    fn breakpoint_ids(&self) -> FxHashMap<hir::Id, usize> {
+        self.breakpoints
+            .values()
+            .flat_map(|ids| ids.iter().map(|(hir_id, id)| (hir_id.clone(), *id)))
+            .collect()
     }
 
     fn parse_module(&self, path: Option<String>) -> Result<Module, &'static str> {
@@ -402,6 +645,52 @@ impl DebugSession {
         })
     }
 
+    async fn send_paused(&self, reason: PauseReason) {
+        match reason {
+            PauseReason::Breakpoint(breakpoint) => {
+                self.send_stopped(StoppedEventReason::Breakpoint, None, vec![breakpoint])
+                    .await;
+            }
+            PauseReason::Step => {
+                self.send_stopped(StoppedEventReason::Step, None, vec![])
+                    .await;
+            }
+        }
+    }
+    async fn send_stopped_at_program_start(&self) {
+        self.send_stopped(
+            StoppedEventReason::Entry,
+            Some("Paused on program start".to_string()),
+            vec![],
+        )
+        .await;
+    }
+    async fn send_stopped(
+        &self,
+        reason: StoppedEventReason,
+        description: Option<String>,
+        hit_breakpoint_ids: Vec<usize>,
+    ) {
+        self.send(EventBody::Stopped(StoppedEventBody {
+            reason,
+            description,
+            thread_id: Some(THREAD_ID),
+            preserve_focus_hint: Some(false),
+            text: None,
+            all_threads_stopped: Some(true),
+            hit_breakpoint_ids: Some(hit_breakpoint_ids),
+        }))
+        .await;
+    }
+    async fn send_terminated(&self) {
+        self.send(EventBody::Thread(ThreadEventBody {
+            reason: ThreadEventReason::Exited,
+            thread_id: THREAD_ID,
+        }))
+        .await;
+        self.send(EventBody::Terminated(None)).await;
+    }
+
     async fn send_response_ok(&self, seq: NonZeroUsize, body: ResponseBody) {
         self.send(Response {
             request_seq: seq,
@@ -432,6 +721,37 @@ impl DebugSession {
 }
 
 impl State {
+    const fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Self::Launched {
+                execution_state: ExecutionState::Running(_),
+                ..
+            },
+        )
+    }
+    fn vm_mut(&mut self) -> Option<&mut DebugVm> {
+        let Self::Launched {
+            execution_state, ..
+        } = self
+        else {
+            return None;
+        };
+        match execution_state {
+            ExecutionState::Running(vm) => Some(&mut vm.vm),
+            ExecutionState::Paused(state) => state.vm.as_mut().map(|it| &mut it.vm),
+        }
+    }
+    fn set_execution_state(&mut self, new_execution_state: ExecutionState) {
+        let Self::Launched {
+            execution_state, ..
+        } = self
+        else {
+            panic!("The program is not launched.");
+        };
+        *execution_state = new_execution_state;
+    }
+
     const fn require_initialized(&self) -> Result<&InitializeArguments, &'static str> {
         match &self {
             Self::Initial => Err("not-initialized"),
@@ -442,15 +762,6 @@ impl State {
             } => Ok(initialize_arguments),
         }
     }
-    const fn require_paused(&self) -> Result<&PausedState, &'static str> {
-        match self {
-            Self::Launched {
-                execution_state: ExecutionState::Paused(state),
-                ..
-            } => Ok(state),
-            _ => Err("not-paused"),
-        }
-    }
     fn require_paused_mut(&mut self) -> Result<&mut PausedState, &'static str> {
         match self {
             Self::Launched {
@@ -495,10 +806,3 @@ impl From<&InitializeArguments> for StartAt1Config {
         }
     }
 }
-
-#[derive(Clone, Copy, Debug)]
-enum StepKind {
-    Next,
-    In,
-    Out,
-}
@@ -1,3 +1,7 @@
+use super::{
+    execution_controller::ExecutionController,
+    history::{Event, History},
+};
 use candy_frontend::hir::Id;
 use candy_vm::{
     heap::{Heap, HirId, InlineObject},
@@ -6,39 +10,91 @@ use candy_vm::{
 
 #[derive(Debug, Default)]
 pub struct DebugTracer {
+    /// The state after all recorded events, i.e., where the VM currently is.
+    live_state: TracerState,
+    pub history: History,
+    pub controller: ExecutionController,
+}
+impl DebugTracer {
+    /// The state to show to the user. This is an earlier one if we traveled
+    /// back in time.
+    #[must_use]
+    pub fn state(&self) -> &TracerState {
+        self.history.past_state().unwrap_or(&self.live_state)
+    }
+
+    /// The history keeps the objects referenced by the event alive, so the
+    /// states don't have to.
+    fn record(&mut self, event: Event) {
+        let call_stack_depth = self.live_state.call_stack.len();
+        if let Some((id, trace_event)) = event.trace_event() {
+            self.controller.on_event(id, trace_event, call_stack_depth);
+        }
+        self.live_state.apply(&event);
+        self.history.record(event, call_stack_depth);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TracerState {
     pub root_locals: Vec<(Id, InlineObject)>,
     /// Analogous to [`StackTracer::call_stack`].
     pub call_stack: Vec<Vec<StackFrame>>,
+    /// The expression that was evaluated or called most recently.
+    pub current_expression: Option<Id>,
+}
+impl TracerState {
+    pub fn apply(&mut self, event: &Event) {
+        if let Some((id, _)) = event.trace_event() {
+            self.current_expression = Some(id.clone());
+        }
+
+        match event {
+            Event::ValueEvaluated { expression, value } => {
+                self.call_stack
+                    .last_mut()
+                    .and_then(|it| it.last_mut())
+                    .map(|it| &mut it.locals)
+                    .unwrap_or(&mut self.root_locals)
+                    .push((expression.clone(), *value));
+            }
+            Event::CallStarted(call) => {
+                self.call_stack.push(vec![StackFrame::new(call.clone())]);
+            }
+            Event::CallEnded => {
+                self.call_stack.pop().unwrap();
+            }
+            Event::TailCall(call) => {
+                self.call_stack
+                    .last_mut()
+                    .unwrap()
+                    .push(StackFrame::new(call.clone()));
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct StackFrame {
     pub call: Call,
     pub locals: Vec<(Id, InlineObject)>,
 }
 impl StackFrame {
-    fn new(call: Call) -> Self {
+    const fn new(call: Call) -> Self {
         Self {
             call,
             locals: vec![],
         }
     }
-
-    fn drop(&self, heap: &mut Heap) {
-        self.call.drop(heap);
-        self.locals.iter().for_each(|(_, value)| value.drop(heap));
-    }
 }
 
 impl Tracer for DebugTracer {
     fn value_evaluated(&mut self, heap: &mut Heap, expression: HirId, value: InlineObject) {
         value.dup(heap);
-        self.call_stack
-            .last_mut()
-            .and_then(|it| it.last_mut())
-            .map(|it| &mut it.locals)
-            .unwrap_or(&mut self.root_locals)
-            .push((expression.get().clone(), value));
+        self.record(Event::ValueEvaluated {
+            expression: expression.get().clone(),
+            value,
+        });
     }
 
     fn call_started(
@@ -56,12 +112,10 @@ impl Tracer for DebugTracer {
             responsible,
         };
         call.dup(heap);
-        self.call_stack.push(vec![StackFrame::new(call)]);
+        self.record(Event::CallStarted(call));
     }
-    fn call_ended(&mut self, heap: &mut Heap, _return_value: Option<InlineObject>) {
-        for call in self.call_stack.pop().unwrap() {
-            call.drop(heap);
-        }
+    fn call_ended(&mut self, _heap: &mut Heap, _return_value: Option<InlineObject>) {
+        self.record(Event::CallEnded);
     }
     fn tail_call(
         &mut self,
@@ -78,9 +132,6 @@ impl Tracer for DebugTracer {
             responsible,
         };
         call.dup(heap);
-        self.call_stack
-            .last_mut()
-            .unwrap()
-            .push(StackFrame::new(call));
+        self.record(Event::TailCall(call));
     }
 }
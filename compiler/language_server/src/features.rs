@@ -1,7 +1,9 @@
 use crate::database::Database;
 use async_trait::async_trait;
 use lsp_types::{
-    FoldingRange, LocationLink, SemanticToken, TextDocumentContentChangeEvent, TextEdit, Url,
+    CodeActionOrCommand, CompletionItem, DocumentSymbol, FoldingRange, Hover, InlayHint,
+    LocationLink, SemanticToken, SignatureHelp, SymbolInformation, TextDocumentContentChangeEvent,
+    TextEdit, Url,
 };
 use rustc_hash::FxHashMap;
 use std::collections::HashMap;
@@ -42,6 +44,40 @@ pub trait LanguageFeatures: Send + Sync {
         unimplemented!()
     }
 
+    fn supports_code_actions(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn code_actions(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _range: lsp_types::Range,
+    ) -> Vec<CodeActionOrCommand> {
+        unimplemented!()
+    }
+
+    fn supports_completion(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn completion(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Vec<CompletionItem> {
+        unimplemented!()
+    }
+
+    fn supports_document_symbols(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn document_symbols(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<DocumentSymbol> {
+        unimplemented!()
+    }
+
     fn supports_folding_ranges(&self) -> bool {
         false
     }
@@ -57,6 +93,15 @@ pub trait LanguageFeatures: Send + Sync {
     async fn format(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<TextEdit> {
         unimplemented!()
     }
+    #[must_use]
+    async fn format_range(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _range: lsp_types::Range,
+    ) -> Vec<TextEdit> {
+        unimplemented!()
+    }
 
     fn supports_find_definition(&self) -> bool {
         false
@@ -71,6 +116,37 @@ pub trait LanguageFeatures: Send + Sync {
         unimplemented!()
     }
 
+    fn supports_hover(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn hover(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Option<Hover> {
+        unimplemented!()
+    }
+
+    fn supports_inlay_hints(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn inlay_hints(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _range: lsp_types::Range,
+    ) -> Vec<InlayHint> {
+        unimplemented!()
+    }
+    /// Only called for hints whose `data` was set by [`Self::inlay_hints`].
+    #[must_use]
+    async fn resolve_inlay_hint(&self, _hint: InlayHint) -> InlayHint {
+        unimplemented!()
+    }
+
     fn supports_references(&self) -> bool {
         false
     }
@@ -117,6 +193,31 @@ pub trait LanguageFeatures: Send + Sync {
     async fn semantic_tokens(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<SemanticToken> {
         unimplemented!()
     }
+
+    fn supports_signature_help(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn signature_help(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Option<SignatureHelp> {
+        unimplemented!()
+    }
+
+    fn supports_workspace_symbols(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn workspace_symbols(
+        &self,
+        _db: &Mutex<Database>,
+        _query: String,
+    ) -> Vec<SymbolInformation> {
+        unimplemented!()
+    }
 }
 
 pub struct Reference {
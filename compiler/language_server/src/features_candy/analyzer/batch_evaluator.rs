@@ -0,0 +1,297 @@
+use crate::database::Database;
+use candy_frontend::{
+    hir,
+    hir_to_mir::ExecutionTarget,
+    id::CountableId,
+    lir_optimize::optimize_lir,
+    mir::{Body, Expression, Id, Mir},
+    mir_optimize::OptimizeMir,
+    mir_to_lir::lower_mir,
+    module::Module,
+    tracing::CallTracingMode,
+    OptimizationConfig, TracingConfig, TracingMode,
+};
+use candy_vm::{
+    byte_code::ByteCode,
+    environment::StateAfterRunWithoutHandles,
+    heap::{Heap, InlineObject},
+    launcher::Launcher,
+    lir_to_byte_code::compile_lir,
+    tracer::evaluated_values::EvaluatedValuesTracer,
+    Vm, VmFinished,
+};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use std::{
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+const TRACING: TracingConfig = TracingConfig {
+    register_fuzzables: TracingMode::Off,
+    calls: CallTracingMode::Off,
+    evaluated_expressions: TracingMode::OnlyCurrent,
+    debug_assertions: true,
+    strip: false,
+};
+const OPTIMIZATION: OptimizationConfig = OptimizationConfig::release();
+
+/// The number of instructions to run per call to [`BatchEvaluator::run`].
+const BATCH_SIZE: usize = 100_000;
+
+/// Evaluates the constants of a module for hints.
+///
+/// The evaluator survives edits of its module. It keeps the values of the last
+/// evaluation in a warm heap, grouped by the top-level definition they belong
+/// to and together with a fingerprint of that definition's MIR. After an edit,
+/// the values of definitions whose MIR didn't change are kept and can be shown
+/// right away. Only the changed definitions and the expressions they depend on
+/// are evaluated again. If no definition changed, the VM doesn't run at all.
+pub struct BatchEvaluator {
+    module: Module,
+    heap: Heap,
+    definitions: FxHashMap<hir::Id, Definition>,
+    running: Option<RunningEvaluation>,
+}
+struct Definition {
+    fingerprint: u64,
+    /// We need to keep a reference to this byte code for its constant heap
+    /// since objects in `values` may refer to it.
+    _byte_code: Rc<ByteCode>,
+    values: Vec<(hir::Id, InlineObject)>,
+}
+struct RunningEvaluation {
+    /// Fingerprints of the definitions being evaluated.
+    fingerprints: FxHashMap<hir::Id, u64>,
+    byte_code: Rc<ByteCode>,
+    vm: Vm<Rc<ByteCode>, EvaluatedValuesTracer>,
+}
+
+impl BatchEvaluator {
+    pub fn for_module(module: Module) -> Self {
+        Self {
+            module,
+            heap: Heap::default(),
+            definitions: FxHashMap::default(),
+            running: None,
+        }
+    }
+
+    /// Compares the module's current MIR with the one of the last evaluation
+    /// and starts a new evaluation of the changed definitions.
+    pub fn start(&mut self, db: &Database) {
+        // The values of an evaluation that was interrupted by an edit are
+        // never stored.
+        self.abort();
+
+        let target = ExecutionTarget::Module(self.module.clone());
+        let Ok((mir, _)) = db.optimized_mir(target, TRACING, OPTIMIZATION) else {
+            // The module doesn't exist (anymore) or isn't Candy code, so there
+            // are no values to show.
+            self.remove_definitions(|_, _| true);
+            return;
+        };
+        let dependencies = definition_dependencies(&mir.body);
+        let fingerprints = dependencies
+            .iter()
+            .map(|(id, dependencies)| (id.clone(), fingerprint(&mir.body, dependencies)))
+            .collect::<FxHashMap<_, _>>();
+
+        self.remove_definitions(|id, definition| {
+            fingerprints.get(id) != Some(&definition.fingerprint)
+        });
+        let fingerprints = fingerprints
+            .into_iter()
+            .filter(|(id, _)| !self.definitions.contains_key(id))
+            .collect::<FxHashMap<_, _>>();
+        if fingerprints.is_empty() {
+            return;
+        }
+
+        // Unchanged definitions are only evaluated if changed ones depend on
+        // them.
+        let expressions_to_evaluate = fingerprints
+            .keys()
+            .flat_map(|id| &dependencies[id])
+            .copied()
+            .collect::<FxHashSet<_>>();
+        let mut mir = Mir {
+            id_generator: mir.id_generator.clone(),
+            body: Body::new(
+                mir.body
+                    .iter()
+                    .filter(|(id, _)| expressions_to_evaluate.contains(id))
+                    .map(|(id, expression)| (id, expression.clone()))
+                    .collect(),
+            ),
+        };
+        // Tracing expressions don't have a value the module could return.
+        mir.body
+            .push_with_new_id(&mut mir.id_generator, Expression::nothing());
+        let optimized_lir = optimize_lir(&lower_mir(self.module.clone(), &mir));
+        let byte_code = Rc::new(compile_lir(self.module.clone(), &optimized_lir));
+
+        let vm = Launcher::new(
+            byte_code.clone(),
+            EvaluatedValuesTracer::new(self.module.clone()),
+        )
+        .module(&mut self.heap);
+        self.running = Some(RunningEvaluation {
+            fingerprints,
+            byte_code,
+            vm,
+        });
+    }
+    /// Stops the current evaluation and drops the values it produced so far.
+    fn abort(&mut self) {
+        let Some(RunningEvaluation { vm, .. }) = self.running.take() else {
+            return;
+        };
+        let tracer = vm.abort(&mut self.heap);
+        for value in tracer.values().values() {
+            value.drop(&mut self.heap);
+        }
+    }
+    fn remove_definitions(&mut self, mut predicate: impl FnMut(&hir::Id, &Definition) -> bool) {
+        let ids = self
+            .definitions
+            .iter()
+            .filter(|(id, definition)| predicate(id, definition))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in ids {
+            let definition = self.definitions.remove(&id).unwrap();
+            for (_, value) in definition.values {
+                value.drop(&mut self.heap);
+            }
+        }
+    }
+
+    #[must_use]
+    pub const fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Continues the current evaluation for a batch of instructions.
+    pub fn run(&mut self) {
+        let Some(RunningEvaluation {
+            fingerprints,
+            byte_code,
+            vm,
+        }) = self.running.take()
+        else {
+            return;
+        };
+
+        match vm.run_n_without_handles(&mut self.heap, BATCH_SIZE) {
+            StateAfterRunWithoutHandles::Running(vm) => {
+                self.running = Some(RunningEvaluation {
+                    fingerprints,
+                    byte_code,
+                    vm,
+                });
+            }
+            StateAfterRunWithoutHandles::Finished(VmFinished { tracer, result, .. }) => {
+                if let Ok(value) = result {
+                    value.drop(&mut self.heap);
+                }
+                self.store(&fingerprints, &byte_code, &tracer);
+            }
+        }
+    }
+    fn store(
+        &mut self,
+        fingerprints: &FxHashMap<hir::Id, u64>,
+        byte_code: &Rc<ByteCode>,
+        tracer: &EvaluatedValuesTracer,
+    ) {
+        let mut new_definitions = FxHashMap::<hir::Id, Definition>::default();
+        for (id, value) in tracer.values() {
+            let definition_id = top_level_ancestor(id);
+            let Some(fingerprint) = fingerprints.get(&definition_id) else {
+                // The definition didn't change, so we already have its values.
+                value.drop(&mut self.heap);
+                continue;
+            };
+
+            new_definitions
+                .entry(definition_id)
+                .or_insert_with(|| Definition {
+                    fingerprint: *fingerprint,
+                    _byte_code: byte_code.clone(),
+                    values: vec![],
+                })
+                .values
+                .push((id.clone(), *value));
+        }
+        self.definitions.extend(new_definitions);
+    }
+
+    /// The values of all expressions evaluated so far.
+    ///
+    /// While an evaluation is running, this contains the values of unchanged
+    /// definitions from the previous evaluation.
+    pub fn values(&self) -> impl Iterator<Item = (&hir::Id, InlineObject)> {
+        self.definitions
+            .values()
+            .flat_map(|definition| definition.values.iter().map(|(id, value)| (id, *value)))
+    }
+}
+
+fn top_level_ancestor(id: &hir::Id) -> hir::Id {
+    let mut id = id.clone();
+    while let Some(parent) = id.parent()
+        && !parent.is_root()
+    {
+        id = parent;
+    }
+    id
+}
+
+/// The expressions that the traced values of each top-level definition depend
+/// on, including the tracing expressions themselves.
+fn definition_dependencies(body: &Body) -> FxHashMap<hir::Id, FxHashSet<Id>> {
+    let expressions: FxHashMap<Id, &Expression> = body.iter().collect();
+    let mut definitions = FxHashMap::<hir::Id, FxHashSet<Id>>::default();
+    for (id, expression) in body.iter() {
+        let Expression::TraceExpressionEvaluated { hir_expression, .. } = expression else {
+            continue;
+        };
+        let Some(Expression::HirId(hir_id)) = expressions.get(hir_expression) else {
+            continue;
+        };
+        if hir_id.is_root() {
+            continue;
+        }
+
+        let dependencies = definitions.entry(top_level_ancestor(hir_id)).or_default();
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            if let Some(expression) = expressions.get(&id)
+                && dependencies.insert(id)
+            {
+                pending.extend(expression.referenced_ids());
+            }
+        }
+    }
+    definitions
+}
+
+/// Hashes the given expressions of the body.
+///
+/// IDs are normalized so that edits elsewhere in the module, which shift the
+/// IDs of the whole MIR, don't change the fingerprint.
+fn fingerprint(body: &Body, dependencies: &FxHashSet<Id>) -> u64 {
+    let mut normalized_ids = FxHashMap::default();
+    let mut normalize = |id: &mut Id| {
+        let next_id = Id::from_usize(normalized_ids.len());
+        *id = *normalized_ids.entry(*id).or_insert(next_id);
+    };
+    let mut hasher = FxHasher::default();
+    for (mut id, expression) in body.iter().filter(|(id, _)| dependencies.contains(id)) {
+        normalize(&mut id);
+        let mut expression = expression.clone();
+        expression.replace_ids(&mut normalize);
+        (id, expression).hash(&mut hasher);
+    }
+    hasher.finish()
+}
@@ -1,19 +1,27 @@
 use super::utils::IdToEndOfLine;
-use crate::{database::Database, utils::LspPositionConversion};
+use crate::{
+    database::Database,
+    utils::{error_to_diagnostic, LspPositionConversion},
+};
 use candy_frontend::{
     ast::{Assignment, AssignmentBody, AstDb, AstKind},
     ast_to_hir::AstToHir,
     format::{MaxLength, Precedence},
     hir::{Expression, HirDb, Id},
+    hir_to_mir::ExecutionTarget,
+    lints::{lint_module, Lint, LintLevel, LintLevels},
     module::Module,
+    OptimizationConfig,
 };
-use candy_fuzzer::{Fuzzer, RunResult, Status};
+use candy_fuzzer::{Fuzzer, RunResult, Status, TRACING as FUZZING_TRACING};
 use candy_vm::{
     heap::{InlineObject, ToDebugText},
+    origins::ByteCodeOriginsDb,
     Panic,
 };
 use extension_trait::extension_trait;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use itertools::Itertools;
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Position, Range};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
@@ -27,6 +35,8 @@ pub struct Hint {
     pub kind: HintKind,
     pub text: String,
     pub position: Position,
+    /// Markdown shown when hovering the hint or the definition it belongs to.
+    pub details: Option<String>,
 }
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, PartialOrd, Ord, Copy)]
 #[serde(rename_all = "camelCase")]
@@ -81,6 +91,7 @@ impl Insight {
             } else {
                 text
             },
+            details: None,
         }))
     }
 
@@ -92,64 +103,171 @@ impl Insight {
             .id_to_end_of_line(id.clone())
             .unwrap_or_else(|| panic!("Can't resolve end of line for {id}"));
 
+        // The coverage is unknown if the byte code has no body for the
+        // function.
         let coverage = match fuzzer.status() {
-            Status::StillFuzzing { total_coverage, .. } => {
-                let function_range = fuzzer.byte_code().range_of_function(&id);
-                let function_coverage = total_coverage.in_range(&function_range);
-                function_coverage.relative_coverage()
-            }
-            Status::FoundPanic { .. } => 1., // TODO: not correct
+            Status::StillFuzzing { total_coverage, .. } => db
+                .byte_code_origins(
+                    ExecutionTarget::Module(id.module.clone()),
+                    FUZZING_TRACING,
+                    OptimizationConfig::default(),
+                )
+                .range_of_function(&id)
+                .map(|range| total_coverage.in_range(&range).relative_coverage()),
+            Status::FoundPanic { .. } => Some(1.), // TODO: not correct
         };
         let function_name = id.function_name();
         let interesting_inputs = fuzzer.input_pool().interesting_inputs();
-        insights.push(Self::Hint(Hint {
-            kind: HintKind::FuzzingStatus,
-            position: end_of_line,
-            text: format!("{:.0} % fuzzed", 100. * coverage),
-        }));
-
-        if let Status::FoundPanic { input, .. } = fuzzer.status() {
-            insights.push(Self::Hint(Hint {
-                kind: HintKind::SampleInputPanickingWithInternalCodeResponsible,
-                position: end_of_line,
-                text: format!("{function_name} {input}"),
-            }));
-        }
-
-        insights.extend(interesting_inputs.into_iter().map(|input| {
-            Self::Hint(match fuzzer.input_pool().result_of(&input) {
-                RunResult::Timeout => unreachable!(),
+        let sample_hints = interesting_inputs
+            .iter()
+            .map(|input| match fuzzer.input_pool().result_of(input) {
+                RunResult::Timeout | RunResult::OutOfMemory => unreachable!(),
                 RunResult::Done { return_value, .. } => Hint {
                     kind: HintKind::SampleInputReturningNormally,
                     position: end_of_line,
                     text: format!("{function_name} {input} = {return_value}"),
+                    details: None,
                 },
                 RunResult::NeedsUnfulfilled { .. } => Hint {
                     kind: HintKind::SampleInputPanickingWithCallerResponsible,
                     position: end_of_line,
                     text: format!("{function_name} {input}"),
+                    details: None,
                 },
                 RunResult::Panicked { .. } => Hint {
                     kind: HintKind::SampleInputPanickingWithInternalCodeResponsible,
                     position: end_of_line,
                     text: format!("{function_name} {input}"),
+                    details: None,
                 },
             })
+            .collect_vec();
+
+        let mut summary = vec![
+            format!(
+                "**Fuzzing** ({})",
+                match fuzzer.status() {
+                    Status::StillFuzzing { .. } => "still running",
+                    Status::FoundPanic { .. } => "found a panic",
+                },
+            ),
+            format!(
+                "Tried {} {}{}.",
+                fuzzer.num_inputs_tried(),
+                if fuzzer.num_inputs_tried() == 1 {
+                    "input"
+                } else {
+                    "inputs"
+                },
+                coverage.map_or_else(String::new, |coverage| format!(
+                    ", covering {:.0} % of the function",
+                    100. * coverage,
+                )),
+            ),
+        ];
+        if let Status::FoundPanic { input, panic, .. } = fuzzer.status() {
+            summary.push(format!(
+                "Panicking input: `{function_name} {input}`\n\n> {}",
+                panic.reason,
+            ));
+        }
+        if !sample_hints.is_empty() {
+            summary.push(format!(
+                "Interesting inputs:\n{}",
+                sample_hints
+                    .iter()
+                    .map(|hint| format!(
+                        "- `{}`{}",
+                        hint.text,
+                        match hint.kind {
+                            HintKind::SampleInputPanickingWithCallerResponsible => {
+                                " (doesn't fulfill the needs)"
+                            }
+                            HintKind::SampleInputPanickingWithInternalCodeResponsible => {
+                                " (panics)"
+                            }
+                            _ => "",
+                        },
+                    ))
+                    .join("\n"),
+            ));
+        }
+        insights.push(Self::Hint(Hint {
+            kind: HintKind::FuzzingStatus,
+            position: end_of_line,
+            text: coverage.map_or_else(
+                || "fuzzed".to_string(),
+                |coverage| format!("{:.0} % fuzzed", 100. * coverage),
+            ),
+            details: Some(summary.join("\n\n")),
         }));
 
+        if let Status::FoundPanic { input, .. } = fuzzer.status() {
+            insights.push(Self::Hint(Hint {
+                kind: HintKind::SampleInputPanickingWithInternalCodeResponsible,
+                position: end_of_line,
+                text: format!("{function_name} {input}"),
+                details: None,
+            }));
+        }
+
+        insights.extend(sample_hints.into_iter().map(Self::Hint));
+
         insights
     }
 
-    pub fn for_static_panic(db: &Database, module: Module, panic: &Panic) -> Self {
+    pub fn for_lints(db: &Database, module: &Module) -> Vec<Self> {
+        // An invalid configuration is reported by `candy check`. While the
+        // user is editing it, we fall back to the default levels.
+        let levels = LintLevels::for_package(db, module.package()).unwrap_or_default();
+        lint_module(db, module, &levels)
+            .into_iter()
+            .map(|warning| {
+                let severity = match warning.level {
+                    LintLevel::Allow | LintLevel::Warn => DiagnosticSeverity::WARNING,
+                    LintLevel::Deny => DiagnosticSeverity::ERROR,
+                };
+                let tags = match warning.lint {
+                    Lint::UnreachableCode | Lint::UnusedDefinition | Lint::UnusedUse => {
+                        vec![DiagnosticTag::UNNECESSARY]
+                    }
+                    Lint::IgnoredResult | Lint::ShadowedName => vec![],
+                };
+                Self::Diagnostic(Diagnostic {
+                    severity: Some(severity),
+                    code: Some(NumberOrString::String(warning.lint.code().to_string())),
+                    tags: Some(tags),
+                    ..Diagnostic::error(
+                        db.range_to_lsp_range(module.clone(), warning.span),
+                        warning.message,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn for_static_panic(db: &Database, module: Module, panic: &Panic) -> Vec<Self> {
+        // Compiler errors know their exact span and related spans, e.g., the
+        // whole call that spans multiple lines.
+        if !panic.responsible.is_root()
+            && let Some(Expression::Error { errors }) =
+                db.find_expression(panic.responsible.clone())
+        {
+            return errors
+                .iter()
+                .map(|error| Self::Diagnostic(error_to_diagnostic(db, error.module.clone(), error)))
+                .collect();
+        }
+
         let call_span = db
             .hir_id_to_display_span(&panic.responsible)
             .unwrap_or_else(|| panic!("Can't resolve responsible ID for panic: {panic:?}"));
         let call_span = db.range_to_lsp_range(module, call_span);
 
-        Self::Diagnostic(Diagnostic::error(
+        vec![Self::Diagnostic(Diagnostic::error(
             call_span,
             ToString::to_string(&panic.reason),
-        ))
+        ))]
     }
 }
 
@@ -1,6 +1,6 @@
 //! Unlike other language server features, hints are not generated on-demand
-//! with the usual request-response model. Instead, a hints server runs in the
-//! background all the time. That way, the hints can progressively get better.
+//! when the client requests inlay hints. Instead, a hints server runs in the
+//! background all the time and requests only return its latest results. That way, the hints can progressively get better.
 //! For example, when opening a long file, the hints may appear from top to
 //! bottom as more code is evaluated. Then, the individual functions could get
 //! fuzzed with ever-more-complex inputs, resulting in some error cases to be
@@ -8,6 +8,11 @@
 //!
 //! While doing all that, we can pause regularly between executing instructions
 //! so that we don't occupy a single CPU at 100 %.
+//!
+//! Whenever the language server sends us a message (e.g., because the user
+//! edited a file), it cancels the [`CancellationToken`] of our database. Long
+//! compilations then abort early so that we can process the edit right away
+//! instead of finishing an analysis that's already outdated.
 
 use self::{
     insights::{Hint, Insight},
@@ -15,23 +20,28 @@ use self::{
 };
 use super::AnalyzerClient;
 use crate::database::Database;
-use candy_frontend::module::{Module, MutableModuleProviderOwner, PackagesPath};
+use candy_frontend::{
+    cancellation::{Canceled, CancellationToken},
+    module::{Module, MutableModuleProviderOwner, PackagesPath},
+};
 use itertools::{Either, Itertools};
-use lsp_types::{notification::Notification, Url};
 use rand::{seq::IteratorRandom, thread_rng};
 use rustc_hash::FxHashMap;
-use serde::{Deserialize, Serialize};
-use std::{fmt, future::Future, time::Duration, vec};
+use std::{fmt, future::Future, panic::AssertUnwindSafe, sync::Arc, time::Duration, vec};
 use tokio::{
-    sync::mpsc::{self, error::TryRecvError},
+    sync::{
+        mpsc::{self, error::TryRecvError},
+        Mutex,
+    },
     time::sleep,
 };
 use tracing::debug;
 
+mod batch_evaluator;
 pub mod insights;
 mod module_analyzer;
 mod static_panics;
-mod utils;
+pub mod utils;
 
 #[derive(Debug)]
 pub enum Message {
@@ -40,36 +50,36 @@ pub enum Message {
     Shutdown,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct HintsNotification {
-    pub uri: Url,
-    pub hints: Vec<Hint>,
-}
-impl Notification for HintsNotification {
-    const METHOD: &'static str = "candy/textDocument/publishHints";
-
-    type Params = Self;
-}
-
 #[tokio::main(worker_threads = 1)]
-#[allow(clippy::needless_pass_by_value, unused_must_use)]
+#[allow(
+    clippy::implicit_hasher,
+    clippy::needless_pass_by_value,
+    unused_must_use
+)]
 pub async fn run_server(
     packages_path: PackagesPath,
     mut incoming_events: mpsc::Receiver<Message>,
     client: AnalyzerClient,
+    latest_hints: Arc<Mutex<FxHashMap<Module, Vec<Hint>>>>,
+    cancellation_token: CancellationToken,
 ) {
-    let mut db = Database::new_with_file_system_module_provider(packages_path);
+    let mut db = Database::new_with_file_system_module_provider(packages_path)
+        .with_cancellation_token(cancellation_token.clone());
     let mut analyzers: FxHashMap<Module, ModuleAnalyzer> = FxHashMap::default();
     let client_ref = &client;
     let mut outgoing_diagnostics = OutgoingCache::new(move |module, diagnostics| {
         client_ref.update_diagnostics(module, diagnostics)
     });
-    let mut outgoing_hints =
-        OutgoingCache::new(move |module, hints| client_ref.update_hints(module, hints));
+    // Clients request hints themselves, so we only tell them when hints
+    // changed.
+    let mut outgoing_hints = OutgoingCache::new(move |_, _: Vec<Hint>| client_ref.refresh_hints());
 
     'server_loop: loop {
         sleep(Duration::from_millis(100)).await;
 
+        // Messages sent from now on cancel the work we do in this iteration,
+        // but they're only processed in the next one.
+        cancellation_token.reset();
         loop {
             let event = match incoming_events.try_recv() {
                 Ok(event) => event,
@@ -79,6 +89,7 @@ pub async fn run_server(
             match event {
                 Message::UpdateModule(module, content) => {
                     db.did_change_module(&module, content);
+                    latest_hints.lock().await.remove(&module);
                     outgoing_hints.send(module.clone(), vec![]).await;
                     analyzers
                         .entry(module.clone())
@@ -88,6 +99,7 @@ pub async fn run_server(
                 Message::CloseModule(module) => {
                     db.did_close_module(&module);
                     analyzers.remove(&module);
+                    latest_hints.lock().await.remove(&module);
                 }
                 Message::Shutdown => {
                     incoming_events.close();
@@ -103,7 +115,10 @@ pub async fn run_server(
 
         analyzer.run(&db, &client).await;
 
-        let insights = analyzer.insights(&db);
+        let Ok(insights) = Canceled::catch(AssertUnwindSafe(|| analyzer.insights(&db))) else {
+            // The insights would be outdated anyway.
+            continue;
+        };
         let (diagnostics, mut hints): (Vec<_>, Vec<_>) =
             insights.into_iter().partition_map(|it| match it {
                 Insight::Diagnostic(diagnostic) => Either::Left(diagnostic),
@@ -112,6 +127,10 @@ pub async fn run_server(
         hints.sort_by_key(|hint| hint.position);
 
         outgoing_diagnostics.send(module.clone(), diagnostics).await;
+        latest_hints
+            .lock()
+            .await
+            .insert(module.clone(), hints.clone());
         outgoing_hints.send(module, hints).await;
     }
 }
@@ -1,47 +1,47 @@
-use super::{insights::Insight, static_panics::StaticPanicsOfMir};
+use super::{batch_evaluator::BatchEvaluator, insights::Insight, static_panics::StaticPanicsOfMir};
 use crate::{
     database::Database, features_candy::analyzer::insights::ErrorDiagnostic,
     server::AnalyzerClient, utils::LspPositionConversion,
 };
 use candy_frontend::{
     ast_to_hir::AstToHir,
+    cancellation::Canceled,
     format::{MaxLength, Precedence},
     hir_to_mir::ExecutionTarget,
     mir_optimize::OptimizeMir,
     module::Module,
-    tracing::CallTracingMode,
-    TracingConfig, TracingMode,
+    OptimizationConfig,
 };
-use candy_fuzzer::{FuzzablesFinder, Fuzzer, Status};
+use candy_fuzzer::{FuzzablesFinder, Fuzzer, Status, TRACING as FUZZING_TRACING};
 use candy_vm::{
     byte_code::ByteCode,
     environment::StateAfterRunWithoutHandles,
     heap::{Heap, ToDebugText},
+    launcher::Launcher,
     lir_to_byte_code::compile_byte_code,
-    tracer::{evaluated_values::EvaluatedValuesTracer, stack_trace::StackTracer},
     Panic, Vm, VmFinished,
 };
 use extension_trait::extension_trait;
 use itertools::Itertools;
 use lsp_types::Diagnostic;
 use rand::{prelude::SliceRandom, thread_rng};
-use std::rc::Rc;
+use std::{panic::AssertUnwindSafe, rc::Rc};
 use tracing::debug;
 
 /// A hints finder is responsible for finding hints for a single module.
 pub struct ModuleAnalyzer {
     module: Module,
+    /// Outlives the state so that the values of unchanged definitions survive
+    /// edits.
+    evaluator: BatchEvaluator,
     state: Option<State>, // only None during state transition
 }
 enum State {
     Initial,
-    /// First, we run the module with tracing of evaluated expressions enabled.
-    /// This enables us to show hints for constants.
+    /// First, the [`BatchEvaluator`] runs the module with tracing of evaluated
+    /// expressions enabled. This enables us to show hints for constants.
     EvaluateConstants {
         static_panics: Vec<Panic>,
-        byte_code: Rc<ByteCode>,
-        heap: Heap,
-        vm: Vm<Rc<ByteCode>, (StackTracer, EvaluatedValuesTracer)>,
     },
     /// Next, we run the module again to finds fuzzable functions. This time, we
     /// disable tracing of evaluated expressions, but we enable registration of
@@ -49,12 +49,6 @@ enum State {
     /// efficient byte code possible.
     FindFuzzables {
         static_panics: Vec<Panic>,
-        heap_for_constants: Heap,
-        stack_tracer: StackTracer,
-        /// We need to keep a reference to this byte code for its constant heap
-        /// since objects in `evaluated_values` refer to it.
-        evaluated_values_byte_code: Rc<ByteCode>,
-        evaluated_values: EvaluatedValuesTracer,
         byte_code: Rc<ByteCode>,
         heap: Heap,
         vm: Vm<Rc<ByteCode>, FuzzablesFinder>,
@@ -63,24 +57,20 @@ enum State {
     Fuzz {
         byte_code: Rc<ByteCode>,
         static_panics: Vec<Panic>,
-        heap_for_constants: Heap,
-        stack_tracer: StackTracer,
-        evaluated_values_byte_code: Rc<ByteCode>,
-        evaluated_values: EvaluatedValuesTracer,
         heap_for_fuzzables: Heap,
         fuzzers: Vec<Fuzzer>,
     },
 }
 
 impl ModuleAnalyzer {
-    pub const fn for_module(module: Module) -> Self {
+    pub fn for_module(module: Module) -> Self {
         Self {
+            evaluator: BatchEvaluator::for_module(module.clone()),
             module,
             state: Some(State::Initial),
         }
     }
     pub fn module_changed(&mut self) {
-        // PERF: Save some incremental state.
         self.state = Some(State::Initial);
     }
 
@@ -89,94 +79,69 @@ impl ModuleAnalyzer {
         let state = self.update_state(db, client, state).await;
         self.state = Some(state);
     }
-    async fn update_state(&self, db: &Database, client: &AnalyzerClient, state: State) -> State {
+    async fn update_state(
+        &mut self,
+        db: &Database,
+        client: &AnalyzerClient,
+        state: State,
+    ) -> State {
         match state {
             State::Initial => {
                 client
                     .update_status(Some(format!("Compiling {}", self.module)))
                     .await;
 
-                let (mir, _) = db
-                    .optimized_mir(
-                        ExecutionTarget::Module(self.module.clone()),
-                        TracingConfig {
-                            register_fuzzables: TracingMode::OnlyCurrent,
-                            calls: CallTracingMode::Off,
-                            evaluated_expressions: TracingMode::Off,
-                        },
-                    )
-                    .unwrap();
-                let mut mir = (*mir).clone();
-                let mut static_panics = mir.static_panics();
-                static_panics.retain(|panic| -> bool { panic.responsible.module == self.module });
+                let static_panics = Canceled::catch(AssertUnwindSafe(|| {
+                    self.evaluator.start(db);
 
-                let tracing = TracingConfig {
-                    register_fuzzables: TracingMode::Off,
-                    calls: CallTracingMode::Off,
-                    evaluated_expressions: TracingMode::OnlyCurrent,
+                    let Ok((mir, _)) = db.optimized_mir(
+                        ExecutionTarget::Module(self.module.clone()),
+                        FUZZING_TRACING,
+                        OptimizationConfig::default(),
+                    ) else {
+                        return vec![];
+                    };
+                    let mut mir = (*mir).clone();
+                    let mut static_panics = mir.static_panics();
+                    static_panics
+                        .retain(|panic| -> bool { panic.responsible.module == self.module });
+                    static_panics
+                }));
+                // If we got canceled, the module changed and we start over.
+                let Ok(static_panics) = static_panics else {
+                    return State::Initial;
                 };
-                let (byte_code, _) =
-                    compile_byte_code(db, ExecutionTarget::Module(self.module.clone()), tracing);
-                let byte_code = Rc::new(byte_code);
-
-                let mut heap = Heap::default();
-                let tracer = (
-                    StackTracer::default(),
-                    EvaluatedValuesTracer::new(self.module.clone()),
-                );
-                let vm = Vm::for_module(byte_code.clone(), &mut heap, tracer);
-
-                State::EvaluateConstants {
-                    static_panics,
-                    byte_code,
-                    heap,
-                    vm,
-                }
+                State::EvaluateConstants { static_panics }
             }
-            State::EvaluateConstants {
-                static_panics,
-                byte_code,
-                heap: mut heap_for_constants,
-                vm,
-            } => {
-                client
-                    .update_status(Some(format!("Evaluating {}", self.module)))
-                    .await;
+            State::EvaluateConstants { static_panics } => {
+                if self.evaluator.is_running() {
+                    client
+                        .update_status(Some(format!("Evaluating {}", self.module)))
+                        .await;
 
-                let tracer = match vm.run_n_without_handles(&mut heap_for_constants, 500) {
-                    StateAfterRunWithoutHandles::Running(vm) => {
-                        return State::EvaluateConstants {
-                            static_panics,
-                            byte_code,
-                            heap: heap_for_constants,
-                            vm,
-                        }
+                    self.evaluator.run();
+                    if self.evaluator.is_running() {
+                        return State::EvaluateConstants { static_panics };
                     }
-                    StateAfterRunWithoutHandles::Finished(VmFinished { tracer, .. }) => tracer,
-                };
-                let (stack_tracer, evaluated_values) = tracer;
+                }
 
-                let tracing = TracingConfig {
-                    register_fuzzables: TracingMode::OnlyCurrent,
-                    calls: CallTracingMode::Off,
-                    evaluated_expressions: TracingMode::Off,
+                let Ok((fuzzing_byte_code, _)) = Canceled::catch(AssertUnwindSafe(|| {
+                    compile_byte_code(
+                        db,
+                        ExecutionTarget::Module(self.module.clone()),
+                        FUZZING_TRACING,
+                        OptimizationConfig::default(),
+                    )
+                })) else {
+                    return State::EvaluateConstants { static_panics };
                 };
-                let (fuzzing_byte_code, _) =
-                    compile_byte_code(db, ExecutionTarget::Module(self.module.clone()), tracing);
                 let fuzzing_byte_code = Rc::new(fuzzing_byte_code);
 
                 let mut heap = Heap::default();
-                let vm = Vm::for_module(
-                    fuzzing_byte_code.clone(),
-                    &mut heap,
-                    FuzzablesFinder::default(),
-                );
+                let vm = Launcher::new(fuzzing_byte_code.clone(), FuzzablesFinder::default())
+                    .module(&mut heap);
                 State::FindFuzzables {
                     static_panics,
-                    heap_for_constants,
-                    stack_tracer,
-                    evaluated_values_byte_code: byte_code,
-                    evaluated_values,
                     byte_code: fuzzing_byte_code,
                     heap,
                     vm,
@@ -184,10 +149,6 @@ impl ModuleAnalyzer {
             }
             State::FindFuzzables {
                 static_panics,
-                heap_for_constants,
-                stack_tracer,
-                evaluated_values_byte_code,
-                evaluated_values,
                 byte_code,
                 mut heap,
                 vm,
@@ -200,10 +161,6 @@ impl ModuleAnalyzer {
                     StateAfterRunWithoutHandles::Running(vm) => {
                         return State::FindFuzzables {
                             static_panics,
-                            heap_for_constants,
-                            stack_tracer,
-                            evaluated_values_byte_code,
-                            evaluated_values,
                             byte_code,
                             heap,
                             vm,
@@ -222,10 +179,6 @@ impl ModuleAnalyzer {
                 State::Fuzz {
                     byte_code,
                     static_panics,
-                    heap_for_constants,
-                    stack_tracer,
-                    evaluated_values_byte_code,
-                    evaluated_values,
                     heap_for_fuzzables: heap,
                     fuzzers,
                 }
@@ -233,10 +186,6 @@ impl ModuleAnalyzer {
             State::Fuzz {
                 byte_code,
                 static_panics,
-                heap_for_constants,
-                stack_tracer,
-                evaluated_values_byte_code,
-                evaluated_values,
                 heap_for_fuzzables,
                 mut fuzzers,
             } => {
@@ -249,10 +198,6 @@ impl ModuleAnalyzer {
                     return State::Fuzz {
                         byte_code,
                         static_panics,
-                        heap_for_constants,
-                        stack_tracer,
-                        evaluated_values_byte_code,
-                        evaluated_values,
                         heap_for_fuzzables,
                         fuzzers,
                     };
@@ -267,10 +212,6 @@ impl ModuleAnalyzer {
                 State::Fuzz {
                     byte_code,
                     static_panics,
-                    heap_for_constants,
-                    stack_tracer,
-                    evaluated_values_byte_code,
-                    evaluated_values,
                     heap_for_fuzzables,
                     fuzzers,
                 }
@@ -279,40 +220,22 @@ impl ModuleAnalyzer {
     }
 
     pub fn insights(&self, db: &Database) -> Vec<Insight> {
-        let mut insights = vec![];
+        let mut insights = Insight::for_lints(db, &self.module);
 
         match self.state.as_ref().unwrap() {
             State::Initial => {}
-            State::EvaluateConstants { static_panics, .. } => {
-                // TODO: Show incremental constant evaluation hints.
+            State::EvaluateConstants { static_panics }
+            | State::FindFuzzables { static_panics, .. } => {
                 insights.extend(static_panics.to_insights(db, &self.module));
-            }
-            State::FindFuzzables {
-                static_panics,
-                evaluated_values,
-                ..
-            } => {
-                insights.extend(static_panics.to_insights(db, &self.module));
-                insights.extend(
-                    evaluated_values
-                        .values()
-                        .iter()
-                        .filter_map(|(id, value)| Insight::for_value(db, id.clone(), *value)),
-                );
+                insights.extend(self.constant_insights(db));
             }
             State::Fuzz {
                 static_panics,
-                evaluated_values,
                 fuzzers,
                 ..
             } => {
                 insights.extend(static_panics.to_insights(db, &self.module));
-                insights.extend(
-                    evaluated_values
-                        .values()
-                        .iter()
-                        .filter_map(|(id, value)| Insight::for_value(db, id.clone(), *value)),
-                );
+                insights.extend(self.constant_insights(db));
 
                 for fuzzer in fuzzers {
                     insights.append(&mut Insight::for_fuzzer_status(db, fuzzer));
@@ -367,13 +290,18 @@ impl ModuleAnalyzer {
 
         insights
     }
+    fn constant_insights<'a>(&'a self, db: &'a Database) -> impl Iterator<Item = Insight> + 'a {
+        self.evaluator
+            .values()
+            .filter_map(|(id, value)| Insight::for_value(db, id.clone(), value))
+    }
 }
 
 #[extension_trait]
 pub impl StaticPanics for Vec<Panic> {
     fn to_insights(&self, db: &Database, module: &Module) -> Vec<Insight> {
         self.iter()
-            .map(|panic| Insight::for_static_panic(db, module.clone(), panic))
+            .flat_map(|panic| Insight::for_static_panic(db, module.clone(), panic))
             .collect_vec()
     }
 }
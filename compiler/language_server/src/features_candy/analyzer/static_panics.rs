@@ -4,7 +4,7 @@ use candy_frontend::{
     mir::{Body, Expression, Mir, VisibleExpressions},
     module::Module,
 };
-use candy_vm::Panic;
+use candy_vm::{Panic, PanicKind};
 use extension_trait::extension_trait;
 use lsp_types::{Diagnostic, DiagnosticSeverity};
 use std::mem;
@@ -86,6 +86,7 @@ impl StaticPanicsOfExpression for Expression {
                 panics.push(Panic {
                     reason: reason.to_string(),
                     responsible: responsible.clone(),
+                    kind: PanicKind::Code,
                 });
             }
             _ => {}
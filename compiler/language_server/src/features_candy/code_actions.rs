@@ -0,0 +1,305 @@
+use super::definitions::{exports, is_assignment, resolve};
+use crate::{database::Database, utils::LspPositionConversion};
+use candy_frontend::{
+    ast::AstDb,
+    ast_to_hir::AstToHir,
+    error::{CompilerError, CompilerErrorPayload},
+    hir::{self, Body, Expression, Function, HirError},
+    hir_to_mir::{ExecutionTarget, HirToMir},
+    module::{Module, ModuleDb, UsePath},
+    position::Offset,
+    unused_uses::{find_unused_uses, is_use_assignment},
+    TracingConfig,
+};
+use itertools::Itertools;
+use lsp_types::{CodeAction, CodeActionKind, CodeActionOrCommand, TextEdit, Url, WorkspaceEdit};
+use rustc_hash::FxHashSet;
+use std::{collections::HashMap, ops::Range};
+
+/// Offers quickfixes for problems in the given range:
+///
+/// - Apply the edits that errors suggest.
+/// - For unknown identifiers, create a missing assignment or add a `use` of a
+///   sibling module or of Core if it exports something with that name.
+/// - Remove definitions and `use`s that are never used.
+/// - Convert `needs` reasons that are definitely not texts into texts.
+pub fn code_actions(
+    db: &Database,
+    module: Module,
+    uri: &Url,
+    range: Range<Offset>,
+) -> Vec<CodeActionOrCommand> {
+    let Some(text) = db.get_module_content_as_string(module.clone()) else {
+        return vec![];
+    };
+    let context = Context {
+        db,
+        module,
+        uri,
+        text: &text,
+        range,
+    };
+
+    let mut actions = vec![];
+    if let Ok((_, errors)) = db.mir(
+        ExecutionTarget::Module(context.module.clone()),
+        TracingConfig::off(),
+    ) {
+        for error in errors.iter().sorted_by_key(|it| it.span.start) {
+            if error.module == context.module && context.intersects(&error.span) {
+                context.actions_for_error(error, &mut actions);
+            }
+        }
+    }
+    if let Ok((hir, _)) = db.hir(context.module.clone()) {
+        let mut referenced = FxHashSet::default();
+        hir.collect_referenced_ids(&mut referenced);
+        context.remove_unused_definitions(&hir, &referenced, &mut actions);
+        context.convert_needs_reasons_to_texts(&hir, &mut actions);
+    }
+    context.remove_unused_uses(&mut actions);
+    actions
+}
+
+struct Context<'a> {
+    db: &'a Database,
+    module: Module,
+    uri: &'a Url,
+    text: &'a str,
+    range: Range<Offset>,
+}
+impl<'a> Context<'a> {
+    fn intersects(&self, span: &Range<Offset>) -> bool {
+        span.start <= self.range.end && self.range.start <= span.end
+    }
+
+    fn actions_for_error(&self, error: &CompilerError, actions: &mut Vec<CodeActionOrCommand>) {
+        for suggestion in &error.suggestions {
+            actions.push(
+                self.quick_fix(
+                    suggestion.message.clone(),
+                    suggestion
+                        .edits
+                        .iter()
+                        .map(|edit| self.replace(edit.span.clone(), edit.replacement.clone()))
+                        .collect(),
+                ),
+            );
+        }
+
+        let CompilerErrorPayload::Hir(HirError::UnknownReference { name }) = &error.payload else {
+            return;
+        };
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return;
+        }
+
+        let line_start = self.line_start(error.span.start);
+        let indentation = &self.text[*line_start..]
+            [..self.text[*line_start..].len() - self.text[*line_start..].trim_start().len()];
+        actions.push(self.quick_fix(
+            format!("Create `{name}`"),
+            vec![self.insert(line_start, format!("{indentation}{name} = Nothing\n"))],
+        ));
+
+        if let Some(edit) = self.add_use_of_sibling_module(name) {
+            actions.push(self.quick_fix(format!("Add `use` of module `{name}`"), vec![edit]));
+        } else if let Some(edit) = self.add_use_of_core(name) {
+            actions.push(self.quick_fix(format!("Import `{name}` from Core"), vec![edit]));
+        }
+    }
+    fn add_use_of_sibling_module(&self, name: &str) -> Option<TextEdit> {
+        let path = format!(".{name}");
+        let sibling = UsePath::parse(&path)
+            .and_then(|it| it.resolve_relative_to(&self.module))
+            .ok()?;
+        self.db.get_module_content(sibling)?;
+        Some(self.insert(Offset(0), format!("{name} = use \"{path}\"\n")))
+    }
+    fn add_use_of_core(&self, name: &str) -> Option<TextEdit> {
+        let core = UsePath::parse("Core")
+            .and_then(|it| it.resolve_relative_to(&self.module))
+            .ok()?;
+        if core.package() == self.module.package()
+            || !exports(self.db, core).iter().any(|(it, _)| it == name)
+        {
+            return None;
+        }
+
+        // Extend an existing `[…] = use "Core"` if there is one.
+        let mut offset = 0;
+        for line in self.text.split_inclusive('\n') {
+            let names = line
+                .trim_end()
+                .strip_prefix('[')
+                .and_then(|it| it.strip_suffix("] = use \"Core\""));
+            if let Some(names) = names {
+                let names = names
+                    .split(',')
+                    .map(str::trim)
+                    .chain([name])
+                    .sorted()
+                    .dedup()
+                    .join(", ");
+                let line_end = Offset(offset + line.trim_end().len());
+                return Some(self.replace(
+                    Offset(offset)..line_end,
+                    format!("[{names}] = use \"Core\""),
+                ));
+            }
+            offset += line.len();
+        }
+        Some(self.insert(Offset(0), format!("[{name}] = use \"Core\"\n")))
+    }
+
+    fn remove_unused_definitions(
+        &self,
+        body: &Body,
+        referenced: &FxHashSet<hir::Id>,
+        actions: &mut Vec<CodeActionOrCommand>,
+    ) {
+        for (id, expression) in &body.expressions {
+            match expression {
+                Expression::Reference(target)
+                    if body.identifiers.contains_key(id)
+                        && !referenced.contains(id)
+                        && is_assignment(self.db, target)
+                        && !is_use(self.db, target) =>
+                {
+                    let Some(span) = self.db.hir_id_to_span(target) else {
+                        continue;
+                    };
+                    if !self.intersects(&span) {
+                        continue;
+                    }
+                    let line_start = self.line_start(span.start);
+                    if !self.text[*line_start..*span.start].trim().is_empty() {
+                        continue;
+                    }
+                    let line_end = self.text[*span.end..]
+                        .find('\n')
+                        .map_or(self.text.len(), |index| *span.end + index + 1);
+                    actions.push(self.quick_fix(
+                        format!("Remove unused `{}`", body.identifiers[id]),
+                        vec![self.replace(line_start..Offset(line_end), String::new())],
+                    ));
+                }
+                Expression::Function(Function { body, .. }) => {
+                    self.remove_unused_definitions(body, referenced, actions);
+                }
+                Expression::Match { cases, .. } => {
+                    for (_, body) in cases {
+                        self.remove_unused_definitions(body, referenced, actions);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn remove_unused_uses(&self, actions: &mut Vec<CodeActionOrCommand>) {
+        for unused_use in find_unused_uses(self.db, self.module.clone()) {
+            if !unused_use
+                .unused_names
+                .iter()
+                .any(|(_, span)| self.intersects(span))
+            {
+                continue;
+            }
+            let names = unused_use
+                .unused_names
+                .iter()
+                .map(|(name, _)| format!("`{name}`"))
+                .join(", ");
+            actions.push(
+                self.quick_fix(
+                    format!("Remove unused {names}"),
+                    unused_use
+                        .removals
+                        .into_iter()
+                        .map(|range| self.replace(range, String::new()))
+                        .collect(),
+                ),
+            );
+        }
+    }
+
+    fn convert_needs_reasons_to_texts(&self, body: &Body, actions: &mut Vec<CodeActionOrCommand>) {
+        for expression in body.expressions.values() {
+            match expression {
+                Expression::Needs { reason, .. } => {
+                    let is_definitely_not_a_text = matches!(
+                        resolve(self.db, reason),
+                        Some(
+                            Expression::Int(_)
+                                | Expression::Symbol(_)
+                                | Expression::List(_)
+                                | Expression::Struct(_)
+                                | Expression::Function(_)
+                                | Expression::Builtin(_)
+                                | Expression::Extern { .. },
+                        ),
+                    );
+                    if !is_definitely_not_a_text {
+                        continue;
+                    }
+                    let Some(span) = self.db.hir_id_to_span(reason) else {
+                        continue;
+                    };
+                    if !self.intersects(&span) {
+                        continue;
+                    }
+                    let source = &self.text[*span.start..*span.end];
+                    actions.push(self.quick_fix(
+                        "Convert the reason into a text".to_string(),
+                        vec![self.replace(span, format!("\"{{{source}}}\""))],
+                    ));
+                }
+                Expression::Function(Function { body, .. }) => {
+                    self.convert_needs_reasons_to_texts(body, actions);
+                }
+                Expression::Match { cases, .. } => {
+                    for (_, body) in cases {
+                        self.convert_needs_reasons_to_texts(body, actions);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn line_start(&self, offset: Offset) -> Offset {
+        Offset(
+            self.text[..*offset]
+                .rfind('\n')
+                .map_or(0, |index| index + 1),
+        )
+    }
+    fn insert(&self, offset: Offset, new_text: String) -> TextEdit {
+        self.replace(offset..offset, new_text)
+    }
+    fn replace(&self, range: Range<Offset>, new_text: String) -> TextEdit {
+        TextEdit {
+            range: self.db.range_to_lsp_range(self.module.clone(), range),
+            new_text,
+        }
+    }
+    fn quick_fix(&self, title: String, edits: Vec<TextEdit>) -> CodeActionOrCommand {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title,
+            kind: Some(CodeActionKind::QUICKFIX),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(self.uri.clone(), edits)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Unused `use`s are handled by [`Context::remove_unused_uses`].
+fn is_use(db: &Database, id: &hir::Id) -> bool {
+    db.hir_to_ast_id(id)
+        .and_then(|id| db.find_ast(id))
+        .is_some_and(|ast| is_use_assignment(&ast))
+}
@@ -0,0 +1,274 @@
+use super::definitions::{exports, resolve};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    builtin_functions::BuiltinFunction,
+    hir::{self, Body, Expression, Function, FunctionKind, HirDb},
+    module::{Module, ModuleDb, UsePath},
+    position::Offset,
+    utils::AdjustCasingOfFirstLetter,
+};
+use itertools::Itertools;
+use lsp_types::{CompletionItem, CompletionItemKind};
+use rustc_hash::FxHashMap;
+use strum::VariantArray;
+use tracing::debug;
+
+/// Suggests completions at the given offset.
+///
+/// After a dot (`foo.|`), this suggests the keys of the struct `foo` if they
+/// are statically known: exported definitions of modules imported via `use`,
+/// keys of struct literals, and builtin functions for `✨`. Otherwise, this
+/// suggests all identifiers that are in scope.
+pub fn completion<DB>(db: &DB, module: &Module, offset: Offset) -> Vec<CompletionItem>
+where
+    DB: AstToHir + HirDb + ModuleDb,
+{
+    let Some(text) = db.get_module_content_as_string(module.clone()) else {
+        return vec![];
+    };
+    let Ok((hir, _)) = db.hir(module.clone()) else {
+        return vec![];
+    };
+
+    // While typing, the current word or struct access might not be part of
+    // the surrounding function's span yet. Therefore, we look up identifiers
+    // at the start of the word or the struct being accessed.
+    let before_cursor = &text[..*offset];
+    let before_word = before_cursor.trim_end_matches(is_identifier_char);
+    let completions = before_word.trim_end().strip_suffix('.').map_or_else(
+        || local_completions(db, &hir, Offset(before_word.len())),
+        |before_dot| struct_access_completions(db, &hir, module, before_dot.trim_end()),
+    );
+    completions
+        .into_iter()
+        .sorted_by(|a, b| a.label.cmp(&b.label))
+        .collect()
+}
+
+fn local_completions<DB>(db: &DB, hir: &Body, offset: Offset) -> Vec<CompletionItem>
+where
+    DB: AstToHir + HirDb + ?Sized,
+{
+    let mut locals = FxHashMap::default();
+    collect_locals(db, hir, offset, &mut locals);
+    locals
+        .into_iter()
+        .map(|(name, id)| {
+            let (kind, detail) = describe(db, &id);
+            CompletionItem {
+                label: name,
+                kind: Some(kind),
+                detail: Some(detail),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+fn struct_access_completions<DB>(
+    db: &DB,
+    hir: &Body,
+    module: &Module,
+    before_dot: &str,
+) -> Vec<CompletionItem>
+where
+    DB: AstToHir + HirDb + ?Sized,
+{
+    let receiver_start = before_dot
+        .rfind(|c: char| !is_identifier_char(c) && c != '✨')
+        .map_or(0, |index| {
+            index + before_dot[index..].chars().next().unwrap().len_utf8()
+        });
+    let receiver = &before_dot[receiver_start..];
+    debug!("Completing struct keys of `{receiver}`.");
+    if receiver == "✨" {
+        return builtin_function_completions();
+    }
+
+    let mut locals = FxHashMap::default();
+    collect_locals(db, hir, Offset(receiver_start), &mut locals);
+    locals
+        .get(receiver)
+        .map(|id| struct_key_completions(db, module, id))
+        .unwrap_or_default()
+}
+
+const fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Collects all identifiers that are visible at the given offset, mapping
+/// their names to their IDs. Later definitions shadow earlier ones.
+fn collect_locals<DB>(db: &DB, body: &Body, offset: Offset, locals: &mut FxHashMap<String, hir::Id>)
+where
+    DB: AstToHir + ?Sized,
+{
+    for (id, expression) in &body.expressions {
+        if let Some(span) = db.hir_id_to_span(id) {
+            if span.start <= offset && offset <= span.end {
+                // The cursor is inside this expression, so neither it nor any
+                // of the following definitions are visible yet.
+                match expression {
+                    Expression::Function(Function {
+                        parameters, body, ..
+                    }) => {
+                        for parameter in parameters {
+                            if let Some(name) = body.identifiers.get(parameter) {
+                                locals.insert(name.clone(), parameter.clone());
+                            }
+                        }
+                        collect_locals(db, body, offset, locals);
+                    }
+                    Expression::Match { cases, .. } => {
+                        for (_, body) in cases {
+                            if body_contains(db, body, offset) {
+                                collect_locals(db, body, offset, locals);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            if span.start > offset {
+                return;
+            }
+        }
+
+        if let Some(name) = body.identifiers.get(id) {
+            locals.insert(name.clone(), id.clone());
+        }
+    }
+}
+fn body_contains<DB>(db: &DB, body: &Body, offset: Offset) -> bool
+where
+    DB: AstToHir + ?Sized,
+{
+    let spans = body
+        .expressions
+        .keys()
+        .filter_map(|id| db.hir_id_to_span(id))
+        .collect_vec();
+    let Some(start) = spans.iter().map(|span| span.start).min() else {
+        return false;
+    };
+    let end = spans.iter().map(|span| span.end).max().unwrap();
+    start <= offset && offset <= end
+}
+
+fn struct_key_completions<DB>(db: &DB, module: &Module, id: &hir::Id) -> Vec<CompletionItem>
+where
+    DB: AstToHir + HirDb + ?Sized,
+{
+    match resolve(db, id) {
+        Some(Expression::Call {
+            function,
+            arguments,
+        }) => {
+            let is_use = matches!(
+                resolve(db, &function),
+                Some(Expression::Function(Function {
+                    kind: FunctionKind::Use,
+                    ..
+                })),
+            );
+            let [path] = arguments.as_slice() else {
+                return vec![];
+            };
+            let Some(Expression::Text(path)) = resolve(db, path) else {
+                return vec![];
+            };
+            if !is_use {
+                return vec![];
+            }
+            let Some(imported_module) = UsePath::parse(&path)
+                .and_then(|path| path.resolve_relative_to(module))
+                .ok()
+            else {
+                return vec![];
+            };
+            export_completions(db, imported_module)
+        }
+        Some(Expression::Struct(fields)) => fields
+            .iter()
+            .filter_map(|(key, value)| {
+                let Some(Expression::Symbol(key)) = resolve(db, key) else {
+                    return None;
+                };
+                let (_, detail) = describe(db, value);
+                Some(CompletionItem {
+                    label: key.lowercase_first_letter(),
+                    kind: Some(CompletionItemKind::FIELD),
+                    detail: Some(detail),
+                    ..Default::default()
+                })
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+fn export_completions<DB>(db: &DB, module: Module) -> Vec<CompletionItem>
+where
+    DB: AstToHir + HirDb + ?Sized,
+{
+    exports(db, module)
+        .into_iter()
+        .map(|(name, value)| {
+            let (kind, detail) = describe(db, &value);
+            CompletionItem {
+                label: name,
+                kind: Some(kind),
+                detail: Some(detail),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+fn builtin_function_completions() -> Vec<CompletionItem> {
+    BuiltinFunction::VARIANTS
+        .iter()
+        .map(|builtin_function| CompletionItem {
+            label: format!("{builtin_function:?}").lowercase_first_letter(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some("builtin function".to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn describe<DB>(db: &DB, id: &hir::Id) -> (CompletionItemKind, String)
+where
+    DB: HirDb + ?Sized,
+{
+    match resolve(db, id) {
+        Some(Expression::Function(Function {
+            parameters, kind, ..
+        })) => {
+            let detail = match kind {
+                FunctionKind::Use => "use function".to_string(),
+                FunctionKind::Normal | FunctionKind::CurlyBraces => match parameters.len() {
+                    1 => "function with 1 parameter".to_string(),
+                    count => format!("function with {count} parameters"),
+                },
+            };
+            (CompletionItemKind::FUNCTION, detail)
+        }
+        Some(Expression::Builtin(_)) => {
+            (CompletionItemKind::FUNCTION, "builtin function".to_string())
+        }
+        Some(Expression::Extern { signature, .. }) => (
+            CompletionItemKind::FUNCTION,
+            format!("extern function {signature}"),
+        ),
+        Some(Expression::Call { .. } | Expression::UseModule { .. }) => {
+            (CompletionItemKind::VARIABLE, "value".to_string())
+        }
+        Some(Expression::Int(_)) => (CompletionItemKind::CONSTANT, "int".to_string()),
+        Some(Expression::Text(_)) => (CompletionItemKind::CONSTANT, "text".to_string()),
+        Some(Expression::Symbol(_)) => (CompletionItemKind::ENUM_MEMBER, "tag".to_string()),
+        Some(Expression::List(_)) => (CompletionItemKind::VARIABLE, "list".to_string()),
+        Some(Expression::Struct(_)) => (CompletionItemKind::STRUCT, "struct".to_string()),
+        // Parameters don't have an expression.
+        None => (CompletionItemKind::VARIABLE, "parameter".to_string()),
+        Some(_) => (CompletionItemKind::VARIABLE, "value".to_string()),
+    }
+}
@@ -0,0 +1,63 @@
+//! Helpers for finding what identifiers refer to that are shared by multiple
+//! features.
+
+use candy_frontend::{
+    ast::{AstDb, AstKind},
+    ast_to_hir::AstToHir,
+    hir::{self, Expression, HirDb},
+    module::Module,
+    utils::AdjustCasingOfFirstLetter,
+};
+
+/// Follows references to find the expression that defines the value of the
+/// given ID.
+pub fn resolve<DB>(db: &DB, id: &hir::Id) -> Option<Expression>
+where
+    DB: HirDb + ?Sized,
+{
+    resolve_with_id(db, id).map(|(_, expression)| expression)
+}
+/// Like [`resolve`], but also returns the ID of the defining expression.
+pub fn resolve_with_id<DB>(db: &DB, id: &hir::Id) -> Option<(hir::Id, Expression)>
+where
+    DB: HirDb + ?Sized,
+{
+    let mut id = id.clone();
+    loop {
+        match db.find_expression(id.clone())? {
+            Expression::Reference(target) => id = target,
+            expression => return Some((id, expression)),
+        }
+    }
+}
+
+/// Whether the HIR ID belongs to an assignment like `foo = …` in the source.
+pub fn is_assignment<DB>(db: &DB, id: &hir::Id) -> bool
+where
+    DB: AstDb + AstToHir + ?Sized,
+{
+    db.hir_to_ast_id(id)
+        .and_then(|id| db.find_ast(id))
+        .is_some_and(|ast| matches!(ast.kind, AstKind::Assignment(_)))
+}
+
+/// The names (e.g., `foo`) and value IDs of the module's exports.
+pub fn exports<DB>(db: &DB, module: Module) -> Vec<(String, hir::Id)>
+where
+    DB: AstToHir + ?Sized,
+{
+    let Ok((hir, _)) = db.hir(module) else {
+        return vec![];
+    };
+    // The last expression of a module is the struct of its exports.
+    let Some((_, Expression::Struct(exports))) = hir.expressions.iter().last() else {
+        return vec![];
+    };
+    exports
+        .iter()
+        .filter_map(|(key, value)| match hir.expressions.get(key) {
+            Some(Expression::Symbol(key)) => Some((key.lowercase_first_letter(), value.clone())),
+            _ => None,
+        })
+        .collect()
+}
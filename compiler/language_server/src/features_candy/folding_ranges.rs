@@ -61,6 +61,7 @@ where
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::RawTextPrefix
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => {}
             // TODO: support folding ranges for comments
@@ -96,6 +97,7 @@ where
                 self.visit_cst(receiver);
                 self.visit_csts(arguments);
             }
+            CstKind::NamedArgument { value, .. } => self.visit_cst(value),
             // TODO: support folding ranges for lists
             CstKind::List { items, .. } => self.visit_csts(items),
             CstKind::ListItem { value, .. } => self.visit_cst(value),
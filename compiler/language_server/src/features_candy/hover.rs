@@ -0,0 +1,88 @@
+use super::{
+    analyzer::{
+        insights::{Hint, HintKind},
+        utils::IdToEndOfLine,
+    },
+    definitions::is_assignment,
+};
+use crate::{database::Database, utils::LspPositionConversion};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    comment::string_to_rcst::CommentStringToRcst,
+    cst::{CstDb, CstKind},
+    hir::{Expression, HirDb},
+    module::Module,
+    position::Offset,
+};
+use itertools::Itertools;
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+use tracing::debug;
+
+/// Shows the definition of the hovered identifier, its doc comment, and – if
+/// the analyzer already evaluated or fuzzed it – its value or a summary of the
+/// fuzzing.
+pub fn hover(db: &Database, module: Module, offset: Offset, hints: &[Hint]) -> Option<Hover> {
+    let origin_cst = db.find_cst_by_offset(module.clone(), offset);
+    let CstKind::Identifier(_) = origin_cst.kind else {
+        return None;
+    };
+
+    let origin_hir_id = db.cst_to_last_hir_id(module.clone(), origin_cst.data.id)?;
+    let mut definition = if is_assignment(db, &origin_hir_id) {
+        origin_hir_id
+    } else {
+        let Expression::Reference(target) = db.find_expression(origin_hir_id)? else {
+            return None;
+        };
+        target
+    };
+    // References may point to an identifier that in turn references the
+    // actual assignment.
+    while !is_assignment(db, &definition) {
+        match db.find_expression(definition.clone()) {
+            Some(Expression::Reference(target)) => definition = target,
+            _ => break,
+        }
+    }
+    debug!("Hovering {definition}");
+    let definition_cst = db.find_cst(module.clone(), db.hir_to_cst_id(&definition)?);
+
+    let mut sections = vec![];
+    // For functions, the first line contains the signature. For most other
+    // values, it contains the whole definition.
+    let definition_source = definition_cst.to_string();
+    let first_line = definition_source.trim().lines().next().unwrap_or_default();
+    sections.push(format!("```candy\n{}\n```", first_line.trim_end()));
+
+    let value_position = db.id_to_end_of_line(definition.clone());
+    if let Some(hint) = hints
+        .iter()
+        .find(|hint| hint.kind == HintKind::Value && Some(hint.position) == value_position)
+    {
+        sections.push(format!("Value: `{}`", hint.text));
+    }
+    if let Some(details) = hints
+        .iter()
+        .filter(|hint| {
+            hint.kind == HintKind::FuzzingStatus && Some(hint.position) == value_position
+        })
+        .find_map(|hint| hint.details.as_ref())
+    {
+        sections.push(details.clone());
+    }
+
+    if matches!(definition_cst.kind, CstKind::Assignment { .. }) {
+        let comment = db.comment_rcst(definition).iter().join("");
+        if !comment.trim().is_empty() {
+            sections.push(comment.trim().to_string());
+        }
+    }
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: sections.join("\n\n---\n\n"),
+        }),
+        range: Some(db.range_to_lsp_range(module, origin_cst.data.span)),
+    })
+}
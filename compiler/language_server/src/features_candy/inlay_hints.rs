@@ -0,0 +1,71 @@
+use super::analyzer::insights::{Hint, HintKind};
+use lsp_types::{
+    InlayHint, InlayHintLabel, InlayHintTooltip, MarkupContent, MarkupKind, Range, Url,
+};
+use serde::{Deserialize, Serialize};
+
+/// Stored in [`InlayHint::data`] so that resolving a hint can be routed to the
+/// features of its document.
+#[derive(Deserialize, Serialize)]
+pub struct InlayHintData {
+    pub uri: Url,
+    pub kind: HintKind,
+}
+
+#[must_use]
+pub fn inlay_hints(uri: &Url, hints: &[Hint], range: Range) -> Vec<InlayHint> {
+    hints
+        .iter()
+        .filter(|hint| range.start <= hint.position && hint.position <= range.end)
+        .map(|hint| InlayHint {
+            position: hint.position,
+            label: InlayHintLabel::String(hint.text.clone()),
+            kind: None,
+            text_edits: None,
+            tooltip: hint.details.clone().map(|details| {
+                InlayHintTooltip::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: details,
+                })
+            }),
+            padding_left: Some(true),
+            padding_right: None,
+            data: Some(
+                serde_json::to_value(InlayHintData {
+                    uri: uri.clone(),
+                    kind: hint.kind,
+                })
+                .unwrap(),
+            ),
+        })
+        .collect()
+}
+
+#[must_use]
+pub fn resolve_inlay_hint(mut hint: InlayHint) -> InlayHint {
+    let Some(data) = hint
+        .data
+        .clone()
+        .and_then(|it| serde_json::from_value::<InlayHintData>(it).ok())
+    else {
+        return hint;
+    };
+    let tooltip = match data.kind {
+        HintKind::Value => "The value of this expression.",
+        HintKind::Panic => "This code panics.",
+        HintKind::FuzzingStatus => "How much of this function the fuzzer covered so far.",
+        HintKind::SampleInputReturningNormally => {
+            "An input found by the fuzzer for which this function returns normally."
+        }
+        HintKind::SampleInputPanickingWithCallerResponsible => {
+            "An input found by the fuzzer that doesn't fulfill this function's needs."
+        }
+        HintKind::SampleInputPanickingWithInternalCodeResponsible => {
+            "An input found by the fuzzer for which this function panics internally."
+        }
+    };
+    if hint.tooltip.is_none() {
+        hint.tooltip = Some(InlayHintTooltip::String(tooltip.to_string()));
+    }
+    hint
+}
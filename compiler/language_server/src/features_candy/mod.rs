@@ -1,36 +1,56 @@
 use self::{
+    analyzer::insights::Hint,
+    code_actions::code_actions,
+    completion::completion,
     find_definition::find_definition,
     folding_ranges::folding_ranges,
-    references::{reference_query_for_offset, references, ReferenceQuery},
+    hover::hover,
+    inlay_hints::{inlay_hints, resolve_inlay_hint},
+    references::{
+        reference_query_for_offset, references, references_in_other_modules, ReferenceQuery,
+    },
     semantic_tokens::semantic_tokens,
+    signature_help::signature_help,
+    symbols::{document_symbols, updated_symbol_index, workspace_symbols},
 };
 use crate::{
     database::Database,
     features::{LanguageFeatures, Reference, RenameError},
     server::AnalyzerClient,
-    utils::{lsp_range_to_range_raw, module_from_url, LspPositionConversion},
+    utils::{lsp_range_to_range_raw, module_from_url, module_to_url, LspPositionConversion},
 };
 use async_trait::async_trait;
 use candy_formatter::Formatter;
 use candy_frontend::{
-    module::{Module, ModuleDb, ModuleKind, MutableModuleProviderOwner, PackagesPath},
+    cancellation::CancellationToken,
+    module::{Module, ModuleDb, ModuleKind, MutableModuleProviderOwner, Package, PackagesPath},
+    position::Offset,
     rcst_to_cst::RcstToCst,
+    symbol_index::SymbolIndex,
 };
 use lsp_types::{
-    notification::Notification, FoldingRange, LocationLink, SemanticToken,
+    notification::Notification, CodeActionOrCommand, CompletionItem, DocumentSymbol, FoldingRange,
+    Hover, InlayHint, LocationLink, SemanticToken, SignatureHelp, SymbolInformation,
     TextDocumentContentChangeEvent, TextEdit, Url,
 };
 use regex::Regex;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, thread};
+use std::{collections::HashMap, ops::Range, sync::Arc, thread};
 use tokio::sync::{mpsc::Sender, Mutex};
 
 pub mod analyzer;
+pub mod code_actions;
+pub mod completion;
+mod definitions;
 pub mod find_definition;
 pub mod folding_ranges;
+pub mod hover;
+pub mod inlay_hints;
 pub mod references;
 pub mod semantic_tokens;
+pub mod signature_help;
+pub mod symbols;
 
 #[derive(Serialize, Deserialize)]
 pub struct ServerStatusNotification {
@@ -45,20 +65,41 @@ impl Notification for ServerStatusNotification {
 #[derive(Debug)]
 pub struct CandyFeatures {
     hints_events_sender: Sender<analyzer::Message>,
+    /// Aborts the analyzer's current work when we send it a message.
+    analyzer_cancellation_token: CancellationToken,
+    /// The hints most recently reported by the analyzer.
+    hints: Arc<Mutex<FxHashMap<Module, Vec<Hint>>>>,
+    /// Loaded from disk when first needed and updated before each use.
+    symbol_indexes: Mutex<FxHashMap<Package, SymbolIndex>>,
 }
 impl CandyFeatures {
     #[must_use]
     pub fn new(packages_path: PackagesPath, client: AnalyzerClient) -> Self {
         let (hints_events_sender, hints_events_receiver) = tokio::sync::mpsc::channel(1024);
+        let hints = Arc::new(Mutex::new(FxHashMap::default()));
+        let analyzer_hints = hints.clone();
+        let analyzer_cancellation_token = CancellationToken::default();
+        let cancellation_token = analyzer_cancellation_token.clone();
         thread::spawn(move || {
-            analyzer::run_server(packages_path, hints_events_receiver, client);
+            analyzer::run_server(
+                packages_path,
+                hints_events_receiver,
+                client,
+                analyzer_hints,
+                cancellation_token,
+            );
         });
         Self {
             hints_events_sender,
+            analyzer_cancellation_token,
+            hints,
+            symbol_indexes: Mutex::default(),
         }
     }
 
     async fn send_to_analyzer(&self, event: analyzer::Message) {
+        // Whatever the analyzer is working on is outdated now.
+        self.analyzer_cancellation_token.cancel();
         match self.hints_events_sender.send(event).await {
             Ok(()) => {}
             Err(error) => panic!("Couldn't send message to hints server: {error:?}."),
@@ -107,6 +148,7 @@ impl LanguageFeatures for CandyFeatures {
             let module = decode_module(&uri, &db.packages_path);
             let content = apply_text_changes(&db, module.clone(), changes).into_bytes();
             db.did_change_module(&module, content.clone());
+            db.start_recording_edit(module.clone());
             (module, content)
         };
         self.send_to_analyzer(analyzer::Message::UpdateModule(module, content))
@@ -126,6 +168,46 @@ impl LanguageFeatures for CandyFeatures {
             .await;
     }
 
+    fn supports_code_actions(&self) -> bool {
+        true
+    }
+    async fn code_actions(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        range: lsp_types::Range,
+    ) -> Vec<CodeActionOrCommand> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let range = db.lsp_position_to_offset(module.clone(), range.start)
+            ..db.lsp_position_to_offset(module.clone(), range.end);
+        code_actions(&db, module, &uri, range)
+    }
+
+    fn supports_completion(&self) -> bool {
+        true
+    }
+    async fn completion(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Vec<CompletionItem> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+        completion(&*db, &module, offset)
+    }
+
+    fn supports_document_symbols(&self) -> bool {
+        true
+    }
+    async fn document_symbols(&self, db: &Mutex<Database>, uri: Url) -> Vec<DocumentSymbol> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        document_symbols(&db, &module)
+    }
+
     fn supports_folding_ranges(&self) -> bool {
         true
     }
@@ -141,18 +223,19 @@ impl LanguageFeatures for CandyFeatures {
     async fn format(&self, db: &Mutex<Database>, uri: Url) -> Vec<TextEdit> {
         let db = db.lock().await;
         let module = decode_module(&uri, &db.packages_path);
-        let Ok(cst) = db.cst(module.clone()) else {
-            return vec![];
-        };
-
-        cst.format_to_edits()
-            .finish()
-            .into_iter()
-            .map(|it| TextEdit {
-                range: db.range_to_lsp_range(module.clone(), it.range),
-                new_text: it.new_text,
-            })
-            .collect()
+        format(&db, &module, None)
+    }
+    async fn format_range(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        range: lsp_types::Range,
+    ) -> Vec<TextEdit> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let range = db.lsp_position_to_offset(module.clone(), range.start)
+            ..db.lsp_position_to_offset(module.clone(), range.end);
+        format(&db, &module, Some(&range))
     }
 
     fn supports_find_definition(&self) -> bool {
@@ -170,6 +253,41 @@ impl LanguageFeatures for CandyFeatures {
         find_definition(&db, module, offset)
     }
 
+    fn supports_hover(&self) -> bool {
+        true
+    }
+    async fn hover(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Option<Hover> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+        let hints = self.hints.lock().await;
+        let hints = hints.get(&module).map_or(&[][..], |hints| hints.as_slice());
+        hover(&db, module, offset, hints)
+    }
+
+    fn supports_inlay_hints(&self) -> bool {
+        true
+    }
+    async fn inlay_hints(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        range: lsp_types::Range,
+    ) -> Vec<InlayHint> {
+        let module = decode_module(&uri, &db.lock().await.packages_path);
+        let hints = self.hints.lock().await;
+        let hints = hints.get(&module).map_or(&[][..], |hints| hints.as_slice());
+        inlay_hints(&uri, hints, range)
+    }
+    async fn resolve_inlay_hint(&self, hint: InlayHint) -> InlayHint {
+        resolve_inlay_hint(hint)
+    }
+
     fn supports_references(&self) -> bool {
         true
     }
@@ -178,7 +296,7 @@ impl LanguageFeatures for CandyFeatures {
         db: &Mutex<Database>,
         uri: Url,
         position: lsp_types::Position,
-        _only_in_same_document: bool,
+        only_in_same_document: bool,
         include_declaration: bool,
     ) -> FxHashMap<Url, Vec<Reference>> {
         let db = db.lock().await;
@@ -186,11 +304,23 @@ impl LanguageFeatures for CandyFeatures {
         let offset = db.lsp_position_to_offset(module.clone(), position);
 
         let mut all_references = FxHashMap::default();
-        let references = references(&*db, module, offset, include_declaration);
-        // TODO: Look for references in all modules
+        let references = references(&*db, module.clone(), offset, include_declaration);
         if !references.is_empty() {
             all_references.insert(uri, references);
         }
+
+        if !only_in_same_document
+            && let Some((ReferenceQuery::Id(id), _)) =
+                reference_query_for_offset(&*db, module, offset)
+        {
+            let mut symbol_indexes = self.symbol_indexes.lock().await;
+            let index = updated_symbol_index(&db, &mut symbol_indexes, id.module.package());
+            for (module, references) in references_in_other_modules(&*db, index, &id) {
+                if let Some(uri) = module_to_url(&module, &db.packages_path) {
+                    all_references.insert(uri, references);
+                }
+            }
+        }
         all_references
     }
 
@@ -271,6 +401,57 @@ impl LanguageFeatures for CandyFeatures {
         let module = decode_module(&uri, &db.packages_path);
         semantic_tokens(&*db, module)
     }
+
+    fn supports_signature_help(&self) -> bool {
+        true
+    }
+    async fn signature_help(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Option<SignatureHelp> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+        signature_help(&db, module, offset)
+    }
+
+    fn supports_workspace_symbols(&self) -> bool {
+        true
+    }
+    async fn workspace_symbols(
+        &self,
+        db: &Mutex<Database>,
+        query: String,
+    ) -> Vec<SymbolInformation> {
+        let mut db = db.lock().await;
+        let mut symbol_indexes = self.symbol_indexes.lock().await;
+        workspace_symbols(&mut db, &mut symbol_indexes, &query)
+    }
+}
+
+/// Formats the whole module and converts the resulting edits to LSP edits.
+///
+/// If a range is given, only edits touching that range are returned.
+fn format(db: &Database, module: &Module, range: Option<&Range<Offset>>) -> Vec<TextEdit> {
+    let Ok(cst) = db.cst(module.clone()) else {
+        return vec![];
+    };
+
+    cst.format_to_edits()
+        .finish()
+        .into_iter()
+        .filter(|it| {
+            range.map_or(true, |range| {
+                it.range.start <= range.end && range.start <= it.range.end
+            })
+        })
+        .map(|it| TextEdit {
+            range: db.range_to_lsp_range(module.clone(), it.range),
+            new_text: it.new_text,
+        })
+        .collect()
 }
 
 fn decode_module(uri: &Url, packages_path: &PackagesPath) -> Module {
@@ -5,9 +5,10 @@ use candy_frontend::{
     hir::{self, Body, Expression, Function, HirDb},
     module::{Module, ModuleDb},
     position::{Offset, PositionConversionDb},
+    symbol_index::SymbolIndex,
 };
 use num_bigint::BigUint;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::ops::Range;
 use tracing::{debug, info};
 
@@ -26,6 +27,54 @@ where
     find_references(db, query, include_declaration)
 }
 
+/// Finds references to an exported definition in the other modules of its
+/// package.
+///
+/// Only modules that use the definition's module and mention its name
+/// according to the symbol index get lowered. In those, the references are the
+/// ones to the identifier that the `use` binds, e.g., `foo` in
+/// `[foo] = use "..bar"`.
+pub fn references_in_other_modules<DB>(
+    db: &DB,
+    index: &SymbolIndex,
+    id: &hir::Id,
+) -> FxHashMap<Module, Vec<Reference>>
+where
+    DB: CstDb + HirDb + ModuleDb + PositionConversionDb,
+{
+    let Some(span) = db.hir_id_to_span(id) else {
+        return FxHashMap::default();
+    };
+    let CstKind::Identifier(name) = db.find_cst_by_offset(id.module.clone(), span.start).kind
+    else {
+        return FxHashMap::default();
+    };
+    let is_exported = index.module(&id.module).is_some_and(|module_index| {
+        module_index.definitions.iter().any(|definition| {
+            definition.is_exported
+                && definition.name == name
+                && definition.span.contains(&span.start)
+        })
+    });
+    if !is_exported {
+        return FxHashMap::default();
+    }
+
+    index
+        .modules_possibly_referencing(&id.module, &name)
+        .into_iter()
+        .filter_map(|module| {
+            let binding = index
+                .module(&module)?
+                .definitions
+                .iter()
+                .find(|definition| definition.name == name)?;
+            let references = references(db, module.clone(), binding.name_span.start, true);
+            (!references.is_empty()).then_some((module, references))
+        })
+        .collect()
+}
+
 pub fn reference_query_for_offset<DB>(
     db: &DB,
     module: Module,
@@ -183,7 +232,7 @@ where
                 // way. Therfore, we already visit them in [visit_body].
                 self.visit_body(body);
             }
-            Expression::Builtin(_) => {}
+            Expression::Builtin(_) | Expression::Extern { .. } => {}
             Expression::Call {
                 function,
                 arguments,
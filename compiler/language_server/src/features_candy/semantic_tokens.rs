@@ -64,7 +64,8 @@ fn visit_cst(
             SemanticTokenType::Operator,
             EnumSet::empty(),
         ),
-        CstKind::Octothorpe => {} // handled by parent
+        CstKind::Octothorpe => {}    // handled by parent
+        CstKind::RawTextPrefix => {} // handled by parent
         CstKind::Whitespace(_) | CstKind::Newline(_) => {}
         CstKind::Comment { octothorpe, .. } => {
             visit_cst(builder, octothorpe, None);
@@ -94,9 +95,17 @@ fn visit_cst(
             EnumSet::empty(),
         ),
         CstKind::OpeningText {
+            raw_prefix,
             opening_single_quotes,
             opening_double_quote,
         } => {
+            if let Some(raw_prefix) = raw_prefix {
+                builder.add(
+                    raw_prefix.data.span.clone(),
+                    SemanticTokenType::Text,
+                    EnumSet::empty(),
+                );
+            }
             for opening_single_quote in opening_single_quotes {
                 builder.add(
                     opening_single_quote.data.span.clone(),
@@ -178,6 +187,11 @@ fn visit_cst(
             visit_cst(builder, receiver, Some(SemanticTokenType::Function));
             visit_csts(builder, arguments, None);
         }
+        CstKind::NamedArgument { name, colon, value } => {
+            visit_cst(builder, name, Some(SemanticTokenType::Parameter));
+            visit_cst(builder, colon, None);
+            visit_cst(builder, value, None);
+        }
         CstKind::List {
             opening_parenthesis,
             items,
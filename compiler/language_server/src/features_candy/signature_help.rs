@@ -0,0 +1,210 @@
+use super::definitions::resolve_with_id;
+use crate::database::Database;
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    cst::{Cst, CstDb, CstKind, UnwrapWhitespaceAndComment},
+    hir::{self, Expression, Function},
+    module::{Module, ModuleDb},
+    position::Offset,
+    rcst_to_cst::RcstToCst,
+};
+use itertools::Itertools;
+use lsp_types::{ParameterInformation, ParameterLabel, SignatureHelp, SignatureInformation};
+use rustc_hash::FxHashMap;
+
+/// Shows the parameters of the function called at the given offset and
+/// highlights the one the cursor is at.
+///
+/// Calls without any arguments yet (e.g., while typing `foo |`) don't appear in
+/// the CST as calls, so we fall back to the identifier before the cursor.
+pub fn signature_help(db: &Database, module: Module, offset: Offset) -> Option<SignatureHelp> {
+    let csts = db.cst(module.clone()).ok()?;
+    let (receiver, active_argument) =
+        if let Some((receiver, arguments)) = csts.iter().find_map(|it| find_call(it, offset)) {
+            (
+                receiver.unwrap_whitespace_and_comment(),
+                active_argument(arguments, offset),
+            )
+        } else {
+            (
+                identifier_before(db, module.clone(), offset)?,
+                ActiveArgument::Positional(0),
+            )
+        };
+    let CstKind::Identifier(name) = &receiver.kind else {
+        return None;
+    };
+
+    let hir_id = db.cst_to_last_hir_id(module, receiver.data.id)?;
+    let parameters = function_parameters(db, &hir_id)?;
+
+    let active_parameter = match active_argument {
+        ActiveArgument::Positional(index) => index,
+        ActiveArgument::Named(name) => parameters
+            .iter()
+            .position(|(parameter, _)| parameter == &name)
+            .unwrap_or(parameters.len()),
+    };
+
+    let mut label = name.clone();
+    let parameters = parameters
+        .iter()
+        .map(|(parameter, default_value)| {
+            label.push(' ');
+            let start = label.len();
+            label.push_str(parameter);
+            if let Some(default_value) = default_value {
+                label.push_str(": ");
+                label.push_str(default_value);
+            }
+            ParameterInformation {
+                label: ParameterLabel::LabelOffsets([
+                    start.try_into().unwrap(),
+                    label.len().try_into().unwrap(),
+                ]),
+                documentation: None,
+            }
+        })
+        .collect_vec();
+    // If more arguments are passed than the function accepts, no parameter is
+    // highlighted.
+    let active_parameter = active_parameter.try_into().unwrap();
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter: None,
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    })
+}
+
+/// Finds the innermost call whose arguments contain the offset.
+fn find_call(cst: &Cst, offset: Offset) -> Option<(&Cst, &[Cst])> {
+    if offset < cst.data.span.start || cst.data.span.end < offset {
+        return None;
+    }
+    if let Some(call) = cst
+        .children()
+        .into_iter()
+        .find_map(|child| find_call(child, offset))
+    {
+        return Some(call);
+    }
+
+    match &cst.kind {
+        CstKind::Call {
+            receiver,
+            arguments,
+        } if receiver.display_span().end < offset => Some((receiver, arguments)),
+        _ => None,
+    }
+}
+
+enum ActiveArgument {
+    Positional(usize),
+    Named(String),
+}
+/// Named arguments don't take up a position, so only positional arguments
+/// before the cursor are counted.
+fn active_argument(arguments: &[Cst], offset: Offset) -> ActiveArgument {
+    let num_arguments_before = arguments
+        .iter()
+        .take_while(|argument| argument.display_span().end < offset)
+        .count();
+    let (before, after) = arguments.split_at(num_arguments_before);
+
+    // The cursor is either inside a named argument or right after one that
+    // doesn't have a value yet (e.g., while typing `foo count: |`).
+    let current = after
+        .first()
+        .filter(|argument| argument.display_span().start <= offset)
+        .or_else(|| {
+            before.last().filter(|argument| {
+                matches!(
+                    &argument.unwrap_whitespace_and_comment().kind,
+                    CstKind::NamedArgument { value, .. } if value.kind.is_error(),
+                )
+            })
+        });
+    if let Some(current) = current
+        && let CstKind::NamedArgument { name, .. } = &current.unwrap_whitespace_and_comment().kind
+        && let CstKind::Identifier(name) = &name.kind
+    {
+        return ActiveArgument::Named(name.clone());
+    }
+
+    ActiveArgument::Positional(
+        before
+            .iter()
+            .filter(|argument| {
+                !argument
+                    .unwrap_whitespace_and_comment()
+                    .kind
+                    .is_named_argument()
+            })
+            .count(),
+    )
+}
+
+fn identifier_before(db: &Database, module: Module, offset: Offset) -> Option<Cst> {
+    let text = db.get_module_content_as_string(module.clone())?;
+    let before = text[..*offset].trim_end_matches(' ');
+    if before.len() == *offset || before.is_empty() {
+        return None;
+    }
+    let cst = db.find_cst_by_offset(module, Offset(before.len() - 1));
+    matches!(cst.kind, CstKind::Identifier(_)).then_some(cst)
+}
+
+/// Returns the parameter names and the source code of their default values.
+fn function_parameters(db: &Database, id: &hir::Id) -> Option<Vec<(String, Option<String>)>> {
+    let (id, Expression::Function(Function { parameters, .. })) = resolve_with_id(db, id)? else {
+        return None;
+    };
+    let mut default_values = default_values(db, &id).unwrap_or_default();
+    Some(
+        parameters
+            .iter()
+            .map(|it| {
+                let name = it.keys.last_as_str().unwrap_or_default().to_string();
+                let default_value = default_values.remove(&name);
+                (name, default_value)
+            })
+            .collect(),
+    )
+}
+/// Optional parameters only exist in function definitions like
+/// `foo bar count: 3 = …`, so we look them up in the assignment's CST.
+fn default_values(db: &Database, function_id: &hir::Id) -> Option<FxHashMap<String, String>> {
+    let module = function_id.module.clone();
+    let cst_id = db.hir_to_cst_id(function_id)?;
+    let CstKind::Assignment { left, .. } = db.find_cst(module.clone(), cst_id).kind else {
+        return None;
+    };
+    let CstKind::Call { arguments, .. } = left.unwrap_whitespace_and_comment().kind else {
+        return None;
+    };
+
+    let text = db.get_module_content_as_string(module)?;
+    Some(
+        arguments
+            .iter()
+            .filter_map(|argument| {
+                let CstKind::NamedArgument { name, value, .. } =
+                    argument.unwrap_whitespace_and_comment().kind
+                else {
+                    return None;
+                };
+                let CstKind::Identifier(name) = name.kind else {
+                    return None;
+                };
+                let span = value.display_span();
+                Some((name, text[*span.start..*span.end].to_string()))
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,115 @@
+use crate::{
+    database::Database,
+    utils::{module_to_url, LspPositionConversion},
+};
+use candy_frontend::{
+    cst::{CstDb, DefinitionKind},
+    module::{Module, ModuleKind, MutableModuleProviderOwner, Package},
+    symbol_index::SymbolIndex,
+};
+use itertools::Itertools;
+use lsp_types::{DocumentSymbol, Location, SymbolInformation, SymbolKind};
+use rustc_hash::FxHashMap;
+use tracing::warn;
+
+const fn symbol_kind(kind: DefinitionKind) -> SymbolKind {
+    match kind {
+        DefinitionKind::Function => SymbolKind::FUNCTION,
+        DefinitionKind::Constant => SymbolKind::CONSTANT,
+        DefinitionKind::Symbol => SymbolKind::ENUM_MEMBER,
+        DefinitionKind::List => SymbolKind::ARRAY,
+        DefinitionKind::Struct => SymbolKind::STRUCT,
+        DefinitionKind::Variable => SymbolKind::VARIABLE,
+    }
+}
+
+pub fn document_symbols(db: &Database, module: &Module) -> Vec<DocumentSymbol> {
+    db.module_skeleton(module.clone())
+        .iter()
+        .map(|symbol| {
+            #[allow(deprecated)]
+            DocumentSymbol {
+                name: symbol.name.clone(),
+                detail: symbol.is_public.then(|| "exported".to_string()),
+                kind: symbol_kind(symbol.kind),
+                tags: None,
+                deprecated: None,
+                range: db.range_to_lsp_range(module.clone(), symbol.span.clone()),
+                selection_range: db.range_to_lsp_range(module.clone(), symbol.name_span.clone()),
+                children: None,
+            }
+        })
+        .collect()
+}
+
+/// Searches the packages of all open modules for definitions containing the
+/// query.
+///
+/// This uses the packages' symbol indexes, so only modules that changed since
+/// the last search get parsed.
+#[allow(clippy::implicit_hasher)]
+pub fn workspace_symbols(
+    db: &mut Database,
+    symbol_indexes: &mut FxHashMap<Package, SymbolIndex>,
+    query: &str,
+) -> Vec<SymbolInformation> {
+    let open_modules = db
+        .get_open_modules()
+        .into_iter()
+        .filter(|module| module.kind() == ModuleKind::Code)
+        .collect_vec();
+    let packages_path = db.packages_path.clone();
+
+    let mut symbols = vec![];
+    for package in open_modules.iter().map(Module::package).unique() {
+        let index = updated_symbol_index(db, symbol_indexes, package);
+        // Open modules outside of packages on disk (e.g., untitled ones)
+        // aren't part of the package's modules.
+        for module in open_modules.iter().filter(|it| it.package() == package) {
+            index.update_module(db, module);
+        }
+
+        for (module, definition) in index.search(package, query) {
+            let Some(uri) = module_to_url(&module, &packages_path) else {
+                continue;
+            };
+            #[allow(deprecated)]
+            symbols.push(SymbolInformation {
+                name: definition.name.clone(),
+                kind: symbol_kind(definition.kind),
+                tags: None,
+                deprecated: None,
+                location: Location {
+                    uri,
+                    range: db.range_to_lsp_range(module.clone(), definition.span.clone()),
+                },
+                container_name: Some(module.to_string()),
+            });
+        }
+    }
+    symbols
+}
+
+/// Loads the package's symbol index if necessary and updates it. If it
+/// changed, it's also saved to disk for the next start of the server.
+#[allow(clippy::implicit_hasher)]
+pub fn updated_symbol_index<'a>(
+    db: &Database,
+    symbol_indexes: &'a mut FxHashMap<Package, SymbolIndex>,
+    package: &Package,
+) -> &'a mut SymbolIndex {
+    let package_path = package.to_path(&db.packages_path);
+    let index = symbol_indexes.entry(package.clone()).or_insert_with(|| {
+        package_path
+            .as_deref()
+            .map(SymbolIndex::load)
+            .unwrap_or_default()
+    });
+    if index.update(db, &db.packages_path, package)
+        && let Some(package_path) = &package_path
+        && let Err(error) = index.save(package_path)
+    {
+        warn!("Couldn't save the symbol index of {package}: {error}");
+    }
+    index
+}
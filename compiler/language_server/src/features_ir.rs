@@ -15,7 +15,7 @@ use candy_frontend::{
         TokenType,
     },
     string_to_rcst::{ModuleError, RcstResult, StringToRcst},
-    TracingConfig,
+    OptimizationConfig, TracingConfig,
 };
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
@@ -119,6 +119,7 @@ impl IrFeatures {
                 db.optimized_mir(
                     ExecutionTarget::Module(config.module.clone()),
                     *tracing_config,
+                    OptimizationConfig::default(),
                 ),
                 *tracing_config,
             ),
@@ -127,6 +128,7 @@ impl IrFeatures {
                 &db.lir(
                     ExecutionTarget::Module(config.module.clone()),
                     *tracing_config,
+                    OptimizationConfig::default(),
                 ),
                 *tracing_config,
             ),
@@ -135,6 +137,7 @@ impl IrFeatures {
                 db.optimized_lir(
                     ExecutionTarget::Module(config.module.clone()),
                     *tracing_config,
+                    OptimizationConfig::default(),
                 ),
                 *tracing_config,
             ),
@@ -144,13 +147,17 @@ impl IrFeatures {
                     db,
                     ExecutionTarget::Module(config.module.clone()),
                     *tracing_config,
+                    OptimizationConfig::default(),
                 )
                 .0,
                 *tracing_config,
             ),
             #[cfg(feature = "inkwell")]
             Ir::LlvmIr => db
-                .llvm_ir(ExecutionTarget::Module(config.module.clone()))
+                .llvm_ir(
+                    ExecutionTarget::Module(config.module.clone()),
+                    OptimizationConfig::default(),
+                )
                 .unwrap(),
         };
 
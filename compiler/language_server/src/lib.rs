@@ -13,6 +13,9 @@
     clippy::missing_errors_doc,
     clippy::missing_panics_doc,
     clippy::module_name_repetitions,
+    // Locks are held across `await`s on purpose, which these lints don't see.
+    clippy::significant_drop_in_scrutinee,
+    clippy::significant_drop_tightening,
     clippy::too_many_lines
 )]
 
@@ -21,6 +24,7 @@ pub mod debug_adapter;
 pub mod features;
 pub mod features_candy;
 pub mod features_ir;
+pub mod recompilation_statistics;
 mod semantic_tokens;
 pub mod server;
 pub mod utils;
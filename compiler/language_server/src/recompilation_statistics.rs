@@ -0,0 +1,141 @@
+//! Statistics about how incremental the language server is.
+//!
+//! After each edit, we count which salsa queries get executed again and which
+//! memoized values salsa validates and reuses instead. Clients can request
+//! these statistics via `candy/recompilationStatistics`.
+//!
+//! Values that salsa already validated in the current revision are reused
+//! without an event, so they don't show up in the counts.
+
+use crate::server::Server;
+use candy_frontend::module::Module;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use salsa::{plumbing::DatabaseOps, DatabaseKeyIndex, Event, EventKind};
+use serde::Serialize;
+use std::collections::VecDeque;
+use tower_lsp::jsonrpc;
+
+/// How many edits we keep statistics for.
+const MAX_EDITS: usize = 100;
+
+#[derive(Debug, Default)]
+pub struct RecompilationStatistics {
+    /// The most recent edits, oldest first. Events are attributed to the last
+    /// one until the next edit starts.
+    edits: VecDeque<EditStatistics>,
+}
+#[derive(Debug)]
+struct EditStatistics {
+    module: Module,
+    /// Keyed by the group and query index of the queries.
+    queries: FxHashMap<(u16, u16), QueryStatistics>,
+}
+#[derive(Clone, Copy, Debug)]
+struct QueryStatistics {
+    /// Any key of this query, used for looking up the query's name.
+    example_key: DatabaseKeyIndex,
+    executed: usize,
+    reused: usize,
+}
+
+impl RecompilationStatistics {
+    pub fn start_edit(&mut self, module: Module) {
+        if self.edits.len() == MAX_EDITS {
+            self.edits.pop_front();
+        }
+        self.edits.push_back(EditStatistics {
+            module,
+            queries: FxHashMap::default(),
+        });
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        let Some(edit) = self.edits.back_mut() else {
+            return;
+        };
+        let (database_key, was_executed) = match event.kind {
+            EventKind::WillExecute { database_key } => (database_key, true),
+            EventKind::DidValidateMemoizedValue { database_key } => (database_key, false),
+            EventKind::WillBlockOn { .. } => return,
+        };
+
+        let statistics = edit
+            .queries
+            .entry((database_key.group_index(), database_key.query_index()))
+            .or_insert(QueryStatistics {
+                example_key: database_key,
+                executed: 0,
+                reused: 0,
+            });
+        if was_executed {
+            statistics.executed += 1;
+        } else {
+            statistics.reused += 1;
+        }
+    }
+
+    pub fn report(&self, db: &dyn DatabaseOps) -> RecompilationStatisticsReport {
+        let edits = self
+            .edits
+            .iter()
+            .map(|edit| {
+                let queries = edit
+                    .queries
+                    .values()
+                    .map(|statistics| {
+                        // Keys are formatted as `query_name(key)`.
+                        let key = format!("{:?}", statistics.example_key.debug(db));
+                        let name = key.split_once('(').map_or(key.as_str(), |(name, _)| name);
+                        QueryReport {
+                            name: name.to_string(),
+                            executed: statistics.executed,
+                            reused: statistics.reused,
+                        }
+                    })
+                    .sorted_by(|a, b| b.executed.cmp(&a.executed).then(a.name.cmp(&b.name)))
+                    .collect_vec();
+                EditReport {
+                    module: edit.module.to_string(),
+                    executed: queries.iter().map(|it| it.executed).sum(),
+                    reused: queries.iter().map(|it| it.reused).sum(),
+                    queries,
+                }
+            })
+            .collect();
+        RecompilationStatisticsReport { edits }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecompilationStatisticsReport {
+    /// The most recent edits, oldest first.
+    pub edits: Vec<EditReport>,
+}
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditReport {
+    /// The edited module.
+    pub module: String,
+    pub executed: usize,
+    pub reused: usize,
+    /// Sorted by how many times the queries were executed, most first.
+    pub queries: Vec<QueryReport>,
+}
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryReport {
+    pub name: String,
+    pub executed: usize,
+    pub reused: usize,
+}
+
+impl Server {
+    pub async fn candy_recompilation_statistics(
+        &self,
+    ) -> jsonrpc::Result<RecompilationStatisticsReport> {
+        let db = self.db.lock().await;
+        Ok(db.recompilation_statistics())
+    }
+}
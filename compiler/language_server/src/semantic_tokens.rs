@@ -3,7 +3,7 @@ use std::ops::Range;
 use candy_frontend::position::Offset;
 use enumset::{EnumSet, EnumSetType};
 use lazy_static::lazy_static;
-use lsp_types::{Position, SemanticToken, SemanticTokensLegend};
+use lsp_types::{Position, SemanticToken, SemanticTokensEdit, SemanticTokensLegend, Url};
 use rustc_hash::FxHashMap;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
@@ -170,3 +170,117 @@ impl<'a> SemanticTokensBuilder<'a> {
         self.tokens
     }
 }
+
+/// Remembers the tokens last sent for each document so that later requests can
+/// be answered with a delta.
+#[derive(Debug, Default)]
+pub struct SemanticTokensCache {
+    next_result_id: u64,
+    documents: FxHashMap<Url, CachedSemanticTokens>,
+}
+#[derive(Debug)]
+struct CachedSemanticTokens {
+    result_id: String,
+    tokens: Vec<SemanticToken>,
+}
+impl SemanticTokensCache {
+    /// Stores the tokens of a document and returns their new result ID.
+    pub fn store(&mut self, uri: Url, tokens: Vec<SemanticToken>) -> String {
+        let result_id = self.next_result_id.to_string();
+        self.next_result_id += 1;
+        self.documents.insert(
+            uri,
+            CachedSemanticTokens {
+                result_id: result_id.clone(),
+                tokens,
+            },
+        );
+        result_id
+    }
+
+    /// Computes the edits from the tokens with the given result ID to the new
+    /// tokens.
+    ///
+    /// Returns `None` if the previous tokens are no longer cached, in which
+    /// case the client needs all tokens.
+    pub fn delta(
+        &self,
+        uri: &Url,
+        previous_result_id: &str,
+        tokens: &[SemanticToken],
+    ) -> Option<Vec<SemanticTokensEdit>> {
+        let previous = self.documents.get(uri)?;
+        if previous.result_id != previous_result_id {
+            return None;
+        }
+        Some(edits(&previous.tokens, tokens))
+    }
+
+    pub fn remove(&mut self, uri: &Url) {
+        self.documents.remove(uri);
+    }
+}
+
+/// A single edit replacing everything between the common prefix and suffix.
+///
+/// Edits are counted in integers of the encoded token array, i.e., five per
+/// token.
+fn edits(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix_length = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix_length = old[prefix_length..]
+        .iter()
+        .rev()
+        .zip(new[prefix_length..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let deleted = old.len() - prefix_length - suffix_length;
+    let inserted = &new[prefix_length..new.len() - suffix_length];
+    if deleted == 0 && inserted.is_empty() {
+        return vec![];
+    }
+    vec![SemanticTokensEdit {
+        start: (prefix_length * 5).try_into().unwrap(),
+        delete_count: (deleted * 5).try_into().unwrap(),
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+/// Only keeps the tokens overlapping the range and re-encodes them relative to
+/// each other.
+pub fn tokens_in_range(tokens: &[SemanticToken], range: lsp_types::Range) -> Vec<SemanticToken> {
+    let mut result = vec![];
+    let mut absolute = Position::new(0, 0);
+    let mut previous_in_range: Option<Position> = None;
+    for token in tokens {
+        absolute = if token.delta_line == 0 {
+            Position::new(absolute.line, absolute.character + token.delta_start)
+        } else {
+            Position::new(absolute.line + token.delta_line, token.delta_start)
+        };
+        let end = Position::new(absolute.line, absolute.character + token.length);
+        if end <= range.start {
+            continue;
+        }
+        if absolute >= range.end {
+            break;
+        }
+
+        let previous = previous_in_range.unwrap_or_else(|| Position::new(0, 0));
+        result.push(SemanticToken {
+            delta_line: absolute.line - previous.line,
+            delta_start: if absolute.line == previous.line {
+                absolute.character - previous.character
+            } else {
+                absolute.character
+            },
+            ..*token
+        });
+        previous_in_range = Some(absolute);
+    }
+    result
+}
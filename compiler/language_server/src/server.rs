@@ -2,27 +2,31 @@ use crate::{
     database::Database,
     debug_adapter::DebugSessionManager,
     features::{LanguageFeatures, Reference, RenameError},
-    features_candy::{
-        analyzer::{insights::Hint, HintsNotification},
-        CandyFeatures, ServerStatusNotification,
-    },
+    features_candy::{inlay_hints::InlayHintData, CandyFeatures, ServerStatusNotification},
     features_ir::{IrFeatures, UpdateIrNotification},
-    semantic_tokens,
+    semantic_tokens::{self, SemanticTokensCache},
     utils::{module_from_url, module_to_url},
 };
 use async_trait::async_trait;
 use candy_frontend::module::{Module, ModuleKind, PackagesPath};
 use lsp_types::{
-    Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DocumentFilter, DocumentFormattingParams, DocumentHighlight, DocumentHighlightKind,
-    DocumentHighlightParams, FoldingRange, FoldingRangeParams, GotoDefinitionParams,
-    GotoDefinitionResponse, InitializeParams, InitializeResult, InitializedParams, Location,
-    MessageType, Position, PrepareRenameResponse, ReferenceParams, Registration, RenameOptions,
-    RenameParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
-    SemanticTokensParams, SemanticTokensRegistrationOptions, SemanticTokensResult,
-    SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, StaticRegistrationOptions,
-    TextDocumentChangeRegistrationOptions, TextDocumentPositionParams,
+    CodeActionParams, CodeActionResponse, CompletionOptions, CompletionParams,
+    CompletionRegistrationOptions, CompletionResponse, Diagnostic, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentFilter,
+    DocumentFormattingParams, DocumentHighlight, DocumentHighlightKind, DocumentHighlightParams,
+    DocumentRangeFormattingParams, DocumentSymbolParams, DocumentSymbolResponse, FoldingRange,
+    FoldingRangeParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
+    InitializeParams, InitializeResult, InitializedParams, InlayHint, InlayHintOptions,
+    InlayHintParams, InlayHintRegistrationOptions, Location, MessageType, Position,
+    PrepareRenameResponse, ReferenceParams, Registration, RenameOptions, RenameParams,
+    SemanticTokens, SemanticTokensDelta, SemanticTokensDeltaParams, SemanticTokensFullDeltaResult,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensRegistrationOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo,
+    SignatureHelp, SignatureHelpOptions, SignatureHelpParams, StaticRegistrationOptions,
+    SymbolInformation, TextDocumentChangeRegistrationOptions, TextDocumentPositionParams,
     TextDocumentRegistrationOptions, TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit,
+    WorkspaceSymbolOptions, WorkspaceSymbolParams,
 };
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
@@ -35,6 +39,7 @@ pub struct Server {
     pub client: Client,
     pub db: Mutex<Database>,
     pub state: RwLock<ServerState>,
+    semantic_tokens_cache: Mutex<SemanticTokensCache>,
 }
 #[derive(Debug)]
 pub enum ServerState {
@@ -143,13 +148,13 @@ impl AnalyzerClient {
             )
             .await;
     }
-    pub async fn update_hints(&self, module: Module, hints: Vec<Hint>) {
-        self.client
-            .send_notification::<HintsNotification>(HintsNotification {
-                uri: module_to_url(&module, &self.packages_path).unwrap(),
-                hints,
-            })
-            .await;
+    /// Asks the client to request inlay hints again.
+    pub async fn refresh_hints(&self) {
+        // Clients that don't support refreshing get the new hints with their
+        // next request.
+        if let Err(error) = self.client.inlay_hint_refresh().await {
+            debug!("Couldn't refresh inlay hints: {error}");
+        }
     }
 }
 
@@ -176,6 +181,7 @@ impl Server {
                     packages_path,
                 )),
                 state: RwLock::new(state),
+                semantic_tokens_cache: Mutex::default(),
             }
         })
         .custom_method(
@@ -186,6 +192,10 @@ impl Server {
             "candy/debugAdapter/message",
             Self::candy_debug_adapter_message,
         )
+        .custom_method(
+            "candy/recompilationStatistics",
+            Self::candy_recompilation_statistics,
+        )
         .custom_method("candy/viewIr", Self::candy_view_ir)
         .finish();
 
@@ -314,10 +324,43 @@ impl LanguageServer for Server {
                     "textDocument/didClose",
                     features.registration_options_where(|it| it.supports_did_close()),
                 ),
+                registration(
+                    "textDocument/codeAction",
+                    features.registration_options_where(|it| it.supports_code_actions()),
+                ),
+                registration(
+                    "textDocument/completion",
+                    CompletionRegistrationOptions {
+                        text_document_registration_options: features
+                            .registration_options_where(|it| it.supports_completion()),
+                        completion_options: CompletionOptions {
+                            trigger_characters: Some(vec![".".to_string()]),
+                            ..Default::default()
+                        },
+                    },
+                ),
                 registration(
                     "textDocument/definition",
                     features.registration_options_where(|it| it.supports_find_definition()),
                 ),
+                registration(
+                    "textDocument/hover",
+                    features.registration_options_where(|it| it.supports_hover()),
+                ),
+                registration(
+                    "textDocument/inlayHint",
+                    InlayHintRegistrationOptions {
+                        inlay_hint_options: InlayHintOptions {
+                            work_done_progress_options: WorkDoneProgressOptions {
+                                work_done_progress: None,
+                            },
+                            resolve_provider: Some(true),
+                        },
+                        text_document_registration_options: features
+                            .registration_options_where(|it| it.supports_inlay_hints()),
+                        static_registration_options: StaticRegistrationOptions { id: None },
+                    },
+                ),
                 registration(
                     "textDocument/references",
                     features.registration_options_where(|it| it.supports_references()),
@@ -326,6 +369,10 @@ impl LanguageServer for Server {
                     "textDocument/documentHighlight",
                     features.registration_options_where(|it| it.supports_references()),
                 ),
+                registration(
+                    "textDocument/documentSymbol",
+                    features.registration_options_where(|it| it.supports_document_symbols()),
+                ),
                 registration(
                     "textDocument/foldingRange",
                     features.registration_options_where(|it| it.supports_folding_ranges()),
@@ -334,6 +381,10 @@ impl LanguageServer for Server {
                     "textDocument/formatting",
                     features.registration_options_where(|it| it.supports_format()),
                 ),
+                registration(
+                    "textDocument/rangeFormatting",
+                    features.registration_options_where(|it| it.supports_format()),
+                ),
                 registration(
                     "textDocument/rename",
                     RenameRegistrationOptions {
@@ -358,14 +409,36 @@ impl LanguageServer for Server {
                                     work_done_progress: None,
                                 },
                                 legend: semantic_tokens::LEGEND.clone(),
-                                // TODO
-                                range: Some(false),
-                                full: Some(SemanticTokensFullOptions::Bool(true)),
+                                range: Some(true),
+                                full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
                             },
                             static_registration_options: StaticRegistrationOptions { id: None },
                         },
                     ),
                 ),
+                registration(
+                    "textDocument/signatureHelp",
+                    SignatureHelpRegistrationOptions {
+                        text_document_registration_options: features
+                            .registration_options_where(|it| it.supports_signature_help()),
+                        signature_help_options: SignatureHelpOptions {
+                            trigger_characters: Some(vec![" ".to_string()]),
+                            retrigger_characters: None,
+                            work_done_progress_options: WorkDoneProgressOptions {
+                                work_done_progress: None,
+                            },
+                        },
+                    },
+                ),
+                registration(
+                    "workspace/symbol",
+                    WorkspaceSymbolOptions {
+                        work_done_progress_options: WorkDoneProgressOptions {
+                            work_done_progress: None,
+                        },
+                        resolve_provider: None,
+                    },
+                ),
             ])
             .await
             .expect("Dynamic capability registration failed.");
@@ -440,9 +513,41 @@ impl LanguageServer for Server {
         let state = self.require_running_state().await;
         let features = self.features_from_url(&state.features, &params.text_document.uri);
         assert!(features.supports_did_close());
+        self.semantic_tokens_cache
+            .lock()
+            .await
+            .remove(&params.text_document.uri);
         features.did_close(&self.db, params.text_document.uri).await;
     }
 
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> jsonrpc::Result<Option<CodeActionResponse>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_code_actions());
+        Ok(Some(
+            features
+                .code_actions(&self.db, params.text_document.uri, params.range)
+                .await,
+        ))
+    }
+
+    async fn completion(
+        &self,
+        params: CompletionParams,
+    ) -> jsonrpc::Result<Option<CompletionResponse>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document_position.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        assert!(features.supports_completion());
+        let items = features
+            .completion(&self.db, uri, params.text_document_position.position)
+            .await;
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
@@ -464,6 +569,47 @@ impl LanguageServer for Server {
         Ok(response)
     }
 
+    async fn hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(
+            &state.features,
+            &params.text_document_position_params.text_document.uri,
+        );
+        assert!(features.supports_hover());
+        let response = features
+            .hover(
+                &self.db,
+                params.text_document_position_params.text_document.uri,
+                params.text_document_position_params.position,
+            )
+            .await;
+        Ok(response)
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> jsonrpc::Result<Option<Vec<InlayHint>>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_inlay_hints());
+        Ok(Some(
+            features
+                .inlay_hints(&self.db, params.text_document.uri, params.range)
+                .await,
+        ))
+    }
+    async fn inlay_hint_resolve(&self, params: InlayHint) -> jsonrpc::Result<InlayHint> {
+        let Some(data) = params
+            .data
+            .clone()
+            .and_then(|it| serde_json::from_value::<InlayHintData>(it).ok())
+        else {
+            return Ok(params);
+        };
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &data.uri);
+        assert!(features.supports_inlay_hints());
+        Ok(features.resolve_inlay_hint(params).await)
+    }
+
     async fn references(&self, params: ReferenceParams) -> jsonrpc::Result<Option<Vec<Location>>> {
         let uri = params.text_document_position.text_document.uri;
         let highlights = self
@@ -517,6 +663,20 @@ impl LanguageServer for Server {
         Ok(Some(highlights))
     }
 
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> jsonrpc::Result<Option<DocumentSymbolResponse>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_document_symbols());
+        Ok(Some(DocumentSymbolResponse::Nested(
+            features
+                .document_symbols(&self.db, params.text_document.uri)
+                .await,
+        )))
+    }
+
     async fn folding_range(
         &self,
         params: FoldingRangeParams,
@@ -542,6 +702,19 @@ impl LanguageServer for Server {
             features.format(&self.db, params.text_document.uri).await,
         ))
     }
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_format());
+        Ok(Some(
+            features
+                .format_range(&self.db, params.text_document.uri, params.range)
+                .await,
+        ))
+    }
 
     async fn prepare_rename(
         &self,
@@ -587,13 +760,87 @@ impl LanguageServer for Server {
         let state = self.require_running_state().await;
         let uri = params.text_document.uri;
         let features = self.features_from_url(&state.features, &uri);
-        let tokens = features.semantic_tokens(&self.db, uri);
-        let tokens = tokens.await;
+        let tokens = features.semantic_tokens(&self.db, uri.clone()).await;
+        let result_id = self
+            .semantic_tokens_cache
+            .lock()
+            .await
+            .store(uri, tokens.clone());
         Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
-            result_id: None,
+            result_id: Some(result_id),
             data: tokens,
         })))
     }
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensFullDeltaResult>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        let tokens = features.semantic_tokens(&self.db, uri.clone()).await;
+
+        let mut cache = self.semantic_tokens_cache.lock().await;
+        let edits = cache.delta(&uri, &params.previous_result_id, &tokens);
+        let result = if let Some(edits) = edits {
+            let result_id = cache.store(uri, tokens);
+            SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                result_id: Some(result_id),
+                edits,
+            })
+        } else {
+            let result_id = cache.store(uri, tokens.clone());
+            SemanticTokensFullDeltaResult::Tokens(SemanticTokens {
+                result_id: Some(result_id),
+                data: tokens,
+            })
+        };
+        Ok(Some(result))
+    }
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensRangeResult>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        let tokens = features.semantic_tokens(&self.db, uri).await;
+        Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::tokens_in_range(&tokens, params.range),
+        })))
+    }
+
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> jsonrpc::Result<Option<SignatureHelp>> {
+        let state = self.require_running_state().await;
+        let params = params.text_document_position_params;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_signature_help());
+        Ok(features
+            .signature_help(&self.db, params.text_document.uri, params.position)
+            .await)
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> jsonrpc::Result<Option<Vec<SymbolInformation>>> {
+        let state = self.require_running_state().await;
+        let mut symbols = vec![];
+        for features in state.features.all_features() {
+            if features.supports_workspace_symbols() {
+                symbols.extend(
+                    features
+                        .workspace_symbols(&self.db, params.query.clone())
+                        .await,
+                );
+            }
+        }
+        Ok(Some(symbols))
+    }
 }
 impl Server {
     async fn references_raw(
@@ -629,3 +876,14 @@ pub struct RenameRegistrationOptions {
     #[serde(flatten)]
     pub rename_options: RenameOptions,
 }
+
+/// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#signatureHelpRegistrationOptions>
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelpRegistrationOptions {
+    #[serde(flatten)]
+    pub text_document_registration_options: TextDocumentRegistrationOptions,
+
+    #[serde(flatten)]
+    pub signature_help_options: SignatureHelpOptions,
+}
@@ -1,6 +1,5 @@
 use crate::database::Database;
 use candy_frontend::{
-    cst::CstDb,
     error::CompilerError,
     module::{Module, ModuleDb, ModuleKind, Package, PackagesPath},
     position::{line_start_offsets_raw, Offset, PositionConversionDb},
@@ -13,17 +12,15 @@ use std::ops::Range;
 #[must_use]
 pub fn error_to_diagnostic(db: &Database, module: Module, error: &CompilerError) -> Diagnostic {
     let related_information = error
-        .to_related_information()
-        .into_iter()
-        .filter_map(|(module, cst_id, message)| {
-            let uri = module_to_url(&module, &db.packages_path)?;
-
-            let span = db.find_cst(module.clone(), cst_id).display_span();
-            let range = db.range_to_lsp_range(module, span);
+        .labels
+        .iter()
+        .filter_map(|label| {
+            let uri = module_to_url(&label.module, &db.packages_path)?;
+            let range = db.range_to_lsp_range(label.module.clone(), label.span.clone());
 
             Some(lsp_types::DiagnosticRelatedInformation {
                 location: lsp_types::Location { uri, range },
-                message,
+                message: label.message.clone(),
             })
         })
         .collect();
@@ -33,7 +30,12 @@ pub fn error_to_diagnostic(db: &Database, module: Module, error: &CompilerError)
         code: None,
         code_description: None,
         source: Some("🍭 Candy".to_owned()),
-        message: error.payload.to_string(),
+        message: error
+            .notes
+            .iter()
+            .fold(error.payload.to_string(), |message, note| {
+                format!("{message}\nNote: {note}")
+            }),
         related_information: Some(related_information),
         tags: None,
         data: None,
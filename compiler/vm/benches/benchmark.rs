@@ -1,18 +1,21 @@
 #![feature(absolute_path)]
 #![allow(unused_attributes)]
 
-use candy_frontend::module::PackagesPath;
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    module::{Module, PackagesPath},
+};
 use candy_vm::{
     byte_code::ByteCode,
     heap::{Heap, Struct},
     tracer::stack_trace::StackTracer,
-    Vm, VmFinished,
+    StateAfterRunForever, Vm, VmFinished,
 };
 use environment::BenchmarkingEnvironment;
 use iai_callgrind::{
     library_benchmark, library_benchmark_group, main, FlamegraphConfig, LibraryBenchmarkConfig,
 };
-use std::fs;
+use std::{env, fs};
 use tracing::Level;
 use tracing_subscriber::{
     filter,
@@ -21,7 +24,7 @@ use tracing_subscriber::{
     util::SubscriberInitExt,
     Layer,
 };
-use utils::{compile, setup, Database};
+use utils::{compile, loaded_modules, setup, setup_packages, Database};
 
 mod environment;
 mod utils;
@@ -44,8 +47,35 @@ fn c(file_path: &str) -> (Database, String) {
     (db, source_code)
 }
 
+// Lowers Builtins and Core to the HIR, which is what the persistent cache
+// stores. With a warm cache, this only deserializes the results.
+#[library_benchmark]
+#[bench::without_cache(f(false))]
+#[bench::with_warm_cache(f(true))]
+pub fn frontend((db, modules): (Database, Vec<Module>)) {
+    for module in modules {
+        let _ = db.hir(module);
+    }
+}
+fn f(use_persistent_cache: bool) -> (Database, Vec<Module>) {
+    init_logger();
+
+    let persistent_cache_directory = use_persistent_cache.then(|| {
+        let directory = env::temp_dir().join("candy-benchmark-cache");
+        let db = setup_packages(Some(directory.clone()));
+        for module in loaded_modules(&db) {
+            let _ = db.hir(module);
+        }
+        directory
+    });
+    let db = setup_packages(persistent_cache_directory);
+    let modules = loaded_modules(&db);
+    (db, modules)
+}
+
 #[library_benchmark]
 #[bench::examples_fibonacci(v("Examples/fibonacci", &["10"]))]
+#[bench::examples_fibonacci_15(v("Examples/fibonacci", &["15"]))]
 #[bench::examples_hello_world(v("Examples/helloWorld", &[]))]
 fn vm_runtime(mut program: PreparedProgram) {
     let vm = Vm::for_main_function(
@@ -70,6 +100,20 @@ fn vm_runtime(mut program: PreparedProgram) {
     });
 }
 
+// Only runs the module, which mostly consists of creating functions and
+// structs, so this measures the instruction dispatch itself.
+#[library_benchmark]
+#[bench::examples_fibonacci(v("Examples/fibonacci", &[]))]
+fn vm_module(mut program: PreparedProgram) {
+    let vm = Vm::for_module(program.byte_code, &mut program.heap, StackTracer::default());
+    let StateAfterRunForever::Finished(VmFinished { result, .. }) =
+        vm.run_forever(&mut program.heap)
+    else {
+        panic!("The module called a handle.");
+    };
+    result.unwrap_or_else(|it| panic!("The module panicked: {}", it.reason));
+}
+
 struct PreparedProgram {
     db: Database,
     byte_code: ByteCode,
@@ -100,7 +144,7 @@ fn v(file_path: &str, arguments: &[&str]) -> PreparedProgram {
 #[allow(unused_mut)]
 library_benchmark_group!(
     name = main;
-    benchmarks = compile, vm_runtime
+    benchmarks = compile, frontend, vm_runtime, vm_module
 );
 #[allow(unused_mut)]
 main!(
@@ -0,0 +1,144 @@
+//! Benchmarks that report their results in the [Bencher Metric Format (BMF)]
+//! so that Bencher can track them over time:
+//!
+//! ```bash
+//! bencher run --adapter json "cargo bench --bench metrics"
+//! ```
+//!
+//! Unlike the Callgrind-based benchmarks in `benchmark.rs`, these also report
+//! custom measures that don't depend on the machine's speed:
+//!
+//! - `latency`: wall-clock time in nanoseconds (median, minimum, and maximum
+//!   of several iterations), which also includes reading the persistent cache
+//!   from disk
+//! - `byte-code-size`: the number of instructions of the compiled program
+//! - `instructions-executed`: the number of instructions the VM executed
+//! - `peak-heap-bytes`: the maximum number of bytes allocated on the heap at
+//!   the same time
+//!
+//! [Bencher Metric Format (BMF)]: https://bencher.dev/docs/reference/bencher-metric-format/
+
+use candy_frontend::ast_to_hir::AstToHir;
+use candy_vm::{heap::Heap, tracer::DummyTracer, Vm, VmFinished};
+use environment::BenchmarkingEnvironment;
+use serde_json::{json, Map, Value};
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+use utils::{compile, loaded_modules, setup, setup_packages};
+
+mod environment;
+mod utils;
+
+const ITERATIONS: usize = 10;
+
+fn main() {
+    let mut report = Map::new();
+    report.insert("frontend".to_string(), frontend_metrics(false));
+    report.insert(
+        "frontend/with-warm-cache".to_string(),
+        frontend_metrics(true),
+    );
+    for file_path in ["Examples/fibonacci", "Examples/helloWorld"] {
+        report.insert(format!("compile/{file_path}"), compile_metrics(file_path));
+    }
+    for (file_path, arguments) in [
+        ("Examples/fibonacci", &["10"][..]),
+        ("Examples/fibonacci", &["15"]),
+        ("Examples/helloWorld", &[]),
+    ] {
+        let name = if arguments.is_empty() {
+            format!("vm_runtime/{file_path}")
+        } else {
+            format!("vm_runtime/{file_path} {}", arguments.join(" "))
+        };
+        report.insert(name, vm_runtime_metrics(file_path, arguments));
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&Value::Object(report)).unwrap()
+    );
+}
+
+/// Lowers Builtins and Core to the HIR, optionally loading the results from a
+/// persistent cache that was filled before.
+fn frontend_metrics(use_persistent_cache: bool) -> Value {
+    let persistent_cache_directory =
+        use_persistent_cache.then(|| env::temp_dir().join("candy-metrics-cache"));
+    let mut durations = vec![];
+    // The first iteration fills the cache.
+    for _ in 0..=ITERATIONS {
+        let db = setup_packages(persistent_cache_directory.clone());
+        let start = Instant::now();
+        for module in loaded_modules(&db) {
+            let _ = db.hir(module);
+        }
+        durations.push(start.elapsed());
+    }
+    durations.remove(0);
+    json!({ "latency": latency(durations) })
+}
+
+fn compile_metrics(file_path: &str) -> Value {
+    let source_code = read_source_code(file_path);
+    let mut durations = vec![];
+    let mut byte_code_size = 0;
+    for _ in 0..ITERATIONS {
+        // A fresh database so that the program isn't cached. `Core` is
+        // compiled during the setup.
+        let mut db = setup();
+        let start = Instant::now();
+        let byte_code = compile(&mut db, &source_code);
+        durations.push(start.elapsed());
+        byte_code_size = byte_code.instructions.len();
+    }
+    json!({
+        "latency": latency(durations),
+        "byte-code-size": { "value": byte_code_size },
+    })
+}
+
+fn vm_runtime_metrics(file_path: &str, arguments: &[&str]) -> Value {
+    let mut db = setup();
+    let byte_code = compile(&mut db, &read_source_code(file_path));
+
+    let mut durations = vec![];
+    let mut executed_instructions = 0;
+    let mut peak_heap_bytes = 0;
+    for _ in 0..ITERATIONS {
+        let mut heap = Heap::default();
+        let (environment_argument, mut environment) =
+            BenchmarkingEnvironment::new(&mut heap, arguments);
+        let start = Instant::now();
+        let vm = Vm::for_main_function(&byte_code, &mut heap, environment_argument, DummyTracer);
+        let VmFinished {
+            result,
+            executed_instructions: executed,
+            ..
+        } = vm.run_forever_with_environment(&mut heap, &mut environment);
+        durations.push(start.elapsed());
+        result.unwrap_or_else(|it| panic!("The program panicked: {}", it.reason));
+        executed_instructions = executed;
+        peak_heap_bytes = heap.statistics().peak_allocated_bytes;
+    }
+    json!({
+        "latency": latency(durations),
+        "instructions-executed": { "value": executed_instructions },
+        "peak-heap-bytes": { "value": peak_heap_bytes },
+    })
+}
+
+fn read_source_code(file_path: &str) -> String {
+    fs::read_to_string(format!("../../packages/{file_path}.candy")).unwrap()
+}
+
+fn latency(mut durations: Vec<Duration>) -> Value {
+    durations.sort();
+    let nanoseconds = |duration: &Duration| duration.as_nanos();
+    json!({
+        "value": nanoseconds(&durations[durations.len() / 2]),
+        "lower_value": nanoseconds(durations.first().unwrap()),
+        "upper_value": nanoseconds(durations.last().unwrap()),
+    })
+}
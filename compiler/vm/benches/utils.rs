@@ -1,6 +1,7 @@
 use candy_frontend::{
     ast::AstDbStorage,
     ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
     cst::CstDbStorage,
     cst_to_ast::CstToAstStorage,
     hir::HirDbStorage,
@@ -10,25 +11,31 @@ use candy_frontend::{
     mir_to_lir::MirToLirStorage,
     module::{
         GetModuleContentQuery, InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind,
-        ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner, Package,
+        ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner, OverlayModuleProvider,
+        Package, VirtualModuleProvider,
     },
+    persistent_cache::{PersistentCache, PersistentCacheOwner},
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
     string_to_rcst::StringToRcstStorage,
-    CallTracingMode, TracingConfig, TracingMode,
+    CallTracingMode, OptimizationConfig, TracingConfig, TracingMode,
 };
 use candy_vm::{
     byte_code::ByteCode, lir_to_byte_code::compile_byte_code,
     PopulateInMemoryProviderFromFileSystem,
 };
 use lazy_static::lazy_static;
+use std::path::PathBuf;
 use tracing::warn;
 
 const TRACING: TracingConfig = TracingConfig {
     register_fuzzables: TracingMode::Off,
     calls: CallTracingMode::OnlyForPanicTraces,
     evaluated_expressions: TracingMode::Off,
+    debug_assertions: false,
+    strip: false,
 };
+const OPTIMIZATION: OptimizationConfig = OptimizationConfig::release();
 lazy_static! {
     static ref PACKAGE: Package = Package::User("/".into());
     static ref MODULE: Module = Module::new(
@@ -56,7 +63,8 @@ lazy_static! {
 #[derive(Default)]
 pub struct Database {
     storage: salsa::Storage<Self>,
-    module_provider: InMemoryModuleProvider,
+    module_provider: OverlayModuleProvider<InMemoryModuleProvider, VirtualModuleProvider>,
+    persistent_cache: Option<PersistentCache>,
 }
 impl salsa::Database for Database {}
 impl ModuleProviderOwner for Database {
@@ -64,9 +72,18 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl PersistentCacheOwner for Database {
+    fn get_persistent_cache(&self) -> Option<&PersistentCache> {
+        self.persistent_cache.as_ref()
+    }
+}
+impl CancellationTokenOwner for Database {}
 impl MutableModuleProviderOwner for Database {
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
-        &mut self.module_provider
+        &mut self.module_provider.overlay
+    }
+    fn get_virtual_module_provider(&mut self) -> &mut VirtualModuleProvider {
+        &mut self.module_provider.fallback
     }
     fn invalidate_module(&mut self, module: &Module) {
         GetModuleContentQuery.in_db_mut(self).invalidate(module);
@@ -75,12 +92,24 @@ impl MutableModuleProviderOwner for Database {
 
 pub fn setup() -> Database {
     let mut db = Database::default();
-    db.module_provider.load_package_from_file_system("Builtins");
-    db.module_provider.load_package_from_file_system("Core");
-    db.module_provider.add_str(&MODULE, r#"_ = use "Core""#);
+    db.module_provider
+        .overlay
+        .load_package_from_file_system("Builtins");
+    db.module_provider
+        .overlay
+        .load_package_from_file_system("Core");
+    db.module_provider
+        .overlay
+        .add_str(&MODULE, r#"_ = use "Core""#);
 
     // Load `Core` into the cache.
-    let errors = compile_byte_code(&db, ExecutionTarget::Module(MODULE.clone()), TRACING).1;
+    let errors = compile_byte_code(
+        &db,
+        ExecutionTarget::Module(MODULE.clone()),
+        TRACING,
+        OPTIMIZATION,
+    )
+    .1;
     if !errors.is_empty() {
         for error in errors.iter() {
             warn!("{}", error.to_string_with_location(&db));
@@ -91,7 +120,35 @@ pub fn setup() -> Database {
     db
 }
 
+/// Only loads the packages without compiling anything.
+pub fn setup_packages(persistent_cache_directory: Option<PathBuf>) -> Database {
+    let mut db = Database {
+        persistent_cache: persistent_cache_directory.and_then(PersistentCache::open),
+        ..Database::default()
+    };
+    db.module_provider
+        .overlay
+        .load_package_from_file_system("Builtins");
+    db.module_provider
+        .overlay
+        .load_package_from_file_system("Core");
+    db
+}
+pub fn loaded_modules(db: &Database) -> Vec<Module> {
+    db.module_provider
+        .overlay
+        .get_all_modules()
+        .cloned()
+        .collect()
+}
+
 pub fn compile(db: &mut Database, source_code: &str) -> ByteCode {
     db.did_open_module(&MODULE, source_code.as_bytes().to_owned());
-    compile_byte_code(db, ExecutionTarget::MainFunction(MODULE.clone()), TRACING).0
+    compile_byte_code(
+        db,
+        ExecutionTarget::MainFunction(MODULE.clone()),
+        TRACING,
+        OPTIMIZATION,
+    )
+    .0
 }
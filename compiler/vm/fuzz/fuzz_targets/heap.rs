@@ -0,0 +1,131 @@
+#![no_main]
+
+use candy_vm::heap::{Heap, InlineObject, Int, List, Struct, Tag, Text};
+use libfuzzer_sys::{arbitrary::Unstructured, fuzz_target};
+use rustc_hash::FxHashMap;
+
+// Interprets the input as a sequence of operations that construct, share, and
+// drop heap objects. After each operation, the heap has to be consistent.
+fuzz_target!(|data: &[u8]| {
+    let mut heap = Heap::default();
+    let default_object_count = heap.objects().len();
+    // Each of these owns one reference.
+    let mut objects: Vec<InlineObject> = vec![];
+
+    let mut input = Unstructured::new(data);
+    while !input.is_empty() {
+        let Ok(operation) = input.int_in_range(0..=9) else {
+            break;
+        };
+        let Ok(object) = run_operation(&mut heap, &mut objects, &mut input, operation) else {
+            break;
+        };
+        if let Some(object) = object {
+            objects.push(object);
+        }
+        heap.validate(&[], objects.iter().copied());
+    }
+
+    for object in objects {
+        object.drop(&mut heap);
+    }
+    heap.validate(&[], []);
+    assert_eq!(
+        heap.objects().len(),
+        default_object_count,
+        "Dropping all objects should free them.",
+    );
+});
+
+fn run_operation(
+    heap: &mut Heap,
+    objects: &mut Vec<InlineObject>,
+    input: &mut Unstructured,
+    operation: u8,
+) -> libfuzzer_sys::arbitrary::Result<Option<InlineObject>> {
+    let object = match operation {
+        0 => Int::create(heap, true, input.arbitrary::<i128>()?).into(),
+        1 => Text::create(heap, true, input.arbitrary::<&str>()?).into(),
+        2 => {
+            let symbol = Text::create(heap, true, input.arbitrary::<&str>()?);
+            match choose(objects, input)? {
+                Some(value) => {
+                    value.dup(heap);
+                    Tag::create_with_value(heap, true, symbol, value).into()
+                }
+                None => Tag::create(symbol).into(),
+            }
+        }
+        3 => {
+            let length = input.int_in_range(0..=8)?;
+            let mut items = vec![];
+            for _ in 0..length {
+                let Some(item) = choose(objects, input)? else {
+                    break;
+                };
+                item.dup(heap);
+                items.push(item);
+            }
+            List::create(heap, true, &items).into()
+        }
+        4 => {
+            let length = input.int_in_range(0..=8)?;
+            let mut fields = FxHashMap::default();
+            for _ in 0..length {
+                let (Some(key), Some(value)) = (choose(objects, input)?, choose(objects, input)?)
+                else {
+                    break;
+                };
+                fields.insert(key, value);
+            }
+            for (key, value) in &fields {
+                key.dup(heap);
+                value.dup(heap);
+            }
+            Struct::create(heap, true, &fields).into()
+        }
+        5 => {
+            let Some(object) = choose(objects, input)? else {
+                return Ok(None);
+            };
+            object.dup(heap);
+            object
+        }
+        6 => {
+            if !objects.is_empty() {
+                let index = input.choose_index(objects.len())?;
+                objects.swap_remove(index).drop(heap);
+            }
+            return Ok(None);
+        }
+        7 => {
+            heap.collect_garbage();
+            return Ok(None);
+        }
+        8 => {
+            let (cloned, _) = heap.clone();
+            cloned.validate(&[], []);
+            return Ok(None);
+        }
+        9 => {
+            let Some(object) = choose(objects, input)? else {
+                return Ok(None);
+            };
+            _ = format!("{object:?}");
+            return Ok(None);
+        }
+        _ => unreachable!(),
+    };
+    Ok(Some(object))
+}
+
+fn choose(
+    objects: &[InlineObject],
+    input: &mut Unstructured,
+) -> libfuzzer_sys::arbitrary::Result<Option<InlineObject>> {
+    if objects.is_empty() {
+        return Ok(None);
+    }
+    let index = input.choose_index(objects.len())?;
+    Ok(Some(objects[index]))
+}
@@ -0,0 +1,33 @@
+#![no_main]
+
+use candy_vm::{
+    heap::Heap,
+    json::{from_json, to_json},
+};
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+// `candy run --args` converts user-provided JSON to heap objects.
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+
+    let mut heap = Heap::default();
+    let default_object_count = heap.objects().len();
+    let Ok(object) = from_json(&mut heap, &value) else {
+        return;
+    };
+    heap.validate(&[], [object]);
+
+    let round_tripped = to_json(object).unwrap();
+    assert_eq!(round_tripped, value);
+
+    object.drop(&mut heap);
+    heap.validate(&[], []);
+    assert_eq!(
+        heap.objects().len(),
+        default_object_count,
+        "Dropping the object should free everything.",
+    );
+});
@@ -0,0 +1,77 @@
+#![no_main]
+
+use candy_frontend::{
+    ast::AstDbStorage,
+    ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
+    cst::CstDbStorage,
+    cst_to_ast::CstToAstStorage,
+    hir::HirDbStorage,
+    hir_to_mir::{ExecutionTarget, HirToMirStorage},
+    mir_optimize::{validate::check_optimizations, OptimizeMirStorage},
+    module::{
+        InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind, ModuleProvider,
+        ModuleProviderOwner, Package,
+    },
+    persistent_cache::PersistentCacheOwner,
+    position::PositionConversionStorage,
+    rcst_to_cst::RcstToCstStorage,
+    string_to_rcst::StringToRcstStorage,
+    OptimizationConfig, TracingConfig,
+};
+use candy_vm::PopulateInMemoryProviderFromFileSystem;
+use lazy_static::lazy_static;
+use libfuzzer_sys::fuzz_target;
+
+const TRACING: TracingConfig = TracingConfig::off();
+const OPTIMIZATION: OptimizationConfig = OptimizationConfig::release();
+lazy_static! {
+    static ref PACKAGE: Package = Package::User("/".into());
+    static ref MODULE: Module = Module::new(
+        PACKAGE.clone(),
+        vec!["fuzzer".to_string()],
+        ModuleKind::Code,
+    );
+}
+
+#[salsa::database(
+    AstDbStorage,
+    AstToHirStorage,
+    CstDbStorage,
+    CstToAstStorage,
+    HirDbStorage,
+    HirToMirStorage,
+    ModuleDbStorage,
+    OptimizeMirStorage,
+    PositionConversionStorage,
+    RcstToCstStorage,
+    StringToRcstStorage
+)]
+#[derive(Default)]
+pub struct Database {
+    storage: salsa::Storage<Self>,
+    module_provider: InMemoryModuleProvider,
+}
+impl salsa::Database for Database {}
+impl ModuleProviderOwner for Database {
+    fn get_module_provider(&self) -> &dyn ModuleProvider {
+        &self.module_provider
+    }
+}
+impl PersistentCacheOwner for Database {}
+impl CancellationTokenOwner for Database {}
+
+fuzz_target!(|data: &[u8]| {
+    let mut db = Database::default();
+    db.module_provider.load_package_from_file_system("Builtins");
+    db.module_provider.add(&MODULE, data.to_vec());
+
+    if let Err(violation) = check_optimizations(
+        &db,
+        ExecutionTarget::Module(MODULE.clone()),
+        TRACING,
+        OPTIMIZATION,
+    ) {
+        panic!("{violation}");
+    }
+});
@@ -3,6 +3,7 @@
 use candy_frontend::{
     ast::AstDbStorage,
     ast_to_hir::AstToHirStorage,
+    cancellation::CancellationTokenOwner,
     cst::CstDbStorage,
     cst_to_ast::CstToAstStorage,
     hir::HirDbStorage,
@@ -14,10 +15,11 @@ use candy_frontend::{
         InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind, ModuleProvider,
         ModuleProviderOwner, Package,
     },
+    persistent_cache::PersistentCacheOwner,
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
     string_to_rcst::StringToRcstStorage,
-    TracingConfig,
+    OptimizationConfig, TracingConfig,
 };
 use candy_vm::{
     heap::{Heap, Struct},
@@ -29,6 +31,7 @@ use lazy_static::lazy_static;
 use libfuzzer_sys::fuzz_target;
 
 const TRACING: TracingConfig = TracingConfig::off();
+const OPTIMIZATION: OptimizationConfig = OptimizationConfig::release();
 lazy_static! {
     static ref PACKAGE: Package = Package::User("/".into());
     static ref MODULE: Module = Module::new(
@@ -64,14 +67,21 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl PersistentCacheOwner for Database {}
+impl CancellationTokenOwner for Database {}
 
 fuzz_target!(|data: &[u8]| {
     let mut db = Database::default();
     db.module_provider.load_package_from_file_system("Builtins");
     db.module_provider.add(&MODULE, data.to_vec());
 
-    let byte_code =
-        compile_byte_code(&db, ExecutionTarget::MainFunction(MODULE.clone()), TRACING).0;
+    let byte_code = compile_byte_code(
+        &db,
+        ExecutionTarget::MainFunction(MODULE.clone()),
+        TRACING,
+        OPTIMIZATION,
+    )
+    .0;
 
     let mut heap = Heap::default();
     let environment = Struct::create(&mut heap, true, &Default::default());
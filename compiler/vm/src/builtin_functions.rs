@@ -1,7 +1,11 @@
 use crate::{
-    heap::{Data, Function, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text, ToDebugText},
+    heap::{
+        Bytes, Cell, Data, Function, Heap, HeapObject, HirId, InlineObject, Int, List, Map, Number,
+        Set, Struct, Tag, Text, TextBuilder, ToDebugText,
+    },
     instructions::InstructionResult,
-    vm::{CallHandle, MachineState, Panic},
+    unwinding::TryBody,
+    vm::{CallHandle, MachineState, Panic, PanicKind},
 };
 use candy_frontend::{
     builtin_functions::BuiltinFunction,
@@ -10,9 +14,10 @@ use candy_frontend::{
 use derive_more::Deref;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use num_rational::BigRational;
 use paste::paste;
 use std::{
-    str::FromStr,
+    str::{self, FromStr},
     sync::atomic::{AtomicBool, Ordering},
 };
 
@@ -30,7 +35,27 @@ impl MachineState {
         responsible: HirId,
     ) -> InstructionResult {
         let result = match &builtin_function {
+            BuiltinFunction::BytesConcatenate => heap.bytes_concatenate(args),
+            BuiltinFunction::BytesFromList => heap.bytes_from_list(args),
+            BuiltinFunction::BytesFromText => heap.bytes_from_text(args),
+            BuiltinFunction::BytesGetRange => heap.bytes_get_range(args),
+            BuiltinFunction::BytesLength => heap.bytes_length(args),
+            BuiltinFunction::BytesToList => heap.bytes_to_list(args),
+            BuiltinFunction::BytesToText => heap.bytes_to_text(args),
+            BuiltinFunction::CellCreate => heap.cell_create(args),
+            BuiltinFunction::CellGet => heap.cell_get(args),
+            BuiltinFunction::CellSet => heap.cell_set(args),
+            BuiltinFunction::DebugAssert => heap.debug_assert(args),
+            BuiltinFunction::DebugLog => heap.debug_log(args),
             BuiltinFunction::Equals => heap.equals(args),
+            BuiltinFunction::FractionAdd => heap.fraction_add(args),
+            BuiltinFunction::FractionCompareTo => heap.fraction_compare_to(args),
+            BuiltinFunction::FractionCreate => heap.fraction_create(args),
+            BuiltinFunction::FractionDenominator => heap.fraction_denominator(args),
+            BuiltinFunction::FractionDivide => heap.fraction_divide(args),
+            BuiltinFunction::FractionMultiply => heap.fraction_multiply(args),
+            BuiltinFunction::FractionNumerator => heap.fraction_numerator(args),
+            BuiltinFunction::FractionSubtract => heap.fraction_subtract(args),
             BuiltinFunction::FunctionRun => Heap::function_run(args, responsible),
             BuiltinFunction::GetArgumentCount => heap.get_argument_count(args),
             BuiltinFunction::IfElse => heap.if_else(args, responsible),
@@ -48,13 +73,30 @@ impl MachineState {
             BuiltinFunction::IntShiftLeft => heap.int_shift_left(args),
             BuiltinFunction::IntShiftRight => heap.int_shift_right(args),
             BuiltinFunction::IntSubtract => heap.int_subtract(args),
+            BuiltinFunction::ListConcatenate => heap.list_concatenate(args),
             BuiltinFunction::ListFilled => heap.list_filled(args),
             BuiltinFunction::ListGet => heap.list_get(args),
+            BuiltinFunction::ListGetRange => heap.list_get_range(args),
             BuiltinFunction::ListInsert => heap.list_insert(args),
             BuiltinFunction::ListLength => heap.list_length(args),
             BuiltinFunction::ListRemoveAt => heap.list_remove_at(args),
             BuiltinFunction::ListReplace => heap.list_replace(args),
+            BuiltinFunction::MapEntries => heap.map_entries(args),
+            BuiltinFunction::MapFromList => heap.map_from_list(args),
+            BuiltinFunction::MapGet => heap.map_get(args),
+            BuiltinFunction::MapHasKey => heap.map_has_key(args),
+            BuiltinFunction::MapInsert => heap.map_insert(args),
+            BuiltinFunction::MapRemove => heap.map_remove(args),
+            BuiltinFunction::MapSize => heap.map_size(args),
+            BuiltinFunction::MapUnion => heap.map_union(args),
             BuiltinFunction::Print => heap.print(args),
+            BuiltinFunction::SetContains => heap.set_contains(args),
+            BuiltinFunction::SetFromList => heap.set_from_list(args),
+            BuiltinFunction::SetInsert => heap.set_insert(args),
+            BuiltinFunction::SetItems => heap.set_items(args),
+            BuiltinFunction::SetRemove => heap.set_remove(args),
+            BuiltinFunction::SetSize => heap.set_size(args),
+            BuiltinFunction::SetUnion => heap.set_union(args),
             BuiltinFunction::StructGet => heap.struct_get(args),
             BuiltinFunction::StructGetKeys => heap.struct_get_keys(args),
             BuiltinFunction::StructHasKey => heap.struct_has_key(args),
@@ -62,7 +104,12 @@ impl MachineState {
             BuiltinFunction::TagHasValue => heap.tag_has_value(args),
             BuiltinFunction::TagWithoutValue => heap.tag_without_value(args),
             BuiltinFunction::TagWithValue => heap.tag_with_value(args),
+            BuiltinFunction::TextBuilderAppend => heap.text_builder_append(args),
+            BuiltinFunction::TextBuilderAppendInt => heap.text_builder_append_int(args),
+            BuiltinFunction::TextBuilderBuild => heap.text_builder_build(args),
+            BuiltinFunction::TextBuilderCreate => heap.text_builder_create(args),
             BuiltinFunction::TextCharacters => heap.text_characters(args),
+            BuiltinFunction::TextCompareTo => heap.text_compare_to(args),
             BuiltinFunction::TextConcatenate => heap.text_concatenate(args),
             BuiltinFunction::TextContains => heap.text_contains(args),
             BuiltinFunction::TextEndsWith => heap.text_ends_with(args),
@@ -71,9 +118,12 @@ impl MachineState {
             BuiltinFunction::TextIsEmpty => heap.text_is_empty(args),
             BuiltinFunction::TextLength => heap.text_length(args),
             BuiltinFunction::TextStartsWith => heap.text_starts_with(args),
+            BuiltinFunction::TextToLowercase => heap.text_to_lowercase(args),
+            BuiltinFunction::TextToUppercase => heap.text_to_uppercase(args),
             BuiltinFunction::TextTrimEnd => heap.text_trim_end(args),
             BuiltinFunction::TextTrimStart => heap.text_trim_start(args),
             BuiltinFunction::ToDebugText => heap.to_debug_text(args),
+            BuiltinFunction::Try => Heap::try_(args, responsible),
             BuiltinFunction::TypeOf => heap.type_of(args),
         };
 
@@ -87,15 +137,20 @@ impl MachineState {
                 responsible,
             }) => self.call_function(function, &[], responsible),
             Ok(CallHandle(call)) => InstructionResult::CallHandle(call),
-            Err(reason) => InstructionResult::Panic(Panic {
+            Ok(Try(body)) => self.call_try(body),
+            Err(BuiltinError::Panic(reason)) => InstructionResult::Panic(Panic {
                 reason,
                 responsible: responsible.get().clone(),
+                kind: PanicKind::Code,
             }),
+            Err(BuiltinError::OutOfMemory { content_size }) => InstructionResult::Panic(
+                Panic::out_of_memory_for_allocation(heap, content_size, responsible.get().clone()),
+            ),
         }
     }
 }
 
-type BuiltinResult = Result<SuccessfulBehavior, String>;
+type BuiltinResult = Result<SuccessfulBehavior, BuiltinError>;
 enum SuccessfulBehavior {
     Return(InlineObject),
     DivergeControlFlow {
@@ -103,6 +158,7 @@ enum SuccessfulBehavior {
         responsible: HirId,
     },
     CallHandle(CallHandle),
+    Try(TryBody),
 }
 
 impl From<SuccessfulBehavior> for BuiltinResult {
@@ -111,6 +167,20 @@ impl From<SuccessfulBehavior> for BuiltinResult {
     }
 }
 
+enum BuiltinError {
+    Panic(String),
+    /// The result would need more memory than the heap's limit allows (see
+    /// [`Heap::check_allocation`]).
+    OutOfMemory {
+        content_size: usize,
+    },
+}
+impl From<String> for BuiltinError {
+    fn from(reason: String) -> Self {
+        Self::Panic(reason)
+    }
+}
+
 macro_rules! unpack {
     ( $heap:expr, $args:expr, |$( $arg:ident: $type:ty ),+| $body:block ) => {
         {
@@ -162,6 +232,155 @@ macro_rules! unpack_and_later_drop {
 use SuccessfulBehavior::*;
 
 impl Heap {
+    /// Builtins whose result can be arbitrarily large check this before
+    /// allocating it. Otherwise, a single call like `listFilled 1000000000 x`
+    /// could exhaust the host's memory before the VM notices that the memory
+    /// limit was exceeded.
+    fn check_allocation(&self, content_size: usize) -> Result<(), BuiltinError> {
+        if self.can_allocate(content_size) {
+            Ok(())
+        } else {
+            Err(BuiltinError::OutOfMemory { content_size })
+        }
+    }
+
+    fn bytes_concatenate(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Bytes, b: Bytes| {
+            self.check_allocation(a.len() + b.len())
+                .map(|()| Return(a.concatenate(self, **b).into()))
+        })
+    }
+    fn bytes_from_list(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |list: List| {
+            list.items()
+                .iter()
+                .map(|&it| {
+                    Int::try_from(it)
+                        .ok()
+                        .and_then(Int::try_get)
+                        .ok_or_else(|| format!("Value is not a byte: {it}."))
+                })
+                .collect::<Result<Vec<u8>, _>>()
+                .map(|bytes| Return(Bytes::create(self, true, &bytes).into()))
+                .map_err(BuiltinError::from)
+        })
+    }
+    fn bytes_from_text(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(Bytes::create(self, true, text.get().as_bytes()).into())
+        })
+    }
+    fn bytes_get_range(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(
+            self,
+            args,
+            |bytes: Bytes, start_inclusive: Int, end_exclusive: Int| {
+                // TODO: Support indices larger than usize.
+                let start_inclusive = start_inclusive.try_get().expect(
+                    "Tried to get a range from bytes with an index that's too large for usize.",
+                );
+                let end_exclusive = end_exclusive.try_get().expect(
+                    "Tried to get a range from bytes with an index that's too large for usize.",
+                );
+                Return(bytes.get_range(self, start_inclusive..end_exclusive).into())
+            }
+        )
+    }
+    fn bytes_length(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |bytes: Bytes| {
+            Return(Int::create(self, true, bytes.len()).into())
+        })
+    }
+    fn bytes_to_list(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |bytes: Bytes| {
+            // Bytes always fit into inline ints, so this doesn't allocate.
+            let items = bytes
+                .get()
+                .iter()
+                .map(|&it| Int::create(self, true, it).into())
+                .collect_vec();
+            Return(List::create(self, true, &items).into())
+        })
+    }
+    fn bytes_to_text(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack!(self, args, |bytes: Bytes| {
+            let text = str::from_utf8(bytes.get())
+                .map(|it| {
+                    let text = Text::create(self, true, it).into();
+                    bytes.drop(self);
+                    text
+                })
+                .map_err(|_| {
+                    Tag::create_with_value(
+                        self,
+                        true,
+                        self.default_symbols().not_utf8,
+                        bytes.object,
+                    )
+                    .into()
+                });
+            Return(Tag::create_result(self, true, text).into())
+        })
+    }
+
+    fn cell_create(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack!(self, args, |value: Any| {
+            Return(Cell::create(self, value.object).into())
+        })
+    }
+    fn cell_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |cell: Cell| {
+            let value = cell.get();
+            value.dup(self);
+            Return(value)
+        })
+    }
+    fn cell_set(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack!(self, args, |cell: Cell, value: Any| {
+            cell.set(self, value.object);
+            cell.object.drop(self);
+            Return(Tag::create_nothing(self).into())
+        })
+    }
+
+    // The debug builtins are only called if debug assertions are enabled. Their
+    // wrappers in the `Builtins` package don't check the arguments so that
+    // they don't cost anything otherwise, so we have to check them here.
+    fn debug_assert(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |condition: Any, message: Any| {
+            let condition = Tag::try_from(condition.object)
+                .ok()
+                .and_then(|condition| condition.try_into_bool(self).ok());
+            match condition {
+                Some(true) => Return(Tag::create_nothing(self).into()).into(),
+                Some(false) => Err(Self::debug_message(message.object).into()),
+                None => Err(
+                    "`debugAssert` expects the condition to be `True` or `False`."
+                        .to_string()
+                        .into(),
+                ),
+            }
+        })
+    }
+    fn debug_log(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |message: Any| {
+            let message = Self::debug_message(message.object);
+            if CAN_USE_STDOUT.load(Ordering::Relaxed) {
+                println!("{message}");
+            } else {
+                eprintln!("{message}");
+            }
+            Return(Tag::create_nothing(self).into())
+        })
+    }
+    /// Texts are used as they are, other values are formatted.
+    fn debug_message(message: InlineObject) -> String {
+        match Data::from(message) {
+            Data::Text(text) => text.get().to_string(),
+            _ => message.to_debug_text(Precedence::Low, MaxLength::Unlimited),
+        }
+    }
+
     fn equals(&mut self, args: &[InlineObject]) -> BuiltinResult {
         let [a, b] = args else {
             panic!("A builtin function was called with the wrong number of arguments.");
@@ -172,6 +391,49 @@ impl Heap {
         result.into()
     }
 
+    fn fraction_add(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Number, b: Number| {
+            Return(a.add(self, *b).into())
+        })
+    }
+    fn fraction_compare_to(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Number, b: Number| {
+            Return(a.compare_to(self, *b).into())
+        })
+    }
+    fn fraction_create(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |numerator: Int, denominator: Int| {
+            let value =
+                BigRational::new(numerator.get().into_owned(), denominator.get().into_owned());
+            Return(Number::create(self, true, value).into())
+        })
+    }
+    fn fraction_denominator(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |number: Number| {
+            Return(number.denominator(self).into())
+        })
+    }
+    fn fraction_divide(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |dividend: Number, divisor: Number| {
+            Return(dividend.divide(self, *divisor).into())
+        })
+    }
+    fn fraction_multiply(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Number, b: Number| {
+            Return(a.multiply(self, *b).into())
+        })
+    }
+    fn fraction_numerator(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |number: Number| {
+            Return(number.numerator(self).into())
+        })
+    }
+    fn fraction_subtract(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Number, b: Number| {
+            Return(a.subtract(self, *b).into())
+        })
+    }
+
     fn function_run(args: &[InlineObject], responsible: HirId) -> BuiltinResult {
         unpack!(self, args, |function: Any| {
             match **function {
@@ -306,10 +568,28 @@ impl Heap {
         })
     }
 
+    fn list_concatenate(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |list_a: List, list_b: List| {
+            self.check_allocation((list_a.len() + list_b.len()) * HeapObject::WORD_SIZE)
+                .map(|()| {
+                    let new_list = list_a.concatenate(self, **list_b);
+                    for item in new_list.items() {
+                        item.dup(self);
+                    }
+                    Return(new_list.into())
+                })
+        })
+    }
     fn list_filled(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack!(self, args, |length: Int, item: Any| {
-            let length_usize = length.try_get().unwrap();
+            let length_usize: usize = length.try_get().unwrap();
             length.object.drop(self);
+            if let Err(error) =
+                self.check_allocation(length_usize.saturating_mul(HeapObject::WORD_SIZE))
+            {
+                item.object.drop(self);
+                return Err(error);
+            }
 
             let item_object = item.object;
             if length_usize == 0 {
@@ -329,11 +609,25 @@ impl Heap {
             Return(item)
         })
     }
+    fn list_get_range(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(
+            self,
+            args,
+            |list: List, start_inclusive: Int, end_exclusive: Int| {
+                let start_inclusive = start_inclusive.try_get().unwrap();
+                let end_exclusive = end_exclusive.try_get().unwrap();
+                Return(list.get_range(self, start_inclusive..end_exclusive).into())
+            }
+        )
+    }
     fn list_insert(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack!(self, args, |list: List, index: Int, item: Any| {
             let index_usize = index.try_get().unwrap();
             index.object.drop(self);
 
+            for item in list.items() {
+                item.dup(self);
+            }
             let new_list = list.insert(self, index_usize, item.object).into();
             list.object.drop(self);
             Return(new_list)
@@ -354,14 +648,94 @@ impl Heap {
             let index_usize = index.try_get().unwrap();
             index.object.drop(self);
 
-            list.get(index_usize).drop(self);
+            // Nobody else can observe a list that we hold the only reference
+            // to, so we can reuse it instead of copying all items.
+            if list.is_unique() {
+                list.get(index_usize).drop(self);
+                list.replace_in_place(index_usize, new_item.object);
+                return Ok(Return(list.object));
+            }
 
+            for (item_index, item) in list.items().iter().enumerate() {
+                if item_index != index_usize {
+                    item.dup(self);
+                }
+            }
             let new_list = list.replace(self, index_usize, new_item.object).into();
             list.object.drop(self);
             Return(new_list)
         })
     }
 
+    fn map_entries(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |map: Map| {
+            let entries = map
+                .entries()
+                .into_iter()
+                .map(|entry| {
+                    let entry: [InlineObject; 2] = entry.into();
+                    for it in entry {
+                        it.dup(self);
+                    }
+                    List::create(self, true, &entry).into()
+                })
+                .collect_vec();
+            Return(List::create(self, true, &entries).into())
+        })
+    }
+    fn map_from_list(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |list: List| {
+            list.items()
+                .iter()
+                .map(|&entry| match List::try_from(entry) {
+                    Ok(entry) if entry.len() == 2 => Ok((entry.get(0), entry.get(1))),
+                    _ => Err(format!("Value is not a list of a key and a value: {entry}.").into()),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|entries| {
+                    for (key, value) in &entries {
+                        key.dup(self);
+                        value.dup(self);
+                    }
+                    Return(Map::create(self, entries).into())
+                })
+        })
+    }
+    fn map_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |map: Map, key: Any| {
+            let value = map.get(key.object).unwrap();
+            value.dup(self);
+            Return(value)
+        })
+    }
+    fn map_has_key(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |map: Map, key: Any| {
+            Return(Tag::create_bool(self, map.get(key.object).is_some()).into())
+        })
+    }
+    fn map_insert(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack!(self, args, |map: Map, key: Any, value: Any| {
+            let new_map = map.insert(self, key.object, value.object);
+            map.object.drop(self);
+            Return(new_map.into())
+        })
+    }
+    fn map_remove(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |map: Map, key: Any| {
+            Return(map.remove(self, key.object).into())
+        })
+    }
+    fn map_size(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |map: Map| {
+            Return(Int::create(self, true, map.len()).into())
+        })
+    }
+    fn map_union(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Map, b: Map| {
+            Return(a.union(self, *b).into())
+        })
+    }
+
     fn print(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |message: Text| {
             if CAN_USE_STDOUT.load(Ordering::Relaxed) {
@@ -373,6 +747,51 @@ impl Heap {
         })
     }
 
+    fn set_contains(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |set: Set, item: Any| {
+            Return(Tag::create_bool(self, set.contains(item.object)).into())
+        })
+    }
+    fn set_from_list(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |list: List| {
+            for item in list.items() {
+                item.dup(self);
+            }
+            Return(Set::create(self, list.items().iter().copied()).into())
+        })
+    }
+    fn set_insert(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack!(self, args, |set: Set, item: Any| {
+            let new_set = set.insert(self, item.object);
+            set.object.drop(self);
+            Return(new_set.into())
+        })
+    }
+    fn set_items(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |set: Set| {
+            let items = set.items();
+            for item in &items {
+                item.dup(self);
+            }
+            Return(List::create(self, true, &items).into())
+        })
+    }
+    fn set_remove(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |set: Set, item: Any| {
+            Return(set.remove(self, item.object).into())
+        })
+    }
+    fn set_size(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |set: Set| {
+            Return(Int::create(self, true, set.len()).into())
+        })
+    }
+    fn set_union(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Set, b: Set| {
+            Return(a.union(self, *b).into())
+        })
+    }
+
     fn struct_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |struct_: Struct, key: Any| {
             let value = struct_.get(key.object).unwrap();
@@ -414,14 +833,42 @@ impl Heap {
         })
     }
 
+    fn text_builder_append(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |builder: TextBuilder, text: Text| {
+            builder.append(text.get());
+            Return(Tag::create_nothing(self).into())
+        })
+    }
+    fn text_builder_append_int(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |builder: TextBuilder, int: Int| {
+            builder.append(&int.get().to_string());
+            Return(Tag::create_nothing(self).into())
+        })
+    }
+    fn text_builder_build(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |builder: TextBuilder| {
+            Return(builder.build(self).into())
+        })
+    }
+    fn text_builder_create(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |initial: Text| {
+            Return(TextBuilder::create(self, initial.get().to_string()).into())
+        })
+    }
     fn text_characters(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |text: Text| {
             Return(text.characters(self).into())
         })
     }
+    fn text_compare_to(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Text, b: Text| {
+            Return(a.compare_to(self, *b).into())
+        })
+    }
     fn text_concatenate(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |a: Text, b: Text| {
-            Return(a.concatenate(self, *b).into())
+            self.check_allocation(a.byte_len() + b.byte_len())
+                .map(|()| Return(a.concatenate(self, *b).into()))
         })
     }
     fn text_contains(&mut self, args: &[InlineObject]) -> BuiltinResult {
@@ -491,6 +938,16 @@ impl Heap {
             Return(text.starts_with(self, *prefix).into())
         })
     }
+    fn text_to_lowercase(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(text.to_lowercase(self).into())
+        })
+    }
+    fn text_to_uppercase(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(text.to_uppercase(self).into())
+        })
+    }
     fn text_trim_end(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |text: Text| {
             Return(text.trim_end(self).into())
@@ -512,6 +969,26 @@ impl Heap {
         })
     }
 
+    fn try_(args: &[InlineObject], responsible: HirId) -> BuiltinResult {
+        unpack!(self, args, |function: Any| {
+            Try(match **function {
+                // All builtins have at least one argument, so ✨.try can never
+                // be called with a builtin.
+                Data::Builtin(_) => unreachable!(),
+                Data::Function(function) => TryBody::Function {
+                    function,
+                    responsible,
+                },
+                Data::Handle(handle) => TryBody::Handle(CallHandle {
+                    handle,
+                    arguments: vec![],
+                    responsible,
+                }),
+                _ => unreachable!(),
+            })
+        })
+    }
+
     fn type_of(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |value: Any| {
             let type_text = match **value {
@@ -526,6 +1003,12 @@ impl Heap {
                 Data::Function(_) => self.default_symbols().function,
                 Data::Builtin(_) => self.default_symbols().builtin,
                 Data::Handle(_) => self.default_symbols().function,
+                Data::Bytes(_) => self.default_symbols().bytes,
+                Data::Cell(_) => self.default_symbols().cell,
+                Data::Fraction(_) => self.default_symbols().fraction,
+                Data::Map(_) => self.default_symbols().map,
+                Data::Set(_) => self.default_symbols().set,
+                Data::TextBuilder(_) => self.default_symbols().text_builder,
             };
             Return(Tag::create(type_text).into())
         })
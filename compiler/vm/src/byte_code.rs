@@ -1,7 +1,7 @@
 use crate::heap::{Function, HirId, InlineData, InlineObject};
 use crate::heap::{Heap, Text};
 use crate::instruction_pointer::InstructionPointer;
-use candy_frontend::hir;
+use crate::origins::Origins;
 use candy_frontend::rich_ir::ReferenceKey;
 use candy_frontend::{
     lir::Id,
@@ -13,17 +13,59 @@ use enumset::EnumSet;
 use extension_trait::extension_trait;
 use itertools::Itertools;
 use pad::{Alignment, PadStr};
-use rustc_hash::FxHashSet;
-use std::ops::Range;
+use rustc_hash::FxHashMap;
 use strum::{EnumDiscriminants, IntoStaticStr};
 
 pub struct ByteCode {
     pub module: Module,
     pub constant_heap: Heap,
     pub instructions: Vec<Instruction>,
-    pub(super) origins: Vec<FxHashSet<hir::Id>>,
+    pub origins: Origins,
     pub module_function: Function,
     pub responsible_module: HirId,
+    /// Frames that a panic may have to unwind, keyed by the instruction at
+    /// which the frame continues.
+    pub frame_cleanups: FxHashMap<InstructionPointer, FrameCleanup>,
+}
+impl ByteCode {
+    /// Functions called by the `try` builtin return to this instruction, an
+    /// [`Instruction::CompleteTry`] at the start of each byte code.
+    pub const COMPLETE_TRY: InstructionPointer = InstructionPointer::null_pointer();
+
+    /// Whether `other` has the same instructions as this byte code except for
+    /// the constants they push, e.g., because only a number or text in the
+    /// source code changed. A VM can then continue running `other` (see
+    /// [`Vm::swap_byte_code`](crate::Vm::swap_byte_code)).
+    #[must_use]
+    pub fn differs_only_in_constants(&self, other: &Self) -> bool {
+        self.instructions.len() == other.instructions.len()
+            && self.frame_cleanups == other.frame_cleanups
+            && self
+                .instructions
+                .iter()
+                .zip(&other.instructions)
+                .all(|instructions| match instructions {
+                    (Instruction::PushConstant(_), Instruction::PushConstant(_)) => true,
+                    (instruction, other_instruction) => instruction == other_instruction,
+                })
+    }
+}
+
+/// What a frame owns while it's suspended at a call or panics.
+///
+/// When a panic unwinds to a `try`, the frames in between never continue.
+/// Instead, the VM drops the references these frames would have dropped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FrameCleanup {
+    /// The number of data stack entries belonging to the frame, including its
+    /// captured values, arguments, and responsibility.
+    pub frame_size: usize,
+    /// The offsets of owned references, relative to the top of the frame,
+    /// and how many references to each entry the frame owns.
+    pub owned_references: Vec<(StackOffset, usize)>,
+    /// The offsets of frame-allocated objects, relative to the top of the
+    /// frame. These are freed instead of dropped.
+    pub frame_objects: Vec<StackOffset>,
 }
 
 pub type StackOffset = usize; // 0 is the last item, 1 the one before that, etc.
@@ -33,18 +75,31 @@ pub type StackOffset = usize; // 0 is the last item, 1 the one before that, etc.
 pub enum Instruction {
     /// Pops 1 argument, pushes a tag.
     ///
+    /// Frame-allocated objects aren't reference-counted. Instead,
+    /// [`Instruction::FreeFrameObjects`] frees them before the function
+    /// returns.
+    ///
     /// a, value -> a, tag
-    CreateTag { symbol: Text },
+    CreateTag {
+        symbol: Text,
+        is_frame_allocated: bool,
+    },
 
     /// Pops num_items items, pushes a list.
     ///
     /// a, item, item, ..., item -> a, pointer to list
-    CreateList { num_items: usize },
+    CreateList {
+        num_items: usize,
+        is_frame_allocated: bool,
+    },
 
     /// Pops 2 * num_fields items, pushes a struct.
     ///
     /// a, key, value, key, value, ..., key, value -> a, pointer to struct
-    CreateStruct { num_fields: usize },
+    CreateStruct {
+        num_fields: usize,
+        is_frame_allocated: bool,
+    },
 
     /// Pushes a function.
     ///
@@ -72,6 +127,12 @@ pub enum Instruction {
     /// a, value -> a
     Drop,
 
+    /// Frees the frame-allocated objects at the given offsets, including their
+    /// children. Leaves the stack untouched.
+    ///
+    /// a -> a
+    FreeFrameObjects(Box<[StackOffset]>),
+
     /// Sets up the data stack for a function execution and then changes the
     /// instruction pointer to the first instruction.
     ///
@@ -88,6 +149,10 @@ pub enum Instruction {
     /// Like `Call`, but after popping the stack entries for the call itself, it
     /// also pops the given number of local stack entries before actually
     /// executing the call.
+    ///
+    /// Loops are written as self-recursive functions. When such a function
+    /// calls itself in tail position, this instruction is the loop's back edge
+    /// and the loop runs in constant stack space.
     TailCall {
         num_locals_to_pop: usize,
         // This is `u32` instead of `usize` to reduce the size of the
@@ -113,6 +178,13 @@ pub enum Instruction {
     /// a, reason, responsible -> 💥
     Panic,
 
+    /// Wraps the return value of a function called by the `try` builtin in an
+    /// `Ok` tag and returns to where `try` was called. Only occurs at
+    /// [`ByteCode::COMPLETE_TRY`].
+    ///
+    /// a, return value -> a, Ok return value
+    CompleteTry,
+
     /// a, HIR ID, function, arg1, arg2, ..., argN, responsible -> a
     TraceCallStarts { num_args: usize },
 
@@ -129,6 +201,40 @@ pub enum Instruction {
 
     /// a, HIR ID, function -> a
     TraceFoundFuzzableFunction,
+
+    // Superinstructions
+    //
+    // These fuse frequent sequences of the instructions above into a single
+    // instruction to reduce the dispatch overhead. They are only emitted by
+    // the peephole pass at the end of lowering a body.
+    /// `PushFromStack(offset)` followed by `Dup { amount }`: Increases the
+    /// reference count of an item in the stack.
+    ///
+    /// a -> a
+    PushFromStackAndDup {
+        offset: StackOffset,
+        // This is `u32` instead of `usize` to keep the enum at 16 bytes.
+        amount: u32,
+    },
+
+    /// `PushFromStack(offset)` followed by `Drop`: Decreases the reference
+    /// count of an item in the stack.
+    ///
+    /// a -> a
+    PushFromStackAndDrop(StackOffset),
+
+    /// `PushFromStack(responsible)` followed by `Call { num_args }`: Calls a
+    /// function with a responsibility that's already in the stack.
+    ///
+    /// a, function, arg1, arg2, ..., argN ~> a, return value from function
+    PushFromStackAndCall {
+        responsible: StackOffset,
+        // This is `u32` instead of `usize` to keep the enum at 16 bytes.
+        num_args: u32, // excluding the responsible argument
+    },
+
+    /// `PopMultipleBelowTop(n)` followed by `Return`.
+    PopMultipleBelowTopAndReturn(usize),
 }
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct CreateFunction {
@@ -142,6 +248,10 @@ pub struct IfElse {
     pub then_captured: Vec<StackOffset>,
     pub else_target: InstructionPointer,
     pub else_captured: Vec<StackOffset>,
+    /// If this is the last instruction of a body, we can leave the current
+    /// frame before jumping into the branch, just like a [`Instruction::TailCall`].
+    /// In that case, this contains the number of locals to pop first.
+    pub num_locals_to_pop: Option<usize>,
 }
 
 impl Instruction {
@@ -154,11 +264,11 @@ impl Instruction {
                 stack.pop();
                 stack.push(result);
             }
-            Self::CreateList { num_items } => {
+            Self::CreateList { num_items, .. } => {
                 stack.pop_multiple(*num_items);
                 stack.push(result);
             }
-            Self::CreateStruct { num_fields } => {
+            Self::CreateStruct { num_fields, .. } => {
                 stack.pop_multiple(2 * num_fields); // fields
                 stack.push(result);
             }
@@ -182,6 +292,7 @@ impl Instruction {
             Self::Drop => {
                 stack.pop();
             }
+            Self::FreeFrameObjects(_) => {}
             Self::Call { num_args } => {
                 stack.pop(); // responsible
                 stack.pop_multiple(*num_args);
@@ -212,6 +323,10 @@ impl Instruction {
                 stack.pop(); // reason
                 stack.push(result);
             }
+            Self::CompleteTry => {
+                stack.pop(); // return value
+                stack.push(result);
+            }
             Self::TraceCallStarts { num_args } | Self::TraceTailCall { num_args } => {
                 stack.pop(); // HIR ID
                 stack.pop(); // responsible
@@ -231,6 +346,17 @@ impl Instruction {
                 stack.pop(); // HIR ID
                 stack.pop(); // value
             }
+            Self::PushFromStackAndDup { .. } | Self::PushFromStackAndDrop(_) => {}
+            Self::PushFromStackAndCall { num_args, .. } => {
+                stack.pop_multiple((*num_args).try_into().unwrap());
+                stack.pop(); // function/builtin
+                stack.push(result); // return value
+            }
+            Self::PopMultipleBelowTopAndReturn(n) => {
+                let top = stack.pop().unwrap();
+                stack.pop_multiple(*n);
+                stack.push(top);
+            }
         }
     }
 }
@@ -246,29 +372,6 @@ impl StackExt for Vec<Id> {
     }
 }
 
-impl ByteCode {
-    #[must_use]
-    pub fn functions_behind(&self, ip: InstructionPointer) -> &FxHashSet<hir::Id> {
-        &self.origins[*ip]
-    }
-    #[must_use]
-    pub fn range_of_function(&self, function: &hir::Id) -> Range<InstructionPointer> {
-        let start = self
-            .origins
-            .iter()
-            .position(|origins| origins.contains(function))
-            .unwrap();
-        let end = start
-            + self
-                .origins
-                .iter()
-                .skip(start)
-                .take_while(|origins| origins.contains(function))
-                .count();
-        start.into()..end.into()
-    }
-}
-
 impl ToRichIr for ByteCode {
     fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
         builder.push("# Constant heap", TokenType::Comment, EnumSet::empty());
@@ -291,19 +394,17 @@ impl ToRichIr for ByteCode {
 
         builder.push("# Instructions", TokenType::Comment, EnumSet::empty());
         let instruction_index_width = (self.instructions.len() * 10 - 1).ilog10() as usize;
-        let mut previous_origins = &FxHashSet::default();
         for (i, instruction) in self.instructions.iter().enumerate() {
             builder.push_newline();
 
-            let origins = &self.origins[i];
-            if origins != previous_origins {
+            let function = self.origins.function_at(i.into());
+            if function.instructions.start == i.into() {
                 builder.push(
-                    format!("# {}", origins.iter().join(", ")),
+                    format!("# {}", function.hir_ids.iter().sorted().join(", ")),
                     TokenType::Comment,
                     EnumSet::empty(),
                 );
                 builder.push_newline();
-                previous_origins = origins;
             }
 
             builder.push(
@@ -326,18 +427,30 @@ impl ToRichIr for Instruction {
         builder.push_simple(Into::<&'static str>::into(discriminant));
 
         match self {
-            Self::CreateTag { symbol } => {
+            Self::CreateTag {
+                symbol,
+                is_frame_allocated,
+            } => {
                 builder.push_simple(" ");
                 let symbol_range = builder.push_simple(symbol.get());
                 builder.push_reference(ReferenceKey::Symbol(symbol.to_string()), symbol_range);
+                push_frame_allocation(builder, *is_frame_allocated);
             }
-            Self::CreateList { num_items } => {
+            Self::CreateList {
+                num_items,
+                is_frame_allocated,
+            } => {
                 builder.push_simple(" ");
                 builder.push_simple(num_items.to_string());
+                push_frame_allocation(builder, *is_frame_allocated);
             }
-            Self::CreateStruct { num_fields } => {
+            Self::CreateStruct {
+                num_fields,
+                is_frame_allocated,
+            } => {
                 builder.push_simple(" ");
                 builder.push_simple(num_fields.to_string());
+                push_frame_allocation(builder, *is_frame_allocated);
             }
             Self::CreateFunction(box CreateFunction {
                 captured,
@@ -385,6 +498,10 @@ impl ToRichIr for Instruction {
                 builder.push_simple(amount.to_string());
             }
             Self::Drop => {}
+            Self::FreeFrameObjects(offsets) => {
+                builder.push_simple(" at ");
+                builder.push_simple(offsets.iter().join(", "));
+            }
             Self::Call { num_args } => {
                 builder.push_simple(format!(" with {num_args} {}", arguments_plural(*num_args)));
             }
@@ -403,7 +520,11 @@ impl ToRichIr for Instruction {
                 then_captured,
                 else_target,
                 else_captured,
+                num_locals_to_pop,
             }) => {
+                if let Some(num_locals_to_pop) = num_locals_to_pop {
+                    builder.push_simple(format!(" (tail, popping {num_locals_to_pop} locals)"));
+                }
                 builder.push_simple(
                     format!(
                         " then call {then_target:?} capturing {} else call {else_target:?} capturing {}",
@@ -421,6 +542,7 @@ impl ToRichIr for Instruction {
                 );
             }
             Self::Panic => {}
+            Self::CompleteTry => {}
             Self::TraceCallStarts { num_args } | Self::TraceTailCall { num_args } => {
                 builder.push_simple(format!(" ({num_args} {})", arguments_plural(*num_args)));
             }
@@ -433,10 +555,35 @@ impl ToRichIr for Instruction {
             }
             Self::TraceExpressionEvaluated => {}
             Self::TraceFoundFuzzableFunction => {}
+            Self::PushFromStackAndDup { offset, amount } => {
+                builder.push_simple(format!(" {offset} by {amount}"));
+            }
+            Self::PushFromStackAndDrop(offset) => {
+                builder.push_simple(" ");
+                builder.push_simple(offset.to_string());
+            }
+            Self::PushFromStackAndCall {
+                responsible,
+                num_args,
+            } => {
+                builder.push_simple(format!(
+                    " {responsible} with {num_args} {}",
+                    arguments_plural((*num_args).try_into().unwrap()),
+                ));
+            }
+            Self::PopMultipleBelowTopAndReturn(count) => {
+                builder.push_simple(" ");
+                builder.push_simple(count.to_string());
+            }
         }
     }
 }
 
+fn push_frame_allocation(builder: &mut RichIrBuilder, is_frame_allocated: bool) {
+    if is_frame_allocated {
+        builder.push_simple(" in frame");
+    }
+}
 const fn arguments_plural(num_args: usize) -> &'static str {
     if num_args == 1 {
         "argument"
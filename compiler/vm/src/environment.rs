@@ -10,14 +10,26 @@ use itertools::Itertools;
 use rustc_hash::FxHashMap;
 use std::{
     borrow::{Borrow, Cow},
-    fs::File,
-    io::{self, BufRead, Read},
+    cell::Cell,
+    fmt::Write as _,
+    fs::{self, File, Metadata, OpenOptions},
+    future::Future,
+    io::{self, Read, Write},
     mem,
     net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
     str::FromStr,
-    time::SystemTime,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 use tiny_http::{Request, Response, Server};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime,
+};
 use tracing::info;
 
 pub trait Environment {
@@ -57,29 +69,60 @@ impl<B: Borrow<ByteCode>, T: Tracer> Vm<B, T> {
 pub struct DefaultEnvironment {
     // Clock
     system_clock_handle: Handle,
+    now_handle: Handle,
+    sleep_handle: Handle,
+    clock: Box<dyn Clock>,
 
     // File
     // path → File handle | Directory handle | TODO Symlink
     file_open_handle: Handle,
+    file_create_handle: Handle,
     file_read_to_end_handle: Handle,
+    file_write_handle: Handle,
     file_close_handle: Handle,
+    directory_list_handle: Handle,
+    file_system_access: FileSystemAccess,
     // FIXME
     // → handle
     // get_working_directory: Handle,
 
     // HTTP
+    http_client_handle: Handle,
     http_server_handle: Handle,
     /// `None` means the server got closed.
     http_server_states: Vec<Option<HttpServerState>>,
 
+    // TCP
+    tcp_connect_handle: Handle,
+    tcp_listen_handle: Handle,
+    /// `None` means the connection got closed.
+    tcp_connections: Vec<Option<TcpStream>>,
+    /// `None` means the listener got closed.
+    tcp_listeners: Vec<Option<TcpListener>>,
+
+    /// Runs network operations. Without one, they fail.
+    network_reactor: Option<runtime::Handle>,
+
+    // Process
+    run_process_handle: Handle,
+
     // Random
     get_random_bytes_handle: Handle,
 
     // Stdio
     stdin_handle: Handle,
     stdout_handle: Handle,
+    stderr_handle: Handle,
+    /// Started when the program first reads from stdin.
+    stdin_reader: Option<StdinReader>,
 
     dynamic_handles: FxHashMap<Handle, DynamicHandle>,
+
+    handle_call_policies: FxHashMap<BlockingHandle, HandleCallPolicy>,
+    /// When the current handle call times out.
+    deadline: Option<Instant>,
+    /// Set by blocking operations that gave up because of the deadline.
+    timed_out: Cell<bool>,
 }
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -88,6 +131,48 @@ enum DynamicHandle {
     HttpServerGetNextRequest(HttpServerIndex),
     HttpServerSendResponse(HttpServerIndex, HttpRequestId),
     HttpServerClose(HttpServerIndex),
+    TcpConnectionRead(TcpConnectionIndex),
+    TcpConnectionWrite(TcpConnectionIndex),
+    TcpConnectionClose(TcpConnectionIndex),
+    TcpListenerAccept(TcpListenerIndex),
+    TcpListenerClose(TcpListenerIndex),
+}
+/// Handles of the [`DefaultEnvironment`] whose calls can block, e.g., until
+/// input arrives or a network peer responds.
+///
+/// If reading from or writing to a TCP connection times out, the connection
+/// gets closed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BlockingHandle {
+    Stdin,
+    HttpClient,
+    HttpServerGetNextRequest,
+    TcpConnect,
+    TcpConnectionRead,
+    TcpConnectionWrite,
+    TcpListenerAccept,
+}
+/// Limits how long calls of a [`BlockingHandle`] may take.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HandleCallPolicy {
+    /// Calls that don't complete in time return `Error TimedOut`. Without a
+    /// timeout, calls may block forever.
+    pub timeout: Option<Duration>,
+    /// How often a call that timed out is attempted again before returning
+    /// the error.
+    pub retries: usize,
+}
+/// Which paths a program may access through `environment.fileSystem`.
+#[derive(Clone, Debug, Default)]
+pub enum FileSystemAccess {
+    #[default]
+    Unrestricted,
+    /// Only the given paths and everything below them are accessible. Writable
+    /// paths are readable as well.
+    Sandboxed {
+        readable: Vec<PathBuf>,
+        writable: Vec<PathBuf>,
+    },
 }
 struct HttpServerState {
     server: Server,
@@ -96,6 +181,8 @@ struct HttpServerState {
 }
 type HttpServerIndex = usize;
 type HttpRequestId = usize;
+type TcpConnectionIndex = usize;
+type TcpListenerIndex = usize;
 
 impl DefaultEnvironment {
     pub fn new(heap: &mut Heap, args: &[String]) -> (Struct, Self) {
@@ -106,34 +193,64 @@ impl DefaultEnvironment {
         let arguments = List::create(heap, true, arguments.as_slice());
 
         let system_clock_handle = Handle::new(heap, 0);
+        let now_handle = Handle::new(heap, 0);
+        let sleep_handle = Handle::new(heap, 1);
 
         let file_open_handle = Handle::new(heap, 1);
+        let file_create_handle = Handle::new(heap, 1);
         let file_read_to_end_handle = Handle::new(heap, 1);
+        let file_write_handle = Handle::new(heap, 2);
         let file_close_handle = Handle::new(heap, 1);
         let file_object = Struct::create_with_symbol_keys(
             heap,
             true,
             [
                 (heap.default_symbols().open, **file_open_handle),
+                (heap.default_symbols().create, **file_create_handle),
                 (
                     heap.default_symbols().read_to_end,
                     **file_read_to_end_handle,
                 ),
+                (heap.default_symbols().write, **file_write_handle),
                 (heap.default_symbols().close, **file_close_handle),
             ],
         );
+        let directory_list_handle = Handle::new(heap, 1);
+        let directory_object = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [(heap.default_symbols().list, **directory_list_handle)],
+        );
         let file_system_object = Struct::create_with_symbol_keys(
             heap,
             true,
-            [(heap.default_symbols().file, file_object.into())],
+            [
+                (heap.default_symbols().file, file_object.into()),
+                (heap.default_symbols().directory, directory_object.into()),
+            ],
         );
 
+        let http_client_handle = Handle::new(heap, 1);
         let http_server_handle = Handle::new(heap, 1);
 
+        let tcp_connect_handle = Handle::new(heap, 1);
+        let tcp_listen_handle = Handle::new(heap, 1);
+        let tcp_object = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().connect, **tcp_connect_handle),
+                (heap.default_symbols().listen, **tcp_listen_handle),
+            ],
+        );
+
+        let run_process_handle = Handle::new(heap, 2);
+
         let get_random_bytes_handle = Handle::new(heap, 1);
 
         let stdin_handle = Handle::new(heap, 0);
         let stdout_handle = Handle::new(heap, 1);
+        let stderr_handle = Handle::new(heap, 1);
 
         let environment_object = Struct::create_with_symbol_keys(
             heap,
@@ -141,33 +258,95 @@ impl DefaultEnvironment {
             [
                 (heap.default_symbols().arguments, arguments.into()),
                 (heap.default_symbols().system_clock, **system_clock_handle),
+                (heap.default_symbols().now, **now_handle),
+                (heap.default_symbols().sleep, **sleep_handle),
                 (
                     heap.default_symbols().file_system,
                     file_system_object.into(),
                 ),
+                (heap.default_symbols().http_client, **http_client_handle),
                 (heap.default_symbols().http_server, **http_server_handle),
+                (heap.default_symbols().tcp, tcp_object.into()),
+                (heap.default_symbols().run_process, **run_process_handle),
                 (
                     heap.default_symbols().get_random_bytes,
                     **get_random_bytes_handle,
                 ),
                 (heap.default_symbols().stdin, **stdin_handle),
                 (heap.default_symbols().stdout, **stdout_handle),
+                (heap.default_symbols().stderr, **stderr_handle),
             ],
         );
         let environment = Self {
             system_clock_handle,
+            now_handle,
+            sleep_handle,
+            clock: Box::<RealClock>::default(),
             file_open_handle,
+            file_create_handle,
             file_read_to_end_handle,
+            file_write_handle,
             file_close_handle,
+            directory_list_handle,
+            file_system_access: FileSystemAccess::default(),
+            http_client_handle,
             http_server_handle,
             http_server_states: vec![],
+            tcp_connect_handle,
+            tcp_listen_handle,
+            tcp_connections: vec![],
+            tcp_listeners: vec![],
+            network_reactor: None,
+            run_process_handle,
             get_random_bytes_handle,
             stdin_handle,
             stdout_handle,
+            stderr_handle,
+            stdin_reader: None,
             dynamic_handles: FxHashMap::default(),
+            handle_call_policies: FxHashMap::default(),
+            deadline: None,
+            timed_out: Cell::new(false),
         };
         (environment_object, environment)
     }
+
+    /// Replaces the clock that the program's time comes from, e.g., with a
+    /// [`FakeClock`] for reproducible runs. By default, it's a [`RealClock`].
+    #[must_use]
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Restricts which paths the program may access. By default, it may
+    /// access everything the current user can.
+    #[must_use]
+    pub fn with_file_system_access(mut self, access: FileSystemAccess) -> Self {
+        self.file_system_access = access;
+        self
+    }
+
+    /// Lets the program access the network. Network operations are spawned on
+    /// the given reactor and the VM waits for them to complete. Without a
+    /// reactor, they fail.
+    #[must_use]
+    pub fn with_network_reactor(mut self, reactor: runtime::Handle) -> Self {
+        self.network_reactor = Some(reactor);
+        self
+    }
+
+    /// Limits how long calls of the handle may block. By default, they may
+    /// block forever.
+    #[must_use]
+    pub fn with_handle_call_policy(
+        mut self,
+        handle: BlockingHandle,
+        policy: HandleCallPolicy,
+    ) -> Self {
+        self.handle_call_policies.insert(handle, policy);
+        self
+    }
 }
 impl Environment for DefaultEnvironment {
     fn handle<B: Borrow<ByteCode>, T: Tracer>(
@@ -175,29 +354,78 @@ impl Environment for DefaultEnvironment {
         heap: &mut Heap,
         call: VmHandleCall<B, T>,
     ) -> Vm<B, T> {
-        let result = if call.handle == self.system_clock_handle {
-            Self::system_clock(heap, &call.arguments)
-        } else if call.handle == self.file_open_handle {
-            self.file_open(heap, &call.arguments)
-        } else if call.handle == self.file_read_to_end_handle {
-            self.file_read_to_end(heap, &call.arguments)
-        } else if call.handle == self.file_close_handle {
-            self.file_close(heap, &call.arguments)
-        } else if call.handle == self.http_server_handle {
-            self.http_server(heap, &call.arguments)
-        } else if call.handle == self.get_random_bytes_handle {
-            Self::get_random_bytes(heap, &call.arguments)
-        } else if call.handle == self.stdin_handle {
-            Self::stdin(heap, &call.arguments)
-        } else if call.handle == self.stdout_handle {
-            Self::stdout(heap, &call.arguments)
+        let policy = self
+            .blocking_handle(call.handle)
+            .and_then(|it| self.handle_call_policies.get(&it))
+            .copied()
+            .unwrap_or_default();
+        let mut retries_left = policy.retries;
+        let result = loop {
+            self.deadline = policy.timeout.map(|timeout| Instant::now() + timeout);
+            let result = self.dispatch(heap, call.handle, &call.arguments);
+            if !self.timed_out.replace(false) {
+                break result;
+            }
+
+            result.drop(heap);
+            if retries_left == 0 {
+                let timed_out = Tag::create(heap.default_symbols().timed_out);
+                break Tag::create_result(heap, true, Err(timed_out.into())).into();
+            }
+            retries_left -= 1;
+        };
+        self.deadline = None;
+        call.complete(heap, result)
+    }
+}
+impl DefaultEnvironment {
+    fn dispatch(
+        &mut self,
+        heap: &mut Heap,
+        handle: Handle,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        if handle == self.system_clock_handle {
+            self.system_clock(heap, arguments)
+        } else if handle == self.now_handle {
+            self.now(heap, arguments)
+        } else if handle == self.sleep_handle {
+            self.sleep(heap, arguments)
+        } else if handle == self.file_open_handle {
+            self.file_open(heap, arguments)
+        } else if handle == self.file_create_handle {
+            self.file_create(heap, arguments)
+        } else if handle == self.file_read_to_end_handle {
+            self.file_read_to_end(heap, arguments)
+        } else if handle == self.file_write_handle {
+            self.file_write(heap, arguments)
+        } else if handle == self.file_close_handle {
+            self.file_close(heap, arguments)
+        } else if handle == self.directory_list_handle {
+            self.directory_list(heap, arguments)
+        } else if handle == self.http_client_handle {
+            self.http_client(heap, arguments)
+        } else if handle == self.http_server_handle {
+            self.http_server(heap, arguments)
+        } else if handle == self.tcp_connect_handle {
+            self.tcp_connect(heap, arguments)
+        } else if handle == self.tcp_listen_handle {
+            self.tcp_listen(heap, arguments)
+        } else if handle == self.run_process_handle {
+            self.run_process(heap, arguments)
+        } else if handle == self.get_random_bytes_handle {
+            Self::get_random_bytes(heap, arguments)
+        } else if handle == self.stdin_handle {
+            self.stdin(heap, arguments)
+        } else if handle == self.stdout_handle {
+            Self::stdout(heap, arguments)
+        } else if handle == self.stderr_handle {
+            Self::stderr(heap, arguments)
         } else {
-            let dynamic_handle = self.dynamic_handles.get(&call.handle).unwrap_or_else(|| {
-                panic!(
-                    "A handle was called that doesn't exist: {handle:?}",
-                    handle = call.handle,
-                )
-            });
+            let dynamic_handle = self
+                .dynamic_handles
+                .get(&handle)
+                .unwrap_or_else(|| panic!("A handle was called that doesn't exist: {handle:?}"));
             match dynamic_handle {
                 DynamicHandle::File(_) => {
                     // TODO: Panic
@@ -206,32 +434,113 @@ impl Environment for DefaultEnvironment {
                     Tag::create_result(heap, true, Err(message.into())).into()
                 }
                 DynamicHandle::HttpServerGetNextRequest(server_index) => {
-                    self.http_server_get_next_request(heap, *server_index, &call.arguments)
+                    self.http_server_get_next_request(heap, *server_index, arguments)
+                }
+                DynamicHandle::HttpServerSendResponse(server_index, request_index) => {
+                    self.http_server_send_response(heap, *server_index, *request_index, arguments)
                 }
-                DynamicHandle::HttpServerSendResponse(server_index, request_index) => self
-                    .http_server_send_response(
-                        heap,
-                        *server_index,
-                        *request_index,
-                        &call.arguments,
-                    ),
                 DynamicHandle::HttpServerClose(server_index) => {
-                    self.http_server_close(heap, *server_index, &call.arguments)
+                    self.http_server_close(heap, *server_index, arguments)
+                }
+                DynamicHandle::TcpConnectionRead(connection_index) => {
+                    self.tcp_connection_read(heap, *connection_index, arguments)
+                }
+                DynamicHandle::TcpConnectionWrite(connection_index) => {
+                    self.tcp_connection_write(heap, *connection_index, arguments)
+                }
+                DynamicHandle::TcpConnectionClose(connection_index) => {
+                    self.tcp_connection_close(heap, *connection_index, arguments)
                 }
+                DynamicHandle::TcpListenerAccept(listener_index) => {
+                    self.tcp_listener_accept(heap, *listener_index, arguments)
+                }
+                DynamicHandle::TcpListenerClose(listener_index) => {
+                    self.tcp_listener_close(heap, *listener_index, arguments)
+                }
+            }
+        }
+    }
+    fn blocking_handle(&self, handle: Handle) -> Option<BlockingHandle> {
+        if handle == self.stdin_handle {
+            return Some(BlockingHandle::Stdin);
+        } else if handle == self.http_client_handle {
+            return Some(BlockingHandle::HttpClient);
+        } else if handle == self.tcp_connect_handle {
+            return Some(BlockingHandle::TcpConnect);
+        }
+        match self.dynamic_handles.get(&handle)? {
+            DynamicHandle::HttpServerGetNextRequest(_) => {
+                Some(BlockingHandle::HttpServerGetNextRequest)
             }
+            DynamicHandle::TcpConnectionRead(_) => Some(BlockingHandle::TcpConnectionRead),
+            DynamicHandle::TcpConnectionWrite(_) => Some(BlockingHandle::TcpConnectionWrite),
+            DynamicHandle::TcpListenerAccept(_) => Some(BlockingHandle::TcpListenerAccept),
+            _ => None,
+        }
+    }
+    /// Waits for the result of a blocking operation. If the current handle
+    /// call's deadline passes first, the call is marked as timed out.
+    ///
+    /// Returns [`None`] if the operation stopped without sending a result or
+    /// if it timed out.
+    fn receive<R>(&self, receiver: &Receiver<R>) -> Option<R> {
+        let Some(deadline) = self.deadline else {
+            return receiver.recv().ok();
         };
-        call.complete(heap, result)
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(value) => Some(value),
+            Err(RecvTimeoutError::Timeout) => {
+                self.timed_out.set(true);
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
     }
 }
 impl DefaultEnvironment {
     // Clock
 
-    fn system_clock(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+    fn system_clock(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [] = arguments else { unreachable!() };
+
+        Int::create(heap, true, self.clock.wall_time().as_nanos()).into()
+    }
+    fn now(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         let [] = arguments else { unreachable!() };
 
-        let now = SystemTime::now();
-        let since_unix_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        Int::create(heap, true, since_unix_epoch.as_nanos()).into()
+        let wall = Int::create(heap, true, self.clock.wall_time().as_nanos());
+        let monotonic = Int::create(heap, true, self.clock.monotonic_time().as_nanos());
+        Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().wall, wall.into()),
+                (heap.default_symbols().monotonic, monotonic.into()),
+            ],
+        )
+        .into()
+    }
+    fn sleep(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [nanoseconds] = arguments else {
+            unreachable!()
+        };
+
+        let nanoseconds = match (*nanoseconds).into() {
+            Data::Int(nanoseconds) => nanoseconds.try_get::<u64>(),
+            _ => None,
+        };
+        let Some(nanoseconds) = nanoseconds else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `sleep` was called with a non-integer or a negative or too large duration.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        self.clock.sleep(Duration::from_nanos(nanoseconds));
+        Tag::create_nothing(heap).into()
     }
 
     // File
@@ -246,10 +555,36 @@ impl DefaultEnvironment {
             return Tag::create_result(heap, true, Err(message.into())).into();
         };
 
-        let file = match File::open(path.get()) {
+        let file = self.file_system_access.open(Path::new(path.get()));
+        let file = match file {
             Ok(file) => file,
             Err(error) => {
-                let message = Text::create(heap, true, &error.to_string());
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        let file_handle = self.create_dynamic_handle(heap, DynamicHandle::File(Some(file)), 0);
+        Tag::create_result(heap, true, Ok(file_handle.into())).into()
+    }
+    fn file_create(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [path] = arguments else { unreachable!() };
+
+        let Data::Text(path) = (*path).into() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `file.create` was called with a non-text.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let file = self.file_system_access.create(Path::new(path.get()));
+        let file = match file {
+            Ok(file) => file,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
                 return Tag::create_result(heap, true, Err(message.into())).into();
             }
         };
@@ -288,6 +623,42 @@ impl DefaultEnvironment {
         let content = List::create(heap, true, content.as_slice()).into();
         Tag::create_result(heap, true, Ok(content)).into()
     }
+    fn file_write(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [file, bytes] = arguments else {
+            unreachable!()
+        };
+
+        let Some(bytes) = bytes_from_list(*bytes) else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `file.write` was called with a non-list of bytes.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let file = match self.resolve_file_handle_mut(heap, "file.write", *file) {
+            Ok(file) => file,
+            Err(result) => return result,
+        };
+
+        let Some(file) = file else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `file.write` was called with a closed file.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let result = file
+            .write_all(&bytes)
+            .map(|()| Tag::create_nothing(heap).into())
+            .map_err(|error| Text::create(heap, true, &error.to_string()).into());
+        Tag::create_result(heap, true, result).into()
+    }
     fn file_close(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         let [file] = arguments else { unreachable!() };
 
@@ -333,8 +704,62 @@ impl DefaultEnvironment {
         }
     }
 
+    fn directory_list(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [path] = arguments else { unreachable!() };
+
+        let Data::Text(path) = (*path).into() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `directory.list` was called with a non-text.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let names = self.file_system_access.list(Path::new(path.get()));
+        let mut names = match names {
+            Ok(names) => names,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        names.sort();
+        let names = names
+            .iter()
+            .map(|it| Text::create(heap, true, it).into())
+            .collect_vec();
+        let names = List::create(heap, true, names.as_slice()).into();
+        Tag::create_result(heap, true, Ok(names)).into()
+    }
+
     // HTTP
 
+    fn http_client(&self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [request] = arguments else { unreachable!() };
+
+        let request = match HttpClientRequest::from_object(heap, *request) {
+            Ok(request) => request,
+            Err(error) => {
+                // TODO: Panic
+                let message = Text::create(
+                    heap,
+                    true,
+                    &format!("Handle `httpClient` was called with an invalid request: {error}"),
+                );
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        let result = self
+            .run_on_reactor(request.send())
+            .and_then(|it| it)
+            .map(|response| response.to_object(heap).into())
+            .map_err(|error| Text::create(heap, true, &error).into());
+        Tag::create_result(heap, true, result).into()
+    }
     fn http_server(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         let [list_of_socket_texts] = arguments else {
             unreachable!()
@@ -415,14 +840,25 @@ impl DefaultEnvironment {
     ) -> InlineObject {
         assert!(arguments.is_empty());
 
+        let timeout = self
+            .deadline
+            .map(|it| it.saturating_duration_since(Instant::now()));
         let server_state = &mut self.http_server_states[server_index];
         let Some(server_state) = server_state else {
             // TODO: Panic
             return Self::http_server_error_closed(heap);
         };
 
-        let mut request = match server_state.server.recv() {
-            Ok(request) => request,
+        let request = match timeout {
+            Some(timeout) => server_state.server.recv_timeout(timeout),
+            None => server_state.server.recv().map(Some),
+        };
+        let mut request = match request {
+            Ok(Some(request)) => request,
+            Ok(None) => {
+                self.timed_out.set(true);
+                return Tag::create_nothing(heap).into();
+            }
             Err(error) => {
                 let message = Text::create(heap, true, &error.to_string());
                 return Tag::create_result(heap, true, Err(message.into())).into();
@@ -528,76 +964,673 @@ impl DefaultEnvironment {
         Tag::create_result(heap, true, Err(message.into())).into()
     }
 
-    // Random
+    // TCP
 
-    fn get_random_bytes(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
-        let [length] = arguments else { unreachable!() };
-        let Data::Int(length) = (*length).into() else {
+    fn tcp_connect(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [address] = arguments else { unreachable!() };
+
+        let Data::Text(address) = (*address).into() else {
             // TODO: Panic
             let message = Text::create(
                 heap,
                 true,
-                "Handle `getRandomBytes` was called with a non-integer.",
+                "Handle `tcp.connect` was called with a non-text.",
             );
             return Tag::create_result(heap, true, Err(message.into())).into();
         };
-        let Some(length) = length.try_get::<usize>() else {
+
+        let address = address.get().to_string();
+        let stream = self
+            .run_on_reactor(async move { TcpStream::connect(address).await })
+            .and_then(|it| it.map_err(|error| error.to_string()));
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        let connection = self.create_tcp_connection(heap, stream);
+        Tag::create_result(heap, true, Ok(connection.into())).into()
+    }
+    fn create_tcp_connection(&mut self, heap: &mut Heap, stream: TcpStream) -> Struct {
+        let connection_index = self.tcp_connections.len();
+        self.tcp_connections.push(Some(stream));
+
+        let read_handle =
+            self.create_dynamic_handle(heap, DynamicHandle::TcpConnectionRead(connection_index), 1);
+        let write_handle = self.create_dynamic_handle(
+            heap,
+            DynamicHandle::TcpConnectionWrite(connection_index),
+            1,
+        );
+        let close_handle = self.create_dynamic_handle(
+            heap,
+            DynamicHandle::TcpConnectionClose(connection_index),
+            0,
+        );
+        Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().read, **read_handle),
+                (heap.default_symbols().write, **write_handle),
+                (heap.default_symbols().close, **close_handle),
+            ],
+        )
+    }
+    fn tcp_connection_read(
+        &mut self,
+        heap: &mut Heap,
+        connection_index: TcpConnectionIndex,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        let [max_length] = arguments else {
+            unreachable!()
+        };
+
+        let max_length = match (*max_length).into() {
+            Data::Int(max_length) => max_length.try_get::<usize>(),
+            _ => None,
+        };
+        let Some(max_length) = max_length else {
             // TODO: Panic
             let message = Text::create(
                 heap,
                 true,
-                "Handle `getRandomBytes` was called with a length that doesn't fit in usize.",
+                "Handle `tcpConnection.read` was called with a non-integer or a length that doesn't fit in usize.",
             );
             return Tag::create_result(heap, true, Err(message.into())).into();
         };
 
-        let mut bytes = vec![0u8; length];
-        if let Err(error) = getrandom::getrandom(&mut bytes) {
-            let message = Text::create(heap, true, &error.to_string());
-            return Tag::create_result(heap, true, Err(message.into())).into();
-        }
-
-        let bytes = bytes
-            .into_iter()
-            .map(|it| Int::create(heap, true, it).into())
-            .collect_vec();
-        let bytes = List::create(heap, true, bytes.as_slice());
-        Tag::create_result(heap, true, Ok(bytes.into())).into()
+        let bytes = self.run_on_tcp_connection(connection_index, |mut stream| async move {
+            let mut buffer = vec![0; max_length];
+            let result = stream.read(&mut buffer).await.map(|length| {
+                buffer.truncate(length);
+                buffer
+            });
+            (stream, result)
+        });
+        let result = bytes
+            .map(|bytes| bytes_to_list(heap, &bytes).into())
+            .map_err(|error| Text::create(heap, true, &error).into());
+        Tag::create_result(heap, true, result).into()
     }
+    fn tcp_connection_write(
+        &mut self,
+        heap: &mut Heap,
+        connection_index: TcpConnectionIndex,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        let [bytes] = arguments else { unreachable!() };
 
-    // Stdio
-
-    fn stdin(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
-        assert!(arguments.is_empty());
-        let input = {
-            let stdin = io::stdin();
-            stdin.lock().lines().next().unwrap().unwrap()
+        let Some(bytes) = bytes_from_list(*bytes) else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `tcpConnection.write` was called with a non-list of bytes.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
         };
-        Text::create(heap, true, &input).into()
+
+        let result = self.run_on_tcp_connection(connection_index, |mut stream| async move {
+            let result = stream.write_all(&bytes).await;
+            (stream, result)
+        });
+        let result = result
+            .map(|()| Tag::create_nothing(heap).into())
+            .map_err(|error| Text::create(heap, true, &error).into());
+        Tag::create_result(heap, true, result).into()
     }
-    fn stdout(heap: &Heap, arguments: &[InlineObject]) -> InlineObject {
-        let [message] = arguments else { unreachable!() };
-        if let Data::Text(text) = (*message).into() {
-            println!("{}", text.get());
-        } else {
-            info!("Non-text value sent to stdout: {message:?}");
+    fn tcp_connection_close(
+        &mut self,
+        heap: &mut Heap,
+        connection_index: TcpConnectionIndex,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        assert!(arguments.is_empty());
+
+        // The connection is closed when dropped.
+        if self.tcp_connections[connection_index].take().is_none() {
+            // TODO: Panic
+            return Self::tcp_error_closed(heap, "connection");
         }
 
         Tag::create_nothing(heap).into()
     }
-
-    fn create_dynamic_handle(
+    /// Moves the connection to the reactor for running the operation and puts
+    /// it back afterwards.
+    fn run_on_tcp_connection<R, F>(
         &mut self,
-        heap: &mut Heap,
-        dynamic_handle: DynamicHandle,
-        argument_count: usize,
-    ) -> Handle {
-        let handle = Handle::new(heap, argument_count);
-        self.dynamic_handles.force_insert(handle, dynamic_handle);
-        handle
+        connection_index: TcpConnectionIndex,
+        operation: impl FnOnce(TcpStream) -> F,
+    ) -> Result<R, String>
+    where
+        R: Send + 'static,
+        F: Future<Output = (TcpStream, io::Result<R>)> + Send + 'static,
+    {
+        let Some(stream) = self.tcp_connections[connection_index].take() else {
+            return Err("The TCP connection was closed already.".to_string());
+        };
+        let (stream, result) = self.run_on_reactor(operation(stream))?;
+        self.tcp_connections[connection_index] = Some(stream);
+        result.map_err(|error| error.to_string())
+    }
+
+    fn tcp_listen(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [address] = arguments else { unreachable!() };
+
+        let Data::Text(address) = (*address).into() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `tcp.listen` was called with a non-text.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let address = address.get().to_string();
+        let listener = self
+            .run_on_reactor(async move {
+                let listener = TcpListener::bind(address).await?;
+                let local_address = listener.local_addr()?;
+                Ok((listener, local_address))
+            })
+            .and_then(|it: io::Result<_>| it.map_err(|error| error.to_string()));
+        let (listener, local_address) = match listener {
+            Ok(listener) => listener,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        let listener_index = self.tcp_listeners.len();
+        self.tcp_listeners.push(Some(listener));
+
+        let accept_handle =
+            self.create_dynamic_handle(heap, DynamicHandle::TcpListenerAccept(listener_index), 0);
+        let close_handle =
+            self.create_dynamic_handle(heap, DynamicHandle::TcpListenerClose(listener_index), 0);
+        let local_address = Text::create(heap, true, &local_address.to_string());
+        let listener = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().accept, **accept_handle),
+                (heap.default_symbols().close, **close_handle),
+                (heap.default_symbols().local_address, local_address.into()),
+            ],
+        );
+        Tag::create_result(heap, true, Ok(listener.into())).into()
+    }
+    fn tcp_listener_accept(
+        &mut self,
+        heap: &mut Heap,
+        listener_index: TcpListenerIndex,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        assert!(arguments.is_empty());
+
+        let Some(listener) = self.tcp_listeners[listener_index].take() else {
+            // TODO: Panic
+            return Self::tcp_error_closed(heap, "listener");
+        };
+        let result = self.run_on_reactor(async move {
+            let result = listener.accept().await;
+            (listener, result)
+        });
+        let stream = result.and_then(|(listener, result)| {
+            self.tcp_listeners[listener_index] = Some(listener);
+            result
+                .map(|(stream, _)| stream)
+                .map_err(|error| error.to_string())
+        });
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        let connection = self.create_tcp_connection(heap, stream);
+        Tag::create_result(heap, true, Ok(connection.into())).into()
+    }
+    fn tcp_listener_close(
+        &mut self,
+        heap: &mut Heap,
+        listener_index: TcpListenerIndex,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        assert!(arguments.is_empty());
+
+        // The listener is closed when dropped.
+        if self.tcp_listeners[listener_index].take().is_none() {
+            // TODO: Panic
+            return Self::tcp_error_closed(heap, "listener");
+        }
+
+        Tag::create_nothing(heap).into()
+    }
+    fn tcp_error_closed(heap: &mut Heap, kind: &str) -> InlineObject {
+        let message = Text::create(heap, true, &format!("The TCP {kind} was closed already."));
+        Tag::create_result(heap, true, Err(message.into())).into()
+    }
+
+    /// Runs the future on the network reactor and waits for its output.
+    fn run_on_reactor<F>(&self, future: F) -> Result<F::Output, String>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let Some(reactor) = &self.network_reactor else {
+            return Err("The environment doesn't have access to the network.".to_string());
+        };
+
+        // We can't block on the future directly because the current thread
+        // might already be driving another runtime.
+        let (sender, receiver) = mpsc::sync_channel(1);
+        reactor.spawn(async move {
+            // If the receiver is gone, nobody is interested in the output.
+            let _ = sender.send(future.await);
+        });
+        self.receive(&receiver).ok_or_else(|| {
+            "The network reactor stopped before the operation completed.".to_string()
+        })
+    }
+
+    // Process
+
+    fn run_process(&self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [program, process_arguments] = arguments else {
+            unreachable!()
+        };
+
+        let Data::Text(program) = (*program).into() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `runProcess` was called with a non-text program.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+        let process_arguments = if let Data::List(process_arguments) = (*process_arguments).into() {
+            process_arguments
+                .items()
+                .iter()
+                .map(|it| match (*it).into() {
+                    Data::Text(text) => Some(text.get().to_string()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        } else {
+            None
+        };
+        let Some(process_arguments) = process_arguments else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `runProcess` was called with arguments that aren't a list of texts.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        // A process could access anything, so sandboxed programs can't run any.
+        if matches!(self.file_system_access, FileSystemAccess::Sandboxed { .. }) {
+            let message = Text::create(heap, true, "The sandbox doesn't allow running processes.");
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        }
+
+        let output = match Command::new(program.get())
+            .args(process_arguments)
+            .stdin(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(error) => {
+                let message = Text::create(heap, true, &error.to_string());
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
+
+        // The exit code is missing if the process was terminated by a signal.
+        let exit_code = match output.status.code() {
+            Some(code) => Int::create(heap, true, code).into(),
+            None => Tag::create_nothing(heap).into(),
+        };
+        let stdout = bytes_to_list(heap, &output.stdout);
+        let stderr = bytes_to_list(heap, &output.stderr);
+        let result = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().exit_code, exit_code),
+                (heap.default_symbols().stdout, stdout.into()),
+                (heap.default_symbols().stderr, stderr.into()),
+            ],
+        );
+        Tag::create_result(heap, true, Ok(result.into())).into()
+    }
+
+    // Random
+
+    fn get_random_bytes(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [length] = arguments else { unreachable!() };
+        let Data::Int(length) = (*length).into() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `getRandomBytes` was called with a non-integer.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+        let Some(length) = length.try_get::<usize>() else {
+            // TODO: Panic
+            let message = Text::create(
+                heap,
+                true,
+                "Handle `getRandomBytes` was called with a length that doesn't fit in usize.",
+            );
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        };
+
+        let mut bytes = vec![0u8; length];
+        if let Err(error) = getrandom::getrandom(&mut bytes) {
+            let message = Text::create(heap, true, &error.to_string());
+            return Tag::create_result(heap, true, Err(message.into())).into();
+        }
+
+        let bytes = bytes
+            .into_iter()
+            .map(|it| Int::create(heap, true, it).into())
+            .collect_vec();
+        let bytes = List::create(heap, true, bytes.as_slice());
+        Tag::create_result(heap, true, Ok(bytes.into())).into()
+    }
+
+    // Stdio
+
+    fn stdin(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+        assert!(arguments.is_empty());
+        // Interactive programs usually print a prompt before reading, which
+        // has to be visible even if it doesn't end with a newline.
+        let _ = io::stdout().flush();
+
+        if self.stdin_reader.is_none() {
+            self.stdin_reader = Some(StdinReader::start());
+        }
+        // The reader thread waits until we take the line, so a read that
+        // timed out doesn't lose any input.
+        match self.receive(&self.stdin_reader.as_ref().unwrap().lines) {
+            Some(line) => Text::create(heap, true, &line).into(),
+            None => Tag::create_nothing(heap).into(),
+        }
+    }
+    fn stdout(heap: &Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [message] = arguments else { unreachable!() };
+        if let Data::Text(text) = (*message).into() {
+            // Stdout is line-buffered. If nobody reads our output anymore
+            // (e.g., when piping into `head`), the program continues anyway.
+            let _ = writeln!(io::stdout().lock(), "{}", text.get());
+        } else {
+            info!("Non-text value sent to stdout: {message:?}");
+        }
+
+        Tag::create_nothing(heap).into()
+    }
+    fn stderr(heap: &Heap, arguments: &[InlineObject]) -> InlineObject {
+        let [message] = arguments else { unreachable!() };
+        if let Data::Text(text) = (*message).into() {
+            eprintln!("{}", text.get());
+        } else {
+            info!("Non-text value sent to stderr: {message:?}");
+        }
+
+        Tag::create_nothing(heap).into()
+    }
+
+    fn create_dynamic_handle(
+        &mut self,
+        heap: &mut Heap,
+        dynamic_handle: DynamicHandle,
+        argument_count: usize,
+    ) -> Handle {
+        let handle = Handle::new(heap, argument_count);
+        self.dynamic_handles.force_insert(handle, dynamic_handle);
+        handle
+    }
+}
+
+/// Reads the process's stdin line by line on a background thread.
+///
+/// The thread reads at most one line ahead of what the program requested, so
+/// input meant for processes that the program starts later isn't consumed.
+struct StdinReader {
+    lines: Receiver<String>,
+}
+impl StdinReader {
+    fn start() -> Self {
+        let (sender, lines) = mpsc::sync_channel(0);
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    // The environment was dropped.
+                    break;
+                }
+            }
+        });
+        Self { lines }
     }
 }
 
+/// Where the time of `environment.systemClock` and `environment.now` comes
+/// from and how `environment.sleep` waits.
+pub trait Clock {
+    /// The time since the Unix epoch.
+    fn wall_time(&mut self) -> Duration;
+    /// The time since some fixed point. Unlike the wall time, this never
+    /// decreases.
+    fn monotonic_time(&mut self) -> Duration;
+    fn sleep(&mut self, duration: Duration);
+}
+/// The operating system's clock.
+pub struct RealClock {
+    start: Instant,
+}
+impl Default for RealClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+impl Clock for RealClock {
+    fn wall_time(&mut self) -> Duration {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+    }
+    fn monotonic_time(&mut self) -> Duration {
+        self.start.elapsed()
+    }
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+/// A clock that only advances when the program sleeps, and does so without
+/// actually waiting. This makes programs that look at the time reproducible.
+#[derive(Clone, Debug, Default)]
+pub struct FakeClock {
+    wall_time_at_start: Duration,
+    elapsed: Duration,
+}
+impl FakeClock {
+    #[must_use]
+    pub const fn new(wall_time_at_start: Duration) -> Self {
+        Self {
+            wall_time_at_start,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+impl Clock for FakeClock {
+    fn wall_time(&mut self) -> Duration {
+        self.wall_time_at_start + self.elapsed
+    }
+    fn monotonic_time(&mut self) -> Duration {
+        self.elapsed
+    }
+    fn sleep(&mut self, duration: Duration) {
+        self.elapsed += duration;
+    }
+}
+
+impl FileSystemAccess {
+    fn open(&self, path: &Path) -> Result<File, String> {
+        let resolved = self.check(path, false)?;
+        let file = File::open(&resolved).map_err(|error| error.to_string())?;
+        self.check_opened(path, &resolved, &file, false)?;
+        Ok(file)
+    }
+    fn create(&self, path: &Path) -> Result<File, String> {
+        let Self::Sandboxed { .. } = self else {
+            return File::create(path).map_err(|error| error.to_string());
+        };
+
+        let resolved = self.check(path, true)?;
+        // Opening an existing file without truncating it and creating new
+        // files exclusively never follows a symlink that was placed at the
+        // resolved path in the meantime. Only once we know that we opened the
+        // checked file, we truncate it.
+        let file = if resolved.exists() {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(&resolved)
+                .map_err(|error| error.to_string())?;
+            self.check_opened(path, &resolved, &file, true)?;
+            file.set_len(0).map_err(|error| error.to_string())?;
+            file
+        } else {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&resolved)
+                .map_err(|error| error.to_string())?
+        };
+        Ok(file)
+    }
+    fn list(&self, path: &Path) -> Result<Vec<String>, String> {
+        let resolved = self.check(path, false)?;
+        let names = fs::read_dir(&resolved)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(|error| error.to_string())?;
+        // We can't inspect the directory that `read_dir` opened, so we at least
+        // check that the path still leads to the same place afterwards.
+        if let Self::Sandboxed { .. } = self
+            && resolved.canonicalize().ok().as_ref() != Some(&resolved)
+        {
+            return Err(Self::denied(path, false));
+        }
+        Ok(names)
+    }
+
+    /// Returns the canonical path to open instead of `path`, so that the
+    /// checked path can't be redirected by changing a symlink in between.
+    fn check(&self, path: &Path, write: bool) -> Result<PathBuf, String> {
+        let Self::Sandboxed { readable, writable } = self else {
+            return Ok(path.to_path_buf());
+        };
+
+        // Files that are about to be created don't exist yet, so we resolve
+        // their parent directory instead. If something exists at the path but
+        // can't be resolved, it's a symlink pointing nowhere (yet), which
+        // creating the file would follow.
+        let resolved = path.canonicalize().or_else(|error| {
+            let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+                return Err(error);
+            };
+            if path.symlink_metadata().is_ok() {
+                return Err(error);
+            }
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            Ok(parent.canonicalize()?.join(file_name))
+        });
+        let allowed = if write {
+            writable.iter().collect_vec()
+        } else {
+            readable.iter().chain(writable).collect_vec()
+        };
+        match resolved {
+            Ok(resolved)
+                if allowed.iter().any(|allowed| {
+                    allowed
+                        .canonicalize()
+                        .is_ok_and(|allowed| resolved.starts_with(allowed))
+                }) =>
+            {
+                Ok(resolved)
+            }
+            _ => Err(Self::denied(path, write)),
+        }
+    }
+    /// Checks that the opened file is the one at the resolved path, i.e., that
+    /// no symlink was swapped in between checking and opening.
+    fn check_opened(
+        &self,
+        path: &Path,
+        resolved: &Path,
+        file: &File,
+        write: bool,
+    ) -> Result<(), String> {
+        let Self::Sandboxed { .. } = self else {
+            return Ok(());
+        };
+        let (Ok(opened), Ok(expected)) = (file.metadata(), resolved.symlink_metadata()) else {
+            return Err(Self::denied(path, write));
+        };
+        if is_same_file(&opened, &expected) {
+            Ok(())
+        } else {
+            Err(Self::denied(path, write))
+        }
+    }
+    fn denied(path: &Path, write: bool) -> String {
+        format!(
+            "The sandbox doesn't allow {} `{}`.",
+            if write { "writing to" } else { "reading from" },
+            path.display(),
+        )
+    }
+}
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+// Other platforms don't expose a file's identity in stable Rust, so we can only
+// compare what we know about the files.
+#[cfg(not(unix))]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.file_type() == b.file_type() && a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
+
 impl HttpServerState {
     fn new(server: Server) -> Self {
         Self {
@@ -608,6 +1641,367 @@ impl HttpServerState {
     }
 }
 
+/// A request sent via `environment.httpClient`:
+/// `[Method: Text, Url: Text, Headers: [Text: Text], Body: List of bytes]`.
+///
+/// `Headers` and `Body` are optional. The `Host`, `Connection`, and
+/// `Content-Length` headers are set automatically.
+struct HttpClientRequest {
+    method: String,
+    host: String,
+    port: u16,
+    /// The host and port as written in the URL.
+    authority: String,
+    /// The path including the query.
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+/// The response to an [`HttpClientRequest`]:
+/// `[Status: Int, Headers: [Text: Text], Body: List of bytes]`.
+///
+/// Header names are lowercase and values of repeated headers are joined by
+/// commas.
+struct HttpClientResponse {
+    status: u16,
+    headers: FxHashMap<String, String>,
+    body: Vec<u8>,
+}
+impl HttpClientRequest {
+    fn from_object(heap: &Heap, request: InlineObject) -> Result<Self, String> {
+        let Data::Struct(request) = request.into() else {
+            return Err("It's not a struct.".to_string());
+        };
+        let field = |symbol| request.get(Tag::create(symbol));
+        let text_field = |symbol: Text| match field(symbol).map(Data::from) {
+            Some(Data::Text(text)) => Ok(text.get().to_string()),
+            _ => Err(format!("It doesn't have a `{}` text.", symbol.get())),
+        };
+
+        let method = text_field(heap.default_symbols().method)?;
+        let url = text_field(heap.default_symbols().url)?;
+        let headers = match field(heap.default_symbols().headers).map(Data::from) {
+            None => vec![],
+            Some(Data::Struct(headers)) => headers
+                .keys()
+                .iter()
+                .zip_eq(headers.values())
+                .map(|(name, value)| match ((*name).into(), (*value).into()) {
+                    (Data::Text(name), Data::Text(value)) => {
+                        Ok((name.get().to_string(), value.get().to_string()))
+                    }
+                    _ => Err("Its `Headers` contain non-texts.".to_string()),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("Its `Headers` aren't a struct.".to_string()),
+        };
+        let body = match field(heap.default_symbols().body) {
+            None => vec![],
+            Some(body) => bytes_from_list(body)
+                .ok_or_else(|| "Its `Body` isn't a list of bytes.".to_string())?,
+        };
+
+        if !method.bytes().all(|it| it.is_ascii_alphabetic()) {
+            return Err(format!("`{method}` is not a valid method."));
+        }
+        if headers
+            .iter()
+            .any(|(name, value)| name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']))
+        {
+            return Err("Its `Headers` contain line breaks.".to_string());
+        }
+
+        // The URL ends up in the request line and the `Host` header, so these
+        // characters could inject headers or even another request.
+        if url.chars().any(|it| it.is_control() || it.is_whitespace()) {
+            return Err(format!(
+                "The URL `{}` contains whitespace or control characters.",
+                url.escape_debug(),
+            ));
+        }
+        let Some(url_without_scheme) = url.strip_prefix("http://") else {
+            return Err(format!(
+                "Only `http://` URLs are supported, but got `{url}`."
+            ));
+        };
+        let path_start = url_without_scheme
+            .find(['/', '?'])
+            .unwrap_or(url_without_scheme.len());
+        let (authority, path) = url_without_scheme.split_at(path_start);
+        let path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{path}")
+        };
+        // IPv6 addresses are written in brackets and contain colons.
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.ends_with(']') => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("The URL `{url}` has an invalid port."))?;
+                (host, port)
+            }
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("The URL `{url}` doesn't have a host."));
+        }
+
+        Ok(Self {
+            method,
+            host: host.to_string(),
+            port,
+            authority: authority.to_string(),
+            path,
+            headers,
+            body,
+        })
+    }
+
+    async fn send(self) -> Result<HttpClientResponse, String> {
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.method,
+            self.path,
+            self.authority,
+            self.body.len(),
+        );
+        for (name, value) in &self.headers {
+            write!(head, "{name}: {value}\r\n").unwrap();
+        }
+        head.push_str("\r\n");
+
+        // Because of `Connection: close`, the server closes the connection
+        // after the response.
+        let mut response = vec![];
+        async {
+            let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(&self.body).await?;
+            stream.read_to_end(&mut response).await
+        }
+        .await
+        .map_err(|error| error.to_string())?;
+        HttpClientResponse::parse(&response)
+    }
+}
+impl HttpClientResponse {
+    fn parse(response: &[u8]) -> Result<Self, String> {
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut parsed = httparse::Response::new(&mut headers);
+        let head_length = match parsed.parse(response) {
+            Ok(httparse::Status::Complete(head_length)) => head_length,
+            Ok(httparse::Status::Partial) => {
+                return Err("The response ended before its headers did.".to_string())
+            }
+            Err(error) => return Err(format!("The response is invalid: {error}")),
+        };
+
+        let mut headers = FxHashMap::<String, String>::default();
+        for header in parsed.headers.iter() {
+            let value = String::from_utf8_lossy(header.value);
+            headers
+                .entry(header.name.to_ascii_lowercase())
+                .and_modify(|it| {
+                    it.push_str(", ");
+                    it.push_str(&value);
+                })
+                .or_insert_with(|| value.into_owned());
+        }
+
+        let body = &response[head_length..];
+        let body = if headers
+            .get("transfer-encoding")
+            .is_some_and(|it| it.eq_ignore_ascii_case("chunked"))
+        {
+            Self::decode_chunked(body)
+                .ok_or_else(|| "The response has an invalid chunked body.".to_string())?
+        } else if let Some(content_length) = headers.get("content-length") {
+            let content_length: usize = content_length
+                .parse()
+                .map_err(|_| "The response has an invalid `Content-Length`.".to_string())?;
+            body.get(..content_length)
+                .ok_or_else(|| "The response ended before its body did.".to_string())?
+                .to_vec()
+        } else {
+            body.to_vec()
+        };
+
+        Ok(Self {
+            status: parsed.code.unwrap(),
+            headers,
+            body,
+        })
+    }
+    fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+        let mut decoded = vec![];
+        loop {
+            let line_end = body.windows(2).position(|it| it == b"\r\n")?;
+            let line = std::str::from_utf8(&body[..line_end]).ok()?;
+            // Chunks may have extensions after a semicolon.
+            let size = line.split(';').next().unwrap().trim();
+            let size = usize::from_str_radix(size, 16).ok()?;
+            body = &body[line_end + 2..];
+            if size == 0 {
+                // We ignore trailers.
+                return Some(decoded);
+            }
+
+            decoded.extend_from_slice(body.get(..size)?);
+            if body.get(size..size + 2)? != b"\r\n" {
+                return None;
+            }
+            body = &body[size + 2..];
+        }
+    }
+
+    fn to_object(&self, heap: &mut Heap) -> Struct {
+        let status = Int::create(heap, true, self.status);
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    Text::create(heap, true, name).into(),
+                    Text::create(heap, true, value).into(),
+                )
+            })
+            .collect();
+        let headers = Struct::create(heap, true, &headers);
+        let body = bytes_to_list(heap, &self.body);
+        Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().status, status.into()),
+                (heap.default_symbols().headers, headers.into()),
+                (heap.default_symbols().body, body.into()),
+            ],
+        )
+    }
+}
+
+fn bytes_from_list(list: InlineObject) -> Option<Vec<u8>> {
+    let Data::List(list) = list.into() else {
+        return None;
+    };
+    list.items()
+        .iter()
+        .map(|it| match (*it).into() {
+            Data::Int(int) => int.try_get::<u8>(),
+            _ => None,
+        })
+        .collect()
+}
+fn bytes_to_list(heap: &mut Heap, bytes: &[u8]) -> List {
+    let bytes = bytes
+        .iter()
+        .map(|it| Int::create(heap, true, *it).into())
+        .collect_vec();
+    List::create(heap, true, bytes.as_slice())
+}
+
+/// An environment for applications that embed the VM, e.g., a playground.
+///
+/// Instead of the process's stdio, programs read lines from and write lines to
+/// channels, so the application can connect them to its UI. Other
+/// capabilities like the file system or HTTP servers are not available.
+pub struct ChannelEnvironment {
+    stdin_handle: Handle,
+    stdout_handle: Handle,
+    stderr_handle: Handle,
+
+    stdin: Receiver<String>,
+    stdout: Sender<String>,
+    stderr: Sender<String>,
+}
+impl ChannelEnvironment {
+    pub fn new(
+        heap: &mut Heap,
+        args: &[String],
+        stdin: Receiver<String>,
+        stdout: Sender<String>,
+        stderr: Sender<String>,
+    ) -> (Struct, Self) {
+        let arguments = args
+            .iter()
+            .map(|it| Text::create(heap, true, it).into())
+            .collect_vec();
+        let arguments = List::create(heap, true, arguments.as_slice());
+
+        let stdin_handle = Handle::new(heap, 0);
+        let stdout_handle = Handle::new(heap, 1);
+        let stderr_handle = Handle::new(heap, 1);
+
+        let environment_object = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().arguments, arguments.into()),
+                (heap.default_symbols().stdin, **stdin_handle),
+                (heap.default_symbols().stdout, **stdout_handle),
+                (heap.default_symbols().stderr, **stderr_handle),
+            ],
+        );
+        let environment = Self {
+            stdin_handle,
+            stdout_handle,
+            stderr_handle,
+            stdin,
+            stdout,
+            stderr,
+        };
+        (environment_object, environment)
+    }
+}
+impl Environment for ChannelEnvironment {
+    fn handle<B: Borrow<ByteCode>, T: Tracer>(
+        &mut self,
+        heap: &mut Heap,
+        call: VmHandleCall<B, T>,
+    ) -> Vm<B, T> {
+        let result = if call.handle == self.stdin_handle {
+            assert!(call.arguments.is_empty());
+            // Like with the process's stdin, the program receives `Nothing`
+            // once the input ended.
+            match self.stdin.recv() {
+                Ok(input) => Text::create(heap, true, &input).into(),
+                Err(_) => Tag::create_nothing(heap).into(),
+            }
+        } else if call.handle == self.stdout_handle {
+            Self::send(heap, &self.stdout, "stdout", &call.arguments)
+        } else if call.handle == self.stderr_handle {
+            Self::send(heap, &self.stderr, "stderr", &call.arguments)
+        } else {
+            panic!(
+                "A handle was called that doesn't exist: {handle:?}",
+                handle = call.handle,
+            )
+        };
+        call.complete(heap, result)
+    }
+}
+impl ChannelEnvironment {
+    fn send(
+        heap: &Heap,
+        channel: &Sender<String>,
+        channel_name: &str,
+        arguments: &[InlineObject],
+    ) -> InlineObject {
+        let [message] = arguments else { unreachable!() };
+        if let Data::Text(text) = (*message).into() {
+            // If the application is no longer interested in the output, we
+            // just discard it.
+            let _ = channel.send(text.get().to_string());
+        } else {
+            info!("Non-text value sent to {channel_name}: {message:?}");
+        }
+
+        Tag::create_nothing(heap).into()
+    }
+}
+
 #[must_use]
 pub enum StateAfterRunWithoutHandles<B: Borrow<ByteCode>, T: Tracer> {
     Running(Vm<B, T>),
@@ -656,3 +2050,310 @@ impl<B: Borrow<ByteCode>, T: Tracer> Vm<B, T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{FileSystemAccess, HttpClientRequest, HttpClientResponse};
+    use crate::heap::{Heap, Struct, Text};
+    use std::{
+        env, fs,
+        io::{Read, Write},
+        net::TcpListener,
+        path::PathBuf,
+        process, thread,
+    };
+    use tokio::runtime;
+
+    /// A fresh directory containing:
+    ///
+    /// - `readable/file.txt`
+    /// - `writable/`
+    /// - `outside/secret.txt`
+    struct Sandbox {
+        root: PathBuf,
+        access: FileSystemAccess,
+    }
+    impl Sandbox {
+        fn new(name: &str) -> Self {
+            let root = env::temp_dir().join(format!("candy-sandbox-{}-{name}", process::id()));
+            let _ = fs::remove_dir_all(&root);
+            for directory in ["readable", "writable", "outside"] {
+                fs::create_dir_all(root.join(directory)).unwrap();
+            }
+            fs::write(root.join("readable/file.txt"), "readable").unwrap();
+            fs::write(root.join("outside/secret.txt"), "secret").unwrap();
+            let access = FileSystemAccess::Sandboxed {
+                readable: vec![root.join("readable")],
+                writable: vec![root.join("writable")],
+            };
+            Self { root, access }
+        }
+        fn path(&self, path: &str) -> PathBuf {
+            self.root.join(path)
+        }
+        fn secret(&self) -> String {
+            fs::read_to_string(self.path("outside/secret.txt")).unwrap()
+        }
+    }
+    impl Drop for Sandbox {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn allows_access_inside_the_sandbox() {
+        let sandbox = Sandbox::new("inside");
+        assert!(sandbox
+            .access
+            .open(&sandbox.path("readable/file.txt"))
+            .is_ok());
+        assert!(sandbox.access.list(&sandbox.path("readable")).is_ok());
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("writable/new.txt"))
+            .is_ok());
+        // Writable paths are readable as well.
+        assert!(sandbox
+            .access
+            .open(&sandbox.path("writable/new.txt"))
+            .is_ok());
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("writable/new.txt"))
+            .is_ok());
+    }
+
+    #[test]
+    fn denies_access_outside_the_sandbox() {
+        let sandbox = Sandbox::new("outside");
+        assert!(sandbox
+            .access
+            .open(&sandbox.path("outside/secret.txt"))
+            .is_err());
+        assert!(sandbox.access.list(&sandbox.path("outside")).is_err());
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("outside/new.txt"))
+            .is_err());
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("readable/new.txt"))
+            .is_err());
+        assert!(!sandbox.path("outside/new.txt").exists());
+        assert!(!sandbox.path("readable/new.txt").exists());
+    }
+
+    #[test]
+    fn resolves_parent_navigations() {
+        let sandbox = Sandbox::new("parent");
+        let access = &sandbox.access;
+        assert!(access
+            .open(&sandbox.path("readable/../readable/file.txt"))
+            .is_ok());
+        assert!(access
+            .open(&sandbox.path("readable/../outside/secret.txt"))
+            .is_err());
+        assert!(access
+            .create(&sandbox.path("writable/../outside/new.txt"))
+            .is_err());
+        assert!(access.list(&sandbox.path("readable/..")).is_err());
+        assert!(!sandbox.path("outside/new.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn denies_symlinks_leading_outside() {
+        use std::os::unix::fs::symlink;
+
+        let sandbox = Sandbox::new("symlinks");
+        symlink(
+            sandbox.path("outside/secret.txt"),
+            sandbox.path("writable/link"),
+        )
+        .unwrap();
+        symlink(
+            sandbox.path("outside/new.txt"),
+            sandbox.path("writable/dangling"),
+        )
+        .unwrap();
+        symlink(sandbox.path("outside"), sandbox.path("writable/directory")).unwrap();
+
+        assert!(sandbox.access.open(&sandbox.path("writable/link")).is_err());
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("writable/link"))
+            .is_err());
+        assert_eq!(sandbox.secret(), "secret");
+
+        assert!(sandbox
+            .access
+            .create(&sandbox.path("writable/dangling"))
+            .is_err());
+        assert!(!sandbox.path("outside/new.txt").exists());
+
+        assert!(sandbox
+            .access
+            .list(&sandbox.path("writable/directory"))
+            .is_err());
+        let through_directory = sandbox.path("writable/directory/secret.txt");
+        assert!(sandbox.access.open(&through_directory).is_err());
+    }
+
+    #[test]
+    fn denies_files_swapped_after_checking() {
+        let sandbox = Sandbox::new("swapped");
+        let path = sandbox.path("readable/file.txt");
+        let resolved = sandbox.access.check(&path, false).unwrap();
+
+        // Simulates the path being replaced after it was checked.
+        let opened = fs::File::open(sandbox.path("outside/secret.txt")).unwrap();
+        assert!(sandbox
+            .access
+            .check_opened(&path, &resolved, &opened, false)
+            .is_err());
+
+        let opened = fs::File::open(&resolved).unwrap();
+        assert!(sandbox
+            .access
+            .check_opened(&path, &resolved, &opened, false)
+            .is_ok());
+    }
+
+    fn request(heap: &mut Heap, method: &str, url: &str) -> Result<HttpClientRequest, String> {
+        let method = Text::create(heap, true, method);
+        let url = Text::create(heap, true, url);
+        let symbols = heap.default_symbols();
+        let fields = [(symbols.method, method.into()), (symbols.url, url.into())];
+        let request = Struct::create_with_symbol_keys(heap, true, fields);
+        let result = HttpClientRequest::from_object(heap, request.into());
+        request.drop(heap);
+        result
+    }
+
+    #[test]
+    fn parses_urls() {
+        let mut heap = Heap::default();
+        let parsed = request(&mut heap, "GET", "http://localhost:8080/a?b=c").unwrap();
+        assert_eq!(parsed.host, "localhost");
+        assert_eq!(parsed.port, 8080);
+        assert_eq!(parsed.authority, "localhost:8080");
+        assert_eq!(parsed.path, "/a?b=c");
+
+        let parsed = request(&mut heap, "GET", "http://[::1]?query").unwrap();
+        assert_eq!(parsed.host, "::1");
+        assert_eq!(parsed.port, 80);
+        assert_eq!(parsed.path, "/?query");
+
+        assert!(request(&mut heap, "GET", "https://example.com").is_err());
+        assert!(request(&mut heap, "GET", "http://:80/").is_err());
+        assert!(request(&mut heap, "GET /", "http://example.com").is_err());
+    }
+
+    #[test]
+    fn rejects_urls_that_would_split_the_request() {
+        let mut heap = Heap::default();
+        for url in [
+            "http://example.com/a\r\nX-Injected: 1",
+            "http://example.com\r\nX-Injected: 1/",
+            "http://example.com/a b",
+            "http://example.com/a\tb",
+            "http://example.com/\0",
+        ] {
+            assert!(request(&mut heap, "GET", url).is_err(), "{url:?}");
+        }
+    }
+
+    #[test]
+    fn parses_responses() {
+        let response = HttpClientResponse::parse(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\nX-A: 1\r\nx-a: 2\r\n\r\nhello, ignored",
+        )
+        .unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.headers["x-a"], "1, 2");
+        assert_eq!(response.body, b"hello");
+
+        let response = HttpClientResponse::parse(b"HTTP/1.1 200 OK\r\n\r\nuntil the end").unwrap();
+        assert_eq!(response.body, b"until the end");
+
+        let response = HttpClientResponse::parse(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.body, b"hello");
+
+        assert!(HttpClientResponse::parse(b"HTTP/1.1 200 OK\r\nX-A: 1\r\n").is_err());
+        assert!(HttpClientResponse::parse(b"not http\r\n\r\n").is_err());
+        assert!(
+            HttpClientResponse::parse(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhi").is_err()
+        );
+        assert!(
+            HttpClientResponse::parse(b"HTTP/1.1 200 OK\r\nContent-Length: x\r\n\r\n").is_err()
+        );
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        assert_eq!(
+            HttpClientResponse::decode_chunked(b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n"),
+            Some(b"hello, world".to_vec()),
+        );
+        assert_eq!(
+            HttpClientResponse::decode_chunked(b"A\r\n0123456789\r\n0\r\nTrailer: 1\r\n\r\n"),
+            Some(b"0123456789".to_vec()),
+        );
+        assert_eq!(
+            HttpClientResponse::decode_chunked(b"0\r\n\r\n"),
+            Some(vec![])
+        );
+        // A missing last chunk, a wrong size, and an invalid size.
+        assert_eq!(HttpClientResponse::decode_chunked(b"5\r\nhello\r\n"), None);
+        assert_eq!(
+            HttpClientResponse::decode_chunked(b"3\r\nhello\r\n0\r\n\r\n"),
+            None
+        );
+        assert_eq!(HttpClientResponse::decode_chunked(b"x\r\nhello\r\n"), None);
+    }
+
+    #[test]
+    fn sends_requests_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"ping") {
+                let length = stream.read(&mut buffer).unwrap();
+                assert_ne!(length, 0);
+                request.extend_from_slice(&buffer[..length]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 4\r\n\r\npong")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut heap = Heap::default();
+        let url = format!("http://127.0.0.1:{port}/a?b");
+        let mut request = request(&mut heap, "POST", &url).unwrap();
+        request.headers.push(("X-A".to_string(), "1".to_string()));
+        request.body = b"ping".to_vec();
+        let response = runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap()
+            .block_on(request.send())
+            .unwrap();
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, b"pong");
+        assert_eq!(
+            server.join().unwrap(),
+            format!(
+                "POST /a?b HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nConnection: close\r\nContent-Length: 4\r\nX-A: 1\r\n\r\nping",
+            ),
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! A cycle collector that complements reference counting.
+//!
+//! Reference counting frees objects as soon as they're no longer referenced,
+//! except for objects that reference each other in a cycle. These keep each
+//! other's reference count above zero forever.
+//!
+//! Instead of requiring the VM to list its roots, the collector derives them
+//! from the reference counts (similar to the garbage collector of CPython): An
+//! object that has more references than other objects in this heap hold to it
+//! is referenced from outside, e.g., from a VM's data stack, a tracer, or a
+//! handle call that is in progress. Everything reachable from these roots
+//! stays alive and all remaining objects are garbage.
+
+use super::{
+    object_heap::HeapObject, Heap, HeapData, HeapObjectTrait, InlineData, InlineObject,
+    ObjectInHeap,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::debug;
+
+impl Heap {
+    /// Below this size, collecting cycles isn't worth the time.
+    pub(super) const MIN_GARBAGE_COLLECTION_THRESHOLD: usize = 1024 * 1024;
+
+    /// Collects reference cycles if the heap grew considerably since the last
+    /// collection.
+    ///
+    /// Only call this while all objects are fully initialized and all
+    /// references to them are counted, e.g., between two instructions.
+    pub fn collect_garbage_if_needed(&mut self) {
+        if self.allocated_bytes() >= self.next_garbage_collection_at {
+            self.collect_garbage();
+        }
+    }
+
+    /// Frees all objects that are only kept alive by reference cycles.
+    ///
+    /// Only call this while all objects are fully initialized and all
+    /// references to them are counted, e.g., between two instructions.
+    pub fn collect_garbage(&mut self) {
+        let allocated_bytes_before = self.allocated_bytes();
+
+        let mut internal_reference_counts = FxHashMap::<ObjectInHeap, usize>::default();
+        for object in &self.objects {
+            for child in self.children_in_heap(**object) {
+                *internal_reference_counts.entry(child).or_default() += 1;
+            }
+        }
+
+        // Objects without a reference count (e.g., the default symbols) are
+        // never freed.
+        let mut to_visit = self
+            .objects
+            .iter()
+            .filter(|object| {
+                object.reference_count().map_or(true, |reference_count| {
+                    let internal_reference_count = internal_reference_counts
+                        .get(object)
+                        .copied()
+                        .unwrap_or_default();
+                    reference_count > internal_reference_count
+                })
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let mut alive = FxHashSet::default();
+        while let Some(object) = to_visit.pop() {
+            if alive.insert(object) {
+                to_visit.extend(self.children_in_heap(*object));
+            }
+        }
+
+        let garbage = self
+            .objects
+            .iter()
+            .filter(|object| !alive.contains(*object))
+            .copied()
+            .collect::<FxHashSet<_>>();
+        if garbage.is_empty() {
+            self.update_garbage_collection_threshold();
+            return;
+        }
+
+        // References between garbage objects don't need to be adjusted since
+        // all of them get deallocated. But garbage can also reference objects
+        // that stay alive and handles.
+        for object in &garbage {
+            for child in HeapData::from(**object).children() {
+                let is_garbage = Self::referenced_object(child)
+                    .is_some_and(|it| garbage.contains(&ObjectInHeap(it)));
+                if !is_garbage {
+                    child.drop(self);
+                }
+            }
+        }
+        for object in &garbage {
+            self.deallocate(HeapData::from(**object));
+        }
+
+        debug!(
+            "Collected {} objects in reference cycles, freeing {} bytes.",
+            garbage.len(),
+            allocated_bytes_before - self.allocated_bytes(),
+        );
+        self.update_garbage_collection_threshold();
+    }
+    fn update_garbage_collection_threshold(&mut self) {
+        self.next_garbage_collection_at =
+            (2 * self.allocated_bytes()).max(Self::MIN_GARBAGE_COLLECTION_THRESHOLD);
+    }
+
+    /// The children of the object that were allocated in this heap. Children
+    /// can also live in other heaps, e.g., constants of the byte code.
+    fn children_in_heap(&self, object: HeapObject) -> impl Iterator<Item = ObjectInHeap> + '_ {
+        HeapData::from(object)
+            .children()
+            .into_iter()
+            .filter_map(Self::referenced_object)
+            .map(ObjectInHeap)
+            .filter(|it| self.objects.contains(it))
+    }
+    pub(super) fn referenced_object(object: InlineObject) -> Option<HeapObject> {
+        match InlineData::from(object) {
+            InlineData::Pointer(pointer) => Some(pointer.get()),
+            InlineData::Tag(tag) => Some(**tag.get()),
+            InlineData::Int(_) | InlineData::Builtin(_) | InlineData::Handle(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Int, List};
+
+    #[test]
+    fn collects_cycles() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+        let allocated_bytes = heap.allocated_bytes();
+
+        let (a, b) = create_cycle(&mut heap);
+        assert_eq!(a.reference_count(), Some(1));
+        assert_eq!(b.reference_count(), Some(1));
+        assert_eq!(heap.objects().len(), object_count + 2);
+
+        heap.collect_garbage();
+        assert_eq!(heap.objects().len(), object_count);
+        assert_eq!(heap.allocated_bytes(), allocated_bytes);
+    }
+
+    #[test]
+    fn keeps_referenced_cycles() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+
+        let (a, _) = create_cycle(&mut heap);
+        a.dup();
+        heap.collect_garbage();
+        assert_eq!(heap.objects().len(), object_count + 2);
+
+        a.drop(&mut heap);
+        heap.collect_garbage();
+        assert_eq!(heap.objects().len(), object_count);
+    }
+
+    /// Creates two lists that reference each other and aren't referenced from
+    /// anywhere else.
+    fn create_cycle(heap: &mut Heap) -> (List, List) {
+        let one: InlineObject = Int::create(heap, true, 1).into();
+        let a = List::create(heap, true, &[one]);
+        let b = List::create(heap, true, &[a.into()]);
+        a.replace_in_place(0, b.into());
+        (a, b)
+    }
+}
@@ -0,0 +1,201 @@
+//! Sampling how long heap objects live.
+//!
+//! Choosing the thresholds of a generational garbage collector or deciding
+//! which kinds of objects are worth storing inline requires knowing how long
+//! objects typically live. While enabled, the [`LifetimeSampler`] follows
+//! every n-th allocation and records how many instructions ran until the
+//! object was freed.
+
+use super::{Data, DataDiscriminants, Heap, HeapObject, ObjectInHeap};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{fmt::Write, num::NonZeroUsize};
+
+impl Heap {
+    /// Follows every `interval`-th allocation from now on to record how long
+    /// objects live. Retrieve the results with
+    /// [`Heap::take_lifetime_histogram`].
+    ///
+    /// Lifetimes are measured in instructions, so the VM running on this heap
+    /// has to report them via [`Heap::record_instruction`].
+    pub fn enable_lifetime_sampling(&mut self, interval: NonZeroUsize) {
+        self.lifetime_sampler = Some(LifetimeSampler::new(interval));
+    }
+    pub fn record_instruction(&mut self) {
+        if let Some(sampler) = &mut self.lifetime_sampler {
+            sampler.instructions += 1;
+        }
+    }
+    /// Stops sampling lifetimes and returns what was recorded so far.
+    ///
+    /// Objects that are still allocated are reported with their current age.
+    #[must_use]
+    pub fn take_lifetime_histogram(&mut self) -> Option<LifetimeHistogram> {
+        self.lifetime_sampler
+            .take()
+            .map(LifetimeSampler::into_histogram)
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct LifetimeSampler {
+    interval: NonZeroUsize,
+    allocations_until_sample: usize,
+    instructions: u64,
+    /// Sampled objects that are still allocated, together with their kind and
+    /// when they were allocated
+    alive: FxHashMap<ObjectInHeap, (DataDiscriminants, u64)>,
+    histogram: LifetimeHistogram,
+}
+impl LifetimeSampler {
+    fn new(interval: NonZeroUsize) -> Self {
+        Self {
+            interval,
+            allocations_until_sample: interval.get(),
+            instructions: 0,
+            alive: FxHashMap::default(),
+            histogram: LifetimeHistogram::default(),
+        }
+    }
+
+    pub(super) fn record_allocation(&mut self, object: HeapObject) {
+        self.allocations_until_sample -= 1;
+        if self.allocations_until_sample > 0 {
+            return;
+        }
+        self.allocations_until_sample = self.interval.get();
+
+        let kind = DataDiscriminants::from(Data::from(object));
+        self.alive
+            .insert(ObjectInHeap(object), (kind, self.instructions));
+    }
+    pub(super) fn record_deallocation(&mut self, object: HeapObject) {
+        if let Some((kind, allocated_at)) = self.alive.remove(&ObjectInHeap(object)) {
+            self.histogram
+                .record(kind, self.instructions - allocated_at, false);
+        }
+    }
+
+    fn into_histogram(mut self) -> LifetimeHistogram {
+        for (kind, allocated_at) in self.alive.into_values() {
+            self.histogram
+                .record(kind, self.instructions - allocated_at, true);
+        }
+        self.histogram
+    }
+}
+
+/// How many of the sampled objects of each kind lived for how many
+/// instructions.
+///
+/// Lifetimes are grouped into buckets whose size doubles: Bucket 0 contains
+/// objects that were freed during the instruction that allocated them, bucket
+/// `i > 0` contains lifetimes from `2^(i - 1)` to `2^i - 1` instructions.
+#[derive(Debug, Default)]
+pub struct LifetimeHistogram {
+    buckets: FxHashMap<(DataDiscriminants, u32), LifetimeBucket>,
+}
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LifetimeBucket {
+    pub freed: usize,
+    /// Objects that were still allocated when the histogram was taken. Their
+    /// lifetime is at least their age at that point.
+    pub still_alive: usize,
+}
+impl LifetimeHistogram {
+    fn record(&mut self, kind: DataDiscriminants, lifetime: u64, is_still_alive: bool) {
+        let bucket = self
+            .buckets
+            .entry((kind, Self::bucket_of(lifetime)))
+            .or_default();
+        if is_still_alive {
+            bucket.still_alive += 1;
+        } else {
+            bucket.freed += 1;
+        }
+    }
+    const fn bucket_of(lifetime: u64) -> u32 {
+        u64::BITS - lifetime.leading_zeros()
+    }
+    /// The smallest and largest lifetime in the bucket.
+    const fn bounds_of(bucket: u32) -> (u64, u64) {
+        if bucket == 0 {
+            (0, 0)
+        } else {
+            (1 << (bucket - 1), u64::MAX >> (u64::BITS - bucket))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, kind: DataDiscriminants, lifetime: u64) -> LifetimeBucket {
+        self.buckets
+            .get(&(kind, Self::bucket_of(lifetime)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the histogram as CSV with one row per kind and bucket that
+    /// contains objects.
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv = "kind,min_instructions,max_instructions,freed,still_alive\n".to_string();
+        for ((kind, bucket), counts) in self
+            .buckets
+            .iter()
+            .sorted_by_key(|((kind, bucket), _)| (<&'static str>::from(*kind), *bucket))
+        {
+            let (min, max) = Self::bounds_of(*bucket);
+            writeln!(
+                csv,
+                "{},{min},{max},{},{}",
+                <&'static str>::from(*kind),
+                counts.freed,
+                counts.still_alive,
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataDiscriminants, Heap, LifetimeBucket};
+    use crate::heap::{InlineObject, Text};
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn records_lifetimes_in_instructions() {
+        let mut heap = Heap::default();
+        heap.enable_lifetime_sampling(NonZeroUsize::new(1).unwrap());
+
+        let short_lived: InlineObject = Text::create(&mut heap, true, "short").into();
+        let long_lived: InlineObject = Text::create(&mut heap, true, "long").into();
+        heap.record_instruction();
+        short_lived.drop(&mut heap);
+        for _ in 0..4 {
+            heap.record_instruction();
+        }
+
+        let histogram = heap.take_lifetime_histogram().unwrap();
+        assert_eq!(
+            histogram.get(DataDiscriminants::Text, 1),
+            LifetimeBucket {
+                freed: 1,
+                still_alive: 0,
+            },
+        );
+        assert_eq!(
+            histogram.get(DataDiscriminants::Text, 5),
+            LifetimeBucket {
+                freed: 0,
+                still_alive: 1,
+            },
+        );
+        assert_eq!(
+            histogram.to_csv(),
+            "kind,min_instructions,max_instructions,freed,still_alive\nText,1,1,1,0\nText,4,7,0,1\n",
+        );
+        long_lived.drop(&mut heap);
+    }
+}
@@ -1,7 +1,11 @@
-use self::object_heap::text::HeapText;
+use self::{
+    lifetimes::LifetimeSampler, object_heap::text::HeapText, object_inline::tag::InlineTag,
+};
 pub use self::{
+    lifetimes::{LifetimeBucket, LifetimeHistogram},
     object::{
-        Builtin, Data, DataDiscriminants, Function, Handle, HirId, Int, List, Struct, Tag, Text,
+        Builtin, Bytes, Cell, Data, DataDiscriminants, Fraction, Function, Handle, HirId, Int,
+        List, Map, Number, Set, Struct, Tag, Text, TextBuilder,
     },
     object_heap::{HeapData, HeapObject, HeapObjectTrait},
     object_inline::{
@@ -12,18 +16,23 @@ pub use self::{
 use crate::handle_id::HandleId;
 use candy_frontend::id::IdGenerator;
 use derive_more::{DebugCustom, Deref, Pointer};
+use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
     alloc::{self, Allocator, Layout},
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter},
     hash::{Hash, Hasher},
     mem,
 };
 use tracing::debug;
 
+mod garbage_collection;
+mod lifetimes;
 mod object;
 mod object_heap;
 mod object_inline;
+mod snapshot;
+mod validation;
 
 pub const DEBUG_ALLOCATIONS: bool = false;
 
@@ -32,9 +41,59 @@ pub struct Heap {
     default_symbols: Option<DefaultSymbols>,
     handle_id_generator: IdGenerator<HandleId>,
     handle_refcounts: FxHashMap<HandleId, usize>,
+    statistics: HeapStatistics,
+    /// When more than this many bytes are allocated, the heap is out of
+    /// memory. Allocations still succeed, but VMs running on this heap panic
+    /// after their current instruction. Builtins that could allocate huge
+    /// objects check [`Heap::can_allocate`] beforehand instead.
+    memory_limit: Option<usize>,
+    /// Once this many bytes are allocated, the VM collects reference cycles.
+    next_garbage_collection_at: usize,
+    /// See [`Heap::enable_lifetime_sampling`].
+    lifetime_sampler: Option<LifetimeSampler>,
 }
 
 impl Heap {
+    #[must_use]
+    pub fn with_memory_limit(memory_limit: usize) -> Self {
+        let mut heap = Self::default();
+        heap.set_memory_limit(Some(memory_limit));
+        heap
+    }
+
+    #[must_use]
+    pub const fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+    pub fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.memory_limit = memory_limit;
+    }
+    /// The number of bytes currently allocated for objects in this heap.
+    #[must_use]
+    pub const fn allocated_bytes(&self) -> usize {
+        self.statistics.allocated_bytes
+    }
+    #[must_use]
+    pub const fn statistics(&self) -> &HeapStatistics {
+        &self.statistics
+    }
+    #[must_use]
+    pub fn is_out_of_memory(&self) -> bool {
+        self.memory_limit
+            .is_some_and(|memory_limit| self.allocated_bytes() > memory_limit)
+    }
+    /// Whether allocating an object with `content_size` bytes of content
+    /// keeps this heap within its memory limit.
+    #[must_use]
+    pub fn can_allocate(&self, content_size: usize) -> bool {
+        self.memory_limit.map_or(true, |memory_limit| {
+            self.allocated_bytes()
+                .saturating_add(2 * HeapObject::WORD_SIZE)
+                .saturating_add(content_size)
+                <= memory_limit
+        })
+    }
+
     pub fn allocate(
         &mut self,
         kind_bits: u64,
@@ -61,27 +120,43 @@ impl Heap {
         }
         let layout = Layout::from_size_align(size, HeapObject::WORD_SIZE).unwrap();
 
-        // TODO: Handle allocation failure by stopping the VM.
-        let pointer = alloc::Global.allocate(layout);
-        let pointer = unsafe { pointer.unwrap_unchecked() };
+        // Exceeding the memory limit is handled by the VM, which panics after
+        // the current instruction. Only if the system itself runs out of
+        // memory, we abort.
+        let pointer = alloc::Global
+            .allocate(layout)
+            .unwrap_or_else(|_| alloc::handle_alloc_error(layout));
         let pointer = pointer.cast();
         unsafe { *pointer.as_ptr() = header_word };
         let object = HeapObject::new(pointer);
+        self.statistics.record_allocation(object, size);
+        if let Some(sampler) = &mut self.lifetime_sampler {
+            sampler.record_allocation(object);
+        }
         if object.is_reference_counted() {
             object.set_reference_count(1);
         }
         self.objects.insert(ObjectInHeap(object));
         object
     }
+    /// Frees an object allocated in a call frame, which isn't
+    /// reference-counted, and drops its children.
+    pub fn free_frame_object(&mut self, object: InlineObject) {
+        let object =
+            HeapObject::try_from(object).expect("Frame-allocated objects are always heap objects.");
+        debug_assert!(!object.is_reference_counted());
+        object.free(self);
+    }
     /// Don't call this method directly, call [drop] or [free] instead!
     pub(super) fn deallocate(&mut self, object: HeapData) {
         object.deallocate_external_stuff();
-        let layout = Layout::from_size_align(
-            2 * HeapObject::WORD_SIZE + object.content_size(),
-            HeapObject::WORD_SIZE,
-        )
-        .unwrap();
+        let size = 2 * HeapObject::WORD_SIZE + object.content_size();
+        let layout = Layout::from_size_align(size, HeapObject::WORD_SIZE).unwrap();
         self.objects.remove(&ObjectInHeap(*object));
+        self.statistics.record_deallocation(*object, size);
+        if let Some(sampler) = &mut self.lifetime_sampler {
+            sampler.record_deallocation(*object);
+        }
         unsafe { alloc::Global.deallocate(object.address().cast(), layout) };
     }
 
@@ -106,6 +181,7 @@ impl Heap {
 
     pub fn adopt(&mut self, mut other: Self) {
         self.objects.extend(mem::take(&mut other.objects));
+        self.statistics.adopt(&mem::take(&mut other.statistics));
         for (handle_id, refcount) in mem::take(&mut other.handle_refcounts) {
             *self.handle_refcounts.entry(handle_id).or_default() += refcount;
         }
@@ -138,6 +214,10 @@ impl Heap {
             default_symbols: None,
             handle_id_generator: self.handle_id_generator.clone(),
             handle_refcounts: self.handle_refcounts.clone(),
+            statistics: HeapStatistics::default(),
+            memory_limit: self.memory_limit,
+            next_garbage_collection_at: self.next_garbage_collection_at,
+            lifetime_sampler: None,
         };
 
         let mut mapping = FxHashMap::default();
@@ -151,9 +231,51 @@ impl Heap {
         for object in &self.objects {
             _ = object.clone_to_heap_with_mapping(&mut cloned, &mut mapping);
         }
+        // Cloning increases the reference counts of the clones, but references
+        // from outside the heap (e.g., a VM's stack) still have to be counted.
+        // Equal objects share one clone, which owns all of their references.
+        let mut reference_counts = FxHashMap::<ObjectInHeap, usize>::default();
+        for object in &self.objects {
+            if let Some(reference_count) = object.reference_count() {
+                *reference_counts
+                    .entry(ObjectInHeap(mapping[&**object]))
+                    .or_default() += reference_count;
+            }
+        }
+        for (object, reference_count) in reference_counts {
+            if object.is_reference_counted() {
+                object.set_reference_count(reference_count);
+            }
+        }
 
         (cloned, mapping)
     }
+    /// Returns the clone of `object` according to the `mapping` returned by
+    /// [`Heap::clone`], without changing any reference counts.
+    ///
+    /// Objects that aren't allocated in this heap (e.g., constants) are
+    /// returned unchanged.
+    #[must_use]
+    pub fn map_to_clone(
+        &self,
+        object: InlineObject,
+        mapping: &FxHashMap<HeapObject, HeapObject>,
+    ) -> InlineObject {
+        // Objects are only looked up if they're allocated in this heap since
+        // the lookup compares their content.
+        let map = |object: HeapObject| {
+            self.objects
+                .contains(&ObjectInHeap(object))
+                .then(|| mapping[&object])
+        };
+        match InlineData::from(object) {
+            InlineData::Pointer(pointer) => map(pointer.get()).map_or(object, Into::into),
+            InlineData::Tag(tag) => map(**tag.get()).map_or(object, |symbol| {
+                *InlineTag::new(HeapText::new_unchecked(symbol).into())
+            }),
+            _ => object,
+        }
+    }
 
     pub fn clear(&mut self) {
         for object in mem::take(&mut self.objects) {
@@ -190,6 +312,10 @@ impl Default for Heap {
             default_symbols: None,
             handle_id_generator: IdGenerator::default(),
             handle_refcounts: FxHashMap::default(),
+            statistics: HeapStatistics::default(),
+            memory_limit: None,
+            next_garbage_collection_at: Self::MIN_GARBAGE_COLLECTION_THRESHOLD,
+            lifetime_sampler: None,
         };
         heap.default_symbols = Some(DefaultSymbols::new(&mut heap));
         heap
@@ -202,6 +328,60 @@ impl Drop for Heap {
     }
 }
 
+/// How much memory a heap uses.
+#[derive(Clone, Debug, Default)]
+pub struct HeapStatistics {
+    /// The number of bytes currently allocated for objects.
+    pub allocated_bytes: usize,
+    /// The maximum number of bytes that were allocated at the same time.
+    pub peak_allocated_bytes: usize,
+    /// The number of objects of each kind that are currently allocated.
+    pub objects_by_kind: FxHashMap<DataDiscriminants, usize>,
+}
+impl HeapStatistics {
+    fn record_allocation(&mut self, object: HeapObject, size: usize) {
+        self.allocated_bytes += size;
+        self.peak_allocated_bytes = self.peak_allocated_bytes.max(self.allocated_bytes);
+        *self
+            .objects_by_kind
+            .entry(DataDiscriminants::from(Data::from(object)))
+            .or_default() += 1;
+    }
+    fn record_deallocation(&mut self, object: HeapObject, size: usize) {
+        self.allocated_bytes -= size;
+        let kind = DataDiscriminants::from(Data::from(object));
+        let count = self.objects_by_kind.get_mut(&kind).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.objects_by_kind.remove(&kind);
+        }
+    }
+    fn adopt(&mut self, other: &Self) {
+        self.allocated_bytes += other.allocated_bytes;
+        self.peak_allocated_bytes = self.peak_allocated_bytes.max(self.allocated_bytes);
+        for (kind, count) in &other.objects_by_kind {
+            *self.objects_by_kind.entry(*kind).or_default() += count;
+        }
+    }
+}
+impl Display for HeapStatistics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes allocated (peak: {} bytes)",
+            self.allocated_bytes, self.peak_allocated_bytes,
+        )?;
+        for (kind, count) in self
+            .objects_by_kind
+            .iter()
+            .sorted_by_key(|(kind, _)| <&'static str>::from(**kind))
+        {
+            write!(f, ", {}: {count}", <&'static str>::from(*kind))?;
+        }
+        Ok(())
+    }
+}
+
 /// For tracking objects allocated in the heap, we don't want deep equality, but
 /// only care about the addresses.
 #[derive(Clone, Copy, DebugCustom, Deref, Pointer)]
@@ -229,72 +409,130 @@ pub struct DefaultSymbols {
     // `clone_to_heap_with_mapping(…)`, and `all_symbols(…)`.
     //
     // Sorted alphabetically
+    pub accept: Text,
     pub arguments: Text,
+    pub body: Text,
     pub builtin: Text,
+    pub bytes: Text,
+    pub cell: Text,
     pub close: Text,
+    pub connect: Text,
+    pub create: Text,
+    pub directory: Text,
     pub equal: Text,
     pub error: Text,
+    pub exit_code: Text,
     pub false_: Text,
     pub file: Text,
     pub file_system: Text,
+    pub fraction: Text,
     pub function: Text,
     pub get_random_bytes: Text,
     pub get_next_request: Text,
     pub greater: Text,
+    pub headers: Text,
+    pub http_client: Text,
     pub http_server: Text,
     pub int: Text,
     pub less: Text,
     pub list: Text,
+    pub listen: Text,
+    pub local_address: Text,
+    pub map: Text,
+    pub method: Text,
+    pub monotonic: Text,
     pub not_an_integer: Text,
     pub not_utf8: Text,
     pub nothing: Text,
+    pub now: Text,
     pub ok: Text,
     pub open: Text,
+    pub read: Text,
     pub read_to_end: Text,
     pub request: Text,
+    pub run_process: Text,
     pub send_response: Text,
+    pub set: Text,
+    pub sleep: Text,
+    pub status: Text,
+    pub stderr: Text,
     pub stdin: Text,
     pub stdout: Text,
     pub struct_: Text,
     pub system_clock: Text,
     pub tag: Text,
+    pub tcp: Text,
     pub text: Text,
+    pub text_builder: Text,
+    pub timed_out: Text,
     pub true_: Text,
+    pub url: Text,
+    pub wall: Text,
+    pub write: Text,
 }
 impl DefaultSymbols {
     pub fn new(heap: &mut Heap) -> Self {
         Self {
+            accept: Text::create(heap, false, "Accept"),
             arguments: Text::create(heap, false, "Arguments"),
+            body: Text::create(heap, false, "Body"),
             builtin: Text::create(heap, false, "Builtin"),
+            bytes: Text::create(heap, false, "Bytes"),
+            cell: Text::create(heap, false, "Cell"),
             close: Text::create(heap, false, "Close"),
+            connect: Text::create(heap, false, "Connect"),
+            create: Text::create(heap, false, "Create"),
+            directory: Text::create(heap, false, "Directory"),
             equal: Text::create(heap, false, "Equal"),
             error: Text::create(heap, false, "Error"),
+            exit_code: Text::create(heap, false, "ExitCode"),
             false_: Text::create(heap, false, "False"),
             file: Text::create(heap, false, "File"),
             file_system: Text::create(heap, false, "FileSystem"),
+            fraction: Text::create(heap, false, "Fraction"),
             function: Text::create(heap, false, "Function"),
             get_next_request: Text::create(heap, false, "GetNextRequest"),
             get_random_bytes: Text::create(heap, false, "GetRandomBytes"),
             greater: Text::create(heap, false, "Greater"),
+            headers: Text::create(heap, false, "Headers"),
+            http_client: Text::create(heap, false, "HttpClient"),
             http_server: Text::create(heap, false, "HttpServer"),
             int: Text::create(heap, false, "Int"),
             less: Text::create(heap, false, "Less"),
             list: Text::create(heap, false, "List"),
+            listen: Text::create(heap, false, "Listen"),
+            local_address: Text::create(heap, false, "LocalAddress"),
+            map: Text::create(heap, false, "Map"),
+            method: Text::create(heap, false, "Method"),
+            monotonic: Text::create(heap, false, "Monotonic"),
             not_an_integer: Text::create(heap, false, "NotAnInteger"),
             not_utf8: Text::create(heap, false, "NotUtf8"),
             nothing: Text::create(heap, false, "Nothing"),
+            now: Text::create(heap, false, "Now"),
             ok: Text::create(heap, false, "Ok"),
             open: Text::create(heap, false, "Open"),
+            read: Text::create(heap, false, "Read"),
             read_to_end: Text::create(heap, false, "ReadToEnd"),
             request: Text::create(heap, false, "Request"),
+            run_process: Text::create(heap, false, "RunProcess"),
             send_response: Text::create(heap, false, "SendResponse"),
+            set: Text::create(heap, false, "Set"),
+            sleep: Text::create(heap, false, "Sleep"),
+            status: Text::create(heap, false, "Status"),
+            stderr: Text::create(heap, false, "Stderr"),
             stdin: Text::create(heap, false, "Stdin"),
             stdout: Text::create(heap, false, "Stdout"),
             struct_: Text::create(heap, false, "Struct"),
             system_clock: Text::create(heap, false, "SystemClock"),
             tag: Text::create(heap, false, "Tag"),
+            tcp: Text::create(heap, false, "Tcp"),
             text: Text::create(heap, false, "Text"),
+            text_builder: Text::create(heap, false, "TextBuilder"),
+            timed_out: Text::create(heap, false, "TimedOut"),
             true_: Text::create(heap, false, "True"),
+            url: Text::create(heap, false, "Url"),
+            wall: Text::create(heap, false, "Wall"),
+            write: Text::create(heap, false, "Write"),
         }
     }
     fn clone_to_heap_with_mapping(
@@ -312,37 +550,66 @@ impl DefaultSymbols {
         }
 
         Self {
+            accept: clone_to_heap(heap, address_map, self.accept),
             arguments: clone_to_heap(heap, address_map, self.arguments),
+            body: clone_to_heap(heap, address_map, self.body),
             builtin: clone_to_heap(heap, address_map, self.builtin),
+            bytes: clone_to_heap(heap, address_map, self.bytes),
+            cell: clone_to_heap(heap, address_map, self.cell),
             close: clone_to_heap(heap, address_map, self.close),
+            connect: clone_to_heap(heap, address_map, self.connect),
+            create: clone_to_heap(heap, address_map, self.create),
+            directory: clone_to_heap(heap, address_map, self.directory),
             equal: clone_to_heap(heap, address_map, self.equal),
             error: clone_to_heap(heap, address_map, self.error),
+            exit_code: clone_to_heap(heap, address_map, self.exit_code),
             false_: clone_to_heap(heap, address_map, self.false_),
             file: clone_to_heap(heap, address_map, self.file),
             file_system: clone_to_heap(heap, address_map, self.file_system),
+            fraction: clone_to_heap(heap, address_map, self.fraction),
             function: clone_to_heap(heap, address_map, self.function),
             get_next_request: clone_to_heap(heap, address_map, self.get_next_request),
             get_random_bytes: clone_to_heap(heap, address_map, self.get_random_bytes),
             greater: clone_to_heap(heap, address_map, self.greater),
+            headers: clone_to_heap(heap, address_map, self.headers),
+            http_client: clone_to_heap(heap, address_map, self.http_client),
             http_server: clone_to_heap(heap, address_map, self.http_server),
             int: clone_to_heap(heap, address_map, self.int),
             less: clone_to_heap(heap, address_map, self.less),
             list: clone_to_heap(heap, address_map, self.list),
+            listen: clone_to_heap(heap, address_map, self.listen),
+            local_address: clone_to_heap(heap, address_map, self.local_address),
+            map: clone_to_heap(heap, address_map, self.map),
+            method: clone_to_heap(heap, address_map, self.method),
+            monotonic: clone_to_heap(heap, address_map, self.monotonic),
             not_an_integer: clone_to_heap(heap, address_map, self.not_an_integer),
             not_utf8: clone_to_heap(heap, address_map, self.not_utf8),
             nothing: clone_to_heap(heap, address_map, self.nothing),
+            now: clone_to_heap(heap, address_map, self.now),
             ok: clone_to_heap(heap, address_map, self.ok),
             open: clone_to_heap(heap, address_map, self.open),
+            read: clone_to_heap(heap, address_map, self.read),
             read_to_end: clone_to_heap(heap, address_map, self.read_to_end),
             request: clone_to_heap(heap, address_map, self.request),
+            run_process: clone_to_heap(heap, address_map, self.run_process),
             send_response: clone_to_heap(heap, address_map, self.send_response),
+            set: clone_to_heap(heap, address_map, self.set),
+            sleep: clone_to_heap(heap, address_map, self.sleep),
+            status: clone_to_heap(heap, address_map, self.status),
+            stderr: clone_to_heap(heap, address_map, self.stderr),
             stdin: clone_to_heap(heap, address_map, self.stdin),
             stdout: clone_to_heap(heap, address_map, self.stdout),
             struct_: clone_to_heap(heap, address_map, self.struct_),
             system_clock: clone_to_heap(heap, address_map, self.system_clock),
             tag: clone_to_heap(heap, address_map, self.tag),
+            tcp: clone_to_heap(heap, address_map, self.tcp),
             text: clone_to_heap(heap, address_map, self.text),
+            text_builder: clone_to_heap(heap, address_map, self.text_builder),
+            timed_out: clone_to_heap(heap, address_map, self.timed_out),
             true_: clone_to_heap(heap, address_map, self.true_),
+            url: clone_to_heap(heap, address_map, self.url),
+            wall: clone_to_heap(heap, address_map, self.wall),
+            write: clone_to_heap(heap, address_map, self.write),
         }
     }
 
@@ -355,39 +622,89 @@ impl DefaultSymbols {
             .map(|it| symbols[it])
     }
     #[must_use]
-    pub const fn all_symbols(&self) -> [Text; 31] {
+    pub const fn all_symbols(&self) -> [Text; 60] {
         [
+            self.accept,
             self.arguments,
+            self.body,
             self.builtin,
+            self.bytes,
+            self.cell,
             self.close,
+            self.connect,
+            self.create,
+            self.directory,
             self.equal,
             self.error,
+            self.exit_code,
             self.false_,
             self.file,
             self.file_system,
+            self.fraction,
             self.function,
             self.get_next_request,
             self.get_random_bytes,
             self.greater,
+            self.headers,
+            self.http_client,
             self.http_server,
             self.int,
             self.less,
             self.list,
+            self.listen,
+            self.local_address,
+            self.map,
+            self.method,
+            self.monotonic,
             self.not_an_integer,
             self.not_utf8,
             self.nothing,
+            self.now,
             self.ok,
             self.open,
+            self.read,
             self.read_to_end,
             self.request,
+            self.run_process,
             self.send_response,
+            self.set,
+            self.sleep,
+            self.status,
+            self.stderr,
             self.stdin,
             self.stdout,
             self.struct_,
             self.system_clock,
             self.tag,
+            self.tcp,
             self.text,
+            self.text_builder,
+            self.timed_out,
             self.true_,
+            self.url,
+            self.wall,
+            self.write,
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Heap, InlineObject, List, Text};
+
+    #[test]
+    fn clone_preserves_reference_counts() {
+        let mut heap = Heap::default();
+        let default_object_count = heap.objects().len();
+        // Equal objects are cloned to the same object.
+        let text: InlineObject = Text::create(&mut heap, true, "Hi").into();
+        let equal_text: InlineObject = Text::create(&mut heap, true, "Hi").into();
+        text.dup(&mut heap);
+        let list: InlineObject = List::create(&mut heap, true, &[text, text, equal_text]).into();
+
+        let (mut cloned, mapping) = heap.clone();
+        heap.map_to_clone(list, &mapping).drop(&mut cloned);
+        cloned.validate(&[], []);
+        assert_eq!(cloned.objects().len(), default_object_count);
+    }
+}
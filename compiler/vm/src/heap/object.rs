@@ -1,7 +1,9 @@
 use super::{
     object_heap::{
-        function::HeapFunction, hir_id::HeapHirId, int::HeapInt, list::HeapList,
-        struct_::HeapStruct, tag::HeapTag, text::HeapText, HeapData, HeapObject,
+        bytes::HeapBytes, cell::HeapCell, fraction::HeapFraction, function::HeapFunction,
+        hir_id::HeapHirId, int::HeapInt, list::HeapList, map::HeapMap, set::HeapSet,
+        struct_::HeapStruct, tag::HeapTag, text::HeapText, text_builder::HeapTextBuilder, HeapData,
+        HeapObject,
     },
     object_inline::{
         builtin::InlineBuiltin, handle::InlineHandle, int::InlineInt, tag::InlineTag, InlineData,
@@ -17,6 +19,7 @@ use crate::{
 use candy_frontend::{builtin_functions::BuiltinFunction, hir::Id};
 use derive_more::{Deref, From};
 use num_bigint::BigInt;
+use num_rational::BigRational;
 use num_traits::Signed;
 use rustc_hash::FxHashMap;
 use std::{
@@ -29,7 +32,7 @@ use std::{
 use strum::{EnumDiscriminants, IntoStaticStr};
 
 #[derive(Clone, Copy, EnumDiscriminants, Eq, Hash, IntoStaticStr, Ord, PartialEq, PartialOrd)]
-#[strum_discriminants(derive(IntoStaticStr))]
+#[strum_discriminants(derive(Hash, IntoStaticStr))]
 pub enum Data {
     Int(Int),
     Tag(Tag),
@@ -40,6 +43,12 @@ pub enum Data {
     Function(Function),
     Builtin(Builtin),
     Handle(Handle),
+    Bytes(Bytes),
+    Cell(Cell),
+    Fraction(Fraction),
+    Map(Map),
+    Set(Set),
+    TextBuilder(TextBuilder),
 }
 impl Data {
     #[must_use]
@@ -73,6 +82,12 @@ impl From<HeapObject> for Data {
             HeapData::Text(text) => Self::Text(Text(text)),
             HeapData::Function(function) => Self::Function(Function(function)),
             HeapData::HirId(hir_id) => Self::HirId(HirId(hir_id)),
+            HeapData::Bytes(bytes) => Self::Bytes(Bytes(bytes)),
+            HeapData::Cell(cell) => Self::Cell(Cell(cell)),
+            HeapData::Fraction(fraction) => Self::Fraction(Fraction(fraction)),
+            HeapData::Map(map) => Self::Map(Map(map)),
+            HeapData::Set(set) => Self::Set(Set(set)),
+            HeapData::TextBuilder(builder) => Self::TextBuilder(TextBuilder(builder)),
         }
     }
 }
@@ -89,6 +104,12 @@ impl DebugDisplay for Data {
             Self::Function(function) => DebugDisplay::fmt(function, f, is_debug),
             Self::Builtin(builtin) => DebugDisplay::fmt(builtin, f, is_debug),
             Self::Handle(send_port) => DebugDisplay::fmt(send_port, f, is_debug),
+            Self::Bytes(bytes) => DebugDisplay::fmt(bytes, f, is_debug),
+            Self::Cell(cell) => DebugDisplay::fmt(cell, f, is_debug),
+            Self::Fraction(fraction) => DebugDisplay::fmt(fraction, f, is_debug),
+            Self::Map(map) => DebugDisplay::fmt(map, f, is_debug),
+            Self::Set(set) => DebugDisplay::fmt(set, f, is_debug),
+            Self::TextBuilder(builder) => DebugDisplay::fmt(builder, f, is_debug),
         }
     }
 }
@@ -298,6 +319,109 @@ impl PartialOrd for Int {
     }
 }
 
+// Fraction
+
+/// A rational number that isn't an integer.
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Fraction(HeapFraction);
+
+impls_via_0!(Fraction);
+impl_try_froms!(Fraction, "Expected a fraction.");
+impl_try_from_heap_object!(Fraction, "Expected a fraction.");
+
+// Number
+
+/// An [`Int`] or a [`Fraction`].
+///
+/// Operations return an [`Int`] whenever the result is an integer, so every
+/// number has exactly one representation.
+#[derive(Clone, Copy, Eq, From, Hash, PartialEq)]
+pub enum Number {
+    Int(Int),
+    Fraction(Fraction),
+}
+
+impl Number {
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: BigRational) -> Self {
+        if value.is_integer() {
+            Int::create_from_bigint(heap, is_reference_counted, value.to_integer()).into()
+        } else {
+            Fraction(HeapFraction::create(heap, is_reference_counted, value)).into()
+        }
+    }
+
+    #[must_use]
+    pub fn get<'a>(self) -> Cow<'a, BigRational> {
+        match self {
+            Self::Int(int) => Cow::Owned(BigRational::from_integer(int.get().into_owned())),
+            Self::Fraction(fraction) => Cow::Borrowed(fraction.get()),
+        }
+    }
+
+    #[must_use]
+    pub fn numerator(self, heap: &mut Heap) -> Int {
+        Int::create_from_bigint(heap, true, self.get().numer().clone())
+    }
+    #[must_use]
+    pub fn denominator(self, heap: &mut Heap) -> Int {
+        Int::create_from_bigint(heap, true, self.get().denom().clone())
+    }
+
+    #[must_use]
+    pub fn add(self, heap: &mut Heap, rhs: Self) -> Self {
+        Self::create(heap, true, &*self.get() + &*rhs.get())
+    }
+    #[must_use]
+    pub fn subtract(self, heap: &mut Heap, rhs: Self) -> Self {
+        Self::create(heap, true, &*self.get() - &*rhs.get())
+    }
+    #[must_use]
+    pub fn multiply(self, heap: &mut Heap, rhs: Self) -> Self {
+        Self::create(heap, true, &*self.get() * &*rhs.get())
+    }
+    /// The `rhs` must not be zero.
+    #[must_use]
+    pub fn divide(self, heap: &mut Heap, rhs: Self) -> Self {
+        Self::create(heap, true, &*self.get() / &*rhs.get())
+    }
+
+    #[must_use]
+    pub fn compare_to(self, heap: &Heap, rhs: Self) -> Tag {
+        Tag::create_ordering(heap, self.get().cmp(&rhs.get()))
+    }
+}
+
+impl DebugDisplay for Number {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        match self {
+            Self::Int(int) => DebugDisplay::fmt(int, f, is_debug),
+            Self::Fraction(fraction) => DebugDisplay::fmt(fraction, f, is_debug),
+        }
+    }
+}
+impl_debug_display_via_debugdisplay!(Number);
+
+impl From<Number> for InlineObject {
+    fn from(value: Number) -> Self {
+        match value {
+            Number::Int(int) => int.into(),
+            Number::Fraction(fraction) => fraction.into(),
+        }
+    }
+}
+impl TryFrom<InlineObject> for Number {
+    type Error = &'static str;
+
+    fn try_from(value: InlineObject) -> Result<Self, Self::Error> {
+        match Data::from(value) {
+            Data::Int(int) => Ok(int.into()),
+            Data::Fraction(fraction) => Ok(fraction.into()),
+            _ => Err("Expected a number."),
+        }
+    }
+}
+
 // Tag
 
 #[derive(Clone, Copy, Eq, From, Hash, PartialEq)]
@@ -536,6 +660,121 @@ impls_via_0!(Function);
 impl_try_froms!(Function, "Expected a function.");
 impl_try_from_heap_object!(Function, "Expected a function.");
 
+// Bytes
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Bytes(HeapBytes);
+
+impl Bytes {
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: &[u8]) -> Self {
+        HeapBytes::create(heap, is_reference_counted, value).into()
+    }
+}
+
+impls_via_0!(Bytes);
+impl_try_froms!(Bytes, "Expected bytes.");
+impl_try_from_heap_object!(Bytes, "Expected bytes.");
+
+// Cell
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cell(HeapCell);
+
+impl Cell {
+    #[must_use]
+    pub fn create(heap: &mut Heap, value: InlineObject) -> Self {
+        HeapCell::create(heap, value).into()
+    }
+}
+
+impls_via_0!(Cell);
+impl_try_froms!(Cell, "Expected a cell.");
+impl_try_from_heap_object!(Cell, "Expected a cell.");
+
+// Map
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Map(HeapMap);
+
+impl Map {
+    /// Takes ownership of the keys and values. For duplicate keys, the last
+    /// entry wins.
+    #[must_use]
+    pub fn create(
+        heap: &mut Heap,
+        entries: impl IntoIterator<Item = (InlineObject, InlineObject)>,
+    ) -> Self {
+        HeapMap::create(heap, entries).into()
+    }
+
+    /// Takes ownership of the `key` and `value`.
+    #[must_use]
+    pub fn insert(self, heap: &mut Heap, key: InlineObject, value: InlineObject) -> Self {
+        self.0.insert(heap, key, value).into()
+    }
+    #[must_use]
+    pub fn remove(self, heap: &mut Heap, key: InlineObject) -> Self {
+        self.0.remove(heap, key).into()
+    }
+    /// For keys contained in both maps, the values of `other` win.
+    #[must_use]
+    pub fn union(self, heap: &mut Heap, other: Self) -> Self {
+        self.0.union(heap, other.0).into()
+    }
+}
+
+impls_via_0!(Map);
+impl_try_froms!(Map, "Expected a map.");
+impl_try_from_heap_object!(Map, "Expected a map.");
+
+// Set
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Set(HeapSet);
+
+impl Set {
+    /// Takes ownership of the items.
+    #[must_use]
+    pub fn create(heap: &mut Heap, items: impl IntoIterator<Item = InlineObject>) -> Self {
+        HeapSet::create(heap, items).into()
+    }
+
+    /// Takes ownership of the `item`.
+    #[must_use]
+    pub fn insert(self, heap: &mut Heap, item: InlineObject) -> Self {
+        self.0.insert(heap, item).into()
+    }
+    #[must_use]
+    pub fn remove(self, heap: &mut Heap, item: InlineObject) -> Self {
+        self.0.remove(heap, item).into()
+    }
+    #[must_use]
+    pub fn union(self, heap: &mut Heap, other: Self) -> Self {
+        self.0.union(heap, other.0).into()
+    }
+}
+
+impls_via_0!(Set);
+impl_try_froms!(Set, "Expected a set.");
+impl_try_from_heap_object!(Set, "Expected a set.");
+
+// Text Builder
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TextBuilder(HeapTextBuilder);
+
+impl TextBuilder {
+    #[must_use]
+    pub fn create(heap: &mut Heap, value: String) -> Self {
+        HeapTextBuilder::create(heap, value).into()
+    }
+}
+
+impls_via_0!(TextBuilder);
+impl_try_froms!(TextBuilder, "Expected a text builder.");
+impl_try_from_heap_object!(TextBuilder, "Expected a text builder.");
+
 // HIR ID
 
 #[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
@@ -0,0 +1,226 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+};
+use derive_more::Deref;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Formatter},
+    ops::Range,
+    ptr::{self, NonNull},
+    slice,
+};
+
+#[derive(Clone, Copy, Deref)]
+pub struct HeapBytes(HeapObject);
+
+impl HeapBytes {
+    const IS_SLICE_SHIFT: usize = 4;
+    const IS_SLICE_MASK: u64 = 0b1 << Self::IS_SLICE_SHIFT;
+    const LEN_SHIFT: usize = 5;
+
+    /// Slices at most this long copy their bytes instead of referencing the
+    /// original bytes since that wouldn't save any memory.
+    const MAX_COPIED_SLICE_LEN: usize = 2 * HeapObject::WORD_SIZE;
+
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: &[u8]) -> Self {
+        let len = value.len();
+        Self::check_len(len);
+        let bytes = Self(heap.allocate(
+            HeapObject::KIND_BYTES,
+            is_reference_counted,
+            (len as u64) << Self::LEN_SHIFT,
+            len,
+        ));
+        unsafe { ptr::copy_nonoverlapping(value.as_ptr(), bytes.owned_pointer().as_ptr(), len) };
+        bytes
+    }
+    /// Creates a slice that references (and keeps alive) the `parent`'s
+    /// bytes, which must not be a slice itself.
+    fn create_slice(heap: &mut Heap, parent: Self, range: Range<usize>) -> Self {
+        debug_assert!(!parent.is_slice());
+        let len = range.len();
+        Self::check_len(len);
+        parent.dup();
+        let bytes = Self(heap.allocate(
+            HeapObject::KIND_BYTES,
+            true,
+            ((len as u64) << Self::LEN_SHIFT) | Self::IS_SLICE_MASK,
+            2 * HeapObject::WORD_SIZE,
+        ));
+        bytes.unsafe_set_content_word(0, InlineObject::from(parent).raw_word().get());
+        bytes.unsafe_set_content_word(1, range.start as u64);
+        bytes
+    }
+    fn check_len(len: usize) {
+        debug_assert_eq!(
+            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
+            len,
+            "Bytes are too long.",
+        );
+    }
+
+    #[must_use]
+    pub fn len(self) -> usize {
+        (self.header_word() >> Self::LEN_SHIFT) as usize
+    }
+    #[must_use]
+    fn is_slice(self) -> bool {
+        self.header_word() & Self::IS_SLICE_MASK != 0
+    }
+    #[must_use]
+    fn owned_pointer(self) -> NonNull<u8> {
+        debug_assert!(!self.is_slice());
+        self.content_word_pointer(0).cast()
+    }
+    /// For slices, the bytes they reference.
+    #[must_use]
+    fn parent(self) -> Option<Self> {
+        self.is_slice().then(|| {
+            let word = self.unsafe_get_content_word(0);
+            Self(HeapObject::new(unsafe {
+                NonNull::new_unchecked(word as *mut u64)
+            }))
+        })
+    }
+    /// Only valid for slices.
+    #[allow(clippy::cast_possible_truncation)]
+    fn offset_in_parent(self) -> usize {
+        self.unsafe_get_content_word(1) as usize
+    }
+    #[must_use]
+    pub fn get<'a>(self) -> &'a [u8] {
+        let pointer = self.parent().map_or_else(
+            || self.owned_pointer().as_ptr(),
+            |parent| unsafe { parent.owned_pointer().as_ptr().add(self.offset_in_parent()) },
+        );
+        unsafe { slice::from_raw_parts(pointer, self.len()) }
+    }
+
+    /// Long slices share the bytes with `self` instead of copying them, so
+    /// this takes constant time.
+    #[must_use]
+    pub fn get_range(self, heap: &mut Heap, range: Range<usize>) -> Self {
+        debug_assert!(range.start <= range.end && range.end <= self.len());
+
+        if range.len() <= Self::MAX_COPIED_SLICE_LEN {
+            return Self::create(heap, true, &self.get()[range]);
+        }
+        match self.parent() {
+            Some(parent) => {
+                let offset = self.offset_in_parent();
+                Self::create_slice(heap, parent, offset + range.start..offset + range.end)
+            }
+            None => Self::create_slice(heap, self, range),
+        }
+    }
+    #[must_use]
+    pub fn concatenate(self, heap: &mut Heap, other: Self) -> Self {
+        let mut bytes = Vec::with_capacity(self.len() + other.len());
+        bytes.extend_from_slice(self.get());
+        bytes.extend_from_slice(other.get());
+        Self::create(heap, true, &bytes)
+    }
+}
+
+impl DebugDisplay for HeapBytes {
+    fn fmt(&self, f: &mut Formatter, _is_debug: bool) -> fmt::Result {
+        write!(f, "<")?;
+        for (index, byte) in self.get().iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, ">")
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapBytes);
+
+impl_eq_hash_ord_via_get!(HeapBytes);
+
+heap_object_impls!(HeapBytes);
+
+impl HeapObjectTrait for HeapBytes {
+    fn content_size(self) -> usize {
+        if self.is_slice() {
+            2 * HeapObject::WORD_SIZE
+        } else {
+            self.len()
+        }
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        heap: &mut Heap,
+        clone: HeapObject,
+        address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        let clone = Self(clone);
+        match self.parent() {
+            Some(parent) => {
+                let parent = parent.clone_to_heap_with_mapping(heap, address_map);
+                clone.unsafe_set_content_word(0, InlineObject::from(parent).raw_word().get());
+                clone.unsafe_set_content_word(1, self.unsafe_get_content_word(1));
+            }
+            None => unsafe {
+                ptr::copy_nonoverlapping(
+                    self.owned_pointer().as_ptr(),
+                    clone.owned_pointer().as_ptr(),
+                    self.len(),
+                );
+            },
+        }
+    }
+
+    fn drop_children(self, heap: &mut Heap) {
+        if let Some(parent) = self.parent() {
+            parent.drop(heap);
+        }
+    }
+    fn children(self) -> Vec<InlineObject> {
+        self.parent()
+            .map(|parent| (*parent).into())
+            .into_iter()
+            .collect()
+    }
+
+    fn deallocate_external_stuff(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Bytes, Heap};
+
+    #[test]
+    fn slices_share_bytes_with_their_parent() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+        let content = (0..=255).collect::<Vec<u8>>();
+
+        let bytes = Bytes::create(&mut heap, true, &content);
+        let slice = bytes.get_range(&mut heap, 10..200);
+        let nested_slice = slice.get_range(&mut heap, 20..100);
+        assert_eq!(slice.get(), &content[10..200]);
+        assert_eq!(nested_slice.get(), &content[30..110]);
+        // Nested slices reference the original bytes directly.
+        assert_eq!(bytes.reference_count(), Some(3));
+
+        let short_slice = slice.get_range(&mut heap, 0..2);
+        assert_eq!(short_slice.get(), &[10, 11]);
+        assert_eq!(bytes.reference_count(), Some(3));
+
+        bytes.drop(&mut heap);
+        slice.drop(&mut heap);
+        assert_eq!(nested_slice.get(), &content[30..110]);
+        nested_slice.drop(&mut heap);
+        short_slice.drop(&mut heap);
+        assert_eq!(heap.objects().len(), object_count);
+    }
+}
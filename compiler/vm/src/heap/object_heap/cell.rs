@@ -0,0 +1,145 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject},
+    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
+};
+use derive_more::Deref;
+use rustc_hash::FxHashMap;
+use std::{
+    cmp::Ordering,
+    fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+    num::NonZeroU64,
+};
+
+/// The only mutable object. Cells are compared by identity, not by their
+/// current value.
+#[derive(Clone, Copy, Deref)]
+pub struct HeapCell(HeapObject);
+
+impl HeapCell {
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    /// Takes ownership of the `value`.
+    ///
+    /// Cells are always reference-counted: Objects that aren't (i.e.,
+    /// constants) are shared across fibers without synchronization.
+    #[must_use]
+    pub fn create(heap: &mut Heap, value: InlineObject) -> Self {
+        let cell = Self(heap.allocate(
+            HeapObject::KIND_HIR_ID_CELL_OR_TEXT_BUILDER,
+            true,
+            HeapObject::IS_CELL_MASK,
+            HeapObject::WORD_SIZE,
+        ));
+        cell.unsafe_set_content_word(0, value.raw_word().get());
+        cell
+    }
+
+    #[must_use]
+    pub fn get(self) -> InlineObject {
+        let word = self.unsafe_get_content_word(0);
+        InlineObject::new(unsafe { NonZeroU64::new_unchecked(word) })
+    }
+    /// Takes ownership of the new `value` and drops the previous one.
+    pub fn set(self, heap: &mut Heap, value: InlineObject) {
+        let previous = self.get();
+        self.unsafe_set_content_word(0, value.raw_word().get());
+        previous.drop(heap);
+    }
+}
+
+impl DebugDisplay for HeapCell {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        // Cells can contain themselves, so we don't print their value.
+        if is_debug {
+            write!(f, "cell at {:p}", self.address())
+        } else {
+            write!(f, "cell")
+        }
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapCell);
+
+impl Eq for HeapCell {}
+impl PartialEq for HeapCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer_equals(**other)
+    }
+}
+impl Hash for HeapCell {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state);
+    }
+}
+impl Ord for HeapCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address().cmp(&other.address())
+    }
+}
+impl PartialOrd for HeapCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+heap_object_impls!(HeapCell);
+
+impl HeapObjectTrait for HeapCell {
+    fn content_size(self) -> usize {
+        HeapObject::WORD_SIZE
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        heap: &mut Heap,
+        clone: HeapObject,
+        address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        let clone = Self(clone);
+        let value = self.get().clone_to_heap_with_mapping(heap, address_map);
+        clone.unsafe_set_content_word(0, value.raw_word().get());
+    }
+
+    fn drop_children(self, heap: &mut Heap) {
+        self.get().drop(heap);
+    }
+    fn children(self) -> Vec<InlineObject> {
+        vec![self.get()]
+    }
+
+    fn deallocate_external_stuff(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Cell, Heap, Int, Text};
+
+    #[test]
+    fn replaces_and_drops_values() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+
+        let text = Text::create(&mut heap, true, "foo");
+        let cell = Cell::create(&mut heap, text.into());
+        let equal_text = Text::create(&mut heap, true, "foo");
+        let other_cell = Cell::create(&mut heap, equal_text.into());
+        assert_ne!(cell, other_cell);
+
+        text.dup();
+        let int = Int::create(&mut heap, true, 42);
+        cell.set(&mut heap, int.into());
+        assert_eq!(text.reference_count(), Some(1));
+        assert_eq!(
+            Int::try_from(cell.get()).unwrap().try_get::<i64>(),
+            Some(42)
+        );
+
+        text.drop(&mut heap);
+        cell.drop(&mut heap);
+        other_cell.drop(&mut heap);
+        assert_eq!(heap.objects().len(), object_count);
+    }
+}
@@ -0,0 +1,83 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+};
+use derive_more::Deref;
+use num_rational::BigRational;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Formatter},
+    mem,
+    ptr::{self, NonNull},
+};
+
+/// A rational number that isn't an integer.
+#[derive(Clone, Copy, Deref)]
+pub struct HeapFraction(HeapObject);
+
+impl HeapFraction {
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: BigRational) -> Self {
+        debug_assert!(!value.is_integer());
+
+        let fraction = Self(heap.allocate(
+            HeapObject::KIND_INT_OR_FRACTION,
+            is_reference_counted,
+            HeapObject::IS_FRACTION_MASK,
+            mem::size_of::<BigRational>(),
+        ));
+        unsafe { ptr::write(fraction.fraction_pointer().as_ptr(), value) };
+        fraction
+    }
+
+    #[must_use]
+    fn fraction_pointer(self) -> NonNull<BigRational> {
+        self.content_word_pointer(0).cast()
+    }
+    #[must_use]
+    pub fn get<'a>(self) -> &'a BigRational {
+        unsafe { self.fraction_pointer().as_ref() }
+    }
+}
+
+impl DebugDisplay for HeapFraction {
+    fn fmt(&self, f: &mut Formatter, _is_debug: bool) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapFraction);
+
+impl_eq_hash_ord_via_get!(HeapFraction);
+
+heap_object_impls!(HeapFraction);
+
+impl HeapObjectTrait for HeapFraction {
+    fn content_size(self) -> usize {
+        mem::size_of::<BigRational>()
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        _heap: &mut Heap,
+        clone: HeapObject,
+        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        let clone = Self(clone);
+        let value = self.get().clone();
+        unsafe { ptr::write(clone.fraction_pointer().as_ptr(), value) };
+    }
+
+    fn drop_children(self, _heap: &mut Heap) {}
+    fn children(self) -> Vec<InlineObject> {
+        vec![]
+    }
+
+    fn deallocate_external_stuff(self) {
+        unsafe { ptr::drop_in_place(self.fraction_pointer().as_ptr()) };
+    }
+}
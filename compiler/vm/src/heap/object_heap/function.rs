@@ -22,6 +22,10 @@ impl HeapFunction {
     const CAPTURED_LEN_SHIFT: usize = 32;
     const ARGUMENT_COUNT_SHIFT: usize = 4;
 
+    pub const MAX_CAPTURED_LEN: usize = usize::MAX >> Self::CAPTURED_LEN_SHIFT;
+    pub const MAX_ARGUMENT_COUNT: usize = usize::MAX
+        >> (InlineObject::BITS as usize - Self::CAPTURED_LEN_SHIFT + Self::ARGUMENT_COUNT_SHIFT);
+
     #[must_use]
     pub const fn new_unchecked(object: HeapObject) -> Self {
         Self(object)
@@ -187,6 +191,9 @@ impl HeapObjectTrait for HeapFunction {
             captured.drop(heap);
         }
     }
+    fn children(self) -> Vec<InlineObject> {
+        self.captured().to_vec()
+    }
 
     fn deallocate_external_stuff(self) {}
 }
@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap},
+    heap::{object_heap::HeapObject, Heap, InlineObject},
     utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
 };
 use candy_frontend::hir::Id;
@@ -21,7 +21,7 @@ impl HeapHirId {
     }
     pub fn create(heap: &mut Heap, is_reference_counted: bool, value: Id) -> Self {
         let id = Self(heap.allocate(
-            HeapObject::KIND_HIR_ID,
+            HeapObject::KIND_HIR_ID_CELL_OR_TEXT_BUILDER,
             is_reference_counted,
             0,
             mem::size_of::<Id>(),
@@ -66,6 +66,9 @@ impl HeapObjectTrait for HeapHirId {
     }
 
     fn drop_children(self, _heap: &mut Heap) {}
+    fn children(self) -> Vec<InlineObject> {
+        vec![]
+    }
 
     fn deallocate_external_stuff(self) {
         unsafe { ptr::drop_in_place(self.id_pointer().as_ptr()) };
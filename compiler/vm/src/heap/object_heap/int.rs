@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, object_inline::int::InlineInt, Heap, Int, Tag},
+    heap::{object_heap::HeapObject, object_inline::int::InlineInt, Heap, InlineObject, Int, Tag},
     utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
 };
 use derive_more::Deref;
@@ -29,7 +29,7 @@ impl HeapInt {
         }
 
         let int = Self(heap.allocate(
-            HeapObject::KIND_INT,
+            HeapObject::KIND_INT_OR_FRACTION,
             is_reference_counted,
             0,
             mem::size_of::<BigInt>(),
@@ -119,6 +119,9 @@ impl HeapObjectTrait for HeapInt {
     }
 
     fn drop_children(self, _heap: &mut Heap) {}
+    fn children(self) -> Vec<InlineObject> {
+        vec![]
+    }
 
     fn deallocate_external_stuff(self) {
         unsafe { ptr::drop_in_place(self.int_pointer().as_ptr()) };
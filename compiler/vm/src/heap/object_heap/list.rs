@@ -9,7 +9,7 @@ use std::{
     cmp::Ordering,
     fmt::{self, Formatter},
     hash::{Hash, Hasher},
-    num::NonZeroU64,
+    ops::Range,
     ptr::{self, NonNull},
     slice,
 };
@@ -18,7 +18,13 @@ use std::{
 pub struct HeapList(HeapObject);
 
 impl HeapList {
-    const LEN_SHIFT: usize = 4;
+    const IS_SLICE_SHIFT: usize = 4;
+    const IS_SLICE_MASK: u64 = 0b1 << Self::IS_SLICE_SHIFT;
+    const LEN_SHIFT: usize = 5;
+
+    /// Slices at most this long copy their items instead of referencing the
+    /// original list since that wouldn't save any memory.
+    const MAX_COPIED_SLICE_LEN: usize = 2;
 
     #[must_use]
     pub const fn new_unchecked(object: HeapObject) -> Self {
@@ -33,11 +39,7 @@ impl HeapList {
     }
     #[must_use]
     fn create_uninitialized(heap: &mut Heap, is_reference_counted: bool, len: usize) -> Self {
-        debug_assert_eq!(
-            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
-            len,
-            "List is too long.",
-        );
+        Self::check_len(len);
         Self(heap.allocate(
             HeapObject::KIND_LIST,
             is_reference_counted,
@@ -45,21 +47,67 @@ impl HeapList {
             len * HeapObject::WORD_SIZE,
         ))
     }
+    /// Creates a slice that references (and keeps alive) the `parent`'s items,
+    /// which must not be a slice itself.
+    fn create_slice(heap: &mut Heap, parent: Self, range: Range<usize>) -> Self {
+        debug_assert!(!parent.is_slice());
+        let len = range.len();
+        Self::check_len(len);
+        parent.dup();
+        let list = Self(heap.allocate(
+            HeapObject::KIND_LIST,
+            true,
+            ((len as u64) << Self::LEN_SHIFT) | Self::IS_SLICE_MASK,
+            2 * HeapObject::WORD_SIZE,
+        ));
+        list.unsafe_set_content_word(0, InlineObject::from(parent).raw_word().get());
+        list.unsafe_set_content_word(1, range.start as u64);
+        list
+    }
+    fn check_len(len: usize) {
+        debug_assert_eq!(
+            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
+            len,
+            "List is too long.",
+        );
+    }
 
     #[must_use]
     pub fn len(self) -> usize {
         (self.header_word() >> Self::LEN_SHIFT) as usize
     }
     #[must_use]
+    fn is_slice(self) -> bool {
+        self.header_word() & Self::IS_SLICE_MASK != 0
+    }
+    /// For slices, the list whose items they reference.
+    #[must_use]
+    fn parent(self) -> Option<Self> {
+        self.is_slice().then(|| {
+            let word = self.unsafe_get_content_word(0);
+            Self(HeapObject::new(unsafe {
+                NonNull::new_unchecked(word as *mut u64)
+            }))
+        })
+    }
+    /// Only valid for slices.
+    #[allow(clippy::cast_possible_truncation)]
+    fn offset_in_parent(self) -> usize {
+        self.unsafe_get_content_word(1) as usize
+    }
+    #[must_use]
     pub fn get(self, index: usize) -> InlineObject {
         debug_assert!(index < self.len());
-        let word = self.unsafe_get_content_word(index);
-        let word = unsafe { NonZeroU64::new_unchecked(word) };
-        InlineObject::new(word)
+        self.items()[index]
     }
     #[must_use]
     fn items_pointer(self) -> NonNull<InlineObject> {
-        self.content_word_pointer(0).cast()
+        self.parent().map_or_else(
+            || self.content_word_pointer(0).cast(),
+            |parent| unsafe {
+                NonNull::new_unchecked(parent.items_pointer().as_ptr().add(self.offset_in_parent()))
+            },
+        )
     }
     #[must_use]
     pub fn items<'a>(self) -> &'a [InlineObject] {
@@ -68,6 +116,18 @@ impl HeapList {
             slice::from_raw_parts(pointer, self.len())
         }
     }
+    /// Whether this list is only referenced by the caller, so that it can be
+    /// changed in place without anyone noticing.
+    ///
+    /// Slices are only unique if nobody else references their parent either.
+    #[must_use]
+    pub fn is_unique(self) -> bool {
+        self.reference_count() == Some(1)
+            && self
+                .parent()
+                .map_or(true, |parent| parent.reference_count() == Some(1))
+    }
+
     #[must_use]
     pub fn insert(self, heap: &mut Heap, index: usize, value: InlineObject) -> Self {
         debug_assert!(index <= self.len());
@@ -76,37 +136,50 @@ impl HeapList {
         let new_list = Self::create_uninitialized(heap, true, len);
         unsafe {
             ptr::copy_nonoverlapping(
-                self.content_word_pointer(0).as_ptr(),
-                new_list.content_word_pointer(0).as_ptr(),
+                self.items_pointer().as_ptr(),
+                new_list.items_pointer().as_ptr(),
                 index,
             );
-            ptr::write(new_list.content_word_pointer(index).cast().as_ptr(), value);
+            ptr::write(new_list.items_pointer().as_ptr().add(index), value);
             ptr::copy_nonoverlapping(
-                self.content_word_pointer(index).as_ptr(),
-                new_list.content_word_pointer(index + 1).as_ptr(),
+                self.items_pointer().as_ptr().add(index),
+                new_list.items_pointer().as_ptr().add(index + 1),
                 self.len() - index,
             );
         }
         new_list
     }
+    /// Unlike the other operations, this takes care of the items' reference
+    /// counts: Removing the first or last item shares the remaining items with
+    /// `self` (see [`Self::get_range`]).
     #[must_use]
     pub fn remove(self, heap: &mut Heap, index: usize) -> Self {
         debug_assert!(index < self.len());
 
+        if index == 0 {
+            return self.get_range(heap, 1..self.len());
+        }
+        if index == self.len() - 1 {
+            return self.get_range(heap, 0..index);
+        }
+
         let len = self.len() - 1;
         let new_list = Self::create_uninitialized(heap, true, len);
         unsafe {
             ptr::copy_nonoverlapping(
-                self.content_word_pointer(0).as_ptr(),
-                new_list.content_word_pointer(0).as_ptr(),
+                self.items_pointer().as_ptr(),
+                new_list.items_pointer().as_ptr(),
                 index,
             );
             ptr::copy_nonoverlapping(
-                self.content_word_pointer(index + 1).as_ptr(),
-                new_list.content_word_pointer(index).as_ptr(),
+                self.items_pointer().as_ptr().add(index + 1),
+                new_list.items_pointer().as_ptr().add(index),
                 self.len() - index - 1,
             );
         }
+        for item in new_list.items() {
+            item.dup(heap);
+        }
         new_list
     }
     #[must_use]
@@ -114,9 +187,58 @@ impl HeapList {
         debug_assert!(index < self.len());
 
         let new_list = Self::create(heap, true, self.items());
-        unsafe { ptr::write(new_list.content_word_pointer(index).cast().as_ptr(), value) };
+        new_list.replace_in_place(index, value);
+        new_list
+    }
+    /// Overwrites the item at `index` without adjusting any reference counts.
+    ///
+    /// Only call this on lists that nobody else can observe, e.g., if
+    /// [`Self::is_unique`] returns `true`.
+    pub fn replace_in_place(self, index: usize, value: InlineObject) {
+        debug_assert!(index < self.len());
+        unsafe { ptr::write(self.items_pointer().as_ptr().add(index), value) };
+    }
+    #[must_use]
+    pub fn concatenate(self, heap: &mut Heap, other: Self) -> Self {
+        let new_list = Self::create_uninitialized(heap, true, self.len() + other.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.items_pointer().as_ptr(),
+                new_list.items_pointer().as_ptr(),
+                self.len(),
+            );
+            ptr::copy_nonoverlapping(
+                other.items_pointer().as_ptr(),
+                new_list.items_pointer().as_ptr().add(self.len()),
+                other.len(),
+            );
+        }
         new_list
     }
+    /// Long ranges share the items with `self` instead of copying them, so this
+    /// takes constant time.
+    ///
+    /// Unlike the other operations, this takes care of the items' reference
+    /// counts.
+    #[must_use]
+    pub fn get_range(self, heap: &mut Heap, range: Range<usize>) -> Self {
+        debug_assert!(range.start <= range.end && range.end <= self.len());
+
+        if range.len() <= Self::MAX_COPIED_SLICE_LEN {
+            let new_list = Self::create(heap, true, &self.items()[range]);
+            for item in new_list.items() {
+                item.dup(heap);
+            }
+            return new_list;
+        }
+        match self.parent() {
+            Some(parent) => {
+                let offset = self.offset_in_parent();
+                Self::create_slice(heap, parent, offset + range.start..offset + range.end)
+            }
+            None => Self::create_slice(heap, self, range),
+        }
+    }
 }
 
 impl DebugDisplay for HeapList {
@@ -165,7 +287,11 @@ heap_object_impls!(HeapList);
 
 impl HeapObjectTrait for HeapList {
     fn content_size(self) -> usize {
-        self.len() * HeapObject::WORD_SIZE
+        if self.is_slice() {
+            2 * HeapObject::WORD_SIZE
+        } else {
+            self.len() * HeapObject::WORD_SIZE
+        }
     }
 
     fn clone_content_to_heap_with_mapping(
@@ -175,6 +301,13 @@ impl HeapObjectTrait for HeapList {
         address_map: &mut FxHashMap<HeapObject, HeapObject>,
     ) {
         let clone = Self(clone);
+        if let Some(parent) = self.parent() {
+            let parent = parent.clone_to_heap_with_mapping(heap, address_map);
+            clone.unsafe_set_content_word(0, InlineObject::from(parent).raw_word().get());
+            clone.unsafe_set_content_word(1, self.unsafe_get_content_word(1));
+            return;
+        }
+
         for (index, &item) in self.items().iter().enumerate() {
             clone.unsafe_set_content_word(
                 index,
@@ -186,10 +319,53 @@ impl HeapObjectTrait for HeapList {
     }
 
     fn drop_children(self, heap: &mut Heap) {
+        if let Some(parent) = self.parent() {
+            parent.drop(heap);
+            return;
+        }
+
         for item in self.items() {
             item.drop(heap);
         }
     }
+    fn children(self) -> Vec<InlineObject> {
+        self.parent()
+            .map_or_else(|| self.items().to_vec(), |parent| vec![(*parent).into()])
+    }
 
     fn deallocate_external_stuff(self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Int, List};
+
+    #[test]
+    fn slices_share_items_with_their_parent() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+        let items = (0..10)
+            .map(|value| Int::create(&mut heap, true, value).into())
+            .collect::<Vec<InlineObject>>();
+
+        let list = List::create(&mut heap, true, &items);
+        let tail = list.remove(&mut heap, 0);
+        let nested_slice = tail.get_range(&mut heap, 2..6);
+        assert_eq!(tail.items(), &items[1..]);
+        assert_eq!(nested_slice.items(), &items[3..7]);
+        // Nested slices reference the original list directly.
+        assert_eq!(list.reference_count(), Some(3));
+        assert!(!tail.is_unique());
+
+        let short_slice = tail.get_range(&mut heap, 0..2);
+        assert_eq!(short_slice.items(), &items[1..3]);
+        assert_eq!(list.reference_count(), Some(3));
+
+        list.drop(&mut heap);
+        tail.drop(&mut heap);
+        assert_eq!(nested_slice.items(), &items[3..7]);
+        nested_slice.drop(&mut heap);
+        short_slice.drop(&mut heap);
+        assert_eq!(heap.objects().len(), object_count);
+    }
+}
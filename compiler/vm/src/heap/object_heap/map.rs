@@ -0,0 +1,210 @@
+use super::{
+    sorted_tree::{self, TreeKind},
+    utils::heap_object_impls,
+    HeapObjectTrait,
+};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject},
+    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
+};
+use derive_more::Deref;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{
+    cmp::Ordering,
+    fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+};
+
+/// A persistent map whose entries are sorted by their keys. See
+/// [`sorted_tree`] for how it's stored.
+#[derive(Clone, Copy, Deref)]
+pub struct HeapMap(HeapObject);
+
+impl HeapMap {
+    const KIND: TreeKind = TreeKind::Map;
+
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    /// Takes ownership of the keys and values. For duplicate keys, the last
+    /// entry wins.
+    #[must_use]
+    pub fn create(
+        heap: &mut Heap,
+        entries: impl IntoIterator<Item = (InlineObject, InlineObject)>,
+    ) -> Self {
+        let entries = entries.into_iter().map(|(key, value)| (key, Some(value)));
+        Self(sorted_tree::from_entries(heap, Self::KIND, entries))
+    }
+
+    #[must_use]
+    pub fn len(self) -> usize {
+        sorted_tree::len(self.0)
+    }
+    #[must_use]
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+    #[must_use]
+    pub fn get(self, key: InlineObject) -> Option<InlineObject> {
+        sorted_tree::get(Self::KIND, self.0, key).map(|(_, value)| value.unwrap())
+    }
+    /// Returns all entries sorted by their keys.
+    #[must_use]
+    pub fn entries(self) -> Vec<(InlineObject, InlineObject)> {
+        sorted_tree::entries(Self::KIND, self.0)
+            .into_iter()
+            .map(|(key, value)| (key, value.unwrap()))
+            .collect()
+    }
+
+    /// Takes ownership of the `key` and `value`.
+    #[must_use]
+    pub fn insert(self, heap: &mut Heap, key: InlineObject, value: InlineObject) -> Self {
+        Self(sorted_tree::insert(
+            heap,
+            Self::KIND,
+            self.0,
+            (key, Some(value)),
+        ))
+    }
+    #[must_use]
+    pub fn remove(self, heap: &mut Heap, key: InlineObject) -> Self {
+        Self(sorted_tree::remove(heap, Self::KIND, self.0, key))
+    }
+    /// For keys contained in both maps, the values of `other` win.
+    #[must_use]
+    pub fn union(self, heap: &mut Heap, other: Self) -> Self {
+        Self(sorted_tree::union(heap, Self::KIND, self.0, other.0))
+    }
+}
+
+impl DebugDisplay for HeapMap {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        write!(
+            f,
+            "map [{}]",
+            self.entries()
+                .into_iter()
+                .map(|(key, value)| format!(
+                    "{}: {}",
+                    DebugDisplay::to_string(&key, is_debug),
+                    DebugDisplay::to_string(&value, is_debug),
+                ))
+                .join(", "),
+        )
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapMap);
+
+// Maps with the same entries can have differently shaped trees, so we compare
+// the entries instead of the nodes.
+impl Eq for HeapMap {}
+impl PartialEq for HeapMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.entries() == other.entries()
+    }
+}
+impl Hash for HeapMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries().hash(state);
+    }
+}
+impl Ord for HeapMap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entries().cmp(&other.entries())
+    }
+}
+impl PartialOrd for HeapMap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+heap_object_impls!(HeapMap);
+
+impl HeapObjectTrait for HeapMap {
+    fn content_size(self) -> usize {
+        Self::KIND.content_size()
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        heap: &mut Heap,
+        clone: HeapObject,
+        address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        sorted_tree::clone_content_to_heap_with_mapping(
+            Self::KIND,
+            self.0,
+            heap,
+            clone,
+            address_map,
+        );
+    }
+
+    fn drop_children(self, heap: &mut Heap) {
+        sorted_tree::drop_children(Self::KIND, self.0, heap);
+    }
+    fn children(self) -> Vec<InlineObject> {
+        sorted_tree::children(Self::KIND, self.0)
+    }
+
+    fn deallocate_external_stuff(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Int, Map};
+
+    fn int(heap: &mut Heap, value: i64) -> InlineObject {
+        Int::create(heap, true, value).into()
+    }
+    fn keys(map: Map) -> Vec<i64> {
+        map.entries()
+            .into_iter()
+            .map(|(key, _)| Int::try_from(key).unwrap().try_get().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn inserts_and_removes_entries_persistently() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+
+        let mut versions = vec![Map::create(&mut heap, [])];
+        // Inserting in descending order forces rotations.
+        for key in (0..100).rev() {
+            let (key, value) = (int(&mut heap, key), int(&mut heap, 2 * key));
+            let map = versions.last().unwrap().insert(&mut heap, key, value);
+            versions.push(map);
+        }
+        let map = *versions.last().unwrap();
+        assert_eq!(map.len(), 100);
+        assert_eq!(keys(map), (0..100).collect::<Vec<_>>());
+        assert_eq!(keys(versions[3]), vec![97, 98, 99]);
+        let key = int(&mut heap, 42);
+        assert_eq!(map.get(key), Some(int(&mut heap, 84)));
+
+        let removed = map.remove(&mut heap, key);
+        assert_eq!(removed.len(), 99);
+        assert_eq!(removed.get(key), None);
+        assert_eq!(map.len(), 100);
+
+        let zero = int(&mut heap, 0);
+        let other = Map::create(&mut heap, [(key, zero)]);
+        let union = removed.union(&mut heap, other);
+        let expected = map.insert(&mut heap, key, zero);
+        assert_eq!(union, expected);
+
+        for map in versions
+            .into_iter()
+            .chain([removed, other, union, expected])
+        {
+            map.drop(&mut heap);
+        }
+        assert_eq!(heap.objects().len(), object_count);
+    }
+}
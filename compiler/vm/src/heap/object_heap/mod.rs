@@ -1,8 +1,9 @@
 use self::{
-    function::HeapFunction, hir_id::HeapHirId, int::HeapInt, list::HeapList, struct_::HeapStruct,
-    tag::HeapTag, text::HeapText,
+    bytes::HeapBytes, cell::HeapCell, fraction::HeapFraction, function::HeapFunction,
+    hir_id::HeapHirId, int::HeapInt, list::HeapList, map::HeapMap, set::HeapSet,
+    struct_::HeapStruct, tag::HeapTag, text::HeapText, text_builder::HeapTextBuilder,
 };
-use super::{Data, Heap};
+use super::{Data, Heap, InlineObject};
 use crate::{
     heap::DEBUG_ALLOCATIONS,
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
@@ -20,13 +21,20 @@ use std::{
 };
 use tracing::debug;
 
+pub(super) mod bytes;
+pub(super) mod cell;
+pub(super) mod fraction;
 pub(super) mod function;
 pub(super) mod hir_id;
 pub(super) mod int;
 pub(super) mod list;
+pub(super) mod map;
+pub(super) mod set;
+mod sorted_tree;
 pub(super) mod struct_;
 pub(super) mod tag;
 pub(super) mod text;
+pub(super) mod text_builder;
 mod utils;
 
 const TRACE: bool = false;
@@ -54,17 +62,36 @@ impl HeapObject {
     pub const WORD_SIZE: usize = 8;
 
     pub const KIND_MASK: u64 = 0b111;
-    const KIND_INT: u64 = 0b000;
-    const KIND_TAG: u64 = 0b001;
+    /// Ints and fractions share a kind since there are no free kinds left.
+    /// Fractions set [`Self::IS_FRACTION_MASK`].
+    const KIND_INT_OR_FRACTION: u64 = 0b000;
+    /// Tags, maps, and sets share a kind since there are no free kinds left.
+    /// Maps set [`Self::IS_MAP_MASK`] and sets set [`Self::IS_SET_MASK`].
+    const KIND_TAG_MAP_OR_SET: u64 = 0b001;
     const KIND_TEXT: u64 = 0b010;
     const KIND_FUNCTION: u64 = 0b011;
     const KIND_LIST: u64 = 0b100;
     const KIND_STRUCT: u64 = 0b101;
-    const KIND_HIR_ID: u64 = 0b110;
+    /// HIR IDs, cells, and text builders share a kind since there are no free
+    /// kinds left. Cells set [`Self::IS_CELL_MASK`] and text builders set
+    /// [`Self::IS_TEXT_BUILDER_MASK`].
+    const KIND_HIR_ID_CELL_OR_TEXT_BUILDER: u64 = 0b110;
+    const KIND_BYTES: u64 = 0b111;
 
     pub const IS_REFERENCE_COUNTED_SHIFT: usize = 3;
     pub const IS_REFERENCE_COUNTED_MASK: u64 = 0b1 << Self::IS_REFERENCE_COUNTED_SHIFT;
 
+    const IS_CELL_SHIFT: usize = 4;
+    const IS_CELL_MASK: u64 = 0b1 << Self::IS_CELL_SHIFT;
+    const IS_FRACTION_SHIFT: usize = 4;
+    const IS_FRACTION_MASK: u64 = 0b1 << Self::IS_FRACTION_SHIFT;
+    const IS_MAP_SHIFT: usize = 4;
+    const IS_MAP_MASK: u64 = 0b1 << Self::IS_MAP_SHIFT;
+    const IS_SET_SHIFT: usize = 5;
+    const IS_SET_MASK: u64 = 0b1 << Self::IS_SET_SHIFT;
+    const IS_TEXT_BUILDER_SHIFT: usize = 5;
+    const IS_TEXT_BUILDER_MASK: u64 = 0b1 << Self::IS_TEXT_BUILDER_SHIFT;
+
     #[must_use]
     pub const fn new(address: NonNull<u64>) -> Self {
         Self(address)
@@ -92,6 +119,37 @@ impl HeapObject {
         self.unsafe_get_word(0)
     }
 
+    /// Whether the header word contains a valid kind (and no unexpected bits
+    /// for kinds that store everything else outside the header). This is only
+    /// used for detecting memory corruption.
+    #[must_use]
+    pub(super) fn has_valid_header(self) -> bool {
+        let header_word = self.header_word();
+        match header_word & Self::KIND_MASK {
+            Self::KIND_INT_OR_FRACTION => {
+                header_word
+                    & !(Self::KIND_MASK | Self::IS_REFERENCE_COUNTED_MASK | Self::IS_FRACTION_MASK)
+                    == 0
+            }
+            Self::KIND_HIR_ID_CELL_OR_TEXT_BUILDER => {
+                let remaining = header_word & !(Self::KIND_MASK | Self::IS_REFERENCE_COUNTED_MASK);
+                remaining == 0
+                    || remaining == Self::IS_CELL_MASK
+                    || remaining == Self::IS_TEXT_BUILDER_MASK
+            }
+            Self::KIND_TAG_MAP_OR_SET => {
+                let remaining = header_word & !(Self::KIND_MASK | Self::IS_REFERENCE_COUNTED_MASK);
+                remaining == 0 || remaining == Self::IS_MAP_MASK || remaining == Self::IS_SET_MASK
+            }
+            Self::KIND_TEXT
+            | Self::KIND_FUNCTION
+            | Self::KIND_LIST
+            | Self::KIND_STRUCT
+            | Self::KIND_BYTES => true,
+            _ => false,
+        }
+    }
+
     // Reference Counting
     #[must_use]
     pub(super) fn is_reference_counted(self) -> bool {
@@ -267,6 +325,12 @@ pub trait HeapObjectTrait: Copy + Into<HeapObject> {
     /// This method is called by [free] prior to deallocating the object's
     /// memory.
     fn drop_children(self, heap: &mut Heap);
+    /// Returns all objects this object references directly.
+    ///
+    /// The garbage collector uses this to find objects that are only kept alive
+    /// by reference cycles.
+    #[must_use]
+    fn children(self) -> Vec<InlineObject>;
 
     // TODO: This is temporary. Once we store everything in the heap (including
     // stuff like big int values and HIR IDs), we can remove this.
@@ -283,6 +347,12 @@ pub enum HeapData {
     Tag(HeapTag),
     Function(HeapFunction),
     HirId(HeapHirId),
+    Bytes(HeapBytes),
+    Cell(HeapCell),
+    Fraction(HeapFraction),
+    Map(HeapMap),
+    Set(HeapSet),
+    TextBuilder(HeapTextBuilder),
 }
 
 impl DebugDisplay for HeapData {
@@ -295,6 +365,12 @@ impl DebugDisplay for HeapData {
             Self::Tag(tag) => DebugDisplay::fmt(tag, f, is_debug),
             Self::Function(function) => DebugDisplay::fmt(function, f, is_debug),
             Self::HirId(hir_id) => DebugDisplay::fmt(hir_id, f, is_debug),
+            Self::Bytes(bytes) => DebugDisplay::fmt(bytes, f, is_debug),
+            Self::Cell(cell) => DebugDisplay::fmt(cell, f, is_debug),
+            Self::Fraction(fraction) => DebugDisplay::fmt(fraction, f, is_debug),
+            Self::Map(map) => DebugDisplay::fmt(map, f, is_debug),
+            Self::Set(set) => DebugDisplay::fmt(set, f, is_debug),
+            Self::TextBuilder(builder) => DebugDisplay::fmt(builder, f, is_debug),
         }
     }
 }
@@ -305,25 +381,44 @@ impl From<HeapObject> for HeapData {
     fn from(object: HeapObject) -> Self {
         let header_word = object.header_word();
         match header_word & HeapObject::KIND_MASK {
-            HeapObject::KIND_INT => {
-                debug_assert_eq!(
-                    header_word & !HeapObject::IS_REFERENCE_COUNTED_MASK,
-                    HeapObject::KIND_INT,
-                );
-                Self::Int(HeapInt::new_unchecked(object))
+            HeapObject::KIND_INT_OR_FRACTION => {
+                if header_word & HeapObject::IS_FRACTION_MASK != 0 {
+                    Self::Fraction(HeapFraction::new_unchecked(object))
+                } else {
+                    debug_assert_eq!(
+                        header_word & !HeapObject::IS_REFERENCE_COUNTED_MASK,
+                        HeapObject::KIND_INT_OR_FRACTION,
+                    );
+                    Self::Int(HeapInt::new_unchecked(object))
+                }
             }
             HeapObject::KIND_LIST => Self::List(HeapList::new_unchecked(object)),
             HeapObject::KIND_STRUCT => Self::Struct(HeapStruct::new_unchecked(object)),
-            HeapObject::KIND_TAG => Self::Tag(HeapTag::new_unchecked(object)),
+            HeapObject::KIND_TAG_MAP_OR_SET => {
+                if header_word & HeapObject::IS_MAP_MASK != 0 {
+                    Self::Map(HeapMap::new_unchecked(object))
+                } else if header_word & HeapObject::IS_SET_MASK != 0 {
+                    Self::Set(HeapSet::new_unchecked(object))
+                } else {
+                    Self::Tag(HeapTag::new_unchecked(object))
+                }
+            }
             HeapObject::KIND_TEXT => Self::Text(HeapText::new_unchecked(object)),
             HeapObject::KIND_FUNCTION => Self::Function(HeapFunction::new_unchecked(object)),
-            HeapObject::KIND_HIR_ID => {
-                debug_assert_eq!(
-                    header_word & !HeapObject::IS_REFERENCE_COUNTED_MASK,
-                    HeapObject::KIND_HIR_ID,
-                );
-                Self::HirId(HeapHirId::new_unchecked(object))
+            HeapObject::KIND_HIR_ID_CELL_OR_TEXT_BUILDER => {
+                if header_word & HeapObject::IS_CELL_MASK != 0 {
+                    Self::Cell(HeapCell::new_unchecked(object))
+                } else if header_word & HeapObject::IS_TEXT_BUILDER_MASK != 0 {
+                    Self::TextBuilder(HeapTextBuilder::new_unchecked(object))
+                } else {
+                    debug_assert_eq!(
+                        header_word & !HeapObject::IS_REFERENCE_COUNTED_MASK,
+                        HeapObject::KIND_HIR_ID_CELL_OR_TEXT_BUILDER,
+                    );
+                    Self::HirId(HeapHirId::new_unchecked(object))
+                }
             }
+            HeapObject::KIND_BYTES => Self::Bytes(HeapBytes::new_unchecked(object)),
             tag => panic!("Invalid tag: {tag:b}"),
         }
     }
@@ -340,6 +435,12 @@ impl Deref for HeapData {
             Self::Tag(tag) => tag,
             Self::Function(function) => function,
             Self::HirId(hir_id) => hir_id,
+            Self::Bytes(bytes) => bytes,
+            Self::Cell(cell) => cell,
+            Self::Fraction(fraction) => fraction,
+            Self::Map(map) => map,
+            Self::Set(set) => set,
+            Self::TextBuilder(builder) => builder,
         }
     }
 }
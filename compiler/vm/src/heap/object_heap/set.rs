@@ -0,0 +1,150 @@
+use super::{
+    sorted_tree::{self, TreeKind},
+    utils::heap_object_impls,
+    HeapObjectTrait,
+};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject},
+    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
+};
+use derive_more::Deref;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{
+    cmp::Ordering,
+    fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+};
+
+/// A persistent set whose items are sorted. See [`sorted_tree`] for how it's
+/// stored.
+#[derive(Clone, Copy, Deref)]
+pub struct HeapSet(HeapObject);
+
+impl HeapSet {
+    const KIND: TreeKind = TreeKind::Set;
+
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    /// Takes ownership of the items.
+    #[must_use]
+    pub fn create(heap: &mut Heap, items: impl IntoIterator<Item = InlineObject>) -> Self {
+        let entries = items.into_iter().map(|item| (item, None));
+        Self(sorted_tree::from_entries(heap, Self::KIND, entries))
+    }
+
+    #[must_use]
+    pub fn len(self) -> usize {
+        sorted_tree::len(self.0)
+    }
+    #[must_use]
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+    #[must_use]
+    pub fn contains(self, item: InlineObject) -> bool {
+        sorted_tree::get(Self::KIND, self.0, item).is_some()
+    }
+    /// Returns all items in ascending order.
+    #[must_use]
+    pub fn items(self) -> Vec<InlineObject> {
+        sorted_tree::entries(Self::KIND, self.0)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect()
+    }
+
+    /// Takes ownership of the `item`.
+    #[must_use]
+    pub fn insert(self, heap: &mut Heap, item: InlineObject) -> Self {
+        Self(sorted_tree::insert(heap, Self::KIND, self.0, (item, None)))
+    }
+    #[must_use]
+    pub fn remove(self, heap: &mut Heap, item: InlineObject) -> Self {
+        Self(sorted_tree::remove(heap, Self::KIND, self.0, item))
+    }
+    #[must_use]
+    pub fn union(self, heap: &mut Heap, other: Self) -> Self {
+        Self(sorted_tree::union(heap, Self::KIND, self.0, other.0))
+    }
+}
+
+impl DebugDisplay for HeapSet {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        let items = self.items();
+        if items.len() == 1 {
+            return write!(f, "set ({},)", DebugDisplay::to_string(&items[0], is_debug));
+        }
+        write!(
+            f,
+            "set ({})",
+            if items.is_empty() {
+                ",".to_string()
+            } else {
+                items
+                    .iter()
+                    .map(|item| DebugDisplay::to_string(item, is_debug))
+                    .join(", ")
+            },
+        )
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapSet);
+
+// Sets with the same items can have differently shaped trees, so we compare
+// the items instead of the nodes.
+impl Eq for HeapSet {}
+impl PartialEq for HeapSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.items() == other.items()
+    }
+}
+impl Hash for HeapSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.items().hash(state);
+    }
+}
+impl Ord for HeapSet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.items().cmp(&other.items())
+    }
+}
+impl PartialOrd for HeapSet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+heap_object_impls!(HeapSet);
+
+impl HeapObjectTrait for HeapSet {
+    fn content_size(self) -> usize {
+        Self::KIND.content_size()
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        heap: &mut Heap,
+        clone: HeapObject,
+        address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        sorted_tree::clone_content_to_heap_with_mapping(
+            Self::KIND,
+            self.0,
+            heap,
+            clone,
+            address_map,
+        );
+    }
+
+    fn drop_children(self, heap: &mut Heap) {
+        sorted_tree::drop_children(Self::KIND, self.0, heap);
+    }
+    fn children(self) -> Vec<InlineObject> {
+        sorted_tree::children(Self::KIND, self.0)
+    }
+
+    fn deallocate_external_stuff(self) {}
+}
@@ -0,0 +1,450 @@
+//! Persistent weight-balanced binary search trees that back maps and sets.
+//!
+//! Each node is a heap object storing the number of entries in its subtree,
+//! its children, its key, and, for maps, its value. Updates copy the nodes on
+//! the path to the changed entry and share all other nodes with the original
+//! tree, so they take logarithmic time and memory. Shared nodes are kept alive
+//! by reference counting like any other heap object.
+//!
+//! Only the root of an empty map or set is a node with a size of zero. Empty
+//! subtrees are stored as null pointers.
+//!
+//! The balancing follows Adams' "Efficient sets – a balancing act", with the
+//! parameters from Haskell's `Data.Map`.
+
+use super::HeapObject;
+use crate::heap::{Heap, InlineObject};
+use rustc_hash::FxHashMap;
+use std::{cmp::Ordering, num::NonZeroU64, ptr::NonNull};
+
+const SIZE_INDEX: usize = 0;
+const LEFT_INDEX: usize = 1;
+const RIGHT_INDEX: usize = 2;
+const KEY_INDEX: usize = 3;
+const VALUE_INDEX: usize = 4;
+
+/// A subtree may contain at most this many times as many entries as its
+/// sibling.
+const DELTA: usize = 3;
+/// Decides between single and double rotations.
+const RATIO: usize = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum TreeKind {
+    Map,
+    Set,
+}
+impl TreeKind {
+    const fn mask(self) -> u64 {
+        match self {
+            Self::Map => HeapObject::IS_MAP_MASK,
+            Self::Set => HeapObject::IS_SET_MASK,
+        }
+    }
+    pub(super) const fn content_size(self) -> usize {
+        let words = match self {
+            Self::Map => 5,
+            Self::Set => 4,
+        };
+        words * HeapObject::WORD_SIZE
+    }
+}
+
+/// A key and, for maps, its value.
+pub(super) type Entry = (InlineObject, Option<InlineObject>);
+
+/// A node and the ownership of the reference to it.
+type Tree = Option<HeapObject>;
+
+#[must_use]
+pub(super) fn create_empty(heap: &mut Heap, kind: TreeKind) -> HeapObject {
+    let node = heap.allocate(
+        HeapObject::KIND_TAG_MAP_OR_SET,
+        true,
+        kind.mask(),
+        kind.content_size(),
+    );
+    for index in 0..kind.content_size() / HeapObject::WORD_SIZE {
+        node.unsafe_set_content_word(index, 0);
+    }
+    node
+}
+/// Takes ownership of all arguments.
+#[must_use]
+fn create_node(
+    heap: &mut Heap,
+    kind: TreeKind,
+    (key, value): Entry,
+    left: Tree,
+    right: Tree,
+) -> HeapObject {
+    let node = heap.allocate(
+        HeapObject::KIND_TAG_MAP_OR_SET,
+        true,
+        kind.mask(),
+        kind.content_size(),
+    );
+    let size = 1 + subtree_size(left) + subtree_size(right);
+    node.unsafe_set_content_word(SIZE_INDEX, size as u64);
+    node.unsafe_set_content_word(LEFT_INDEX, tree_to_word(left));
+    node.unsafe_set_content_word(RIGHT_INDEX, tree_to_word(right));
+    node.unsafe_set_content_word(KEY_INDEX, key.raw_word().get());
+    if kind == TreeKind::Map {
+        node.unsafe_set_content_word(VALUE_INDEX, value.unwrap().raw_word().get());
+    }
+    node
+}
+
+// Accessing Nodes
+
+#[must_use]
+pub(super) fn len(node: HeapObject) -> usize {
+    #[allow(clippy::cast_possible_truncation)]
+    let size = node.unsafe_get_content_word(SIZE_INDEX) as usize;
+    size
+}
+#[must_use]
+fn subtree_size(tree: Tree) -> usize {
+    tree.map_or(0, len)
+}
+/// Returns `None` for the root of an empty tree.
+#[must_use]
+fn root(node: HeapObject) -> Tree {
+    if len(node) == 0 {
+        None
+    } else {
+        Some(node)
+    }
+}
+#[must_use]
+fn left(node: HeapObject) -> Tree {
+    word_to_tree(node.unsafe_get_content_word(LEFT_INDEX))
+}
+#[must_use]
+fn right(node: HeapObject) -> Tree {
+    word_to_tree(node.unsafe_get_content_word(RIGHT_INDEX))
+}
+#[must_use]
+fn entry(kind: TreeKind, node: HeapObject) -> Entry {
+    let word =
+        |index| InlineObject::new(NonZeroU64::new(node.unsafe_get_content_word(index)).unwrap());
+    let value = match kind {
+        TreeKind::Map => Some(word(VALUE_INDEX)),
+        TreeKind::Set => None,
+    };
+    (word(KEY_INDEX), value)
+}
+
+#[must_use]
+fn tree_to_word(tree: Tree) -> u64 {
+    tree.map_or(0, |it| it.address().addr().get() as u64)
+}
+#[must_use]
+fn word_to_tree(word: u64) -> Tree {
+    NonNull::new(word as *mut u64).map(HeapObject::new)
+}
+
+/// Returns the key and value of the node with the given `key`.
+#[must_use]
+pub(super) fn get(kind: TreeKind, node: HeapObject, key: InlineObject) -> Option<Entry> {
+    let mut tree = root(node);
+    while let Some(node) = tree {
+        let entry = entry(kind, node);
+        tree = match key.cmp(&entry.0) {
+            Ordering::Less => left(node),
+            Ordering::Equal => return Some(entry),
+            Ordering::Greater => right(node),
+        };
+    }
+    None
+}
+/// Returns all entries sorted by their keys.
+#[must_use]
+pub(super) fn entries(kind: TreeKind, node: HeapObject) -> Vec<Entry> {
+    fn collect(kind: TreeKind, tree: Tree, entries: &mut Vec<Entry>) {
+        let Some(node) = tree else {
+            return;
+        };
+        collect(kind, left(node), entries);
+        entries.push(entry(kind, node));
+        collect(kind, right(node), entries);
+    }
+
+    let mut entries = Vec::with_capacity(len(node));
+    collect(kind, root(node), &mut entries);
+    entries
+}
+
+// Heap Object Implementations
+
+pub(super) fn clone_content_to_heap_with_mapping(
+    kind: TreeKind,
+    node: HeapObject,
+    heap: &mut Heap,
+    clone: HeapObject,
+    address_map: &mut FxHashMap<HeapObject, HeapObject>,
+) {
+    clone.unsafe_set_content_word(SIZE_INDEX, node.unsafe_get_content_word(SIZE_INDEX));
+    for index in [LEFT_INDEX, RIGHT_INDEX] {
+        let child = word_to_tree(node.unsafe_get_content_word(index))
+            .map(|it| it.clone_to_heap_with_mapping(heap, address_map));
+        clone.unsafe_set_content_word(index, tree_to_word(child));
+    }
+    if len(node) == 0 {
+        clone.unsafe_set_content_word(KEY_INDEX, 0);
+        if kind == TreeKind::Map {
+            clone.unsafe_set_content_word(VALUE_INDEX, 0);
+        }
+        return;
+    }
+    let (key, value) = entry(kind, node);
+    let key = key.clone_to_heap_with_mapping(heap, address_map);
+    clone.unsafe_set_content_word(KEY_INDEX, key.raw_word().get());
+    if let Some(value) = value {
+        let value = value.clone_to_heap_with_mapping(heap, address_map);
+        clone.unsafe_set_content_word(VALUE_INDEX, value.raw_word().get());
+    }
+}
+pub(super) fn drop_children(kind: TreeKind, node: HeapObject, heap: &mut Heap) {
+    for child in children(kind, node) {
+        child.drop(heap);
+    }
+}
+#[must_use]
+pub(super) fn children(kind: TreeKind, node: HeapObject) -> Vec<InlineObject> {
+    if len(node) == 0 {
+        return vec![];
+    }
+    let (key, value) = entry(kind, node);
+    [left(node), right(node)]
+        .into_iter()
+        .flatten()
+        .map(InlineObject::from)
+        .chain([key])
+        .chain(value)
+        .collect()
+}
+
+// Updates
+//
+// Unless noted otherwise, the following functions take ownership of the trees
+// and entries passed to them.
+
+/// Borrows the `node` and returns an owned reference to it.
+#[must_use]
+fn owned_root(node: HeapObject) -> Tree {
+    let tree = root(node);
+    if let Some(node) = tree {
+        node.dup();
+    }
+    tree
+}
+#[must_use]
+fn to_node(heap: &mut Heap, kind: TreeKind, tree: Tree) -> HeapObject {
+    tree.unwrap_or_else(|| create_empty(heap, kind))
+}
+
+/// Returns owned references to the `node`'s entry and children and drops the
+/// reference to the `node` itself.
+#[must_use]
+fn take_parts(heap: &mut Heap, kind: TreeKind, node: HeapObject) -> (Entry, Tree, Tree) {
+    let (key, value) = entry(kind, node);
+    let (left, right) = (left(node), right(node));
+    key.dup(heap);
+    if let Some(value) = value {
+        value.dup(heap);
+    }
+    for child in [left, right].into_iter().flatten() {
+        child.dup();
+    }
+    node.drop(heap);
+    ((key, value), left, right)
+}
+fn drop_entry(heap: &mut Heap, (key, value): Entry) {
+    key.drop(heap);
+    if let Some(value) = value {
+        value.drop(heap);
+    }
+}
+
+/// Creates a node, restoring the balance if one side got too big by inserting
+/// or removing a single entry.
+#[must_use]
+fn balance(heap: &mut Heap, kind: TreeKind, entry: Entry, left: Tree, right: Tree) -> HeapObject {
+    let (left_size, right_size) = (subtree_size(left), subtree_size(right));
+    if left_size + right_size <= 1 {
+        create_node(heap, kind, entry, left, right)
+    } else if right_size > DELTA * left_size {
+        let (right_entry, right_left, right_right) = take_parts(heap, kind, right.unwrap());
+        if subtree_size(right_left) < RATIO * subtree_size(right_right) {
+            let new_left = create_node(heap, kind, entry, left, right_left);
+            create_node(heap, kind, right_entry, Some(new_left), right_right)
+        } else {
+            let (middle_entry, middle_left, middle_right) =
+                take_parts(heap, kind, right_left.unwrap());
+            let new_left = create_node(heap, kind, entry, left, middle_left);
+            let new_right = create_node(heap, kind, right_entry, middle_right, right_right);
+            create_node(heap, kind, middle_entry, Some(new_left), Some(new_right))
+        }
+    } else if left_size > DELTA * right_size {
+        let (left_entry, left_left, left_right) = take_parts(heap, kind, left.unwrap());
+        if subtree_size(left_right) < RATIO * subtree_size(left_left) {
+            let new_right = create_node(heap, kind, entry, left_right, right);
+            create_node(heap, kind, left_entry, left_left, Some(new_right))
+        } else {
+            let (middle_entry, middle_left, middle_right) =
+                take_parts(heap, kind, left_right.unwrap());
+            let new_left = create_node(heap, kind, left_entry, left_left, middle_left);
+            let new_right = create_node(heap, kind, entry, middle_right, right);
+            create_node(heap, kind, middle_entry, Some(new_left), Some(new_right))
+        }
+    } else {
+        create_node(heap, kind, entry, left, right)
+    }
+}
+
+/// Replaces the entry if the tree already contains its key.
+#[must_use]
+fn insert_into(heap: &mut Heap, kind: TreeKind, tree: Tree, entry: Entry) -> HeapObject {
+    let Some(node) = tree else {
+        return create_node(heap, kind, entry, None, None);
+    };
+    let (node_entry, left, right) = take_parts(heap, kind, node);
+    match entry.0.cmp(&node_entry.0) {
+        Ordering::Less => {
+            let left = insert_into(heap, kind, left, entry);
+            balance(heap, kind, node_entry, Some(left), right)
+        }
+        Ordering::Equal => {
+            drop_entry(heap, node_entry);
+            create_node(heap, kind, entry, left, right)
+        }
+        Ordering::Greater => {
+            let right = insert_into(heap, kind, right, entry);
+            balance(heap, kind, node_entry, left, Some(right))
+        }
+    }
+}
+/// Borrows the `key`, which the tree must contain.
+#[must_use]
+fn remove_from(heap: &mut Heap, kind: TreeKind, node: HeapObject, key: InlineObject) -> Tree {
+    let (node_entry, left, right) = take_parts(heap, kind, node);
+    match key.cmp(&node_entry.0) {
+        Ordering::Less => {
+            let left = remove_from(heap, kind, left.unwrap(), key);
+            Some(balance(heap, kind, node_entry, left, right))
+        }
+        Ordering::Equal => {
+            drop_entry(heap, node_entry);
+            glue(heap, kind, left, right)
+        }
+        Ordering::Greater => {
+            let right = remove_from(heap, kind, right.unwrap(), key);
+            Some(balance(heap, kind, node_entry, left, right))
+        }
+    }
+}
+/// Joins two balanced trees whose sizes differ by at most one removed entry
+/// and whose keys are all less than (`left`) or greater than (`right`) each
+/// other.
+#[must_use]
+fn glue(heap: &mut Heap, kind: TreeKind, left: Tree, right: Tree) -> Tree {
+    match (left, right) {
+        (None, tree) | (tree, None) => tree,
+        (Some(left), Some(right)) => Some(if len(left) > len(right) {
+            let (entry, left) = remove_max(heap, kind, left);
+            balance(heap, kind, entry, left, Some(right))
+        } else {
+            let (entry, right) = remove_min(heap, kind, right);
+            balance(heap, kind, entry, Some(left), right)
+        }),
+    }
+}
+#[must_use]
+fn remove_min(heap: &mut Heap, kind: TreeKind, node: HeapObject) -> (Entry, Tree) {
+    let (entry, left, right) = take_parts(heap, kind, node);
+    let Some(left) = left else {
+        return (entry, right);
+    };
+    let (min, left) = remove_min(heap, kind, left);
+    (min, Some(balance(heap, kind, entry, left, right)))
+}
+#[must_use]
+fn remove_max(heap: &mut Heap, kind: TreeKind, node: HeapObject) -> (Entry, Tree) {
+    let (entry, left, right) = take_parts(heap, kind, node);
+    let Some(right) = right else {
+        return (entry, left);
+    };
+    let (max, right) = remove_max(heap, kind, right);
+    (max, Some(balance(heap, kind, entry, left, right)))
+}
+
+/// Takes ownership of the entries. Later entries replace earlier ones with the
+/// same key.
+#[must_use]
+pub(super) fn from_entries(
+    heap: &mut Heap,
+    kind: TreeKind,
+    entries: impl IntoIterator<Item = Entry>,
+) -> HeapObject {
+    let tree = entries.into_iter().fold(None, |tree, entry| {
+        Some(insert_into(heap, kind, tree, entry))
+    });
+    to_node(heap, kind, tree)
+}
+/// Borrows the `node` and takes ownership of the `entry`.
+#[must_use]
+pub(super) fn insert(
+    heap: &mut Heap,
+    kind: TreeKind,
+    node: HeapObject,
+    entry: Entry,
+) -> HeapObject {
+    let tree = owned_root(node);
+    insert_into(heap, kind, tree, entry)
+}
+/// Borrows the `node` and the `key`.
+#[must_use]
+pub(super) fn remove(
+    heap: &mut Heap,
+    kind: TreeKind,
+    node: HeapObject,
+    key: InlineObject,
+) -> HeapObject {
+    if get(kind, node, key).is_none() {
+        node.dup();
+        return node;
+    }
+    let tree = remove_from(heap, kind, owned_root(node).unwrap(), key);
+    to_node(heap, kind, tree)
+}
+/// Borrows both nodes. For keys contained in both trees, the entries of `b`
+/// win.
+#[must_use]
+pub(super) fn union(heap: &mut Heap, kind: TreeKind, a: HeapObject, b: HeapObject) -> HeapObject {
+    // Inserting the entries of the smaller tree into the bigger one takes
+    // O(m log(n + m)) time.
+    let tree = if len(a) >= len(b) {
+        entries(kind, b)
+            .into_iter()
+            .fold(owned_root(a), |tree, entry| {
+                dup_entry(heap, entry);
+                Some(insert_into(heap, kind, tree, entry))
+            })
+    } else {
+        entries(kind, a)
+            .into_iter()
+            .filter(|(key, _)| get(kind, b, *key).is_none())
+            .fold(owned_root(b), |tree, entry| {
+                dup_entry(heap, entry);
+                Some(insert_into(heap, kind, tree, entry))
+            })
+    };
+    to_node(heap, kind, tree)
+}
+fn dup_entry(heap: &mut Heap, (key, value): Entry) {
+    key.dup(heap);
+    if let Some(value) = value {
+        value.dup(heap);
+    }
+}
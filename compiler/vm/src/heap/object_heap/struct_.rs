@@ -311,6 +311,9 @@ impl HeapObjectTrait for HeapStruct {
             value.drop(heap);
         }
     }
+    fn children(self) -> Vec<InlineObject> {
+        self.keys().iter().chain(self.values()).copied().collect()
+    }
 
     fn deallocate_external_stuff(self) {}
 }
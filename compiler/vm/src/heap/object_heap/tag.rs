@@ -31,7 +31,7 @@ impl HeapTag {
     ) -> Self {
         let value = value.into();
         let tag = Self(heap.allocate(
-            HeapObject::KIND_TAG,
+            HeapObject::KIND_TAG_MAP_OR_SET,
             is_reference_counted,
             0,
             2 * HeapObject::WORD_SIZE,
@@ -129,6 +129,9 @@ impl HeapObjectTrait for HeapTag {
         self.symbol().drop(heap);
         self.value().drop(heap);
     }
+    fn children(self) -> Vec<InlineObject> {
+        vec![self.symbol().into(), self.value()]
+    }
 
     fn deallocate_external_stuff(self) {}
 }
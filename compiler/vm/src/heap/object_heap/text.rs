@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, Int, List, Tag, Text},
+    heap::{object_heap::HeapObject, Heap, InlineObject, Int, List, Tag, Text},
     utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
 };
 use derive_more::Deref;
@@ -73,6 +73,12 @@ impl HeapText {
             .collect_vec();
         List::create(heap, true, &characters)
     }
+    /// Compares by Unicode code points, which is also the order of the UTF-8
+    /// bytes.
+    #[must_use]
+    pub fn compare_to(self, heap: &Heap, other: Text) -> Tag {
+        Tag::create_ordering(heap, self.get().cmp(other.get()))
+    }
     #[must_use]
     pub fn contains(self, heap: &Heap, pattern: Text) -> Tag {
         Tag::create_bool(heap, self.get().contains(pattern.get()))
@@ -110,6 +116,14 @@ impl HeapText {
         Text::create(heap, true, &format!("{}{}", self.get(), other.get()))
     }
     #[must_use]
+    pub fn to_lowercase(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, &self.get().to_lowercase())
+    }
+    #[must_use]
+    pub fn to_uppercase(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, &self.get().to_uppercase())
+    }
+    #[must_use]
     pub fn trim_start(self, heap: &mut Heap) -> Text {
         Text::create(heap, true, self.get().trim_start())
     }
@@ -152,6 +166,9 @@ impl HeapObjectTrait for HeapText {
     }
 
     fn drop_children(self, _heap: &mut Heap) {}
+    fn children(self) -> Vec<InlineObject> {
+        vec![]
+    }
 
     fn deallocate_external_stuff(self) {}
 }
@@ -0,0 +1,142 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Heap, InlineObject, Text},
+    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
+};
+use derive_more::Deref;
+use rustc_hash::FxHashMap;
+use std::{
+    cmp::Ordering,
+    fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+    mem,
+    ptr::{self, NonNull},
+};
+
+/// A growable text for building large texts piece by piece. Unlike
+/// concatenating texts, which copies both of them, appending to a builder only
+/// copies the appended text (amortized).
+///
+/// Like cells, text builders are mutable and therefore compared by identity.
+#[derive(Clone, Copy, Deref)]
+pub struct HeapTextBuilder(HeapObject);
+
+impl HeapTextBuilder {
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    /// Text builders are always reference-counted: Objects that aren't (i.e.,
+    /// constants) are shared across fibers without synchronization.
+    #[must_use]
+    pub fn create(heap: &mut Heap, value: String) -> Self {
+        let builder = Self(heap.allocate(
+            HeapObject::KIND_HIR_ID_CELL_OR_TEXT_BUILDER,
+            true,
+            HeapObject::IS_TEXT_BUILDER_MASK,
+            mem::size_of::<String>(),
+        ));
+        unsafe { ptr::write(builder.string_pointer().as_ptr(), value) };
+        builder
+    }
+
+    #[must_use]
+    fn string_pointer(self) -> NonNull<String> {
+        self.content_word_pointer(0).cast()
+    }
+    #[must_use]
+    pub fn get<'a>(self) -> &'a str {
+        unsafe { self.string_pointer().as_ref() }
+    }
+
+    pub fn append(self, value: &str) {
+        unsafe { self.string_pointer().as_mut() }.push_str(value);
+    }
+    #[must_use]
+    pub fn build(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, self.get())
+    }
+}
+
+impl DebugDisplay for HeapTextBuilder {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        if is_debug {
+            write!(f, "textBuilder at {:p} ({:?})", self.address(), self.get())
+        } else {
+            write!(f, "textBuilder")
+        }
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapTextBuilder);
+
+impl Eq for HeapTextBuilder {}
+impl PartialEq for HeapTextBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer_equals(**other)
+    }
+}
+impl Hash for HeapTextBuilder {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state);
+    }
+}
+impl Ord for HeapTextBuilder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address().cmp(&other.address())
+    }
+}
+impl PartialOrd for HeapTextBuilder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+heap_object_impls!(HeapTextBuilder);
+
+impl HeapObjectTrait for HeapTextBuilder {
+    fn content_size(self) -> usize {
+        mem::size_of::<String>()
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        _heap: &mut Heap,
+        clone: HeapObject,
+        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        let clone = Self(clone);
+        let value = self.get().to_string();
+        unsafe { ptr::write(clone.string_pointer().as_ptr(), value) };
+    }
+
+    fn drop_children(self, _heap: &mut Heap) {}
+    fn children(self) -> Vec<InlineObject> {
+        vec![]
+    }
+
+    fn deallocate_external_stuff(self) {
+        unsafe { ptr::drop_in_place(self.string_pointer().as_ptr()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, TextBuilder};
+
+    #[test]
+    fn appends_without_changing_built_texts() {
+        let mut heap = Heap::default();
+        let object_count = heap.objects().len();
+
+        let builder = TextBuilder::create(&mut heap, "foo".to_string());
+        builder.append("bar");
+        let text = builder.build(&mut heap);
+        builder.append("baz");
+        assert_eq!(text.get(), "foobar");
+        assert_eq!(builder.get(), "foobarbaz");
+
+        text.drop(&mut heap);
+        builder.drop(&mut heap);
+        assert_eq!(heap.objects().len(), object_count);
+    }
+}
@@ -237,6 +237,15 @@ pub impl ToDebugText for InlineObject {
                         .map(|(_, key, value)| (key, value))
                         .collect_vec(),
                 )),
+                Data::Bytes(bytes) => FormatValue::Bytes(bytes.get()),
+                Data::Cell(_) => FormatValue::Cell,
+                Data::TextBuilder(_) => FormatValue::TextBuilder,
+                Data::Map(map) => FormatValue::Map(map.entries()),
+                Data::Set(set) => FormatValue::Set(set.items()),
+                Data::Fraction(fraction) => FormatValue::Fraction {
+                    numerator: fraction.get().numer(),
+                    denominator: fraction.get().denom(),
+                },
                 Data::HirId(_) => unreachable!(),
                 Data::Function(_) | Data::Builtin(_) | Data::Handle(_) => FormatValue::Function,
             })
@@ -0,0 +1,576 @@
+//! Serialization of objects into a compact binary format, e.g., for persisting
+//! a VM's state between runs.
+//!
+//! A snapshot starts with [`MAGIC`] and [`VERSION`], followed by the number of
+//! nodes, the nodes themselves, the number of roots, and the roots. Nodes
+//! reference other nodes by their index, and each node only references nodes
+//! before it. Objects that are referenced multiple times are only stored once,
+//! so sharing is preserved. All numbers are encoded as unsigned LEB128.
+//!
+//! | Kind       | Content                                                      |
+//! |------------|--------------------------------------------------------------|
+//! | 0 int      | length, value as little-endian two's complement bytes        |
+//! | 1 text     | length, UTF-8 bytes                                          |
+//! | 2 tag      | symbol, 0 or 1 for whether a value follows, [value]          |
+//! | 3 list     | length, items                                                |
+//! | 4 struct   | length, keys and values alternating                          |
+//! | 5 function | body (instruction offset), argument count, length, captured |
+//! | 6 builtin  | index in [`BuiltinFunction::VARIANTS`]                       |
+//! | 7 bytes    | length, bytes                                                |
+//! | 8 fraction | numerator, then denominator, each encoded like an int        |
+//! | 9 map      | length, keys and values alternating, sorted by key           |
+//! | 10 set     | length, items in ascending order                             |
+//!
+//! Handles and HIR IDs can't be serialized since they only make sense in the
+//! running program. Reference cycles aren't supported either, and neither are
+//! cells since their contents may still change after the snapshot.
+
+use super::{
+    object_heap::function::HeapFunction, Builtin, Bytes, Data, Function, Heap, InlineObject, Int,
+    List, Map, Number, Set, Struct, Tag, Text,
+};
+use crate::byte_code::ByteCode;
+use candy_frontend::builtin_functions::BuiltinFunction;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::VariantArray;
+
+const MAGIC: &[u8] = b"CANDYHEAP";
+const VERSION: u8 = 6;
+
+const KIND_INT: u8 = 0;
+const KIND_TEXT: u8 = 1;
+const KIND_TAG: u8 = 2;
+const KIND_LIST: u8 = 3;
+const KIND_STRUCT: u8 = 4;
+const KIND_FUNCTION: u8 = 5;
+const KIND_BUILTIN: u8 = 6;
+const KIND_BYTES: u8 = 7;
+const KIND_FRACTION: u8 = 8;
+const KIND_MAP: u8 = 9;
+const KIND_SET: u8 = 10;
+
+impl Heap {
+    /// Serializes `roots` and all objects reachable from them.
+    pub fn serialize(roots: &[InlineObject]) -> Result<Vec<u8>, String> {
+        let mut serializer = Serializer::default();
+        let roots: Vec<_> = roots
+            .iter()
+            .map(|root| serializer.visit(*root))
+            .collect::<Result<_, _>>()?;
+
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.push(VERSION);
+        write_number(&mut snapshot, serializer.node_count);
+        snapshot.extend(serializer.nodes);
+        write_number(&mut snapshot, roots.len());
+        for root in roots {
+            write_number(&mut snapshot, root);
+        }
+        Ok(snapshot)
+    }
+
+    /// Creates the objects of a snapshot created by [`Heap::serialize`] in this
+    /// heap and returns the roots, each of which owns one reference.
+    ///
+    /// Functions in the snapshot have to belong to `byte_code`.
+    pub fn deserialize(
+        &mut self,
+        snapshot: &[u8],
+        byte_code: &ByteCode,
+    ) -> Result<Vec<InlineObject>, String> {
+        let mut nodes = vec![];
+        let result = Deserializer {
+            snapshot,
+            offset: 0,
+            heap: self,
+            instruction_count: byte_code.instructions.len(),
+        }
+        .deserialize(&mut nodes);
+
+        // Each node owns one reference, so the roots stay alive.
+        if let Ok(roots) = &result {
+            for root in roots {
+                root.dup(self);
+            }
+        }
+        for node in nodes {
+            node.drop(self);
+        }
+        result
+    }
+}
+
+#[derive(Default)]
+struct Serializer {
+    nodes: Vec<u8>,
+    node_count: usize,
+    /// Maps the raw words of objects to their node indices.
+    indices: FxHashMap<u64, usize>,
+    in_progress: FxHashSet<u64>,
+}
+impl Serializer {
+    fn visit(&mut self, object: InlineObject) -> Result<usize, String> {
+        let key = object.raw_word().get();
+        if let Some(index) = self.indices.get(&key) {
+            return Ok(*index);
+        }
+        if !self.in_progress.insert(key) {
+            return Err("The objects contain a reference cycle.".to_string());
+        }
+
+        let mut node = vec![];
+        match Data::from(object) {
+            Data::Int(int) => {
+                node.push(KIND_INT);
+                write_bytes(&mut node, &int.get().to_signed_bytes_le());
+            }
+            Data::Text(text) => {
+                node.push(KIND_TEXT);
+                write_bytes(&mut node, text.get().as_bytes());
+            }
+            Data::Tag(tag) => {
+                let symbol = self.visit(tag.symbol().into())?;
+                let value = tag.value().map(|value| self.visit(value)).transpose()?;
+                node.push(KIND_TAG);
+                write_number(&mut node, symbol);
+                match value {
+                    Some(value) => {
+                        node.push(1);
+                        write_number(&mut node, value);
+                    }
+                    None => node.push(0),
+                }
+            }
+            Data::List(list) => {
+                let items = self.visit_all(list.items())?;
+                node.push(KIND_LIST);
+                write_number(&mut node, items.len());
+                for item in items {
+                    write_number(&mut node, item);
+                }
+            }
+            Data::Struct(struct_) => {
+                let mut fields = vec![];
+                for (_, key, value) in struct_.iter() {
+                    fields.push((self.visit(key)?, self.visit(value)?));
+                }
+                node.push(KIND_STRUCT);
+                write_number(&mut node, fields.len());
+                for (key, value) in fields {
+                    write_number(&mut node, key);
+                    write_number(&mut node, value);
+                }
+            }
+            Data::Function(function) => {
+                let captured = self.visit_all(function.captured())?;
+                node.push(KIND_FUNCTION);
+                write_number(&mut node, *function.body());
+                write_number(&mut node, function.argument_count());
+                write_number(&mut node, captured.len());
+                for captured in captured {
+                    write_number(&mut node, captured);
+                }
+            }
+            Data::Builtin(builtin) => {
+                let index = BuiltinFunction::VARIANTS
+                    .iter()
+                    .position(|it| *it == builtin.get())
+                    .unwrap();
+                node.push(KIND_BUILTIN);
+                write_number(&mut node, index);
+            }
+            Data::Bytes(bytes) => {
+                node.push(KIND_BYTES);
+                write_bytes(&mut node, bytes.get());
+            }
+            Data::Fraction(fraction) => {
+                node.push(KIND_FRACTION);
+                write_bytes(&mut node, &fraction.get().numer().to_signed_bytes_le());
+                write_bytes(&mut node, &fraction.get().denom().to_signed_bytes_le());
+            }
+            Data::Map(map) => {
+                let mut entries = vec![];
+                for (key, value) in map.entries() {
+                    entries.push((self.visit(key)?, self.visit(value)?));
+                }
+                node.push(KIND_MAP);
+                write_number(&mut node, entries.len());
+                for (key, value) in entries {
+                    write_number(&mut node, key);
+                    write_number(&mut node, value);
+                }
+            }
+            Data::Set(set) => {
+                let items = self.visit_all(&set.items())?;
+                node.push(KIND_SET);
+                write_number(&mut node, items.len());
+                for item in items {
+                    write_number(&mut node, item);
+                }
+            }
+            Data::HirId(_) => return Err("HIR IDs can't be serialized.".to_string()),
+            Data::Handle(_) => return Err("Handles can't be serialized.".to_string()),
+            Data::Cell(_) => return Err("Cells can't be serialized.".to_string()),
+            Data::TextBuilder(_) => {
+                return Err("Text builders can't be serialized.".to_string());
+            }
+        }
+
+        self.in_progress.remove(&key);
+        let index = self.node_count;
+        self.nodes.extend(node);
+        self.node_count += 1;
+        self.indices.insert(key, index);
+        Ok(index)
+    }
+    fn visit_all(&mut self, objects: &[InlineObject]) -> Result<Vec<usize>, String> {
+        objects.iter().map(|it| self.visit(*it)).collect()
+    }
+}
+
+fn write_number(output: &mut Vec<u8>, number: usize) {
+    let mut number = number as u64;
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    write_number(output, bytes.len());
+    output.extend_from_slice(bytes);
+}
+
+struct Deserializer<'s, 'h> {
+    snapshot: &'s [u8],
+    offset: usize,
+    heap: &'h mut Heap,
+    instruction_count: usize,
+}
+impl<'s, 'h> Deserializer<'s, 'h> {
+    /// Pushes the created nodes to `nodes` so that they can be dropped even
+    /// if the snapshot is invalid.
+    fn deserialize(mut self, nodes: &mut Vec<InlineObject>) -> Result<Vec<InlineObject>, String> {
+        if self.read_slice(MAGIC.len())? != MAGIC {
+            return Err("This is not a heap snapshot.".to_string());
+        }
+        let version = self.read_byte()?;
+        if version != VERSION {
+            return Err(format!(
+                "The snapshot has version {version}, but only version {VERSION} is supported.",
+            ));
+        }
+
+        let node_count = self.read_number()?;
+        for _ in 0..node_count {
+            let node = self.read_node(nodes)?;
+            nodes.push(node);
+        }
+
+        let root_count = self.read_number()?;
+        let roots = (0..root_count)
+            .map(|_| self.read_reference(nodes))
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.offset != self.snapshot.len() {
+            return Err("The snapshot contains trailing bytes.".to_string());
+        }
+        Ok(roots)
+    }
+
+    fn read_node(&mut self, nodes: &[InlineObject]) -> Result<InlineObject, String> {
+        let kind = self.read_byte()?;
+        let node = match kind {
+            KIND_INT => {
+                let bytes = self.read_bytes()?;
+                Int::create_from_bigint(self.heap, true, BigInt::from_signed_bytes_le(bytes)).into()
+            }
+            KIND_TEXT => {
+                let text = std::str::from_utf8(self.read_bytes()?)
+                    .map_err(|_| "A text is not valid UTF-8.".to_string())?;
+                self.heap
+                    .default_symbols()
+                    .get(text)
+                    .unwrap_or_else(|| Text::create(self.heap, true, text))
+                    .into()
+            }
+            KIND_TAG => {
+                let symbol = Text::try_from(self.read_reference(nodes)?)
+                    .map_err(|_| "The symbol of a tag is not a text.".to_string())?;
+                let value = match self.read_byte()? {
+                    0 => None,
+                    1 => Some(self.read_reference(nodes)?),
+                    _ => return Err("Invalid tag value marker.".to_string()),
+                };
+                symbol.dup();
+                if let Some(value) = value {
+                    value.dup(self.heap);
+                }
+                Tag::create_with_value_option(self.heap, true, symbol, value).into()
+            }
+            KIND_LIST => {
+                let items = self.read_references(nodes)?;
+                self.dup_all(&items);
+                List::create(self.heap, true, &items).into()
+            }
+            KIND_STRUCT => {
+                let length = self.read_number()?;
+                let mut fields = FxHashMap::default();
+                for _ in 0..length {
+                    let key = self.read_reference(nodes)?;
+                    let value = self.read_reference(nodes)?;
+                    if fields.insert(key, value).is_some() {
+                        return Err("A struct contains a key multiple times.".to_string());
+                    }
+                }
+                for (key, value) in &fields {
+                    key.dup(self.heap);
+                    value.dup(self.heap);
+                }
+                Struct::create(self.heap, true, &fields).into()
+            }
+            KIND_FUNCTION => {
+                let body = self.read_number()?;
+                if body >= self.instruction_count {
+                    return Err(format!(
+                        "A function's body starts at instruction {body}, but the byte code only has {} instructions.",
+                        self.instruction_count,
+                    ));
+                }
+                let argument_count = self.read_number()?;
+                if argument_count > HeapFunction::MAX_ARGUMENT_COUNT {
+                    return Err("A function accepts too many arguments.".to_string());
+                }
+                let captured = self.read_references(nodes)?;
+                if captured.len() > HeapFunction::MAX_CAPTURED_LEN {
+                    return Err("A function captures too many objects.".to_string());
+                }
+                self.dup_all(&captured);
+                Function::create(self.heap, true, &captured, argument_count, body.into()).into()
+            }
+            KIND_BUILTIN => {
+                let index = self.read_number()?;
+                let builtin = BuiltinFunction::VARIANTS
+                    .get(index)
+                    .ok_or_else(|| format!("There's no builtin function with index {index}."))?;
+                Builtin::create(*builtin).into()
+            }
+            KIND_BYTES => {
+                let bytes = self.read_bytes()?;
+                Bytes::create(self.heap, true, bytes).into()
+            }
+            KIND_FRACTION => {
+                let numerator = BigInt::from_signed_bytes_le(self.read_bytes()?);
+                let denominator = BigInt::from_signed_bytes_le(self.read_bytes()?);
+                if denominator.is_zero() {
+                    return Err("A fraction has a denominator of zero.".to_string());
+                }
+                let value = BigRational::new(numerator, denominator);
+                Number::create(self.heap, true, value).into()
+            }
+            KIND_MAP => {
+                let length = self.read_number()?;
+                let mut entries = vec![];
+                for _ in 0..length {
+                    let key = self.read_reference(nodes)?;
+                    let value = self.read_reference(nodes)?;
+                    entries.push((key, value));
+                }
+                for (key, value) in &entries {
+                    key.dup(self.heap);
+                    value.dup(self.heap);
+                }
+                Map::create(self.heap, entries).into()
+            }
+            KIND_SET => {
+                let items = self.read_references(nodes)?;
+                self.dup_all(&items);
+                Set::create(self.heap, items).into()
+            }
+            _ => return Err(format!("Invalid node kind: {kind}")),
+        };
+        Ok(node)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        Ok(self.read_slice(1)?[0])
+    }
+    fn read_slice(&mut self, length: usize) -> Result<&'s [u8], String> {
+        let end = self
+            .offset
+            .checked_add(length)
+            .filter(|end| *end <= self.snapshot.len())
+            .ok_or_else(|| "The snapshot ends unexpectedly.".to_string())?;
+        let slice = &self.snapshot[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+    fn read_bytes(&mut self) -> Result<&'s [u8], String> {
+        let length = self.read_number()?;
+        self.read_slice(length)
+    }
+    fn read_number(&mut self) -> Result<usize, String> {
+        let mut number = 0u64;
+        for shift in (0..u64::BITS).step_by(7) {
+            let byte = self.read_byte()?;
+            number |= u64::from(byte & 0x7F)
+                .checked_shl(shift)
+                .filter(|it| it >> shift == u64::from(byte & 0x7F))
+                .ok_or_else(|| "A number is too large.".to_string())?;
+            if byte & 0x80 == 0 {
+                return usize::try_from(number).map_err(|_| "A number is too large.".to_string());
+            }
+        }
+        Err("A number is too large.".to_string())
+    }
+    fn read_reference(&mut self, nodes: &[InlineObject]) -> Result<InlineObject, String> {
+        let index = self.read_number()?;
+        nodes
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("Node {index} is referenced before it's defined."))
+    }
+    fn read_references(&mut self, nodes: &[InlineObject]) -> Result<Vec<InlineObject>, String> {
+        let length = self.read_number()?;
+        (0..length).map(|_| self.read_reference(nodes)).collect()
+    }
+    fn dup_all(&mut self, objects: &[InlineObject]) {
+        for object in objects {
+            object.dup(self.heap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        byte_code::{ByteCode, Instruction},
+        heap::{
+            Builtin, Data, Function, Heap, HirId, InlineObject, Int, List, Number, Struct, Tag,
+            Text,
+        },
+        origins::Origins,
+    };
+    use candy_frontend::{
+        builtin_functions::BuiltinFunction,
+        hir,
+        module::{Module, ModuleKind, Package},
+    };
+    use num_rational::BigRational;
+    use rustc_hash::FxHashMap;
+
+    #[test]
+    fn round_trips_objects() {
+        let byte_code = create_byte_code();
+        let mut heap = Heap::default();
+        let text: InlineObject = Text::create(&mut heap, true, "Hello").into();
+        text.dup_by(&mut heap, 2);
+        let big_int = Int::create(&mut heap, true, i128::MAX).into();
+        let ok = heap.default_symbols().ok;
+        let tag = Tag::create_with_value(&mut heap, true, ok, text);
+        let function = Function::create(&mut heap, true, &[text], 1, 1.into());
+        let list = List::create(
+            &mut heap,
+            true,
+            &[
+                big_int,
+                Builtin::create(BuiltinFunction::IntAdd).into(),
+                function.into(),
+            ],
+        );
+        let fields = FxHashMap::from_iter([(tag.into(), list.into())]);
+        let struct_: InlineObject = Struct::create(&mut heap, true, &fields).into();
+
+        let snapshot = Heap::serialize(&[struct_, text]).unwrap();
+        let mut other_heap = Heap::default();
+        let object_count = other_heap.objects().len();
+        let roots = other_heap.deserialize(&snapshot, &byte_code).unwrap();
+
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0], struct_);
+        assert_eq!(roots[1], text);
+        // The text is stored only once.
+        assert_eq!(other_heap.objects().len(), object_count + 6);
+        let Data::Struct(deserialized) = Data::from(roots[0]) else {
+            panic!("Expected a struct.");
+        };
+        let Data::List(list) = Data::from(deserialized.values()[0]) else {
+            panic!("Expected a list.");
+        };
+        let Data::Function(function) = Data::from(list.get(2)) else {
+            panic!("Expected a function.");
+        };
+        assert_eq!(function.captured(), &[roots[1]]);
+        assert_eq!(*function.body(), 1);
+
+        for root in roots {
+            root.drop(&mut other_heap);
+        }
+        assert_eq!(other_heap.objects().len(), object_count);
+    }
+
+    #[test]
+    fn round_trips_fractions() {
+        let byte_code = create_byte_code();
+        let mut heap = Heap::default();
+        let value = BigRational::new((-2).into(), 6.into());
+        let fraction: InlineObject = Number::create(&mut heap, true, value.clone()).into();
+        assert!(matches!(Data::from(fraction), Data::Fraction(_)));
+
+        let snapshot = Heap::serialize(&[fraction]).unwrap();
+        let mut other_heap = Heap::default();
+        let roots = other_heap.deserialize(&snapshot, &byte_code).unwrap();
+        let number = Number::try_from(roots[0]).unwrap();
+        assert_eq!(*number.get(), value);
+        assert_eq!(number.numerator(&mut other_heap).try_get::<i64>(), Some(-1));
+        assert_eq!(
+            number.denominator(&mut other_heap).try_get::<i64>(),
+            Some(3)
+        );
+        roots[0].drop(&mut other_heap);
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        let byte_code = create_byte_code();
+        let mut heap = Heap::default();
+        let function: InlineObject = Function::create(&mut heap, true, &[], 0, 5.into()).into();
+        let snapshot = Heap::serialize(&[function]).unwrap();
+
+        let mut other_heap = Heap::default();
+        let object_count = other_heap.objects().len();
+        assert!(other_heap.deserialize(&snapshot, &byte_code).is_err());
+        assert!(other_heap
+            .deserialize(&snapshot[..snapshot.len() - 1], &byte_code)
+            .is_err());
+        assert!(other_heap.deserialize(b"CANDYHEAP", &byte_code).is_err());
+        assert_eq!(other_heap.objects().len(), object_count);
+    }
+
+    fn create_byte_code() -> ByteCode {
+        let module = Module::new(Package::User("/".into()), vec![], ModuleKind::Code);
+        let mut constant_heap = Heap::default();
+        let module_function = Function::create(&mut constant_heap, false, &[], 0, 0.into());
+        let responsible_module = HirId::create(
+            &mut constant_heap,
+            false,
+            hir::Id::new(module.clone(), vec![]),
+        );
+        ByteCode {
+            module,
+            constant_heap,
+            instructions: vec![Instruction::Return, Instruction::Return],
+            origins: Origins::default(),
+            module_function,
+            responsible_module,
+            frame_cleanups: FxHashMap::default(),
+        }
+    }
+}
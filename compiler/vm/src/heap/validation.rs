@@ -0,0 +1,174 @@
+//! Checks of the heap's invariants for detecting memory corruption, e.g.,
+//! caused by a bug in reference counting. These look at every object, so
+//! they're too slow to run all the time. See
+//! [`Vm::with_paranoid_checks`](crate::Vm::with_paranoid_checks) for running
+//! them regularly.
+
+use super::{
+    Data, DataDiscriminants, Heap, HeapData, HeapObjectTrait, InlineData, InlineObject,
+    ObjectInHeap,
+};
+use crate::handle_id::HandleId;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::fmt::Display;
+
+impl Heap {
+    /// Panics if the heap is inconsistent.
+    ///
+    /// `external_references` are references to objects in this heap that don't
+    /// come from the heap itself, e.g., the arguments of a handle call. Each of
+    /// them must own one reference count. Other references that aren't passed
+    /// here (e.g., held by a tracer) are fine, but can hide reference counts
+    /// that are too low.
+    ///
+    /// Objects in this heap may reference objects in `other_heaps` that aren't
+    /// reference counted, e.g., constants of the byte code.
+    pub fn validate(
+        &self,
+        other_heaps: &[&Self],
+        external_references: impl IntoIterator<Item = InlineObject>,
+    ) {
+        let mut validation = Validation {
+            heap: self,
+            other_heaps,
+            object_references: FxHashMap::default(),
+            handle_references: FxHashMap::default(),
+            problems: vec![],
+        };
+
+        let mut allocated_bytes = 0;
+        let mut objects_by_kind = FxHashMap::<DataDiscriminants, usize>::default();
+        for object in &self.objects {
+            if !object.has_valid_header() {
+                validation.problems.push(format!(
+                    "{object:p} has an invalid header: {:#066b}",
+                    object.header_word(),
+                ));
+                continue;
+            }
+            if object.reference_count() == Some(0) {
+                validation
+                    .problems
+                    .push(format!("{object:p} has no references, but wasn't freed."));
+            }
+
+            let data = HeapData::from(**object);
+            allocated_bytes += data.total_size();
+            *objects_by_kind
+                .entry(DataDiscriminants::from(Data::from(**object)))
+                .or_default() += 1;
+            for child in data.children() {
+                validation.visit_reference(child, format_args!("{object:p}"));
+            }
+        }
+        for reference in external_references {
+            validation.visit_reference(reference, "an external reference");
+        }
+
+        if allocated_bytes != self.statistics.allocated_bytes {
+            validation.problems.push(format!(
+                "The objects use {allocated_bytes} bytes, but the statistics say {} bytes.",
+                self.statistics.allocated_bytes,
+            ));
+        }
+        if objects_by_kind != self.statistics.objects_by_kind {
+            validation.problems.push(format!(
+                "The number of objects by kind ({objects_by_kind:?}) doesn't match the statistics ({:?}).",
+                self.statistics.objects_by_kind,
+            ));
+        }
+        for (object, references) in &validation.object_references {
+            if let Some(reference_count) = object.reference_count()
+                && reference_count < *references
+            {
+                validation.problems.push(format!(
+                    "{object:p} is referenced {references} times, but its reference count is only {reference_count}.",
+                ));
+            }
+        }
+        for (handle_id, references) in &validation.handle_references {
+            match self.handle_refcounts.get(handle_id) {
+                Some(reference_count) if reference_count < references => {
+                    validation.problems.push(format!(
+                        "{handle_id:?} is referenced {references} times, but its reference count is only {reference_count}.",
+                    ));
+                }
+                Some(_) => {}
+                None => validation
+                    .problems
+                    .push(format!("{handle_id:?} is referenced, but doesn't exist.")),
+            }
+        }
+
+        assert!(
+            validation.problems.is_empty(),
+            "The heap is corrupted:\n{}",
+            validation.problems.iter().sorted().join("\n"),
+        );
+    }
+}
+
+struct Validation<'h> {
+    heap: &'h Heap,
+    other_heaps: &'h [&'h Heap],
+    object_references: FxHashMap<ObjectInHeap, usize>,
+    handle_references: FxHashMap<HandleId, usize>,
+    problems: Vec<String>,
+}
+impl<'h> Validation<'h> {
+    fn visit_reference(&mut self, reference: InlineObject, referenced_by: impl Display) {
+        if let InlineData::Handle(handle) = InlineData::from(reference) {
+            *self
+                .handle_references
+                .entry(handle.handle_id())
+                .or_default() += 1;
+            return;
+        }
+        let Some(object) = Heap::referenced_object(reference) else {
+            return;
+        };
+        let object = ObjectInHeap(object);
+
+        if self.heap.objects.contains(&object) {
+            *self.object_references.entry(object).or_default() += 1;
+        } else if self
+            .other_heaps
+            .iter()
+            .any(|heap| heap.objects.contains(&object))
+        {
+            if object.reference_count().is_some() {
+                self.problems.push(format!(
+                    "{referenced_by} references {object:p}, which is reference counted but lives in another heap.",
+                ));
+            }
+        } else {
+            self.problems.push(format!(
+                "{referenced_by} references {object:p}, which isn't allocated.",
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, List, Text};
+
+    #[test]
+    fn accepts_consistent_heap() {
+        let mut heap = Heap::default();
+        let text: InlineObject = Text::create(&mut heap, true, "Hi").into();
+        let list = List::create(&mut heap, true, &[text, text]);
+        text.dup(&mut heap);
+        heap.validate(&[], [list.into()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "reference count is only 1")]
+    fn detects_missing_reference_count() {
+        let mut heap = Heap::default();
+        let text: InlineObject = Text::create(&mut heap, true, "Hi").into();
+        let list = List::create(&mut heap, true, &[text, text]);
+        heap.validate(&[], [list.into()]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Rust functions that Candy code can call like handles.
+//!
+//! Embedders register them using [`Vm::register_handle`]. Unlike other
+//! handles, the VM calls them itself, so they work with any [`Environment`]
+//! and the environment never sees these calls.
+//!
+//! [`Environment`]: crate::environment::Environment
+//! [`Vm::register_handle`]: crate::Vm::register_handle
+
+use crate::{
+    heap::{Heap, InlineObject},
+    instructions::InstructionResult,
+    vm::{CallHandle, MachineState, Panic, PanicKind},
+};
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// Receives the arguments as borrowed references and returns an owned value.
+/// Returning an error makes the calling Candy code panic with that reason.
+pub type HostFunction = Box<dyn FnMut(&mut Heap, &[InlineObject]) -> Result<InlineObject, String>>;
+
+pub struct RegisteredHostFunction {
+    pub name: String,
+    pub argument_count: usize,
+    pub function: HostFunction,
+}
+impl RegisteredHostFunction {
+    pub fn call(
+        &mut self,
+        heap: &mut Heap,
+        state: &mut MachineState,
+        call: &CallHandle,
+    ) -> InstructionResult {
+        let result = if call.arguments.len() == self.argument_count {
+            // The function may have left the heap in an inconsistent state,
+            // but taking down the whole host application would be worse.
+            panic::catch_unwind(AssertUnwindSafe(|| (self.function)(heap, &call.arguments)))
+                .unwrap_or_else(|payload| {
+                    Err(format!(
+                        "The host function `{}` panicked: {}",
+                        self.name,
+                        panic_message(payload.as_ref()),
+                    ))
+                })
+        } else {
+            Err(format!(
+                "The host function `{}` expects {} arguments, but it was called with {}.",
+                self.name,
+                self.argument_count,
+                call.arguments.len(),
+            ))
+        };
+
+        call.handle.drop(heap);
+        for argument in &call.arguments {
+            argument.drop(heap);
+        }
+
+        match result {
+            Ok(return_value) => {
+                state.data_stack.push(return_value);
+                InstructionResult::Done
+            }
+            Err(reason) => InstructionResult::Panic(Panic {
+                reason,
+                responsible: call.responsible.get().clone(),
+                kind: PanicKind::Code,
+            }),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown reason")
+}
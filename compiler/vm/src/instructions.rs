@@ -2,7 +2,7 @@ use crate::{
     byte_code::{CreateFunction, IfElse, Instruction},
     heap::{Data, Function, Heap, HirId, InlineObject, List, Struct, Tag, Text},
     tracer::Tracer,
-    vm::{CallHandle, MachineState, Panic},
+    vm::{CallHandle, MachineState, Panic, PanicKind},
 };
 use itertools::Itertools;
 use tracing::trace;
@@ -52,30 +52,39 @@ impl MachineState {
         }
 
         match instruction {
-            Instruction::CreateTag { symbol } => {
+            Instruction::CreateTag {
+                symbol,
+                is_frame_allocated,
+            } => {
                 let value = self.pop_from_data_stack();
-                let tag = Tag::create_with_value(heap, true, *symbol, value);
+                let tag = Tag::create_with_value(heap, !is_frame_allocated, *symbol, value);
                 self.push_to_data_stack(tag);
                 InstructionResult::Done
             }
-            Instruction::CreateList { num_items } => {
+            Instruction::CreateList {
+                num_items,
+                is_frame_allocated,
+            } => {
                 let mut item_addresses = vec![];
                 for _ in 0..*num_items {
                     item_addresses.push(self.pop_from_data_stack());
                 }
                 let items = item_addresses.into_iter().rev().collect_vec();
-                let list = List::create(heap, true, &items);
+                let list = List::create(heap, !is_frame_allocated, &items);
                 self.push_to_data_stack(list);
                 InstructionResult::Done
             }
-            Instruction::CreateStruct { num_fields } => {
+            Instruction::CreateStruct {
+                num_fields,
+                is_frame_allocated,
+            } => {
                 // PERF: Avoid collecting keys and values into a `Vec` before creating the `HashMap`
                 let mut key_value_addresses = vec![];
                 for _ in 0..(2 * num_fields) {
                     key_value_addresses.push(self.pop_from_data_stack());
                 }
                 let entries = key_value_addresses.into_iter().rev().tuples().collect();
-                let struct_ = Struct::create(heap, true, &entries);
+                let struct_ = Struct::create(heap, !is_frame_allocated, &entries);
                 self.push_to_data_stack(struct_);
                 InstructionResult::Done
             }
@@ -115,6 +124,12 @@ impl MachineState {
                 self.pop_from_data_stack().drop(heap);
                 InstructionResult::Done
             }
+            Instruction::FreeFrameObjects(offsets) => {
+                for offset in offsets.iter() {
+                    heap.free_frame_object(self.get_from_data_stack(*offset));
+                }
+                InstructionResult::Done
+            }
             Instruction::Call { num_args } => {
                 let responsible = HirId::new_unchecked(self.pop_from_data_stack());
                 let mut arguments = (0..*num_args)
@@ -153,6 +168,7 @@ impl MachineState {
                 then_captured,
                 else_target,
                 else_captured,
+                num_locals_to_pop,
             }) => {
                 let responsible = self.pop_from_data_stack();
                 let condition = Tag::value_into_bool_unchecked(self.pop_from_data_stack(), heap);
@@ -162,17 +178,30 @@ impl MachineState {
                     (*else_target, else_captured)
                 };
 
+                if let Some(num_locals_to_pop) = num_locals_to_pop {
+                    // Like a tail call: We collect the captured values before leaving
+                    // our frame and then pretend we are our caller.
+                    let captured = captured
+                        .iter()
+                        .map(|offset| self.get_from_data_stack(*offset - 2))
+                        .collect_vec();
+                    self.pop_multiple_from_data_stack(*num_locals_to_pop);
+                    self.data_stack.extend(captured);
+                    self.next_instruction = self.call_stack.pop();
+                } else {
+                    // Initially, we need to adjust the offset because we already
+                    // popped two values from the data stack. Afterwards, increment
+                    // it for each value.
+                    for (index, offset) in captured.iter().enumerate() {
+                        let captured = self.get_from_data_stack(*offset - 2 + index);
+                        self.data_stack.push(captured);
+                    }
+                }
+
                 if let Some(next_instruction) = self.next_instruction {
                     self.call_stack.push(next_instruction);
                 }
 
-                // Initially, we need to adjust the offset because we already
-                // popped two values from the data stack. Afterwards, increment
-                // it for each value.
-                for (index, offset) in captured.iter().enumerate() {
-                    let captured = self.get_from_data_stack(*offset - 2 + index);
-                    self.data_stack.push(captured);
-                }
                 self.push_to_data_stack(responsible);
                 self.next_instruction = Some(target);
                 InstructionResult::Done
@@ -189,10 +218,25 @@ impl MachineState {
                     panic!("We should never generate byte code where the reason is not a text.");
                 };
 
-                InstructionResult::Panic(Panic {
+                let panic = Panic {
                     reason: reason.get().to_string(),
                     responsible: responsible.get().clone(),
-                })
+                    kind: PanicKind::Code,
+                };
+                // The panic may be caught by a `try`, so the program can continue.
+                reason.drop(heap);
+                responsible.drop(heap);
+                InstructionResult::Panic(panic)
+            }
+            Instruction::CompleteTry => {
+                let try_frame = self.try_frames.pop().unwrap();
+                debug_assert_eq!(self.data_stack.len(), try_frame.data_stack_len + 1);
+
+                let return_value = self.pop_from_data_stack();
+                let result = Tag::create_result(heap, true, Ok(return_value));
+                self.push_to_data_stack(result);
+                self.next_instruction = try_frame.return_to;
+                InstructionResult::Done
             }
             Instruction::TraceCallStarts { num_args } => {
                 let responsible = HirId::new_unchecked(self.pop_from_data_stack());
@@ -205,6 +249,7 @@ impl MachineState {
 
                 args.reverse();
                 tracer.call_started(heap, call_site, callee, args, responsible);
+                self.traced_call_depth += 1;
                 InstructionResult::Done
             }
             Instruction::TraceCallEnds { has_return_value } => {
@@ -214,6 +259,7 @@ impl MachineState {
                     None
                 };
                 tracer.call_ended(heap, return_value);
+                self.traced_call_depth -= 1;
                 InstructionResult::Done
             }
             Instruction::TraceTailCall { num_args } => {
@@ -245,6 +291,36 @@ impl MachineState {
                 tracer.found_fuzzable_function(heap, definition, function);
                 InstructionResult::Done
             }
+            Instruction::PushFromStackAndDup { offset, amount } => {
+                self.get_from_data_stack(*offset)
+                    .dup_by(heap, *amount as usize);
+                InstructionResult::Done
+            }
+            Instruction::PushFromStackAndDrop(offset) => {
+                self.get_from_data_stack(*offset).drop(heap);
+                InstructionResult::Done
+            }
+            Instruction::PushFromStackAndCall {
+                responsible,
+                num_args,
+            } => {
+                let responsible = HirId::new_unchecked(self.get_from_data_stack(*responsible));
+                let mut arguments = (0..*num_args)
+                    .map(|_| self.pop_from_data_stack())
+                    .collect_vec();
+                // PERF: Build the reverse list in place.
+                arguments.reverse();
+                let callee = self.pop_from_data_stack();
+
+                self.call(heap, callee, &arguments, responsible)
+            }
+            Instruction::PopMultipleBelowTopAndReturn(n) => {
+                let top = self.pop_from_data_stack();
+                self.pop_multiple_from_data_stack(*n);
+                self.push_to_data_stack(top);
+                self.next_instruction = self.call_stack.pop();
+                InstructionResult::Done
+            }
         }
     }
 
@@ -0,0 +1,119 @@
+//! Conversion between JSON values and Candy values.
+//!
+//! | JSON              | Candy                                      |
+//! |-------------------|--------------------------------------------|
+//! | `null`            | `Nothing`                                  |
+//! | `true` / `false`  | `True` / `False`                           |
+//! | integer number    | int                                        |
+//! | string            | text                                       |
+//! | array             | list                                       |
+//! | object            | struct                                     |
+//!
+//! Object keys starting with an uppercase letter become tags, all other keys
+//! become texts. When converting back to JSON, other tags without a value
+//! become strings, and tags with a value become objects with a single key.
+//! Functions, builtins, handles, and HIR IDs can't be converted to JSON.
+
+use crate::heap::{Data, Heap, InlineObject, Int, List, Struct, Tag, Text};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use serde_json::{Map, Value};
+
+pub fn from_json(heap: &mut Heap, value: &Value) -> Result<InlineObject, String> {
+    let object = match value {
+        Value::Null => Tag::create_nothing(heap).into(),
+        Value::Bool(value) => Tag::create_bool(heap, *value).into(),
+        Value::Number(number) => {
+            if let Some(int) = number.as_i64() {
+                Int::create(heap, true, int).into()
+            } else if let Some(int) = number.as_u64() {
+                Int::create(heap, true, int).into()
+            } else {
+                return Err(format!("Only integers are supported, but got `{number}`."));
+            }
+        }
+        Value::String(text) => Text::create(heap, true, text).into(),
+        Value::Array(items) => {
+            let items: Vec<_> = items
+                .iter()
+                .map(|item| from_json(heap, item))
+                .try_collect()?;
+            List::create(heap, true, &items).into()
+        }
+        Value::Object(fields) => {
+            let fields: FxHashMap<_, _> = fields
+                .iter()
+                .map(|(key, value)| {
+                    Ok::<_, String>((key_from_json(heap, key), from_json(heap, value)?))
+                })
+                .try_collect()?;
+            Struct::create(heap, true, &fields).into()
+        }
+    };
+    Ok(object)
+}
+fn key_from_json(heap: &mut Heap, key: &str) -> InlineObject {
+    if key.starts_with(|c: char| c.is_ascii_uppercase()) {
+        let symbol = heap
+            .default_symbols()
+            .get(key)
+            .unwrap_or_else(|| Text::create(heap, true, key));
+        Tag::create(symbol).into()
+    } else {
+        Text::create(heap, true, key).into()
+    }
+}
+
+pub fn to_json(object: InlineObject) -> Result<Value, String> {
+    let value = match Data::from(object) {
+        Data::Int(int) => {
+            let int = int.get();
+            i64::try_from(&*int)
+                .map(Value::from)
+                .or_else(|_| u64::try_from(&*int).map(Value::from))
+                .map_err(|_| format!("The int {int} is too large for JSON."))?
+        }
+        Data::Tag(tag) => {
+            let symbol = tag.symbol().get();
+            match (symbol, tag.value()) {
+                ("Nothing", None) => Value::Null,
+                ("True", None) => Value::Bool(true),
+                ("False", None) => Value::Bool(false),
+                (symbol, None) => Value::String(symbol.to_string()),
+                (symbol, Some(value)) => {
+                    Value::Object(Map::from_iter([(symbol.to_string(), to_json(value)?)]))
+                }
+            }
+        }
+        Data::Text(text) => Value::String(text.get().to_string()),
+        Data::List(list) => Value::Array(list.items().iter().map(|it| to_json(*it)).try_collect()?),
+        Data::Struct(struct_) => Value::Object(
+            struct_
+                .iter()
+                .map(|(_, key, value)| Ok::<_, String>((key_to_json(key)?, to_json(value)?)))
+                .try_collect()?,
+        ),
+        Data::HirId(_)
+        | Data::Function(_)
+        | Data::Builtin(_)
+        | Data::Handle(_)
+        | Data::Bytes(_)
+        | Data::Cell(_)
+        | Data::Fraction(_)
+        | Data::Map(_)
+        | Data::Set(_)
+        | Data::TextBuilder(_) => {
+            return Err(format!("{object:?} can't be converted to JSON."));
+        }
+    };
+    Ok(value)
+}
+fn key_to_json(key: InlineObject) -> Result<String, String> {
+    match Data::from(key) {
+        Data::Tag(tag) if !tag.has_value() => Ok(tag.symbol().get().to_string()),
+        Data::Text(text) => Ok(text.get().to_string()),
+        _ => Err(format!(
+            "Only tags without values and texts are supported as struct keys in JSON, but got {key:?}.",
+        )),
+    }
+}
@@ -0,0 +1,216 @@
+//! Starting programs the same way everywhere.
+//!
+//! The CLI, the language server's analyzer and debugger, and tests all have to
+//! run a module's top-level code, call its `main` function with an
+//! environment, or call another exported function. A [`Launcher`] creates VMs
+//! for these cases and applies the same [`LaunchOptions`] to each of them.
+//!
+//! Callers hook into the running program through the [`Tracer`] they pass to
+//! [`Launcher::new`] and the [`Environment`] that handles calls to handles.
+
+use crate::{
+    byte_code::ByteCode,
+    environment::{DefaultEnvironment, Environment},
+    heap::{Data, Function, Heap, HirId, InlineObject, Struct, Tag, Text},
+    tracer::Tracer,
+    Vm, VmFinished,
+};
+use candy_frontend::{hir, hir_to_mir::ExecutionTarget, utils::AdjustCasingOfFirstLetter};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display, Formatter},
+    num::NonZeroUsize,
+};
+
+/// Limits and checks applied to every VM created by a [`Launcher`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LaunchOptions {
+    /// See [`Vm::with_max_stack_depth`].
+    pub max_stack_depth: Option<usize>,
+    /// See [`Vm::with_profiler`].
+    pub profile_interval: Option<NonZeroUsize>,
+    /// See [`Vm::with_paranoid_checks`].
+    pub paranoid_interval: Option<NonZeroUsize>,
+}
+
+pub struct Launcher<B: Borrow<ByteCode>, T: Tracer> {
+    byte_code: B,
+    tracer: T,
+    options: LaunchOptions,
+}
+impl<B: Borrow<ByteCode>, T: Tracer> Launcher<B, T> {
+    pub const fn new(byte_code: B, tracer: T) -> Self {
+        Self {
+            byte_code,
+            tracer,
+            options: LaunchOptions {
+                max_stack_depth: None,
+                profile_interval: None,
+                paranoid_interval: None,
+            },
+        }
+    }
+    #[must_use]
+    pub const fn with_options(mut self, options: LaunchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Runs the module's top-level code. The VM's result is the struct of the
+    /// module's exports.
+    pub fn module(self, heap: &mut Heap) -> Vm<B, T> {
+        let vm = Vm::for_module(self.byte_code, heap, self.tracer);
+        self.options.apply(vm)
+    }
+    /// Runs the module and then calls the returned main function with
+    /// `environment`.
+    ///
+    /// This only supports byte code compiled for
+    /// [`ExecutionTarget::MainFunction`].
+    pub fn main_function(self, heap: &mut Heap, environment: Struct) -> Vm<B, T> {
+        let vm = Vm::for_main_function(self.byte_code, heap, environment, self.tracer);
+        self.options.apply(vm)
+    }
+    pub fn function(
+        self,
+        heap: &mut Heap,
+        function: Function,
+        arguments: &[InlineObject],
+        responsible: HirId,
+    ) -> Vm<B, T> {
+        let vm = Vm::for_function(
+            self.byte_code,
+            heap,
+            function,
+            arguments,
+            responsible,
+            self.tracer,
+        );
+        self.options.apply(vm)
+    }
+
+    /// Runs the byte code to completion. For
+    /// [`ExecutionTarget::MainFunction`], the main function receives a
+    /// [`DefaultEnvironment`] with the given command line `arguments`.
+    pub fn run_with_default_environment(
+        self,
+        heap: &mut Heap,
+        execution_target: &ExecutionTarget,
+        arguments: &[String],
+    ) -> VmFinished<T> {
+        match execution_target {
+            ExecutionTarget::Module(_) => self.module(heap).run_forever_without_handles(heap),
+            ExecutionTarget::MainFunction(_) => {
+                let (environment_object, mut environment) =
+                    DefaultEnvironment::new(heap, arguments);
+                self.main_function(heap, environment_object)
+                    .run_forever_with_environment(heap, &mut environment)
+            }
+        }
+    }
+
+    /// Runs the module and then calls its exported function `name` with
+    /// `arguments`. Both phases share the same tracer.
+    ///
+    /// If the module itself panics, that panic is the returned result.
+    ///
+    /// This only supports byte code compiled for [`ExecutionTarget::Module`].
+    pub fn run_exported_function(
+        self,
+        heap: &mut Heap,
+        name: &str,
+        arguments: &[InlineObject],
+        environment: &mut impl Environment,
+    ) -> Result<VmFinished<T>, LaunchError> {
+        let finished = self
+            .options
+            .apply(Vm::for_module(self.byte_code.borrow(), heap, self.tracer))
+            .run_forever_with_environment(heap, environment);
+        let exports = match finished.result {
+            Ok(exports) => Struct::try_from(exports).unwrap(),
+            Err(_) => return Ok(finished),
+        };
+
+        let function = find_exported_function(heap, exports, name)?;
+        if function.argument_count() != arguments.len() {
+            return Err(LaunchError::WrongNumberOfArguments {
+                name: name.to_string(),
+                expected: function.argument_count(),
+                actual: arguments.len(),
+            });
+        }
+
+        let responsible = HirId::create(heap, true, hir::Id::user());
+        let vm = Vm::for_function(
+            self.byte_code,
+            heap,
+            function,
+            arguments,
+            responsible,
+            finished.tracer,
+        );
+        Ok(self
+            .options
+            .apply(vm)
+            .run_forever_with_environment(heap, environment))
+    }
+}
+impl LaunchOptions {
+    fn apply<B: Borrow<ByteCode>, T: Tracer>(self, mut vm: Vm<B, T>) -> Vm<B, T> {
+        if let Some(max_stack_depth) = self.max_stack_depth {
+            vm = vm.with_max_stack_depth(max_stack_depth);
+        }
+        if let Some(interval) = self.profile_interval {
+            vm = vm.with_profiler(interval);
+        }
+        if let Some(interval) = self.paranoid_interval {
+            vm = vm.with_paranoid_checks(interval);
+        }
+        vm
+    }
+}
+
+fn find_exported_function(
+    heap: &mut Heap,
+    exports: Struct,
+    name: &str,
+) -> Result<Function, LaunchError> {
+    let key = Tag::create(Text::create(heap, true, &name.uppercase_first_letter()));
+    let value = exports.get(key);
+    InlineObject::from(key).drop(heap);
+    match value.map(Data::from) {
+        Some(Data::Function(function)) => Ok(function),
+        _ => Err(LaunchError::FunctionNotExported {
+            name: name.to_string(),
+        }),
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LaunchError {
+    FunctionNotExported {
+        name: String,
+    },
+    WrongNumberOfArguments {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+}
+impl Display for LaunchError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::FunctionNotExported { name } => {
+                write!(f, "The module doesn't export a function called `{name}`.")
+            }
+            Self::WrongNumberOfArguments {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "`{name}` expects {expected} arguments, but {actual} were given.",
+            ),
+        }
+    }
+}
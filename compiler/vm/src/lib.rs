@@ -28,18 +28,29 @@
 )]
 
 pub use builtin_functions::CAN_USE_STDOUT;
+pub use host_function::HostFunction;
 pub use instruction_pointer::InstructionPointer;
 pub use utils::PopulateInMemoryProviderFromFileSystem;
-pub use vm::{Panic, StateAfterRun, StateAfterRunForever, Vm, VmFinished, VmHandleCall};
+pub use vm::{
+    Panic, PanicKind, StateAfterRun, StateAfterRunForever, Vm, VmFinished, VmHandleCall, VmSnapshot,
+};
 
 mod builtin_functions;
 pub mod byte_code;
 pub mod environment;
 mod handle_id;
 pub mod heap;
+mod host_function;
 mod instruction_pointer;
 mod instructions;
+pub mod json;
+pub mod launcher;
 pub mod lir_to_byte_code;
+pub mod origins;
+mod paranoid;
+pub mod profiler;
+pub mod scheduling;
 pub mod tracer;
+mod unwinding;
 mod utils;
 mod vm;
@@ -1,7 +1,8 @@
 use crate::{
-    byte_code::{ByteCode, CreateFunction, IfElse, Instruction, StackOffset},
+    byte_code::{ByteCode, CreateFunction, FrameCleanup, IfElse, Instruction, StackOffset},
     heap::{Builtin, Function, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text},
     instruction_pointer::InstructionPointer,
+    origins::Origins,
 };
 use candy_frontend::{
     cst::CstDb,
@@ -14,9 +15,11 @@ use candy_frontend::{
     module::Module,
     tracing::TracingConfig,
     utils::HashMapExtension,
+    OptimizationConfig,
 };
 use extension_trait::extension_trait;
 use itertools::Itertools;
+use num_bigint::BigInt;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{mem, sync::Arc};
 
@@ -24,51 +27,83 @@ pub fn compile_byte_code<Db>(
     db: &Db,
     target: ExecutionTarget,
     tracing: TracingConfig,
+    optimization: OptimizationConfig,
 ) -> (ByteCode, Arc<FxHashSet<CompilerError>>)
 where
-    Db: CstDb + OptimizeLir,
+    Db: CstDb + OptimizeLir + ?Sized,
 {
     let module = target.module().clone();
     #[allow(clippy::map_unwrap_or)]
-    let (lir, errors) = db.optimized_lir(target, tracing).unwrap_or_else(|error| {
-        let mut constants = Constants::default();
-        let payload = CompilerErrorPayload::Module(error);
-        let reason_id = constants.push(payload.to_string());
-        let responsible_id = constants.push(hir::Id::user());
-
-        let mut body = Body::new(
-            FxHashSet::from_iter([hir::Id::new(module.clone(), vec![])]),
-            0,
-            0,
-        );
-        let reason_id = body.push(Expression::Constant(reason_id));
-        let responsible_id = body.push(Expression::Constant(responsible_id));
-        body.push(Expression::Panic {
-            reason: reason_id,
-            responsible: responsible_id,
-        });
+    let (lir, errors) = db
+        .optimized_lir(target, tracing, optimization)
+        .unwrap_or_else(|error| {
+            let mut constants = Constants::default();
+            let payload = CompilerErrorPayload::Module(error);
+            let reason_id = constants.push(payload.to_string());
+            let responsible_id = constants.push(hir::Id::user());
 
-        let mut bodies = Bodies::default();
-        bodies.push(body);
+            let mut body = Body::new(
+                FxHashSet::from_iter([hir::Id::new(module.clone(), vec![])]),
+                0,
+                0,
+            );
+            let reason_id = body.push(Expression::Constant(reason_id));
+            let responsible_id = body.push(Expression::Constant(responsible_id));
+            body.push(Expression::Panic {
+                reason: reason_id,
+                responsible: responsible_id,
+            });
 
-        let lir = Lir::new(constants, bodies);
-        let errors = vec![CompilerError::for_whole_module(module.clone(), payload)]
-            .into_iter()
-            .collect();
-        (Arc::new(lir), Arc::new(errors))
-    });
+            let mut bodies = Bodies::default();
+            bodies.push(body);
+
+            let lir = Lir::new(constants, bodies);
+            let errors = vec![CompilerError::for_whole_module(module.clone(), payload)]
+                .into_iter()
+                .collect();
+            (Arc::new(lir), Arc::new(errors))
+        });
     let byte_code = LoweringContext::compile(module, lir.as_ref());
     (byte_code, errors)
 }
 
+/// Compiles LIR that doesn't come from the frontend, e.g., LIR parsed from its
+/// textual representation (see [`Lir::parse`]).
+#[must_use]
+pub fn compile_lir(module: Module, lir: &Lir) -> ByteCode {
+    LoweringContext::compile(module, lir)
+}
+
 struct LoweringContext<'c> {
+    module: Module,
     lir: &'c Lir,
     constant_heap: Heap,
     constant_mapping: FxHashMap<ConstantId, InlineObject>,
-    origins: Vec<FxHashSet<hir::Id>>,
+
+    /// Values in the constant heap, keyed by their contents.
+    ///
+    /// Different LIR constants and tag symbols can have the same value (e.g.,
+    /// when multiple modules use the same text). Each value is only allocated
+    /// once so that the constant heap doesn't contain duplicates.
+    interned_constants: FxHashMap<InternedConstant, InlineObject>,
+    origins: Origins,
     body_mapping: FxHashMap<BodyId, InstructionPointer>,
     stack: Vec<Id>,
 
+    /// How many references to each value the body currently being lowered
+    /// owns at the current instruction.
+    ///
+    /// This is negative for values that are not owned, e.g., captured values
+    /// used after the body has consumed the dups it made for them.
+    owned_references: FxHashMap<Id, isize>,
+    /// Frame-allocated objects of the body currently being lowered that
+    /// weren't freed yet.
+    frame_objects: Vec<Id>,
+    /// Frame cleanups of the body currently being lowered, by the index of the
+    /// call or panic instruction in [`LoweringContext::current_instructions`].
+    current_frame_cleanups: Vec<(usize, FrameCleanup)>,
+    frame_cleanups: FxHashMap<InstructionPointer, FrameCleanup>,
+
     /// Instructions for the body currently being lowered.
     ///
     /// For nested functions, [`LoweringContext::compile_body`] [`mem::take`]s
@@ -89,15 +124,28 @@ impl<'c> LoweringContext<'c> {
         );
 
         let mut context = LoweringContext {
+            module: module.clone(),
             lir,
             constant_heap,
             constant_mapping: FxHashMap::default(),
-            origins: vec![],
+            interned_constants: FxHashMap::default(),
+            origins: Origins::default(),
             body_mapping: FxHashMap::default(),
             stack: vec![],
+            owned_references: FxHashMap::default(),
+            frame_objects: vec![],
+            current_frame_cleanups: vec![],
+            frame_cleanups: FxHashMap::default(),
             current_instructions: vec![],
             final_instructions: vec![],
         };
+
+        context.final_instructions.push(Instruction::CompleteTry);
+        context.origins.push_function(
+            ByteCode::COMPLETE_TRY..ByteCode::COMPLETE_TRY.next(),
+            FxHashSet::default(),
+        );
+
         let mut start = None;
         for (id, _) in lir.bodies().ids_and_bodies() {
             start = Some(context.compile_body(id));
@@ -118,6 +166,7 @@ impl<'c> LoweringContext<'c> {
             origins: context.origins,
             module_function,
             responsible_module,
+            frame_cleanups: context.frame_cleanups,
         }
     }
 
@@ -126,6 +175,9 @@ impl<'c> LoweringContext<'c> {
     }
     fn compile_body(&mut self, body_id: BodyId) -> InstructionPointer {
         let old_stack = mem::take(&mut self.stack);
+        let old_owned_references = mem::take(&mut self.owned_references);
+        let old_frame_objects = mem::take(&mut self.frame_objects);
+        let old_frame_cleanups = mem::take(&mut self.current_frame_cleanups);
         let old_instructions = mem::take(&mut self.current_instructions);
 
         let body = self.lir.bodies().get(body_id);
@@ -134,12 +186,23 @@ impl<'c> LoweringContext<'c> {
         }
         for parameter in body.parameter_ids() {
             self.stack.push(parameter);
+            // Functions drop their arguments.
+            self.owned_references.force_insert(parameter, 1);
         }
         self.stack.push(body.responsible_parameter_id());
 
+        let last_expression_id = body.last_expression_id();
         for (id, expression) in body.ids_and_expressions() {
+            // Frame-allocated objects don't escape, so the returned expression
+            // usually doesn't use them. Freeing them before it keeps tail calls
+            // possible.
+            if Some(id) == last_expression_id && !self.is_using_frame_objects(expression) {
+                self.emit_free_frame_objects();
+            }
             self.compile_expression(id, expression);
+            self.track_owned_references(id, expression);
         }
+        self.emit_free_frame_objects();
 
         if matches!(
             self.current_instructions.last().unwrap(),
@@ -152,6 +215,12 @@ impl<'c> LoweringContext<'c> {
                 num_locals_to_pop: self.stack.len() - 1,
                 num_args: num_args.try_into().unwrap(),
             });
+            // Our frame is gone once the callee runs.
+            self.current_frame_cleanups.pop();
+        } else if let Instruction::IfElse(if_else) = self.current_instructions.last_mut().unwrap() {
+            // The branch's body returns directly to our caller.
+            if_else.num_locals_to_pop = Some(self.stack.len() - 1);
+            self.current_frame_cleanups.pop();
         } else {
             let dummy_id = Id::from_usize(0);
             self.emit(
@@ -161,15 +230,27 @@ impl<'c> LoweringContext<'c> {
             self.emit(dummy_id, Instruction::Return);
         }
 
-        let num_current_instructions = self.current_instructions.len();
-        let start = self.final_instructions.len().into();
-        self.final_instructions
-            .append(&mut self.current_instructions);
-        self.origins
-            .extend((0..num_current_instructions).map(|_| body.original_hirs().clone()));
+        let instructions = mem::take(&mut self.current_instructions);
+        let start = self.final_instructions.len();
+        let (instructions, fused_indices) = fuse_superinstructions(instructions);
+        self.final_instructions.extend(instructions);
+        for (index, cleanup) in mem::take(&mut self.current_frame_cleanups) {
+            // The frame continues at the instruction after the call.
+            let continues_at = start + fused_indices[index] + 1;
+            self.frame_cleanups
+                .force_insert(continues_at.into(), cleanup);
+        }
+        let start = start.into();
+        self.origins.push_function(
+            start..self.final_instructions.len().into(),
+            body.original_hirs().clone(),
+        );
         self.body_mapping.force_insert(body_id, start);
 
         self.stack = old_stack;
+        self.owned_references = old_owned_references;
+        self.frame_objects = old_frame_objects;
+        self.current_frame_cleanups = old_frame_cleanups;
         self.current_instructions = old_instructions;
 
         start
@@ -177,17 +258,26 @@ impl<'c> LoweringContext<'c> {
 
     fn compile_expression(&mut self, id: Id, expression: &Expression) {
         match expression {
-            Expression::CreateTag { symbol, value } => {
-                let symbol = self
-                    .constant_heap
-                    .default_symbols()
-                    .get(symbol)
-                    .unwrap_or_else(|| Text::create(&mut self.constant_heap, false, symbol));
+            Expression::CreateTag {
+                symbol,
+                value,
+                is_frame_allocated,
+            } => {
+                let symbol = self.intern_symbol(symbol);
 
                 self.emit_reference_to(*value);
-                self.emit(id, Instruction::CreateTag { symbol });
+                self.emit(
+                    id,
+                    Instruction::CreateTag {
+                        symbol,
+                        is_frame_allocated: *is_frame_allocated,
+                    },
+                );
             }
-            Expression::CreateList(items) => {
+            Expression::CreateList {
+                items,
+                is_frame_allocated,
+            } => {
                 for item in items {
                     self.emit_reference_to(*item);
                 }
@@ -195,10 +285,14 @@ impl<'c> LoweringContext<'c> {
                     id,
                     Instruction::CreateList {
                         num_items: items.len(),
+                        is_frame_allocated: *is_frame_allocated,
                     },
                 );
             }
-            Expression::CreateStruct(fields) => {
+            Expression::CreateStruct {
+                fields,
+                is_frame_allocated,
+            } => {
                 for (key, value) in fields {
                     self.emit_reference_to(*key);
                     self.emit_reference_to(*value);
@@ -207,6 +301,7 @@ impl<'c> LoweringContext<'c> {
                     id,
                     Instruction::CreateStruct {
                         num_fields: fields.len(),
+                        is_frame_allocated: *is_frame_allocated,
                     },
                 );
             }
@@ -258,6 +353,17 @@ impl<'c> LoweringContext<'c> {
                 else_captured,
                 responsible,
             } => {
+                // We look up the captured values before pushing the condition
+                // and responsibility. Otherwise, a branch capturing one of
+                // them would refer to the copy that gets popped.
+                let find_captured = |captured: &[Id]| {
+                    captured
+                        .iter()
+                        .map(|id| self.stack.find_id(*id) + 2)
+                        .collect()
+                };
+                let then_captured = find_captured(then_captured);
+                let else_captured = find_captured(else_captured);
                 self.emit_reference_to(*condition);
                 self.emit_reference_to(*responsible);
                 let then_target = self.get_body(*then_body_id);
@@ -266,15 +372,10 @@ impl<'c> LoweringContext<'c> {
                     id,
                     Instruction::IfElse(Box::new(IfElse {
                         then_target,
-                        then_captured: then_captured
-                            .iter()
-                            .map(|id| self.stack.find_id(*id))
-                            .collect(),
+                        then_captured,
                         else_target,
-                        else_captured: else_captured
-                            .iter()
-                            .map(|id| self.stack.find_id(*id))
-                            .collect(),
+                        else_captured,
+                        num_locals_to_pop: None,
                     })),
                 );
             }
@@ -369,34 +470,43 @@ impl<'c> LoweringContext<'c> {
     }
     fn compile_constant(&mut self, id: ConstantId) -> InlineObject {
         let constant: InlineObject = match self.lir.constants().get(id) {
-            Constant::Int(int) => {
-                Int::create_from_bigint(&mut self.constant_heap, false, int.clone()).into()
-            }
-            Constant::Text(text) => Text::create(&mut self.constant_heap, false, text).into(),
+            Constant::Int(int) => self.intern(InternedConstant::Int(int.clone()), |heap| {
+                Int::create_from_bigint(heap, false, int.clone()).into()
+            }),
+            Constant::Text(text) => self.intern_text(text).into(),
             Constant::Tag { symbol, value } => {
-                let symbol = self
-                    .constant_heap
-                    .default_symbols()
-                    .get(symbol)
-                    .unwrap_or_else(|| Text::create(&mut self.constant_heap, false, symbol));
+                let symbol = self.intern_symbol(symbol);
                 let value = value.map(|id| self.get_constant(id));
-                Tag::create_with_value_option(&mut self.constant_heap, false, symbol, value).into()
+                self.intern(InternedConstant::Tag { symbol, value }, |heap| {
+                    Tag::create_with_value_option(heap, false, symbol, value).into()
+                })
             }
             Constant::Builtin(builtin) => Builtin::create(*builtin).into(),
+            Constant::Extern { symbol, signature } => {
+                let arity = signature.arity();
+                let body = self.compile_extern_stub(symbol, arity);
+                Function::create(&mut self.constant_heap, false, &[], arity, body).into()
+            }
             Constant::List(items) => {
                 let items = items.iter().map(|id| self.get_constant(*id)).collect_vec();
-                List::create(&mut self.constant_heap, false, &items).into()
+                self.intern(InternedConstant::List(items.clone()), |heap| {
+                    List::create(heap, false, &items).into()
+                })
             }
             Constant::Struct(fields) => {
                 let fields = fields
                     .iter()
                     .map(|(key, value)| (self.get_constant(*key), self.get_constant(*value)))
-                    .collect();
-                Struct::create(&mut self.constant_heap, false, &fields).into()
-            }
-            Constant::HirId(hir_id) => {
-                HirId::create(&mut self.constant_heap, false, hir_id.clone()).into()
+                    .sorted()
+                    .collect_vec();
+                self.intern(InternedConstant::Struct(fields.clone()), |heap| {
+                    Struct::create(heap, false, &fields.into_iter().collect()).into()
+                })
             }
+            Constant::HirId(hir_id) => self
+                .intern(InternedConstant::HirId(hir_id.clone()), |heap| {
+                    HirId::create(heap, false, hir_id.clone()).into()
+                }),
             Constant::Function(body_id) => {
                 let body = self.get_body(*body_id);
                 Function::create(
@@ -412,6 +522,229 @@ impl<'c> LoweringContext<'c> {
         self.constant_mapping.force_insert(id, constant);
         constant
     }
+    fn intern(
+        &mut self,
+        constant: InternedConstant,
+        create: impl FnOnce(&mut Heap) -> InlineObject,
+    ) -> InlineObject {
+        *self
+            .interned_constants
+            .entry(constant)
+            .or_insert_with(|| create(&mut self.constant_heap))
+    }
+    fn intern_text(&mut self, text: &str) -> Text {
+        self.intern(InternedConstant::Text(text.to_string()), |heap| {
+            Text::create(heap, false, text).into()
+        })
+        .try_into()
+        .unwrap()
+    }
+    fn intern_symbol(&mut self, symbol: &str) -> Text {
+        self.constant_heap
+            .default_symbols()
+            .get(symbol)
+            .unwrap_or_else(|| self.intern_text(symbol))
+    }
+
+    /// The VM can't call C functions, so extern functions are lowered to a
+    /// function that panics when called.
+    fn compile_extern_stub(&mut self, symbol: &str, arity: usize) -> InstructionPointer {
+        let reason = self.intern_text(&format!(
+            "The extern function `{symbol}` can only be called in compiled code."
+        ));
+
+        let start = self.final_instructions.len().into();
+        // The stack contains the arguments and the responsible HIR ID.
+        self.final_instructions.extend([
+            Instruction::PushConstant(reason.into()),
+            Instruction::PushFromStack(1),
+            Instruction::Panic,
+        ]);
+        self.frame_cleanups.force_insert(
+            self.final_instructions.len().into(),
+            FrameCleanup {
+                frame_size: arity + 1,
+                owned_references: (1..=arity).map(|offset| (offset, 1)).collect(),
+                frame_objects: vec![],
+            },
+        );
+        self.origins.push_function(
+            start..self.final_instructions.len().into(),
+            FxHashSet::from_iter([hir::Id::new(self.module.clone(), vec![])]),
+        );
+        start
+    }
+
+    /// Updates [`LoweringContext::owned_references`] after lowering the
+    /// `expression` and records a [`FrameCleanup`] if the frame can be unwound
+    /// at it.
+    fn track_owned_references(&mut self, id: Id, expression: &Expression) {
+        if let Expression::Dup { id, amount } = expression {
+            *self.owned_references.entry(*id).or_default() += isize::try_from(*amount).unwrap();
+        }
+        let mut consume = |id: &Id| *self.owned_references.entry(*id).or_default() -= 1;
+        match expression {
+            Expression::CreateTag { value, .. } => consume(value),
+            Expression::CreateList { items, .. } => items.iter().for_each(&mut consume),
+            Expression::CreateStruct { fields, .. } => {
+                for (key, value) in fields {
+                    consume(key);
+                    consume(value);
+                }
+            }
+            Expression::CreateFunction { captured, .. } => captured.iter().for_each(&mut consume),
+            Expression::Constant(_) => {}
+            Expression::Reference(referenced) => consume(referenced),
+            // The responsibility is not reference-counted.
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                consume(function);
+                arguments.iter().for_each(&mut consume);
+            }
+            Expression::IfElse {
+                condition,
+                then_captured,
+                else_captured,
+                ..
+            } => {
+                consume(condition);
+                then_captured.iter().for_each(&mut consume);
+                else_captured.iter().for_each(&mut consume);
+            }
+            Expression::Panic {
+                reason,
+                responsible,
+            } => {
+                consume(reason);
+                consume(responsible);
+            }
+            Expression::Dup { .. } => {}
+            Expression::Drop(id) => consume(id),
+            Expression::TraceCallStarts {
+                hir_call,
+                function,
+                arguments,
+                responsible,
+            }
+            | Expression::TraceTailCall {
+                hir_call,
+                function,
+                arguments,
+                responsible,
+            } => {
+                consume(hir_call);
+                consume(function);
+                arguments.iter().for_each(&mut consume);
+                consume(responsible);
+            }
+            Expression::TraceCallEnds { return_value } => {
+                return_value.iter().for_each(&mut consume);
+            }
+            Expression::TraceExpressionEvaluated {
+                hir_expression,
+                value,
+            } => {
+                consume(hir_expression);
+                consume(value);
+            }
+            Expression::TraceFoundFuzzableFunction {
+                hir_definition,
+                function,
+            } => {
+                consume(hir_definition);
+                consume(function);
+            }
+        }
+
+        if matches!(
+            expression,
+            Expression::Call { .. } | Expression::IfElse { .. } | Expression::Panic { .. },
+        ) {
+            self.record_frame_cleanup();
+        }
+        if matches!(
+            expression,
+            Expression::CreateTag {
+                is_frame_allocated: true,
+                ..
+            } | Expression::CreateList {
+                is_frame_allocated: true,
+                ..
+            } | Expression::CreateStruct {
+                is_frame_allocated: true,
+                ..
+            },
+        ) {
+            self.frame_objects.push(id);
+        } else if matches!(
+            expression,
+            Expression::CreateTag { .. }
+                | Expression::CreateList { .. }
+                | Expression::CreateStruct { .. }
+                | Expression::CreateFunction { .. }
+                | Expression::Reference(_)
+                | Expression::Call { .. }
+                | Expression::IfElse { .. },
+        ) {
+            self.owned_references.force_insert(id, 1);
+        }
+    }
+    /// Records what the current frame owns while it's suspended at the last
+    /// instruction, which has to be a call or panic.
+    fn record_frame_cleanup(&mut self) {
+        // The result of the instruction is already on the stack, but it's
+        // only pushed once the frame continues.
+        let frame = &self.stack[..self.stack.len() - 1];
+        let owned_references = frame
+            .iter()
+            .rev()
+            .enumerate()
+            .unique_by(|(_, id)| **id)
+            .filter_map(|(offset, id)| {
+                let amount = *self.owned_references.get(id)?;
+                (amount > 0).then(|| (offset, amount.unsigned_abs()))
+            })
+            .collect();
+        let frame_objects = self
+            .frame_objects
+            .iter()
+            .map(|id| frame.len() - 1 - frame.iter().rposition(|it| it == id).unwrap())
+            .collect();
+        self.current_frame_cleanups.push((
+            self.current_instructions.len() - 1,
+            FrameCleanup {
+                frame_size: frame.len(),
+                owned_references,
+                frame_objects,
+            },
+        ));
+    }
+
+    fn is_using_frame_objects(&self, expression: &Expression) -> bool {
+        if self.frame_objects.is_empty() {
+            return false;
+        }
+        let mut is_using_frame_objects = false;
+        expression.clone().replace_ids(|id| {
+            is_using_frame_objects |= self.frame_objects.contains(&id);
+            id
+        });
+        is_using_frame_objects
+    }
+    fn emit_free_frame_objects(&mut self) {
+        if self.frame_objects.is_empty() {
+            return;
+        }
+        let offsets = mem::take(&mut self.frame_objects)
+            .into_iter()
+            .map(|id| self.stack.find_id(id))
+            .collect();
+        let dummy_id = Id::from_usize(0);
+        self.emit(dummy_id, Instruction::FreeFrameObjects(offsets));
+    }
 
     fn emit_reference_to(&mut self, id: Id) {
         let offset = self.stack.find_id(id);
@@ -423,6 +756,66 @@ impl<'c> LoweringContext<'c> {
     }
 }
 
+#[derive(Eq, Hash, PartialEq)]
+enum InternedConstant {
+    Int(BigInt),
+    Text(String),
+    Tag {
+        symbol: Text,
+        value: Option<InlineObject>,
+    },
+    List(Vec<InlineObject>),
+    Struct(Vec<(InlineObject, InlineObject)>),
+    HirId(hir::Id),
+}
+
+/// Fuses frequent sequences of instructions into superinstructions (see the
+/// end of [`Instruction`]) to reduce the dispatch overhead.
+///
+/// Control flow never enters a body in the middle: calls target the start of a
+/// body and return to the instruction after the call. Hence, we can fuse any
+/// adjacent instructions.
+///
+/// Also returns the index of each original instruction in the fused
+/// instructions.
+fn fuse_superinstructions(instructions: Vec<Instruction>) -> (Vec<Instruction>, Vec<usize>) {
+    let mut fused = Vec::with_capacity(instructions.len());
+    let mut fused_indices = Vec::with_capacity(instructions.len());
+    let mut instructions = instructions.into_iter().peekable();
+    while let Some(instruction) = instructions.next() {
+        fused_indices.push(fused.len());
+        let superinstruction = match (&instruction, instructions.peek()) {
+            (Instruction::PushFromStack(offset), Some(Instruction::Dup { amount })) => {
+                Some(Instruction::PushFromStackAndDup {
+                    offset: *offset,
+                    amount: (*amount).try_into().unwrap(),
+                })
+            }
+            (Instruction::PushFromStack(offset), Some(Instruction::Drop)) => {
+                Some(Instruction::PushFromStackAndDrop(*offset))
+            }
+            (Instruction::PushFromStack(offset), Some(Instruction::Call { num_args })) => {
+                Some(Instruction::PushFromStackAndCall {
+                    responsible: *offset,
+                    num_args: (*num_args).try_into().unwrap(),
+                })
+            }
+            (Instruction::PopMultipleBelowTop(n), Some(Instruction::Return)) => {
+                Some(Instruction::PopMultipleBelowTopAndReturn(*n))
+            }
+            _ => None,
+        };
+        if let Some(superinstruction) = superinstruction {
+            instructions.next();
+            fused_indices.push(fused.len());
+            fused.push(superinstruction);
+        } else {
+            fused.push(instruction);
+        }
+    }
+    (fused, fused_indices)
+}
+
 #[extension_trait]
 impl StackExt for Vec<Id> {
     fn pop_multiple(&mut self, n: usize) {
@@ -437,3 +830,137 @@ impl StackExt for Vec<Id> {
             .unwrap_or_else(|| panic!("Id {id} not found in stack: {}", self.iter().join(" ")))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::compile_lir;
+    use crate::{
+        byte_code::{IfElse, Instruction},
+        heap::{Data, Heap},
+        tracer::DummyTracer,
+        Vm,
+    };
+    use candy_frontend::{lir::Lir, module::Module};
+
+    fn constant_heap_object_count(constants: &str) -> usize {
+        let lir = Lir::parse(&format!(
+            "constants:\n{constants}\nbodies:\n  body_0 captured 0 parameters 0:\n    $1 = constant %0",
+        ))
+        .unwrap();
+        let byte_code = compile_lir(Module::from_package_name("Test".to_string()), &lir);
+        byte_code.constant_heap.objects().len()
+    }
+
+    #[test]
+    fn interns_duplicate_constants() {
+        let base_count = constant_heap_object_count("  %0 = int 0");
+        let count = constant_heap_object_count(
+            r#"
+              %0 = list [%3 %4]
+              %1 = text "Hi"
+              %2 = text "Hi"
+              %3 = tag Greeting %1
+              %4 = tag Greeting %2
+            "#,
+        );
+        // One list, one tag, one text for the symbol, and one text for "Hi".
+        assert_eq!(count, base_count + 4);
+    }
+
+    #[test]
+    fn if_else_at_the_end_of_a_body_is_a_tail_call() {
+        let lir = Lir::parse(
+            r"
+            constants:
+              %0 = int 0
+            bodies:
+              body_0 captured 0 parameters 0:
+                $1 = constant %0
+              body_1 captured 0 parameters 1:
+                $2 = if_else $0 body_0 [] body_0 [] $1
+            ",
+        )
+        .unwrap();
+        let byte_code = compile_lir(Module::from_package_name("Test".to_string()), &lir);
+        let num_locals_to_pop = byte_code
+            .instructions
+            .iter()
+            .find_map(|instruction| match instruction {
+                Instruction::IfElse(box IfElse {
+                    num_locals_to_pop, ..
+                }) => Some(*num_locals_to_pop),
+                _ => None,
+            })
+            .unwrap();
+        // The parameter and the responsibility.
+        assert_eq!(num_locals_to_pop, Some(2));
+        assert!(!byte_code.instructions.iter().any(|instruction| matches!(
+            instruction,
+            Instruction::PopMultipleBelowTopAndReturn(2)
+        )));
+    }
+
+    #[test]
+    fn if_else_branches_can_capture_the_condition_and_responsibility() {
+        let lir = Lir::parse(
+            r"
+            constants:
+              %0 = tag True
+              %1 = int 0
+            bodies:
+              body_0 captured 2 parameters 0:
+                $3 = reference $0
+              body_1 captured 0 parameters 0:
+                $1 = constant %1
+              body_2 captured 0 parameters 0:
+                $1 = constant %0
+                $2 = if_else $1 body_0 [$1 $0] body_1 [] $0
+            ",
+        )
+        .unwrap();
+        let byte_code = compile_lir(Module::from_package_name("Test".to_string()), &lir);
+
+        let mut heap = Heap::default();
+        let result = Vm::for_module(&byte_code, &mut heap, DummyTracer)
+            .run_forever_without_handles(&mut heap)
+            .result
+            .unwrap();
+        assert_eq!(format!("{:?}", Data::from(result)), "True");
+    }
+
+    #[test]
+    fn frees_frame_allocated_objects() {
+        let lir = Lir::parse(
+            r#"
+            constants:
+              %0 = int 1
+              %1 = builtin list_length
+              %2 = hir_id tooling "test" [] code ""
+            bodies:
+              body_0 captured 0 parameters 0:
+                $1 = constant %0
+                $2 = create_list [$1]
+                $3 = create_list [$2] in_frame
+                $4 = constant %1
+                $5 = constant %2
+                $6 = call $4 [$3] $5
+                $7 = reference $6
+            "#,
+        )
+        .unwrap();
+        let byte_code = compile_lir(Module::from_package_name("Test".to_string()), &lir);
+        assert!(byte_code
+            .instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::FreeFrameObjects(_))));
+
+        let mut heap = Heap::default();
+        let result = Vm::for_module(&byte_code, &mut heap, DummyTracer)
+            .run_forever_without_handles(&mut heap)
+            .result
+            .unwrap();
+        assert_eq!(format!("{:?}", Data::from(result)), "1");
+        result.drop(&mut heap);
+        assert_eq!(heap.objects().len(), Heap::default().objects().len());
+    }
+}
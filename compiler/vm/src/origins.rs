@@ -0,0 +1,141 @@
+//! Mapping between byte code instructions and the HIR functions they were
+//! compiled from.
+//!
+//! Each LIR body is lowered to a contiguous range of instructions, so the
+//! mapping is stored per function body rather than per instruction. It can be
+//! queried in both directions: from an [`InstructionPointer`] to the HIR IDs
+//! of the function containing it, and from a HIR ID to the range of
+//! instructions of its function.
+//!
+//! Tools that have a database should use [`ByteCodeOriginsDb::byte_code_origins`]
+//! instead of compiling the byte code themselves. The VM and tools that only
+//! have the byte code use [`ByteCode::origins`](crate::byte_code::ByteCode).
+
+use crate::{instruction_pointer::InstructionPointer, lir_to_byte_code::compile_byte_code};
+use candy_frontend::{
+    cst::CstDb, hir, hir_to_mir::ExecutionTarget, lir_optimize::OptimizeLir, OptimizationConfig,
+    TracingConfig,
+};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{ops::Range, sync::Arc};
+
+#[salsa::query_group(ByteCodeOriginsStorage)]
+pub trait ByteCodeOriginsDb: CstDb + OptimizeLir {
+    fn byte_code_origins(
+        &self,
+        target: ExecutionTarget,
+        tracing: TracingConfig,
+        optimization: OptimizationConfig,
+    ) -> Arc<Origins>;
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn byte_code_origins(
+    db: &dyn ByteCodeOriginsDb,
+    target: ExecutionTarget,
+    tracing: TracingConfig,
+    optimization: OptimizationConfig,
+) -> Arc<Origins> {
+    let (byte_code, _) = compile_byte_code(db, target, tracing, optimization);
+    Arc::new(byte_code.origins)
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Origins {
+    /// Function bodies ordered by their start.
+    functions: Vec<FunctionOrigin>,
+    ranges_by_hir_id: FxHashMap<hir::Id, Range<InstructionPointer>>,
+}
+
+/// A function body in the byte code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionOrigin {
+    pub instructions: Range<InstructionPointer>,
+    /// The HIR functions this body was compiled from. This usually contains a
+    /// single ID, but optimizations may merge multiple functions into one body.
+    pub hir_ids: FxHashSet<hir::Id>,
+}
+impl FunctionOrigin {
+    /// A human-readable name like `Examples:fibonacci:fibRec`. Bodies of whole
+    /// modules are named after the module.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.names().join(" | ")
+    }
+    /// The sorted names of all HIR functions this body was compiled from.
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.hir_ids
+            .iter()
+            .map(|it| {
+                if it.is_root() {
+                    it.module.to_string()
+                } else {
+                    format!("{}:{}", it.module, it.function_name())
+                }
+            })
+            .sorted()
+            .collect()
+    }
+}
+
+impl Origins {
+    /// Records a function body that follows directly after the previously
+    /// pushed ones.
+    pub(crate) fn push_function(
+        &mut self,
+        instructions: Range<InstructionPointer>,
+        hir_ids: FxHashSet<hir::Id>,
+    ) {
+        assert_eq!(
+            instructions.start,
+            self.functions
+                .last()
+                .map_or(InstructionPointer::null_pointer(), |it| it.instructions.end),
+        );
+
+        for hir_id in &hir_ids {
+            let range = self
+                .ranges_by_hir_id
+                .entry(hir_id.clone())
+                .or_insert_with(|| instructions.clone());
+            // A function's range is the first contiguous run of instructions
+            // originating from it.
+            if range.end == instructions.start {
+                range.end = instructions.end;
+            }
+        }
+        self.functions.push(FunctionOrigin {
+            instructions,
+            hir_ids,
+        });
+    }
+
+    /// All function bodies, ordered by their position in the byte code.
+    #[must_use]
+    pub fn functions(&self) -> &[FunctionOrigin] {
+        &self.functions
+    }
+
+    #[must_use]
+    pub fn function_at(&self, ip: InstructionPointer) -> &FunctionOrigin {
+        let index = self
+            .functions
+            .partition_point(|function| function.instructions.end <= ip);
+        let function = &self.functions[index];
+        assert!(function.instructions.contains(&ip));
+        function
+    }
+    #[must_use]
+    pub fn functions_behind(&self, ip: InstructionPointer) -> &FxHashSet<hir::Id> {
+        &self.function_at(ip).hir_ids
+    }
+
+    /// Returns [`None`] if the function doesn't exist in the byte code, e.g.,
+    /// because it was optimized away.
+    #[must_use]
+    pub fn range_of_function(&self, function: &hir::Id) -> Option<Range<InstructionPointer>> {
+        self.ranges_by_hir_id.get(function).cloned()
+    }
+}
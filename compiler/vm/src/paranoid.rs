@@ -0,0 +1,75 @@
+//! A mode in which the VM regularly checks its own invariants.
+//!
+//! This makes programs much slower, but it detects memory corruption (e.g.,
+//! wrong reference counts) close to where it happens instead of when the
+//! corrupted memory is used later on. That's useful in CI and when chasing
+//! crashes reported by users.
+
+use crate::{byte_code::ByteCode, heap::Heap, heap::InlineObject, vm::MachineState};
+use std::num::NonZeroUsize;
+
+#[derive(Debug)]
+pub struct ParanoidChecker {
+    interval: NonZeroUsize,
+    instructions_until_check: usize,
+}
+impl ParanoidChecker {
+    #[must_use]
+    pub const fn new(interval: NonZeroUsize) -> Self {
+        Self {
+            interval,
+            instructions_until_check: interval.get(),
+        }
+    }
+
+    /// Returns whether the VM should be checked after the current instruction.
+    pub fn on_instruction(&mut self) -> bool {
+        self.instructions_until_check -= 1;
+        if self.instructions_until_check > 0 {
+            return false;
+        }
+        self.instructions_until_check = self.interval.get();
+        true
+    }
+}
+
+/// Panics if the heap or the VM's stacks are inconsistent.
+///
+/// `owned_references` are objects the VM owns outside of its data stack, e.g.,
+/// the handle and arguments of a handle call in progress.
+pub fn check(
+    byte_code: &ByteCode,
+    heap: &Heap,
+    state: &MachineState,
+    owned_references: impl IntoIterator<Item = InlineObject>,
+) {
+    let instruction_count = byte_code.instructions.len();
+    for instruction_pointer in state.call_stack.iter().chain(&state.next_instruction) {
+        assert!(
+            **instruction_pointer < instruction_count,
+            "The instruction pointer {instruction_pointer:?} is out of bounds, the byte code only has {instruction_count} instructions.",
+        );
+    }
+
+    // Each function on the call stack keeps at least its responsibility on
+    // the data stack until it returns. A call of `try` replaces its caller's
+    // entry in the call stack with `ByteCode::COMPLETE_TRY`, except if it
+    // doesn't return to a caller.
+    let frame_count = state.call_stack.len() + usize::from(state.next_instruction.is_some())
+        - state
+            .try_frames
+            .iter()
+            .filter(|it| it.return_to.is_none())
+            .count();
+    assert!(
+        state.data_stack.len() >= frame_count,
+        "The data stack contains only {} objects for {frame_count} call frames.",
+        state.data_stack.len(),
+    );
+
+    // Objects on the data stack are only owned by one of their entries:
+    // `PushFromStack` doesn't duplicate them, and entries whose values were
+    // moved into a call stay on the stack until the function returns. So we
+    // can't use them for validating reference counts.
+    heap.validate(&[&byte_code.constant_heap], owned_references);
+}
@@ -0,0 +1,143 @@
+//! A sampling profiler that's built into the VM.
+//!
+//! Every few instructions, the profiler records which functions are on the
+//! call stack. Functions that show up in many samples are where the program
+//! spends most of its time. Unlike tracing all calls (see
+//! [`FlameGraphTracer`](crate::tracer::flame_graph::FlameGraphTracer)), this
+//! doesn't require compiling the program with call tracing and barely slows it
+//! down.
+
+use crate::{
+    byte_code::ByteCode,
+    instruction_pointer::InstructionPointer,
+    origins::{FunctionOrigin, Origins},
+};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{fmt::Write, num::NonZeroUsize};
+
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    instructions_until_sample: usize,
+    profile: Profile,
+}
+impl Profiler {
+    #[must_use]
+    pub(crate) fn new(interval: NonZeroUsize) -> Self {
+        Self {
+            instructions_until_sample: interval.get(),
+            profile: Profile {
+                interval,
+                samples: FxHashMap::default(),
+            },
+        }
+    }
+
+    pub(crate) fn on_instruction(
+        &mut self,
+        origins: &Origins,
+        current_instruction: InstructionPointer,
+        call_stack: &[InstructionPointer],
+    ) {
+        self.instructions_until_sample -= 1;
+        if self.instructions_until_sample > 0 {
+            return;
+        }
+        self.instructions_until_sample = self.profile.interval.get();
+
+        // The call stack contains the return addresses, which lie in the
+        // calling functions.
+        let stack = call_stack
+            .iter()
+            .chain([&current_instruction])
+            .map(|ip| origins.function_at(*ip).instructions.start)
+            .collect_vec();
+        *self.profile.samples.entry(stack).or_default() += 1;
+    }
+
+    #[must_use]
+    pub(crate) fn into_profile(self) -> Profile {
+        self.profile
+    }
+}
+
+#[derive(Debug)]
+pub struct Profile {
+    interval: NonZeroUsize,
+    /// Sampled call stacks (containing the start of each function, outermost
+    /// first) and how often they were sampled
+    samples: FxHashMap<Vec<InstructionPointer>, usize>,
+}
+impl Profile {
+    #[must_use]
+    pub fn sample_count(&self) -> usize {
+        self.samples.values().sum()
+    }
+
+    /// Returns a table of the `limit` functions in which the most samples were
+    /// taken, together with the share of samples that were taken while the
+    /// function was anywhere on the call stack.
+    #[must_use]
+    pub fn format_hot_functions(&self, byte_code: &ByteCode, limit: usize) -> String {
+        let sample_count = self.sample_count();
+        let mut result = String::new();
+        writeln!(
+            result,
+            "{sample_count} samples, one every {} instructions",
+            self.interval,
+        )
+        .unwrap();
+        if sample_count == 0 {
+            return result;
+        }
+
+        let mut self_samples = FxHashMap::<InstructionPointer, usize>::default();
+        let mut total_samples = FxHashMap::<InstructionPointer, usize>::default();
+        for (stack, count) in &self.samples {
+            *self_samples.entry(*stack.last().unwrap()).or_default() += count;
+            // Recursive functions should only be counted once per sample.
+            for function in stack.iter().unique() {
+                *total_samples.entry(*function).or_default() += count;
+            }
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let percentage = |count: usize| count as f64 / sample_count as f64 * 100.0;
+        writeln!(result, "  Self   Total  Function").unwrap();
+        for (self_count, total, function) in total_samples
+            .into_iter()
+            .map(|(function, total)| {
+                let self_count = self_samples.get(&function).copied().unwrap_or_default();
+                (self_count, total, function)
+            })
+            .sorted_by(|a, b| b.cmp(a))
+            .take(limit)
+        {
+            writeln!(
+                result,
+                "{:5.1} % {:5.1} %  {}",
+                percentage(self_count),
+                percentage(total),
+                Self::short_name(byte_code.origins.function_at(function)),
+            )
+            .unwrap();
+        }
+        result
+    }
+    /// Optimizations merge functions with the same body, e.g., small helpers
+    /// generated for pattern matching. Listing all of them would make the
+    /// report unreadable.
+    fn short_name(origin: &FunctionOrigin) -> String {
+        const MAX_NAMES: usize = 3;
+
+        let names = origin.names();
+        if names.len() <= MAX_NAMES {
+            return names.join(" | ");
+        }
+        format!(
+            "{} | … ({} more)",
+            names[..MAX_NAMES].join(" | "),
+            names.len() - MAX_NAMES,
+        )
+    }
+}
@@ -0,0 +1,167 @@
+//! Statistics about how a VM runs and a run quantum that adapts to them.
+//!
+//! Hosts that interleave several VMs on one thread run each of them for a
+//! slice of instructions at a time. With a fixed slice length, a VM that
+//! computes for a long time delays the VMs whose handle calls were answered in
+//! the meantime. [`AdaptiveQuantum`] shortens the slices while any VM waits for
+//! a handle response and lengthens them again once none does, so that
+//! compute-heavy VMs don't pay for constant rescheduling.
+
+use std::time::Duration;
+
+/// Statistics about a single VM, available via [`Vm::statistics`],
+/// [`StateAfterRun::statistics`], and [`VmFinished::statistics`].
+///
+/// Restored VMs (see [`Vm::restore`]) start with empty statistics.
+///
+/// [`Vm::statistics`]: crate::Vm::statistics
+/// [`Vm::restore`]: crate::Vm::restore
+/// [`StateAfterRun::statistics`]: crate::StateAfterRun::statistics
+/// [`VmFinished::statistics`]: crate::VmFinished::statistics
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RunStatistics {
+    pub executed_instructions: usize,
+    /// How often [`Vm::run_n`](crate::Vm::run_n) ran out of instructions
+    /// before the VM called a handle or finished.
+    pub preemptions: usize,
+    /// Handle calls that the VM's host had to answer. Calls of functions
+    /// registered via [`Vm::register_handle`](crate::Vm::register_handle)
+    /// don't count.
+    pub handle_calls: usize,
+    pub instructions_since_handle_call: usize,
+    /// A moving average of how many instructions the VM runs between two
+    /// handle calls, or [`None`] if it didn't call a handle yet.
+    pub average_instructions_between_handle_calls: Option<usize>,
+    /// Time spent in [`Vm::run_n`](crate::Vm::run_n) and
+    /// [`Vm::run_forever`](crate::Vm::run_forever). Single steps using
+    /// [`Vm::run`](crate::Vm::run) aren't timed.
+    pub running_time: Duration,
+    /// Time between handle calls and their completion using
+    /// [`VmHandleCall::complete`](crate::VmHandleCall::complete).
+    pub handle_waiting_time: Duration,
+}
+impl RunStatistics {
+    pub(crate) fn on_instruction(&mut self) {
+        self.executed_instructions += 1;
+        self.instructions_since_handle_call += 1;
+    }
+    pub(crate) fn on_handle_call(&mut self) {
+        self.handle_calls += 1;
+        let instructions = self.instructions_since_handle_call;
+        self.average_instructions_between_handle_calls = Some(
+            self.average_instructions_between_handle_calls
+                .map_or(instructions, |average| (3 * average + instructions) / 4),
+        );
+        self.instructions_since_handle_call = 0;
+    }
+
+    /// How many instructions the VM is expected to run until its next handle
+    /// call, or [`None`] if it doesn't call handles or is already overdue.
+    #[must_use]
+    pub fn expected_instructions_until_handle_call(&self) -> Option<usize> {
+        self.average_instructions_between_handle_calls?
+            .checked_sub(self.instructions_since_handle_call)
+            .filter(|it| *it > 0)
+    }
+}
+
+/// Decides how many instructions to run a VM for before switching to another
+/// one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AdaptiveQuantum {
+    min: usize,
+    max: usize,
+    current: usize,
+}
+impl AdaptiveQuantum {
+    /// The fixed slice length of the old fiber-based VM.
+    pub const DEFAULT_MIN: usize = 100;
+    pub const DEFAULT_MAX: usize = 100_000;
+
+    #[must_use]
+    pub fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0, "The minimum quantum must be positive.");
+        assert!(
+            min <= max,
+            "The minimum quantum must not exceed the maximum."
+        );
+        Self {
+            min,
+            max,
+            current: min,
+        }
+    }
+
+    #[must_use]
+    pub const fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Returns how many instructions to run the VM with the given `statistics`
+    /// for next.
+    ///
+    /// While `is_any_vm_waiting_for_handle` is true, the quantum halves with
+    /// every slice down to the minimum so that the host gets to deliver handle
+    /// responses soon. Otherwise, it doubles up to the maximum. A VM that is
+    /// expected to call a handle shortly gets enough instructions to do so
+    /// without being preempted right before.
+    pub fn next(
+        &mut self,
+        statistics: &RunStatistics,
+        is_any_vm_waiting_for_handle: bool,
+    ) -> usize {
+        self.current = if is_any_vm_waiting_for_handle {
+            (self.current / 2).max(self.min)
+        } else {
+            self.current.saturating_mul(2).min(self.max)
+        };
+
+        match statistics.expected_instructions_until_handle_call() {
+            Some(expected) if expected <= self.max => self.current.max(expected),
+            _ => self.current,
+        }
+    }
+}
+impl Default for AdaptiveQuantum {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MIN, Self::DEFAULT_MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptiveQuantum, RunStatistics};
+
+    #[test]
+    fn quantum_shrinks_while_vms_wait_for_handles() {
+        let statistics = RunStatistics::default();
+        let mut quantum = AdaptiveQuantum::new(100, 1000);
+        assert_eq!(quantum.next(&statistics, false), 200);
+        assert_eq!(quantum.next(&statistics, false), 400);
+        assert_eq!(quantum.next(&statistics, false), 800);
+        assert_eq!(quantum.next(&statistics, false), 1000);
+        assert_eq!(quantum.next(&statistics, true), 500);
+        assert_eq!(quantum.next(&statistics, true), 250);
+        assert_eq!(quantum.next(&statistics, true), 125);
+        assert_eq!(quantum.next(&statistics, true), 100);
+    }
+
+    #[test]
+    fn vms_about_to_call_a_handle_are_not_preempted() {
+        let mut statistics = RunStatistics::default();
+        for _ in 0..300 {
+            statistics.on_instruction();
+        }
+        statistics.on_handle_call();
+        for _ in 0..50 {
+            statistics.on_instruction();
+        }
+        assert_eq!(
+            statistics.expected_instructions_until_handle_call(),
+            Some(250)
+        );
+
+        let mut quantum = AdaptiveQuantum::new(100, 1000);
+        assert_eq!(quantum.next(&statistics, true), 250);
+    }
+}
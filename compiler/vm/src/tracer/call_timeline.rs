@@ -0,0 +1,111 @@
+use crate::{
+    byte_code::ByteCode,
+    heap::{Data, InlineObject},
+    instruction_pointer::InstructionPointer,
+};
+use candy_frontend::{builtin_functions::BuiltinFunction, utils::AdjustCasingOfFirstLetter};
+use rustc_hash::FxHashMap;
+use std::time::{Duration, Instant};
+
+/// When calls started and ended, as recorded by the profiling tracers.
+///
+/// The VM traces running the module and its main function as calls, so these
+/// are the outermost frames. Tail calls replace the frame of their caller, so
+/// the time after a tail call is attributed to the called function only.
+#[derive(Debug)]
+pub struct CallTimeline {
+    start: Instant,
+    call_stack: Vec<Frame>,
+    pub events: Vec<TimelineEvent>,
+}
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Frame {
+    Function(InstructionPointer),
+    Builtin(BuiltinFunction),
+    /// Calls of values that aren't functions, which panic.
+    NotAFunction,
+}
+#[derive(Clone, Copy, Debug)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    pub frame: Frame,
+    /// Time since the timeline was created
+    pub at: Duration,
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimelineEventKind {
+    Open,
+    Close,
+}
+
+impl Default for CallTimeline {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            call_stack: vec![],
+            events: vec![],
+        }
+    }
+}
+impl CallTimeline {
+    pub fn call_started(&mut self, callee: InlineObject) {
+        let frame = match Data::from(callee) {
+            Data::Function(function) => Frame::Function(function.body()),
+            Data::Builtin(builtin) => Frame::Builtin(builtin.get()),
+            _ => Frame::NotAFunction,
+        };
+        self.call_stack.push(frame);
+        self.record(TimelineEventKind::Open, frame);
+    }
+    pub fn call_ended(&mut self) {
+        let frame = self.call_stack.pop().unwrap();
+        self.record(TimelineEventKind::Close, frame);
+    }
+    pub fn tail_call(&mut self, callee: InlineObject) {
+        self.call_ended();
+        self.call_started(callee);
+    }
+    fn record(&mut self, kind: TimelineEventKind, frame: Frame) {
+        self.events.push(TimelineEvent {
+            kind,
+            frame,
+            at: self.start.elapsed(),
+        });
+    }
+
+    #[must_use]
+    pub fn end(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |it| it.at)
+    }
+    /// Frames of calls that never ended because the program panicked,
+    /// innermost first.
+    pub fn unfinished_frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        self.call_stack.iter().rev().copied()
+    }
+}
+
+pub struct FrameNames<'b> {
+    byte_code: &'b ByteCode,
+    cache: FxHashMap<Frame, String>,
+}
+impl<'b> FrameNames<'b> {
+    pub fn new(byte_code: &'b ByteCode) -> Self {
+        Self {
+            byte_code,
+            cache: FxHashMap::default(),
+        }
+    }
+
+    pub fn get(&mut self, frame: Frame) -> String {
+        self.cache
+            .entry(frame)
+            .or_insert_with(|| match frame {
+                Frame::Function(body) => self.byte_code.origins.function_at(body).name(),
+                Frame::Builtin(builtin) => {
+                    format!("✨.{}", format!("{builtin:?}").lowercase_first_letter())
+                }
+                Frame::NotAFunction => "<not a function>".to_string(),
+            })
+            .clone()
+    }
+}
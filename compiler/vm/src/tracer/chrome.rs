@@ -0,0 +1,107 @@
+use super::{
+    call_timeline::{CallTimeline, FrameNames, TimelineEventKind},
+    Tracer,
+};
+use crate::{
+    byte_code::ByteCode,
+    heap::{Heap, HirId, InlineObject},
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// Records the duration of calls in Chrome's
+/// [trace event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+/// which can be viewed in [Perfetto](https://ui.perfetto.dev) or
+/// `chrome://tracing`.
+///
+/// The VM runs a program on a single call stack, so all calls end up on one
+/// track.
+#[derive(Debug, Default)]
+pub struct ChromeTracer {
+    timeline: CallTimeline,
+}
+impl ChromeTracer {
+    const PROCESS_ID: usize = 1;
+    const THREAD_ID: usize = 1;
+
+    #[must_use]
+    pub fn to_json(&self, byte_code: &ByteCode) -> serde_json::Value {
+        let mut names = FrameNames::new(byte_code);
+        let mut events = vec![
+            json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": Self::PROCESS_ID,
+                "args": { "name": byte_code.module.to_string() },
+            }),
+            json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": Self::PROCESS_ID,
+                "tid": Self::THREAD_ID,
+                "args": { "name": "VM" },
+            }),
+        ];
+        for event in &self.timeline.events {
+            let phase = match event.kind {
+                TimelineEventKind::Open => "B",
+                TimelineEventKind::Close => "E",
+            };
+            events.push(json!({
+                "name": names.get(event.frame),
+                "cat": "call",
+                "ph": phase,
+                "ts": Self::micros(event.at),
+                "pid": Self::PROCESS_ID,
+                "tid": Self::THREAD_ID,
+            }));
+        }
+        // If the program panicked, some calls never ended.
+        let end = Self::micros(self.timeline.end());
+        for frame in self.timeline.unfinished_frames() {
+            events.push(json!({
+                "name": names.get(frame),
+                "cat": "call",
+                "ph": "E",
+                "ts": end,
+                "pid": Self::PROCESS_ID,
+                "tid": Self::THREAD_ID,
+            }));
+        }
+
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ns",
+        })
+    }
+    /// Timestamps in this format are given in microseconds.
+    fn micros(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1_000_000.0
+    }
+}
+
+impl Tracer for ChromeTracer {
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        _call_site: HirId,
+        callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.timeline.call_started(callee);
+    }
+    fn call_ended(&mut self, _heap: &mut Heap, _return_value: Option<InlineObject>) {
+        self.timeline.call_ended();
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        _call_site: HirId,
+        callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.timeline.tail_call(callee);
+    }
+}
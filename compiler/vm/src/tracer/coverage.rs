@@ -0,0 +1,70 @@
+use super::Tracer;
+use crate::heap::{Heap, HirId, InlineObject};
+use candy_frontend::{hir::Id, module::Module};
+use rustc_hash::FxHashMap;
+
+/// Counts how often each expression of a module was executed.
+///
+/// Calls are traced both when they start and when their value is evaluated
+/// (except for tail calls and calls that panic), so we count both separately
+/// and use the maximum.
+#[derive(Debug)]
+pub struct CoverageTracer {
+    module: Module,
+    evaluations: FxHashMap<Id, usize>,
+    calls: FxHashMap<Id, usize>,
+}
+impl CoverageTracer {
+    #[must_use]
+    pub fn new(module: Module) -> Self {
+        Self {
+            module,
+            evaluations: FxHashMap::default(),
+            calls: FxHashMap::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn counts(&self) -> FxHashMap<Id, usize> {
+        let mut counts = self.evaluations.clone();
+        for (id, count) in &self.calls {
+            let entry = counts.entry(id.clone()).or_default();
+            *entry = (*entry).max(*count);
+        }
+        counts
+    }
+
+    fn record(module: &Module, counts: &mut FxHashMap<Id, usize>, id: &Id) {
+        if &id.module != module {
+            return;
+        }
+
+        *counts.entry(id.clone()).or_default() += 1;
+    }
+}
+impl Tracer for CoverageTracer {
+    fn value_evaluated(&mut self, _heap: &mut Heap, expression: HirId, _value: InlineObject) {
+        Self::record(&self.module, &mut self.evaluations, expression.get());
+    }
+
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        _callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        Self::record(&self.module, &mut self.calls, call_site.get());
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        _callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        Self::record(&self.module, &mut self.calls, call_site.get());
+    }
+}
@@ -0,0 +1,147 @@
+use super::{
+    call_timeline::{CallTimeline, FrameNames, TimelineEventKind},
+    Tracer,
+};
+use crate::{
+    byte_code::ByteCode,
+    heap::{Heap, HirId, InlineObject},
+};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use serde_json::json;
+use std::{fmt::Write, time::Duration};
+
+/// Measures how much time is spent in which functions.
+///
+/// The measurements can be exported in the collapsed stack format, which is
+/// understood by most flame graph tools (e.g., `flamegraph.pl` and `inferno`),
+/// or as a [speedscope](https://www.speedscope.app) profile.
+#[derive(Debug, Default)]
+pub struct FlameGraphTracer {
+    timeline: CallTimeline,
+}
+impl FlameGraphTracer {
+    /// Returns the measurements in the collapsed stack format: One line per
+    /// call stack, containing the frames separated by semicolons and the time
+    /// spent directly in the innermost frame in nanoseconds.
+    #[must_use]
+    pub fn format_collapsed_stacks(&self, byte_code: &ByteCode) -> String {
+        let mut self_times = FxHashMap::<Vec<_>, Duration>::default();
+        let mut stack = vec![];
+        let mut last_event_at = Duration::ZERO;
+        for event in &self.timeline.events {
+            if !stack.is_empty() {
+                *self_times.entry(stack.clone()).or_default() += event.at - last_event_at;
+            }
+            last_event_at = event.at;
+            match event.kind {
+                TimelineEventKind::Open => stack.push(event.frame),
+                TimelineEventKind::Close => {
+                    stack.pop();
+                }
+            }
+        }
+
+        let mut names = FrameNames::new(byte_code);
+        let mut result = String::new();
+        for (stack, self_time) in self_times
+            .into_iter()
+            .filter(|(_, self_time)| !self_time.is_zero())
+            .map(|(stack, self_time)| {
+                let stack = stack.iter().map(|it| names.get(*it)).join(";");
+                (stack, self_time)
+            })
+            .sorted()
+        {
+            writeln!(result, "{stack} {}", self_time.as_nanos()).unwrap();
+        }
+        result
+    }
+
+    /// Returns the measurements as an evented profile in speedscope's file
+    /// format.
+    #[must_use]
+    pub fn to_speedscope_json(&self, byte_code: &ByteCode) -> serde_json::Value {
+        let mut names = FrameNames::new(byte_code);
+        let mut frame_indices = FxHashMap::default();
+        let mut frame_index = |frame| {
+            let next_index = frame_indices.len();
+            *frame_indices.entry(frame).or_insert(next_index)
+        };
+
+        let end = self.timeline.end();
+        let mut events = self
+            .timeline
+            .events
+            .iter()
+            .map(|event| {
+                let kind = match event.kind {
+                    TimelineEventKind::Open => "O",
+                    TimelineEventKind::Close => "C",
+                };
+                json!({
+                    "type": kind,
+                    "frame": frame_index(event.frame),
+                    "at": Self::nanos(event.at),
+                })
+            })
+            .collect_vec();
+        events.extend(self.timeline.unfinished_frames().map(|frame| {
+            json!({
+                "type": "C",
+                "frame": frame_index(frame),
+                "at": Self::nanos(end),
+            })
+        }));
+
+        let frames = frame_indices
+            .into_iter()
+            .sorted_by_key(|(_, index)| *index)
+            .map(|(frame, _)| json!({ "name": names.get(frame) }))
+            .collect_vec();
+        let name = byte_code.module.to_string();
+        json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "shared": { "frames": frames },
+            "profiles": [{
+                "type": "evented",
+                "name": name,
+                "unit": "nanoseconds",
+                "startValue": 0,
+                "endValue": Self::nanos(end),
+                "events": events,
+            }],
+            "name": name,
+            "exporter": "candy",
+        })
+    }
+    fn nanos(duration: Duration) -> u64 {
+        duration.as_nanos().try_into().unwrap()
+    }
+}
+
+impl Tracer for FlameGraphTracer {
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        _call_site: HirId,
+        callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.timeline.call_started(callee);
+    }
+    fn call_ended(&mut self, _heap: &mut Heap, _return_value: Option<InlineObject>) {
+        self.timeline.call_ended();
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        _call_site: HirId,
+        callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        self.timeline.tail_call(callee);
+    }
+}
@@ -1,8 +1,13 @@
 pub use self::dummy::DummyTracer;
 use crate::heap::{Function, Heap, HirId, InlineObject};
 
+mod call_timeline;
+pub mod chrome;
+pub mod coverage;
 mod dummy;
 pub mod evaluated_values;
+pub mod flame_graph;
+mod option;
 pub mod stack_trace;
 pub mod tuple;
 
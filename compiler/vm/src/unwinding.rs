@@ -0,0 +1,204 @@
+//! Catching panics using the `try` builtin.
+//!
+//! Calling `try` with a function pushes a [`TryFrame`] and calls the function
+//! so that it returns to [`ByteCode::COMPLETE_TRY`]. If the function returns
+//! normally, that instruction wraps the return value in `Ok` and continues
+//! after the call of `try`.
+//!
+//! If the code panics in the meantime, the frames between the panic and the
+//! `try` are unwound instead: For each of them, the byte code contains a
+//! [`FrameCleanup`] describing the references the frame would have dropped if
+//! it had continued. After dropping those, the `try` returns `Error reason`.
+//!
+//! Panics caused by resource limits (see [`PanicKind`]) are never caught
+//! because retrying the same code would likely run into them again.
+
+use crate::{
+    byte_code::{ByteCode, FrameCleanup},
+    heap::{Function, Heap, HirId, Tag, Text},
+    instruction_pointer::InstructionPointer,
+    instructions::InstructionResult,
+    tracer::Tracer,
+    vm::{CallHandle, MachineState, Panic, PanicKind},
+};
+
+/// A call of the `try` builtin that hasn't returned yet.
+#[derive(Clone, Copy, Debug)]
+pub struct TryFrame {
+    /// Where to continue after `try` returns. This is [`None`] if `try` was
+    /// called in tail position of the outermost function.
+    pub return_to: Option<InstructionPointer>,
+    /// The length of the data stack when `try` was called, after the call's
+    /// arguments were popped.
+    pub data_stack_len: usize,
+    pub call_stack_len: usize,
+    /// See [`MachineState::traced_call_depth`].
+    pub traced_call_depth: usize,
+}
+
+/// What `try` does with its argument.
+pub enum TryBody {
+    Function {
+        function: Function,
+        responsible: HirId,
+    },
+    Handle(CallHandle),
+}
+
+impl MachineState {
+    pub(super) fn call_try(&mut self, body: TryBody) -> InstructionResult {
+        self.try_frames.push(TryFrame {
+            return_to: self.next_instruction,
+            data_stack_len: self.data_stack.len(),
+            call_stack_len: self.call_stack.len(),
+            traced_call_depth: self.traced_call_depth,
+        });
+        self.next_instruction = Some(ByteCode::COMPLETE_TRY);
+        match body {
+            TryBody::Function {
+                function,
+                responsible,
+            } => self.call_function(function, &[], responsible),
+            // When the handle call completes, its return value is pushed and
+            // we continue at `COMPLETE_TRY`.
+            TryBody::Handle(call) => InstructionResult::CallHandle(call),
+        }
+    }
+
+    /// Whether the panic is caught by a surrounding `try`.
+    #[must_use]
+    pub fn can_catch(&self, panic: &Panic) -> bool {
+        panic.kind == PanicKind::Code && !self.try_frames.is_empty()
+    }
+    /// Unwinds the frames up to the innermost `try` and makes it return
+    /// `Error reason`.
+    ///
+    /// Only call this if [`MachineState::can_catch`] the panic.
+    pub fn catch_panic<T: Tracer>(
+        &mut self,
+        heap: &mut Heap,
+        byte_code: &ByteCode,
+        tracer: &mut T,
+        panic: &Panic,
+    ) {
+        let try_frame = self.try_frames.pop().unwrap();
+
+        let mut position = self.next_instruction;
+        let mut frame_end = self.data_stack.len();
+        while let Some(instruction_pointer) = position
+            && instruction_pointer != ByteCode::COMPLETE_TRY
+        {
+            let FrameCleanup {
+                frame_size,
+                owned_references,
+                frame_objects,
+            } = byte_code
+                .frame_cleanups
+                .get(&instruction_pointer)
+                .unwrap_or_else(|| {
+                    panic!("There's no cleanup for the frame at {instruction_pointer:?}.")
+                });
+            for (offset, amount) in owned_references {
+                let object = self.data_stack[frame_end - 1 - offset];
+                for _ in 0..*amount {
+                    object.drop(heap);
+                }
+            }
+            for offset in frame_objects {
+                heap.free_frame_object(self.data_stack[frame_end - 1 - offset]);
+            }
+            frame_end -= frame_size;
+            position = self.call_stack.pop();
+        }
+        assert_eq!(
+            position,
+            Some(ByteCode::COMPLETE_TRY),
+            "The call stack doesn't contain the `try` that should catch the panic.",
+        );
+        debug_assert_eq!(frame_end, try_frame.data_stack_len);
+        debug_assert_eq!(self.call_stack.len(), try_frame.call_stack_len);
+        self.data_stack.truncate(try_frame.data_stack_len);
+
+        // The tracer still considers the calls that were in progress to be
+        // running.
+        for _ in try_frame.traced_call_depth..self.traced_call_depth {
+            tracer.call_ended(heap, None);
+        }
+        self.traced_call_depth = try_frame.traced_call_depth;
+
+        let reason = Text::create(heap, true, &panic.reason);
+        let result = Tag::create_result(heap, true, Err(reason.into()));
+        self.data_stack.push(result.into());
+        self.next_instruction = try_frame.return_to;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        heap::{Data, Heap},
+        lir_to_byte_code::compile_lir,
+        tracer::DummyTracer,
+        Vm,
+    };
+    use candy_frontend::{lir::Lir, module::Module};
+
+    /// Runs a module that calls `try` with a function that creates a list and
+    /// then returns it or panics.
+    fn run_try(body_end: &str) -> (Heap, String) {
+        let lir = Lir::parse(&format!(
+            r#"
+            constants:
+              %0 = int 1
+              %1 = text "Oops."
+              %2 = hir_id tooling "test" [] code ""
+              %3 = builtin try
+              %4 = function body_0
+            bodies:
+              body_0 captured 0 parameters 0:
+                $1 = constant %0
+                $2 = create_list [$1]
+                {body_end}
+              body_1 captured 0 parameters 0:
+                $1 = constant %3
+                $2 = constant %4
+                $3 = constant %2
+                $4 = call $1 [$2] $3
+            "#,
+        ))
+        .unwrap();
+        let byte_code = compile_lir(Module::from_package_name("Test".to_string()), &lir);
+
+        let mut heap = Heap::default();
+        let result = Vm::for_module(&byte_code, &mut heap, DummyTracer)
+            .run_forever_without_handles(&mut heap)
+            .result
+            .unwrap();
+        let debug_text = format!("{:?}", Data::from(result));
+        result.drop(&mut heap);
+        (heap, debug_text)
+    }
+
+    #[test]
+    fn wraps_return_value_in_ok() {
+        let (heap, result) = run_try("$3 = reference $2");
+        assert_eq!(result, "Ok ((1,))");
+        assert_eq!(heap.objects().len(), Heap::default().objects().len());
+    }
+
+    #[test]
+    fn catches_panic_and_drops_owned_references() {
+        let (heap, result) = run_try("$3 = constant %1\n$4 = constant %2\n$5 = panic $3 $4");
+        assert_eq!(result, "Error (\"Oops.\")");
+        assert_eq!(heap.objects().len(), Heap::default().objects().len());
+    }
+
+    #[test]
+    fn catches_panic_and_frees_frame_objects() {
+        let (heap, result) = run_try(
+            "$3 = create_list [$1] in_frame\n$4 = constant %1\n$5 = constant %2\n$6 = panic $4 $5\n$7 = reference $2",
+        );
+        assert_eq!(result, "Error (\"Oops.\")");
+        assert_eq!(heap.objects().len(), Heap::default().objects().len());
+    }
+}
@@ -1,14 +1,26 @@
 use crate::{
     byte_code::ByteCode,
-    heap::{Function, Handle, Heap, HirId, InlineObject, Struct},
+    heap::{Function, Handle, Heap, HeapObject, HirId, InlineObject, Struct, Tag, Text},
+    host_function::RegisteredHostFunction,
     instruction_pointer::InstructionPointer,
     instructions::InstructionResult,
+    paranoid::{self, ParanoidChecker},
+    profiler::{Profile, Profiler},
+    scheduling::RunStatistics,
     tracer::Tracer,
+    unwinding::TryFrame,
+};
+use candy_frontend::{
+    hir::{self, Id},
+    utils::AdjustCasingOfFirstLetter,
 };
-use candy_frontend::hir::{self, Id};
 use derive_more::Deref;
 use extension_trait::extension_trait;
-use std::{borrow::Borrow, collections::HashMap, fmt::Debug, hash::Hash};
+use rustc_hash::FxHashMap;
+use std::{
+    borrow::Borrow, collections::HashMap, fmt::Debug, hash::Hash, mem, num::NonZeroUsize,
+    ops::ControlFlow, time::Instant,
+};
 
 /// A VM represents a Candy program that thinks it's currently running. Because
 /// VMs are first-class Rust structs, they enable other code to store "freezed"
@@ -32,11 +44,29 @@ struct VmInner<B: Borrow<ByteCode>, T: Tracer> {
     /// is [`None`] in the second phase or if just running a module or function
     /// on its own.
     environment_for_main_function: Option<Struct>,
+    /// Responsible if the heap runs out of memory.
+    responsible: Id,
+    profiler: Option<Profiler>,
+    paranoid_checker: Option<ParanoidChecker>,
+    /// See [`Vm::with_max_stack_depth`].
+    max_stack_depth: Option<usize>,
+    /// See [`Vm::register_handle`].
+    host_functions: FxHashMap<Handle, RegisteredHostFunction>,
+    /// See [`Vm::statistics`].
+    statistics: RunStatistics,
 }
 pub struct MachineState {
     pub next_instruction: Option<InstructionPointer>,
     pub data_stack: Vec<InlineObject>,
     pub call_stack: Vec<InstructionPointer>,
+    /// Calls of the `try` builtin that haven't returned yet, innermost last.
+    pub try_frames: Vec<TryFrame>,
+    /// The number of calls reported to the tracer by
+    /// [`Instruction::TraceCallStarts`] that haven't ended yet. When a panic is
+    /// caught, we end the calls it interrupted.
+    ///
+    /// [`Instruction::TraceCallStarts`]: crate::byte_code::Instruction::TraceCallStarts
+    pub traced_call_depth: usize,
 }
 
 #[derive(Debug)]
@@ -45,11 +75,77 @@ pub struct CallHandle {
     pub arguments: Vec<InlineObject>,
     pub responsible: HirId,
 }
+impl CallHandle {
+    fn references(&self) -> impl Iterator<Item = InlineObject> + '_ {
+        [self.handle.into(), self.responsible.into()]
+            .into_iter()
+            .chain(self.arguments.iter().copied())
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Panic {
     pub reason: String,
     pub responsible: Id,
+    pub kind: PanicKind,
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PanicKind {
+    /// The Candy code panicked, e.g., because a need wasn't fulfilled.
+    Code,
+    /// The heap exceeded its memory limit (see [`Heap::set_memory_limit`]).
+    OutOfMemory,
+    /// The call stack exceeded its maximum depth (see
+    /// [`Vm::with_max_stack_depth`]).
+    StackOverflow,
+}
+// Panics of kinds other than `Code` are caused by resource limits and can't be
+// caught using `try` (see [`crate::unwinding`]). They always stop the VM.
+
+impl Panic {
+    #[must_use]
+    pub fn out_of_memory(heap: &Heap, memory_limit: usize, responsible: Id) -> Self {
+        Self {
+            reason: format!(
+                "Out of memory: {} bytes are allocated, but the limit is {memory_limit} bytes.",
+                heap.allocated_bytes(),
+            ),
+            responsible,
+            kind: PanicKind::OutOfMemory,
+        }
+    }
+    /// A builtin didn't allocate an object with `content_size` bytes because
+    /// that would have exceeded the memory limit.
+    #[must_use]
+    pub fn out_of_memory_for_allocation(heap: &Heap, content_size: usize, responsible: Id) -> Self {
+        Self {
+            reason: format!(
+                "Out of memory: {} bytes are allocated and {content_size} more bytes were requested, but the limit is {} bytes.",
+                heap.allocated_bytes(),
+                heap.memory_limit().unwrap(),
+            ),
+            responsible,
+            kind: PanicKind::OutOfMemory,
+        }
+    }
+    #[must_use]
+    pub fn is_out_of_memory(&self) -> bool {
+        self.kind == PanicKind::OutOfMemory
+    }
+    #[must_use]
+    pub fn stack_overflow(max_stack_depth: usize, responsible: Id) -> Self {
+        Self {
+            reason: format!(
+                "Stack overflow: The call stack exceeded its maximum depth of {max_stack_depth}.",
+            ),
+            responsible,
+            kind: PanicKind::StackOverflow,
+        }
+    }
+    #[must_use]
+    pub fn is_stack_overflow(&self) -> bool {
+        self.kind == PanicKind::StackOverflow
+    }
 }
 
 impl<B, T> Vm<B, T>
@@ -78,30 +174,29 @@ where
         function: Function,
         arguments: &[InlineObject],
         responsible: HirId,
-        mut tracer: T,
+        tracer: T,
     ) -> Self {
-        tracer.call_started(
-            heap,
-            responsible,
-            function.into(),
-            arguments.to_vec(),
-            responsible,
-        );
-
-        let mut state = MachineState {
-            next_instruction: None,
-            data_stack: vec![],
-            call_stack: vec![],
-        };
-        state.call_function(function, arguments, responsible);
-
         let inner = Box::new(VmInner {
             byte_code,
-            state,
+            state: MachineState {
+                next_instruction: None,
+                data_stack: vec![],
+                call_stack: vec![],
+                try_frames: vec![],
+                traced_call_depth: 0,
+            },
             tracer,
             environment_for_main_function: None,
+            responsible: responsible.get().clone(),
+            profiler: None,
+            paranoid_checker: None,
+            max_stack_depth: None,
+            host_functions: FxHashMap::default(),
+            statistics: RunStatistics::default(),
         });
-        Self { inner }
+        let mut vm = Self { inner };
+        vm.start_function(heap, function, arguments, responsible);
+        vm
     }
     pub fn for_module(byte_code: B, heap: &mut Heap, tracer: T) -> Self {
         let actual_byte_code = byte_code.borrow();
@@ -120,6 +215,98 @@ where
         Self::for_function(byte_code, heap, function, &[], responsible, tracer)
     }
 
+    /// Samples the call stack every `interval` instructions. The resulting
+    /// [`Profile`] is returned in [`VmFinished`].
+    #[must_use]
+    pub fn with_profiler(mut self, interval: NonZeroUsize) -> Self {
+        self.inner.profiler = Some(Profiler::new(interval));
+        self
+    }
+    /// Checks the heap and the VM's stacks for consistency every `interval`
+    /// instructions and whenever a handle is called or returns. If they're
+    /// inconsistent, the VM panics (in Rust, not in Candy).
+    ///
+    /// This is very slow and only meant for detecting memory corruption.
+    #[must_use]
+    pub fn with_paranoid_checks(mut self, interval: NonZeroUsize) -> Self {
+        self.inner.paranoid_checker = Some(ParanoidChecker::new(interval));
+        self
+    }
+    /// Panics once the call stack contains more than `max_stack_depth`
+    /// frames, e.g., because of an infinite recursion. Tail calls don't
+    /// count. Like running out of memory, this panic can't be caught by `try`.
+    ///
+    /// By default, the depth is unlimited: The call stack lives on the host's
+    /// heap, so deep recursions don't crash the host, and Candy code uses
+    /// recursion where other languages use loops, so any fixed limit would
+    /// reject valid programs.
+    #[must_use]
+    pub fn with_max_stack_depth(mut self, max_stack_depth: usize) -> Self {
+        self.inner.max_stack_depth = Some(max_stack_depth);
+        self
+    }
+    /// Makes `function` callable from Candy code as a handle with the given
+    /// number of arguments and returns that handle.
+    ///
+    /// If this VM runs a main function (see [`Vm::for_main_function`]) that
+    /// hasn't started yet, the handle is also added to its environment, e.g.,
+    /// as `environment.httpGet` for the name `httpGet`.
+    ///
+    /// Calls with a different number of arguments, returned errors, and Rust
+    /// panics in `function` make the calling Candy code panic.
+    pub fn register_handle(
+        &mut self,
+        heap: &mut Heap,
+        name: impl Into<String>,
+        argument_count: usize,
+        function: impl FnMut(&mut Heap, &[InlineObject]) -> Result<InlineObject, String> + 'static,
+    ) -> Handle {
+        let name = name.into();
+        let handle = Handle::new(heap, argument_count);
+
+        if let Some(environment) = self.inner.environment_for_main_function {
+            let symbol = name.uppercase_first_letter();
+            let symbol = heap
+                .default_symbols()
+                .get(&symbol)
+                .unwrap_or_else(|| Text::create(heap, true, &symbol));
+            let mut fields = environment
+                .iter()
+                .map(|(_, key, value)| (key, value))
+                .collect::<FxHashMap<_, _>>();
+            for (key, value) in &fields {
+                key.dup(heap);
+                value.dup(heap);
+            }
+            fields.insert(Tag::create(symbol).into(), **handle);
+            self.inner.environment_for_main_function = Some(Struct::create(heap, true, &fields));
+            InlineObject::from(environment).drop(heap);
+        }
+
+        self.inner.host_functions.insert(
+            handle,
+            RegisteredHostFunction {
+                name,
+                argument_count,
+                function: Box::new(function),
+            },
+        );
+        handle
+    }
+
+    fn check_paranoidly(&self, heap: &Heap, owned_references: Vec<InlineObject>) {
+        paranoid::check(
+            self.inner.byte_code.borrow(),
+            heap,
+            &self.inner.state,
+            self.inner
+                .environment_for_main_function
+                .map(InlineObject::from)
+                .into_iter()
+                .chain(owned_references),
+        );
+    }
+
     #[must_use]
     pub const fn byte_code(&self) -> &B {
         &self.inner.byte_code
@@ -129,6 +316,10 @@ where
         &self.inner.tracer
     }
     #[must_use]
+    pub fn tracer_mut(&mut self) -> &mut T {
+        &mut self.inner.tracer
+    }
+    #[must_use]
     pub fn next_instruction(&self) -> Option<InstructionPointer> {
         self.inner.state.next_instruction
     }
@@ -136,6 +327,143 @@ where
     pub fn call_stack(&self) -> &[InstructionPointer] {
         &self.inner.state.call_stack
     }
+    /// How this VM ran so far, e.g., to decide how long to run it next using
+    /// an [`AdaptiveQuantum`](crate::scheduling::AdaptiveQuantum).
+    #[must_use]
+    pub const fn statistics(&self) -> &RunStatistics {
+        &self.inner.statistics
+    }
+
+    /// Captures the VM's instruction pointer, stacks, and `heap` so that
+    /// execution can be forked from this point using [`Vm::restore`].
+    ///
+    /// The tracer, profiler, paranoid checks, and registered host functions are
+    /// not part of the snapshot.
+    #[must_use]
+    pub fn snapshot(&self, heap: &Heap) -> VmSnapshot {
+        let (cloned_heap, mapping) = heap.clone();
+        VmSnapshot {
+            heap: cloned_heap,
+            state: self.inner.state.map_to_clone(heap, &mapping),
+            environment_for_main_function: self
+                .inner
+                .environment_for_main_function
+                .map(|environment| map_struct_to_clone(heap, environment, &mapping)),
+            responsible: self.inner.responsible.clone(),
+            max_stack_depth: self.inner.max_stack_depth,
+        }
+    }
+    /// Creates a VM that continues where the VM of the `snapshot` was when it
+    /// was created. The returned heap is a copy of the snapshot's heap, so the
+    /// snapshot can be restored multiple times.
+    ///
+    /// `byte_code` has to be the byte code of the original VM.
+    pub fn restore(snapshot: &VmSnapshot, byte_code: B, tracer: T) -> (Heap, Self) {
+        let (heap, mapping) = snapshot.heap.clone();
+        let inner = Box::new(VmInner {
+            byte_code,
+            state: snapshot.state.map_to_clone(&snapshot.heap, &mapping),
+            tracer,
+            environment_for_main_function: snapshot
+                .environment_for_main_function
+                .map(|environment| map_struct_to_clone(&snapshot.heap, environment, &mapping)),
+            responsible: snapshot.responsible.clone(),
+            profiler: None,
+            paranoid_checker: None,
+            max_stack_depth: snapshot.max_stack_depth,
+            host_functions: FxHashMap::default(),
+            statistics: RunStatistics::default(),
+        });
+        (heap, Self { inner })
+    }
+
+    /// Continues running `byte_code` instead of the current byte code, e.g.,
+    /// after the program's source code changed.
+    ///
+    /// Call stacks and functions on the heap reference instructions by their
+    /// position, so this only works if the new byte code differs only in the
+    /// constants it pushes (see [`ByteCode::differs_only_in_constants`]).
+    /// Otherwise, this returns the new byte code as an error and the VM keeps
+    /// running the current one.
+    ///
+    /// On success, this returns the old byte code. Values that the program
+    /// already created may reference its constants, so the caller has to keep
+    /// it alive as long as these values.
+    pub fn swap_byte_code(&mut self, byte_code: B) -> Result<B, B> {
+        if !self
+            .inner
+            .byte_code
+            .borrow()
+            .differs_only_in_constants(byte_code.borrow())
+        {
+            return Err(byte_code);
+        }
+        Ok(mem::replace(&mut self.inner.byte_code, byte_code))
+    }
+
+    /// Stops the VM before it finished and returns its tracer.
+    ///
+    /// Dropping a VM leaks the values on its stack in the `heap`, which
+    /// matters if the heap outlives the VM. This drops them instead.
+    pub fn abort(self, heap: &mut Heap) -> T {
+        let VmInner {
+            state,
+            tracer,
+            environment_for_main_function,
+            ..
+        } = *self.inner;
+        for value in state.data_stack {
+            value.drop(heap);
+        }
+        if let Some(environment) = environment_for_main_function {
+            InlineObject::from(environment).drop(heap);
+        }
+        tracer
+    }
+}
+
+/// A copy of a VM's state and heap, created by [`Vm::snapshot`].
+pub struct VmSnapshot {
+    heap: Heap,
+    state: MachineState,
+    environment_for_main_function: Option<Struct>,
+    responsible: Id,
+    max_stack_depth: Option<usize>,
+}
+impl VmSnapshot {
+    #[must_use]
+    pub const fn heap(&self) -> &Heap {
+        &self.heap
+    }
+    #[must_use]
+    pub const fn next_instruction(&self) -> Option<InstructionPointer> {
+        self.state.next_instruction
+    }
+}
+
+impl MachineState {
+    fn map_to_clone(&self, heap: &Heap, mapping: &FxHashMap<HeapObject, HeapObject>) -> Self {
+        Self {
+            next_instruction: self.next_instruction,
+            data_stack: self
+                .data_stack
+                .iter()
+                .map(|object| heap.map_to_clone(*object, mapping))
+                .collect(),
+            call_stack: self.call_stack.clone(),
+            try_frames: self.try_frames.clone(),
+            traced_call_depth: self.traced_call_depth,
+        }
+    }
+}
+fn map_struct_to_clone(
+    heap: &Heap,
+    struct_: Struct,
+    mapping: &FxHashMap<HeapObject, HeapObject>,
+) -> Struct {
+    heap.map_to_clone(struct_.into(), mapping)
+        .try_into()
+        .unwrap()
 }
 
 #[derive(Deref)]
@@ -143,11 +471,19 @@ pub struct VmHandleCall<B: Borrow<ByteCode>, T: Tracer> {
     #[deref]
     pub call: CallHandle,
     vm: Vm<B, T>,
+    called_at: Instant,
 }
 #[must_use]
 pub struct VmFinished<T: Tracer> {
     pub tracer: T,
     pub result: Result<InlineObject, Panic>,
+    /// Only available if the VM was created using [`Vm::with_profiler`].
+    pub profile: Option<Profile>,
+    /// How many instructions the VM executed, e.g., for benchmarks that
+    /// shouldn't depend on the machine's speed. Restored VMs start counting
+    /// at zero.
+    pub executed_instructions: usize,
+    pub statistics: RunStatistics,
 }
 
 #[must_use]
@@ -156,19 +492,41 @@ pub enum StateAfterRun<B: Borrow<ByteCode>, T: Tracer> {
     CallingHandle(VmHandleCall<B, T>),
     Finished(VmFinished<T>),
 }
+impl<B: Borrow<ByteCode>, T: Tracer> StateAfterRun<B, T> {
+    #[must_use]
+    pub const fn statistics(&self) -> &RunStatistics {
+        match self {
+            Self::Running(vm) => vm.statistics(),
+            Self::CallingHandle(call) => call.vm.statistics(),
+            Self::Finished(finished) => &finished.statistics,
+        }
+    }
+}
 
 impl<B, T> VmHandleCall<B, T>
 where
     B: Borrow<ByteCode>,
     T: Tracer,
 {
+    fn new(vm: Vm<B, T>, call: CallHandle) -> Self {
+        Self {
+            call,
+            vm,
+            called_at: Instant::now(),
+        }
+    }
+
     pub fn complete(mut self, heap: &mut Heap, return_value: impl Into<InlineObject>) -> Vm<B, T> {
+        self.vm.inner.statistics.handle_waiting_time += self.called_at.elapsed();
         self.handle.drop(heap);
         for argument in &self.call.arguments {
             argument.drop(heap);
         }
 
         self.vm.inner.state.data_stack.push(return_value.into());
+        if self.vm.inner.paranoid_checker.is_some() {
+            self.vm.check_paranoidly(heap, vec![]);
+        }
         self.vm
     }
 }
@@ -180,71 +538,186 @@ where
 {
     /// Runs one instruction in the VM and returns its new state.
     pub fn run(mut self, heap: &mut Heap) -> StateAfterRun<B, T> {
-        let Some(current_instruction) = self.inner.state.next_instruction else {
-            let return_value = self.inner.state.data_stack.pop().unwrap();
-            self.inner.tracer.call_ended(heap, Some(return_value));
+        match self.step(heap) {
+            ControlFlow::Continue(()) => StateAfterRun::Running(self),
+            ControlFlow::Break(Stop::CallHandle(call)) => {
+                StateAfterRun::CallingHandle(VmHandleCall::new(self, call))
+            }
+            ControlFlow::Break(Stop::Finished(result)) => {
+                StateAfterRun::Finished(self.finish(result))
+            }
+        }
+    }
+
+    /// Runs at most `max_instructions` in the VM.
+    pub fn run_n(mut self, heap: &mut Heap, max_instructions: usize) -> StateAfterRun<B, T> {
+        let started_at = Instant::now();
+        for _ in 0..max_instructions {
+            match self.step(heap) {
+                ControlFlow::Continue(()) => {}
+                ControlFlow::Break(Stop::CallHandle(call)) => {
+                    self.inner.statistics.running_time += started_at.elapsed();
+                    return StateAfterRun::CallingHandle(VmHandleCall::new(self, call));
+                }
+                ControlFlow::Break(Stop::Finished(result)) => {
+                    self.inner.statistics.running_time += started_at.elapsed();
+                    return StateAfterRun::Finished(self.finish(result));
+                }
+            }
+        }
+        self.inner.statistics.running_time += started_at.elapsed();
+        self.inner.statistics.preemptions += 1;
+        StateAfterRun::Running(self)
+    }
+
+    /// Runs one instruction. Unlike [`Vm::run`], this doesn't move the VM, so
+    /// running many instructions in a loop doesn't copy the VM's state around
+    /// after each instruction.
+    fn step(&mut self, heap: &mut Heap) -> ControlFlow<Stop> {
+        let inner = &mut *self.inner;
+        let Some(current_instruction) = inner.state.next_instruction else {
+            let return_value = inner.state.data_stack.pop().unwrap();
+            inner.tracer.call_ended(heap, Some(return_value));
 
-            if let Some(environment) = self.inner.environment_for_main_function {
+            if let Some(environment) = inner.environment_for_main_function.take() {
                 // We just ran the whole module which returned the main
                 // function. Now execute this main function using the
                 // environment we received earlier.
                 let responsible = HirId::create(heap, true, hir::Id::user());
                 let function = return_value.try_into()
                     .unwrap_or_else(|_| panic!("Expected module's return value to be the main function, but got {return_value:?}"));
-                let new_vm = Self::for_function(
-                    self.inner.byte_code,
-                    heap,
-                    function,
-                    &[environment.into()],
-                    responsible,
-                    self.inner.tracer,
-                );
-                return StateAfterRun::Running(new_vm);
+                self.start_function(heap, function, &[environment.into()], responsible);
+                return ControlFlow::Continue(());
             }
 
-            return StateAfterRun::Finished(VmFinished {
-                tracer: self.inner.tracer,
-                result: Ok(return_value),
-            });
+            return ControlFlow::Break(Stop::Finished(Ok(return_value)));
         };
 
-        let instruction = self
-            .inner
+        let instruction = inner
             .byte_code
             .borrow()
             .instructions
             .get(*current_instruction);
         let instruction = unsafe { instruction.unwrap_unchecked() };
-        self.inner.state.next_instruction = Some(current_instruction.next());
+        if let Some(profiler) = &mut inner.profiler {
+            profiler.on_instruction(
+                &inner.byte_code.borrow().origins,
+                current_instruction,
+                &inner.state.call_stack,
+            );
+        }
+        inner.state.next_instruction = Some(current_instruction.next());
+        inner.statistics.on_instruction();
+        heap.record_instruction();
 
-        let result = self
-            .inner
+        let mut result = inner
             .state
-            .run_instruction(heap, instruction, &mut self.inner.tracer);
+            .run_instruction(heap, instruction, &mut inner.tracer);
+        if let InstructionResult::CallHandle(call) = &result
+            && let Some(host_function) = inner.host_functions.get_mut(&call.handle)
+        {
+            let InstructionResult::CallHandle(call) = result else {
+                unreachable!();
+            };
+            result = host_function.call(heap, &mut inner.state, &call);
+        }
+        // Between instructions, all references are counted. Collecting cycles
+        // before checking the memory limit avoids panicking because of
+        // garbage.
+        heap.collect_garbage_if_needed();
+        if let Some(paranoid_checker) = &mut inner.paranoid_checker
+            && (paranoid_checker.on_instruction()
+                || matches!(result, InstructionResult::CallHandle(_)))
+        {
+            let call_references = match &result {
+                InstructionResult::CallHandle(call) => call.references().collect(),
+                _ => vec![],
+            };
+            self.check_paranoidly(heap, call_references);
+        }
+        if matches!(result, InstructionResult::Done)
+            && let Some(panic) = self.exceeded_limit(heap)
+        {
+            result = InstructionResult::Panic(panic);
+        }
         match result {
-            InstructionResult::Done => StateAfterRun::Running(self),
+            InstructionResult::Done => ControlFlow::Continue(()),
             InstructionResult::CallHandle(call) => {
-                StateAfterRun::CallingHandle(VmHandleCall { vm: self, call })
+                self.inner.statistics.on_handle_call();
+                ControlFlow::Break(Stop::CallHandle(call))
+            }
+            InstructionResult::Panic(panic) => {
+                if !self.inner.state.can_catch(&panic) {
+                    return ControlFlow::Break(Stop::Finished(Err(panic)));
+                }
+                let inner = &mut *self.inner;
+                inner
+                    .state
+                    .catch_panic(heap, inner.byte_code.borrow(), &mut inner.tracer, &panic);
+                ControlFlow::Continue(())
             }
-            InstructionResult::Panic(panic) => StateAfterRun::Finished(VmFinished {
-                tracer: self.inner.tracer,
-                result: Err(panic),
-            }),
         }
     }
-
-    /// Runs at most `max_instructions` in the VM.
-    pub fn run_n(mut self, heap: &mut Heap, max_instructions: usize) -> StateAfterRun<B, T> {
-        for _ in 0..max_instructions {
-            match self.run(heap) {
-                StateAfterRun::Running(vm) => self = vm,
-                a => return a,
-            }
+    /// Checked after each instruction that completed normally, so the result
+    /// is reported as a panic of that instruction. Limits exceeded while
+    /// calling a handle are noticed after the next instruction.
+    fn exceeded_limit(&self, heap: &Heap) -> Option<Panic> {
+        if let Some(memory_limit) = heap.memory_limit()
+            && heap.allocated_bytes() > memory_limit
+        {
+            return Some(Panic::out_of_memory(
+                heap,
+                memory_limit,
+                self.inner.responsible.clone(),
+            ));
+        }
+        if let Some(max_stack_depth) = self.inner.max_stack_depth
+            && self.inner.state.call_stack.len() > max_stack_depth
+        {
+            return Some(Panic::stack_overflow(
+                max_stack_depth,
+                self.inner.responsible.clone(),
+            ));
+        }
+        None
+    }
+    /// Starts running `function` as the outermost function of this VM.
+    fn start_function(
+        &mut self,
+        heap: &mut Heap,
+        function: Function,
+        arguments: &[InlineObject],
+        responsible: HirId,
+    ) {
+        let inner = &mut *self.inner;
+        inner.tracer.call_started(
+            heap,
+            responsible,
+            function.into(),
+            arguments.to_vec(),
+            responsible,
+        );
+        inner.state.data_stack.clear();
+        inner.state.call_function(function, arguments, responsible);
+        inner.responsible = responsible.get().clone();
+    }
+    fn finish(self, result: Result<InlineObject, Panic>) -> VmFinished<T> {
+        VmFinished {
+            tracer: self.inner.tracer,
+            result,
+            profile: self.inner.profiler.map(Profiler::into_profile),
+            executed_instructions: self.inner.statistics.executed_instructions,
+            statistics: self.inner.statistics,
         }
-        StateAfterRun::Running(self)
     }
 }
 
+/// Why [`Vm::step`] didn't continue running.
+enum Stop {
+    CallHandle(CallHandle),
+    Finished(Result<InlineObject, Panic>),
+}
+
 #[must_use]
 pub enum StateAfterRunForever<B: Borrow<ByteCode>, T: Tracer> {
     CallingHandle(VmHandleCall<B, T>),
@@ -259,16 +732,18 @@ where
     /// Runs the VM until a handle call is performed, the VM returns, or it
     /// panics.
     pub fn run_forever(mut self, heap: &mut Heap) -> StateAfterRunForever<B, T> {
-        loop {
-            match self.run(heap) {
-                StateAfterRun::Running(vm) => self = vm,
-                StateAfterRun::CallingHandle(call) => {
-                    break StateAfterRunForever::CallingHandle(call)
-                }
-                StateAfterRun::Finished(finished) => {
-                    break StateAfterRunForever::Finished(finished)
-                }
+        let started_at = Instant::now();
+        let stop = loop {
+            if let ControlFlow::Break(stop) = self.step(heap) {
+                break stop;
+            }
+        };
+        self.inner.statistics.running_time += started_at.elapsed();
+        match stop {
+            Stop::CallHandle(call) => {
+                StateAfterRunForever::CallingHandle(VmHandleCall::new(self, call))
             }
+            Stop::Finished(result) => StateAfterRunForever::Finished(self.finish(result)),
         }
     }
 }
@@ -281,3 +756,125 @@ impl<K: Eq + Hash, V> ReplaceHashMapValue<K, V> for HashMap<K, V> {
         self.insert(key, value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        byte_code::ByteCode,
+        heap::{Data, Heap},
+        lir_to_byte_code::compile_lir,
+        tracer::DummyTracer,
+        Vm,
+    };
+    use candy_frontend::{lir::Lir, module::Module};
+    use std::ptr;
+
+    const TRY_LIST_FILLED: &str = r#"
+        constants:
+          %0 = int 1000000000000
+          %1 = hir_id tooling "test" [] code ""
+          %2 = builtin list_filled
+          %3 = builtin try
+          %4 = function body_0
+        bodies:
+          body_0 captured 0 parameters 0:
+            $1 = constant %2
+            $2 = constant %0
+            $3 = constant %1
+            $4 = call $1 [$2 $2] $3
+          body_1 captured 0 parameters 0:
+            $1 = constant %3
+            $2 = constant %4
+            $3 = constant %1
+            $4 = call $1 [$2] $3
+    "#;
+    const RECURSION: &str = r#"
+        constants:
+          %0 = hir_id tooling "test" [] code ""
+          %1 = function body_0
+        bodies:
+          body_0 captured 0 parameters 1:
+            $2 = call $0 [$0] $1
+            $3 = reference $2
+          body_1 captured 0 parameters 0:
+            $1 = constant %1
+            $2 = constant %0
+            $3 = call $1 [$1] $2
+    "#;
+
+    fn compile(body: &str) -> ByteCode {
+        compile_text(&format!(
+            r#"
+            constants:
+              %0 = int 1
+              %1 = int 2
+            bodies:
+              body_0 captured 0 parameters 0:
+                {body}
+            "#,
+        ))
+    }
+    fn compile_text(lir: &str) -> ByteCode {
+        let lir = Lir::parse(lir).unwrap();
+        compile_lir(Module::from_package_name("Test".to_string()), &lir)
+    }
+    fn run(vm: Vm<&ByteCode, DummyTracer>, heap: &mut Heap) -> String {
+        let result = vm.run_forever_without_handles(heap).result.unwrap();
+        let debug_text = format!("{:?}", Data::from(result));
+        result.drop(heap);
+        debug_text
+    }
+
+    #[test]
+    fn swaps_byte_code_that_only_differs_in_constants() {
+        let byte_code = compile("$1 = constant %0");
+        let new_byte_code = compile("$1 = constant %1");
+
+        let mut heap = Heap::default();
+        let mut vm = Vm::for_module(&byte_code, &mut heap, DummyTracer);
+        assert!(vm
+            .swap_byte_code(&new_byte_code)
+            .is_ok_and(|old_byte_code| ptr::eq(old_byte_code, &byte_code)));
+        assert_eq!(run(vm, &mut heap), "2");
+    }
+
+    #[test]
+    fn keeps_byte_code_with_different_instructions() {
+        let byte_code = compile("$1 = constant %0");
+        let new_byte_code = compile("$1 = constant %0\n$2 = create_list [$1]");
+
+        let mut heap = Heap::default();
+        let mut vm = Vm::for_module(&byte_code, &mut heap, DummyTracer);
+        assert!(vm.swap_byte_code(&new_byte_code).is_err());
+        assert_eq!(run(vm, &mut heap), "1");
+    }
+
+    #[test]
+    fn panics_before_allocating_more_than_the_memory_limit() {
+        let byte_code = compile_text(TRY_LIST_FILLED);
+
+        let mut heap = Heap::default();
+        let vm = Vm::for_module(&byte_code, &mut heap, DummyTracer);
+        heap.set_memory_limit(Some(heap.allocated_bytes() + 1000));
+        // `try` doesn't catch panics caused by resource limits.
+        let panic = vm
+            .run_forever_without_handles(&mut heap)
+            .result
+            .unwrap_err();
+        assert!(panic.is_out_of_memory());
+        assert!(!heap.is_out_of_memory());
+    }
+
+    #[test]
+    fn panics_when_exceeding_the_max_stack_depth() {
+        let byte_code = compile_text(RECURSION);
+
+        let mut heap = Heap::default();
+        let vm = Vm::for_module(&byte_code, &mut heap, DummyTracer).with_max_stack_depth(100);
+        let panic = vm
+            .run_forever_without_handles(&mut heap)
+            .result
+            .unwrap_err();
+        assert!(panic.is_stack_overflow());
+    }
+}
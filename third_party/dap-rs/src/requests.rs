@@ -162,6 +162,10 @@ pub struct LaunchRequestArguments {
     /// array of strings, each string has the format of NAME=VALUE
     /// (extension to the specification, see: lldb-vscode)
     pub env: Option<Vec<String>>,
+
+    /// whether to pause before the program executes its first instruction
+    /// (extension to the specification, see: lldb-vscode)
+    pub stop_on_entry: Option<bool>,
 }
 
 /// Arguments for an Attach request.
@@ -8,7 +8,6 @@ import {
 } from "vscode-languageclient/node";
 import { registerDebugAdapter } from "./debug_adapter";
 import { registerDebugIrCommands } from "./debug_irs";
-import { HintsDecorations } from "./hints";
 import { ServerStatusService } from "./server_status";
 
 let client: LanguageClient | undefined;
@@ -47,7 +46,6 @@ export async function activate(context: vscode.ExtensionContext) {
   await client.start();
 
   context.subscriptions.push(new ServerStatusService(client));
-  context.subscriptions.push(new HintsDecorations(client));
   registerDebugIrCommands(client);
   registerDebugAdapter(context, client);
 }
@@ -2,8 +2,8 @@ import * as vscode from "vscode";
 import {
   DocumentUri,
   NotificationType,
-  Position,
   RequestType,
+  RequestType0,
 } from "vscode-languageclient";
 
 // Debug Adapter Protocol
@@ -42,26 +42,6 @@ export interface UpdateIrParams {
   readonly uri: DocumentUri;
 }
 
-// Hints
-export const publishHintsType = new NotificationType<HintsParams>(
-  "candy/textDocument/publishHints",
-);
-export interface HintsParams {
-  readonly uri: string;
-  readonly hints: Hint[];
-}
-export interface Hint {
-  readonly kind: HintKind;
-  readonly text: string;
-  readonly position: Position;
-}
-export type HintKind =
-  | "value"
-  | "fuzzingStatus"
-  | "sampleInputReturningNormally"
-  | "sampleInputPanickingWithCallerResponsible"
-  | "sampleInputPanickingWithInternalCodeResponsible";
-
 // Status
 export const publishServerStatusType = new NotificationType<ServerStatus>(
   "candy/publishServerStatus",
@@ -69,3 +49,24 @@ export const publishServerStatusType = new NotificationType<ServerStatus>(
 export interface ServerStatus {
   text: string;
 }
+
+// Recompilation Statistics
+export const recompilationStatistics = new RequestType0<
+  RecompilationStatistics,
+  void
+>("candy/recompilationStatistics");
+export interface RecompilationStatistics {
+  // The most recent edits, oldest first.
+  readonly edits: EditStatistics[];
+}
+export interface EditStatistics {
+  readonly module: string;
+  readonly executed: number;
+  readonly reused: number;
+  readonly queries: QueryStatistics[];
+}
+export interface QueryStatistics {
+  readonly name: string;
+  readonly executed: number;
+  readonly reused: number;
+}